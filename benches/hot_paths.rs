@@ -0,0 +1,206 @@
+//! Criterion benchmarks of the paths every candidate game goes through, from ranking it to
+//! claiming its triplets, and of a whole run: `cargo bench --bench hot_paths`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rand::rngs::StdRng;
+use rand::seq::index;
+use rand::SeedableRng;
+use rk_lottery::config::Config;
+use rk_lottery::custom_utils;
+use rk_lottery::data_structures::{DenseNumberSet, NumberSet, RankSet};
+use rk_lottery::game::GameMask;
+use rk_lottery::generation;
+use std::hint::black_box;
+
+/// Random sorted games of 60 numbers, the same on every run.
+fn games(count: usize) -> Vec<Vec<i64>> {
+    let mut rng = StdRng::seed_from_u64(1);
+    (0..count)
+        .map(|_| {
+            let mut game: Vec<i64> = index::sample(&mut rng, 60, 6)
+                .into_iter()
+                .map(|number| number as i64 + 1)
+                .collect();
+            game.sort_unstable();
+            game
+        })
+        .collect()
+}
+
+/// Guards the combinadic conversions against slowing down, e.g. by computing the binomials
+/// with loops or allocating while unranking: every candidate is ranked, and the combinadic
+/// strategy unranks one per draw.
+fn combinadic(c: &mut Criterion) {
+    let games = games(1024);
+    let ranks: Vec<i64> = games
+        .iter()
+        .map(|game| custom_utils::game2enum(game))
+        .collect();
+    c.bench_function("binomial(60, 6)", |b| {
+        b.iter(|| custom_utils::binomial(black_box(60), black_box(6)))
+    });
+    c.bench_function("game2enum", |b| {
+        let mut index = 0;
+        b.iter(|| {
+            index = (index + 1) % games.len();
+            custom_utils::game2enum(black_box(&games[index]))
+        })
+    });
+    c.bench_function("enum2game", |b| {
+        let mut index = 0;
+        b.iter(|| {
+            index = (index + 1) % ranks.len();
+            custom_utils::enum2game(black_box(ranks[index])).unwrap()
+        })
+    });
+}
+
+/// Guards the allocation-free `game2triplet_enums` staying ahead of building the triplets
+/// with `game2triplets` and ranking each, which it replaced on the path of every candidate.
+fn triplets(c: &mut Criterion) {
+    let games = games(1024);
+    let mut group = c.benchmark_group("triplet ranks");
+    group.bench_function("game2triplets + triplet2enum", |b| {
+        let mut index = 0;
+        b.iter(|| {
+            index = (index + 1) % games.len();
+            custom_utils::game2triplets(black_box(&games[index]))
+                .iter()
+                .map(|triplet| custom_utils::triplet2enum(triplet).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("game2triplet_enums", |b| {
+        let mut index = 0;
+        b.iter(|| {
+            index = (index + 1) % games.len();
+            custom_utils::game2triplet_enums(black_box(&games[index]))
+        })
+    });
+    group.finish();
+}
+
+/// Guards the subsets of a Lotofácil game, whose 455 triplets the coverage counts visit per
+/// game, against allocating one vector per subset again.
+fn ksubsets(c: &mut Criterion) {
+    let game: Vec<i64> = (1..=15).collect();
+    let mut group = c.benchmark_group("triplets of 15 numbers");
+    group.bench_function("game2ksubsets", |b| {
+        b.iter(|| custom_utils::game2ksubsets(black_box(&game), 3).len())
+    });
+    group.bench_function("for_each_ksubset", |b| {
+        b.iter(|| {
+            let mut sum = 0;
+            custom_utils::for_each_ksubset(black_box(&game), 3, |subset| sum += subset[0]);
+            sum
+        })
+    });
+    group.finish();
+}
+
+/// Guards the `GameMask` checks staying ahead of those on the numbers of the games, which
+/// they replaced for the range of the candidates and their overlap with the accepted games.
+fn masks(c: &mut Criterion) {
+    let games = games(1024);
+    let masks: Vec<GameMask> = games
+        .iter()
+        .map(|game| GameMask::from_numbers(game).unwrap())
+        .collect();
+    let mut group = c.benchmark_group("range and overlap checks");
+    group.bench_function("numbers in range", |b| {
+        b.iter(|| {
+            games
+                .iter()
+                .filter(|game| game.iter().all(|x| (31..=60).contains(black_box(x))))
+                .count()
+        })
+    });
+    group.bench_function("GameMask::is_subset_of", |b| {
+        let valid = GameMask::range(31, 60);
+        b.iter(|| {
+            masks
+                .iter()
+                .filter(|mask| mask.is_subset_of(black_box(valid)))
+                .count()
+        })
+    });
+    group.bench_function("numbers shared", |b| {
+        let mut index = 0;
+        b.iter(|| {
+            index = (index + 1) % games.len();
+            let game = black_box(&games[index]);
+            games
+                .iter()
+                .map(|other| game.iter().filter(|x| other.contains(x)).count())
+                .max()
+        })
+    });
+    group.bench_function("GameMask::overlap", |b| {
+        let mut index = 0;
+        b.iter(|| {
+            index = (index + 1) % masks.len();
+            let mask = black_box(masks[index]);
+            masks.iter().map(|&other| mask.overlap(other)).max()
+        })
+    });
+    group.finish();
+}
+
+/// Claims the triplets of `triplet_nos` in a fresh `set`, one game after the other, returning
+/// the games accepted.
+fn claim_all(mut set: impl RankSet, triplet_nos: &[[i64; 20]]) -> usize {
+    triplet_nos
+        .iter()
+        .filter(|triplets| set.check_and_insert_all(&triplets[..]))
+        .count()
+}
+
+/// Guards `check_and_insert_all`, which claims the triplets of every candidate, on the
+/// hashed `NumberSet` and on the bitmap of `DenseNumberSet`, the latter expected to stay the
+/// faster of the two.
+fn insert_all(c: &mut Criterion) {
+    let triplet_nos: Vec<[i64; 20]> = games(10_000)
+        .iter()
+        .map(|game| custom_utils::game2triplet_enums(game))
+        .collect();
+    let domain = custom_utils::binomial(60, 3) as usize;
+    let mut group = c.benchmark_group("check_and_insert_all");
+    group.bench_function("NumberSet", |b| {
+        b.iter_batched(
+            NumberSet::new,
+            |set| claim_all(set, &triplet_nos),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("DenseNumberSet", |b| {
+        b.iter_batched(
+            || DenseNumberSet::new(domain),
+            |set| claim_all(set, &triplet_nos),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+/// Guards a whole run against regressions the micro-benchmarks miss, e.g. in the constraints
+/// or in how often candidates are drawn: 100 games of the 40 numbers from 21, with no five of
+/// a game on one progression, from a fixed seed so every run does the same work.
+fn generate(c: &mut Criterion) {
+    let config = Config::from_toml(
+        r#"
+        no_of_games = 100
+        initial_games = []
+        seed = 2925
+        max_number = 60
+        min_desired_number = 21
+        max_arithmetic_run = 4
+        "#,
+    )
+    .unwrap();
+    c.bench_function("generate 100 games, tight constraints", |b| {
+        b.iter(|| generation::generate_games(black_box(&config)).unwrap())
+    });
+}
+
+criterion_group!(benches, combinadic, triplets, ksubsets, masks, insert_all, generate);
+criterion_main!(benches);
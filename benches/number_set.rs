@@ -0,0 +1,53 @@
+//! Compares the probe throughput of `NumberSet`, `TripletSet` and `DenseNumberSet` on the
+//! triplet ranks of 60 numbers, then the memory of the first two holding the ranks of a long
+//! run: `cargo bench --bench number_set`.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rk_lottery::custom_utils;
+use rk_lottery::data_structures::{DenseNumberSet, NumberSet, RankSet, TripletSet};
+use std::hint::black_box;
+use std::time::Instant;
+
+const PROBES: usize = 20_000_000;
+
+/// Adds every other triplet rank to `set`, then probes it with random ranks, printing the
+/// probes per second.
+fn bench<S: RankSet>(name: &str, mut set: S, domain: i64) {
+    for rank in (0..domain).step_by(2) {
+        set.add_number(rank);
+    }
+    let mut rng = StdRng::seed_from_u64(1);
+    let probes: Vec<i64> = (0..1 << 16).map(|_| rng.gen_range(0..domain)).collect();
+
+    let start = Instant::now();
+    let mut found = 0;
+    for index in 0..PROBES {
+        found += usize::from(set.contains(black_box(probes[index % probes.len()])));
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<15} {:>6.1} M probes/s ({} found)",
+        name,
+        PROBES as f64 / elapsed.as_secs_f64() / 1e6,
+        found
+    );
+}
+
+fn main() {
+    let domain = custom_utils::binomial(60, 3);
+    bench("NumberSet", NumberSet::new(), domain);
+    bench("TripletSet", TripletSet::default(), domain);
+    bench(
+        "DenseNumberSet",
+        DenseNumberSet::new(domain as usize),
+        domain,
+    );
+
+    // As many ranks as the triplets of 100,000 games, spread over those of 1,000 numbers
+    let domain = custom_utils::binomial(1000, 3);
+    let ranks = (0..domain).step_by(83).take(2_000_000);
+    let wide: NumberSet = ranks.clone().collect();
+    let narrow: TripletSet = ranks.collect();
+    println!("{:<15} {}", "NumberSet", wide.stats());
+    println!("{:<15} {}", "TripletSet", narrow.stats());
+}
@@ -0,0 +1,71 @@
+//! Compares the claim throughput of `SharedNumberSet` (one mutex) and `ShardedNumberSet`
+//! under 4 threads, each claiming the triplets of random games of 60 numbers:
+//! `cargo bench --bench shared_set`.
+
+use rand::rngs::StdRng;
+use rand::seq::index;
+use rand::SeedableRng;
+use rk_lottery::custom_utils;
+use rk_lottery::shared_set::{ConcurrentRankSet, ShardedNumberSet, SharedNumberSet};
+use std::time::Instant;
+
+const THREADS: u64 = 4;
+const GAMES_PER_THREAD: usize = 500_000;
+
+/// Has `THREADS` threads claim the triplets of random games in `set`, printing the games
+/// checked per second.
+fn bench(name: &str, set: impl ConcurrentRankSet) {
+    // The games are drawn beforehand, so the threads only contend for the set
+    let games: Vec<Vec<[i64; 20]>> = (0..THREADS)
+        .map(|thread| {
+            let mut rng = StdRng::seed_from_u64(thread);
+            (0..GAMES_PER_THREAD)
+                .map(|_| {
+                    let mut game: Vec<i64> = index::sample(&mut rng, 60, 6)
+                        .into_iter()
+                        .map(|number| number as i64 + 1)
+                        .collect();
+                    game.sort_unstable();
+                    custom_utils::game2triplet_enums_from(&game, 1)
+                })
+                .collect()
+        })
+        .collect();
+
+    let start = Instant::now();
+    let accepted: usize = std::thread::scope(|scope| {
+        let workers: Vec<_> = games
+            .iter()
+            .map(|games| {
+                let set = &set;
+                scope.spawn(move || {
+                    games
+                        .iter()
+                        .filter(|triplet_nos| set.check_and_insert_all(&triplet_nos[..]))
+                        .count()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .sum()
+    });
+    let elapsed = start.elapsed();
+    println!(
+        "{:<22} {:>6.2} M games/s ({} accepted)",
+        name,
+        (THREADS as usize * GAMES_PER_THREAD) as f64 / elapsed.as_secs_f64() / 1e6,
+        accepted
+    );
+}
+
+fn main() {
+    bench("SharedNumberSet", SharedNumberSet::new());
+    for shards in [4, 16, 64] {
+        bench(
+            &format!("ShardedNumberSet({})", shards),
+            ShardedNumberSet::new(shards),
+        );
+    }
+}
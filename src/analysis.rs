@@ -0,0 +1,810 @@
+use crate::custom_utils;
+use crate::data_structures::{DenseNumberSet, NumberSet, RankSet, DENSE_DOMAIN_THRESHOLD};
+use crate::game::Game;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// How many of all possible subsets of a given size are contained in a batch of games.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CoverageStats {
+    /// The subset size (e.g., 3 for triplets).
+    pub subset_size: usize,
+    /// Number of distinct subsets contained in at least one game.
+    pub covered: u64,
+    /// Number of possible subsets, C(number of playable numbers, subset_size).
+    pub total: u64,
+}
+
+impl CoverageStats {
+    /// Returns the covered subsets as a percentage of all possible subsets.
+    pub fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        100.0 * self.covered as f64 / self.total as f64
+    }
+}
+
+impl fmt::Display for CoverageStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.subset_size {
+            1 => "numbers".to_string(),
+            2 => "pairs".to_string(),
+            3 => "triplets".to_string(),
+            k => format!("{}-subsets", k),
+        };
+        write!(
+            f,
+            "{} / {} {} covered ({:.2}%)",
+            self.covered,
+            self.total,
+            name,
+            self.percentage()
+        )
+    }
+}
+
+/// Counts the distinct subsets of `subset_size` numbers covered by a batch of games.
+///
+/// # Arguments
+/// * `games` - The games to analyze.
+/// * `subset_size` - The subset size (e.g., 3 for triplets).
+/// * `min_number` - The smallest playable number (e.g., 0 or 1).
+/// * `max_number` - The maximum playable number.
+///
+/// # Returns
+/// * The coverage statistics of the batch.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::analysis::coverage_stats;
+/// use rk_lottery::game::Game;
+///
+/// let games = vec![Game::new(vec![1, 2, 3, 4, 5, 6])];
+/// let stats = coverage_stats(&games, 3, 1, 6);
+/// assert_eq!((stats.covered, stats.total), (20, 20));
+/// ```
+pub fn coverage_stats(
+    games: &[Game],
+    subset_size: usize,
+    min_number: i64,
+    max_number: i64,
+) -> CoverageStats {
+    let total = custom_utils::binomial(max_number - min_number + 1, subset_size as i64) as u64;
+    // The subsets of every number are ranked below `total`, so a bit each is enough
+    let covered = if total as usize <= DENSE_DOMAIN_THRESHOLD {
+        count_covered(
+            DenseNumberSet::new(total as usize),
+            games,
+            subset_size,
+            min_number,
+        )
+    } else {
+        count_covered(NumberSet::new(), games, subset_size, min_number)
+    };
+
+    CoverageStats {
+        subset_size,
+        covered,
+        total,
+    }
+}
+
+/// Counts the distinct `subset_size`-subsets of the games, adding their ranks to `covered_set`.
+/// A subset repeating a number, or holding one below `min_number`, is no subset of the lottery
+/// and is left out.
+fn count_covered<S: RankSet>(
+    mut covered_set: S,
+    games: &[Game],
+    subset_size: usize,
+    min_number: i64,
+) -> u64 {
+    for game in games {
+        let mut numbers = game.to_vec();
+        numbers.sort_unstable();
+        custom_utils::for_each_ksubset(&numbers, subset_size, |subset| {
+            if let Ok(rank) = custom_utils::subset2enum_from(subset, subset_size, min_number) {
+                covered_set.add_number(rank);
+            }
+        });
+    }
+    covered_set.len() as u64
+}
+
+/// Estimates how many subsets `no_of_games` games drawn uniformly at random would cover.
+///
+/// Each random game contains C(`numbers_per_game`, `subset_size`) of the C(n', `subset_size`)
+/// subsets of the n' numbers in `[min_desired_number, max_number]`, so a given subset is
+/// missed by all of them with probability (1 - C(`numbers_per_game`, `subset_size`) /
+/// C(n', `subset_size`))^`no_of_games`.
+///
+/// # Arguments
+/// * `no_of_games` - The number of games drawn.
+/// * `subset_size` - The subset size (e.g., 3 for triplets).
+/// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+/// * `min_desired_number` - The minimum number allowed in a game.
+/// * `max_number` - The maximum number in a game (e.g., 60).
+///
+/// # Returns
+/// * The expected number of covered subsets.
+pub fn expected_random_coverage(
+    no_of_games: usize,
+    subset_size: usize,
+    numbers_per_game: i64,
+    min_desired_number: i64,
+    max_number: i64,
+) -> f64 {
+    let span = max_number - min_desired_number + 1;
+    let available = custom_utils::binomial(span, subset_size as i64) as f64;
+    if available == 0.0 {
+        return 0.0;
+    }
+    let per_game = custom_utils::binomial(numbers_per_game, subset_size as i64) as f64;
+    available * (1.0 - (1.0 - per_game / available).powi(no_of_games as i32))
+}
+
+/// Counts how many numbers are played in each number of games.
+///
+/// # Arguments
+/// * `games` - The games to count the numbers of.
+/// * `min_number` - The smallest number counted (e.g., the minimum desired number).
+/// * `max_number` - The largest number counted (e.g., 60).
+///
+/// # Returns
+/// * The histogram of the usage: entry `k` is the number of numbers of
+///   `min_number..=max_number` found in exactly `k` games, up to the most used number.
+pub fn usage_histogram(games: &[Game], min_number: i64, max_number: i64) -> Vec<usize> {
+    let span = (max_number - min_number + 1).max(0) as usize;
+    let mut usage = vec![0; span];
+    for number in games.iter().flat_map(|game| game.iter()) {
+        if let Some(count) = usize::try_from(number - min_number)
+            .ok()
+            .and_then(|index| usage.get_mut(index))
+        {
+            *count += 1;
+        }
+    }
+    let mut histogram = vec![0; usage.iter().max().map_or(0, |&most| most + 1)];
+    for count in usage {
+        histogram[count] += 1;
+    }
+    histogram
+}
+
+/// Counts how many pairs of games share each number of numbers.
+///
+/// # Arguments
+/// * `games` - The games to compare with each other.
+///
+/// # Returns
+/// * The histogram of the overlaps: entry `k` is the number of pairs of games sharing exactly
+///   `k` numbers, up to the largest overlap.
+pub fn overlap_histogram(games: &[Game]) -> Vec<usize> {
+    let sorted: Vec<Vec<i64>> = games
+        .iter()
+        .map(|game| {
+            let mut numbers = game.to_vec();
+            numbers.sort_unstable();
+            numbers
+        })
+        .collect();
+    let mut histogram = Vec::new();
+    for (index, game) in sorted.iter().enumerate() {
+        for other in &sorted[..index] {
+            let shared = shared_numbers(game, other);
+            if histogram.len() <= shared {
+                histogram.resize(shared + 1, 0);
+            }
+            histogram[shared] += 1;
+        }
+    }
+    histogram
+}
+
+/// The numbers two sorted games have in common.
+fn shared_numbers(first: &[i64], second: &[i64]) -> usize {
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < first.len() && j < second.len() {
+        match first[i].cmp(&second[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    shared
+}
+
+/// How many games contain each pair of numbers of `min_number..=max_number`, as a symmetric
+/// matrix whose diagonal is left at 0.
+#[derive(Debug, PartialEq)]
+pub struct PairMatrix {
+    min_number: i64,
+    max_number: i64,
+    /// Row-major counts, `span * span` of them.
+    counts: Vec<u32>,
+    /// Number of games added.
+    pub games: usize,
+}
+
+impl PairMatrix {
+    /// Creates an empty matrix for the numbers of `min_number..=max_number`.
+    pub fn new(min_number: i64, max_number: i64) -> Self {
+        let span = (max_number - min_number + 1).max(0) as usize;
+        Self {
+            min_number,
+            max_number,
+            counts: vec![0; span * span],
+            games: 0,
+        }
+    }
+
+    /// Number of numbers of the lottery, the size of the matrix.
+    fn span(&self) -> usize {
+        (self.max_number - self.min_number + 1).max(0) as usize
+    }
+
+    /// Index of the count of the pair `(a, b)` in `counts`.
+    fn index(&self, a: i64, b: i64) -> usize {
+        (a - self.min_number) as usize * self.span() + (b - self.min_number) as usize
+    }
+
+    /// Counts the pairs of a game, once in each half of the matrix.
+    ///
+    /// # Returns
+    /// * An error, leaving the matrix unchanged, if a number is outside the lottery.
+    pub fn add(&mut self, game: &[i64]) -> Result<(), String> {
+        if let Some(number) = game
+            .iter()
+            .find(|number| !(self.min_number..=self.max_number).contains(number))
+        {
+            return Err(format!(
+                "number {} is outside {} to {}",
+                number, self.min_number, self.max_number
+            ));
+        }
+        for (a, b) in custom_utils::game2pairs(game) {
+            if a != b {
+                let (ab, ba) = (self.index(a, b), self.index(b, a));
+                self.counts[ab] += 1;
+                self.counts[ba] += 1;
+            }
+        }
+        self.games += 1;
+        Ok(())
+    }
+
+    /// Number of games containing both `a` and `b`, 0 on the diagonal.
+    pub fn count(&self, a: i64, b: i64) -> u32 {
+        self.counts[self.index(a, b)]
+    }
+
+    /// The pairs in most games, most repeated first then in increasing order, leaving out the
+    /// pairs in no game.
+    ///
+    /// # Returns
+    /// * Up to `limit` pairs `(a, b, count)`, with `a < b`.
+    pub fn most_repeated(&self, limit: usize) -> Vec<(i64, i64, u32)> {
+        let mut pairs: Vec<(i64, i64, u32)> = (self.min_number..=self.max_number)
+            .flat_map(|a| (a + 1..=self.max_number).map(move |b| (a, b)))
+            .map(|(a, b)| (a, b, self.count(a, b)))
+            .filter(|&(_, _, count)| count > 0)
+            .collect();
+        pairs.sort_by(|x, y| y.2.cmp(&x.2).then((x.0, x.1).cmp(&(y.0, y.1))));
+        pairs.truncate(limit);
+        pairs
+    }
+
+    /// The matrix as a CSV file: a `number,<min_number>,...,<max_number>` header row, then one
+    /// row per number starting with the number.
+    pub fn to_csv(&self) -> Vec<u8> {
+        let mut wtr = csv::Writer::from_writer(Vec::new());
+        let numbers = (self.min_number..=self.max_number).map(|number| number.to_string());
+        let header: Vec<String> = std::iter::once("number".to_string())
+            .chain(numbers)
+            .collect();
+        wtr.write_record(&header)
+            .expect("writing to memory cannot fail");
+        for a in self.min_number..=self.max_number {
+            let row: Vec<String> = std::iter::once(a.to_string())
+                .chain((self.min_number..=self.max_number).map(|b| self.count(a, b).to_string()))
+                .collect();
+            wtr.write_record(&row)
+                .expect("writing to memory cannot fail");
+        }
+        wtr.into_inner().expect("writing to memory cannot fail")
+    }
+}
+
+/// File the frequencies of the numbers of a run are written to by `--histogram`.
+pub const FREQUENCIES_FILE: &str = "number_frequencies.csv";
+
+/// Longest bar of the histogram of a `FrequencyTable`, in characters; the bars are scaled down
+/// to it when a number is in more games.
+pub const HISTOGRAM_WIDTH: usize = 40;
+
+/// How many games play each number of `min_number..=max_number`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrequencyTable {
+    min_number: i64,
+    max_number: i64,
+    /// Games playing each number, from `min_number` on.
+    counts: Vec<usize>,
+}
+
+/// Counts the games playing each number of the lottery. Numbers outside of it are left out.
+///
+/// # Arguments
+/// * `games` - The games to count the numbers of.
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+/// * `max_number` - The largest number of the lottery (e.g., 60).
+///
+/// # Returns
+/// * The table of the frequencies, every number of the lottery included.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::analysis::frequency_table;
+/// use rk_lottery::game::Game;
+///
+/// let games = vec![Game::new(vec![1, 2, 3]), Game::new(vec![2, 3, 4])];
+/// let table = frequency_table(&games, 1, 5);
+/// assert_eq!(table.count(2), 2);
+/// assert_eq!(table.never_played(), vec![5]);
+/// ```
+pub fn frequency_table(games: &[Game], min_number: i64, max_number: i64) -> FrequencyTable {
+    let span = (max_number - min_number + 1).max(0) as usize;
+    let mut counts = vec![0; span];
+    for number in games.iter().flat_map(|game| game.iter()) {
+        if let Some(count) = usize::try_from(number - min_number)
+            .ok()
+            .and_then(|index| counts.get_mut(index))
+        {
+            *count += 1;
+        }
+    }
+    FrequencyTable {
+        min_number,
+        max_number,
+        counts,
+    }
+}
+
+impl FrequencyTable {
+    /// The numbers of the lottery with the games playing each, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = (i64, usize)> + '_ {
+        (self.min_number..).zip(self.counts.iter().copied())
+    }
+
+    /// Number of games playing `number`, 0 outside of the lottery.
+    pub fn count(&self, number: i64) -> usize {
+        usize::try_from(number - self.min_number)
+            .ok()
+            .and_then(|index| self.counts.get(index))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The numbers of the lottery no game plays, in increasing order.
+    pub fn never_played(&self) -> Vec<i64> {
+        self.iter()
+            .filter(|&(_, count)| count == 0)
+            .map(|(number, _)| number)
+            .collect()
+    }
+
+    /// The table as a CSV file: a `number,count` header row, then one row per number of the
+    /// lottery, in increasing order.
+    pub fn to_csv(&self) -> Vec<u8> {
+        let mut wtr = csv::Writer::from_writer(Vec::new());
+        wtr.write_record(["number", "count"])
+            .expect("writing to memory cannot fail");
+        for (number, count) in self.iter() {
+            wtr.write_record([number.to_string(), count.to_string()])
+                .expect("writing to memory cannot fail");
+        }
+        wtr.into_inner().expect("writing to memory cannot fail")
+    }
+}
+
+/// The histogram of the table, one line per number: the number zero-padded to the width of
+/// `max_number`, the games playing it and a bar of one `#` per game, scaled down to
+/// `HISTOGRAM_WIDTH` for the most played number; numbers no game plays are flagged.
+impl fmt::Display for FrequencyTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let number_width = self.max_number.to_string().len();
+        let most = self.counts.iter().copied().max().unwrap_or(0);
+        let count_width = most.to_string().len();
+        for (number, count) in self.iter() {
+            let bar = if count == 0 {
+                "(never played)".to_string()
+            } else if most <= HISTOGRAM_WIDTH {
+                "#".repeat(count)
+            } else {
+                // Rounded, but never down to nothing for a played number
+                "#".repeat(((count * HISTOGRAM_WIDTH + most / 2) / most).max(1))
+            };
+            writeln!(
+                f,
+                "{:0number_width$}  {:>count_width$}  {}",
+                number,
+                count,
+                bar,
+                number_width = number_width,
+                count_width = count_width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of a chi-square test of the per-number frequencies of random games.
+#[derive(Debug, Serialize)]
+pub struct UniformityReport {
+    /// Number of games drawn.
+    pub draws: u64,
+    /// How often each number was drawn.
+    pub frequencies: BTreeMap<i64, u64>,
+    /// The frequency expected for every number, k * M / n.
+    pub expected_frequency: f64,
+    /// The chi-square statistic of the frequencies.
+    pub statistic: f64,
+    /// Degrees of freedom of the test, n - 1.
+    pub degrees_of_freedom: u64,
+    /// Probability of a statistic at least as large for a uniform generator.
+    pub p_value: f64,
+    /// The significance level the p-value is compared with.
+    pub significance: f64,
+    /// Whether the frequencies are consistent with a uniform generator.
+    pub passed: bool,
+}
+
+impl fmt::Display for UniformityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Chi-square statistic {:.2} with {} degrees of freedom over {} games, p-value {:.4}: {} at the {}% significance level",
+            self.statistic,
+            self.degrees_of_freedom,
+            self.draws,
+            self.p_value,
+            if self.passed { "uniform" } else { "NOT uniform" },
+            self.significance * 100.0
+        )
+    }
+}
+
+/// Draws games from the combinadic generator and tests whether every number comes up
+/// equally often, with a chi-square goodness-of-fit test against the uniform frequency.
+///
+/// The numbers of a game are drawn without replacement, which makes the test slightly
+/// conservative: a uniform generator passes a little more often than the significance
+/// level implies.
+///
+/// # Arguments
+/// * `rng` - The random number generator to draw from.
+/// * `draws` - The number of games to draw (M).
+/// * `min_number` - The smallest playable number (e.g., 0 or 1).
+/// * `max_number` - The maximum playable number (e.g., 60).
+/// * `numbers_per_game` - The number of numbers per game (k, e.g., 6).
+/// * `significance` - The significance level (e.g., 0.01).
+///
+/// # Returns
+/// * The frequencies and the outcome of the test.
+pub fn analyze_rng<R: Rng>(
+    rng: R,
+    draws: u64,
+    min_number: i64,
+    max_number: i64,
+    numbers_per_game: i64,
+    significance: f64,
+) -> UniformityReport {
+    let n = max_number - min_number + 1;
+    let mut draw = custom_utils::create_combinadic_rng_with(rng, n, numbers_per_game);
+    let mut frequencies: BTreeMap<i64, u64> = (min_number..=max_number).map(|x| (x, 0)).collect();
+    for _ in 0..draws {
+        let game = custom_utils::enum2subset_from(draw(), n, numbers_per_game, min_number)
+            .expect("the combinadic generator only draws valid ranks");
+        for number in game {
+            *frequencies.entry(number).or_insert(0) += 1;
+        }
+    }
+
+    let expected_frequency = (numbers_per_game as u64 * draws) as f64 / n as f64;
+    let statistic = frequencies
+        .values()
+        .map(|&observed| (observed as f64 - expected_frequency).powi(2) / expected_frequency)
+        .sum();
+    let degrees_of_freedom = (n - 1) as u64;
+    let p_value = 1.0 - chi_square_cdf(statistic, degrees_of_freedom);
+    UniformityReport {
+        draws,
+        frequencies,
+        expected_frequency,
+        statistic,
+        degrees_of_freedom,
+        p_value,
+        significance,
+        passed: p_value >= significance,
+    }
+}
+
+/// Approximates the chi-square cumulative distribution function with the Wilson-Hilferty
+/// transformation, accurate to about 1e-3 for the degrees of freedom of usual lotteries.
+///
+/// # Arguments
+/// * `x` - The value of the statistic.
+/// * `degrees_of_freedom` - The degrees of freedom of the distribution.
+pub fn chi_square_cdf(x: f64, degrees_of_freedom: u64) -> f64 {
+    if x <= 0.0 || degrees_of_freedom == 0 {
+        return 0.0;
+    }
+    let k = degrees_of_freedom as f64;
+    let variance = 2.0 / (9.0 * k);
+    let z = ((x / k).powf(1.0 / 3.0) - (1.0 - variance)) / variance.sqrt();
+    standard_normal_cdf(z)
+}
+
+/// Approximates the standard normal cumulative distribution function (Abramowitz and Stegun
+/// 7.1.26, absolute error below 1.5e-7).
+fn standard_normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let polynomial = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - polynomial * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_stats_disjoint_games() {
+        let games = vec![
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![7, 8, 9, 10, 11, 12]),
+        ];
+        let stats = coverage_stats(&games, 3, 1, 60);
+        assert_eq!(stats.covered, 40);
+        assert_eq!(stats.total, 34_220);
+        assert_eq!(stats.to_string(), "40 / 34220 triplets covered (0.12%)");
+    }
+
+    #[test]
+    fn test_coverage_stats_overlapping_games() {
+        // The two games share the triplet 1-2-3 and the pairs among 1, 2 and 3
+        let games = vec![
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![1, 2, 3, 7, 8, 9]),
+        ];
+        assert_eq!(coverage_stats(&games, 3, 1, 9).covered, 39);
+        assert_eq!(coverage_stats(&games, 2, 1, 9).covered, 27);
+
+        let stats = coverage_stats(&games, 1, 1, 9);
+        assert_eq!((stats.covered, stats.total), (9, 9));
+        assert_eq!(stats.percentage(), 100.0);
+    }
+
+    #[test]
+    fn test_coverage_stats_no_games() {
+        let stats = coverage_stats(&[], 3, 1, 10);
+        assert_eq!(stats.covered, 0);
+        assert_eq!(stats.total, 120);
+        assert_eq!(stats.percentage(), 0.0);
+    }
+
+    #[test]
+    fn test_coverage_stats_zero_based_lottery() {
+        // The same games shifted down by one, in a lottery numbered from 0
+        let games = vec![
+            Game::new(vec![0, 1, 2, 3, 4, 5]),
+            Game::new(vec![0, 1, 2, 6, 7, 8]),
+        ];
+        assert_eq!(coverage_stats(&games, 3, 0, 8).covered, 39);
+        let stats = coverage_stats(&games, 1, 0, 8);
+        assert_eq!((stats.covered, stats.total), (9, 9));
+    }
+
+    #[test]
+    fn test_expected_random_coverage() {
+        // A single game always covers its own subsets
+        assert!((expected_random_coverage(1, 3, 6, 1, 60) - 20.0).abs() < 1e-9);
+        // With only 6 numbers available, every game is the same one
+        assert!((expected_random_coverage(5, 1, 6, 55, 60) - 6.0).abs() < 1e-9);
+        // Two games over 8 numbers: each pair is missed with probability (1 - 15/28)^2
+        let expected = 28.0 * (1.0 - (13.0f64 / 28.0).powi(2));
+        assert!((expected_random_coverage(2, 2, 6, 53, 60) - expected).abs() < 1e-9);
+        assert_eq!(expected_random_coverage(0, 3, 6, 1, 60), 0.0);
+    }
+
+    /// A generator whose values never have the top bit set, so that ranks (and therefore
+    /// numbers) are drawn from the lower half only.
+    struct LowHalfRng(rand::rngs::StdRng);
+
+    impl rand::RngCore for LowHalfRng {
+        fn next_u32(&mut self) -> u32 {
+            self.0.next_u32() >> 1
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.0.next_u64() >> 1
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for chunk in dest.chunks_mut(8) {
+                let bytes = self.next_u64().to_le_bytes();
+                chunk.copy_from_slice(&bytes[..chunk.len()]);
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_usage_histogram() {
+        let games = [
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![1, 2, 3, 7, 8, 9]),
+        ];
+        // 10 is never played, 4 to 9 once and 1 to 3 twice
+        assert_eq!(usage_histogram(&games, 1, 10), vec![1, 6, 3]);
+        assert_eq!(usage_histogram(&games, 4, 6), vec![0, 3]);
+        assert!(usage_histogram(&[], 1, 0).is_empty());
+    }
+
+    #[test]
+    fn test_frequency_table_counts_and_flags_the_numbers_never_played() {
+        let games = [
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![1, 2, 3, 7, 8, 9]),
+            Game::new(vec![1, 12, 3, 9, 8, 61]),
+        ];
+        let table = frequency_table(&games, 1, 12);
+        let counts: Vec<usize> = table.iter().map(|(_, count)| count).collect();
+        assert_eq!(counts, [3, 2, 3, 1, 1, 1, 1, 2, 2, 0, 0, 1]);
+        assert_eq!((table.count(9), table.count(0), table.count(61)), (2, 0, 0));
+        assert_eq!(table.never_played(), [10, 11]);
+        assert_eq!(
+            table.to_string(),
+            "\
+01  3  ###
+02  2  ##
+03  3  ###
+04  1  #
+05  1  #
+06  1  #
+07  1  #
+08  2  ##
+09  2  ##
+10  0  (never played)
+11  0  (never played)
+12  1  #
+"
+        );
+        assert!(String::from_utf8(table.to_csv())
+            .unwrap()
+            .starts_with("number,count\n1,3\n2,2\n3,3\n4,1\n"));
+
+        // The bars are scaled down to the most played number
+        let mut games = vec![Game::new(vec![1, 2]); 60];
+        games.extend(vec![Game::new(vec![1, 3]); 20]);
+        games.push(Game::new(vec![1, 4]));
+        let lines: Vec<String> = frequency_table(&games, 1, 5)
+            .to_string()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        assert_eq!(lines[0], format!("1  81  {}", "#".repeat(HISTOGRAM_WIDTH)));
+        assert_eq!(lines[1], format!("2  60  {}", "#".repeat(30)));
+        assert_eq!(lines[2], format!("3  20  {}", "#".repeat(10)));
+        assert_eq!(lines[3], "4   1  #");
+        assert_eq!(lines[4], "5   0  (never played)");
+        assert!(frequency_table(&games, 1, 4).never_played().is_empty());
+    }
+
+    #[test]
+    fn test_overlap_histogram() {
+        let games = [
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![9, 8, 7, 3, 2, 1]),
+            Game::new(vec![10, 11, 12, 13, 14, 1]),
+        ];
+        // The first two games share 1 to 3, the third one shares 1 with each
+        assert_eq!(overlap_histogram(&games), vec![0, 2, 0, 1]);
+        assert!(overlap_histogram(&games[..1]).is_empty());
+    }
+
+    #[test]
+    fn test_chi_square_cdf() {
+        // Critical values of the chi-square distribution: 10% and 1% upper tails
+        assert!((chi_square_cdf(14.684, 9) - 0.90).abs() < 2e-3);
+        assert!((chi_square_cdf(87.166, 59) - 0.99).abs() < 2e-3);
+        assert!((chi_square_cdf(59.0, 59) - 0.52).abs() < 1e-2); // Median close to the mean
+        assert_eq!(chi_square_cdf(0.0, 59), 0.0);
+        assert!((standard_normal_cdf(1.96) - 0.975).abs() < 1e-4);
+        assert!((standard_normal_cdf(-1.96) - 0.025).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_analyze_rng_seeded_std_rng_passes() {
+        use rand::SeedableRng;
+        let report = analyze_rng(rand::rngs::StdRng::seed_from_u64(1), 20_000, 1, 60, 6, 0.01);
+        assert!(report.passed, "{}", report);
+        assert_eq!(report.frequencies.len(), 60);
+        assert_eq!(report.frequencies.values().sum::<u64>(), 120_000);
+        assert_eq!(report.expected_frequency, 2_000.0);
+        assert_eq!(report.degrees_of_freedom, 59);
+    }
+
+    #[test]
+    fn test_analyze_rng_biased_rng_fails() {
+        use rand::SeedableRng;
+        let rng = LowHalfRng(rand::rngs::StdRng::seed_from_u64(1));
+        let report = analyze_rng(rng, 20_000, 1, 60, 6, 0.01);
+        assert!(!report.passed, "{}", report);
+        assert!(report.p_value < 1e-6);
+        // Low ranks hold the low numbers, so the top number is never drawn
+        assert_eq!(report.frequencies[&60], 0);
+    }
+
+    #[test]
+    fn test_pair_matrix_of_a_tiny_file() {
+        let mut matrix = PairMatrix::new(1, 5);
+        for game in [[1, 2, 3], [3, 2, 4], [2, 3, 5]] {
+            matrix.add(&game).unwrap();
+        }
+        assert_eq!(matrix.games, 3);
+        assert_eq!(matrix.count(2, 3), 3);
+        assert_eq!(matrix.count(3, 2), 3);
+        assert_eq!(matrix.count(1, 4), 0);
+        assert!((1..=5).all(|number| matrix.count(number, number) == 0));
+        assert_eq!(
+            String::from_utf8(matrix.to_csv()).unwrap(),
+            "number,1,2,3,4,5\n\
+             1,0,1,1,0,0\n\
+             2,1,0,3,1,1\n\
+             3,1,3,0,1,1\n\
+             4,0,1,1,0,0\n\
+             5,0,1,1,0,0\n"
+        );
+        assert_eq!(
+            matrix.most_repeated(3),
+            vec![(2, 3, 3), (1, 2, 1), (1, 3, 1)]
+        );
+        assert_eq!(matrix.most_repeated(100).len(), 7);
+
+        assert_eq!(
+            matrix.add(&[1, 2, 6]),
+            Err("number 6 is outside 1 to 5".to_string())
+        );
+        assert_eq!(matrix.games, 3);
+    }
+
+    #[test]
+    fn test_uniformity_report_json() {
+        use rand::SeedableRng;
+        let report = analyze_rng(rand::rngs::StdRng::seed_from_u64(2), 100, 0, 24, 6, 0.05);
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["draws"], 100);
+        assert_eq!(json["degrees_of_freedom"], 24);
+        assert_eq!(json["frequencies"].as_object().unwrap().len(), 25);
+        assert!(json["frequencies"]["0"].is_u64());
+        assert!(json["passed"].is_boolean());
+    }
+}
@@ -0,0 +1,218 @@
+use crate::config::Lottery;
+use crate::history::{Date, Draw};
+use serde::Deserialize;
+use std::fmt;
+
+/// Address of the Mega-Sena results of the public API of Caixa: the latest result, or the
+/// result of contest `N` at `/N`.
+pub const API_URL: &str = "https://servicebus2.caixa.gov.br/portaldeloterias/api/megasena";
+
+/// Address of the Dupla Sena results of the public API of Caixa, like `API_URL`.
+pub const DUPLA_SENA_API_URL: &str =
+    "https://servicebus2.caixa.gov.br/portaldeloterias/api/duplasena";
+
+/// Address of the Lotofácil results of the public API of Caixa, like `API_URL`.
+pub const LOTOFACIL_API_URL: &str =
+    "https://servicebus2.caixa.gov.br/portaldeloterias/api/lotofacil";
+
+/// Seconds to wait for the API before giving up.
+pub const TIMEOUT_SECS: u64 = 20;
+
+/// The fields of a result of the API used to score tickets, the others being ignored.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiResult {
+    /// The number of the contest.
+    numero: u32,
+    /// The date of the draw, as `dd/mm/yyyy`.
+    data_apuracao: String,
+    /// The numbers drawn, sorted, as two-digit strings.
+    lista_dezenas: Vec<String>,
+    /// The numbers of the second result of the Dupla Sena, `null` for the other lotteries.
+    #[serde(default)]
+    lista_dezenas_segundo_sorteio: Option<Vec<String>>,
+}
+
+/// An error getting a result from the API.
+#[derive(Debug)]
+pub enum FetchError {
+    /// The API could not be reached, or answered with an error.
+    Network { url: String, message: String },
+    /// The API answered with something else than a result.
+    Invalid { url: String, message: String },
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Network { url, message } => {
+                write!(f, "cannot fetch the result from {}: {}", url, message)
+            }
+            FetchError::Invalid { url, message } => {
+                write!(f, "{} did not give a result: {}", url, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// The address of the result of `contest` of `lottery`, or of the latest result for `None`.
+pub fn result_url(lottery: Lottery, contest: Option<u32>) -> String {
+    let url = match lottery {
+        Lottery::MegaSena => API_URL,
+        Lottery::DuplaSena => DUPLA_SENA_API_URL,
+        Lottery::Lotofacil => LOTOFACIL_API_URL,
+    };
+    match contest {
+        Some(contest) => format!("{}/{}", url, contest),
+        None => url.to_string(),
+    }
+}
+
+/// Reads the numbers of a result, given as strings.
+fn parse_numbers(numbers: &[String]) -> Result<Vec<i64>, String> {
+    let mut numbers = numbers
+        .iter()
+        .map(|number| {
+            number
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{}' is not a number", number))
+        })
+        .collect::<Result<Vec<i64>, String>>()?;
+    numbers.sort_unstable();
+    Ok(numbers)
+}
+
+/// Reads a result as the API gives it: the contest in `numero`, the date in `dataApuracao`
+/// and the numbers drawn in `listaDezenas`.
+///
+/// # Returns
+/// * The draw, its numbers sorted.
+/// * An error if the JSON is not a result, or its date or numbers cannot be read.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::caixa::parse_result;
+///
+/// let draw = parse_result(
+///     r#"{"numero": 2925, "dataApuracao": "11/10/2025", "listaDezenas": ["60", "04", "18", "23", "39", "51"]}"#,
+/// )
+/// .unwrap();
+/// assert_eq!(draw.contest, Some(2925));
+/// assert_eq!(draw.numbers, vec![4, 18, 23, 39, 51, 60]);
+/// ```
+pub fn parse_result(json: &str) -> Result<Draw, String> {
+    let mut draws = parse_results(json)?;
+    Ok(draws.swap_remove(0))
+}
+
+/// Reads every result of a contest as the API gives it, like `parse_result`: the first result
+/// in `listaDezenas`, then the second result of the Dupla Sena in `listaDezenasSegundoSorteio`.
+///
+/// # Returns
+/// * The draws of the contest, in the order drawn, their numbers sorted.
+/// * An error if the JSON is not a result, or its date or numbers cannot be read.
+pub fn parse_results(json: &str) -> Result<Vec<Draw>, String> {
+    let result: ApiResult = serde_json::from_str(json).map_err(|error| error.to_string())?;
+    let date: Date = result.data_apuracao.parse()?;
+    std::iter::once(&result.lista_dezenas)
+        .chain(&result.lista_dezenas_segundo_sorteio)
+        .map(|numbers| {
+            Ok(Draw {
+                contest: Some(result.numero),
+                date,
+                numbers: parse_numbers(numbers)?,
+            })
+        })
+        .collect()
+}
+
+/// Downloads the results of `contest` of `lottery`, or the latest ones for `None`, from the
+/// API.
+///
+/// # Returns
+/// * The draws of the contest, one per result of `lottery`, their numbers sorted.
+/// * An error if the API cannot be reached within `TIMEOUT_SECS`, answers with an error, or
+///   gives something else than the results of `contest`.
+#[cfg(feature = "net")]
+pub fn fetch_results(lottery: Lottery, contest: Option<u32>) -> Result<Vec<Draw>, FetchError> {
+    let url = result_url(lottery, contest);
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(std::time::Duration::from_secs(TIMEOUT_SECS)))
+        .build()
+        .into();
+    let network = |error: ureq::Error| FetchError::Network {
+        url: url.clone(),
+        message: error.to_string(),
+    };
+    let body = agent
+        .get(&url)
+        .header("Accept", "application/json")
+        .call()
+        .map_err(network)?
+        .body_mut()
+        .read_to_string()
+        .map_err(network)?;
+    let draws = parse_results(&body).map_err(|message| FetchError::Invalid {
+        url: url.clone(),
+        message,
+    })?;
+    let fetched = draws[0].contest.expect("results have a contest");
+    if contest.is_some_and(|contest| fetched != contest) {
+        return Err(FetchError::Invalid {
+            message: format!("it gave contest {}", fetched),
+            url,
+        });
+    }
+    if draws.len() != lottery.results_per_draw() {
+        return Err(FetchError::Invalid {
+            message: format!("it gave {} result(s) of contest {}", draws.len(), fetched),
+            url,
+        });
+    }
+    Ok(draws)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../tests/fixtures/megasena_result.json");
+    const DUPLA_SENA_FIXTURE: &str = include_str!("../tests/fixtures/duplasena_result.json");
+
+    #[test]
+    fn test_saved_response_reads_as_a_draw() {
+        let draw = parse_result(FIXTURE).unwrap();
+        assert_eq!(draw.contest, Some(2925));
+        assert_eq!(draw.date.to_string(), "11/10/2025");
+        assert_eq!(draw.numbers, vec![4, 18, 23, 39, 51, 60]);
+        assert_eq!(parse_results(FIXTURE).unwrap(), [draw]);
+        assert_eq!(result_url(Lottery::MegaSena, None), API_URL);
+        assert_eq!(
+            result_url(Lottery::MegaSena, Some(2925)),
+            format!("{}/2925", API_URL)
+        );
+
+        // An error page, or a result with a mangled field, is no draw
+        assert!(parse_result("<html>Service unavailable</html>").is_err());
+        assert!(parse_result(&FIXTURE.replace("\"11/10/2025\"", "\"2025\"")).is_err());
+        let mangled = FIXTURE.replace("\"listaDezenas\":[\"04\"", "\"listaDezenas\":[\"x4\"");
+        assert_eq!(parse_result(&mangled).unwrap_err(), "'x4' is not a number");
+    }
+
+    #[test]
+    fn test_dupla_sena_response_reads_as_two_draws() {
+        let draws = parse_results(DUPLA_SENA_FIXTURE).unwrap();
+        assert_eq!(draws.len(), 2);
+        assert_eq!(draws[0].contest, Some(2870));
+        assert_eq!(draws[1].date, draws[0].date);
+        assert_eq!(draws[0].numbers, vec![4, 18, 23, 39, 47, 50]);
+        assert_eq!(draws[1].numbers, vec![1, 2, 3, 5, 6, 8]);
+        assert_eq!(
+            result_url(Lottery::DuplaSena, Some(2870)),
+            format!("{}/2870", DUPLA_SENA_API_URL)
+        );
+    }
+}
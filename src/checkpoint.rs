@@ -0,0 +1,158 @@
+use crate::config::Config;
+use crate::data_structures::{NumberSet, TripletSet};
+use crate::game::Game;
+use crate::generation::SourceState;
+use crate::persistence;
+use crate::stats::GenerationStats;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Start of the name of a checkpoint file, followed by the number of games it holds.
+pub const CHECKPOINT_PREFIX: &str = "checkpoint_";
+
+/// Where a run drawing its games one at a time was, for `generation::resume_from_checkpoint`
+/// to carry on from there as if the run had never stopped.
+///
+/// The random generator is not saved as such: its seed is in the configuration, and `source`
+/// tells how many games and words were drawn from it, which are skipped on restart.
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Version of the program that wrote the checkpoint.
+    pub version: String,
+    /// The settings of the run, command line overrides included.
+    pub config: Config,
+    /// The games accepted so far, initial games included, in order.
+    pub games: Vec<Game>,
+    /// The combinadic numbers of the games drawn.
+    pub game_set: NumberSet,
+    /// The combinadic numbers of the triplets of the games.
+    pub triplet_set: TripletSet,
+    /// The counters of the run so far, the rejections by constraint and the wall time included.
+    pub stats: GenerationStats,
+    /// How far the candidate source went.
+    pub source: SourceState,
+}
+
+impl Checkpoint {
+    /// Writes the checkpoint to `dir`, creating it if needed, as `checkpoint_<games>.json`,
+    /// then removes the oldest checkpoints of `dir` but the last `keep`.
+    ///
+    /// # Returns
+    /// * The path of the checkpoint written.
+    /// * An error if the directory or the file could not be written.
+    pub fn save(&self, dir: impl AsRef<Path>, keep: usize) -> io::Result<PathBuf> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}{:09}.json", CHECKPOINT_PREFIX, self.games.len()));
+        let serialized = serde_json::to_string(self)?;
+        persistence::write_atomically(&path, serialized.as_bytes())?;
+        let checkpoints = checkpoints(dir)?;
+        for old in &checkpoints[..checkpoints.len().saturating_sub(keep.max(1))] {
+            fs::remove_file(old)?;
+        }
+        Ok(path)
+    }
+
+    /// Loads a checkpoint saved by `save`.
+    ///
+    /// # Arguments
+    /// * `path` - A checkpoint file, or a directory of them, whose latest checkpoint is loaded.
+    ///
+    /// # Returns
+    /// * The checkpoint, whose configuration is not validated yet, with the path it was
+    ///   loaded from.
+    /// * An error if no checkpoint is found, or if it could not be read or parsed.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<(Self, PathBuf)> {
+        let path = path.as_ref();
+        let path = if path.is_dir() {
+            checkpoints(path)?.pop().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no checkpoint in the directory")
+            })?
+        } else {
+            path.to_path_buf()
+        };
+        let contents = persistence::read_file(&path)?;
+        let checkpoint = serde_json::from_slice(&contents).map_err(persistence::corrupted)?;
+        Ok((checkpoint, path))
+    }
+}
+
+/// The checkpoint files of `dir`, oldest first.
+fn checkpoints(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut checkpoints = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with(CHECKPOINT_PREFIX) && name.ends_with(".json") {
+            checkpoints.push(path);
+        }
+    }
+    // The counts of games are zero-padded, so the names sort as the counts
+    checkpoints.sort();
+    Ok(checkpoints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(games: usize) -> Checkpoint {
+        let config = Config::from_toml(
+            "no_of_games = 50\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\n",
+        )
+        .unwrap();
+        Checkpoint {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            config,
+            games: vec![Game::new(vec![1, 2, 3, 4, 5, 6]); games],
+            game_set: [0].into_iter().collect(),
+            triplet_set: TripletSet::default(),
+            stats: GenerationStats::default(),
+            source: SourceState {
+                draws: 7,
+                rng_draws: Some(90),
+                window: vec![false, true],
+                scan: None,
+                rejected_in_row: 3,
+            },
+        }
+    }
+
+    #[test]
+    fn test_only_the_latest_checkpoints_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        for games in [10, 20, 30, 40] {
+            checkpoint(games).save(dir.path().join("run"), 2).unwrap();
+        }
+        fs::write(dir.path().join("run/notes.txt"), "kept").unwrap();
+        let mut names: Vec<String> = fs::read_dir(dir.path().join("run"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "checkpoint_000000030.json",
+                "checkpoint_000000040.json",
+                "notes.txt"
+            ]
+        );
+
+        // The directory gives its latest checkpoint, a file that checkpoint alone
+        let (latest, path) = Checkpoint::load(dir.path().join("run")).unwrap();
+        assert_eq!(latest.games.len(), 40);
+        assert_eq!(path, dir.path().join("run/checkpoint_000000040.json"));
+        assert_eq!(latest.source, checkpoint(40).source);
+        assert!(latest.game_set.contains(0));
+        let (older, _) =
+            Checkpoint::load(dir.path().join("run/checkpoint_000000030.json")).unwrap();
+        assert_eq!(older.games.len(), 30);
+
+        let empty = tempfile::tempdir().unwrap();
+        let error = Checkpoint::load(empty.path()).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -1,21 +1,1017 @@
-use serde::Deserialize;
+use crate::constraints;
+use crate::data_structures::{NumberSet, SetFormat, MAX_TRIPLET_NUMBERS};
+use crate::game::{Game, GameMask};
+use crate::wheel;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
 
-#[derive(Deserialize)]
+/// Seed of the standard generator when the configuration does not set one.
+pub const DEFAULT_SEED: u64 = 12345;
+
+/// Prefix of a `storage` setting naming a SQLite database.
+pub const SQLITE_PREFIX: &str = "sqlite:";
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Config {
     pub no_of_games: usize,
-    pub initial_games: Vec<Vec<i64>>,
-    pub seed: Option<u64>,       // Optional random seed
-    pub max_number: i64,         // Maximum playable number
+    #[serde(deserialize_with = "sorted_games")]
+    pub initial_games: Vec<Game>, // Games to start from, their numbers sorted in ascending order when read
+    pub seed: Option<u64>, // Optional random seed
+    pub max_number: i64,   // Maximum playable number
+    #[serde(default = "default_min_number")]
+    pub min_number: i64, // Smallest playable number, 1 unless the lottery starts at 0
     pub min_desired_number: i64, // Minimum number desired in a valid game
+    pub numbers_per_game: Option<usize>, // Numbers of each game, those of a game of the lottery when missing
+    pub max_arithmetic_run: Option<usize>, // Maximum numbers of a game on one arithmetic progression
+    pub max_number_usage: Option<usize>, // Maximum games of the batch each number may be played in
+    #[serde(alias = "max_shared_numbers")]
+    pub max_overlap: Option<usize>, // Maximum numbers any two games may share, 10 for the Lotofácil when missing
+    pub max_attempts: Option<u64>, // Candidates rejected in a row after which the run gives up, or relaxes a constraint
+    #[serde(default)]
+    pub relax_on_exhaustion: bool, // Give up on the least important constraint once no more games follow them all
+    #[serde(default)]
+    pub constraint_priority: Vec<String>, // Constraints `relax_on_exhaustion` may give up on, most important first
+    pub wheel: Option<WheelConfig>, // Optional wheeling mode, replacing random generation
+    #[serde(default)]
+    pub rng: RngKind, // Random number generator used to draw games
+    pub threads: Option<usize>,     // Optional number of worker threads for parallel generation
+    #[serde(default)]
+    pub strategy: Strategy, // How the games are chosen among the valid candidates
+    pub optimize: Option<OptimizeConfig>, // Optional settings of the `--optimize` pass
+    pub checkpoint: Option<CheckpointConfig>, // Optional checkpoints of a long run, for `--from-checkpoint` to carry on from
+    #[serde(default = "default_output")]
+    pub output: String, // File the games are written to
+    #[serde(default)]
+    pub resume: bool,    // Continue the previous run from its saved files instead of starting over
+    #[serde(default)]
+    pub append: bool,    // Add `no_of_games` games to those of the existing output file
+    #[serde(default = "default_csv_header")]
+    pub csv_header: bool, // Write a header row and an index column in the output file
+    #[serde(default = "default_csv_delimiter")]
+    pub csv_delimiter: char, // Field separator of the CSV output file, e.g. ';' for spreadsheets using decimal commas
+    #[serde(default)]
+    pub output_format: OutputFormat, // How the games are written to the output file
+    #[serde(default)]
+    pub zero_pad: bool, // Write the numbers of CSV files zero-padded to the digits of `max_number`, e.g. 05
+    #[serde(default)]
+    pub output_stats_columns: bool, // Write the sum, odd, low, longest run, smallest and largest number of each game after its numbers
+    #[serde(default)]
+    pub output_order: OutputOrder, // Order the games are written in, as found unless shuffled or sorted
+    pub set_format: Option<SetFormat>, // How the sets of games and triplets are saved for `--resume`, by file extension when missing
+    #[serde(default)]
+    pub label_pool: Vec<String>, // Names given in turn to the games without a label, "auto-<game>" once all are taken
+    #[serde(default = "default_tickets_per_block")]
+    pub tickets_per_block: usize, // Tickets between two blank lines of the `--print` table
+    #[serde(default = "default_batches")]
+    pub batches: usize, // Independent sets of `no_of_games` games, each with its own seed and output file
+    #[serde(default)]
+    pub share_triplet_set: bool, // No triplet repeats across the batches either
+    #[serde(default)]
+    pub journal: bool, // Journal the accepted games as they come, for `--resume` to recover from a crash
+    #[serde(default)]
+    pub track_triplet_owners: bool, // Name the game a candidate shares a triplet with in debug logs, at some memory cost
+    #[serde(default)]
+    pub allow_initial_conflicts: bool, // Keep initial games sharing triplets with each other, warning about them
+    #[serde(default)]
+    pub allow_duplicate_initial: DuplicateInitial, // Refuse initial games repeating earlier ones, or keep a single copy
+    #[serde(default)]
+    pub on_invalid_initial: InvalidInitial, // Refuse initial games breaking the constraints, skip them, or fix them when trivial
+    #[serde(default)]
+    pub lottery: Lottery, // The lottery `check` and `simulate` score the tickets against
+    pub prizes: Option<PrizesConfig>, // Optional prize values, for `simulate` to estimate the winnings
+    pub weights: Option<BTreeMap<String, f64>>, // Optional weight of each number for the weighted strategy, 1 when missing
+    pub weights_from_history: Option<String>, // Optional file of past draws weighting each number by its draws instead
+    pub historical_draws_file: Option<String>, // Optional file of past draws, whose games are never generated
+    #[serde(default, deserialize_with = "sorted_games")]
+    pub blacklisted_games: Vec<Game>, // Games never to be played, left out of the output, their numbers sorted when read
+    pub blacklist_file: Option<String>, // Optional file of games never to be played, like `blacklisted_games`
+    #[serde(default)]
+    pub avoid_triplets_from: Vec<String>, // Files of games held by others, whose triplets no game may hold
+    pub storage: Option<String>, // Optional database the games of every run are kept in, as `sqlite:path.db`
+    #[serde(skip)]
+    pub historical_ranks: Option<Arc<NumberSet>>, // Ranks of the games of `historical_draws_file`, once loaded
+    #[serde(skip)]
+    pub blacklist_file_games: Option<Vec<Game>>, // Games of `blacklist_file`, once loaded
+}
+
+/// Settings given on the command line, replacing those of the configuration file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Overrides {
+    pub no_of_games: Option<usize>,
+    pub seed: Option<u64>,
+    pub max_number: Option<i64>,
+    pub min_desired_number: Option<i64>,
+    pub output: Option<String>,
+    pub resume: bool,
+    pub append: bool,
+    pub output_format: Option<OutputFormat>,
+    pub batches: Option<usize>,
+    pub strategy: Option<Strategy>,
+    pub output_order: Option<OutputOrder>,
+}
+
+/// The lotteries the games are made for and the tickets scored against.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Lottery {
+    /// Games of 6 numbers, one result per contest, paying from 4 matches.
+    #[default]
+    MegaSena,
+    /// Games of 6 numbers, two results per contest, every ticket playing in both, paying from
+    /// 3 matches.
+    DuplaSena,
+    /// Games of 15 of 25 numbers, one result per contest, paying from 11 matches.
+    Lotofacil,
+}
+
+impl Lottery {
+    /// The number of results drawn in each contest.
+    pub fn results_per_draw(self) -> usize {
+        match self {
+            Lottery::MegaSena | Lottery::Lotofacil => 1,
+            Lottery::DuplaSena => 2,
+        }
+    }
+
+    /// The numbers of a game, and of a result.
+    pub fn numbers_per_game(self) -> usize {
+        match self {
+            Lottery::MegaSena | Lottery::DuplaSena => 6,
+            Lottery::Lotofacil => 15,
+        }
+    }
+
+    /// The most numbers two games may share unless the configuration tells otherwise.
+    ///
+    /// Two Lotofácil games always share at least 5 of their 15 numbers, and so 10 triplets:
+    /// they are kept from sharing 11 numbers, the lowest prize tier, instead.
+    pub fn default_max_overlap(self) -> Option<usize> {
+        match self {
+            Lottery::MegaSena | Lottery::DuplaSena => None,
+            Lottery::Lotofacil => Some(10),
+        }
+    }
+}
+
+impl std::fmt::Display for Lottery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lottery::MegaSena => write!(f, "Mega-Sena"),
+            Lottery::DuplaSena => write!(f, "Dupla Sena"),
+            Lottery::Lotofacil => write!(f, "Lotofácil"),
+        }
+    }
+}
+
+/// Prize values of the `[prizes]` table, by tier of the lottery.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone)]
+#[serde(default)]
+pub struct PrizesConfig {
+    pub terno: f64,  // Prize of a ticket matching 3 numbers, paid by the Dupla Sena only
+    pub quadra: f64, // Prize of a ticket matching 4 numbers
+    pub quina: f64,  // Prize of a ticket matching 5 numbers
+    pub sena: f64,   // Prize of a ticket matching 6 numbers
+    pub acertos_11: f64, // Prize of a Lotofácil ticket matching 11 numbers
+    pub acertos_12: f64, // Prize of a Lotofácil ticket matching 12 numbers
+    pub acertos_13: f64, // Prize of a Lotofácil ticket matching 13 numbers
+    pub acertos_14: f64, // Prize of a Lotofácil ticket matching 14 numbers
+    pub acertos_15: f64, // Prize of a Lotofácil ticket matching 15 numbers
+    pub ticket_price: f64, // Price of a ticket, taken off the winnings
+}
+
+impl PrizesConfig {
+    /// The prize of a ticket matching `matches` numbers, 0 below the terno and from 7 to 10
+    /// matches, the tiers of the Lotofácil starting at 11.
+    pub fn prize(&self, matches: usize) -> f64 {
+        match matches {
+            3 => self.terno,
+            4 => self.quadra,
+            5 => self.quina,
+            6 => self.sena,
+            11 => self.acertos_11,
+            12 => self.acertos_12,
+            13 => self.acertos_13,
+            14 => self.acertos_14,
+            15 => self.acertos_15,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Settings of the simulated annealing pass improving an existing batch of games.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct OptimizeConfig {
+    pub iterations: u64,          // Number of moves tried
+    pub initial_temperature: f64, // Temperature of the first move
+    pub final_temperature: f64,   // Temperature of the last move, reached geometrically
+}
+
+impl Default for OptimizeConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 100_000,
+            initial_temperature: 2.0,
+            final_temperature: 0.01,
+        }
+    }
+}
+
+/// Settings of the `[checkpoint]` table: how often a long run saves where it is, so that
+/// `--from-checkpoint` can carry on from there after a crash.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct CheckpointConfig {
+    pub dir: String,                // Directory the checkpoints are written to
+    pub every_games: Option<usize>, // Accepted games between two checkpoints
+    pub every_minutes: Option<f64>, // Minutes between two checkpoints
+    pub keep: usize,                // Checkpoints kept, the older ones being removed
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            dir: "checkpoints".to_string(),
+            every_games: None,
+            every_minutes: None,
+            keep: 3,
+        }
+    }
+}
+
+/// The strategies available for choosing the games.
+#[derive(Deserialize, Serialize, ValueEnum, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// Accept every valid candidate as it is drawn.
+    #[default]
+    Random,
+    /// Pick, among a sample of valid candidates, the one covering the most new pairs and numbers.
+    GreedyCoverage,
+    /// Accept every valid candidate as it is drawn, drawing the numbers with the probabilities
+    /// given by `weights` (or `weights_from_history`) instead of uniformly.
+    Weighted,
+    /// Accept every valid candidate as it is drawn, turning down repeated games only: the
+    /// games may share triplets, none being tracked.
+    QuickPick,
+}
+
+/// The random number generators available for drawing games.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum RngKind {
+    /// The standard generator, seeded with `seed` so runs are reproducible.
+    #[default]
+    Std,
+    /// The operating system's entropy source; runs cannot be reproduced.
+    Os,
+    /// A counter-based generator, seeded with `seed` like the standard one but drawing other
+    /// games, that skips the values drawn by a run at once when a checkpoint is carried on.
+    Counter,
+}
+
+/// The formats the games can be written in.
+#[derive(Deserialize, Serialize, ValueEnum, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Comma-separated numbers, one game per row.
+    #[default]
+    Csv,
+    /// An array of `{ "index", "numbers", "rank" }` objects.
+    Json,
+    /// One `{ "index", "numbers", "rank" }` object per line.
+    Ndjson,
+    /// An Excel workbook of the games and the summary of the run, written by binaries built
+    /// with the `xlsx` feature.
+    Xlsx,
+}
+
+/// What to do with the initial games repeating an earlier one, their numbers in any order.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateInitial {
+    /// Refuse the configuration, naming the games repeated.
+    #[default]
+    Reject,
+    /// Keep the first copy of each game, leaving the others out.
+    Dedupe,
+}
+
+/// What to do with the initial games breaking the constraints.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum InvalidInitial {
+    /// Refuse the run, naming the game and the rules it breaks.
+    #[default]
+    Error,
+    /// Leave the game out with a warning, another game being generated in its place.
+    Skip,
+    /// Drop the numbers the game repeats, skipping it as `Skip` does when it is still invalid,
+    /// e.g. when a number is out of range or missing: no number is made up.
+    Fix,
+}
+
+/// The orders the games can be written in.
+#[derive(Deserialize, Serialize, ValueEnum, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputOrder {
+    /// The order the games were found in, the initial games first.
+    #[default]
+    #[value(skip)]
+    Discovery,
+    /// A permutation of the games drawn from the seed of the run, so it is reproducible too.
+    #[value(skip)]
+    Shuffle,
+    /// By their smallest number, then by the next ones.
+    Asc,
+    /// By their combinadic rank.
+    Rank,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "xlsx" => Ok(OutputFormat::Xlsx),
+            _ => Err(format!(
+                "unknown output format '{}', expected csv, json, ndjson or xlsx",
+                s
+            )),
+        }
+    }
+}
+
+/// Settings of the abbreviated wheel generation mode.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct WheelConfig {
+    pub pool: Vec<i64>,   // Favorite numbers to wheel
+    pub guarantee: usize, // Any `guarantee` drawn pool numbers appear together in some game
+}
+
+fn default_min_number() -> i64 {
+    1
+}
+
+fn default_output() -> String {
+    "optimized_games.csv".to_string()
+}
+
+fn default_csv_header() -> bool {
+    true
+}
+
+fn default_csv_delimiter() -> char {
+    ','
+}
+
+fn default_tickets_per_block() -> usize {
+    5
+}
+
+fn default_batches() -> usize {
+    1
+}
+
+/// Reads games with their numbers sorted in ascending order, keeping their labels, so that a
+/// game is written, compared and ranked the same whatever the order it was typed in.
+fn sorted_games<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<Game>, D::Error> {
+    let games = Vec::<Game>::deserialize(deserializer)?;
+    Ok(games.into_iter().map(Game::sorted).collect())
+}
+
+/// The file of batch `batch` of a run writing to `path`: `_<batch>` inserted before the
+/// extension, e.g. `optimized_games_2.csv` for batch 2 of `optimized_games.csv`.
+pub fn batch_path(path: &str, batch: usize) -> String {
+    suffixed_path(path, &batch.to_string())
+}
+
+/// `path` with `_<suffix>` inserted before the extension, e.g. `tickets_rejects.csv` for
+/// `tickets.csv` and `rejects`.
+pub fn suffixed_path(path: &str, suffix: &str) -> String {
+    let path = Path::new(path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, suffix),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
 }
 
 impl Config {
     pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+        Self::from_toml(&content)
+    }
+
+    /// Parses and validates a configuration from TOML text.
+    ///
+    /// # Examples
+    /// ```
+    /// use rk_lottery::config::Config;
+    ///
+    /// let config = Config::from_toml(
+    ///     r#"
+    ///     no_of_games = 3
+    ///     initial_games = [[1, 2, 3, 4, 5, 6]]
+    ///     max_number = 60
+    ///     min_desired_number = 1
+    ///     "#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(config.no_of_games, 3);
+    /// assert_eq!(config.min_number, 1);
+    /// ```
+    pub fn from_toml(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let config: Config = toml::from_str(content)?;
+        config.validate()?;
         Ok(config)
     }
+
+    /// Replaces the settings given in `overrides`, keeping the others, and checks the result.
+    ///
+    /// # Arguments
+    /// * `overrides` - The settings replacing those of the configuration file.
+    ///
+    /// # Returns
+    /// * The merged configuration.
+    /// * An error if the merged settings are inconsistent.
+    pub fn merge(mut self, overrides: &Overrides) -> Result<Self, String> {
+        if let Some(no_of_games) = overrides.no_of_games {
+            self.no_of_games = no_of_games;
+        }
+        if let Some(seed) = overrides.seed {
+            self.seed = Some(seed);
+        }
+        if let Some(max_number) = overrides.max_number {
+            self.max_number = max_number;
+        }
+        if let Some(min_desired_number) = overrides.min_desired_number {
+            self.min_desired_number = min_desired_number;
+        }
+        if let Some(output) = &overrides.output {
+            self.output = output.clone();
+        }
+        if overrides.resume {
+            self.resume = true;
+        }
+        if overrides.append {
+            self.append = true;
+        }
+        if let Some(output_format) = overrides.output_format {
+            self.output_format = output_format;
+        }
+        if let Some(batches) = overrides.batches {
+            self.batches = batches;
+        }
+        if let Some(strategy) = overrides.strategy {
+            self.strategy = strategy;
+        }
+        if let Some(output_order) = overrides.output_order {
+            self.output_order = output_order;
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// The seed the games are drawn with: `seed`, or `DEFAULT_SEED` when unset.
+    ///
+    /// # Returns
+    /// * The seed of the standard generator.
+    /// * `None` when drawing from OS entropy, which cannot be seeded.
+    pub fn effective_seed(&self) -> Option<u64> {
+        match self.rng {
+            RngKind::Std | RngKind::Counter => Some(self.seed.unwrap_or(DEFAULT_SEED)),
+            RngKind::Os => None,
+        }
+    }
+
+    /// The path of the SQLite database of `storage`, when set; `validate` makes sure it is given
+    /// as `sqlite:path`.
+    pub fn sqlite_path(&self) -> Option<&str> {
+        self.storage
+            .as_deref()
+            .and_then(|storage| storage.strip_prefix(SQLITE_PREFIX))
+    }
+
+    /// The field separator of the CSV output file, as the byte the CSV writer and readers take;
+    /// `validate` makes sure it is ASCII.
+    pub fn csv_delimiter_byte(&self) -> u8 {
+        self.csv_delimiter as u8
+    }
+
+    /// The settings of batch `batch` (starting at 1) of a run of several batches, as for a run
+    /// of that batch alone: its seed is the seed of the run plus `batch - 1` and its output file
+    /// is named by `batch_path`. When the batches share their triplets, only the first batch
+    /// starts with the initial games.
+    pub fn batch(&self, batch: usize) -> Config {
+        let seed = match self.effective_seed() {
+            Some(seed) => Some(seed.wrapping_add(batch as u64 - 1)),
+            None => self.seed,
+        };
+        let initial_games = if self.share_triplet_set && batch > 1 {
+            Vec::new()
+        } else {
+            self.initial_games.clone()
+        };
+        Config {
+            seed,
+            initial_games,
+            output: batch_path(&self.output, batch),
+            batches: 1,
+            ..self.clone()
+        }
+    }
+
+    /// The initial games repeating an earlier one, as `(index, earlier)` positions starting
+    /// at 0, in order; two games are the same when their sorted numbers are.
+    pub fn repeated_initial_games(&self) -> Vec<(usize, usize)> {
+        let mut first: HashMap<Vec<i64>, usize> = HashMap::new();
+        self.initial_games
+            .iter()
+            .enumerate()
+            .filter_map(|(index, game)| {
+                let earlier = *first.entry(game.clone().sorted().to_vec()).or_insert(index);
+                (earlier != index).then_some((index, earlier))
+            })
+            .collect()
+    }
+
+    /// The initial games to start from: those of `initial_games`, the repeated ones left out
+    /// when `allow_duplicate_initial` is `"dedupe"`, each with its position in
+    /// `initial_games` starting at 0, for the messages to name the games as configured.
+    pub fn distinct_initial_games(&self) -> Vec<(usize, Game)> {
+        let repeated: HashSet<usize> = match self.allow_duplicate_initial {
+            DuplicateInitial::Reject => HashSet::new(),
+            DuplicateInitial::Dedupe => self
+                .repeated_initial_games()
+                .into_iter()
+                .map(|(index, _)| index)
+                .collect(),
+        };
+        self.initial_games
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !repeated.contains(index))
+            .map(|(index, game)| (index, game.clone()))
+            .collect()
+    }
+
+    /// Whether the games are labeled: some initial game has a label, or `label_pool` names
+    /// the owners of the generated games.
+    pub fn labeled(&self) -> bool {
+        !self.label_pool.is_empty() || self.initial_games.iter().any(|game| game.label().is_some())
+    }
+
+    /// The digits the numbers of the CSV files written are zero-padded to: those of
+    /// `max_number` with `zero_pad`, 0 for no padding otherwise.
+    pub fn number_width(&self) -> usize {
+        if self.zero_pad {
+            self.max_number.to_string().len()
+        } else {
+            0
+        }
+    }
+
+    /// The smallest and largest numbers of the lottery, with `output_stats_columns`, for the
+    /// figures of each game to be written along with it.
+    pub fn stats_range(&self) -> Option<(i64, i64)> {
+        self.output_stats_columns
+            .then_some((self.min_number, self.max_number))
+    }
+
+    /// The numbers of each game: `numbers_per_game`, or those of a game of the lottery.
+    pub fn numbers_per_game(&self) -> usize {
+        self.numbers_per_game
+            .unwrap_or(self.lottery.numbers_per_game())
+    }
+
+    /// The most numbers two games may share: `max_overlap`, or the default of the lottery
+    /// unless drawn by the quick-pick strategy.
+    pub fn max_overlap(&self) -> Option<usize> {
+        self.max_overlap.or(self
+            .lottery
+            .default_max_overlap()
+            .filter(|_| self.strategy != Strategy::QuickPick))
+    }
+
+    /// Whether no two games may share a triplet, which only holds for games of 6 numbers
+    /// drawn by another strategy than quick-pick; games of other sizes are kept apart by
+    /// `max_overlap` alone.
+    pub fn unique_triplets(&self) -> bool {
+        self.numbers_per_game() == 6 && self.strategy != Strategy::QuickPick
+    }
+
+    /// The games never to be played: `blacklisted_games`, then those of `blacklist_file` once
+    /// loaded.
+    pub fn blacklist(&self) -> impl Iterator<Item = &Game> {
+        self.blacklisted_games
+            .iter()
+            .chain(self.blacklist_file_games.iter().flatten())
+    }
+
+    /// The weight of each number of `min_desired_number..=max_number` for the weighted
+    /// strategy, 1 for the numbers missing from `weights`; numbers of weight 0 are never drawn.
+    pub fn number_weights(&self) -> Vec<(i64, f64)> {
+        let weights: BTreeMap<i64, f64> = self
+            .weights
+            .iter()
+            .flatten()
+            .filter_map(|(number, &weight)| Some((number.trim().parse().ok()?, weight)))
+            .collect();
+        (self.min_desired_number..=self.max_number)
+            .map(|number| (number, weights.get(&number).copied().unwrap_or(1.0)))
+            .collect()
+    }
+
+    /// Checks the settings that depend on each other.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_desired_number < self.min_number {
+            return Err(format!(
+                "min_desired_number ({}) must not be below min_number ({})",
+                self.min_desired_number, self.min_number
+            ));
+        }
+        if self.resume && self.append {
+            return Err("resume and append cannot be combined".to_string());
+        }
+        if self.tickets_per_block == 0 {
+            return Err("tickets_per_block must be at least 1".to_string());
+        }
+        if self.batches == 0 {
+            return Err("batches must be at least 1".to_string());
+        }
+        if self.batches > 1 && (self.resume || self.append) {
+            return Err("batches cannot be combined with resume or append".to_string());
+        }
+        if self.batches > 1 && self.journal {
+            return Err("batches cannot be combined with journal".to_string());
+        }
+        if self.output_order != OutputOrder::Discovery
+            && (self.resume || self.append || self.journal)
+        {
+            return Err(
+                "output_order cannot be combined with resume, append or journal, which carry on the games in the order found"
+                    .to_string(),
+            );
+        }
+        if self.output_format == OutputFormat::Xlsx {
+            if !cfg!(feature = "xlsx") {
+                return Err(
+                    "output_format xlsx is not supported by this binary, rebuild it with `cargo build --features xlsx`"
+                        .to_string(),
+                );
+            }
+            if self.resume || self.append {
+                return Err("output_format xlsx cannot be combined with resume or append, the workbook being written whole".to_string());
+            }
+        }
+        if let Some(storage) = &self.storage {
+            if self.sqlite_path().is_none_or(str::is_empty) {
+                return Err(format!(
+                    "storage must be given as \"{}path.db\", got {:?}",
+                    SQLITE_PREFIX, storage
+                ));
+            }
+            if !cfg!(feature = "sqlite") {
+                return Err(
+                    "storage is not supported by this binary, rebuild it with `cargo build --features sqlite`"
+                        .to_string(),
+                );
+            }
+            if self.resume || self.append || self.batches > 1 {
+                return Err(
+                    "storage cannot be combined with resume, append or batches, each run being stored on its own"
+                        .to_string(),
+                );
+            }
+        }
+        if self.labeled() && self.output_format == OutputFormat::Csv && !self.csv_header {
+            return Err(
+                "labeled games need the header row of csv_header = true, naming the label column"
+                    .to_string(),
+            );
+        }
+        if self.output_stats_columns {
+            if self.output_format == OutputFormat::Csv && !self.csv_header {
+                return Err("output_stats_columns needs the header row of csv_header = true, naming the columns".to_string());
+            }
+            if self.output_format == OutputFormat::Xlsx {
+                return Err("output_stats_columns cannot be combined with output_format xlsx, whose sheet holds the numbers only".to_string());
+            }
+        }
+        if !self.avoid_triplets_from.is_empty()
+            && (self.resume
+                || self.append
+                || self.journal
+                || self.batches > 1
+                || self.wheel.is_some())
+        {
+            return Err(
+                "avoid_triplets_from cannot be combined with resume, append, journal, batches or a wheel"
+                    .to_string(),
+            );
+        }
+        if self.allow_initial_conflicts && (self.resume || self.journal || self.storage.is_some()) {
+            return Err(
+                "allow_initial_conflicts cannot be combined with resume, journal or storage, which need games sharing no triplet"
+                    .to_string(),
+            );
+        }
+        if self.max_number_usage == Some(0) {
+            return Err("max_number_usage must be at least 1".to_string());
+        }
+        self.validate_checkpoint()?;
+        self.validate_relaxation()?;
+        self.validate_initial_games()?;
+        self.validate_game_size()?;
+        self.validate_blacklist()?;
+        self.validate_wheel()?;
+        let delimiter = self.csv_delimiter;
+        if !delimiter.is_ascii()
+            || delimiter.is_ascii_alphanumeric()
+            || "\"\r\n".contains(delimiter)
+        {
+            return Err(format!(
+                "csv_delimiter must be an ASCII character other than a letter, a digit, a quote or a line break, got {:?}",
+                delimiter
+            ));
+        }
+        let numbers = self.max_number - self.min_number + 1;
+        if numbers > MAX_TRIPLET_NUMBERS {
+            return Err(format!(
+                "the lottery has {} numbers, at most {} are supported",
+                numbers, MAX_TRIPLET_NUMBERS
+            ));
+        }
+        self.validate_weights()
+    }
+
+    /// Checks `max_attempts`, `relax_on_exhaustion` and `constraint_priority`: the constraints
+    /// listed may be relaxed and are listed once, and the candidates are counted one at a time
+    /// by a run starting from its initial games alone, since the constraints relaxed are not
+    /// carried on by a run continuing it.
+    fn validate_relaxation(&self) -> Result<(), String> {
+        if self.max_attempts == Some(0) {
+            return Err("max_attempts must be at least 1".to_string());
+        }
+        for (index, name) in self.constraint_priority.iter().enumerate() {
+            if !constraints::RELAXABLE_CONSTRAINTS.contains(&name.as_str()) {
+                return Err(format!(
+                    "constraint_priority lists unknown constraint {:?}, expected any of {}",
+                    name,
+                    constraints::RELAXABLE_CONSTRAINTS.join(", ")
+                ));
+            }
+            if self.constraint_priority[..index].contains(name) {
+                return Err(format!("constraint_priority lists {:?} twice", name));
+            }
+        }
+        if (self.max_attempts.is_some() || self.relax_on_exhaustion)
+            && (self.threads.is_some() || self.strategy == Strategy::GreedyCoverage)
+        {
+            return Err(
+                "max_attempts and relax_on_exhaustion need the games drawn one at a time, on a single thread and without the greedy-coverage strategy"
+                    .to_string(),
+            );
+        }
+        if self.relax_on_exhaustion
+            && (self.resume || self.append || self.journal || self.checkpoint.is_some())
+        {
+            return Err(
+                "relax_on_exhaustion cannot be combined with resume, append, journal or checkpoint, which carry on the run with every constraint"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks the `[checkpoint]` table says when to write a checkpoint, and is only given to
+    /// runs drawing their games one at a time from the seeded generator, which a run can carry
+    /// on from by drawing the same games again.
+    fn validate_checkpoint(&self) -> Result<(), String> {
+        let Some(checkpoint) = &self.checkpoint else {
+            return Ok(());
+        };
+        if checkpoint.every_games.is_none() && checkpoint.every_minutes.is_none() {
+            return Err("[checkpoint] needs every_games or every_minutes".to_string());
+        }
+        if checkpoint.every_games == Some(0) {
+            return Err("[checkpoint] every_games must be at least 1".to_string());
+        }
+        if checkpoint
+            .every_minutes
+            .is_some_and(|minutes| !(minutes > 0.0 && minutes.is_finite()))
+        {
+            return Err("[checkpoint] every_minutes must be a positive number".to_string());
+        }
+        if checkpoint.keep == 0 {
+            return Err("[checkpoint] keep must be at least 1".to_string());
+        }
+        if self.threads.is_some() || self.strategy == Strategy::GreedyCoverage {
+            return Err(
+                "[checkpoint] needs the games drawn one at a time, on a single thread and without the greedy-coverage strategy"
+                    .to_string(),
+            );
+        }
+        if self.rng == RngKind::Os {
+            return Err(
+                "[checkpoint] needs a seeded generator, \"std\" or \"counter\", whose games can be drawn again"
+                    .to_string(),
+            );
+        }
+        if self.resume
+            || self.append
+            || self.journal
+            || self.batches > 1
+            || self.storage.is_some()
+            || self.wheel.is_some()
+            || !self.avoid_triplets_from.is_empty()
+        {
+            return Err(
+                "[checkpoint] cannot be combined with resume, append, journal, batches, storage, avoid_triplets_from or a wheel"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks the `[wheel]` pool is small enough for its games to be picked in a few seconds.
+    fn validate_wheel(&self) -> Result<(), String> {
+        let Some(wheel) = &self.wheel else {
+            return Ok(());
+        };
+        let distinct = wheel.pool.iter().collect::<HashSet<_>>().len();
+        if distinct > wheel::MAX_POOL_SIZE {
+            return Err(format!(
+                "[wheel] pool has {} distinct numbers, at most {} can be wheeled",
+                distinct,
+                wheel::MAX_POOL_SIZE
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the games have numbers, and that games of other sizes than 6, which have no 20
+    /// triplets to keep apart, are not combined with what tracks the triplets.
+    fn validate_game_size(&self) -> Result<(), String> {
+        let numbers_per_game = self.numbers_per_game();
+        if numbers_per_game == 0 {
+            return Err("numbers_per_game must be at least 1".to_string());
+        }
+        if self.max_overlap().is_some() {
+            let numbers = self.max_number - self.min_number + 1;
+            if numbers > GameMask::MAX_NUMBER {
+                return Err(format!(
+                    "max_overlap needs a lottery of at most {} numbers, the lottery has {}",
+                    GameMask::MAX_NUMBER,
+                    numbers
+                ));
+            }
+        }
+        if self.unique_triplets() {
+            return Ok(());
+        }
+        let triplet_settings = [
+            ("resume", self.resume),
+            ("journal", self.journal),
+            ("share_triplet_set", self.share_triplet_set),
+            ("track_triplet_owners", self.track_triplet_owners),
+            ("storage", self.storage.is_some()),
+            ("wheel", self.wheel.is_some()),
+            ("avoid_triplets_from", !self.avoid_triplets_from.is_empty()),
+        ];
+        match triplet_settings.iter().find(|&&(_, set)| set) {
+            Some((name, _)) if self.strategy == Strategy::QuickPick => Err(format!(
+                "{} keeps track of the triplets of the games, which the quick-pick strategy does not",
+                name
+            )),
+            Some((name, _)) => Err(format!(
+                "{} keeps track of the triplets of the games, and needs games of 6 numbers, not {}",
+                name, numbers_per_game
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `game` is a game of the lottery: `numbers_per_game` distinct numbers of
+    /// `min_number..=max_number`, in any order.
+    pub fn is_lottery_game(&self, game: &[i64]) -> bool {
+        let mut numbers = game.to_vec();
+        numbers.sort_unstable();
+        numbers.dedup();
+        numbers.len() == game.len()
+            && game.len() == self.numbers_per_game()
+            && game
+                .iter()
+                .all(|number| (self.min_number..=self.max_number).contains(number))
+    }
+
+    /// Checks the blacklisted games are games of the lottery, which a run could draw.
+    fn validate_blacklist(&self) -> Result<(), String> {
+        match self
+            .blacklist()
+            .enumerate()
+            .find(|(_, game)| !self.is_lottery_game(game))
+        {
+            Some((index, game)) => Err(format!(
+                "blacklisted game #{} ({}) is not a game of {} distinct numbers from {} to {}",
+                index + 1,
+                game,
+                self.numbers_per_game(),
+                self.min_number,
+                self.max_number
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks no initial game repeats an earlier one, unless `allow_duplicate_initial` keeps a
+    /// single copy of each.
+    fn validate_initial_games(&self) -> Result<(), String> {
+        let repeated = self.repeated_initial_games();
+        if repeated.is_empty() || self.allow_duplicate_initial == DuplicateInitial::Dedupe {
+            return Ok(());
+        }
+        let repeated: Vec<String> = repeated
+            .iter()
+            .map(|&(index, earlier)| {
+                format!(
+                    "#{} repeats #{} ({})",
+                    index + 1,
+                    earlier + 1,
+                    self.initial_games[earlier]
+                )
+            })
+            .collect();
+        Err(format!(
+            "initial games repeat earlier ones: {}; remove the repeats, or set allow_duplicate_initial = \"dedupe\" to keep a single copy of each",
+            repeated.join(", ")
+        ))
+    }
+
+    /// Checks the weights are only given to the weighted strategy, and leave it enough numbers.
+    fn validate_weights(&self) -> Result<(), String> {
+        let weighted = self.weights.is_some() || self.weights_from_history.is_some();
+        if self.weights.is_some() && self.weights_from_history.is_some() {
+            return Err("weights and weights_from_history cannot be combined".to_string());
+        }
+        if self.strategy == Strategy::Weighted && !weighted {
+            return Err(
+                "the weighted strategy needs a [weights] table or weights_from_history".to_string(),
+            );
+        }
+        if self.strategy != Strategy::Weighted && weighted {
+            return Err(
+                "weights are only used by the weighted strategy, set strategy = \"weighted\""
+                    .to_string(),
+            );
+        }
+        let Some(weights) = &self.weights else {
+            return Ok(());
+        };
+        for (key, &weight) in weights {
+            let number: i64 = key
+                .trim()
+                .parse()
+                .map_err(|_| format!("weights: '{}' is not a number", key))?;
+            if !(self.min_desired_number..=self.max_number).contains(&number) {
+                return Err(format!(
+                    "weights: number {} is outside min_desired_number ({}) to max_number ({})",
+                    number, self.min_desired_number, self.max_number
+                ));
+            }
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(format!(
+                    "weights: the weight of {} must be 0 or more, not {}",
+                    number, weight
+                ));
+            }
+        }
+        let positive = self
+            .number_weights()
+            .iter()
+            .filter(|(_, weight)| *weight > 0.0)
+            .count();
+        if positive < 6 {
+            return Err(format!(
+                "weights: only {} numbers have a positive weight, a game needs 6",
+                positive
+            ));
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -31,6 +1027,9 @@ mod tests {
             seed = 12345
             max_number = 49
             min_desired_number = 10
+            max_arithmetic_run = 3
+            threads = 4
+            strategy = "greedy-coverage"
         "#;
 
         // Try to parse the TOML string into our Config struct
@@ -41,10 +1040,874 @@ mod tests {
         assert_eq!(parsed_config.no_of_games, 3);
         assert_eq!(
             parsed_config.initial_games,
-            vec![vec![1, 2, 3], vec![4, 5, 6]]
+            vec![Game::new(vec![1, 2, 3]), Game::new(vec![4, 5, 6])]
         );
         assert_eq!(parsed_config.seed, Some(12345));
         assert_eq!(parsed_config.max_number, 49);
+        assert_eq!(parsed_config.min_number, 1);
         assert_eq!(parsed_config.min_desired_number, 10);
+        assert_eq!(parsed_config.max_arithmetic_run, Some(3));
+        assert!(parsed_config.wheel.is_none());
+        assert_eq!(parsed_config.rng, RngKind::Std);
+        assert_eq!(parsed_config.threads, Some(4));
+        assert_eq!(parsed_config.strategy, Strategy::GreedyCoverage);
+    }
+
+    #[test]
+    fn test_config_can_parse_rng_kind() {
+        let toml_str = r#"
+            no_of_games = 3
+            initial_games = []
+            max_number = 60
+            min_desired_number = 1
+            rng = "os"
+        "#;
+
+        let parsed_config: Config =
+            toml::from_str(toml_str).expect("Failed to parse TOML string into Config");
+        assert_eq!(parsed_config.rng, RngKind::Os);
+        assert_eq!(parsed_config.strategy, Strategy::Random);
+        assert_eq!(parsed_config.effective_seed(), None);
+
+        let counter: Config = toml::from_str(&toml_str.replace(r#""os""#, r#""counter""#))
+            .expect("Failed to parse TOML string into Config");
+        assert_eq!(counter.rng, RngKind::Counter);
+        assert_eq!(counter.effective_seed(), Some(DEFAULT_SEED));
+
+        let invalid = toml_str.replace(r#""os""#, r#""dice""#);
+        assert!(toml::from_str::<Config>(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_config_validates_min_number() {
+        let toml_str = r#"
+            no_of_games = 3
+            initial_games = []
+            max_number = 24
+            min_number = 0
+            min_desired_number = 0
+        "#;
+
+        let parsed_config: Config =
+            toml::from_str(toml_str).expect("Failed to parse TOML string into Config");
+        assert_eq!(parsed_config.min_number, 0);
+        assert!(parsed_config.validate().is_ok());
+
+        let invalid: Config = toml::from_str(&toml_str.replace("min_number = 0", "min_number = 1"))
+            .expect("Failed to parse TOML string into Config");
+        assert_eq!(
+            invalid.validate(),
+            Err("min_desired_number (0) must not be below min_number (1)".to_string())
+        );
+    }
+
+    fn sample_config() -> Config {
+        Config::from_toml(
+            r#"
+            no_of_games = 3
+            initial_games = []
+            seed = 12345
+            max_number = 60
+            min_desired_number = 32
+            "#,
+        )
+        .expect("Failed to parse TOML string into Config")
+    }
+
+    #[test]
+    fn test_merge_without_overrides_keeps_file_values() {
+        let merged = sample_config().merge(&Overrides::default()).unwrap();
+        assert_eq!(merged, sample_config());
+        assert_eq!(merged.output, "optimized_games.csv");
+        assert!(!merged.resume);
+    }
+
+    #[test]
+    fn test_merge_applies_each_override() {
+        let merge = |overrides: Overrides| sample_config().merge(&overrides).unwrap();
+
+        assert_eq!(
+            merge(Overrides {
+                no_of_games: Some(10),
+                ..Overrides::default()
+            }),
+            Config {
+                no_of_games: 10,
+                ..sample_config()
+            }
+        );
+        assert_eq!(
+            merge(Overrides {
+                strategy: Some(Strategy::GreedyCoverage),
+                ..Overrides::default()
+            }),
+            Config {
+                strategy: Strategy::GreedyCoverage,
+                ..sample_config()
+            }
+        );
+        assert_eq!(
+            merge(Overrides {
+                seed: Some(7),
+                ..Overrides::default()
+            }),
+            Config {
+                seed: Some(7),
+                ..sample_config()
+            }
+        );
+        assert_eq!(
+            merge(Overrides {
+                max_number: Some(49),
+                ..Overrides::default()
+            }),
+            Config {
+                max_number: 49,
+                ..sample_config()
+            }
+        );
+        assert_eq!(
+            merge(Overrides {
+                min_desired_number: Some(1),
+                ..Overrides::default()
+            }),
+            Config {
+                min_desired_number: 1,
+                ..sample_config()
+            }
+        );
+        assert_eq!(
+            merge(Overrides {
+                output: Some("tickets.csv".to_string()),
+                ..Overrides::default()
+            }),
+            Config {
+                output: "tickets.csv".to_string(),
+                ..sample_config()
+            }
+        );
+        assert_eq!(
+            merge(Overrides {
+                output_format: Some(OutputFormat::Ndjson),
+                ..Overrides::default()
+            }),
+            Config {
+                output_format: OutputFormat::Ndjson,
+                ..sample_config()
+            }
+        );
+        assert_eq!(
+            merge(Overrides {
+                output_order: Some(OutputOrder::Shuffle),
+                ..Overrides::default()
+            }),
+            Config {
+                output_order: OutputOrder::Shuffle,
+                ..sample_config()
+            }
+        );
+    }
+
+    #[test]
+    fn test_lotteries_too_large_for_triplet_ranks_are_refused() {
+        let config = |max_number| Config {
+            max_number,
+            ..sample_config()
+        };
+        assert!(config(MAX_TRIPLET_NUMBERS).validate().is_ok());
+        assert_eq!(
+            config(MAX_TRIPLET_NUMBERS + 1).validate(),
+            Err("the lottery has 2955 numbers, at most 2954 are supported".to_string())
+        );
+    }
+
+    #[test]
+    fn test_csv_delimiter_must_separate_numbers() {
+        assert_eq!(sample_config().csv_delimiter_byte(), b',');
+        let config = Config::from_toml(
+            "no_of_games = 3\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\ncsv_delimiter = \";\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.csv_delimiter_byte(), b';');
+        for delimiter in ['\t', '|', ' '] {
+            let config = Config {
+                csv_delimiter: delimiter,
+                ..sample_config()
+            };
+            assert!(config.validate().is_ok());
+        }
+        for delimiter in ['7', 'n', '"', '\n', 'é'] {
+            let config = Config {
+                csv_delimiter: delimiter,
+                ..sample_config()
+            };
+            assert!(config
+                .validate()
+                .unwrap_err()
+                .starts_with("csv_delimiter must be an ASCII character"));
+        }
+    }
+
+    #[test]
+    fn test_storage_names_a_sqlite_database() {
+        assert_eq!(sample_config().sqlite_path(), None);
+        let config = Config {
+            storage: Some("sqlite:tickets.db".to_string()),
+            ..sample_config()
+        };
+        assert_eq!(config.sqlite_path(), Some("tickets.db"));
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "sqlite"));
+        for storage in ["tickets.db", "sqlite:", "postgres://localhost"] {
+            let config = Config {
+                storage: Some(storage.to_string()),
+                ..sample_config()
+            };
+            assert!(config
+                .validate()
+                .unwrap_err()
+                .starts_with("storage must be given"));
+        }
+        let config = Config {
+            append: true,
+            ..config
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_labels_come_from_the_initial_games_or_the_pool() {
+        assert!(!sample_config().labeled());
+        let config = Config::from_toml(
+            r#"
+            no_of_games = 3
+            initial_games = [[1, 2, 3, 4, 5, 6], { numbers = [7, 8, 9, 10, 11, 12], label = "Maria" }]
+            max_number = 60
+            min_desired_number = 1
+            "#,
+        )
+        .unwrap();
+        assert!(config.labeled());
+        assert_eq!(config.initial_games[1].label(), Some("Maria"));
+        let pooled = Config {
+            label_pool: vec!["João".to_string()],
+            ..sample_config()
+        };
+        assert!(pooled.labeled());
+
+        // Without the header row, a label column could not be told from a malformed row
+        let plain = Config {
+            csv_header: false,
+            ..config
+        };
+        assert!(plain
+            .validate()
+            .unwrap_err()
+            .starts_with("labeled games need the header row"));
+        let json = Config {
+            output_format: OutputFormat::Json,
+            ..plain
+        };
+        assert!(json.validate().is_ok());
+    }
+
+    #[test]
+    fn test_tickets_per_block_must_be_positive() {
+        assert_eq!(sample_config().tickets_per_block, 5);
+        let config = Config {
+            tickets_per_block: 0,
+            ..sample_config()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_batches_get_their_own_seed_and_file() {
+        let config = Config {
+            batches: 3,
+            ..sample_config()
+        };
+        let batches: Vec<Config> = (1..=3).map(|batch| config.batch(batch)).collect();
+        assert_eq!(
+            batches.iter().map(|batch| batch.seed).collect::<Vec<_>>(),
+            [Some(12345), Some(12346), Some(12347)]
+        );
+        assert_eq!(batches[1].output, "optimized_games_2.csv");
+        assert!(batches.iter().all(|batch| batch.batches == 1));
+        assert_eq!(batches[2].initial_games, config.initial_games);
+
+        let shared = Config {
+            share_triplet_set: true,
+            ..config
+        };
+        assert_eq!(shared.batch(1).initial_games, shared.initial_games);
+        assert!(shared.batch(2).initial_games.is_empty());
+        assert!(Config {
+            batches: 0,
+            ..sample_config()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_batch_path_keeps_directory_and_extension() {
+        assert_eq!(
+            batch_path("optimized_games.csv", 1),
+            "optimized_games_1.csv"
+        );
+        assert_eq!(
+            batch_path("out/tickets.v2.json", 12),
+            "out/tickets.v2_12.json"
+        );
+        assert_eq!(batch_path("tickets", 3), "tickets_3");
+    }
+
+    #[test]
+    fn test_output_format_parses_from_file_and_flag() {
+        assert_eq!(sample_config().output_format, OutputFormat::Csv);
+        let mut config: Config = toml::from_str(
+            "no_of_games = 3\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\noutput_format = \"json\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.output_format, OutputFormat::Json);
+        assert_eq!("ndjson".parse(), Ok(OutputFormat::Ndjson));
+        assert!("xml".parse::<OutputFormat>().is_err());
+        config.output_format = "xlsx".parse().unwrap();
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "xlsx"));
+        config.resume = true;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_stats_columns_need_a_header_row() {
+        let mut config = sample_config();
+        assert_eq!(config.stats_range(), None);
+        config.output_stats_columns = true;
+        assert_eq!(config.stats_range(), Some((1, 60)));
+        assert!(config.validate().is_ok());
+        config.csv_header = false;
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .starts_with("output_stats_columns needs the header row"));
+        // JSON names its fields anyway
+        config.output_format = OutputFormat::Json;
+        assert!(config.validate().is_ok());
+        config.output_format = OutputFormat::Xlsx;
+        if cfg!(feature = "xlsx") {
+            assert!(config
+                .validate()
+                .unwrap_err()
+                .starts_with("output_stats_columns cannot be combined"));
+        }
+    }
+
+    #[test]
+    fn test_relaxation_checks_the_priorities_and_the_run() {
+        let mut config = sample_config();
+        config.max_attempts = Some(0);
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "max_attempts must be at least 1"
+        );
+        config.max_attempts = Some(10_000);
+        config.relax_on_exhaustion = true;
+        config.constraint_priority = vec!["usage".to_string(), "blacklist".to_string()];
+        assert!(config.validate().is_ok());
+
+        config.constraint_priority.push("range".to_string());
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .starts_with("constraint_priority lists unknown constraint \"range\""));
+        config.constraint_priority[2] = "usage".to_string();
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "constraint_priority lists \"usage\" twice"
+        );
+        config.constraint_priority.pop();
+
+        config.threads = Some(2);
+        assert!(config.validate().unwrap_err().starts_with(
+            "max_attempts and relax_on_exhaustion need the games drawn one at a time"
+        ));
+        config.threads = None;
+        config.append = true;
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .starts_with("relax_on_exhaustion cannot be combined with resume"));
+        // Counting the attempts alone carries on with the run
+        config.relax_on_exhaustion = false;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_merge_applies_combined_overrides_and_validates() {
+        let overrides = Overrides {
+            no_of_games: Some(5),
+            seed: Some(1),
+            max_number: Some(25),
+            min_desired_number: Some(2),
+            output: Some("out.csv".to_string()),
+            resume: true,
+            append: false,
+            output_format: Some(OutputFormat::Json),
+            batches: None,
+            strategy: None,
+            output_order: None,
+        };
+        assert_eq!(
+            sample_config().merge(&overrides).unwrap(),
+            Config {
+                no_of_games: 5,
+                seed: Some(1),
+                max_number: 25,
+                min_desired_number: 2,
+                output: "out.csv".to_string(),
+                resume: true,
+                output_format: OutputFormat::Json,
+                ..sample_config()
+            }
+        );
+
+        let invalid = Overrides {
+            min_desired_number: Some(0),
+            ..Overrides::default()
+        };
+        assert!(sample_config().merge(&invalid).is_err());
+        let invalid = Overrides {
+            append: true,
+            ..overrides.clone()
+        };
+        assert!(sample_config().merge(&invalid).is_err());
+        let invalid = Overrides {
+            batches: Some(3),
+            ..overrides
+        };
+        assert!(sample_config().merge(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_config_can_parse_wheel_section() {
+        let toml_str = r#"
+            no_of_games = 3
+            initial_games = []
+            max_number = 60
+            min_desired_number = 1
+
+            [wheel]
+            pool = [3, 8, 15, 22, 31, 42, 47, 51, 59]
+            guarantee = 3
+        "#;
+
+        let parsed_config: Config =
+            toml::from_str(toml_str).expect("Failed to parse TOML string into Config");
+        let wheel = parsed_config.wheel.expect("Wheel section should be parsed");
+        assert_eq!(wheel.pool, vec![3, 8, 15, 22, 31, 42, 47, 51, 59]);
+        assert_eq!(wheel.guarantee, 3);
+        assert!(parsed_config.optimize.is_none());
+    }
+
+    #[test]
+    fn test_wheel_pool_is_capped() {
+        let parse = |pool: std::ops::RangeInclusive<i64>| {
+            Config::from_toml(&format!(
+                "no_of_games = 3\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\n[wheel]\npool = {:?}\nguarantee = 3\n",
+                pool.collect::<Vec<_>>()
+            ))
+            .map_err(|error| error.to_string())
+        };
+        assert!(parse(1..=30).is_ok());
+        assert!(parse(1..=45)
+            .unwrap_err()
+            .ends_with("[wheel] pool has 45 distinct numbers, at most 30 can be wheeled"));
+    }
+
+    #[test]
+    fn test_config_can_parse_optimize_section() {
+        let toml_str = r#"
+            no_of_games = 3
+            initial_games = []
+            max_number = 60
+            min_desired_number = 1
+
+            [optimize]
+            iterations = 500
+        "#;
+
+        let parsed_config: Config =
+            toml::from_str(toml_str).expect("Failed to parse TOML string into Config");
+        let optimize = parsed_config
+            .optimize
+            .expect("Optimize section should be parsed");
+        assert_eq!(optimize.iterations, 500);
+        // Missing settings take their default value
+        assert_eq!(
+            optimize.initial_temperature,
+            OptimizeConfig::default().initial_temperature
+        );
+    }
+
+    #[test]
+    fn test_config_can_parse_prizes_section() {
+        let toml_str = r#"
+            no_of_games = 3
+            initial_games = []
+            max_number = 60
+            min_desired_number = 1
+
+            [prizes]
+            quina = 50000.0
+            sena = 40000000.0
+            ticket_price = 5.0
+        "#;
+
+        let parsed_config: Config =
+            toml::from_str(toml_str).expect("Failed to parse TOML string into Config");
+        let prizes = parsed_config
+            .prizes
+            .expect("Prizes section should be parsed");
+        assert_eq!(prizes.prize(4), 0.0);
+        assert_eq!(prizes.prize(5), 50000.0);
+        assert_eq!(prizes.prize(6), 40000000.0);
+        assert_eq!(prizes.prize(3), 0.0);
+        assert_eq!(prizes.ticket_price, 5.0);
+        assert!(sample_config().prizes.is_none());
+    }
+
+    #[test]
+    fn test_lottery_defaults_to_the_mega_sena() {
+        let base = "no_of_games = 3\ninitial_games = []\nmax_number = 50\nmin_desired_number = 1\n";
+        assert_eq!(sample_config().lottery, Lottery::MegaSena);
+        let config = Config::from_toml(&format!("{}lottery = \"dupla-sena\"\n", base)).unwrap();
+        assert_eq!(config.lottery, Lottery::DuplaSena);
+        assert_eq!(config.lottery.results_per_draw(), 2);
+        assert_eq!(config.lottery.to_string(), "Dupla Sena");
+        assert!(Config::from_toml(&format!("{}lottery = \"quina\"\n", base)).is_err());
+    }
+
+    #[test]
+    fn test_lotofacil_games_of_15_are_kept_apart_by_their_overlap() {
+        let base = "no_of_games = 5\ninitial_games = []\nmax_number = 25\nmin_desired_number = 1\nlottery = \"lotofacil\"\n";
+        let config = Config::from_toml(base).unwrap();
+        assert_eq!(config.numbers_per_game(), 15);
+        assert_eq!(config.max_overlap(), Some(10));
+        assert!(!config.unique_triplets());
+        assert_eq!(config.lottery.to_string(), "Lotofácil");
+        let config = Config::from_toml(&format!("{}max_overlap = 9\n", base)).unwrap();
+        assert_eq!(config.max_overlap(), Some(9));
+        // The Mega-Sena keeps its games apart by their triplets alone
+        let config = sample_config();
+        assert_eq!((config.numbers_per_game(), config.max_overlap()), (6, None));
+        assert!(config.unique_triplets());
+
+        let error = |extra: &str| {
+            Config::from_toml(&format!("{}{}", base, extra))
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("numbers_per_game = 0\n"),
+            "numbers_per_game must be at least 1"
+        );
+        assert_eq!(
+            error("resume = true\n"),
+            "resume keeps track of the triplets of the games, and needs games of 6 numbers, not 15"
+        );
+        assert_eq!(
+            error("min_number = -50\n"),
+            "max_overlap needs a lottery of at most 64 numbers, the lottery has 76"
+        );
+    }
+
+    #[test]
+    fn test_blacklisted_games_must_be_games_of_the_lottery() {
+        let base =
+            "no_of_games = 5\ninitial_games = []\nmax_number = 60\nmin_desired_number = 31\n";
+        let config = Config::from_toml(&format!(
+            "{}blacklisted_games = [[1, 2, 3, 4, 5, 6], [60, 59, 58, 57, 56, 55]]\n",
+            base
+        ))
+        .unwrap();
+        assert_eq!(config.blacklist().count(), 2);
+        // The games of the file come after those of the configuration
+        let loaded = Config {
+            blacklist_file_games: Some(vec![Game::new(vec![7, 8, 9, 10, 11, 12])]),
+            ..config
+        };
+        let blacklist: Vec<&Game> = loaded.blacklist().collect();
+        assert_eq!(blacklist.len(), 3);
+        assert_eq!(blacklist[2][..], [7, 8, 9, 10, 11, 12]);
+
+        for game in [
+            "[1, 2, 3, 4, 5]",
+            "[1, 2, 3, 4, 5, 5]",
+            "[1, 2, 3, 4, 5, 61]",
+        ] {
+            let error = Config::from_toml(&format!(
+                "{}blacklisted_games = [[1, 2, 3, 4, 5, 6], {}]\n",
+                base, game
+            ))
+            .unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .starts_with("blacklisted game #2 (01-02-03-04-05"),
+                "{}",
+                error
+            );
+            assert!(error
+                .to_string()
+                .ends_with("is not a game of 6 distinct numbers from 1 to 60"));
+        }
+    }
+
+    #[test]
+    fn test_avoided_triplets_need_a_fresh_run_of_games_of_6() {
+        let base = "no_of_games = 5\ninitial_games = []\nmax_number = 60\nmin_desired_number = 31\navoid_triplets_from = [\"held.csv\"]\n";
+        let config = Config::from_toml(base).unwrap();
+        assert_eq!(config.avoid_triplets_from, ["held.csv"]);
+        let error = |extra: &str| {
+            Config::from_toml(&format!("{}{}", base, extra))
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("append = true\n"),
+            "avoid_triplets_from cannot be combined with resume, append, journal, batches or a wheel"
+        );
+        assert_eq!(
+            error("numbers_per_game = 5\n"),
+            "avoid_triplets_from keeps track of the triplets of the games, and needs games of 6 numbers, not 5"
+        );
+    }
+
+    #[test]
+    fn test_initial_conflicts_are_allowed_only_without_resume_journal_or_storage() {
+        let plain =
+            "no_of_games = 5\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\n";
+        let base = format!("{}allow_initial_conflicts = true\n", plain);
+        assert!(!Config::from_toml(plain).unwrap().allow_initial_conflicts);
+        assert!(Config::from_toml(&base).unwrap().allow_initial_conflicts);
+        assert_eq!(
+            Config::from_toml(&format!("{}journal = true\n", base))
+                .unwrap_err()
+                .to_string(),
+            "allow_initial_conflicts cannot be combined with resume, journal or storage, which need games sharing no triplet"
+        );
+    }
+
+    #[test]
+    fn test_repeated_initial_games_are_refused_or_kept_once() {
+        let base = "no_of_games = 5\nmax_number = 60\nmin_desired_number = 1\n";
+        let config = |initial_games: &str, mode: &str| {
+            Config::from_toml(&format!(
+                "{}initial_games = {}\n{}",
+                base, initial_games, mode
+            ))
+        };
+        // Exact repeats, and a repeat in another order, are named with the game they repeat
+        let error = config(
+            "[[1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12], [1, 2, 3, 4, 5, 6], [12, 11, 10, 9, 8, 7]]",
+            "",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "initial games repeat earlier ones: #3 repeats #1 (01-02-03-04-05-06), #4 repeats #2 (07-08-09-10-11-12); remove the repeats, or set allow_duplicate_initial = \"dedupe\" to keep a single copy of each"
+        );
+        assert!(config("[[1, 2, 3, 4, 5, 6], \"06-05-04-03-02-01\"]", "")
+            .unwrap_err()
+            .to_string()
+            .contains("#2 repeats #1 (01-02-03-04-05-06)"));
+
+        // Deduplicated, the first copy of each game is kept, with its label
+        let deduped = config(
+            "[{ numbers = [1, 2, 3, 4, 5, 6], label = \"Maria\" }, [7, 8, 9, 10, 11, 12], [6, 5, 4, 3, 2, 1], [7, 8, 9, 10, 11, 12]]",
+            "allow_duplicate_initial = \"dedupe\"\n",
+        )
+        .unwrap();
+        assert_eq!(deduped.allow_duplicate_initial, DuplicateInitial::Dedupe);
+        assert_eq!(deduped.repeated_initial_games(), [(2, 0), (3, 1)]);
+        let distinct = deduped.distinct_initial_games();
+        assert_eq!(
+            distinct,
+            [
+                (0, deduped.initial_games[0].clone()),
+                (1, deduped.initial_games[1].clone())
+            ]
+        );
+        assert_eq!(distinct[0].1.label(), Some("Maria"));
+        assert!(config("[]", "allow_duplicate_initial = \"keep\"\n").is_err());
+    }
+
+    #[test]
+    fn test_invalid_initial_games_are_refused_by_default() {
+        let base = "no_of_games = 5\ninitial_games = [[1, 2, 3, 4, 5, 61]]\nmax_number = 60\nmin_desired_number = 1\n";
+        let config = |mode: &str| Config::from_toml(&format!("{}{}", base, mode));
+        // The games are checked against the constraints once the run starts, not when read
+        assert_eq!(
+            config("").unwrap().on_invalid_initial,
+            InvalidInitial::Error
+        );
+        assert_eq!(
+            config("on_invalid_initial = \"fix\"\n")
+                .unwrap()
+                .on_invalid_initial,
+            InvalidInitial::Fix
+        );
+        assert!(config("on_invalid_initial = \"drop\"\n").is_err());
+    }
+
+    #[test]
+    fn test_checkpoints_need_a_period_and_a_run_drawing_one_game_at_a_time() {
+        let parse = |settings: &str, table: &str| {
+            Config::from_toml(&format!(
+                "no_of_games = 5\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\n{}[checkpoint]\n{}",
+                settings, table
+            ))
+            .map_err(|error| error.to_string())
+        };
+        let checkpoint = parse("", "every_games = 1000\n")
+            .unwrap()
+            .checkpoint
+            .unwrap();
+        assert_eq!(checkpoint.dir, "checkpoints");
+        assert_eq!(checkpoint.keep, 3);
+        assert_eq!(checkpoint.every_minutes, None);
+        assert!(parse("", "every_minutes = 30.0\nkeep = 1\n").is_ok());
+
+        assert_eq!(
+            parse("", "keep = 2\n").unwrap_err(),
+            "[checkpoint] needs every_games or every_minutes"
+        );
+        assert_eq!(
+            parse("", "every_minutes = -1.0\n").unwrap_err(),
+            "[checkpoint] every_minutes must be a positive number"
+        );
+        assert_eq!(
+            parse("", "every_games = 10\nkeep = 0\n").unwrap_err(),
+            "[checkpoint] keep must be at least 1"
+        );
+        assert_eq!(
+            parse("threads = 2\n", "every_games = 10\n").unwrap_err(),
+            "[checkpoint] needs the games drawn one at a time, on a single thread and without the greedy-coverage strategy"
+        );
+        assert_eq!(
+            parse("rng = \"os\"\n", "every_games = 10\n").unwrap_err(),
+            "[checkpoint] needs a seeded generator, \"std\" or \"counter\", whose games can be drawn again"
+        );
+        assert_eq!(
+            parse("journal = true\n", "every_games = 10\n").unwrap_err(),
+            "[checkpoint] cannot be combined with resume, append, journal, batches, storage, avoid_triplets_from or a wheel"
+        );
+    }
+
+    #[test]
+    fn test_output_order_leaves_the_games_of_earlier_runs_in_place() {
+        let base = "no_of_games = 5\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\noutput_order = \"rank\"\n";
+        assert_eq!(
+            Config::from_toml(base).unwrap().output_order,
+            OutputOrder::Rank
+        );
+        assert_eq!(
+            Config::from_toml(&format!("{}append = true\n", base))
+                .unwrap_err()
+                .to_string(),
+            "output_order cannot be combined with resume, append or journal, which carry on the games in the order found"
+        );
+        assert!(Config::from_toml(&base.replace("rank", "reversed")).is_err());
+    }
+
+    #[test]
+    fn test_quick_pick_tracks_no_triplet() {
+        let base = "no_of_games = 5\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\nstrategy = \"quick-pick\"\n";
+        let config = Config::from_toml(base).unwrap();
+        assert_eq!(config.strategy, Strategy::QuickPick);
+        assert!(!config.unique_triplets());
+        assert_eq!(config.max_overlap(), None);
+        // Quick-picks of the Lotofácil drop its default overlap, but keep one given
+        let lotofacil = format!(
+            "{}lottery = \"lotofacil\"\nmax_number = 25\n",
+            base.replace("max_number = 60\n", "")
+        );
+        assert_eq!(Config::from_toml(&lotofacil).unwrap().max_overlap(), None);
+        let config = Config::from_toml(&format!("{}max_overlap = 12\n", lotofacil)).unwrap();
+        assert_eq!(config.max_overlap(), Some(12));
+
+        let error = Config::from_toml(&format!("{}journal = true\n", base)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "journal keeps track of the triplets of the games, which the quick-pick strategy does not"
+        );
+    }
+
+    #[test]
+    fn test_config_validates_weights() {
+        let toml_str = r#"
+            no_of_games = 3
+            initial_games = []
+            max_number = 10
+            min_desired_number = 1
+            strategy = "weighted"
+
+            [weights]
+            2 = 5
+            3 = 0.5
+            9 = 0
+            10 = 0
+        "#;
+
+        let parsed_config = Config::from_toml(toml_str).expect("Weights should be valid");
+        assert_eq!(parsed_config.strategy, Strategy::Weighted);
+        let weights = parsed_config.number_weights();
+        assert_eq!(weights.len(), 10);
+        assert_eq!(weights[0], (1, 1.0));
+        assert_eq!(weights[1], (2, 5.0));
+        assert_eq!(weights[2], (3, 0.5));
+        assert_eq!(weights[9], (10, 0.0));
+
+        let invalid = |from: &str, to: &str| {
+            Config::from_toml(&toml_str.replace(from, to))
+                .expect_err("Weights should be invalid")
+                .to_string()
+        };
+        assert_eq!(
+            invalid("2 = 5", "2 = 0\n3 = 0\n4 = 0\n5 = 0"),
+            "weights: only 5 numbers have a positive weight, a game needs 6"
+        );
+        assert_eq!(
+            invalid("2 = 5", "11 = 5"),
+            "weights: number 11 is outside min_desired_number (1) to max_number (10)"
+        );
+        assert_eq!(
+            invalid("2 = 5", "2 = -1"),
+            "weights: the weight of 2 must be 0 or more, not -1"
+        );
+        assert_eq!(
+            invalid("2 = 5", "\"two\" = 5"),
+            "weights: 'two' is not a number"
+        );
+        assert!(invalid("\"weighted\"", "\"random\"").starts_with("weights are only used"));
+        assert!(invalid(
+            "[weights]",
+            "weights_from_history = \"draws.csv\"\n[weights]"
+        )
+        .contains("cannot be combined"));
+
+        let history = toml_str
+            .replace("[weights]", "weights_from_history = \"draws.csv\"")
+            .replace(
+                "2 = 5\n            3 = 0.5\n            9 = 0\n            10 = 0",
+                "",
+            );
+        assert!(Config::from_toml(&history).is_ok());
+        assert!(
+            Config::from_toml(&history.replace("weights_from_history = \"draws.csv\"", ""))
+                .expect_err("The weighted strategy needs weights")
+                .to_string()
+                .starts_with("the weighted strategy needs")
+        );
     }
 }
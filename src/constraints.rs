@@ -0,0 +1,1377 @@
+use crate::config::{Config, Strategy};
+use crate::custom_utils;
+use crate::data_structures::{NumberSet, SubsetIndex};
+use crate::game::{Game, GameMask};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+/// A rule broken by a game.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// A number is below the minimum desired number.
+    BelowMinimum { value: i64 },
+    /// A number is above the maximum playable number.
+    AboveMaximum { value: i64 },
+    /// A number appears more than once in the game.
+    DuplicateNumber { value: i64 },
+    /// The game does not have the expected amount of numbers.
+    WrongLength { expected: usize, actual: usize },
+    /// Too many numbers of the game lie on one arithmetic progression.
+    ArithmeticProgression {
+        start: i64,
+        difference: i64,
+        length: usize,
+    },
+    /// A number has a weight of 0 for the weighted strategy.
+    ExcludedNumber { value: i64 },
+    /// The game was drawn in a past contest.
+    HistoricalDraw,
+    /// The game is one of the games never to be played.
+    Blacklisted,
+    /// A number is already in as many accepted games as allowed.
+    NumberUsage { value: i64, max_usage: usize },
+    /// The game shares more numbers than allowed with an accepted game, counted from 1 in the
+    /// order accepted.
+    Overlap {
+        game: usize,
+        shared: usize,
+        max_overlap: usize,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::BelowMinimum { value } => {
+                write!(f, "number {} is below the minimum desired number", value)
+            }
+            Violation::AboveMaximum { value } => {
+                write!(f, "number {} is above the maximum playable number", value)
+            }
+            Violation::DuplicateNumber { value } => {
+                write!(f, "number {} appears more than once", value)
+            }
+            Violation::WrongLength { expected, actual } => {
+                write!(f, "game has {} numbers instead of {}", actual, expected)
+            }
+            Violation::ArithmeticProgression {
+                start,
+                difference,
+                length,
+            } => write!(
+                f,
+                "{} numbers form an arithmetic progression starting at {} with difference {}",
+                length, start, difference
+            ),
+            Violation::ExcludedNumber { value } => {
+                write!(f, "number {} has a weight of 0", value)
+            }
+            Violation::HistoricalDraw => write!(f, "the game was drawn in a past contest"),
+            Violation::Blacklisted => write!(f, "the game is blacklisted"),
+            Violation::NumberUsage { value, max_usage } => write!(
+                f,
+                "number {} is already in {} games, the most allowed",
+                value, max_usage
+            ),
+            Violation::Overlap {
+                game,
+                shared,
+                max_overlap,
+            } => write!(
+                f,
+                "it shares {} numbers with game #{}, at most {} allowed",
+                shared, game, max_overlap
+            ),
+        }
+    }
+}
+
+/// A rule that games must follow.
+///
+/// Constraints are shared by the workers of the parallel generation, hence `Send + Sync`.
+pub trait GameConstraint: Send + Sync {
+    /// A short name identifying the constraint in reports.
+    fn name(&self) -> &'static str;
+
+    /// Checks a game against the rule, its numbers in any order: the games of a file are
+    /// checked as written.
+    ///
+    /// # Returns
+    /// * The first violation found, or `None` if the game follows the rule.
+    fn check(&self, game: &[i64]) -> Option<Violation>;
+
+    /// Checks a game against the rule, reporting every violation found.
+    ///
+    /// Constraints that can be broken several times by the same game (e.g. once per
+    /// out-of-range number) override this; by default it is the result of `check`.
+    fn check_all(&self, game: &[i64]) -> Vec<Violation> {
+        self.check(game).into_iter().collect()
+    }
+
+    /// Records a game accepted in the batch.
+    ///
+    /// Constraints spanning the whole batch (e.g. how often each number is played) override
+    /// this; by default it does nothing.
+    fn accept(&mut self, _game: &[i64]) {}
+
+    /// Whether `ConstraintSet::relax` may give up on the rule once no more games follow them
+    /// all. The shape and range of the games and the numbers they may share are kept; by
+    /// default a rule may be relaxed.
+    fn relaxable(&self) -> bool {
+        true
+    }
+}
+
+/// Names of the configured constraints `relax_on_exhaustion` may give up on, the only ones
+/// `constraint_priority` may list.
+pub const RELAXABLE_CONSTRAINTS: [&str; 5] = [
+    "blacklist",
+    "arithmetic-progression",
+    "excluded",
+    "historical",
+    "usage",
+];
+
+/// A constraint given up on by `ConstraintSet::relax`, with the point of the run it was.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relaxation {
+    /// The name of the constraint.
+    pub constraint: String,
+    /// Number of games accepted by then, initial games included.
+    pub games: usize,
+    /// Number of candidates checked by then.
+    pub attempts: u64,
+}
+
+/// Requires games to have exactly `numbers_per_game` numbers.
+pub struct LengthConstraint {
+    pub numbers_per_game: usize,
+}
+
+impl GameConstraint for LengthConstraint {
+    fn name(&self) -> &'static str {
+        "length"
+    }
+
+    fn relaxable(&self) -> bool {
+        false
+    }
+
+    fn check(&self, game: &[i64]) -> Option<Violation> {
+        (game.len() != self.numbers_per_game).then_some(Violation::WrongLength {
+            expected: self.numbers_per_game,
+            actual: game.len(),
+        })
+    }
+}
+
+/// Requires every number to lie in `[min_desired_number, max_number]`.
+pub struct RangeConstraint {
+    min_desired_number: i64,
+    max_number: i64,
+    /// The mask of valid numbers, when they all fit in a `GameMask`.
+    valid_mask: Option<GameMask>,
+}
+
+impl RangeConstraint {
+    /// Creates a range constraint, precomputing the mask of valid numbers when possible.
+    pub fn new(min_desired_number: i64, max_number: i64) -> Self {
+        let representable = min_desired_number >= 1 && max_number <= GameMask::MAX_NUMBER;
+        Self {
+            min_desired_number,
+            max_number,
+            valid_mask: representable.then(|| GameMask::range(min_desired_number, max_number)),
+        }
+    }
+
+    /// Returns the violation for a single number, if it is out of range.
+    fn check_number(&self, value: i64) -> Option<Violation> {
+        if value < self.min_desired_number {
+            Some(Violation::BelowMinimum { value })
+        } else if value > self.max_number {
+            Some(Violation::AboveMaximum { value })
+        } else {
+            None
+        }
+    }
+}
+
+impl GameConstraint for RangeConstraint {
+    fn name(&self) -> &'static str {
+        "range"
+    }
+
+    fn relaxable(&self) -> bool {
+        false
+    }
+
+    fn check(&self, game: &[i64]) -> Option<Violation> {
+        // Fast path: a single AND against the valid-range mask
+        if let (Some(valid), Some(mask)) = (self.valid_mask, GameMask::from_numbers(game)) {
+            if mask.is_subset_of(valid) {
+                return None;
+            }
+        }
+        game.iter().find_map(|&value| self.check_number(value))
+    }
+
+    fn check_all(&self, game: &[i64]) -> Vec<Violation> {
+        game.iter()
+            .filter_map(|&value| self.check_number(value))
+            .collect()
+    }
+}
+
+/// Requires the numbers of a game to be distinct.
+pub struct DistinctConstraint;
+
+impl GameConstraint for DistinctConstraint {
+    fn name(&self) -> &'static str {
+        "distinct"
+    }
+
+    fn relaxable(&self) -> bool {
+        false
+    }
+
+    fn check(&self, game: &[i64]) -> Option<Violation> {
+        // Fast path: distinct numbers set as many bits as there are numbers
+        if GameMask::from_numbers(game).is_some_and(|mask| mask.len() == game.len()) {
+            return None;
+        }
+        self.check_all(game).into_iter().next()
+    }
+
+    fn check_all(&self, game: &[i64]) -> Vec<Violation> {
+        let mut seen = HashSet::new();
+        let mut duplicates = HashSet::new();
+        game.iter()
+            .filter(|&&value| !seen.insert(value) && duplicates.insert(value))
+            .map(|&value| Violation::DuplicateNumber { value })
+            .collect()
+    }
+}
+
+/// Rejects games where more than `max_run` numbers lie on a single arithmetic progression,
+/// whatever its common difference (e.g. 5, 10, 15, 20).
+pub struct ArithmeticProgressionConstraint {
+    pub max_run: usize,
+}
+
+impl ArithmeticProgressionConstraint {
+    /// Finds the longest arithmetic progression among the numbers of a game.
+    ///
+    /// # Returns
+    /// * The `(start, difference, length)` of the longest progression, or `None` for games
+    ///   with fewer than two distinct numbers.
+    fn longest_run(game: &[i64]) -> Option<(i64, i64, usize)> {
+        let mut numbers = game.to_vec();
+        numbers.sort_unstable();
+        numbers.dedup();
+
+        let mut longest = None;
+        for (i, &start) in numbers.iter().enumerate() {
+            for &second in &numbers[i + 1..] {
+                let difference = second - start;
+                let length = (0..)
+                    .map(|m| start + m * difference)
+                    .take_while(|term| numbers.binary_search(term).is_ok())
+                    .count();
+                if longest.is_none_or(|(_, _, best)| length > best) {
+                    longest = Some((start, difference, length));
+                }
+            }
+        }
+        longest
+    }
+}
+
+impl GameConstraint for ArithmeticProgressionConstraint {
+    fn name(&self) -> &'static str {
+        "arithmetic-progression"
+    }
+
+    fn check(&self, game: &[i64]) -> Option<Violation> {
+        let (start, difference, length) = Self::longest_run(game)?;
+        (length > self.max_run).then_some(Violation::ArithmeticProgression {
+            start,
+            difference,
+            length,
+        })
+    }
+}
+
+/// Rejects games holding any of the numbers of weight 0 for the weighted strategy, which the
+/// sequential scan would otherwise go through.
+pub struct ExcludedNumbersConstraint {
+    pub numbers: Vec<i64>,
+}
+
+impl GameConstraint for ExcludedNumbersConstraint {
+    fn name(&self) -> &'static str {
+        "excluded"
+    }
+
+    fn check(&self, game: &[i64]) -> Option<Violation> {
+        self.check_all(game).into_iter().next()
+    }
+
+    fn check_all(&self, game: &[i64]) -> Vec<Violation> {
+        game.iter()
+            .filter(|value| self.numbers.contains(value))
+            .map(|&value| Violation::ExcludedNumber { value })
+            .collect()
+    }
+}
+
+/// Rejects the games drawn in past contests, looking their combinadic number up among those
+/// of the draws, computed once.
+pub struct HistoricalDrawConstraint {
+    pub ranks: Arc<NumberSet>,
+    /// The smallest number of the lottery, from which the games are ranked.
+    pub min_number: i64,
+}
+
+impl GameConstraint for HistoricalDrawConstraint {
+    fn name(&self) -> &'static str {
+        "historical"
+    }
+
+    fn check(&self, game: &[i64]) -> Option<Violation> {
+        self.ranks
+            .contains(rank_in_any_order(game, self.min_number))
+            .then_some(Violation::HistoricalDraw)
+    }
+}
+
+/// The combinadic number of `game`, ranked from `min_number`, sorting its numbers first when
+/// they are not in ascending order, as those of a game of a file may not be.
+fn rank_in_any_order(game: &[i64], min_number: i64) -> i64 {
+    if game.is_sorted() {
+        return custom_utils::game2enum_from(game, min_number);
+    }
+    let mut sorted = game.to_vec();
+    sorted.sort_unstable();
+    custom_utils::game2enum_from(&sorted, min_number)
+}
+
+/// Rejects the games never to be played, looking their combinadic number up among those of
+/// the blacklisted games, which take no part in the triplets of the run.
+pub struct BlacklistConstraint {
+    pub ranks: NumberSet,
+    /// The smallest number of the lottery, from which the games are ranked.
+    pub min_number: i64,
+}
+
+impl BlacklistConstraint {
+    /// Rejects `games`, ranked from `min_number`.
+    pub fn new<'a>(games: impl IntoIterator<Item = &'a Game>, min_number: i64) -> Self {
+        Self {
+            ranks: games
+                .into_iter()
+                .map(|game| rank_in_any_order(game, min_number))
+                .collect(),
+            min_number,
+        }
+    }
+}
+
+impl GameConstraint for BlacklistConstraint {
+    fn name(&self) -> &'static str {
+        "blacklist"
+    }
+
+    fn check(&self, game: &[i64]) -> Option<Violation> {
+        self.ranks
+            .contains(rank_in_any_order(game, self.min_number))
+            .then_some(Violation::Blacklisted)
+    }
+}
+
+/// Limits the number of games of the batch each number is played in, counting the games
+/// accepted so far.
+pub struct NumberUsageConstraint {
+    max_usage: usize,
+    min_number: i64,
+    /// Number of accepted games holding each number, from `min_number` up.
+    counts: Vec<usize>,
+}
+
+impl NumberUsageConstraint {
+    /// Allows each number of `[min_number, max_number]` in at most `max_usage` games.
+    pub fn new(max_usage: usize, min_number: i64, max_number: i64) -> Self {
+        Self {
+            max_usage,
+            min_number,
+            counts: vec![0; (max_number - min_number + 1).max(0) as usize],
+        }
+    }
+
+    /// The number of accepted games holding `number`.
+    pub fn usage(&self, number: i64) -> usize {
+        usize::try_from(number - self.min_number)
+            .ok()
+            .and_then(|index| self.counts.get(index))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+impl GameConstraint for NumberUsageConstraint {
+    fn name(&self) -> &'static str {
+        "usage"
+    }
+
+    fn check(&self, game: &[i64]) -> Option<Violation> {
+        game.iter()
+            .find(|&&number| self.usage(number) >= self.max_usage)
+            .map(|&value| Violation::NumberUsage {
+                value,
+                max_usage: self.max_usage,
+            })
+    }
+
+    fn check_all(&self, game: &[i64]) -> Vec<Violation> {
+        game.iter()
+            .filter(|&&number| self.usage(number) >= self.max_usage)
+            .map(|&value| Violation::NumberUsage {
+                value,
+                max_usage: self.max_usage,
+            })
+            .collect()
+    }
+
+    fn accept(&mut self, game: &[i64]) {
+        for &number in game {
+            if let Some(count) = usize::try_from(number - self.min_number)
+                .ok()
+                .and_then(|index| self.counts.get_mut(index))
+            {
+                *count += 1;
+            }
+        }
+    }
+}
+
+/// Mask comparisons a lookup of a subset rank costs about as much as, ranking the subset and
+/// hashing the rank included.
+const SUBSET_LOOKUP_COST: u64 = 16;
+
+/// How `MaxOverlapConstraint` finds an accepted game sharing too many numbers with a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapIndex {
+    /// Compares the mask of the game with the mask of every accepted game.
+    Pairwise,
+    /// Looks up each subset of `max_overlap + 1` numbers of the game among those of the
+    /// accepted games, a game sharing more than `max_overlap` numbers sharing such a subset.
+    Subsets,
+}
+
+impl OverlapIndex {
+    /// The cheaper index for a run of about `games` games of `numbers_per_game` numbers: a
+    /// game is compared with the masks of `games / 2` accepted games on average, against
+    /// C(`numbers_per_game`, `max_overlap + 1`) lookups of its subsets.
+    ///
+    /// # Examples
+    /// ```
+    /// use rk_lottery::constraints::OverlapIndex;
+    ///
+    /// // The 1365 subsets of 11 of 15 numbers outweigh the masks of a few thousand games
+    /// assert_eq!(OverlapIndex::cheaper(15, 10, 3000), OverlapIndex::Pairwise);
+    /// // Unlike the 15 pairs of 6 numbers
+    /// assert_eq!(OverlapIndex::cheaper(6, 1, 3000), OverlapIndex::Subsets);
+    /// ```
+    pub fn cheaper(numbers_per_game: usize, max_overlap: usize, games: usize) -> Self {
+        let subsets = custom_utils::binomial(numbers_per_game as i64, max_overlap as i64 + 1);
+        if (subsets.max(0) as u64).saturating_mul(SUBSET_LOOKUP_COST) < games as u64 / 2 {
+            OverlapIndex::Subsets
+        } else {
+            OverlapIndex::Pairwise
+        }
+    }
+}
+
+/// Limits the numbers a game may share with each accepted game, compared on bitmasks so that
+/// games of 15 numbers stay fast to check, or through the subsets of the accepted games when
+/// they are many (see `OverlapIndex`).
+pub struct MaxOverlapConstraint {
+    max_overlap: usize,
+    min_number: i64,
+    /// The masks of the accepted games, their numbers shifted to start at 1.
+    accepted: Vec<GameMask>,
+    /// The accepted game holding each subset of `max_overlap + 1` numbers, when indexed by
+    /// subsets.
+    subsets: Option<SubsetIndex>,
+}
+
+impl MaxOverlapConstraint {
+    /// Allows a game to share at most `max_overlap` numbers with each accepted game, the
+    /// numbers of the lottery starting at `min_number` and spanning at most
+    /// `GameMask::MAX_NUMBER` numbers.
+    pub fn new(max_overlap: usize, min_number: i64) -> Self {
+        Self {
+            max_overlap,
+            min_number,
+            accepted: Vec::new(),
+            subsets: None,
+        }
+    }
+
+    /// Chooses how `check` finds the accepted games a game shares too many numbers with,
+    /// `OverlapIndex::Pairwise` by default; call it before accepting any game.
+    pub fn indexed_by(mut self, index: OverlapIndex) -> Self {
+        self.subsets = (index == OverlapIndex::Subsets).then(SubsetIndex::new);
+        self
+    }
+
+    /// The index `check` goes through.
+    pub fn index(&self) -> OverlapIndex {
+        match self.subsets {
+            Some(_) => OverlapIndex::Subsets,
+            None => OverlapIndex::Pairwise,
+        }
+    }
+
+    /// Calls `visit` with the rank of each subset of `max_overlap + 1` numbers of `game`,
+    /// skipping those that cannot be ranked.
+    fn for_each_subset_rank(&self, game: &[i64], mut visit: impl FnMut(i64)) {
+        let k = self.max_overlap + 1;
+        custom_utils::for_each_ksubset(game, k, |subset| {
+            if let Ok(rank) = custom_utils::subset2enum_from(subset, k, self.min_number) {
+                visit(rank);
+            }
+        });
+    }
+
+    /// The violation of sharing numbers with the accepted game at `index`, if it shares too
+    /// many.
+    fn overlap_with(&self, index: usize, mask: GameMask) -> Option<Violation> {
+        let shared = self.accepted[index].overlap(mask);
+        (shared > self.max_overlap).then_some(Violation::Overlap {
+            game: index + 1,
+            shared,
+            max_overlap: self.max_overlap,
+        })
+    }
+
+    /// The mask of a game, `None` if some number of it does not fit.
+    fn mask(&self, game: &[i64]) -> Option<GameMask> {
+        let shifted: Vec<i64> = game.iter().map(|&x| x - self.min_number + 1).collect();
+        GameMask::from_numbers(&shifted)
+    }
+}
+
+impl GameConstraint for MaxOverlapConstraint {
+    fn name(&self) -> &'static str {
+        "overlap"
+    }
+
+    fn relaxable(&self) -> bool {
+        false
+    }
+
+    fn check(&self, game: &[i64]) -> Option<Violation> {
+        // Games of numbers out of the lottery are left to the range constraint
+        let mask = self.mask(game)?;
+        let Some(subsets) = &self.subsets else {
+            return (0..self.accepted.len()).find_map(|index| self.overlap_with(index, mask));
+        };
+        let mut owner = None;
+        self.for_each_subset_rank(game, |rank| {
+            owner = owner.or_else(|| subsets.owner(rank));
+        });
+        owner.and_then(|index| self.overlap_with(index, mask))
+    }
+
+    fn check_all(&self, game: &[i64]) -> Vec<Violation> {
+        let Some(mask) = self.mask(game) else {
+            return Vec::new();
+        };
+        self.accepted
+            .iter()
+            .map(|accepted| accepted.overlap(mask))
+            .enumerate()
+            .filter(|&(_, shared)| shared > self.max_overlap)
+            .map(|(index, shared)| Violation::Overlap {
+                game: index + 1,
+                shared,
+                max_overlap: self.max_overlap,
+            })
+            .collect()
+    }
+
+    fn accept(&mut self, game: &[i64]) {
+        // A game that does not fit keeps its place, so the positions of the others hold
+        let mask = self.mask(game).unwrap_or_default();
+        let index = self.accepted.len();
+        self.accepted.push(mask);
+        if let Some(mut subsets) = self.subsets.take() {
+            self.for_each_subset_rank(game, |rank| {
+                subsets.insert(rank, index);
+            });
+            self.subsets = Some(subsets);
+        }
+    }
+}
+
+/// An ordered collection of constraints, counting how many games each one rejects.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::constraints::{ConstraintSet, DistinctConstraint, RangeConstraint};
+///
+/// let mut constraints = ConstraintSet::new()
+///     .with(RangeConstraint::new(1, 60))
+///     .with(DistinctConstraint);
+/// assert!(!constraints.reject(&[1, 2, 3, 4, 5, 6]));
+/// assert!(constraints.reject(&[1, 1, 3, 4, 5, 6]));
+/// assert_eq!(constraints.rejection_counts(), vec![("range", 0), ("distinct", 1)]);
+/// ```
+#[derive(Default)]
+pub struct ConstraintSet {
+    /// The constraints, in evaluation order.
+    constraints: Vec<Box<dyn GameConstraint>>,
+    /// Number of games rejected by each constraint.
+    rejections: Vec<u64>,
+    /// Whether `reject` evaluates every constraint instead of stopping at the first violated one.
+    exhaustive: bool,
+    /// The accepted game holding each triplet, with the smallest number of the lottery the
+    /// triplets are ranked from, when tracked.
+    triplet_owners: Option<(SubsetIndex, i64)>,
+    /// Whether games may share triplets, as the quick-pick strategy allows.
+    shared_triplets: bool,
+    /// Number of games accepted.
+    accepted: usize,
+    /// Whether each constraint was relaxed, then checking no game.
+    relaxed: Vec<bool>,
+    /// Names of the constraints, most important first, for `relax` to give up on the last.
+    priority: Vec<String>,
+    /// Whether `relax` gives up on constraints.
+    relax_on_exhaustion: bool,
+    /// The constraints relaxed so far, in order.
+    relaxations: Vec<Relaxation>,
+}
+
+impl ConstraintSet {
+    /// Creates an empty set that stops at the first violated constraint.
+    pub fn new() -> Self {
+        Self {
+            constraints: Vec::new(),
+            rejections: Vec::new(),
+            exhaustive: false,
+            triplet_owners: None,
+            shared_triplets: false,
+            accepted: 0,
+            relaxed: Vec::new(),
+            priority: Vec::new(),
+            relax_on_exhaustion: false,
+            relaxations: Vec::new(),
+        }
+    }
+
+    /// Builds the set of constraints configured in `config`.
+    pub fn from_config(config: &Config) -> Self {
+        let mut set = Self::new()
+            .with(LengthConstraint {
+                numbers_per_game: config.numbers_per_game(),
+            })
+            .with(RangeConstraint::new(
+                config.min_desired_number,
+                config.max_number,
+            ))
+            .with(DistinctConstraint);
+        // Checked before the other rules, once the shape of the game is known to rank it
+        let blacklist = BlacklistConstraint::new(config.blacklist(), config.min_number);
+        if !blacklist.ranks.is_empty() {
+            set = set.with(blacklist);
+        }
+        if let Some(max_run) = config.max_arithmetic_run {
+            set = set.with(ArithmeticProgressionConstraint { max_run });
+        }
+        if config.strategy == Strategy::Weighted {
+            let numbers: Vec<i64> = config
+                .number_weights()
+                .into_iter()
+                .filter(|&(_, weight)| weight == 0.0)
+                .map(|(number, _)| number)
+                .collect();
+            if !numbers.is_empty() {
+                set = set.with(ExcludedNumbersConstraint { numbers });
+            }
+        }
+        if let Some(ranks) = &config.historical_ranks {
+            set = set.with(HistoricalDrawConstraint {
+                ranks: Arc::clone(ranks),
+                min_number: config.min_number,
+            });
+        }
+        if let Some(max_usage) = config.max_number_usage {
+            set = set.with(NumberUsageConstraint::new(
+                max_usage,
+                config.min_number,
+                config.max_number,
+            ));
+        }
+        if let Some(max_overlap) = config.max_overlap() {
+            let games = config.no_of_games + config.initial_games.len();
+            set = set.with(
+                MaxOverlapConstraint::new(max_overlap, config.min_number).indexed_by(
+                    OverlapIndex::cheaper(config.numbers_per_game(), max_overlap, games),
+                ),
+            );
+        }
+        if config.track_triplet_owners {
+            set = set.track_triplet_owners(config.min_number);
+        }
+        set.allow_shared_triplets(!config.unique_triplets())
+            .relax_on_exhaustion(config.relax_on_exhaustion, &config.constraint_priority)
+    }
+
+    /// Adds a constraint, evaluated after the ones already in the set.
+    pub fn with(mut self, constraint: impl GameConstraint + 'static) -> Self {
+        self.constraints.push(Box::new(constraint));
+        self.rejections.push(0);
+        self.relaxed.push(false);
+        self
+    }
+
+    /// Chooses whether `relax` gives up on constraints, in the order of `priority`: the names
+    /// of the constraints, most important first, those left out being less important than
+    /// any listed and the latest added of them the least.
+    pub fn relax_on_exhaustion(mut self, relax: bool, priority: &[String]) -> Self {
+        self.relax_on_exhaustion = relax;
+        self.priority = priority.to_vec();
+        self
+    }
+
+    /// Gives up on the least important constraint that may be relaxed (see
+    /// `GameConstraint::relaxable`), when the set relaxes constraints, so that games breaking
+    /// it are accepted from now on. Its rejections so far are still counted.
+    ///
+    /// # Arguments
+    /// * `games` - Number of games accepted so far, recorded with the relaxation.
+    /// * `attempts` - Number of candidates checked so far, recorded with the relaxation.
+    ///
+    /// # Returns
+    /// * The name of the constraint relaxed, `None` when the set does not relax constraints
+    ///   or only those that may not be relaxed are left.
+    pub fn relax(&mut self, games: usize, attempts: u64) -> Option<&'static str> {
+        if !self.relax_on_exhaustion {
+            return None;
+        }
+        let (index, _) = self
+            .constraints
+            .iter()
+            .enumerate()
+            .filter(|(index, constraint)| !self.relaxed[*index] && constraint.relaxable())
+            .max_by_key(|(index, constraint)| {
+                match self
+                    .priority
+                    .iter()
+                    .position(|name| name == constraint.name())
+                {
+                    Some(position) => (false, position),
+                    None => (true, *index),
+                }
+            })?;
+        self.relaxed[index] = true;
+        let name = self.constraints[index].name();
+        self.relaxations.push(Relaxation {
+            constraint: name.to_string(),
+            games,
+            attempts,
+        });
+        Some(name)
+    }
+
+    /// Returns the constraints relaxed so far, in order.
+    pub fn relaxations(&self) -> &[Relaxation] {
+        &self.relaxations
+    }
+
+    /// The constraints still checked.
+    fn enforced(&self) -> impl Iterator<Item = &dyn GameConstraint> {
+        self.constraints
+            .iter()
+            .zip(&self.relaxed)
+            .filter(|(_, &relaxed)| !relaxed)
+            .map(|(constraint, _)| constraint.as_ref())
+    }
+
+    /// Chooses whether `reject` evaluates every constraint (counting a rejection for each
+    /// violated one) or stops at the first violated constraint.
+    pub fn exhaustive(mut self, exhaustive: bool) -> Self {
+        self.exhaustive = exhaustive;
+        self
+    }
+
+    /// Chooses whether games may share triplets, `triplet_nos` then giving none to keep apart.
+    pub fn allow_shared_triplets(mut self, allow: bool) -> Self {
+        self.shared_triplets = allow;
+        self
+    }
+
+    /// Whether games may share triplets, as chosen with `allow_shared_triplets`.
+    pub fn allows_shared_triplets(&self) -> bool {
+        self.shared_triplets
+    }
+
+    /// The combinadic numbers of the triplets a game must not share with the others, like
+    /// `custom_utils::game2triplet_enums_from`, ranked from `min_number`.
+    ///
+    /// # Returns
+    /// * The numbers of the 20 triplets of a game of 6 numbers, `None` when games may share
+    ///   triplets or for a game of another size (e.g., the 15 numbers of the Lotofácil), kept
+    ///   apart by the overlap of their numbers instead.
+    pub fn triplet_nos(&self, game: &[i64], min_number: i64) -> Option<[i64; 20]> {
+        (!self.shared_triplets && game.len() == 6)
+            .then(|| custom_utils::game2triplet_enums_from(game, min_number))
+    }
+
+    /// Records which accepted game holds each triplet, for `triplet_owner` to tell, the
+    /// triplets being ranked from `min_number`.
+    pub fn track_triplet_owners(mut self, min_number: i64) -> Self {
+        self.triplet_owners = Some((SubsetIndex::new(), min_number));
+        self
+    }
+
+    /// The first triplet of `game` held by an accepted game, with that game, in order of
+    /// acceptance starting at 0.
+    ///
+    /// # Returns
+    /// * `None` if no triplet of the game is held, or the owners are not tracked.
+    pub fn triplet_owner(&self, game: &[i64]) -> Option<(Vec<i64>, usize)> {
+        let (owners, min_number) = self.triplet_owners.as_ref()?;
+        let mut sorted = game.to_vec();
+        sorted.sort_unstable();
+        custom_utils::game2triplet_enums_from(&sorted, *min_number)
+            .iter()
+            .zip(custom_utils::game2triplets(&sorted))
+            .find_map(|(triplet_no, triplet)| {
+                owners.owner(*triplet_no).map(|owner| (triplet, owner))
+            })
+    }
+
+    /// Checks a game, counting a rejection for the violated constraints.
+    ///
+    /// # Returns
+    /// * `true` if the game violates any constraint.
+    pub fn reject(&mut self, game: &[i64]) -> bool {
+        let mut counts = std::mem::take(&mut self.rejections);
+        let rejected = self.reject_into(game, &mut counts);
+        self.rejections = counts;
+        rejected
+    }
+
+    /// Checks a game like `reject`, but counts the rejections in `counts` (one entry per
+    /// constraint) so that several workers can share the set; see `add_rejections`.
+    ///
+    /// # Returns
+    /// * `true` if the game violates any constraint.
+    pub fn reject_into(&self, game: &[i64], counts: &mut [u64]) -> bool {
+        let mut rejected = false;
+        for ((constraint, count), _) in self
+            .constraints
+            .iter()
+            .zip(counts.iter_mut())
+            .zip(&self.relaxed)
+            .filter(|(_, &relaxed)| !relaxed)
+        {
+            if constraint.check(game).is_some() {
+                *count += 1;
+                rejected = true;
+                if !self.exhaustive {
+                    break;
+                }
+            }
+        }
+        rejected
+    }
+
+    /// Adds rejections counted with `reject_into` to the counts of the set.
+    pub fn add_rejections(&mut self, counts: &[u64]) {
+        for (total, count) in self.rejections.iter_mut().zip(counts) {
+            *total += count;
+        }
+    }
+
+    /// Returns the first violation found, without counting rejections.
+    pub fn first_violation(&self, game: &[i64]) -> Option<Violation> {
+        self.enforced()
+            .find_map(|constraint| constraint.check(game))
+    }
+
+    /// Returns every violation of every constraint not relaxed, without counting rejections.
+    pub fn violations(&self, game: &[i64]) -> Vec<Violation> {
+        self.enforced()
+            .flat_map(|constraint| constraint.check_all(game))
+            .collect()
+    }
+
+    /// Records a game accepted in the batch, for the constraints spanning the whole batch and
+    /// the triplet owners, when tracked.
+    pub fn accept(&mut self, game: &[i64]) {
+        for constraint in &mut self.constraints {
+            constraint.accept(game);
+        }
+        if let Some((owners, min_number)) = &mut self.triplet_owners {
+            let mut sorted = game.to_vec();
+            sorted.sort_unstable();
+            for triplet_no in custom_utils::game2triplet_enums_from(&sorted, *min_number) {
+                owners.insert(triplet_no, self.accepted);
+            }
+        }
+        self.accepted += 1;
+    }
+
+    /// Returns the name and rejection count of each constraint, in evaluation order.
+    pub fn rejection_counts(&self) -> Vec<(&'static str, u64)> {
+        self.constraints
+            .iter()
+            .map(|constraint| constraint.name())
+            .zip(self.rejections.iter().copied())
+            .collect()
+    }
+}
+
+/// Checks a game against every rule and reports all the violated ones.
+///
+/// # Arguments
+/// * `game` - The game numbers.
+/// * `constraints` - The rules to check.
+///
+/// # Returns
+/// * Every violation found, constraint by constraint; empty if the game is valid.
+pub fn validate_game(game: &[i64], constraints: &ConstraintSet) -> Vec<Violation> {
+    constraints.violations(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The standard constraints for games of 6 numbers between `min` and `max`.
+    fn standard(min_desired_number: i64, max_number: i64) -> ConstraintSet {
+        ConstraintSet::new()
+            .with(LengthConstraint {
+                numbers_per_game: 6,
+            })
+            .with(RangeConstraint::new(min_desired_number, max_number))
+            .with(DistinctConstraint)
+    }
+
+    fn constraints() -> ConstraintSet {
+        standard(31, 60)
+    }
+
+    #[test]
+    fn test_validate_valid_game() {
+        assert!(validate_game(&[32, 35, 41, 48, 50, 59], &constraints()).is_empty());
+        assert!(validate_game(&[31, 60, 35, 41, 48, 50], &constraints()).is_empty());
+    }
+
+    #[test]
+    fn test_validate_out_of_range_numbers() {
+        assert_eq!(
+            validate_game(&[5, 35, 41, 48, 61, 30], &constraints()),
+            vec![
+                Violation::BelowMinimum { value: 5 },
+                Violation::AboveMaximum { value: 61 },
+                Violation::BelowMinimum { value: 30 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_duplicates_and_length() {
+        assert_eq!(
+            validate_game(&[40, 40, 40, 41, 41], &constraints()),
+            vec![
+                Violation::WrongLength {
+                    expected: 6,
+                    actual: 5
+                },
+                Violation::DuplicateNumber { value: 40 },
+                Violation::DuplicateNumber { value: 41 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repeated_numbers_and_short_games_are_rejected() {
+        let constraints = standard(1, 60);
+
+        let messages: Vec<String> = validate_game(&[5, 5, 5, 5, 5, 5], &constraints)
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect();
+        assert_eq!(messages, vec!["number 5 appears more than once"]);
+
+        let messages: Vec<String> = validate_game(&[5, 12, 33, 41], &constraints)
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect();
+        assert_eq!(messages, vec!["game has 4 numbers instead of 6"]);
+    }
+
+    #[test]
+    fn test_parsed_game_with_out_of_range_values() {
+        let game: crate::game::Game = "01-35-41-48-50-99".parse().unwrap();
+        assert_eq!(
+            validate_game(&game, &constraints()),
+            vec![
+                Violation::BelowMinimum { value: 1 },
+                Violation::AboveMaximum { value: 99 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_violation_messages() {
+        assert_eq!(
+            Violation::BelowMinimum { value: 5 }.to_string(),
+            "number 5 is below the minimum desired number"
+        );
+        assert_eq!(
+            Violation::WrongLength {
+                expected: 6,
+                actual: 4
+            }
+            .to_string(),
+            "game has 4 numbers instead of 6"
+        );
+    }
+
+    /// Rejects games whose numbers add up to more than `max_sum`, to exercise custom constraints.
+    struct MaxSumConstraint {
+        max_sum: i64,
+    }
+
+    impl GameConstraint for MaxSumConstraint {
+        fn name(&self) -> &'static str {
+            "sum"
+        }
+
+        fn check(&self, game: &[i64]) -> Option<Violation> {
+            // Reuse an existing variant, the tests only care about which constraint fires
+            let sum: i64 = game.iter().sum();
+            (sum > self.max_sum).then_some(Violation::AboveMaximum { value: sum })
+        }
+    }
+
+    #[test]
+    fn test_constraint_set_counts_rejections_short_circuit() {
+        let mut constraints = standard(31, 60).with(MaxSumConstraint { max_sum: 300 });
+
+        assert!(!constraints.reject(&[32, 35, 41, 48, 50, 59]));
+        assert!(constraints.reject(&[5, 35, 41, 48, 50, 59])); // Range
+        assert!(constraints.reject(&[5, 5, 41, 48, 50, 59])); // Range first, distinct skipped
+        assert!(constraints.reject(&[41, 41, 48, 50, 59, 60])); // Distinct
+        assert!(constraints.reject(&[55, 56, 57, 58, 59, 60])); // Sum of 345
+        assert!(constraints.reject(&[55, 56, 57, 58, 59])); // Length
+
+        assert_eq!(
+            constraints.rejection_counts(),
+            vec![("length", 1), ("range", 2), ("distinct", 1), ("sum", 1)]
+        );
+    }
+
+    /// Rejects games of more than `max_odd` odd numbers.
+    struct ParityConstraint {
+        max_odd: usize,
+    }
+
+    impl GameConstraint for ParityConstraint {
+        fn name(&self) -> &'static str {
+            "parity"
+        }
+
+        fn check(&self, game: &[i64]) -> Option<Violation> {
+            let odd = game.iter().filter(|&&number| number % 2 == 1).count();
+            (odd > self.max_odd).then_some(Violation::AboveMaximum { value: odd as i64 })
+        }
+    }
+
+    #[test]
+    fn test_constraints_are_relaxed_least_important_first() {
+        let with_sum_and_parity = || {
+            standard(31, 60)
+                .with(MaxSumConstraint { max_sum: 200 })
+                .with(ParityConstraint { max_odd: 3 })
+        };
+        assert_eq!(with_sum_and_parity().relax(0, 0), None);
+
+        // The sum is left out of the priorities, so less important than the parity
+        let mut constraints = with_sum_and_parity().relax_on_exhaustion(true, &["parity".into()]);
+        assert!(constraints.reject(&[32, 35, 41, 48, 50, 59]));
+        assert_eq!(constraints.relax(4, 90), Some("sum"));
+        assert!(!constraints.reject(&[32, 35, 41, 48, 50, 60]));
+        assert!(constraints.reject(&[33, 35, 41, 47, 50, 60]));
+        assert_eq!(constraints.first_violation(&[32, 35, 41, 48, 50, 59]), None);
+        assert_eq!(constraints.relax(5, 120), Some("parity"));
+        assert!(constraints.violations(&[33, 35, 41, 47, 51, 59]).is_empty());
+
+        // Length, range and distinct are never relaxed, and the rejections are kept
+        assert_eq!(constraints.relax(5, 130), None);
+        assert!(constraints.reject(&[5, 35, 41, 48, 50, 59]));
+        assert_eq!(
+            constraints.rejection_counts(),
+            vec![
+                ("length", 0),
+                ("range", 1),
+                ("distinct", 0),
+                ("sum", 1),
+                ("parity", 1)
+            ]
+        );
+        assert_eq!(
+            constraints.relaxations(),
+            [
+                Relaxation {
+                    constraint: "sum".to_string(),
+                    games: 4,
+                    attempts: 90
+                },
+                Relaxation {
+                    constraint: "parity".to_string(),
+                    games: 5,
+                    attempts: 120
+                }
+            ]
+        );
+
+        // Listed last, the parity goes before the sum
+        let mut constraints = with_sum_and_parity()
+            .relax_on_exhaustion(true, &["sum".to_string(), "parity".to_string()]);
+        assert_eq!(constraints.relax(0, 0), Some("parity"));
+    }
+
+    #[test]
+    fn test_constraint_set_counts_rejections_exhaustive() {
+        let mut constraints = standard(31, 60)
+            .with(MaxSumConstraint { max_sum: 300 })
+            .exhaustive(true);
+
+        assert!(constraints.reject(&[5, 5, 41, 48, 50, 59]));
+        assert!(constraints.reject(&[60, 60, 60, 60, 60, 60, 60]));
+
+        assert_eq!(
+            constraints.rejection_counts(),
+            vec![("length", 1), ("range", 1), ("distinct", 2), ("sum", 1)]
+        );
+    }
+
+    #[test]
+    fn test_violations_do_not_count_rejections() {
+        let constraints = constraints();
+        assert_eq!(constraints.violations(&[5, 35, 41, 48, 50, 59]).len(), 1);
+        assert!(constraints
+            .rejection_counts()
+            .iter()
+            .all(|&(_, count)| count == 0));
+    }
+
+    #[test]
+    fn test_reject_into_counts_outside_the_set() {
+        let mut constraints = constraints();
+        let mut counts = vec![0; constraints.rejection_counts().len()];
+        assert!(constraints.reject_into(&[5, 35, 41, 48, 50, 59], &mut counts));
+        assert!(constraints.reject_into(&[32, 35], &mut counts));
+        assert!(!constraints.reject_into(&[32, 35, 41, 48, 50, 59], &mut counts));
+        assert_eq!(counts, vec![1, 1, 0]);
+        assert!(constraints
+            .rejection_counts()
+            .iter()
+            .all(|&(_, count)| count == 0));
+
+        constraints.add_rejections(&counts);
+        constraints.add_rejections(&counts);
+        assert_eq!(
+            constraints.rejection_counts(),
+            vec![("length", 2), ("range", 2), ("distinct", 0)]
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_progression_constraint() {
+        let constraint = ArithmeticProgressionConstraint { max_run: 3 };
+
+        // Pure progression, given out of order
+        assert_eq!(
+            constraint.check(&[30, 5, 20, 10, 25, 15]),
+            Some(Violation::ArithmeticProgression {
+                start: 5,
+                difference: 5,
+                length: 6
+            })
+        );
+
+        // 3, 7, 11, 15 hides among other numbers
+        assert_eq!(
+            constraint.check(&[7, 40, 11, 3, 15, 52]),
+            Some(Violation::ArithmeticProgression {
+                start: 3,
+                difference: 4,
+                length: 4
+            })
+        );
+
+        // Powers of two contain no three-term progression
+        assert_eq!(constraint.check(&[1, 2, 4, 8, 16, 32]), None);
+        assert_eq!(
+            ArithmeticProgressionConstraint { max_run: 1 }.check(&[1, 2, 4, 8, 16, 32]),
+            Some(Violation::ArithmeticProgression {
+                start: 1,
+                difference: 1,
+                length: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_historical_draws_are_rejected_in_any_order() {
+        let (draws, _) =
+            crate::history::read_draws(include_str!("../tests/fixtures/draws.csv"), 1, 60).unwrap();
+        let mut constraints = standard(1, 60).with(HistoricalDrawConstraint {
+            ranks: Arc::new(crate::history::ranks(&draws, 1)),
+            min_number: 1,
+        });
+        // Contests 1 and 4 of the fixture, the second one as the export lists it
+        assert!(constraints.reject(&[4, 5, 30, 33, 41, 52]));
+        assert!(constraints.reject(&[6, 59, 42, 27, 1, 5]));
+        assert_eq!(
+            constraints.violations(&[41, 5, 4, 52, 30, 33]),
+            vec![Violation::HistoricalDraw]
+        );
+        // Sharing five numbers with a draw is fine
+        assert!(!constraints.reject(&[4, 5, 30, 33, 41, 53]));
+        assert_eq!(
+            constraints.rejection_counts(),
+            vec![
+                ("length", 0),
+                ("range", 0),
+                ("distinct", 0),
+                ("historical", 2)
+            ]
+        );
+        assert_eq!(
+            Violation::HistoricalDraw.to_string(),
+            "the game was drawn in a past contest"
+        );
+    }
+
+    #[test]
+    fn test_number_usage_counts_the_accepted_games() {
+        let mut constraints = standard(1, 60).with(NumberUsageConstraint::new(2, 1, 60));
+        for game in [[1, 2, 3, 4, 5, 6], [1, 7, 8, 9, 10, 11]] {
+            assert!(!constraints.reject(&game));
+            constraints.accept(&game);
+        }
+        // 1 is in two games already, 2 in one only
+        assert!(constraints.reject(&[1, 12, 13, 14, 15, 16]));
+        assert!(!constraints.reject(&[2, 12, 13, 14, 15, 16]));
+        assert_eq!(
+            constraints.violations(&[1, 2, 3, 7, 8, 20]),
+            vec![Violation::NumberUsage {
+                value: 1,
+                max_usage: 2
+            }]
+        );
+        assert_eq!(constraints.rejection_counts().last(), Some(&("usage", 1)));
+        assert_eq!(
+            constraints
+                .first_violation(&[1, 2, 3, 4, 5, 6])
+                .unwrap()
+                .to_string(),
+            "number 1 is already in 2 games, the most allowed"
+        );
+    }
+
+    #[test]
+    fn test_max_overlap_compares_with_every_accepted_game() {
+        let mut constraints = standard(0, 24).with(MaxOverlapConstraint::new(2, 0));
+        for game in [[0, 1, 2, 3, 4, 5], [0, 1, 6, 7, 8, 9]] {
+            assert!(!constraints.reject(&game));
+            constraints.accept(&game);
+        }
+        // 0 to 2 are in the first game, 0, 1 and 6 to 8 in the second one
+        assert!(!constraints.reject(&[0, 10, 11, 12, 13, 14]));
+        assert_eq!(
+            constraints.violations(&[0, 1, 2, 6, 7, 24]),
+            vec![
+                Violation::Overlap {
+                    game: 1,
+                    shared: 3,
+                    max_overlap: 2
+                },
+                Violation::Overlap {
+                    game: 2,
+                    shared: 4,
+                    max_overlap: 2
+                }
+            ]
+        );
+        assert_eq!(
+            constraints
+                .first_violation(&[0, 1, 2, 20, 21, 22])
+                .unwrap()
+                .to_string(),
+            "it shares 3 numbers with game #1, at most 2 allowed"
+        );
+    }
+
+    #[test]
+    fn test_overlap_indexes_turn_down_the_same_games() {
+        let games = [
+            [1, 2, 3, 4, 5, 6],
+            [1, 7, 8, 9, 10, 11],
+            [2, 7, 12, 13, 14, 15],
+            [1, 2, 16, 17, 18, 19],
+            [3, 8, 12, 16, 20, 21],
+            [4, 9, 13, 17, 20, 22],
+            [5, 10, 14, 18, 21, 22],
+            [6, 11, 15, 19, 20, 23],
+        ];
+        for max_overlap in [1, 2] {
+            let mut pairwise = MaxOverlapConstraint::new(max_overlap, 1);
+            let mut subsets =
+                MaxOverlapConstraint::new(max_overlap, 1).indexed_by(OverlapIndex::Subsets);
+            assert_eq!(subsets.index(), OverlapIndex::Subsets);
+            for game in &games {
+                let violation = pairwise.check(game);
+                assert_eq!(subsets.check(game).is_some(), violation.is_some());
+                if let Some(Violation::Overlap { shared, .. }) = subsets.check(game) {
+                    assert!(shared > max_overlap);
+                } else {
+                    pairwise.accept(game);
+                    subsets.accept(game);
+                }
+                assert_eq!(subsets.check_all(game), pairwise.check_all(game));
+            }
+        }
+        assert_eq!(OverlapIndex::cheaper(6, 2, 600), OverlapIndex::Pairwise);
+        assert_eq!(OverlapIndex::cheaper(6, 2, 700), OverlapIndex::Subsets);
+        assert_eq!(OverlapIndex::cheaper(6, 6, 100), OverlapIndex::Subsets);
+    }
+
+    #[test]
+    fn test_shared_triplets_leave_no_triplet_to_keep_apart() {
+        let game = [6, 5, 4, 3, 2, 1];
+        let constraints = ConstraintSet::new();
+        assert_eq!(
+            constraints.triplet_nos(&game, 1),
+            Some(custom_utils::game2triplet_enums_from(&game, 1))
+        );
+        assert_eq!(constraints.triplet_nos(&[1, 2, 3, 4, 5], 1), None);
+        let quick_pick = Config::from_toml(
+            "no_of_games = 1\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\nstrategy = \"quick-pick\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            ConstraintSet::from_config(&quick_pick).triplet_nos(&game, 1),
+            None
+        );
+    }
+}
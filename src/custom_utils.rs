@@ -1,5 +1,8 @@
+use crate::constraints::{ConstraintSet, DistinctConstraint, LengthConstraint, RangeConstraint};
+use crate::game::{Game, GameMask};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
+use std::fmt;
 
 /// Computes the binomial coefficient C(n, k), which represents the number
 /// of ways to choose k elements from a set of n elements.
@@ -10,7 +13,7 @@ use rand::{Rng, SeedableRng};
 ///
 /// # Returns
 /// * The binomial coefficient as `i64`.
-fn binomial(n: i64, k: i64) -> i64 {
+pub fn binomial(n: i64, k: i64) -> i64 {
     if k == 0 || n == k {
         return 1;
     }
@@ -42,6 +45,89 @@ fn combinadic(combination: Vec<i64>) -> i64 {
         .fold(0, |acc, (i, &ci)| acc + binomial(ci, k - i as i64))
 }
 
+/// A combinadic number outside the range of the combinations it should convert to.
+#[derive(Debug, PartialEq)]
+pub struct RankOutOfRangeError {
+    /// The combinadic number given.
+    pub rank: i64,
+    /// Total number of elements in the set.
+    pub n: i64,
+    /// Number of elements in the combination.
+    pub k: i64,
+}
+
+impl fmt::Display for RankOutOfRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = binomial(self.n, self.k);
+        if total == 0 {
+            return write!(
+                f,
+                "rank {} is out of range: there are no combinations of {} out of {} elements",
+                self.rank, self.k, self.n
+            );
+        }
+        write!(
+            f,
+            "rank {} is out of range: combinations of {} out of {} elements have ranks 0 to {}",
+            self.rank,
+            self.k,
+            self.n,
+            total - 1
+        )
+    }
+}
+
+impl std::error::Error for RankOutOfRangeError {}
+
+/// A subset that cannot be ranked among the subsets of the size expected, its rank being that
+/// of a subset of another size or of no subset at all.
+#[derive(Debug, PartialEq)]
+pub enum SubsetError {
+    /// The subset does not have `k` numbers.
+    WrongSize { subset: Vec<i64>, k: usize },
+    /// A number of the subset is below the smallest number of the lottery.
+    BelowMinimum {
+        subset: Vec<i64>,
+        number: i64,
+        min_number: i64,
+    },
+    /// A number appears more than once in the subset.
+    Repeated { subset: Vec<i64>, number: i64 },
+}
+
+impl fmt::Display for SubsetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubsetError::WrongSize { subset, k } => write!(
+                f,
+                "cannot rank {} among the subsets of {} numbers: it has {}",
+                Game::new(subset.clone()),
+                k,
+                subset.len()
+            ),
+            SubsetError::BelowMinimum {
+                subset,
+                number,
+                min_number,
+            } => write!(
+                f,
+                "cannot rank {}: {} is below the smallest number, {}",
+                Game::new(subset.clone()),
+                number,
+                min_number
+            ),
+            SubsetError::Repeated { subset, number } => write!(
+                f,
+                "cannot rank {}: {} is repeated",
+                Game::new(subset.clone()),
+                number
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SubsetError {}
+
 /// Converts a combinadic number to its corresponding combination.
 ///
 /// # Arguments
@@ -51,7 +137,20 @@ fn combinadic(combination: Vec<i64>) -> i64 {
 ///
 /// # Returns
 /// * A vector of integers representing the combination.
-fn inverse_combinadic(combination_no: i64, n: i64, k: i64) -> Vec<i64> {
+/// * An error if `combination_no` is negative or not below C(n, k).
+fn inverse_combinadic(
+    combination_no: i64,
+    n: i64,
+    k: i64,
+) -> Result<Vec<i64>, RankOutOfRangeError> {
+    if combination_no < 0 || k < 0 || combination_no >= binomial(n, k) {
+        return Err(RankOutOfRangeError {
+            rank: combination_no,
+            n,
+            k,
+        });
+    }
+
     let mut combination_no = combination_no;
     let mut combination = vec![0; k as usize];
     let mut ci = n - 1;
@@ -65,7 +164,7 @@ fn inverse_combinadic(combination_no: i64, n: i64, k: i64) -> Vec<i64> {
         ci -= 1;
     }
 
-    combination
+    Ok(combination)
 }
 
 /// Converts a combinadic number to its corresponding lottery game.
@@ -75,57 +174,379 @@ fn inverse_combinadic(combination_no: i64, n: i64, k: i64) -> Vec<i64> {
 ///
 /// # Returns
 /// * A vector of integers representing the game numbers.
-pub fn enum2game(game_no: i64) -> Vec<i64> {
-    inverse_combinadic(game_no, 60, 6)
+/// * An error if `game_no` is not the number of any game.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::custom_utils::{enum2game, game2enum};
+///
+/// let game_no = game2enum(&[3, 11, 22, 34, 45, 58]);
+/// assert_eq!(enum2game(game_no).unwrap(), vec![3, 11, 22, 34, 45, 58]);
+/// ```
+pub fn enum2game(game_no: i64) -> Result<Vec<i64>, RankOutOfRangeError> {
+    enum2subset(game_no, 60, 6)
+}
+
+/// Converts a combinadic number to its corresponding subset of `k` numbers
+/// drawn from `1..=n`, in ascending order.
+///
+/// # Arguments
+/// * `subset_no` - The combinadic number representing the subset.
+/// * `n` - The maximum number in the lottery (e.g., 60).
+/// * `k` - The number of numbers in the subset (e.g., 6).
+///
+/// # Returns
+/// * A vector of `k` integers in ascending order.
+/// * An error if `subset_no` is negative or not below C(n, k).
+pub fn enum2subset(subset_no: i64, n: i64, k: i64) -> Result<Vec<i64>, RankOutOfRangeError> {
+    enum2subset_from(subset_no, n, k, 1)
+}
+
+/// Converts a combinadic number to its corresponding subset of `k` numbers drawn from the
+/// `n` numbers starting at `min_number`, in ascending order.
+///
+/// # Arguments
+/// * `subset_no` - The combinadic number representing the subset.
+/// * `n` - How many numbers the lottery has (e.g., 60).
+/// * `k` - The number of numbers in the subset (e.g., 6).
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+///
+/// # Returns
+/// * A vector of `k` integers in ascending order.
+/// * An error if `subset_no` is negative or not below C(n, k).
+pub fn enum2subset_from(
+    subset_no: i64,
+    n: i64,
+    k: i64,
+    min_number: i64,
+) -> Result<Vec<i64>, RankOutOfRangeError> {
+    Ok(inverse_combinadic(subset_no, n, k)?
         .iter()
-        .map(|&x| x + 1)
+        .map(|&x| x + min_number)
         .rev()
-        .collect()
+        .collect())
 }
 
 /// Converts a lottery game (set of numbers) to its unique combinadic number.
 ///
+/// The game is ranked among the subsets of its size without further checks, as candidates are
+/// ranked by the million; `subset2enum` checks a subset of unknown origin first. Its numbers
+/// must be in ascending order, as those of the games drawn and of the games of the
+/// configuration are: a game read in the order of its ticket is sorted first, e.g. with
+/// `Game::sorted`. Debug builds panic on an unsorted game.
+///
 /// # Arguments
-/// * `game` - A vector of integers representing the game numbers.
+/// * `game` - A slice of integers representing the game numbers, in ascending order.
 ///
 /// # Returns
 /// * The combinadic number representing the game.
-pub fn game2enum(game: Vec<i64>) -> i64 {
-    combinadic(game.iter().map(|&x| x - 1).rev().collect())
+///
+/// # Examples
+/// ```
+/// use rk_lottery::custom_utils::game2enum;
+///
+/// // The first game in colex order has number 0
+/// assert_eq!(game2enum(&[1, 2, 3, 4, 5, 6]), 0);
+/// assert_eq!(game2enum(&[1, 2, 3, 4, 5, 7]), 1);
+/// ```
+pub fn game2enum(game: &[i64]) -> i64 {
+    game2enum_from(game, 1)
+}
+
+/// Converts a lottery game to its unique combinadic number, for numbers starting at
+/// `min_number`.
+///
+/// # Arguments
+/// * `game` - A slice of integers representing the game numbers, in ascending order.
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+///
+/// # Returns
+/// * The combinadic number representing the game.
+pub fn game2enum_from(game: &[i64], min_number: i64) -> i64 {
+    // Ranked out of order, the numbers would give the rank of another game
+    debug_assert!(
+        game.is_sorted(),
+        "game {:?} must be sorted to be ranked",
+        game
+    );
+    combinadic(game.iter().map(|&x| x - min_number).rev().collect())
 }
 
 /// Generates all unique triplets from a game (set of 6 numbers).
 ///
 /// # Arguments
-/// * `game` - A vector of exactly 6 integers.
+/// * `game` - A slice of exactly 6 integers.
 ///
 /// # Returns
 /// * A vector of vectors, each containing 3 integers (triplets).
-pub fn game2triplets(game: Vec<i64>) -> Vec<Vec<i64>> {
+pub fn game2triplets(game: &[i64]) -> Vec<Vec<i64>> {
     if game.len() != 6 {
         return vec![]; // Return an empty vector if the game does not have exactly 6 numbers
     }
+    game2ksubsets(game, 3)
+}
 
-    let mut triplets = Vec::new();
+/// Generates the pairs of numbers of a game, in lexicographic order of positions.
+///
+/// # Arguments
+/// * `game` - The game numbers.
+///
+/// # Returns
+/// * The C(game.len(), 2) pairs, each in the order of the game.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::custom_utils::game2pairs;
+///
+/// assert_eq!(game2pairs(&[4, 1, 9]), vec![(4, 1), (4, 9), (1, 9)]);
+/// ```
+pub fn game2pairs(game: &[i64]) -> Vec<(i64, i64)> {
+    game.iter()
+        .enumerate()
+        .flat_map(|(i, &first)| game[i + 1..].iter().map(move |&second| (first, second)))
+        .collect()
+}
+
+/// Computes the combinadic numbers of the 20 triplets of a game (set of 6 numbers) directly,
+/// without building the triplets.
+///
+/// # Arguments
+/// * `game` - A slice of exactly 6 integers, in any order.
+///
+/// # Returns
+/// * The combinadic numbers of the triplets, in the order of `game2triplets`.
+///
+/// # Panics
+/// * If the game does not have exactly 6 numbers.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::custom_utils::{game2triplet_enums, subset2enum};
+///
+/// let triplet_nos = game2triplet_enums(&[1, 2, 3, 4, 5, 6]);
+/// assert!(triplet_nos.contains(&subset2enum(&[2, 4, 6], 3).unwrap()));
+/// ```
+pub fn game2triplet_enums(game: &[i64]) -> [i64; 20] {
+    game2triplet_enums_from(game, 1)
+}
+
+/// Computes the combinadic numbers of the 20 triplets of a game directly, for numbers
+/// starting at `min_number`; see `game2triplet_enums`.
+///
+/// # Arguments
+/// * `game` - A slice of exactly 6 integers.
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+///
+/// # Panics
+/// * If the game does not have exactly 6 numbers.
+pub fn game2triplet_enums_from(game: &[i64], min_number: i64) -> [i64; 20] {
+    assert_eq!(
+        game.len(),
+        6,
+        "a game must have 6 numbers to have 20 triplets"
+    );
+    // The numbers of each triplet of an unsorted game are sorted before ranking it
+    let sorted = game.is_sorted();
+    let mut triplet_nos = [0; 20];
+    let mut index = 0;
     for i in 0..4 {
         for j in i + 1..5 {
             for k in j + 1..6 {
-                triplets.push(vec![game[i], game[j], game[k]]);
+                let mut triplet = [game[i], game[j], game[k]];
+                if !sorted {
+                    triplet.sort_unstable();
+                }
+                triplet_nos[index] = binomial(triplet[2] - min_number, 3)
+                    + binomial(triplet[1] - min_number, 2)
+                    + binomial(triplet[0] - min_number, 1);
+                index += 1;
             }
         }
     }
-    triplets
+    triplet_nos
+}
+
+/// Generates all unique subsets of `k` numbers from a game, in lexicographic order of positions.
+///
+/// # Arguments
+/// * `game` - The game numbers.
+/// * `k` - The number of numbers in each subset.
+///
+/// # Returns
+/// * A vector of C(game.len(), k) subsets, empty if `k` is larger than the game.
+pub fn game2ksubsets(game: &[i64], k: usize) -> Vec<Vec<i64>> {
+    let mut subsets = Vec::with_capacity(binomial(game.len() as i64, k as i64).max(0) as usize);
+    for_each_ksubset(game, k, |subset| subsets.push(subset.to_vec()));
+    subsets
+}
+
+/// Visits the subsets of `k` numbers of a game in the order of `game2ksubsets`, without
+/// allocating one vector per subset: a game of 15 numbers has 455 triplets.
+///
+/// # Arguments
+/// * `game` - The game numbers.
+/// * `k` - The number of numbers in each subset.
+/// * `visit` - Called with each subset, in a buffer reused for the next one.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::custom_utils::for_each_ksubset;
+///
+/// let mut count = 0;
+/// for_each_ksubset(&(1..=15).collect::<Vec<i64>>(), 3, |_| count += 1);
+/// assert_eq!(count, 455);
+/// ```
+pub fn for_each_ksubset(game: &[i64], k: usize, mut visit: impl FnMut(&[i64])) {
+    let n = game.len();
+    if k > n {
+        return;
+    }
+
+    let mut indices: Vec<usize> = (0..k).collect();
+    let mut subset: Vec<i64> = game[..k].to_vec();
+    loop {
+        visit(&subset);
+
+        // Find the rightmost index that can still move to the right
+        let Some(i) = (0..k).rev().find(|&i| indices[i] < n - k + i) else {
+            return;
+        };
+        indices[i] += 1;
+        subset[i] = game[indices[i]];
+        for j in i + 1..k {
+            indices[j] = indices[j - 1] + 1;
+            subset[j] = game[indices[j]];
+        }
+    }
+}
+
+/// Converts a subset of `k` numbers (in any order) to its unique combinadic number.
+///
+/// # Arguments
+/// * `subset` - The subset numbers, starting at 1.
+/// * `k` - The number of numbers the subset should have (e.g., 3).
+///
+/// # Returns
+/// * The combinadic number representing the subset among the subsets of `k` numbers.
+/// * An error if the subset does not have `k` distinct numbers from 1 up.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::custom_utils::subset2enum;
+///
+/// assert_eq!(subset2enum(&[1, 2, 4], 3), Ok(1));
+/// // Ranked as a triplet, a pair would collide with the triplet of the same rank
+/// assert!(subset2enum(&[2, 3], 3).is_err());
+/// ```
+pub fn subset2enum(subset: &[i64], k: usize) -> Result<i64, SubsetError> {
+    subset2enum_from(subset, k, 1)
+}
+
+/// Converts a subset of `k` numbers (in any order) to its unique combinadic number, for
+/// numbers starting at `min_number`.
+///
+/// # Arguments
+/// * `subset` - The subset numbers.
+/// * `k` - The number of numbers the subset should have (e.g., 3).
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+///
+/// # Returns
+/// * The combinadic number representing the subset among the subsets of `k` numbers.
+/// * An error if the subset does not have `k` distinct numbers from `min_number` up.
+pub fn subset2enum_from(subset: &[i64], k: usize, min_number: i64) -> Result<i64, SubsetError> {
+    if subset.len() != k {
+        return Err(SubsetError::WrongSize {
+            subset: subset.to_vec(),
+            k,
+        });
+    }
+    for (index, &number) in subset.iter().enumerate() {
+        if number < min_number {
+            return Err(SubsetError::BelowMinimum {
+                subset: subset.to_vec(),
+                number,
+                min_number,
+            });
+        }
+        if subset[..index].contains(&number) {
+            return Err(SubsetError::Repeated {
+                subset: subset.to_vec(),
+                number,
+            });
+        }
+    }
+    let mut numbers: Vec<i64> = subset.iter().map(|&x| x - min_number).collect();
+    numbers.sort_unstable();
+    Ok(combinadic(numbers.into_iter().rev().collect()))
 }
 
 /// Converts a triplet to its unique combinadic number.
 ///
 /// # Arguments
-/// * `triplet` - A vector of 3 integers.
+/// * `triplet` - A slice of 3 integers.
 ///
 /// # Returns
 /// * The combinadic number representing the triplet.
-pub fn triplet2enum(triplet: Vec<i64>) -> i64 {
-    combinadic(triplet.iter().map(|&x| x - 1).rev().collect())
+/// * An error if the triplet does not have 3 distinct numbers from 1 up, as its rank would be
+///   that of no triplet or of one it is not.
+pub fn triplet2enum(triplet: &[i64]) -> Result<i64, SubsetError> {
+    subset2enum(triplet, 3)
+}
+
+/// Counts how many numbers two games have in common.
+///
+/// Games may have different lengths, in which case the result is at most the length of
+/// the shorter one. Games are compared with a bitmask popcount when both fit in a
+/// `GameMask`, and otherwise with a sorted merge (sorting unsorted input first).
+///
+/// # Arguments
+/// * `a` - The first game.
+/// * `b` - The second game.
+///
+/// # Returns
+/// * The number of shared numbers.
+pub fn game_overlap(a: &[i64], b: &[i64]) -> usize {
+    if let (Some(mask_a), Some(mask_b)) = (GameMask::from_numbers(a), GameMask::from_numbers(b)) {
+        if mask_a.len() == a.len() && mask_b.len() == b.len() {
+            return mask_a.overlap(mask_b);
+        }
+    }
+    if !a.is_sorted() || !b.is_sorted() {
+        let mut a = a.to_vec();
+        let mut b = b.to_vec();
+        a.sort_unstable();
+        b.sort_unstable();
+        return game_overlap(&a, &b);
+    }
+
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    shared
+}
+
+/// Computes the largest overlap between a candidate game and any game of a set.
+///
+/// # Arguments
+/// * `set` - The games to compare against.
+/// * `candidate` - The game being compared.
+///
+/// # Returns
+/// * The maximum number of numbers shared with a single game of `set`, or 0 if `set` is empty.
+pub fn max_overlap_with(set: &[Game], candidate: &[i64]) -> usize {
+    set.iter()
+        .map(|game| game_overlap(game, candidate))
+        .max()
+        .unwrap_or(0)
 }
 
 /// Creates a game validation closure based on the given configuration values.
@@ -133,6 +554,7 @@ pub fn triplet2enum(triplet: Vec<i64>) -> i64 {
 /// # Arguments
 /// * `min_desired_number` - The minimum number allowed in a valid game.
 /// * `max_number` - The maximum number allowed in a valid game.
+/// * `numbers_per_game` - The exact number of distinct numbers of a valid game.
 ///
 /// # Returns
 /// * A closure that takes a reference to a game (a vector of numbers) and returns `true`
@@ -140,11 +562,13 @@ pub fn triplet2enum(triplet: Vec<i64>) -> i64 {
 pub fn create_invalidate_game(
     min_desired_number: i64,
     max_number: i64,
-) -> impl Fn(&Vec<i64>) -> bool {
-    move |game: &Vec<i64>| {
-        game.iter()
-            .any(|&x| x < min_desired_number || x > max_number)
-    }
+    numbers_per_game: usize,
+) -> impl Fn(&[i64]) -> bool {
+    let constraints = ConstraintSet::new()
+        .with(LengthConstraint { numbers_per_game })
+        .with(RangeConstraint::new(min_desired_number, max_number))
+        .with(DistinctConstraint);
+    move |game: &[i64]| constraints.first_violation(game).is_some()
 }
 
 /// Computes the maximum combinadic number based on the total numbers in the game (`n`)
@@ -181,15 +605,337 @@ pub fn create_combinadic_rng(
     max_number: i64,
     numbers_per_game: i64,
 ) -> impl FnMut() -> i64 {
-    let mut rng = StdRng::seed_from_u64(seed);
+    create_combinadic_rng_with(StdRng::seed_from_u64(seed), max_number, numbers_per_game)
+}
+
+/// Creates a function generating combinadic numbers from any random source, such as
+/// `rand::rngs::OsRng` for unpredictable picks or a fake generator in tests.
+///
+/// # Arguments
+/// * `rng` - The random number generator to draw from.
+/// * `max_number` - The maximum number in a game (e.g., 60).
+/// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+///
+/// # Returns
+/// * A closure that generates random combinadic numbers.
+pub fn create_combinadic_rng_with<R: Rng>(
+    mut rng: R,
+    max_number: i64,
+    numbers_per_game: i64,
+) -> impl FnMut() -> i64 {
     let max_combinadic = max_combinadic(max_number, numbers_per_game);
 
     move || rng.gen_range(0..max_combinadic)
 }
 
+/// Creates a random game generator drawing directly from the reduced space of games
+/// whose numbers all lie in `[min_desired_number, max_number]`.
+///
+/// Ranks are drawn uniformly over C(n', k), with n' = `max_number - min_desired_number + 1`,
+/// and unranked numbers are shifted into place, so every valid game is equally likely and
+/// no draw is wasted on a game outside the range.
+///
+/// # Arguments
+/// * `seed` - A `u64` seed for reproducible randomness.
+/// * `min_desired_number` - The minimum number allowed in a game.
+/// * `max_number` - The maximum number in a game (e.g., 60).
+/// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+///
+/// # Returns
+/// * A closure that generates random games in ascending order.
+pub fn create_ranged_game_rng(
+    seed: u64,
+    min_desired_number: i64,
+    max_number: i64,
+    numbers_per_game: i64,
+) -> impl FnMut() -> Game {
+    create_ranged_game_rng_with(
+        StdRng::seed_from_u64(seed),
+        min_desired_number,
+        max_number,
+        numbers_per_game,
+    )
+}
+
+/// Creates a random game generator restricted to `[min_desired_number, max_number]`
+/// drawing from any random source (see `create_ranged_game_rng`).
+///
+/// # Arguments
+/// * `rng` - The random number generator to draw from.
+/// * `min_desired_number` - The minimum number allowed in a game.
+/// * `max_number` - The maximum number in a game (e.g., 60).
+/// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+///
+/// # Returns
+/// * A closure that generates random games in ascending order.
+pub fn create_ranged_game_rng_with<R: Rng>(
+    rng: R,
+    min_desired_number: i64,
+    max_number: i64,
+    numbers_per_game: i64,
+) -> impl FnMut() -> Game {
+    let offset = min_desired_number - 1;
+    let span = max_number - offset;
+    let mut rng = create_combinadic_rng_with(rng, span, numbers_per_game);
+
+    move || {
+        enum2subset(rng(), span, numbers_per_game)
+            .expect("the combinadic generator only draws valid ranks")
+            .iter()
+            .map(|&x| x + offset)
+            .collect()
+    }
+}
+
+/// Creates a random game generator drawing numbers with probabilities proportional to their
+/// weights, by weighted sampling without replacement (the A-Res method of Efraimidis and
+/// Spirakis): each number gets the key `u^(1/weight)` for a uniform `u` in [0, 1), and the
+/// `numbers_per_game` numbers with the largest keys form the game.
+///
+/// # Arguments
+/// * `rng` - The random number generator to draw from.
+/// * `weights` - Each number with its weight; numbers of weight 0 are never drawn.
+/// * `numbers_per_game` - The number of numbers per game (e.g., 6), at most the number of
+///   positive weights.
+///
+/// # Returns
+/// * A closure that generates random games in ascending order.
+///
+/// # Examples
+/// ```
+/// use rand::SeedableRng;
+/// use rk_lottery::custom_utils::create_weighted_game_rng_with;
+///
+/// let weights: Vec<(i64, f64)> = (1..=8).map(|n| (n, if n == 8 { 0.0 } else { 1.0 })).collect();
+/// let mut rng = create_weighted_game_rng_with(rand::rngs::StdRng::seed_from_u64(1), &weights, 6);
+/// assert!(!rng().contains(&8));
+/// ```
+pub fn create_weighted_game_rng_with<R: Rng>(
+    mut rng: R,
+    weights: &[(i64, f64)],
+    numbers_per_game: usize,
+) -> impl FnMut() -> Game {
+    let weights: Vec<(i64, f64)> = weights
+        .iter()
+        .copied()
+        .filter(|&(_, weight)| weight > 0.0)
+        .collect();
+    assert!(
+        weights.len() >= numbers_per_game,
+        "only {} numbers have a positive weight",
+        weights.len()
+    );
+    let mut keys: Vec<(f64, i64)> = Vec::with_capacity(weights.len());
+
+    move || {
+        keys.clear();
+        keys.extend(
+            weights
+                .iter()
+                .map(|&(number, weight)| (rng.gen::<f64>().powf(1.0 / weight), number)),
+        );
+        // The largest keys first, then the numbers in ascending order
+        keys.select_nth_unstable_by(numbers_per_game - 1, |a, b| b.0.total_cmp(&a.0));
+        let mut game: Vec<i64> = keys[..numbers_per_game]
+            .iter()
+            .map(|&(_, number)| number)
+            .collect();
+        game.sort_unstable();
+        Game::new(game)
+    }
+}
+
+/// Moves an ascending combination to its successor in combinadic order, in place.
+///
+/// # Returns
+/// * `false` (leaving the combination untouched) if it is the last one.
+fn advance_combination(combination: &mut [i64], max_number: i64) -> bool {
+    let k = combination.len();
+    // Find the lowest position that can be incremented without colliding
+    // with its upper neighbour (or exceeding `max_number` for the last one).
+    for i in 0..k {
+        let limit = if i + 1 < k {
+            combination[i + 1]
+        } else {
+            max_number + 1
+        };
+        if combination[i] + 1 < limit {
+            combination[i] += 1;
+            for (j, number) in combination.iter_mut().enumerate().take(i) {
+                *number = j as i64 + 1;
+            }
+            return true;
+        }
+    }
+    false
+}
+
+/// Moves an ascending combination to its predecessor in combinadic order, in place.
+///
+/// # Returns
+/// * `false` (leaving the combination untouched) if it is the first one.
+fn retreat_combination(combination: &mut [i64]) -> bool {
+    // Find the lowest position above its minimum value j + 1, decrement it and
+    // push every position below it as high as possible.
+    let Some(i) = (0..combination.len()).find(|&i| combination[i] > i as i64 + 1) else {
+        return false;
+    };
+    combination[i] -= 1;
+    for j in 0..i {
+        combination[j] = combination[i] - (i - j) as i64;
+    }
+    true
+}
+
+/// Checks that a game is strictly ascending with numbers in `1..=max_number`.
+fn is_canonical_game(game: &[i64], max_number: i64) -> bool {
+    game.windows(2).all(|pair| pair[0] < pair[1])
+        && game.first().is_none_or(|&x| x >= 1)
+        && game.last().is_none_or(|&x| x <= max_number)
+}
+
+/// Returns the game following `game` in combinadic order, without ranking and unranking.
+///
+/// # Arguments
+/// * `game` - A game in ascending order with numbers in `1..=max_number`.
+/// * `max_number` - The maximum number in a game (e.g., 60).
+///
+/// # Returns
+/// * The next game, or `None` if `game` is the last one or is not in ascending order.
+pub fn next_game(game: &[i64], max_number: i64) -> Option<Game> {
+    if !is_canonical_game(game, max_number) {
+        return None;
+    }
+    let mut next = game.to_vec();
+    advance_combination(&mut next, max_number).then(|| Game::new(next))
+}
+
+/// Returns the game preceding `game` in combinadic order, without ranking and unranking.
+///
+/// # Arguments
+/// * `game` - A game in ascending order with numbers starting at 1.
+///
+/// # Returns
+/// * The previous game, or `None` if `game` is the first one or is not in ascending order.
+pub fn prev_game(game: &[i64]) -> Option<Game> {
+    if !is_canonical_game(game, i64::MAX) {
+        return None;
+    }
+    let mut prev = game.to_vec();
+    retreat_combination(&mut prev).then(|| Game::new(prev))
+}
+
+/// Lazily iterates over every possible game of a lottery in combinadic (rank) order.
+///
+/// Only the current game is kept in memory, so the whole space is never allocated.
+pub struct GameIterator {
+    /// The maximum number in a game (e.g., 60).
+    max_number: i64,
+    /// The number of numbers per game (e.g., 6).
+    numbers_per_game: i64,
+    /// The next game to be yielded, in ascending order.
+    current: Vec<i64>,
+    /// How many games are still to be yielded.
+    remaining: usize,
+}
+
+impl GameIterator {
+    /// Creates an iterator over all C(`max_number`, `numbers_per_game`) games,
+    /// starting at rank 0.
+    ///
+    /// # Arguments
+    /// * `max_number` - The maximum number in a game (e.g., 60).
+    /// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+    pub fn new(max_number: i64, numbers_per_game: i64) -> Self {
+        let mut iterator = Self {
+            max_number,
+            numbers_per_game,
+            current: Vec::new(),
+            remaining: 0,
+        };
+        iterator.skip_to(0);
+        iterator
+    }
+
+    /// Repositions the iterator so that the next game yielded is the one with
+    /// combinadic number `rank`. Ranks past the last game leave the iterator empty.
+    ///
+    /// # Arguments
+    /// * `rank` - The combinadic number of the next game to yield.
+    pub fn skip_to(&mut self, rank: i64) {
+        let total = binomial(self.max_number, self.numbers_per_game);
+        if rank < 0 || rank >= total || self.numbers_per_game > self.max_number {
+            self.current = Vec::new();
+            self.remaining = 0;
+            return;
+        }
+        self.current = enum2subset(rank, self.max_number, self.numbers_per_game)
+            .expect("the rank was checked to be within range");
+        self.remaining = (total - rank) as usize;
+    }
+
+    /// Advances `current` to the game with the next combinadic number.
+    fn advance(&mut self) {
+        advance_combination(&mut self.current, self.max_number);
+    }
+}
+
+impl Iterator for GameIterator {
+    type Item = Game;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let game = Game::new(self.current.clone());
+        self.remaining -= 1;
+        if self.remaining > 0 {
+            self.advance();
+        }
+        Some(game)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for GameIterator {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::RngCore;
+
+    /// A fake random number generator cycling through fixed values.
+    struct CycleRng {
+        values: Vec<u64>,
+        index: usize,
+    }
+
+    impl RngCore for CycleRng {
+        fn next_u32(&mut self) -> u32 {
+            self.next_u64() as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            let value = self.values[self.index % self.values.len()];
+            self.index += 1;
+            value
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for chunk in dest.chunks_mut(8) {
+                let bytes = self.next_u64().to_le_bytes();
+                chunk.copy_from_slice(&bytes[..chunk.len()]);
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
 
     // Test functions:
 
@@ -202,6 +948,7 @@ mod tests {
     /// * A vector of 3 integers representing the triplet.
     fn enum2triplet(triplet_no: i64) -> Vec<i64> {
         inverse_combinadic(triplet_no, 60, 3)
+            .unwrap()
             .iter()
             .map(|&x| x + 1)
             .rev()
@@ -225,56 +972,182 @@ mod tests {
 
     #[test]
     fn test_inverse_combinadic() {
-        assert_eq!(inverse_combinadic(0, 4, 3), vec![2, 1, 0]);
-        assert_eq!(inverse_combinadic(1, 4, 3), vec![3, 1, 0]);
+        assert_eq!(inverse_combinadic(0, 4, 3), Ok(vec![2, 1, 0]));
+        assert_eq!(inverse_combinadic(1, 4, 3), Ok(vec![3, 1, 0]));
         assert_eq!(
             inverse_combinadic(50_063_859, 60, 6),
-            vec![59, 58, 57, 56, 55, 54]
+            Ok(vec![59, 58, 57, 56, 55, 54])
         ); // Edge case: last combination
     }
 
+    #[test]
+    fn test_inverse_combinadic_out_of_range() {
+        let error = inverse_combinadic(50_063_860, 60, 6).unwrap_err(); // C(60, 6)
+        assert_eq!(
+            error,
+            RankOutOfRangeError {
+                rank: 50_063_860,
+                n: 60,
+                k: 6
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "rank 50063860 is out of range: combinations of 6 out of 60 elements have ranks 0 to 50063859"
+        );
+        assert!(inverse_combinadic(-1, 60, 6).is_err());
+        assert!(inverse_combinadic(4, 4, 3).is_err());
+        assert_eq!(inverse_combinadic(3, 4, 3), Ok(vec![3, 2, 1])); // Maximum valid rank
+        assert_eq!(
+            inverse_combinadic(0, 3, 4).unwrap_err().to_string(),
+            "rank 0 is out of range: there are no combinations of 4 out of 3 elements"
+        );
+
+        // Propagated through the game conversion
+        assert!(enum2game(-1).is_err());
+        assert!(enum2game(binomial(60, 6)).is_err());
+        assert_eq!(
+            enum2game(binomial(60, 6) - 1),
+            Ok(vec![55, 56, 57, 58, 59, 60])
+        );
+    }
+
     #[test]
     fn test_game2enum_and_enum2game() {
         let game = vec![1, 2, 3, 4, 5, 6];
-        let game_no = game2enum(game.clone());
+        let game_no = game2enum(&game);
         assert_eq!(game_no, 0);
-        assert_eq!(enum2game(game_no), game);
+        assert_eq!(enum2game(game_no), Ok(game));
 
         let game = vec![10, 20, 30, 40, 50, 60];
-        let game_no = game2enum(game.clone());
+        let game_no = game2enum(&game);
         assert!(game_no > 0);
-        assert_eq!(enum2game(game_no), game);
+        assert_eq!(enum2game(game_no), Ok(game));
+
+        // The numbers of an unsorted subset are ranked as the sorted ones, as a set
+        assert_eq!(subset2enum(&[2, 1], 2), subset2enum(&[1, 2], 2));
+        let mut triplet_nos = game2triplet_enums(&[60, 10, 50, 20, 40, 30]);
+        triplet_nos.sort_unstable();
+        let mut sorted_nos = game2triplet_enums(&[10, 20, 30, 40, 50, 60]);
+        sorted_nos.sort_unstable();
+        assert_eq!(triplet_nos, sorted_nos);
+    }
+
+    #[test]
+    fn test_unsorted_initial_game_is_sorted_and_ranked_as_the_sorted_one() {
+        let typed = [60, 1, 5, 12, 33, 47];
+        let sorted = [1, 5, 12, 33, 47, 60];
+        // Ranked in the order typed, as by the reversal assuming ascending numbers, the game
+        // took the rank of another game
+        let typed_rank = combinadic(typed.iter().map(|&x| x - 1).rev().collect());
+        assert_ne!(typed_rank, game2enum(&sorted));
+        assert_ne!(enum2game(typed_rank), Ok(sorted.to_vec()));
+
+        // Read from the configuration, its numbers are sorted, so it is written and ranked
+        // as the sorted game
+        let config = crate::config::Config::from_toml(
+            "no_of_games = 3\ninitial_games = [[60, 1, 5, 12, 33, 47]]\nblacklisted_games = [{ numbers = [9, 8, 7, 6, 5, 4], label = \"Zé\" }]\nmax_number = 60\nmin_desired_number = 1\n",
+        )
+        .unwrap();
+        assert_eq!(config.initial_games[0].numbers(), sorted);
+        assert_eq!(game2enum(&config.initial_games[0]), game2enum(&sorted));
+        assert_eq!(config.blacklisted_games[0].numbers(), [4, 5, 6, 7, 8, 9]);
+        assert_eq!(config.blacklisted_games[0].label(), Some("Zé"));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "must be sorted to be ranked")]
+    fn test_game2enum_refuses_an_unsorted_game() {
+        game2enum(&[60, 1, 5, 12, 33, 47]);
     }
 
     #[test]
     fn test_game2triplets() {
         let game = vec![1, 2, 3, 4, 5, 6];
-        let triplets = game2triplets(game);
+        let triplets = game2triplets(&game);
         assert_eq!(triplets.len(), 20); // There should be 20 triplets from 6 numbers
         assert!(triplets.contains(&vec![1, 2, 3]));
         assert!(triplets.contains(&vec![4, 5, 6]));
     }
 
+    #[test]
+    fn test_zero_based_25_number_lottery_roundtrip() {
+        let total = binomial(25, 6);
+        let mut seen = std::collections::HashSet::new();
+        for rank in 0..total {
+            let game = enum2subset_from(rank, 25, 6, 0).unwrap();
+            assert!(game.iter().all(|x| (0..=24).contains(x)), "{:?}", game);
+            assert_eq!(game2enum_from(&game, 0), rank);
+            assert!(seen.insert(game));
+        }
+        assert_eq!(enum2subset_from(0, 25, 6, 0), Ok(vec![0, 1, 2, 3, 4, 5]));
+        assert_eq!(
+            enum2subset_from(total - 1, 25, 6, 0),
+            Ok(vec![19, 20, 21, 22, 23, 24])
+        );
+        assert!(enum2subset_from(total, 25, 6, 0).is_err());
+
+        // Triplets containing 0 get ranks of their own
+        let game = vec![0, 3, 7, 12, 18, 24];
+        let expected: Vec<i64> = game2triplets(&game)
+            .iter()
+            .map(|triplet| subset2enum_from(triplet, 3, 0).unwrap())
+            .collect();
+        assert_eq!(game2triplet_enums_from(&game, 0).to_vec(), expected);
+        for (triplet, rank) in game2triplets(&game).iter().zip(expected) {
+            assert_eq!(&enum2subset_from(rank, 25, 3, 0).unwrap(), triplet);
+        }
+
+        // 1-based conversions are the same shifted by one
+        let shifted: Vec<i64> = game.iter().map(|x| x + 1).collect();
+        assert_eq!(game2enum_from(&game, 0), game2enum(&shifted));
+    }
+
+    #[test]
+    fn test_game2triplet_enums_matches_game2triplets() {
+        let mut rng = create_ranged_game_rng(77, 1, 60, 6);
+        let mut games: Vec<Vec<i64>> = (0..10_000).map(|_| rng().to_vec()).collect();
+        games.push(vec![1, 2, 3, 4, 5, 6]);
+        games.push(vec![55, 56, 57, 58, 59, 60]);
+        games.push(vec![48, 3, 59, 12, 31, 7]); // Not sorted
+
+        for game in &games {
+            let expected: Vec<i64> = game2triplets(game)
+                .iter()
+                .map(|triplet| triplet2enum(triplet).unwrap())
+                .collect();
+            assert_eq!(game2triplet_enums(game).to_vec(), expected, "{:?}", game);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_game2triplet_enums_rejects_wrong_length() {
+        game2triplet_enums(&[1, 2, 3, 4, 5]);
+    }
+
     #[test]
     fn test_triplet2enum_and_enum2triplet() {
         let triplet = vec![1, 2, 3];
-        let triplet_no = triplet2enum(triplet.clone());
+        let triplet_no = triplet2enum(&triplet).unwrap();
         assert_eq!(triplet_no, 0);
         assert_eq!(enum2triplet(triplet_no), triplet);
 
         let triplet = vec![58, 59, 60];
-        let triplet_no = triplet2enum(triplet.clone());
+        let triplet_no = triplet2enum(&triplet).unwrap();
         assert!(triplet_no > 0);
         assert_eq!(enum2triplet(triplet_no), triplet);
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_create_invalidate_game() {
         let min_desired_number = 31;
         let max_number = 60;
 
         // Create the invalidate_game closure
-        let invalidate_game = create_invalidate_game(min_desired_number, max_number);
+        let invalidate_game = create_invalidate_game(min_desired_number, max_number, 6);
 
         // Test cases
         let valid_game = vec![32, 35, 41, 48, 50, 59];
@@ -300,5 +1173,275 @@ mod tests {
             false,
             "Game should be valid as all numbers are within the boundaries"
         );
+
+        let game_with_repeated_numbers = vec![35, 35, 35, 35, 35, 35];
+        assert!(
+            invalidate_game(&game_with_repeated_numbers),
+            "Game should be invalid due to repeated numbers"
+        );
+
+        let game_with_too_few_numbers = vec![35, 41, 48, 50];
+        assert!(
+            invalidate_game(&game_with_too_few_numbers),
+            "Game should be invalid due to having fewer than 6 numbers"
+        );
+
+        let game_with_too_many_numbers = vec![32, 35, 41, 48, 50, 59, 60];
+        assert!(
+            invalidate_game(&game_with_too_many_numbers),
+            "Game should be invalid due to having more than 6 numbers"
+        );
+    }
+
+    #[test]
+    fn test_game_iterator_first_and_last_match_enum2game() {
+        let total = binomial(60, 6);
+        let mut iterator = GameIterator::new(60, 6);
+        assert_eq!(iterator.len(), total as usize);
+        assert_eq!(iterator.next(), Some(Game::new(enum2game(0).unwrap())));
+
+        iterator.skip_to(total - 1);
+        assert_eq!(iterator.len(), 1);
+        assert_eq!(
+            iterator.next(),
+            Some(Game::new(enum2game(total - 1).unwrap()))
+        );
+        assert_eq!(iterator.next(), None);
+    }
+
+    #[test]
+    fn test_game_iterator_small_lottery_in_rank_order() {
+        let games: Vec<Game> = GameIterator::new(10, 3).collect();
+        assert_eq!(games.len() as i64, binomial(10, 3)); // Exactly C(10, 3) = 120 games
+        assert_eq!(games.first(), Some(&Game::new(vec![1, 2, 3])));
+        assert_eq!(games.last(), Some(&Game::new(vec![8, 9, 10])));
+        for (rank, game) in games.iter().enumerate() {
+            assert_eq!(enum2subset(rank as i64, 10, 3), Ok(game.to_vec()));
+            assert_eq!(triplet2enum(game), Ok(rank as i64));
+        }
+    }
+
+    #[test]
+    fn test_game_iterator_skip_to() {
+        let mut iterator = GameIterator::new(10, 3);
+        iterator.skip_to(100);
+        assert_eq!(iterator.len(), 20);
+        assert_eq!(
+            iterator.next(),
+            Some(Game::new(enum2subset(100, 10, 3).unwrap()))
+        );
+
+        iterator.skip_to(binomial(10, 3)); // Past the last game
+        assert_eq!(iterator.next(), None);
+    }
+
+    #[test]
+    fn test_create_ranged_game_rng_never_leaves_the_range() {
+        let min_desired_number = 52;
+        let max_number = 60;
+        let invalidate_game = create_invalidate_game(min_desired_number, max_number, 6);
+        let mut rng = create_ranged_game_rng(12345, min_desired_number, max_number, 6);
+
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..1000 {
+            let game = rng();
+            assert_eq!(game.len(), 6);
+            assert!(
+                !invalidate_game(&game),
+                "Out of range game drawn: {:?}",
+                game
+            );
+            seen.insert(game);
+        }
+        // All C(9, 6) = 84 valid games should show up in 1000 uniform draws
+        assert_eq!(seen.len() as i64, binomial(9, 6));
+    }
+
+    #[test]
+    fn test_create_ranged_game_rng_is_reproducible() {
+        let mut first = create_ranged_game_rng(7, 31, 60, 6);
+        let mut second = create_ranged_game_rng(7, 31, 60, 6);
+        for _ in 0..100 {
+            assert_eq!(first(), second());
+        }
+    }
+
+    #[test]
+    fn test_game_overlap() {
+        let game = vec![32, 35, 41, 48, 50, 59];
+        assert_eq!(game_overlap(&game, &game), 6); // Identical games
+        assert_eq!(game_overlap(&game, &[1, 2, 3, 4, 5, 6]), 0); // Disjoint games
+        assert_eq!(game_overlap(&game, &[1, 35, 41, 49, 50, 60]), 3); // Partial overlap
+        assert_eq!(game_overlap(&game, &[59, 1, 32, 2]), 2); // Unsorted and shorter
+        assert_eq!(game_overlap(&game, &[]), 0);
+    }
+
+    #[test]
+    fn test_max_overlap_with() {
+        let set = vec![
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![7, 8, 9, 10, 11, 12]),
+        ];
+        assert_eq!(max_overlap_with(&set, &[1, 2, 7, 8, 9, 13]), 3);
+        assert_eq!(max_overlap_with(&set, &[20, 21, 22, 23, 24, 25]), 0);
+        assert_eq!(max_overlap_with(&[], &[1, 2, 3, 4, 5, 6]), 0);
+    }
+
+    #[test]
+    fn test_game2ksubsets() {
+        let game = vec![1, 2, 3, 4, 5, 6];
+        assert_eq!(game2ksubsets(&game, 3), game2triplets(&game));
+        assert_eq!(game2ksubsets(&game, 2).len(), 15);
+        assert_eq!(game2ksubsets(&game, 6), vec![game.clone()]);
+        assert_eq!(game2ksubsets(&game, 0), vec![Vec::<i64>::new()]);
+        assert!(game2ksubsets(&game, 7).is_empty());
+    }
+
+    #[test]
+    fn test_subset2enum() {
+        assert_eq!(subset2enum(&[1, 2, 3], 3), triplet2enum(&[1, 2, 3]));
+        assert_eq!(subset2enum(&[58, 59, 60], 3), triplet2enum(&[58, 59, 60]));
+        assert_eq!(subset2enum(&[1, 2], 2), Ok(0));
+        assert_eq!(subset2enum(&[59, 60], 2), Ok(binomial(60, 2) - 1));
+    }
+
+    #[test]
+    fn test_malformed_subsets_are_not_ranked() {
+        // Unchecked, a pair and a 4-subset get the rank of a triplet they are not
+        let rank = |subset: &[i64]| combinadic(subset.iter().map(|x| x - 1).rev().collect());
+        assert_eq!(rank(&[2, 3]), rank(&[1, 3, 4]));
+        assert_eq!(rank(&[1, 2, 3, 5]), rank(&[1, 2, 4]));
+        assert_eq!(triplet2enum(&[1, 3, 4]), Ok(rank(&[2, 3])));
+        assert_eq!(triplet2enum(&[1, 2, 4]), Ok(rank(&[1, 2, 3, 5])));
+
+        assert_eq!(
+            triplet2enum(&[2, 3]),
+            Err(SubsetError::WrongSize {
+                subset: vec![2, 3],
+                k: 3
+            })
+        );
+        assert_eq!(
+            triplet2enum(&[1, 2, 3, 5]).unwrap_err().to_string(),
+            "cannot rank 01-02-03-05 among the subsets of 3 numbers: it has 4"
+        );
+        assert_eq!(
+            subset2enum_from(&[0, 4, 9], 3, 1).unwrap_err().to_string(),
+            "cannot rank 00-04-09: 0 is below the smallest number, 1"
+        );
+        assert_eq!(subset2enum_from(&[0, 4, 9], 3, 0), Ok(rank(&[1, 5, 10])));
+        assert_eq!(
+            triplet2enum(&[4, 9, 4]),
+            Err(SubsetError::Repeated {
+                subset: vec![4, 9, 4],
+                number: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_next_and_prev_game_agree_with_ranks() {
+        let (n, k) = (10, 4);
+        let total = binomial(n, k);
+        for rank in 0..total {
+            let game = enum2subset(rank, n, k).unwrap();
+            let expected_next =
+                (rank + 1 < total).then(|| Game::new(enum2subset(rank + 1, n, k).unwrap()));
+            let expected_prev = (rank > 0).then(|| Game::new(enum2subset(rank - 1, n, k).unwrap()));
+            assert_eq!(next_game(&game, n), expected_next, "next of rank {}", rank);
+            assert_eq!(prev_game(&game), expected_prev, "prev of rank {}", rank);
+        }
+    }
+
+    #[test]
+    fn test_next_and_prev_game_boundaries() {
+        let first = enum2game(0).unwrap();
+        let last = enum2game(binomial(60, 6) - 1).unwrap();
+        assert_eq!(first, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(last, vec![55, 56, 57, 58, 59, 60]);
+
+        assert_eq!(prev_game(&first), None);
+        assert_eq!(
+            next_game(&first, 60),
+            Some(Game::new(enum2game(1).unwrap()))
+        );
+        assert_eq!(next_game(&last, 60), None);
+        assert_eq!(
+            prev_game(&last),
+            Some(Game::new(enum2game(binomial(60, 6) - 2).unwrap()))
+        );
+
+        // Games that are not ascending or out of range have no neighbours
+        assert_eq!(next_game(&[3, 2, 1, 4, 5, 6], 60), None);
+        assert_eq!(next_game(&[1, 2, 3, 4, 5, 61], 60), None);
+        assert_eq!(prev_game(&[0, 2, 3, 4, 5, 6]), None);
+    }
+
+    #[test]
+    fn test_create_combinadic_rng_with_mock_rng() {
+        // With a value v, ranks are drawn as the high word of v * C(60, 6)
+        let mock = CycleRng {
+            values: vec![0, 1 << 63, 1 << 62],
+            index: 0,
+        };
+        let mut rng = create_combinadic_rng_with(mock, 60, 6);
+        assert_eq!(rng(), 0);
+        assert_eq!(rng(), 25_031_930); // C(60, 6) / 2
+        assert_eq!(rng(), 12_515_965); // C(60, 6) / 4
+        assert_eq!(rng(), 0);
+    }
+
+    #[test]
+    fn test_create_ranged_game_rng_with_mock_rng() {
+        let mock = CycleRng {
+            values: vec![0, 1 << 63],
+            index: 0,
+        };
+        let mut rng = create_ranged_game_rng_with(mock, 31, 60, 6);
+        assert_eq!(rng(), Game::new(vec![31, 32, 33, 34, 35, 36]));
+        assert_eq!(rng(), Game::new(vec![33, 34, 36, 39, 43, 58])); // Rank C(30, 6) / 2
+        assert_eq!(rng(), Game::new(vec![31, 32, 33, 34, 35, 36]));
+    }
+
+    #[test]
+    fn test_weighted_game_rng_follows_extreme_weights() {
+        // Uniformly, each number is in 10% of the games
+        let weights: Vec<(i64, f64)> = (1..=60)
+            .map(|number| match number {
+                1 => (number, 100.0),
+                60 => (number, 0.01),
+                _ => (number, 1.0),
+            })
+            .collect();
+        let mut rng = create_weighted_game_rng_with(StdRng::seed_from_u64(3), &weights, 6);
+        let mut counts = [0u32; 61];
+        for _ in 0..10_000 {
+            let game = rng();
+            assert!(game.iter().zip(game.iter().skip(1)).all(|(a, b)| a < b));
+            for &number in game.iter() {
+                counts[number as usize] += 1;
+            }
+        }
+        assert!(counts[1] > 9_900, "{}", counts[1]);
+        assert!(counts[60] < 50, "{}", counts[60]);
+        assert!(counts[2..60]
+            .iter()
+            .all(|&count| (700..1_200).contains(&count)));
+    }
+
+    #[test]
+    fn test_weighted_game_rng_never_draws_zero_weights() {
+        let weights: Vec<(i64, f64)> = (1..=10)
+            .map(|number| (number, if number % 4 == 0 { 0.0 } else { 1.0 }))
+            .collect();
+        let mut rng = create_weighted_game_rng_with(StdRng::seed_from_u64(9), &weights, 6);
+        let mut drawn = [false; 11];
+        for _ in 0..1_000 {
+            for &number in rng().iter() {
+                drawn[number as usize] = true;
+            }
+        }
+        let drawn: Vec<i64> = (1..=10).filter(|&number| drawn[number as usize]).collect();
+        assert_eq!(drawn, [1, 2, 3, 5, 6, 7, 9, 10]);
     }
 }
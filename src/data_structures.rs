@@ -1,91 +1,1580 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::fs;
+use crate::custom_utils::{self, RankOutOfRangeError};
+use crate::game::Game;
+use crate::games_file::{self, Layout};
+use crate::persistence;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::any::type_name;
+use std::collections::hash_set;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::io;
+use std::str::FromStr;
+
+/// First bytes of a set saved by `NumberSet::save_to_file_binary`.
+pub const BINARY_MAGIC: &[u8; 4] = b"RKNS";
+
+/// Version of the binary format written by `NumberSet::save_with_header`; version 1, written
+/// by `NumberSet::save_to_file_binary`, has no header.
+pub const BINARY_VERSION: u8 = 2;
+
+/// Version of the sets saved by `NumberSet::save_with_header`, in any format; the sets saved
+/// without a header are of version 1.
+pub const SET_VERSION: u8 = 2;
+
+/// Numbers of a `NumberSet` its `Debug` output shows, the smallest ones.
+const DEBUG_PREVIEW: usize = 10;
+
+/// Header of a set saved by `NumberSet::save_to_file_csv`.
+const CSV_HEADER: &str = "rank";
+
+/// Start of the line a CSV set saved with a header begins with, before its `rank` header.
+const CSV_HEADER_PREFIX: &str = "# rk_lottery set";
+
+/// The formats a `NumberSet` can be saved in.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SetFormat {
+    /// A JSON array of the numbers, see `NumberSet::save_to_file`.
+    Json,
+    /// A `rank` header then one number per line, see `NumberSet::save_to_file_csv`.
+    Csv,
+    /// The compact binary format of `NumberSet::save_to_file_binary`.
+    Binary,
+}
+
+impl SetFormat {
+    /// The format a file name calls for: CSV for `.csv`, binary for `.bin` and JSON otherwise
+    /// (e.g. for `triplet_set.log`), the `.gz` of a compressed file aside.
+    pub fn from_path(filename: &str) -> Self {
+        let mut path = std::path::Path::new(filename);
+        if persistence::is_gzip_path(path) {
+            path = path.file_stem().map_or(path, std::path::Path::new);
+        }
+        match path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("csv") => SetFormat::Csv,
+            Some("bin") => SetFormat::Binary,
+            _ => SetFormat::Json,
+        }
+    }
+}
+
+/// What the ranks of a saved set are the combinadic numbers of.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SetKind {
+    /// Games, as in `games.csv`.
+    Game,
+    /// Triplets of the games, as in `triplet_set.log`.
+    Triplet,
+}
+
+impl SetKind {
+    /// The byte standing for the kind in a binary set.
+    fn code(self) -> u8 {
+        match self {
+            SetKind::Game => 0,
+            SetKind::Triplet => 1,
+        }
+    }
+
+    /// The kind a byte of a binary set stands for.
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(SetKind::Game),
+            1 => Some(SetKind::Triplet),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetKind::Game => write!(f, "game"),
+            SetKind::Triplet => write!(f, "triplet"),
+        }
+    }
+}
+
+impl FromStr for SetKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "game" => Ok(SetKind::Game),
+            "triplet" => Ok(SetKind::Triplet),
+            _ => Err(format!(
+                "unknown kind of set '{}', expected game or triplet",
+                s
+            )),
+        }
+    }
+}
+
+/// What the ranks of a saved set stand for: combinadic numbers of subsets of `k` numbers of a
+/// lottery of `n` numbers. The same rank is another combination under other parameters, so a
+/// set is only resumed from under the parameters it was saved with.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SetHeader {
+    pub kind: SetKind,
+    /// Number of numbers of the lottery.
+    pub n: i64,
+    /// Number of numbers of each subset.
+    pub k: i64,
+}
+
+impl SetHeader {
+    /// The header of a set of games of 6 numbers out of `n`.
+    pub fn games(n: i64) -> Self {
+        Self {
+            kind: SetKind::Game,
+            n,
+            k: 6,
+        }
+    }
+
+    /// The header of a set of triplets of games out of `n` numbers.
+    pub fn triplets(n: i64) -> Self {
+        Self {
+            kind: SetKind::Triplet,
+            n,
+            k: 3,
+        }
+    }
+
+    /// Checks that a set saved with the header `found` holds the ranks this header describes.
+    ///
+    /// # Returns
+    /// * An error telling the kind or the parameters that differ, if any.
+    pub fn check(&self, found: &SetHeader) -> Result<(), SetFileError> {
+        if found.kind != self.kind {
+            Err(SetFileError::KindMismatch {
+                expected: self.kind,
+                found: found.kind,
+            })
+        } else if (found.n, found.k) != (self.n, self.k) {
+            Err(SetFileError::ParameterMismatch {
+                expected: *self,
+                found: *found,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A set in JSON format saved with its header.
+#[derive(Deserialize, Serialize)]
+struct Envelope {
+    version: u8,
+    kind: SetKind,
+    n: i64,
+    k: i64,
+    data: Vec<i64>,
+}
+
+/// The body of a set in binary format, after the magic bytes and the version byte, encoded
+/// with postcard: `header` (nothing for version 1), then the count of ranks and the ranks,
+/// all integers as LEB128 varints.
+#[derive(Deserialize, Serialize)]
+struct BinaryBody<H> {
+    header: H,
+    /// The ranks in ascending order, the first one zigzag-encoded and each following one as
+    /// its difference with the previous one, so that close ranks take a byte each.
+    ranks: Vec<u64>,
+}
+
+/// The header of a set in binary format of version `BINARY_VERSION`.
+#[derive(Deserialize, Serialize)]
+struct BinaryHeader {
+    kind: u8,
+    n: u64,
+    k: u64,
+}
+
+/// The ranks of a `BinaryBody` for `numbers`, in ascending order.
+fn delta_encode(numbers: &[i64]) -> Vec<u64> {
+    let mut previous = None;
+    numbers
+        .iter()
+        .map(|&number| {
+            let rank = match previous {
+                None => ((number << 1) ^ (number >> 63)) as u64,
+                Some(previous) => number.wrapping_sub(previous) as u64,
+            };
+            previous = Some(number);
+            rank
+        })
+        .collect()
+}
+
+/// The numbers the ranks of a `BinaryBody` stand for.
+fn delta_decode(ranks: &[u64]) -> HashSet<i64> {
+    let mut previous: Option<i64> = None;
+    ranks
+        .iter()
+        .map(|&rank| {
+            let number = match previous {
+                None => ((rank >> 1) as i64) ^ -((rank & 1) as i64),
+                Some(previous) => previous.wrapping_add(rank as i64),
+            };
+            previous = Some(number);
+            number
+        })
+        .collect()
+}
+
+/// Reads the `version=2 kind=triplet n=60 k=3` fields of the header line of a CSV set, after
+/// `CSV_HEADER_PREFIX`.
+fn parse_csv_header(fields: &str) -> Result<SetHeader, SetFileError> {
+    let invalid = || SetFileError::Corrupted(format!("invalid set header '{}'", fields.trim()));
+    let mut values = HashMap::new();
+    for field in fields.split_whitespace() {
+        let (key, value) = field.split_once('=').ok_or_else(invalid)?;
+        values.insert(key, value);
+    }
+    let version: u8 = values
+        .get("version")
+        .and_then(|version| version.parse().ok())
+        .ok_or_else(invalid)?;
+    if version != SET_VERSION {
+        return Err(SetFileError::UnsupportedVersion(version));
+    }
+    let kind = match values.get("kind") {
+        Some(&"game") => SetKind::Game,
+        Some(&"triplet") => SetKind::Triplet,
+        _ => return Err(invalid()),
+    };
+    let number = |key| {
+        values
+            .get(key)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(invalid)
+    };
+    Ok(SetHeader {
+        kind,
+        n: number("n")?,
+        k: number("k")?,
+    })
+}
+
+/// Why a saved `NumberSet` could not be loaded.
+#[derive(Debug)]
+pub enum SetFileError {
+    /// The file does not exist.
+    NotFound,
+    /// The file exists but could not be read.
+    Unreadable(io::Error),
+    /// The file is not a JSON array of numbers, e.g. because a write was interrupted.
+    InvalidJson {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The file holds a set in another format than the one asked for.
+    WrongFormat(&'static str),
+    /// The file is a set of a version this program cannot read.
+    UnsupportedVersion(u8),
+    /// The file is a CSV or binary set whose content is invalid.
+    Corrupted(String),
+    /// The file holds the ranks of another kind of subsets than expected.
+    KindMismatch { expected: SetKind, found: SetKind },
+    /// The file holds ranks computed for other lottery parameters than expected.
+    ParameterMismatch {
+        expected: SetHeader,
+        found: SetHeader,
+    },
+}
+
+impl fmt::Display for SetFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetFileError::NotFound => write!(f, "file not found"),
+            SetFileError::Unreadable(error) => write!(f, "{}", error),
+            SetFileError::InvalidJson {
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "file appears corrupted: invalid JSON at line {} column {}: {}",
+                line, column, message
+            ),
+            SetFileError::WrongFormat(message) => write!(f, "{}", message),
+            SetFileError::UnsupportedVersion(version) => write!(
+                f,
+                "file of version {}, which this program cannot read",
+                version
+            ),
+            SetFileError::Corrupted(message) => write!(f, "file appears corrupted: {}", message),
+            SetFileError::KindMismatch { expected, found } => write!(
+                f,
+                "file holds {} ranks, {} ranks were expected",
+                found, expected
+            ),
+            SetFileError::ParameterMismatch { expected, found } => write!(
+                f,
+                "file holds the ranks of {} numbers out of {}, not {} out of {} as configured",
+                found.k, found.n, expected.k, expected.n
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SetFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SetFileError::Unreadable(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SetFileError {
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::NotFound {
+            SetFileError::NotFound
+        } else {
+            SetFileError::Unreadable(error)
+        }
+    }
+}
+
+impl From<SetFileError> for io::Error {
+    fn from(error: SetFileError) -> Self {
+        match error {
+            SetFileError::NotFound => io::Error::new(io::ErrorKind::NotFound, "file not found"),
+            SetFileError::Unreadable(error) => error,
+            error => io::Error::new(io::ErrorKind::InvalidData, error.to_string()),
+        }
+    }
+}
+
+/// The size of a `NumberSet`, as given by `NumberSet::stats`.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct SetStats {
+    /// Number of numbers in the set.
+    pub len: usize,
+    /// Number of numbers the set can hold before it grows.
+    pub capacity: usize,
+    /// Approximate memory taken by the set, in bytes.
+    pub bytes: usize,
+}
+
+impl fmt::Display for SetStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (size, unit) = if self.bytes < 1 << 20 {
+            (self.bytes as f64 / 1024.0, "KiB")
+        } else {
+            (self.bytes as f64 / (1024.0 * 1024.0), "MiB")
+        };
+        write!(f, "{} numbers, about {:.1} {}", self.len, size, unit)
+    }
+}
+
+/// The integer types a `NumberSet` can store its numbers as. The numbers are given to the set
+/// and read from it as `i64` ranks whatever the type, which only changes the memory they take.
+pub trait Rank: Copy + Eq + Hash + Into<i64> + TryFrom<i64> {}
+
+impl<T: Copy + Eq + Hash + Into<i64> + TryFrom<i64>> Rank for T {}
+
+/// A set of triplet ranks, stored as `u32` at half the memory of `i64` ranks: the triplets of
+/// lotteries of up to `MAX_TRIPLET_NUMBERS` numbers all have ranks that fit.
+pub type TripletSet = NumberSet<u32>;
+
+/// Largest number of numbers of a lottery whose triplet ranks, below C(n, 3), fit in the `u32`
+/// of a `TripletSet`.
+pub const MAX_TRIPLET_NUMBERS: i64 = 2954;
 
 /// Represents a set of unique numbers with functionality for manipulation and persistence.
-#[derive(Serialize, Deserialize)]
-pub struct NumberSet {
+///
+/// The numbers are stored as `T`, `i64` by default; a narrower type, as for a `TripletSet`,
+/// saves memory, but a number that does not fit in it cannot be added, which panics. A set
+/// saves its numbers as the same integers whatever the type, so a set saved as one type loads
+/// as another as long as its numbers fit.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::data_structures::NumberSet;
+///
+/// let mut set = NumberSet::new();
+/// assert!(set.add_number(7));
+/// assert!(!set.add_number(7));
+/// assert!(set.contains(7));
+///
+/// let set: NumberSet = [3, 1, 4, 1].into_iter().collect();
+/// assert_eq!(set.len(), 3);
+/// let mut numbers: Vec<i64> = set.into_iter().collect();
+/// numbers.sort_unstable();
+/// assert_eq!(numbers, [1, 3, 4]);
+///
+/// let mut triplets = rk_lottery::data_structures::TripletSet::default();
+/// assert!(triplets.check_and_insert_all(&[34_219, 0]));
+/// assert!(triplets.contains(34_219) && !triplets.contains(-1));
+/// ```
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct NumberSet<T: Rank = i64> {
     /// The internal hash set storing unique numbers.
-    numbers: HashSet<i64>,
+    numbers: HashSet<T>,
+}
+
+impl NumberSet {
+    /// Creates a new, empty `NumberSet` of `i64` numbers; `NumberSet::<T>::default()` makes
+    /// one of another type.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: Rank> Default for NumberSet<T> {
+    fn default() -> Self {
+        Self {
+            numbers: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Rank> NumberSet<T> {
+    /// `number` as stored in the set.
+    ///
+    /// # Panics
+    /// If `number` does not fit in `T`: the configuration rules out such ranks, so it would be
+    /// a bug.
+    fn stored(number: i64) -> T {
+        T::try_from(number).unwrap_or_else(|_| {
+            panic!(
+                "rank {} does not fit in a set of {}",
+                number,
+                type_name::<T>()
+            )
+        })
+    }
+
+    /// The set of the `numbers` read from a file, reporting it as corrupted if one does not
+    /// fit in `T`.
+    fn from_file_numbers(numbers: HashSet<i64>) -> Result<Self, SetFileError> {
+        let numbers = numbers
+            .into_iter()
+            .map(|number| {
+                T::try_from(number).map_err(|_| {
+                    SetFileError::Corrupted(format!(
+                        "rank {} does not fit in a set of {}",
+                        number,
+                        type_name::<T>()
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { numbers })
+    }
+
+    /// Creates an empty `NumberSet` holding at least `capacity` numbers before it grows, so
+    /// that filling a set whose final size is known does not rehash it on the way.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            numbers: HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// Makes room for at least `additional` more numbers.
+    pub fn reserve(&mut self, additional: usize) {
+        self.numbers.reserve(additional);
+    }
+
+    /// Releases the room not used by the numbers of the set.
+    pub fn shrink_to_fit(&mut self) {
+        self.numbers.shrink_to_fit();
+    }
+
+    /// Returns the number of numbers the set can hold before it grows.
+    pub fn capacity(&self) -> usize {
+        self.numbers.capacity()
+    }
+
+    /// Returns the size of the set and its approximate memory footprint.
+    pub fn stats(&self) -> SetStats {
+        let capacity = self.capacity();
+        // The table keeps 1 bucket in 8 empty, each bucket holding a number and a control byte
+        let buckets = capacity + capacity.div_ceil(7);
+        SetStats {
+            len: self.len(),
+            capacity,
+            bytes: std::mem::size_of::<Self>() + buckets * (std::mem::size_of::<T>() + 1),
+        }
+    }
+
+    /// Adds a number to the set.
+    ///
+    /// # Arguments
+    /// * `number` - The number to be added.
+    ///
+    /// # Returns
+    /// * `true` if the number was successfully added (not already present).
+    /// * `false` if the number was already in the set.
+    ///
+    /// # Panics
+    /// If `number` does not fit in `T`.
+    pub fn add_number(&mut self, number: i64) -> bool {
+        self.numbers.insert(Self::stored(number))
+    }
+
+    /// Checks whether a number is in the set.
+    ///
+    /// # Arguments
+    /// * `number` - The number to look up.
+    ///
+    /// # Returns
+    /// * `true` if the number is in the set, `false` otherwise.
+    pub fn contains(&self, number: i64) -> bool {
+        T::try_from(number).is_ok_and(|number| self.numbers.contains(&number))
+    }
+
+    /// Removes a number from the set.
+    ///
+    /// # Arguments
+    /// * `number` - The number to be removed.
+    ///
+    /// # Returns
+    /// * `true` if the number was in the set, `false` otherwise.
+    pub fn remove_number(&mut self, number: i64) -> bool {
+        T::try_from(number).is_ok_and(|number| self.numbers.remove(&number))
+    }
+
+    /// Returns the number of numbers in the set.
+    pub fn len(&self) -> usize {
+        self.numbers.len()
+    }
+
+    /// Checks whether the set holds no number.
+    pub fn is_empty(&self) -> bool {
+        self.numbers.is_empty()
+    }
+
+    /// Iterates over the numbers of the set as stored, in no particular order.
+    pub fn iter(&self) -> hash_set::Iter<'_, T> {
+        self.numbers.iter()
+    }
+
+    /// Iterates over the numbers of the set as `i64` ranks, in no particular order.
+    pub fn ranks(&self) -> impl Iterator<Item = i64> + '_ {
+        self.numbers.iter().map(|&number| number.into())
+    }
+
+    /// Returns the numbers in `self`, in `other`, or in both, as a new set.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            numbers: self.numbers.union(&other.numbers).copied().collect(),
+        }
+    }
+
+    /// Returns the numbers in both `self` and `other`, as a new set.
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self {
+            numbers: self.numbers.intersection(&other.numbers).copied().collect(),
+        }
+    }
+
+    /// Returns the numbers in `self` but not in `other`, as a new set.
+    pub fn difference(&self, other: &Self) -> Self {
+        Self {
+            numbers: self.numbers.difference(&other.numbers).copied().collect(),
+        }
+    }
+
+    /// Adds every number of `other` to the set, in place.
+    ///
+    /// # Arguments
+    /// * `other` - The set whose numbers are added.
+    pub fn merge_from(&mut self, other: &Self) {
+        self.numbers.extend(other.numbers.iter().copied());
+    }
+
+    /// Saves the `NumberSet` to a file in the given format, or in the one its extension calls
+    /// for (see `SetFormat::from_path`) when `format` is `None`, compressed with gzip when
+    /// `filename` ends with `.gz`.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file where the data should be saved.
+    /// * `format` - The format to save in, overriding the extension of `filename`.
+    ///
+    /// # Returns
+    /// * `Ok(())` on success.
+    /// * An error if the file could not be written.
+    pub fn save(&self, filename: &str, format: Option<SetFormat>) -> std::io::Result<()> {
+        let format = format.unwrap_or_else(|| SetFormat::from_path(filename));
+        persistence::write_atomically(filename, &self.encode(format, None)?)
+    }
+
+    /// Saves the `NumberSet` like `save`, recording in the file what its ranks stand for, so
+    /// that `load_with_header` can tell them apart from the ranks of another lottery.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file where the data should be saved.
+    /// * `format` - The format to save in, overriding the extension of `filename`.
+    /// * `header` - What the ranks of the set stand for.
+    ///
+    /// # Returns
+    /// * `Ok(())` on success.
+    /// * An error if the file could not be written.
+    pub fn save_with_header(
+        &self,
+        filename: &str,
+        format: Option<SetFormat>,
+        header: &SetHeader,
+    ) -> std::io::Result<()> {
+        let format = format.unwrap_or_else(|| SetFormat::from_path(filename));
+        persistence::write_atomically(filename, &self.encode(format, Some(header))?)
+    }
+
+    /// The content of a file holding the set in `format`, with `header` when given (version 2)
+    /// or as older versions saved it otherwise (version 1).
+    fn encode(&self, format: SetFormat, header: Option<&SetHeader>) -> io::Result<Vec<u8>> {
+        let mut numbers: Vec<i64> = self.ranks().collect();
+        numbers.sort_unstable();
+        match format {
+            SetFormat::Json => Ok(match header {
+                None => serde_json::to_vec(&numbers)?,
+                Some(header) => serde_json::to_vec(&Envelope {
+                    version: SET_VERSION,
+                    kind: header.kind,
+                    n: header.n,
+                    k: header.k,
+                    data: numbers,
+                })?,
+            }),
+            SetFormat::Csv => {
+                let mut content = String::new();
+                if let Some(header) = header {
+                    content.push_str(&format!(
+                        "{} version={} kind={} n={} k={}\n",
+                        CSV_HEADER_PREFIX, SET_VERSION, header.kind, header.n, header.k
+                    ));
+                }
+                content.push_str(CSV_HEADER);
+                content.push('\n');
+                for number in numbers {
+                    content.push_str(&number.to_string());
+                    content.push('\n');
+                }
+                Ok(content.into_bytes())
+            }
+            SetFormat::Binary => {
+                let mut bytes = BINARY_MAGIC.to_vec();
+                let body = match header {
+                    None => {
+                        bytes.push(1);
+                        postcard::to_allocvec(&BinaryBody {
+                            header: (),
+                            ranks: delta_encode(&numbers),
+                        })
+                    }
+                    Some(header) => {
+                        bytes.push(BINARY_VERSION);
+                        let header = BinaryHeader {
+                            kind: header.kind.code(),
+                            n: header.n as u64,
+                            k: header.k as u64,
+                        };
+                        postcard::to_allocvec(&BinaryBody {
+                            header,
+                            ranks: delta_encode(&numbers),
+                        })
+                    }
+                };
+                bytes.extend(body.map_err(io::Error::other)?);
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Saves the `NumberSet` to a file in JSON format, as an array sorted in ascending order so
+    /// that the same set always gives the same file. The file is replaced atomically, so an
+    /// interrupted save leaves the previous file in place.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file where the data should be saved.
+    ///
+    /// # Returns
+    /// * `Ok(())` on success.
+    /// * An error if the file could not be written.
+    pub fn save_to_file(&self, filename: &str) -> std::io::Result<()> {
+        self.save(filename, Some(SetFormat::Json))
+    }
+
+    /// Loads a `NumberSet` from a file in JSON format, either a bare array or the envelope
+    /// written by `save_with_header`.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file to load from.
+    ///
+    /// # Returns
+    /// * `Ok(NumberSet)` if the file was successfully loaded and parsed.
+    /// * An error if the file could not be read, or reporting it as corrupted if it could not
+    ///   be parsed.
+    pub fn load_from_file(filename: &str) -> Result<Self, SetFileError> {
+        Ok(Self::decode_json(&persistence::read_file(filename)?)?.0)
+    }
+
+    /// Reads a set in JSON format, with its header if it has one.
+    fn decode_json(contents: &[u8]) -> Result<(Self, Option<SetHeader>), SetFileError> {
+        if contents.starts_with(BINARY_MAGIC) {
+            return Err(SetFileError::WrongFormat(
+                "file holds a binary set, not a JSON one: load it with load_from_file_binary",
+            ));
+        }
+        let invalid = |error: serde_json::Error| {
+            let message = error.to_string();
+            let position = format!(" at line {} column {}", error.line(), error.column());
+            SetFileError::InvalidJson {
+                line: error.line(),
+                column: error.column(),
+                message: message
+                    .strip_suffix(&position)
+                    .unwrap_or(&message)
+                    .to_string(),
+            }
+        };
+        if !contents.trim_ascii_start().starts_with(b"{") {
+            let numbers: HashSet<i64> = serde_json::from_slice(contents).map_err(invalid)?;
+            return Ok((Self::from_file_numbers(numbers)?, None));
+        }
+        // The version decides how the rest of the envelope is read
+        #[derive(Deserialize)]
+        struct Versioned {
+            version: u64,
+        }
+        let version = serde_json::from_slice::<Versioned>(contents)
+            .map_err(invalid)?
+            .version;
+        if version != u64::from(SET_VERSION) {
+            return Err(SetFileError::UnsupportedVersion(
+                version.try_into().unwrap_or(u8::MAX),
+            ));
+        }
+        let envelope: Envelope = serde_json::from_slice(contents).map_err(invalid)?;
+        let header = SetHeader {
+            kind: envelope.kind,
+            n: envelope.n,
+            k: envelope.k,
+        };
+        let numbers = envelope.data.into_iter().collect();
+        Ok((Self::from_file_numbers(numbers)?, Some(header)))
+    }
+
+    /// Saves the `NumberSet` to a CSV file: a `rank` header, then one number per line in
+    /// ascending order. The file is replaced atomically, like with `save_to_file`.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file where the data should be saved.
+    ///
+    /// # Returns
+    /// * `Ok(())` on success.
+    /// * An error if the file could not be written.
+    pub fn save_to_file_csv(&self, filename: &str) -> std::io::Result<()> {
+        self.save(filename, Some(SetFormat::Csv))
+    }
+
+    /// Loads a `NumberSet` saved by `save_to_file_csv`, or by `save_with_header` in CSV format.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file to load from.
+    ///
+    /// # Returns
+    /// * `Ok(NumberSet)` if the file was successfully loaded and parsed.
+    /// * An error if the file could not be read, or reporting it as corrupted if a line is not
+    ///   a number.
+    pub fn load_from_file_csv(filename: &str) -> Result<Self, SetFileError> {
+        Ok(Self::decode_csv(&persistence::read_to_string(filename)?)?.0)
+    }
+
+    /// Reads a set in CSV format, with its header if it has one.
+    fn decode_csv(contents: &str) -> Result<(Self, Option<SetHeader>), SetFileError> {
+        let mut lines = contents.lines().enumerate().peekable();
+        let mut header = None;
+        if let Some((_, line)) = lines.next_if(|(_, line)| line.starts_with(CSV_HEADER_PREFIX)) {
+            header = Some(parse_csv_header(&line[CSV_HEADER_PREFIX.len()..])?);
+        }
+        lines.next_if(|(_, line)| line.trim() == CSV_HEADER);
+        let mut numbers = HashSet::new();
+        for (index, line) in lines {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let number = line.parse().map_err(|_| {
+                SetFileError::Corrupted(format!("line {} is not a number: '{}'", index + 1, line))
+            })?;
+            numbers.insert(number);
+        }
+        Ok((Self::from_file_numbers(numbers)?, header))
+    }
+
+    /// Saves the `NumberSet` to a file in a compact binary format, atomically like
+    /// `save_to_file`: the `BINARY_MAGIC` bytes and the version byte, then the numbers in
+    /// ascending order encoded with postcard, as described by `BinaryBody`. A set saved with
+    /// `save_with_header` is of version `BINARY_VERSION`, the kind byte then `n` and `k`
+    /// coming between the version and the numbers.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file where the data should be saved.
+    ///
+    /// # Returns
+    /// * `Ok(())` on success.
+    /// * An error if the file could not be written.
+    pub fn save_to_file_binary(&self, filename: &str) -> std::io::Result<()> {
+        self.save(filename, Some(SetFormat::Binary))
+    }
+
+    /// Loads a `NumberSet` saved by `save_to_file_binary`, or by `save_with_header` in binary
+    /// format.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file to load from.
+    ///
+    /// # Returns
+    /// * `Ok(NumberSet)` if the file was successfully loaded.
+    /// * An error if the file could not be read, is not a binary set (e.g. a JSON one), has an
+    ///   unknown version, or ends early.
+    pub fn load_from_file_binary(filename: &str) -> Result<Self, SetFileError> {
+        Ok(Self::decode_binary(&persistence::read_file(filename)?)?.0)
+    }
+
+    /// Reads a set in binary format, with its header if it has one.
+    fn decode_binary(contents: &[u8]) -> Result<(Self, Option<SetHeader>), SetFileError> {
+        let Some(rest) = contents.strip_prefix(BINARY_MAGIC) else {
+            return Err(SetFileError::WrongFormat(
+                if contents.trim_ascii_start().starts_with(b"[") {
+                    "file is not a binary set, it looks like a JSON set: load it with load_from_file"
+                } else {
+                    "file is not a binary set"
+                },
+            ));
+        };
+        let Some((&version, rest)) = rest.split_first() else {
+            return Err(truncated());
+        };
+        let (header, ranks, rest) = match version {
+            1 => {
+                let (body, rest) =
+                    postcard::take_from_bytes::<BinaryBody<()>>(rest).map_err(binary_error)?;
+                (None, body.ranks, rest)
+            }
+            BINARY_VERSION => {
+                let (body, rest) = postcard::take_from_bytes::<BinaryBody<BinaryHeader>>(rest)
+                    .map_err(binary_error)?;
+                let kind = SetKind::from_code(body.header.kind).ok_or_else(|| {
+                    SetFileError::Corrupted(format!("unknown kind of set {}", body.header.kind))
+                })?;
+                let header = SetHeader {
+                    kind,
+                    n: body.header.n as i64,
+                    k: body.header.k as i64,
+                };
+                (Some(header), body.ranks, rest)
+            }
+            version => return Err(SetFileError::UnsupportedVersion(version)),
+        };
+        if !rest.is_empty() {
+            return Err(SetFileError::Corrupted(format!(
+                "{} bytes after the last number",
+                rest.len()
+            )));
+        }
+        let numbers = delta_decode(&ranks);
+        Ok((Self::from_file_numbers(numbers)?, header))
+    }
+
+    /// Loads a `NumberSet` saved in any format, whatever the extension of the file: binary
+    /// when it starts with the `BINARY_MAGIC` bytes, JSON when it starts with `[` (like the
+    /// JSON sets older versions saved to `games.csv`) or `{`, or is empty, CSV otherwise. A
+    /// file compressed with gzip is decompressed first, as by every `load_*` function.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file to load from.
+    pub fn load(filename: &str) -> Result<Self, SetFileError> {
+        Ok(Self::load_with_header(filename)?.0)
+    }
+
+    /// Loads a `NumberSet` like `load`, along with the header it was saved with.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file to load from.
+    ///
+    /// # Returns
+    /// * The set and its header, `None` for a set saved without one (version 1).
+    /// * An error if the file cannot be read or parsed.
+    pub fn load_with_header(filename: &str) -> Result<(Self, Option<SetHeader>), SetFileError> {
+        let contents = persistence::read_file(filename)?;
+        let start = contents.trim_ascii_start();
+        if contents.starts_with(BINARY_MAGIC) {
+            Self::decode_binary(&contents)
+        } else if start.is_empty() || start.starts_with(b"[") || start.starts_with(b"{") {
+            Self::decode_json(&contents)
+        } else {
+            let contents = String::from_utf8(contents).map_err(|_| {
+                SetFileError::Corrupted("the CSV set is not valid UTF-8".to_string())
+            })?;
+            Self::decode_csv(&contents)
+        }
+    }
+
+    /// Loads a `NumberSet` like `load`, checking its ranks stand for what `expected` says.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file to load from.
+    /// * `expected` - What the ranks of the set should stand for.
+    ///
+    /// # Returns
+    /// * The set, and whether it was saved without a header (version 1), so was not checked.
+    /// * An error if the file cannot be read or parsed, or holds the ranks of another kind of
+    ///   subsets or of another lottery.
+    pub fn load_checked(
+        filename: &str,
+        expected: &SetHeader,
+    ) -> Result<(Self, bool), SetFileError> {
+        let (set, header) = Self::load_with_header(filename)?;
+        match header {
+            Some(header) => {
+                expected.check(&header)?;
+                Ok((set, false))
+            }
+            None => Ok((set, true)),
+        }
+    }
+
+    /// Writes the numbers of the set as the subsets of `k` numbers out of `1..=n` they are the
+    /// combinadic numbers of, e.g. the games of `games.csv`, to a CSV file with a header row
+    /// and an index column, the subsets sorted. See `export_games_from`.
+    ///
+    /// # Arguments
+    /// * `n` - How many numbers the lottery has (e.g., 60).
+    /// * `k` - The number of numbers of each subset (e.g., 6 for games, 3 for triplets).
+    /// * `path` - The CSV file to write.
+    ///
+    /// # Returns
+    /// * The numbers of the set that are not ranks of such subsets, left out of the file.
+    /// * An error if the file could not be written.
+    pub fn export_games(&self, n: i64, k: i64, path: &str) -> io::Result<Vec<RankOutOfRangeError>> {
+        self.export_games_from(n, k, 1, path)
+    }
+
+    /// Writes the numbers of the set as subsets like `export_games`, for a lottery of `n`
+    /// numbers starting at `min_number`. A number that is not the rank of such a subset, as
+    /// when the ranks were saved for another lottery, is left out rather than failing the
+    /// whole export.
+    ///
+    /// # Arguments
+    /// * `n` - How many numbers the lottery has (e.g., 60).
+    /// * `k` - The number of numbers of each subset (e.g., 6 for games, 3 for triplets).
+    /// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+    /// * `path` - The CSV file to write.
+    ///
+    /// # Returns
+    /// * The numbers of the set that are not ranks of such subsets, in ascending order.
+    /// * An error if the file could not be written.
+    ///
+    /// # Examples
+    /// ```
+    /// use rk_lottery::data_structures::NumberSet;
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("games_export.csv");
+    /// let path = path.to_str().unwrap();
+    /// let set: NumberSet = [1, 0, -1].into_iter().collect();
+    /// let out_of_range = set.export_games_from(60, 6, 1, path).unwrap();
+    /// assert_eq!(out_of_range[0].rank, -1);
+    /// assert_eq!(
+    ///     std::fs::read_to_string(path).unwrap(),
+    ///     "game,n1,n2,n3,n4,n5,n6\n1,1,2,3,4,5,6\n2,1,2,3,4,5,7\n"
+    /// );
+    /// ```
+    pub fn export_games_from(
+        &self,
+        n: i64,
+        k: i64,
+        min_number: i64,
+        path: &str,
+    ) -> io::Result<Vec<RankOutOfRangeError>> {
+        let mut ranks: Vec<i64> = self.ranks().collect();
+        ranks.sort_unstable();
+        let mut games = Vec::with_capacity(ranks.len());
+        let mut out_of_range = Vec::new();
+        for rank in ranks {
+            match custom_utils::enum2subset_from(rank, n, k, min_number) {
+                Ok(numbers) => games.push(Game::new(numbers)),
+                Err(error) => out_of_range.push(error),
+            }
+        }
+        games.sort_unstable();
+        let csv = games_file::to_csv(
+            &games,
+            Layout::Indexed,
+            k.max(0) as usize,
+            games_file::DEFAULT_DELIMITER,
+            0,
+            None,
+        );
+        persistence::write_atomically(path, &csv)?;
+        Ok(out_of_range)
+    }
+
+    /// Loads a `NumberSet` like `load`, or gives an empty one if the file does not exist, e.g.
+    /// when resuming a run that never saved it.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file to load from.
+    ///
+    /// # Returns
+    /// * The loaded set, empty when the file is missing.
+    /// * An error if the file exists but cannot be read or parsed.
+    pub fn load_or_default(filename: &str) -> Result<Self, SetFileError> {
+        match Self::load(filename) {
+            Err(SetFileError::NotFound) => Ok(Self::default()),
+            result => result,
+        }
+    }
+
+    /// Attempts to insert all numbers in a vector into the set, all or nothing.
+    ///
+    /// # Arguments
+    /// * `numbers` - A slice of numbers to be added.
+    ///
+    /// # Returns
+    /// * `true` if all numbers were successfully added (none were duplicates).
+    /// * `false` if at least one number was already in the set or appears twice in `numbers`;
+    ///   the set is then left unchanged.
+    ///
+    /// # Panics
+    /// If a number does not fit in `T`.
+    pub fn check_and_insert_all(&mut self, numbers: &[i64]) -> bool {
+        if numbers.iter().any(|&num| self.contains(num)) {
+            return false; // At least one number already exists in the set
+        }
+
+        for (index, &num) in numbers.iter().enumerate() {
+            if !self.numbers.insert(Self::stored(num)) {
+                // A number repeated in the input: take back the ones inserted before it
+                for &inserted in &numbers[..index] {
+                    self.numbers.remove(&Self::stored(inserted));
+                }
+                return false;
+            }
+        }
+        true // All numbers successfully added
+    }
+}
+
+/// Largest domain for which a `DenseNumberSet` is preferred to a `NumberSet`: 2^24 ranks take
+/// 2 MiB of bits, less than a `HashSet` holding a few hundred thousand of them.
+pub const DENSE_DOMAIN_THRESHOLD: usize = 1 << 24;
+
+/// The operations the generation needs from a set of ranks, so that it can use whichever
+/// storage suits the size of the domain.
+pub trait RankSet {
+    /// Adds a number, returning `true` if it was not in the set yet.
+    fn add_number(&mut self, number: i64) -> bool;
+
+    /// Checks whether a number is in the set.
+    fn contains(&self, number: i64) -> bool;
+
+    /// Returns the number of numbers in the set.
+    fn len(&self) -> usize;
+
+    /// Checks whether the set holds no number.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts all the numbers if none of them is in the set yet nor repeated in `numbers`.
+    ///
+    /// # Returns
+    /// * `true` if all numbers were added, `false` (leaving the set unchanged) if at least one
+    ///   was already in the set or appears twice in `numbers`.
+    fn check_and_insert_all(&mut self, numbers: &[i64]) -> bool {
+        if numbers.iter().any(|&number| self.contains(number)) {
+            return false;
+        }
+        if (1..numbers.len()).any(|index| numbers[..index].contains(&numbers[index])) {
+            return false;
+        }
+        for &number in numbers {
+            self.add_number(number);
+        }
+        true
+    }
+}
+
+impl<T: Rank> RankSet for NumberSet<T> {
+    fn add_number(&mut self, number: i64) -> bool {
+        NumberSet::add_number(self, number)
+    }
+
+    fn contains(&self, number: i64) -> bool {
+        NumberSet::contains(self, number)
+    }
+
+    fn len(&self) -> usize {
+        NumberSet::len(self)
+    }
+
+    fn check_and_insert_all(&mut self, numbers: &[i64]) -> bool {
+        NumberSet::check_and_insert_all(self, numbers)
+    }
+}
+
+/// A set of ranks stored as one bit per rank of `0..domain_size`, smaller and faster than a
+/// `NumberSet` when the domain is known and small (e.g. the 34,220 triplets of 60 numbers).
+///
+/// Ranks beyond the domain grow the bits as needed; negative numbers are never in the set and
+/// cannot be added. The set serializes as the list of its ranks in ascending order, like a
+/// saved `NumberSet`.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::data_structures::{DenseNumberSet, RankSet};
+///
+/// let mut set = DenseNumberSet::new(34_220);
+/// assert!(set.add_number(7));
+/// assert!(!set.add_number(7));
+/// assert!(set.check_and_insert_all(&[1, 34_219]));
+/// assert_eq!(set.iter().collect::<Vec<_>>(), [1, 7, 34_219]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DenseNumberSet {
+    /// Bit `rank % 64` of word `rank / 64` is set when `rank` is in the set.
+    words: Vec<u64>,
+    /// Number of bits set.
+    len: usize,
+}
+
+impl DenseNumberSet {
+    /// Creates an empty set for the ranks of `0..domain_size`.
+    pub fn new(domain_size: usize) -> Self {
+        Self {
+            words: vec![0; domain_size.div_ceil(64)],
+            len: 0,
+        }
+    }
+
+    /// The word and bit holding `number`, if it fits in the words allocated.
+    fn position(&self, number: i64) -> Option<(usize, u64)> {
+        let rank = usize::try_from(number).ok()?;
+        (rank / 64 < self.words.len()).then_some((rank / 64, 1 << (rank % 64)))
+    }
+
+    /// Removes a number from the set, returning `true` if it was in the set.
+    pub fn remove_number(&mut self, number: i64) -> bool {
+        let Some((word, bit)) = self.position(number) else {
+            return false;
+        };
+        let removed = self.words[word] & bit != 0;
+        self.words[word] &= !bit;
+        self.len -= usize::from(removed);
+        removed
+    }
+
+    /// Iterates over the numbers of the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = i64> + '_ {
+        self.words.iter().enumerate().flat_map(|(index, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    (index * 64 + bit) as i64
+                })
+            })
+        })
+    }
+}
+
+impl RankSet for DenseNumberSet {
+    fn add_number(&mut self, number: i64) -> bool {
+        let rank = usize::try_from(number).expect("ranks are never negative");
+        if rank / 64 >= self.words.len() {
+            self.words.resize(rank / 64 + 1, 0);
+        }
+        let (word, bit) = (rank / 64, 1 << (rank % 64));
+        let added = self.words[word] & bit == 0;
+        self.words[word] |= bit;
+        self.len += usize::from(added);
+        added
+    }
+
+    fn contains(&self, number: i64) -> bool {
+        self.position(number)
+            .is_some_and(|(word, bit)| self.words[word] & bit != 0)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn check_and_insert_all(&mut self, numbers: &[i64]) -> bool {
+        if numbers.iter().any(|&number| self.contains(number)) {
+            return false;
+        }
+        for (index, &number) in numbers.iter().enumerate() {
+            if !self.add_number(number) {
+                // A number repeated in the input: clear the bits set before it
+                for &inserted in &numbers[..index] {
+                    let (word, bit) = self.position(inserted).expect("inserted above");
+                    self.words[word] &= !bit;
+                    self.len -= 1;
+                }
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl PartialEq for DenseNumberSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl FromIterator<i64> for DenseNumberSet {
+    fn from_iter<I: IntoIterator<Item = i64>>(iter: I) -> Self {
+        let mut set = Self::default();
+        for number in iter {
+            set.add_number(number);
+        }
+        set
+    }
 }
 
-impl NumberSet {
-    /// Creates a new, empty `NumberSet`.
-    pub fn new() -> Self {
+impl Serialize for DenseNumberSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for DenseNumberSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let numbers = Vec::<i64>::deserialize(deserializer)?;
+        if let Some(&number) = numbers.iter().find(|&&number| number < 0) {
+            return Err(serde::de::Error::custom(format!(
+                "rank {} is negative",
+                number
+            )));
+        }
+        Ok(numbers.into_iter().collect())
+    }
+}
+
+/// The triplet ranks a run checks its candidates against: one bit per triplet of the lottery
+/// when there are at most `DENSE_DOMAIN_THRESHOLD` of them, a `TripletSet` otherwise. The run
+/// hands its triplets back as a `TripletSet`, for saving and resuming.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::data_structures::{RankSet, TripletIndex, TripletSet};
+///
+/// let mut index = TripletIndex::new(TripletSet::default(), 1, 60);
+/// assert!(matches!(index, TripletIndex::Dense(_)));
+/// assert!(index.check_and_insert_all(&[0, 34_219]));
+/// assert!(!index.check_and_insert_all(&[7, 0]));
+/// assert_eq!(index.into_set().len(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum TripletIndex {
+    /// The triplets of a lottery of up to a few hundred numbers, one bit each.
+    Dense(DenseNumberSet),
+    /// The triplets of a larger lottery.
+    Hashed(TripletSet),
+}
+
+impl TripletIndex {
+    /// The index of the triplets of `set`, for a lottery of the numbers from `min_number` to
+    /// `max_number`.
+    pub fn new(set: TripletSet, min_number: i64, max_number: i64) -> Self {
+        let span = (max_number - min_number + 1).max(0);
+        let domain = custom_utils::binomial(span, 3);
+        match usize::try_from(domain) {
+            Ok(domain) if domain <= DENSE_DOMAIN_THRESHOLD => {
+                let mut dense = DenseNumberSet::new(domain);
+                for rank in set.ranks() {
+                    dense.add_number(rank);
+                }
+                TripletIndex::Dense(dense)
+            }
+            _ => TripletIndex::Hashed(set),
+        }
+    }
+
+    /// Makes room for at least `additional` more triplets; a dense index has room for all.
+    pub fn reserve(&mut self, additional: usize) {
+        if let TripletIndex::Hashed(set) = self {
+            set.reserve(additional);
+        }
+    }
+
+    /// Removes a triplet, returning `true` if it was in the index.
+    pub fn remove_number(&mut self, number: i64) -> bool {
+        match self {
+            TripletIndex::Dense(set) => set.remove_number(number),
+            TripletIndex::Hashed(set) => set.remove_number(number),
+        }
+    }
+
+    /// The triplets of the index, as a `TripletSet`.
+    pub fn to_set(&self) -> TripletSet {
+        match self {
+            TripletIndex::Dense(set) => {
+                let mut triplets = TripletSet::with_capacity(set.len());
+                for rank in set.iter() {
+                    triplets.add_number(rank);
+                }
+                triplets
+            }
+            TripletIndex::Hashed(set) => set.clone(),
+        }
+    }
+
+    /// Ends the index, giving its triplets as a `TripletSet`.
+    pub fn into_set(self) -> TripletSet {
+        match self {
+            TripletIndex::Dense(_) => self.to_set(),
+            TripletIndex::Hashed(set) => set,
+        }
+    }
+}
+
+impl RankSet for TripletIndex {
+    fn add_number(&mut self, number: i64) -> bool {
+        match self {
+            TripletIndex::Dense(set) => set.add_number(number),
+            TripletIndex::Hashed(set) => set.add_number(number),
+        }
+    }
+
+    fn contains(&self, number: i64) -> bool {
+        match self {
+            TripletIndex::Dense(set) => set.contains(number),
+            TripletIndex::Hashed(set) => set.contains(number),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            TripletIndex::Dense(set) => set.len(),
+            TripletIndex::Hashed(set) => set.len(),
+        }
+    }
+
+    fn check_and_insert_all(&mut self, numbers: &[i64]) -> bool {
+        match self {
+            TripletIndex::Dense(set) => set.check_and_insert_all(numbers),
+            TripletIndex::Hashed(set) => set.check_and_insert_all(numbers),
+        }
+    }
+}
+
+/// The error for a binary set postcard cannot read.
+fn binary_error(error: postcard::Error) -> SetFileError {
+    match error {
+        postcard::Error::DeserializeUnexpectedEnd => truncated(),
+        postcard::Error::DeserializeBadVarint => {
+            SetFileError::Corrupted("a number takes more than 64 bits".to_string())
+        }
+        error => SetFileError::Corrupted(error.to_string()),
+    }
+}
+
+/// An error for a binary set ending early, usually because a write was interrupted.
+fn truncated() -> SetFileError {
+    SetFileError::Corrupted("the binary set ends early".to_string())
+}
+
+/// Shows the size of the set and its `DEBUG_PREVIEW` smallest numbers, in ascending order, so
+/// that a set of millions of ranks still prints on a line.
+impl<T: Rank> fmt::Debug for NumberSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut smallest: Vec<i64> = self.ranks().collect();
+        if smallest.len() > DEBUG_PREVIEW {
+            smallest.select_nth_unstable(DEBUG_PREVIEW);
+            smallest.truncate(DEBUG_PREVIEW);
+        }
+        smallest.sort_unstable();
+        f.debug_struct("NumberSet")
+            .field("len", &self.numbers.len())
+            .field(
+                "numbers",
+                &Preview {
+                    smallest: &smallest,
+                    truncated: smallest.len() < self.numbers.len(),
+                },
+            )
+            .finish()
+    }
+}
+
+/// The numbers shown by the `Debug` output of a `NumberSet`, ending with `..` if some are left
+/// out.
+struct Preview<'a> {
+    smallest: &'a [i64],
+    truncated: bool,
+}
+
+impl fmt::Debug for Preview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut list = f.debug_list();
+        list.entries(self.smallest);
+        if self.truncated {
+            list.finish_non_exhaustive()
+        } else {
+            list.finish()
+        }
+    }
+}
+
+/// Collects `i64` ranks, panicking like `NumberSet::add_number` on one that does not fit in `T`.
+impl<T: Rank> FromIterator<i64> for NumberSet<T> {
+    fn from_iter<I: IntoIterator<Item = i64>>(iter: I) -> Self {
         Self {
-            numbers: HashSet::new(),
+            numbers: iter.into_iter().map(Self::stored).collect(),
         }
     }
+}
 
-    /// Adds a number to the set.
-    ///
-    /// # Arguments
-    /// * `number` - The number to be added.
-    ///
-    /// # Returns
-    /// * `true` if the number was successfully added (not already present).
-    /// * `false` if the number was already in the set.
-    pub fn add_number(&mut self, number: i64) -> bool {
-        self.numbers.insert(number)
+impl<T: Rank> IntoIterator for NumberSet<T> {
+    type Item = T;
+    type IntoIter = hash_set::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.numbers.into_iter()
     }
+}
 
-    /// Saves the `NumberSet` to a file in JSON format.
-    ///
-    /// # Arguments
-    /// * `filename` - The path to the file where the data should be saved.
-    ///
-    /// # Returns
-    /// * `Ok(())` on success.
-    /// * An error if the file could not be written.
-    pub fn save_to_file(&self, filename: &str) -> std::io::Result<()> {
-        let serialized = serde_json::to_string(&self.numbers)?;
-        fs::write(filename, serialized)
+impl<'a, T: Rank> IntoIterator for &'a NumberSet<T> {
+    type Item = &'a T;
+    type IntoIter = hash_set::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
+}
 
-    /// Loads a `NumberSet` from a file in JSON format.
-    ///
-    /// # Arguments
-    /// * `filename` - The path to the file to load from.
+/// The game owning each subset rank (e.g. of a triplet), the first one recorded with it, so a
+/// collision can name the game it collides with. It takes about three times the memory of a
+/// `NumberSet` of the same ranks.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::data_structures::SubsetIndex;
+///
+/// let mut index = SubsetIndex::new();
+/// assert_eq!(index.insert_all(&[10, 11, 12], 0), Ok(()));
+/// assert_eq!(index.insert_all(&[13, 11], 1), Err((1, 0)));
+/// assert_eq!(index.owner(11), Some(0));
+/// assert_eq!(index.owner(13), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubsetIndex {
+    /// The owning game of each rank.
+    owners: HashMap<i64, usize>,
+}
+
+impl SubsetIndex {
+    /// Creates an empty index.
+    pub fn new() -> Self {
+        Self {
+            owners: HashMap::new(),
+        }
+    }
+
+    /// Records `owner` as the game of `rank`, unless the rank already has one.
     ///
     /// # Returns
-    /// * `Ok(NumberSet)` if the file was successfully loaded and parsed.
-    /// * An error if the file could not be read or parsed.
-    #[cfg(test)]
-    pub fn load_from_file(filename: &str) -> std::io::Result<Self> {
-        let contents = fs::read_to_string(filename)?;
-        let numbers: HashSet<i64> = serde_json::from_str(&contents)?;
-        Ok(Self { numbers })
+    /// * `None` if the rank was new.
+    /// * The earlier owner of the rank, which is kept, otherwise.
+    pub fn insert(&mut self, rank: i64, owner: usize) -> Option<usize> {
+        match self.owners.get(&rank) {
+            Some(&earlier) => Some(earlier),
+            None => {
+                self.owners.insert(rank, owner);
+                None
+            }
+        }
     }
 
-    /// Attempts to insert all numbers in a vector into the set.
-    ///
-    /// # Arguments
-    /// * `numbers` - A vector of numbers to be added.
+    /// Records `owner` as the game of every rank of `ranks`, only if none is in the index yet.
     ///
     /// # Returns
-    /// * `true` if all numbers were successfully added (none were duplicates).
-    /// * `false` if at least one number was already in the set.
-    pub fn check_and_insert_all(&mut self, numbers: Vec<i64>) -> bool {
-        if numbers.iter().any(|&num| self.numbers.contains(&num)) {
-            return false; // At least one number already exists in the set
+    /// * `Ok(())` if all the ranks were recorded.
+    /// * The position in `ranks` of the first rank already there, or repeated, with its owner,
+    ///   the index being left unchanged, otherwise.
+    pub fn insert_all(&mut self, ranks: &[i64], owner: usize) -> Result<(), (usize, usize)> {
+        if let Some(conflict) = ranks
+            .iter()
+            .enumerate()
+            .find_map(|(position, rank)| self.owner(*rank).map(|earlier| (position, earlier)))
+        {
+            return Err(conflict);
         }
-
-        for num in numbers {
-            self.numbers.insert(num);
+        for (position, &rank) in ranks.iter().enumerate() {
+            if self.insert(rank, owner).is_some() {
+                for &inserted in &ranks[..position] {
+                    self.owners.remove(&inserted);
+                }
+                return Err((position, owner));
+            }
         }
-        true // All numbers successfully added
+        Ok(())
+    }
+
+    /// The game owning `rank`, if it is in the index.
+    pub fn owner(&self, rank: i64) -> Option<usize> {
+        self.owners.get(&rank).copied()
+    }
+
+    /// Returns the number of ranks in the index.
+    pub fn len(&self) -> usize {
+        self.owners.len()
+    }
+
+    /// Checks if the index is empty.
+    pub fn is_empty(&self) -> bool {
+        self.owners.is_empty()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
     use tempfile::NamedTempFile;
 
     #[test]
     fn test_new() {
         let number_set = NumberSet::new();
-        assert!(number_set.numbers.is_empty());
+        assert!(number_set.is_empty());
+        assert_eq!(number_set, NumberSet::default());
+    }
+
+    #[test]
+    fn test_debug_shows_a_sorted_preview() {
+        assert_eq!(
+            format!("{:?}", set(&[15, -3, 7])),
+            "NumberSet { len: 3, numbers: [-3, 7, 15] }"
+        );
+        let large: NumberSet = (0..1_000_000).rev().collect();
+        assert_eq!(
+            format!("{:?}", large),
+            "NumberSet { len: 1000000, numbers: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, ..] }"
+        );
+        let mut copy = large.clone();
+        assert_eq!(copy, large);
+        copy.add_number(-1);
+        assert_ne!(copy, large);
     }
 
     #[test]
@@ -95,6 +1584,149 @@ mod tests {
         assert!(!number_set.add_number(5)); // Duplicate, not added
     }
 
+    #[test]
+    fn test_contains_len_and_is_empty() {
+        let mut number_set = NumberSet::new();
+        assert!(number_set.is_empty());
+        assert_eq!(number_set.len(), 0);
+        assert!(!number_set.contains(5));
+        number_set.add_number(5);
+        number_set.add_number(-2);
+        number_set.add_number(5);
+        assert!(!number_set.is_empty());
+        assert_eq!(number_set.len(), 2);
+        assert!(number_set.contains(5) && number_set.contains(-2));
+        assert!(!number_set.contains(2));
+    }
+
+    #[test]
+    fn test_iteration_visits_each_number_once() {
+        let number_set: NumberSet = [8, 3, 8, 15, -1].into_iter().collect();
+        assert_eq!(number_set.iter().count(), number_set.len());
+        let mut borrowed: Vec<i64> = (&number_set).into_iter().copied().collect();
+        borrowed.sort_unstable();
+        assert_eq!(borrowed, [-1, 3, 8, 15]);
+        let mut owned: Vec<i64> = number_set.into_iter().collect();
+        owned.sort_unstable();
+        assert_eq!(owned, borrowed);
+        assert!(std::iter::empty().collect::<NumberSet>().is_empty());
+    }
+
+    fn set(numbers: &[i64]) -> NumberSet {
+        numbers.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_set_algebra_of_overlapping_sets() {
+        let (a, b) = (set(&[1, 2, 3, 4]), set(&[3, 4, 5]));
+        assert_eq!(a.union(&b), set(&[1, 2, 3, 4, 5]));
+        assert_eq!(a.intersection(&b), set(&[3, 4]));
+        assert_eq!(a.difference(&b), set(&[1, 2]));
+        assert_eq!(b.difference(&a), set(&[5]));
+        assert_eq!(a.intersection(&b), b.intersection(&a));
+    }
+
+    #[test]
+    fn test_set_algebra_of_disjoint_and_empty_sets() {
+        let (a, b, empty) = (set(&[1, 2]), set(&[7, 8]), NumberSet::new());
+        assert_eq!(a.union(&b), set(&[1, 2, 7, 8]));
+        assert!(a.intersection(&b).is_empty());
+        assert_eq!(a.difference(&b), a);
+        assert_eq!(a.union(&empty), a);
+        assert!(a.intersection(&empty).is_empty());
+        assert_eq!(a.difference(&empty), a);
+        assert!(empty.difference(&a).is_empty());
+        assert!(empty.union(&empty).is_empty());
+    }
+
+    #[test]
+    fn test_merge_from_adds_the_other_set_in_place() {
+        let mut merged = set(&[1, 2, 3]);
+        merged.merge_from(&set(&[3, 4]));
+        assert_eq!(merged, set(&[1, 2, 3, 4]));
+        merged.merge_from(&NumberSet::new());
+        assert_eq!(merged.len(), 4);
+        let mut empty = NumberSet::new();
+        empty.merge_from(&merged);
+        assert_eq!(empty, merged);
+    }
+
+    #[test]
+    fn test_dense_set_behaves_like_the_hash_set() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut hashed = NumberSet::new();
+        // Start below the ranks drawn, so that the bits have to grow
+        let mut dense = DenseNumberSet::new(1000);
+        for _ in 0..20_000 {
+            let number = rng.gen_range(0..34_220);
+            match rng.gen_range(0..3) {
+                0 => assert_eq!(dense.add_number(number), hashed.add_number(number)),
+                1 => assert_eq!(dense.contains(number), hashed.contains(number)),
+                _ => {
+                    let numbers: Vec<i64> = (0..20).map(|_| rng.gen_range(0..34_220)).collect();
+                    assert_eq!(
+                        RankSet::check_and_insert_all(&mut dense, &numbers),
+                        hashed.check_and_insert_all(&numbers)
+                    );
+                }
+            }
+            assert_eq!(dense.len(), hashed.len());
+        }
+        let mut numbers: Vec<i64> = hashed.iter().copied().collect();
+        numbers.sort_unstable();
+        assert_eq!(dense.iter().collect::<Vec<_>>(), numbers);
+        assert!(!dense.contains(-1) && !dense.contains(1 << 40));
+        assert_eq!(RankSet::is_empty(&dense), hashed.is_empty());
+    }
+
+    #[test]
+    fn test_triplet_index_is_dense_for_small_lotteries_and_gives_its_triplets_back() {
+        let triplets: TripletSet = [0, 7, 34_219].into_iter().collect();
+        let mut small = TripletIndex::new(triplets.clone(), 1, 60);
+        let mut large = TripletIndex::new(triplets.clone(), 1, 1000);
+        assert!(matches!(small, TripletIndex::Dense(_)));
+        assert!(matches!(large, TripletIndex::Hashed(_)));
+        for index in [&mut small, &mut large] {
+            assert_eq!(index.len(), 3);
+            assert!(!index.check_and_insert_all(&[1, 7]));
+            assert!(index.check_and_insert_all(&[1, 2]));
+            assert!(index.remove_number(2));
+            assert!(!index.remove_number(2));
+            index.reserve(20);
+        }
+        let expected: TripletSet = [0, 1, 7, 34_219].into_iter().collect();
+        assert_eq!(small.to_set(), expected);
+        assert_eq!(small.into_set(), expected);
+        assert_eq!(large.into_set(), expected);
+    }
+
+    #[test]
+    fn test_dense_set_reads_and_writes_saved_sets() -> std::io::Result<()> {
+        let number_set = set(&[42, 7, 1000, 0, 15]);
+        let tmp_file = NamedTempFile::new()?;
+        number_set.save_to_file(tmp_file.path().to_str().unwrap())?;
+        let saved = fs::read_to_string(tmp_file.path())?;
+        let dense: DenseNumberSet = serde_json::from_str(&saved)?;
+        assert_eq!(dense, [0, 7, 15, 42, 1000].into_iter().collect());
+        assert_eq!(serde_json::to_string(&dense)?, saved);
+        assert!(serde_json::from_str::<DenseNumberSet>("[3,-1]").is_err());
+        // Sets differing in their allocated bits only are equal
+        assert_eq!(DenseNumberSet::new(10_000), DenseNumberSet::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_number() {
+        let mut number_set = NumberSet::new();
+        number_set.add_number(5);
+        assert!(number_set.remove_number(5)); // Present, removed
+        assert!(!number_set.remove_number(5)); // Already gone
+        assert!(!number_set.contains(5));
+        assert!(number_set.add_number(5)); // Can be added again
+    }
+
     #[test]
     fn test_save_and_load_from_file() -> std::io::Result<()> {
         // 1. Create and populate a NumberSet.
@@ -112,19 +1744,453 @@ mod tests {
 
         // 4. Load it back and verify contents.
         let loaded_number_set = NumberSet::load_from_file(tmp_path.to_str().unwrap())?;
-        assert_eq!(number_set.numbers, loaded_number_set.numbers);
+        assert_eq!(number_set, loaded_number_set);
 
         // No manual cleanup needed; `tmp_file` gets deleted automatically.
         Ok(())
     }
 
+    #[test]
+    fn test_save_to_file_sorts_the_numbers() -> std::io::Result<()> {
+        let mut number_set = NumberSet::new();
+        for number in [42, -3, 7, 1000, 0, 15] {
+            number_set.add_number(number);
+        }
+        let tmp_file = NamedTempFile::new()?;
+        number_set.save_to_file(tmp_file.path().to_str().unwrap())?;
+        assert_eq!(fs::read_to_string(tmp_file.path())?, "[-3,0,7,15,42,1000]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_from_file_reports_truncated_files() -> std::io::Result<()> {
+        let tmp_file = NamedTempFile::new()?;
+        fs::write(tmp_file.path(), "[3,1,4,1")?;
+        let Err(error) = NumberSet::<i64>::load_from_file(tmp_file.path().to_str().unwrap()) else {
+            panic!("a truncated file loads");
+        };
+        assert!(
+            matches!(
+                error,
+                SetFileError::InvalidJson {
+                    line: 1,
+                    column: 8,
+                    ..
+                }
+            ),
+            "{:?}",
+            error
+        );
+        assert_eq!(
+            error.to_string(),
+            "file appears corrupted: invalid JSON at line 1 column 8: EOF while parsing a list"
+        );
+        assert_eq!(
+            std::io::Error::from(error).kind(),
+            std::io::ErrorKind::InvalidData
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_errors_tell_missing_from_unreadable_files() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let missing = dir.path().join("missing.log");
+        let missing = missing.to_str().unwrap();
+        for result in [
+            NumberSet::<i64>::load_from_file(missing),
+            NumberSet::load_from_file_csv(missing),
+            NumberSet::load_from_file_binary(missing),
+            NumberSet::load(missing),
+        ] {
+            assert!(matches!(result, Err(SetFileError::NotFound)));
+        }
+        assert!(NumberSet::<i64>::load_or_default(missing)?.is_empty());
+        assert_eq!(
+            std::io::Error::from(SetFileError::NotFound).kind(),
+            std::io::ErrorKind::NotFound
+        );
+
+        // A directory exists but cannot be read as a file
+        let directory = dir.path().to_str().unwrap();
+        assert!(matches!(
+            NumberSet::<i64>::load(directory),
+            Err(SetFileError::Unreadable(_))
+        ));
+        assert!(matches!(
+            NumberSet::<i64>::load_or_default(directory),
+            Err(SetFileError::Unreadable(_))
+        ));
+
+        let present = dir.path().join("present.log");
+        set(&[2, 4]).save_to_file(present.to_str().unwrap())?;
+        assert_eq!(
+            NumberSet::load_or_default(present.to_str().unwrap())?,
+            set(&[2, 4])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_files_round_trip() -> std::io::Result<()> {
+        let tmp_file = NamedTempFile::new()?;
+        let path = tmp_file.path().to_str().unwrap();
+        for number_set in [
+            set(&[42, -3, 7, 1000, 0, 15, i64::MIN, i64::MAX]),
+            (0..34_220).step_by(3).collect(),
+            NumberSet::new(),
+        ] {
+            number_set.save_to_file_binary(path)?;
+            assert_eq!(NumberSet::load_from_file_binary(path)?, number_set);
+            assert_eq!(NumberSet::load(path)?, number_set);
+        }
+        // The magic, the version, the count of ranks and the ranks
+        set(&[3, 70]).save_to_file_binary(path)?;
+        assert_eq!(fs::read(path)?, b"RKNS\x01\x02\x06\x43");
+        // One byte per close rank, against six or so in JSON
+        let dense: NumberSet = (0..10_000).collect();
+        dense.save_to_file_binary(path)?;
+        assert!(fs::metadata(path)?.len() < 10_010);
+        Ok(())
+    }
+
+    #[test]
+    fn test_headers_round_trip_in_every_format() -> std::io::Result<()> {
+        let file = NamedTempFile::new()?;
+        let path = file.path().to_str().unwrap();
+        let number_set = set(&[3, 70, 34_219]);
+        let header = SetHeader::triplets(60);
+        for format in [SetFormat::Json, SetFormat::Csv, SetFormat::Binary] {
+            number_set.save_with_header(path, Some(format), &header)?;
+            let (loaded, found) = NumberSet::load_with_header(path).unwrap();
+            assert_eq!(loaded, number_set, "{:?}", format);
+            assert_eq!(found, Some(header), "{:?}", format);
+            assert_eq!(NumberSet::load(path).unwrap(), number_set);
+            assert!(!NumberSet::<i64>::load_checked(path, &header).unwrap().1);
+
+            // Sets saved without a header load as version 1, unchecked
+            number_set.save(path, Some(format))?;
+            assert_eq!(NumberSet::<i64>::load_with_header(path).unwrap().1, None);
+            assert!(NumberSet::<i64>::load_checked(path, &header).unwrap().1);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_sets_of_other_games_are_refused() -> std::io::Result<()> {
+        let file = NamedTempFile::new()?;
+        let path = file.path().to_str().unwrap();
+        let number_set = set(&[1, 2]);
+        for format in [SetFormat::Json, SetFormat::Csv, SetFormat::Binary] {
+            number_set.save_with_header(path, Some(format), &SetHeader::games(60))?;
+            assert!(matches!(
+                NumberSet::<i64>::load_checked(path, &SetHeader::triplets(60)),
+                Err(SetFileError::KindMismatch {
+                    expected: SetKind::Triplet,
+                    found: SetKind::Game
+                })
+            ));
+            let error = NumberSet::<i64>::load_checked(path, &SetHeader::games(61))
+                .expect_err("the lottery has another size");
+            assert!(matches!(error, SetFileError::ParameterMismatch { .. }));
+            assert_eq!(
+                error.to_string(),
+                "file holds the ranks of 6 numbers out of 60, not 6 out of 61 as configured"
+            );
+        }
+
+        // Versions this program does not know, in each format
+        fs::write(
+            path,
+            r#"{"version":3,"kind":"game","n":60,"k":6,"data":[1]}"#,
+        )?;
+        assert!(matches!(
+            NumberSet::<i64>::load(path),
+            Err(SetFileError::UnsupportedVersion(3))
+        ));
+        fs::write(
+            path,
+            "# rk_lottery set version=7 kind=game n=60 k=6\nrank\n1\n",
+        )?;
+        assert!(matches!(
+            NumberSet::<i64>::load(path),
+            Err(SetFileError::UnsupportedVersion(7))
+        ));
+        fs::write(path, b"RKNS\x03\x00")?;
+        assert!(matches!(
+            NumberSet::<i64>::load(path),
+            Err(SetFileError::UnsupportedVersion(3))
+        ));
+        fs::write(
+            path,
+            "# rk_lottery set version=2 kind=pair n=60 k=2\nrank\n",
+        )?;
+        assert!(matches!(
+            NumberSet::<i64>::load(path),
+            Err(SetFileError::Corrupted(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_tells_the_formats_apart() -> std::io::Result<()> {
+        let (json, binary) = (NamedTempFile::new()?, NamedTempFile::new()?);
+        let (json, binary) = (
+            json.path().to_str().unwrap(),
+            binary.path().to_str().unwrap(),
+        );
+        let number_set = set(&[5, 10]);
+        number_set.save_to_file(json)?;
+        number_set.save_to_file_binary(binary)?;
+        assert_eq!(NumberSet::load(json)?, number_set);
+        assert_eq!(NumberSet::load(binary)?, number_set);
+
+        let message = |result: Result<NumberSet, SetFileError>| result.unwrap_err().to_string();
+        assert!(matches!(
+            NumberSet::<i64>::load_from_file(binary),
+            Err(SetFileError::WrongFormat(_))
+        ));
+        assert_eq!(
+            message(NumberSet::load_from_file(binary)),
+            "file holds a binary set, not a JSON one: load it with load_from_file_binary"
+        );
+        assert_eq!(
+            message(NumberSet::load_from_file_binary(json)),
+            "file is not a binary set, it looks like a JSON set: load it with load_from_file"
+        );
+
+        let mut bytes = fs::read(binary)?;
+        bytes[4] = 9;
+        fs::write(binary, &bytes)?;
+        assert!(matches!(
+            NumberSet::<i64>::load(binary),
+            Err(SetFileError::UnsupportedVersion(9))
+        ));
+        assert_eq!(
+            message(NumberSet::load(binary)),
+            "file of version 9, which this program cannot read"
+        );
+        bytes[4] = 1;
+        bytes.pop();
+        fs::write(binary, &bytes)?;
+        let error = NumberSet::<i64>::load(binary).unwrap_err();
+        assert!(matches!(error, SetFileError::Corrupted(_)));
+        assert_eq!(
+            error.to_string(),
+            "file appears corrupted: the binary set ends early"
+        );
+        fs::write(json, "")?;
+        assert!(message(NumberSet::load(json)).starts_with("file appears corrupted: "));
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_or_garbage_binary_files_are_errors() -> std::io::Result<()> {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let file = NamedTempFile::new()?;
+        let path = file.path().to_str().unwrap();
+        let number_set = set(&[3, 70, 300, 34_219]);
+        number_set.save_with_header(path, Some(SetFormat::Binary), &SetHeader::triplets(60))?;
+        let bytes = fs::read(path)?;
+
+        // Cut anywhere, even inside the magic bytes
+        for len in 0..bytes.len() {
+            fs::write(path, &bytes[..len])?;
+            assert!(
+                NumberSet::<i64>::load_from_file_binary(path).is_err(),
+                "{} bytes of {} load",
+                len,
+                bytes.len()
+            );
+        }
+
+        let corrupted = |tail: &[u8]| {
+            let mut contents = BINARY_MAGIC.to_vec();
+            contents.extend_from_slice(tail);
+            fs::write(path, &contents)?;
+            Ok::<_, std::io::Error>(NumberSet::<i64>::load(path).unwrap_err().to_string())
+        };
+        assert_eq!(
+            corrupted(&[BINARY_VERSION, 0xff, 60, 3, 0])?,
+            "file appears corrupted: unknown kind of set 255"
+        );
+        assert_eq!(
+            corrupted(&[1, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01])?,
+            "file appears corrupted: a number takes more than 64 bits"
+        );
+        assert_eq!(
+            corrupted(&[1, 1, 4, 0, 0])?,
+            "file appears corrupted: 2 bytes after the last number"
+        );
+        // A huge count is not allocated for up front
+        assert_eq!(
+            corrupted(&[1, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f, 2])?,
+            "file appears corrupted: the binary set ends early"
+        );
+
+        // Random bytes, with or without the magic in front, never panic
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..2000 {
+            let mut contents: Vec<u8> = (0..rng.gen_range(0..32)).map(|_| rng.gen()).collect();
+            if rng.gen_bool(0.5) {
+                contents.splice(0..0, BINARY_MAGIC.iter().copied());
+            }
+            fs::write(path, &contents)?;
+            let _ = NumberSet::<i64>::load_from_file_binary(path);
+            let _ = NumberSet::<u32>::load(path);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_picks_the_format_of_the_extension_unless_told() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        let number_set = set(&[15, -3, 7]);
+
+        number_set.save(&path("games.csv"), None)?;
+        assert_eq!(fs::read_to_string(path("games.csv"))?, "rank\n-3\n7\n15\n");
+        assert_eq!(
+            NumberSet::load_from_file_csv(&path("games.csv"))?,
+            number_set
+        );
+        assert_eq!(NumberSet::load(&path("games.csv"))?, number_set);
+
+        number_set.save(&path("triplet_set.log"), None)?;
+        assert_eq!(fs::read_to_string(path("triplet_set.log"))?, "[-3,7,15]");
+        assert_eq!(NumberSet::load(&path("triplet_set.log"))?, number_set);
+
+        number_set.save(&path("games.json"), Some(SetFormat::Binary))?;
+        assert!(fs::read(path("games.json"))?.starts_with(BINARY_MAGIC));
+        assert_eq!(NumberSet::load(&path("games.json"))?, number_set);
+
+        NumberSet::new().save(&path("empty.csv"), None)?;
+        assert!(NumberSet::<i64>::load(&path("empty.csv"))?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_gz_sets_are_compressed_and_open_once_renamed() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        let number_set: NumberSet = (0..5_000).map(|number| number * 37).collect();
+        let header = SetHeader::triplets(60);
+
+        number_set.save_with_header(&path("triplet_set.log"), None, &header)?;
+        number_set.save_with_header(&path("triplet_set.log.gz"), None, &header)?;
+        assert!(
+            fs::metadata(path("triplet_set.log.gz"))?.len() * 2
+                < fs::metadata(path("triplet_set.log"))?.len()
+        );
+        assert_eq!(
+            NumberSet::load_checked(&path("triplet_set.log.gz"), &header).unwrap(),
+            (number_set, false)
+        );
+
+        // The extension under `.gz` decides the format, the magic bytes the decompression
+        let number_set = set(&[15, -3, 7]);
+        number_set.save(&path("games.csv.gz"), None)?;
+        fs::rename(path("games.csv.gz"), path("games.csv"))?;
+        assert_eq!(
+            NumberSet::load_from_file_csv(&path("games.csv"))?,
+            number_set
+        );
+        assert_eq!(NumberSet::load(&path("games.csv"))?, number_set);
+
+        let mut bytes = fs::read(path("games.csv"))?;
+        bytes.truncate(bytes.len() - 8);
+        fs::write(path("games.csv"), &bytes)?;
+        assert!(NumberSet::<i64>::load(&path("games.csv"))
+            .unwrap_err()
+            .to_string()
+            .starts_with("file is not a valid gzip stream: "));
+        Ok(())
+    }
+
+    #[test]
+    fn test_exported_games_rank_back_to_the_set() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("export.csv");
+        let path = path.to_str().unwrap();
+        let games = [
+            [3, 11, 22, 34, 45, 58],
+            [1, 2, 3, 4, 5, 6],
+            [7, 19, 23, 40, 51, 60],
+        ];
+        let mut game_set: NumberSet = games
+            .iter()
+            .map(|game| custom_utils::game2enum(game))
+            .collect();
+        let triplet_set: NumberSet = games
+            .iter()
+            .flat_map(|game| custom_utils::game2triplet_enums(game))
+            .collect();
+
+        let reranked = |k: usize| -> NumberSet {
+            let mut rdr = csv::Reader::from_path(path).unwrap();
+            assert_eq!(rdr.headers().unwrap().len(), k + 1);
+            rdr.records()
+                .map(|record| {
+                    let numbers: Vec<i64> = record
+                        .unwrap()
+                        .iter()
+                        .skip(1)
+                        .map(|number| number.parse().unwrap())
+                        .collect();
+                    assert_eq!(numbers.len(), k);
+                    custom_utils::subset2enum(&numbers, k).unwrap()
+                })
+                .collect()
+        };
+        assert!(game_set.export_games(60, 6, path)?.is_empty());
+        assert_eq!(reranked(6), game_set);
+        assert!(fs::read_to_string(path)?.starts_with("game,n1,n2,n3,n4,n5,n6\n1,1,2,3,4,5,6\n"));
+        assert!(triplet_set.export_games(60, 3, path)?.is_empty());
+        assert_eq!(reranked(3), triplet_set);
+
+        // Ranks of a larger lottery are reported, the others still exported
+        let too_large = custom_utils::binomial(50, 6);
+        game_set.add_number(too_large);
+        game_set.add_number(-2);
+        let out_of_range = game_set.export_games(50, 6, path)?;
+        let ranks: Vec<i64> = out_of_range.iter().map(|error| error.rank).collect();
+        let in_range: NumberSet = game_set
+            .iter()
+            .copied()
+            .filter(|&rank| rank >= 0 && rank < too_large)
+            .collect();
+        assert_eq!(ranks[0], -2);
+        assert!(ranks.contains(&too_large));
+        assert_eq!(ranks.len() + in_range.len(), game_set.len());
+        assert_eq!(reranked(6), in_range);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_reads_legacy_json_games_csv() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let legacy = dir.path().join("games.csv");
+        // What older versions saved to games.csv
+        fs::write(&legacy, "[3,1,4]")?;
+        let legacy = legacy.to_str().unwrap();
+        assert_eq!(NumberSet::load(legacy)?, set(&[1, 3, 4]));
+
+        fs::write(legacy, "rank\n1\nx\n")?;
+        assert_eq!(
+            NumberSet::<i64>::load(legacy).unwrap_err().to_string(),
+            "file appears corrupted: line 3 is not a number: 'x'"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_check_and_insert_all() {
         let mut number_set = NumberSet::new();
         let numbers_to_insert = vec![1, 2, 3, 4, 5];
 
         // Insert all numbers successfully
-        assert!(number_set.check_and_insert_all(numbers_to_insert.clone()));
+        assert!(number_set.check_and_insert_all(&numbers_to_insert));
 
         // Verify all numbers were added
         for &num in &numbers_to_insert {
@@ -133,6 +2199,152 @@ mod tests {
 
         // Attempt to insert a mix of existing and new numbers
         let new_numbers_to_insert = vec![6, 7, 8, 9, 5];
-        assert!(!number_set.check_and_insert_all(new_numbers_to_insert));
+        assert!(!number_set.check_and_insert_all(&new_numbers_to_insert));
+        assert_eq!(number_set, set(&[1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn test_triplet_sets_store_narrow_ranks_and_save_the_same_files() -> std::io::Result<()> {
+        let triplet_nos: Vec<i64> = (0..custom_utils::binomial(60, 3)).step_by(3).collect();
+        let wide: NumberSet = triplet_nos.iter().copied().collect();
+        let mut narrow = TripletSet::default();
+        assert!(narrow.check_and_insert_all(&triplet_nos));
+        assert!(!narrow.check_and_insert_all(&[1, 3]));
+        assert!(narrow.contains(3) && !narrow.contains(1));
+        assert!(!narrow.contains(-3) && !narrow.contains(i64::MAX));
+        assert!(!narrow.remove_number(-3));
+        assert_eq!(narrow.len(), wide.len());
+        assert!(narrow.stats().bytes * 10 < wide.stats().bytes * 7);
+
+        let dir = tempfile::tempdir()?;
+        for format in [SetFormat::Json, SetFormat::Csv, SetFormat::Binary] {
+            let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+            let header = SetHeader::triplets(60);
+            wide.save_with_header(&path("wide"), Some(format), &header)?;
+            narrow.save_with_header(&path("narrow"), Some(format), &header)?;
+            assert_eq!(fs::read(path("wide"))?, fs::read(path("narrow"))?);
+            let (loaded, legacy) = TripletSet::load_checked(&path("wide"), &header).unwrap();
+            assert_eq!((loaded, legacy), (narrow.clone(), false));
+        }
+
+        // Ranks of a file that do not fit are reported, not truncated
+        let path = dir.path().join("triplet_set.log");
+        fs::write(&path, "[1,4294967296]")?;
+        assert_eq!(
+            TripletSet::load(path.to_str().unwrap())
+                .unwrap_err()
+                .to_string(),
+            "file appears corrupted: rank 4294967296 does not fit in a set of u32"
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "rank -1 does not fit in a set of u32")]
+    fn test_adding_a_rank_that_does_not_fit_panics() {
+        TripletSet::default().add_number(-1);
+    }
+
+    #[test]
+    fn test_triplets_of_the_largest_lottery_fit() {
+        let max = i64::from(u32::MAX);
+        assert!(custom_utils::binomial(MAX_TRIPLET_NUMBERS, 3) - 1 <= max);
+        assert!(custom_utils::binomial(MAX_TRIPLET_NUMBERS + 1, 3) - 1 > max);
+    }
+
+    #[test]
+    fn test_capacity_hints() {
+        let mut set = NumberSet::<i64>::with_capacity(1000);
+        assert_eq!(set.len(), 0);
+        assert!(set.is_empty());
+        assert!(set.capacity() >= 1000);
+        let stats = set.stats();
+        assert_eq!((stats.len, stats.capacity), (0, set.capacity()));
+        assert!(stats.bytes > 1000 * 9);
+
+        for number in 0..10 {
+            set.add_number(number);
+        }
+        set.shrink_to_fit();
+        assert!(set.capacity() >= 10 && set.capacity() < 1000);
+        assert!(set.stats().bytes < stats.bytes);
+        set.reserve(5000);
+        assert!(set.capacity() >= 5010);
+        assert_eq!(set.len(), 10);
+        assert_eq!(NumberSet::new().stats().len, 0);
+        assert_eq!(
+            SetStats {
+                len: 3,
+                capacity: 3,
+                bytes: 3 << 20
+            }
+            .to_string(),
+            "3 numbers, about 3.0 MiB"
+        );
+        assert_eq!(
+            SetStats {
+                len: 3,
+                capacity: 3,
+                bytes: 1536
+            }
+            .to_string(),
+            "3 numbers, about 1.5 KiB"
+        );
+    }
+
+    #[test]
+    fn test_subset_index_keeps_the_first_owner() {
+        let mut index = SubsetIndex::new();
+        assert!(index.is_empty());
+        assert_eq!(index.insert(5, 0), None);
+        assert_eq!(index.insert(5, 3), Some(0));
+        assert_eq!(index.insert_all(&[6, 7, 8], 1), Ok(()));
+        assert_eq!(index.insert_all(&[9, 10, 7, 5], 2), Err((2, 1)));
+        assert_eq!(index.len(), 4);
+        // A rank repeated in the input is owned by the game being inserted, and rolled back
+        assert_eq!(index.insert_all(&[20, 21, 20], 4), Err((2, 4)));
+        assert_eq!(index.owner(20), None);
+        assert_eq!(
+            [5, 6, 8].map(|rank| index.owner(rank)),
+            [Some(0), Some(1), Some(1)]
+        );
+    }
+
+    #[test]
+    fn test_check_and_insert_all_rejects_internal_duplicates() {
+        let mut number_set = set(&[1, 2]);
+        assert!(!number_set.check_and_insert_all(&[10, 11, 12, 11, 13]));
+        assert_eq!(number_set, set(&[1, 2]));
+        assert!(!number_set.check_and_insert_all(&[7, 7]));
+        assert_eq!(number_set, set(&[1, 2]));
+        assert!(number_set.check_and_insert_all(&[10, 11, 12, 13]));
+        assert_eq!(number_set.len(), 6);
+
+        let mut dense = DenseNumberSet::new(100);
+        dense.add_number(1);
+        assert!(!RankSet::check_and_insert_all(&mut dense, &[64, 3, 200, 3]));
+        assert_eq!(dense.iter().collect::<Vec<_>>(), [1]);
+        assert_eq!(RankSet::len(&dense), 1);
+        assert!(!RankSet::check_and_insert_all(&mut dense, &[5, 1]));
+        assert_eq!(RankSet::len(&dense), 1);
+
+        // The default implementation of the trait, for a set that does not override it
+        struct Plain(NumberSet);
+        impl RankSet for Plain {
+            fn add_number(&mut self, number: i64) -> bool {
+                self.0.add_number(number)
+            }
+            fn contains(&self, number: i64) -> bool {
+                self.0.contains(number)
+            }
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+        }
+        let mut plain = Plain(set(&[1]));
+        assert!(!plain.check_and_insert_all(&[4, 5, 4]));
+        assert_eq!(plain.0, set(&[1]));
+        assert!(plain.check_and_insert_all(&[4, 5]));
+        assert_eq!(plain.0, set(&[1, 4, 5]));
     }
 }
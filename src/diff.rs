@@ -0,0 +1,171 @@
+use crate::custom_utils;
+use crate::game::Game;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+/// Two games of different files sharing at least `subset_size` numbers, with their rows
+/// (starting at 1) and numbers sorted.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Collision {
+    /// Row of the game in the first file.
+    pub row_a: usize,
+    /// Row of the game in the second file.
+    pub row_b: usize,
+    pub game_a: Vec<i64>,
+    pub game_b: Vec<i64>,
+    /// The numbers the two games have in common.
+    pub shared: Vec<i64>,
+}
+
+impl fmt::Display for Collision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rows {} and {} share {}: {} and {}",
+            self.row_a,
+            self.row_b,
+            Game::new(self.shared.clone()),
+            Game::new(self.game_a.clone()),
+            Game::new(self.game_b.clone())
+        )
+    }
+}
+
+/// Finds the pairs of games, one from each file, sharing `subset_size` numbers or more.
+///
+/// The `subset_size`-subsets of the games of `a` are indexed by combinadic number, then the
+/// games of `b` are looked up subset by subset, so the cost grows with the number of games
+/// rather than with the number of pairs.
+///
+/// # Arguments
+/// * `a` - The games of the first file, in order.
+/// * `b` - The games of the second file, in order.
+/// * `subset_size` - The number of numbers two games must share to collide (e.g., 3).
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+///
+/// # Returns
+/// * The collisions, by row of `b` then row of `a`, each pair reported once with all the
+///   numbers shared.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::diff::diff_games;
+/// use rk_lottery::game::Game;
+///
+/// let a = [Game::new(vec![1, 2, 3, 4, 5, 6])];
+/// let b = [Game::new(vec![10, 11, 12, 13, 14, 15]), Game::new(vec![6, 3, 1, 40, 50, 60])];
+/// let collisions = diff_games(&a, &b, 3, 1);
+/// assert_eq!(collisions.len(), 1);
+/// assert_eq!((collisions[0].row_a, collisions[0].row_b), (1, 2));
+/// assert_eq!(collisions[0].shared, vec![1, 3, 6]);
+/// ```
+pub fn diff_games<'a>(
+    a: &[Game],
+    b: impl IntoIterator<Item = &'a Game>,
+    subset_size: usize,
+    min_number: i64,
+) -> Vec<Collision> {
+    let sorted = |game: &Game| {
+        let mut numbers = game.to_vec();
+        numbers.sort_unstable();
+        numbers
+    };
+    let a: Vec<Vec<i64>> = a.iter().map(sorted).collect();
+    // The rows of `a` holding each subset, by combinadic number
+    let mut subset_rows: HashMap<i64, Vec<usize>> = HashMap::new();
+    for (index, game) in a.iter().enumerate() {
+        for subset in custom_utils::game2ksubsets(game, subset_size) {
+            if let Ok(rank) = custom_utils::subset2enum_from(&subset, subset_size, min_number) {
+                subset_rows.entry(rank).or_default().push(index);
+            }
+        }
+    }
+
+    let mut collisions = Vec::new();
+    for (index_b, game_b) in b.into_iter().enumerate() {
+        let game_b = sorted(game_b);
+        let rows_a: BTreeSet<usize> = custom_utils::game2ksubsets(&game_b, subset_size)
+            .iter()
+            .filter_map(|subset| {
+                custom_utils::subset2enum_from(subset, subset_size, min_number)
+                    .ok()
+                    .and_then(|rank| subset_rows.get(&rank))
+            })
+            .flatten()
+            .copied()
+            .collect();
+        for index_a in rows_a {
+            let game_a = &a[index_a];
+            let shared: Vec<i64> = game_a
+                .iter()
+                .copied()
+                .filter(|number| game_b.contains(number))
+                .collect();
+            // Ranks of numbers above the lottery may collide, so the shared numbers decide
+            if shared.len() >= subset_size {
+                collisions.push(Collision {
+                    row_a: index_a + 1,
+                    row_b: index_b + 1,
+                    game_a: game_a.clone(),
+                    game_b: game_b.clone(),
+                    shared,
+                });
+            }
+        }
+    }
+    collisions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn games(rows: &[[i64; 6]]) -> Vec<Game> {
+        rows.iter().map(|row| Game::new(row.to_vec())).collect()
+    }
+
+    #[test]
+    fn test_disjoint_files_do_not_collide() {
+        let a = games(&[[1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12]]);
+        let b = games(&[[1, 2, 7, 8, 13, 14], [20, 21, 22, 23, 24, 25]]);
+        assert!(diff_games(&a, &b, 3, 1).is_empty());
+        assert_eq!(diff_games(&a, &b, 2, 1).len(), 2);
+    }
+
+    #[test]
+    fn test_each_pair_is_reported_once_with_every_shared_number() {
+        let a = games(&[
+            [1, 2, 3, 4, 5, 6],
+            [30, 31, 32, 33, 34, 35],
+            [1, 2, 3, 40, 41, 42],
+        ]);
+        let b = games(&[[6, 5, 4, 3, 50, 51], [1, 2, 3, 31, 32, 33]]);
+        let collisions = diff_games(&a, &b, 3, 1);
+        let pairs: Vec<(usize, usize, Vec<i64>)> = collisions
+            .iter()
+            .map(|collision| (collision.row_a, collision.row_b, collision.shared.clone()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                (1, 1, vec![3, 4, 5, 6]),
+                (1, 2, vec![1, 2, 3]),
+                (2, 2, vec![31, 32, 33]),
+                (3, 2, vec![1, 2, 3]),
+            ]
+        );
+        assert_eq!(
+            collisions[0].to_string(),
+            "rows 1 and 1 share 03-04-05-06: 01-02-03-04-05-06 and 03-04-05-06-50-51"
+        );
+        assert_eq!(diff_games(&a, &b, 4, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_zero_based_lottery() {
+        let a = games(&[[0, 1, 2, 3, 4, 5]]);
+        let b = games(&[[0, 1, 2, 10, 11, 12]]);
+        assert_eq!(diff_games(&a, &b, 3, 0)[0].shared, vec![0, 1, 2]);
+    }
+}
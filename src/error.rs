@@ -0,0 +1,333 @@
+use crate::generation::{GenError, Generated};
+use crate::manifest::Rejections;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+/// Exit code of a run interrupted by Ctrl-C.
+pub const INTERRUPTED_EXIT_CODE: u8 = 130;
+
+/// An error ending a run of the program, each kind exiting with its own code.
+#[derive(Debug)]
+pub enum Error {
+    /// The command line arguments are not understood.
+    Usage(String),
+    /// The configuration cannot be read, parsed or used.
+    Config(String),
+    /// A game breaks the constraints or shares a triplet.
+    Validation(String),
+    /// The requested games could not be generated.
+    Generation(GenError),
+    /// A file could not be read or written.
+    Io { path: String, source: io::Error },
+    /// A check ran to completion and failed.
+    Check(String),
+    /// A tool ran to completion and changed its input, e.g. `repair` writing the file cleaned,
+    /// the changes told on stdout rather than as an error.
+    Changed(String),
+    /// The run was interrupted, after saving the games found so far.
+    Interrupted {
+        games: usize,
+        requested: usize,
+        /// The batch interrupted, for a run of several batches.
+        batch: Option<usize>,
+    },
+}
+
+impl Error {
+    /// The process exit code of the error: 2 for usage and configuration errors, 3 for
+    /// invalid games or saved sets, 4 when the games cannot be generated, 5 for file errors,
+    /// 130 (as for a process killed by SIGINT) for an interrupted run and 1 otherwise, e.g.
+    /// for a failed check or a file changed by `repair`.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Usage(_) | Error::Config(_) => 2,
+            Error::Validation(_) => 3,
+            Error::Generation(error) => match error {
+                GenError::InvalidInitialGame { .. }
+                | GenError::RepeatedTriplets { .. }
+                | GenError::DuplicateInitialGame { .. }
+                | GenError::StoredTriplet { .. }
+                | GenError::Inconsistent { .. } => 3,
+                GenError::Unsupported(_) => 2,
+                GenError::Infeasible { .. }
+                | GenError::UsageInfeasible { .. }
+                | GenError::OverlapInfeasible { .. }
+                | GenError::Exhausted { .. }
+                | GenError::OutOfAttempts { .. } => 4,
+                GenError::ThreadPool(_) => 1,
+                GenError::Cancelled { .. } => INTERRUPTED_EXIT_CODE,
+            },
+            Error::Io { .. } => 5,
+            Error::Check(_) | Error::Changed(_) => 1,
+            Error::Interrupted { .. } => INTERRUPTED_EXIT_CODE,
+        }
+    }
+
+    /// The kind of the error, as named in the summary of `--exit-summary`: `usage`, `config`,
+    /// `validation`, `generation`, `io`, `check`, `changed` or `interrupted`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Usage(_) => "usage",
+            Error::Config(_) => "config",
+            Error::Validation(_) => "validation",
+            Error::Generation(_) => "generation",
+            Error::Io { .. } => "io",
+            Error::Check(_) => "check",
+            Error::Changed(_) => "changed",
+            Error::Interrupted { .. } => "interrupted",
+        }
+    }
+
+    /// Wraps an error met while reading or writing `path`.
+    pub fn io(path: &str, source: impl Into<io::Error>) -> Self {
+        Error::Io {
+            path: path.to_string(),
+            source: source.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) | Error::Config(message) | Error::Validation(message) => {
+                write!(f, "{}", message)
+            }
+            Error::Generation(error) => write!(f, "{}", error),
+            Error::Io { path, source } => write!(f, "{}: {}", path, source),
+            Error::Check(message) | Error::Changed(message) => write!(f, "{}", message),
+            Error::Interrupted {
+                games,
+                requested,
+                batch: None,
+            } => write!(
+                f,
+                "Interrupted after {} of the {} games, which were saved: run again with --resume --games {} to generate the rest",
+                games, requested, requested
+            ),
+            Error::Interrupted {
+                games,
+                requested,
+                batch: Some(batch),
+            } => write!(
+                f,
+                "Interrupted in batch {} after {} of its {} games, which were saved along with the batches before it",
+                batch, games, requested
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Generation(error) => Some(error),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<GenError> for Error {
+    fn from(error: GenError) -> Self {
+        Error::Generation(error)
+    }
+}
+
+/// How a run of the program ended, written as a single JSON object by `--exit-summary` for the
+/// scripts running the program.
+#[derive(Debug, Default, Serialize)]
+pub struct ExitSummary {
+    /// `ok`, `interrupted` for a run stopped once its games were saved, `changed` for a tool
+    /// having changed its input, or `error`.
+    pub status: &'static str,
+    /// The exit code of the process.
+    pub exit_code: u8,
+    /// Number of games generated, initial games included, over every batch; `None` for a run
+    /// not generating games.
+    pub games: Option<usize>,
+    /// Number of candidates checked, over every batch.
+    pub attempts: Option<u64>,
+    /// Number of initial games left out for breaking the constraints, over every batch.
+    pub skipped_initial: Option<usize>,
+    /// The candidates turned down, by reason, over every batch.
+    pub rejections: Option<Rejections>,
+    /// The files written by the run, in order.
+    pub outputs: Vec<String>,
+    /// The seed the games were drawn with, `None` when drawn from OS entropy or when the
+    /// configuration was not read.
+    pub seed: Option<u64>,
+    /// What went wrong, for a run ending in an error.
+    pub error: Option<ErrorSummary>,
+}
+
+/// An error ending a run, as told by `ExitSummary`.
+#[derive(Debug, Serialize)]
+pub struct ErrorSummary {
+    /// The kind of the error; see `Error::kind`.
+    pub kind: &'static str,
+    /// The message printed for the error.
+    pub message: String,
+}
+
+impl ExitSummary {
+    /// Adds the games of a run, or of a batch, and the files written for them.
+    pub fn add_run(&mut self, generated: &Generated, outputs: impl IntoIterator<Item = String>) {
+        *self.games.get_or_insert(0) += generated.games.len();
+        *self.attempts.get_or_insert(0) += generated.stats.attempts;
+        *self.skipped_initial.get_or_insert(0) += generated.stats.skipped_initial;
+        let rejections = self.rejections.get_or_insert_with(|| Rejections {
+            duplicate_games: 0,
+            shared_triplets: 0,
+            constraints: BTreeMap::new(),
+        });
+        rejections.duplicate_games += generated.stats.duplicate_games;
+        rejections.shared_triplets += generated.stats.shared_triplets;
+        for (name, count) in &generated.stats.constraint_rejections {
+            *rejections.constraints.entry(name.clone()).or_default() += count;
+        }
+        self.outputs.extend(outputs);
+    }
+
+    /// Records how the run ended. The games found before a generation error telling them are
+    /// counted when no run was added.
+    pub fn finish(&mut self, result: &Result<(), Error>) {
+        let Err(error) = result else {
+            self.status = "ok";
+            self.exit_code = 0;
+            return;
+        };
+        self.status = match error {
+            Error::Interrupted { .. } => "interrupted",
+            Error::Changed(_) => "changed",
+            _ => "error",
+        };
+        self.exit_code = error.exit_code();
+        if let Error::Generation(
+            GenError::Exhausted { generated, .. } | GenError::OutOfAttempts { generated, .. },
+        ) = error
+        {
+            self.games.get_or_insert(*generated);
+        }
+        self.error = Some(ErrorSummary {
+            kind: error.kind(),
+            message: error.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::TripletConflict;
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(Error::Config("bad".to_string()).exit_code(), 2);
+        assert_eq!(
+            Error::from(GenError::RepeatedTriplets {
+                conflicts: vec![TripletConflict {
+                    earlier: 0,
+                    index: 1,
+                    triplets: vec![vec![1, 2, 3]],
+                }],
+            })
+            .exit_code(),
+            3
+        );
+        assert_eq!(
+            Error::from(GenError::Exhausted {
+                generated: 1,
+                requested: 2,
+            })
+            .exit_code(),
+            4
+        );
+        assert_eq!(
+            Error::from(GenError::OutOfAttempts {
+                generated: 1,
+                requested: 2,
+                max_attempts: 1000,
+            })
+            .exit_code(),
+            4
+        );
+        assert_eq!(
+            Error::io("out.csv", io::Error::from(io::ErrorKind::StorageFull)).exit_code(),
+            5
+        );
+        assert_eq!(
+            Error::Interrupted {
+                games: 1,
+                requested: 2,
+                batch: None,
+            }
+            .exit_code(),
+            130
+        );
+    }
+
+    #[test]
+    fn test_summary_tells_how_the_run_ended() {
+        let mut summary = ExitSummary::default();
+        summary.finish(&Ok(()));
+        assert_eq!((summary.status, summary.exit_code), ("ok", 0));
+        assert!(summary.error.is_none());
+
+        let mut summary = ExitSummary::default();
+        summary.finish(&Err(Error::from(GenError::Exhausted {
+            generated: 7,
+            requested: 9,
+        })));
+        assert_eq!((summary.status, summary.exit_code), ("error", 4));
+        assert_eq!(summary.games, Some(7));
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["error"]["kind"], "generation");
+        assert!(json["error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("No more valid games exist"));
+        assert!(json["attempts"].is_null());
+
+        let mut summary = ExitSummary::default();
+        summary.finish(&Err(Error::Interrupted {
+            games: 1,
+            requested: 2,
+            batch: None,
+        }));
+        assert_eq!((summary.status, summary.exit_code), ("interrupted", 130));
+
+        let mut summary = ExitSummary::default();
+        summary.finish(&Err(Error::Changed(
+            "u.csv: 1 of 1 rows changed".to_string(),
+        )));
+        assert_eq!((summary.status, summary.exit_code), ("changed", 1));
+        assert_eq!(summary.error.unwrap().kind, "changed");
+    }
+
+    #[test]
+    fn test_messages_are_single_lines() {
+        let error = Error::from(GenError::RepeatedTriplets {
+            conflicts: vec![
+                TripletConflict {
+                    earlier: 0,
+                    index: 2,
+                    triplets: vec![vec![1, 2, 3], vec![1, 2, 4]],
+                },
+                TripletConflict {
+                    earlier: 1,
+                    index: 2,
+                    triplets: vec![vec![5, 6, 7]],
+                },
+            ],
+        });
+        assert_eq!(
+            error.to_string(),
+            "Initial games share triplets: games #1 and #3 share 01-02-03, 01-02-04; games #2 and #3 share 05-06-07"
+        );
+        let error = Error::io("out.csv", io::Error::other("disk full"));
+        assert_eq!(error.to_string(), "out.csv: disk full");
+    }
+}
@@ -0,0 +1,252 @@
+use crate::custom_utils;
+
+/// Fraction of the upper bound above which a request is reported as close to infeasible.
+pub const WARNING_RATIO: f64 = 0.5;
+
+/// How a requested number of games compares with what the constraints allow.
+#[derive(Debug, PartialEq)]
+pub enum Feasibility {
+    /// Comfortably below the upper bound.
+    Feasible,
+    /// Within reach of the upper bound: generation may become very slow or fail.
+    NearBound { bound: u64 },
+    /// Above the upper bound: generation cannot succeed.
+    Infeasible { bound: u64 },
+}
+
+/// Computes an upper bound on the number of games that can pairwise share no triplet.
+///
+/// Every game uses C(`numbers_per_game`, 3) triplets of its own out of the C(n', 3)
+/// triplets available in `[min_desired_number, max_number]`, where n' is the size of that
+/// range, so no more than C(n', 3) / C(`numbers_per_game`, 3) games can coexist.
+///
+/// # Arguments
+/// * `min_desired_number` - The minimum number allowed in a game.
+/// * `max_number` - The maximum number in a game (e.g., 60).
+/// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+///
+/// # Returns
+/// * The upper bound on the number of games.
+pub fn triplet_upper_bound(min_desired_number: i64, max_number: i64, numbers_per_game: i64) -> u64 {
+    let span = max_number - min_desired_number + 1;
+    if span < numbers_per_game {
+        return 0;
+    }
+    let triplets_per_game = custom_utils::binomial(numbers_per_game, 3).max(1);
+    let by_triplets = custom_utils::binomial(span, 3) / triplets_per_game;
+    let by_games = custom_utils::binomial(span, numbers_per_game);
+    by_triplets.min(by_games) as u64
+}
+
+/// Counts the games of `numbers_per_game` numbers in `[min_desired_number, max_number]`, the
+/// candidates a run draws from before the other constraints.
+///
+/// # Arguments
+/// * `min_desired_number` - The minimum number allowed in a game.
+/// * `max_number` - The maximum number in a game (e.g., 60).
+/// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+pub fn valid_games(min_desired_number: i64, max_number: i64, numbers_per_game: i64) -> u64 {
+    let span = max_number - min_desired_number + 1;
+    if span < numbers_per_game {
+        return 0;
+    }
+    custom_utils::binomial(span, numbers_per_game) as u64
+}
+
+/// Computes an upper bound on the number of games that pairwise share at most `max_overlap`
+/// numbers.
+///
+/// Every subset of `max_overlap` + 1 numbers is then in one game at most, and every game holds
+/// C(`numbers_per_game`, `max_overlap` + 1) of the C(n', `max_overlap` + 1) of them, like the
+/// triplets of `triplet_upper_bound`.
+///
+/// # Arguments
+/// * `min_desired_number` - The minimum number allowed in a game.
+/// * `max_number` - The maximum number in a game (e.g., 25).
+/// * `numbers_per_game` - The number of numbers per game (e.g., 15).
+/// * `max_overlap` - The most numbers two games may have in common (e.g., 10).
+pub fn overlap_upper_bound(
+    min_desired_number: i64,
+    max_number: i64,
+    numbers_per_game: i64,
+    max_overlap: i64,
+) -> u64 {
+    let span = max_number - min_desired_number + 1;
+    if span < numbers_per_game {
+        return 0;
+    }
+    let subset_size = max_overlap + 1;
+    let by_games = custom_utils::binomial(span, numbers_per_game);
+    if subset_size > numbers_per_game {
+        return by_games as u64;
+    }
+    let by_subsets = custom_utils::binomial(span, subset_size)
+        / custom_utils::binomial(numbers_per_game, subset_size);
+    by_subsets.min(by_games) as u64
+}
+
+/// Computes an upper bound on the number of games when each of the `numbers` numbers may be
+/// played in at most `max_number_usage` games.
+///
+/// # Arguments
+/// * `numbers` - The number of numbers allowed in a game.
+/// * `max_number_usage` - The maximum games each number may be played in.
+/// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+pub fn usage_upper_bound(
+    numbers: usize,
+    max_number_usage: usize,
+    numbers_per_game: usize,
+) -> usize {
+    numbers * max_number_usage / numbers_per_game
+}
+
+/// Computes the probability that a random game shares none of its triplets with `used`
+/// triplets taken at random among `triplets`, which is how a run sees the triplets of the
+/// games accepted so far.
+///
+/// The triplets of the game are drawn without replacement, so this is the hypergeometric
+/// probability C(`triplets` - `used`, t) / C(`triplets`, t), t being the triplets per game.
+///
+/// # Arguments
+/// * `triplets` - The number of triplets of the desired numbers.
+/// * `used` - The number of triplets already taken.
+/// * `triplets_per_game` - The number of triplets of a game (e.g., 20).
+pub fn triplet_free_probability(triplets: i64, used: i64, triplets_per_game: i64) -> f64 {
+    if triplets - used < triplets_per_game {
+        return 0.0;
+    }
+    (0..triplets_per_game)
+        .map(|i| (triplets - used - i) as f64 / (triplets - i) as f64)
+        .product()
+}
+
+/// Estimates the attempts expected for each game of a run, in the manner of the coupon
+/// collector: the game after `k` accepted ones takes 1 / p attempts, p being the probability
+/// that a candidate passes the constraints and avoids the triplets of those `k` games.
+///
+/// For games of 3 numbers, each game being one triplet drawn uniformly, this is the coupon
+/// collector exactly; for bigger games it overlooks that the triplets of a game overlap.
+///
+/// # Arguments
+/// * `start` - The games the run starts from, e.g. its initial games.
+/// * `no_of_games` - The number of games once the run is over.
+/// * `min_desired_number` - The minimum number allowed in a game.
+/// * `max_number` - The maximum number in a game (e.g., 60).
+/// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+/// * `pass_rate` - The fraction of the candidates passing the constraints, 1 without any.
+///
+/// # Returns
+/// * The expected attempts of the games after the first `start`, in order, infinite for a
+///   game that cannot be expected to be found.
+pub fn expected_attempts(
+    start: usize,
+    no_of_games: usize,
+    min_desired_number: i64,
+    max_number: i64,
+    numbers_per_game: i64,
+    pass_rate: f64,
+) -> Vec<f64> {
+    let span = (max_number - min_desired_number + 1).max(0);
+    let triplets = custom_utils::binomial(span, 3);
+    let triplets_per_game = custom_utils::binomial(numbers_per_game, 3);
+    (start..no_of_games)
+        .map(|accepted| {
+            let used = accepted as i64 * triplets_per_game;
+            1.0 / (pass_rate * triplet_free_probability(triplets, used, triplets_per_game))
+        })
+        .collect()
+}
+
+/// Compares a requested number of games with the upper bound.
+///
+/// # Arguments
+/// * `no_of_games` - The number of games requested.
+/// * `bound` - The upper bound on the number of games.
+pub fn check_feasibility(no_of_games: usize, bound: u64) -> Feasibility {
+    let no_of_games = no_of_games as u64;
+    if no_of_games > bound {
+        Feasibility::Infeasible { bound }
+    } else if no_of_games as f64 >= WARNING_RATIO * bound as f64 {
+        Feasibility::NearBound { bound }
+    } else {
+        Feasibility::Feasible
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triplet_upper_bound() {
+        assert_eq!(triplet_upper_bound(1, 60, 6), 1711); // 34220 / 20
+        assert_eq!(triplet_upper_bound(31, 60, 6), 203); // C(30, 3) = 4060, / 20
+        assert_eq!(triplet_upper_bound(0, 59, 6), 1711); // 60 numbers starting at 0
+        assert_eq!(triplet_upper_bound(55, 60, 6), 1); // A single possible game
+        assert_eq!(triplet_upper_bound(56, 60, 6), 0); // Not even one game
+    }
+
+    #[test]
+    fn test_overlap_upper_bound() {
+        assert_eq!(overlap_upper_bound(1, 25, 15, 10), 3265); // C(25, 11) = 4457400, / 1365
+                                                              // Sharing at most 2 numbers is sharing no triplet
+        assert_eq!(
+            overlap_upper_bound(1, 60, 6, 2),
+            triplet_upper_bound(1, 60, 6)
+        );
+        assert_eq!(overlap_upper_bound(1, 25, 15, 15), valid_games(1, 25, 15));
+        assert_eq!(overlap_upper_bound(12, 25, 15, 10), 0);
+    }
+
+    #[test]
+    fn test_valid_games_and_usage_bound() {
+        assert_eq!(valid_games(1, 60, 6), 50_063_860);
+        assert_eq!(valid_games(55, 60, 6), 1);
+        assert_eq!(valid_games(56, 60, 6), 0);
+        assert_eq!(usage_upper_bound(30, 10, 6), 50);
+        assert_eq!(usage_upper_bound(31, 1, 6), 5);
+    }
+
+    #[test]
+    fn test_expected_attempts_of_tiny_lotteries() {
+        // Games of 3 of 5 numbers are the 10 coupons of the coupon collector: the k-th new one
+        // takes 10 / (10 - k) draws, 10 * H(10) for all of them
+        let attempts = expected_attempts(0, 10, 1, 5, 3, 1.0);
+        let coupons: Vec<f64> = (0..10).map(|k| 10.0 / (10 - k) as f64).collect();
+        assert_eq!(attempts, coupons);
+        let total: f64 = attempts.iter().sum();
+        assert!((total - 29.289_682_539_682_54).abs() < 1e-9);
+        // Half the candidates breaking the constraints doubles every wait
+        assert_eq!(expected_attempts(4, 5, 1, 5, 3, 0.5), vec![10.0 / 3.0]);
+
+        // 6 of 6 numbers: the first game is the only one
+        assert_eq!(
+            expected_attempts(0, 2, 1, 6, 6, 1.0),
+            vec![1.0, f64::INFINITY]
+        );
+        // 6 of 8 numbers: C(36, 20) / C(56, 20) of the candidates avoid the first game
+        let attempts = expected_attempts(1, 2, 1, 8, 6, 1.0);
+        let expected = 9.302_120_612_423_637e-6;
+        assert!((1.0 / attempts[0] - expected).abs() < expected * 1e-12);
+        assert_eq!(triplet_free_probability(56, 56, 20), 0.0);
+    }
+
+    #[test]
+    fn test_check_feasibility() {
+        let bound = triplet_upper_bound(31, 60, 6);
+        assert_eq!(check_feasibility(28, bound), Feasibility::Feasible);
+        assert_eq!(
+            check_feasibility(102, bound),
+            Feasibility::NearBound { bound }
+        );
+        // Exactly at the bound is not ruled out, but warned about
+        assert_eq!(
+            check_feasibility(203, bound),
+            Feasibility::NearBound { bound }
+        );
+        assert_eq!(
+            check_feasibility(204, bound),
+            Feasibility::Infeasible { bound }
+        );
+    }
+}
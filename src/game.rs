@@ -0,0 +1,639 @@
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// A lottery game: the numbers of a single ticket, with the label telling whose ticket it is,
+/// if any.
+///
+/// Games can be written as human-friendly strings such as `"03-11-22-34-45-58"`,
+/// `"3,11,22,34,45,58"` or `"3 11 22 34 45 58"`, and are displayed zero-padded and
+/// dash-separated, without their label.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Game {
+    numbers: Vec<i64>,
+    label: Option<String>,
+}
+
+impl Game {
+    /// Creates an unlabeled game from its numbers, kept in the given order.
+    pub fn new(numbers: Vec<i64>) -> Self {
+        Self {
+            numbers,
+            label: None,
+        }
+    }
+
+    /// Returns the numbers of the game.
+    pub fn numbers(&self) -> &[i64] {
+        &self.numbers
+    }
+
+    /// Returns the label of the game, e.g. the member of the pool the ticket is for.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns the game labeled `label`, in place of its label, if any.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Returns the game with its numbers in ascending order, keeping its label.
+    pub fn sorted(mut self) -> Self {
+        self.numbers.sort_unstable();
+        self
+    }
+
+    /// Returns the game with its numbers in ascending order, each one once, keeping its label.
+    pub fn deduped(mut self) -> Self {
+        self.numbers.sort_unstable();
+        self.numbers.dedup();
+        self
+    }
+
+    /// Returns the figures of the numbers of the game, for a lottery of the numbers from
+    /// `min_number` to `max_number`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rk_lottery::game::{Game, GameStats};
+    ///
+    /// let stats = Game::new(vec![33, 3, 4, 5, 41, 60]).stats(1, 60);
+    /// assert_eq!(
+    ///     stats,
+    ///     GameStats { sum: 146, odd: 4, low: 3, max_run: 3, min: 3, max: 60 }
+    /// );
+    /// ```
+    pub fn stats(&self, min_number: i64, max_number: i64) -> GameStats {
+        let mut numbers = self.numbers.clone();
+        numbers.sort_unstable();
+        numbers.dedup();
+        let mut max_run = numbers.len().min(1);
+        let mut run = max_run;
+        for pair in numbers.windows(2) {
+            run = if pair[1] == pair[0] + 1 { run + 1 } else { 1 };
+            max_run = max_run.max(run);
+        }
+        GameStats {
+            sum: self.numbers.iter().sum(),
+            odd: self
+                .numbers
+                .iter()
+                .filter(|&&x| x.rem_euclid(2) == 1)
+                .count(),
+            // Below the midpoint: in the lower half of the numbers, e.g. up to 30 of 1 to 60
+            low: self
+                .numbers
+                .iter()
+                .filter(|&&x| 2 * x < min_number + max_number)
+                .count(),
+            max_run,
+            min: numbers.first().copied().unwrap_or_default(),
+            max: numbers.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Figures of the numbers of a game, written along with it by `output_stats_columns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameStats {
+    /// The sum of the numbers.
+    pub sum: i64,
+    /// How many numbers are odd.
+    pub odd: usize,
+    /// How many numbers are below the midpoint of the lottery, the others being high.
+    pub low: usize,
+    /// The length of the longest run of consecutive numbers, e.g. 3 for 04-05-06.
+    pub max_run: usize,
+    /// The smallest number, 0 for a game without numbers.
+    pub min: i64,
+    /// The largest number, 0 for a game without numbers.
+    pub max: i64,
+}
+
+impl GameStats {
+    /// Names of the columns of the figures, in the order of `values`.
+    pub const COLUMNS: [&'static str; 6] = ["sum", "odd", "low", "max_run", "min", "max"];
+
+    /// The figures, in the order of `COLUMNS`.
+    pub fn values(&self) -> [i64; 6] {
+        [
+            self.sum,
+            self.odd as i64,
+            self.low as i64,
+            self.max_run as i64,
+            self.min,
+            self.max,
+        ]
+    }
+}
+
+impl Deref for Game {
+    type Target = [i64];
+
+    fn deref(&self) -> &Self::Target {
+        &self.numbers
+    }
+}
+
+impl From<Vec<i64>> for Game {
+    fn from(numbers: Vec<i64>) -> Self {
+        Self::new(numbers)
+    }
+}
+
+impl From<Game> for Vec<i64> {
+    fn from(game: Game) -> Self {
+        game.numbers
+    }
+}
+
+impl FromIterator<i64> for Game {
+    fn from_iter<I: IntoIterator<Item = i64>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+/// Labels the games without a label, in order: the names of `pool` no game holds yet come
+/// first, then `auto-<index>`, the 1-based index of the game in `games`.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::game::{label_games, Game};
+///
+/// let mut games = vec![
+///     Game::new(vec![1, 2, 3, 4, 5, 6]).with_label("Maria"),
+///     Game::new(vec![1, 7, 8, 9, 10, 11]),
+///     Game::new(vec![2, 7, 12, 13, 14, 15]),
+/// ];
+/// label_games(&mut games, &["Maria".to_string(), "João".to_string()]);
+/// let labels: Vec<_> = games.iter().map(|game| game.label().unwrap()).collect();
+/// assert_eq!(labels, ["Maria", "João", "auto-3"]);
+/// ```
+pub fn label_games(games: &mut [Game], pool: &[String]) {
+    let taken: Vec<&str> = games.iter().filter_map(Game::label).collect();
+    let mut names: Vec<String> = pool
+        .iter()
+        .filter(|name| !taken.contains(&name.as_str()))
+        .cloned()
+        .collect();
+    names.reverse();
+    for (index, game) in games.iter_mut().enumerate() {
+        if game.label.is_none() {
+            game.label = Some(names.pop().unwrap_or_else(|| format!("auto-{}", index + 1)));
+        }
+    }
+}
+
+/// A game represented as a bitmask, with bit `x - 1` set for each number `x`.
+///
+/// Only numbers from 1 to `GameMask::MAX_NUMBER` can be represented, which covers the
+/// usual lotteries; larger lotteries keep using the `Game` representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct GameMask(u64);
+
+impl GameMask {
+    /// The largest number a mask can hold.
+    pub const MAX_NUMBER: i64 = 64;
+
+    /// Converts game numbers to a mask.
+    ///
+    /// # Returns
+    /// * `None` if a number lies outside `1..=GameMask::MAX_NUMBER`. Repeated numbers
+    ///   collapse into a single bit, which `len` can be compared against to detect them.
+    pub fn from_numbers(numbers: &[i64]) -> Option<Self> {
+        numbers.iter().try_fold(Self(0), |mask, &x| {
+            (1..=Self::MAX_NUMBER)
+                .contains(&x)
+                .then(|| Self(mask.0 | 1 << (x - 1)))
+        })
+    }
+
+    /// Returns the mask of every number in `[min, max]`, clamped to the representable range.
+    pub fn range(min: i64, max: i64) -> Self {
+        let min = min.max(1);
+        let max = max.min(Self::MAX_NUMBER);
+        if min > max {
+            return Self(0);
+        }
+        let upper = if max == 64 { u64::MAX } else { (1 << max) - 1 };
+        Self(upper & !((1 << (min - 1)) - 1))
+    }
+
+    /// Returns the number of distinct numbers in the mask.
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Returns whether the mask holds no numbers.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns whether `number` is in the mask.
+    pub fn contains(self, number: i64) -> bool {
+        (1..=Self::MAX_NUMBER).contains(&number) && self.0 & (1 << (number - 1)) != 0
+    }
+
+    /// Returns whether every number of the mask is also in `other`.
+    pub fn is_subset_of(self, other: Self) -> bool {
+        self.0 & !other.0 == 0
+    }
+
+    /// Returns how many numbers the two masks have in common.
+    pub fn overlap(self, other: Self) -> usize {
+        (self.0 & other.0).count_ones() as usize
+    }
+
+    /// Converts the mask back to a game in ascending order.
+    pub fn to_game(self) -> Game {
+        (1..=Self::MAX_NUMBER)
+            .filter(|&x| self.0 & (1 << (x - 1)) != 0)
+            .collect()
+    }
+}
+
+impl Game {
+    /// Converts the game to a bitmask, if all its numbers can be represented.
+    pub fn to_mask(&self) -> Option<GameMask> {
+        GameMask::from_numbers(&self.numbers)
+    }
+}
+
+/// An error found while parsing a game from a string.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseGameError {
+    /// The string contains no numbers.
+    Empty,
+    /// A piece of the string is not a non-negative number.
+    InvalidNumber { token: String },
+}
+
+impl fmt::Display for ParseGameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseGameError::Empty => write!(f, "game contains no numbers"),
+            ParseGameError::InvalidNumber { token } => {
+                write!(f, "'{}' is not a valid game number", token)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseGameError {}
+
+impl FromStr for Game {
+    type Err = ParseGameError;
+
+    /// Parses numbers separated by dashes, commas and/or whitespace, with optional zero-padding.
+    ///
+    /// # Examples
+    /// ```
+    /// use rk_lottery::game::Game;
+    ///
+    /// let game: Game = "03-11-22, 34 45 58".parse().unwrap();
+    /// assert_eq!(game.numbers(), &[3, 11, 22, 34, 45, 58]);
+    /// assert_eq!(game.to_string(), "03-11-22-34-45-58");
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numbers = s
+            .split(|c: char| c == '-' || c == ',' || c.is_whitespace())
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                if !token.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(ParseGameError::InvalidNumber {
+                        token: token.to_string(),
+                    });
+                }
+                token.parse().map_err(|_| ParseGameError::InvalidNumber {
+                    token: token.to_string(),
+                })
+            })
+            .collect::<Result<Vec<i64>, _>>()?;
+        if numbers.is_empty() {
+            return Err(ParseGameError::Empty);
+        }
+        Ok(Self::new(numbers))
+    }
+}
+
+impl fmt::Display for Game {
+    /// Writes the numbers zero-padded to the width of the formatter, two digits by default,
+    /// e.g. `{:3}` for `005-023-041` in a lottery of three-digit numbers.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = f.width().unwrap_or(2);
+        let numbers: Vec<String> = self
+            .numbers
+            .iter()
+            .map(|x| format!("{:0width$}", x, width = width))
+            .collect();
+        write!(f, "{}", numbers.join("-"))
+    }
+}
+
+impl Serialize for Game {
+    /// Writes an unlabeled game as an array of numbers, and a labeled one as a table of its
+    /// `numbers` and `label`.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Some(label) = &self.label else {
+            return self.numbers.serialize(serializer);
+        };
+        let mut table = serializer.serialize_struct("Game", 2)?;
+        table.serialize_field("numbers", &self.numbers)?;
+        table.serialize_field("label", label)?;
+        table.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    /// Accepts either an array of numbers (`[1, 2, 3, 4, 5, 6]`), a string
+    /// (`"01-02-03-04-05-06"`), or a table of either as `numbers` with a `label`
+    /// (`{ numbers = [1, 2, 3, 4, 5, 6], label = "Maria" }`).
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct GameVisitor;
+
+        impl<'de> Visitor<'de> for GameVisitor {
+            type Value = Game;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(
+                    f,
+                    "an array of numbers, a string like \"01-02-03-04-05-06\" or a table of numbers and label"
+                )
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Game, E> {
+                value.parse().map_err(E::custom)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Game, A::Error> {
+                let mut numbers = Vec::new();
+                while let Some(number) = seq.next_element()? {
+                    numbers.push(number);
+                }
+                Ok(Game::new(numbers))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Game, A::Error> {
+                const FIELDS: &[&str] = &["numbers", "label"];
+                let (mut numbers, mut label) = (None, None);
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "numbers" => numbers = Some(map.next_value::<Game>()?.numbers),
+                        "label" => label = Some(map.next_value::<String>()?),
+                        other => return Err(de::Error::unknown_field(other, FIELDS)),
+                    }
+                }
+                Ok(Game {
+                    numbers: numbers.ok_or_else(|| de::Error::missing_field("numbers"))?,
+                    label,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(GameVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str_accepts_all_separators() {
+        let expected = Game::new(vec![3, 11, 22, 34, 45, 58]);
+        assert_eq!("03-11-22-34-45-58".parse::<Game>(), Ok(expected.clone()));
+        assert_eq!("3 11 22 34 45 58".parse::<Game>(), Ok(expected.clone()));
+        assert_eq!("3,11,22,34,45,58".parse::<Game>(), Ok(expected.clone()));
+        assert_eq!(" 03, 11  22-34\t45 ,58 ".parse::<Game>(), Ok(expected));
+    }
+
+    #[test]
+    fn test_from_str_rejects_malformed_strings() {
+        assert_eq!("".parse::<Game>(), Err(ParseGameError::Empty));
+        assert_eq!(" - , ".parse::<Game>(), Err(ParseGameError::Empty));
+        assert_eq!(
+            "03-11-x2-34-45-58".parse::<Game>(),
+            Err(ParseGameError::InvalidNumber {
+                token: "x2".to_string()
+            })
+        );
+        assert_eq!(
+            "03;11;22;34;45;58".parse::<Game>(),
+            Err(ParseGameError::InvalidNumber {
+                token: "03;11;22;34;45;58".to_string()
+            })
+        );
+        assert_eq!(
+            "1.5 2 3 4 5 6".parse::<Game>(),
+            Err(ParseGameError::InvalidNumber {
+                token: "1.5".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_str_rejects_out_of_range_values() {
+        // Too large to be represented at all
+        assert_eq!(
+            "1-2-3-4-5-99999999999999999999".parse::<Game>(),
+            Err(ParseGameError::InvalidNumber {
+                token: "99999999999999999999".to_string()
+            })
+        );
+        // Signs are not accepted, a leading dash is a separator
+        assert_eq!(
+            "+1 2 3 4 5 6".parse::<Game>(),
+            Err(ParseGameError::InvalidNumber {
+                token: "+1".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_display_and_roundtrip() {
+        let game = Game::new(vec![3, 11, 22, 34, 45, 58]);
+        assert_eq!(game.to_string(), "03-11-22-34-45-58");
+        assert_eq!(game.to_string().parse::<Game>(), Ok(game.clone()));
+        // A width pads the numbers of larger lotteries, and still reads back
+        let padded = format!("{:3}", Game::new(vec![5, 23, 41, 108]));
+        assert_eq!(padded, "005-023-041-108");
+        assert_eq!(format!("{:1}", game), "3-11-22-34-45-58");
+        assert_eq!(padded.parse::<Game>().unwrap().numbers(), &[5, 23, 41, 108]);
+    }
+
+    #[test]
+    fn test_stats_of_known_games() {
+        let stats = |numbers: Vec<i64>, min, max| {
+            let stats = Game::new(numbers).stats(min, max);
+            (
+                stats.sum,
+                stats.odd,
+                stats.low,
+                stats.max_run,
+                stats.min,
+                stats.max,
+            )
+        };
+        assert_eq!(stats(vec![1, 2, 3, 4, 5, 6], 1, 60), (21, 3, 6, 6, 1, 6));
+        assert_eq!(
+            stats(vec![60, 59, 58, 10, 31, 30], 1, 60),
+            (248, 2, 2, 3, 10, 60)
+        );
+        // 30 is low of 1 to 60, 31 high; 29 is low of 0 to 59, 30 high
+        assert_eq!(stats(vec![30, 31], 1, 60), (61, 1, 1, 2, 30, 31));
+        assert_eq!(
+            stats(vec![0, 29, 30, 1, 2, 47], 0, 59),
+            (109, 3, 4, 3, 0, 47)
+        );
+        // A repeated number counts twice but runs once
+        assert_eq!(stats(vec![5, 5, 6], 1, 60), (16, 2, 3, 2, 5, 6));
+        assert_eq!(stats(vec![], 1, 60), (0, 0, 0, 0, 0, 0));
+        assert_eq!(
+            Game::new(vec![60, 59, 58, 10, 31, 30])
+                .stats(1, 60)
+                .values(),
+            [248, 2, 2, 3, 10, 60]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_array_and_string_forms() {
+        #[derive(Deserialize)]
+        struct Games {
+            games: Vec<Game>,
+        }
+
+        let parsed: Games =
+            toml::from_str(r#"games = [[1, 2, 3, 4, 5, 6], "01-02-03-04-05-07"]"#).unwrap();
+        assert_eq!(
+            parsed.games,
+            vec![
+                Game::new(vec![1, 2, 3, 4, 5, 6]),
+                Game::new(vec![1, 2, 3, 4, 5, 7])
+            ]
+        );
+
+        let error = toml::from_str::<Games>(r#"games = ["01-02-zz"]"#)
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .contains("'zz' is not a valid game number"));
+    }
+
+    #[test]
+    fn test_labeled_games_mix_with_plain_ones_and_serialize_back() {
+        #[derive(Deserialize)]
+        struct Games {
+            games: Vec<Game>,
+        }
+
+        let parsed: Games = toml::from_str(
+            r#"games = [
+                [1, 2, 3, 4, 5, 6],
+                { numbers = [7, 8, 9, 10, 11, 12], label = "Maria" },
+                { numbers = "13-14-15-16-17-18" },
+            ]"#,
+        )
+        .unwrap();
+        let expected = vec![
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![7, 8, 9, 10, 11, 12]).with_label("Maria"),
+            Game::new(vec![13, 14, 15, 16, 17, 18]),
+        ];
+        assert_eq!(parsed.games, expected);
+        assert_eq!(parsed.games[1].label(), Some("Maria"));
+        assert_eq!(parsed.games[1].to_string(), "07-08-09-10-11-12");
+
+        // Only the labeled game is written as a table, so the manifests of unlabeled runs
+        // are unchanged
+        let json = serde_json::to_string(&parsed.games).unwrap();
+        assert_eq!(
+            json,
+            r#"[[1,2,3,4,5,6],{"numbers":[7,8,9,10,11,12],"label":"Maria"},[13,14,15,16,17,18]]"#
+        );
+        assert_eq!(serde_json::from_str::<Vec<Game>>(&json).unwrap(), expected);
+
+        for (toml, message) in [
+            (
+                r#"games = [{ label = "Maria" }]"#,
+                "missing field `numbers`",
+            ),
+            (
+                r#"games = [{ numbers = [1], owner = "Maria" }]"#,
+                "unknown field `owner`",
+            ),
+        ] {
+            let error = toml::from_str::<Games>(toml).err().unwrap();
+            assert!(error.to_string().contains(message), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_mask_roundtrip() {
+        let game = Game::new(vec![1, 11, 22, 34, 45, 64]);
+        let mask = game.to_mask().unwrap();
+        assert_eq!(mask.len(), 6);
+        assert_eq!(mask.to_game(), game);
+        assert!(mask.contains(64) && mask.contains(1) && !mask.contains(2));
+
+        // Unsorted input comes back sorted, repeated numbers collapse
+        let mask = GameMask::from_numbers(&[45, 11, 11, 3]).unwrap();
+        assert_eq!(mask.to_game(), Game::new(vec![3, 11, 45]));
+        assert_eq!(mask.len(), 3);
+
+        // Numbers outside 1..=64 cannot be represented
+        assert_eq!(GameMask::from_numbers(&[0, 1, 2]), None);
+        assert_eq!(GameMask::from_numbers(&[1, 2, 65]), None);
+        assert!(GameMask::from_numbers(&[]).unwrap().is_empty());
+
+        // A Lotofácil game of 15 of 25 numbers takes the 25 lowest bits at most
+        let game = Game::new((1..=25).step_by(2).chain([2, 24]).collect());
+        let mask = game.to_mask().unwrap();
+        assert_eq!(mask.len(), 15);
+        assert!(mask.is_subset_of(GameMask::range(1, 25)));
+        assert_eq!(mask.to_game(), game.sorted());
+    }
+
+    #[test]
+    fn test_mask_range_matches_vec_range_check() {
+        for (min, max) in [(1, 60), (31, 60), (1, 64), (60, 60), (10, 5)] {
+            let valid = GameMask::range(min, max);
+            for x in 1..=GameMask::MAX_NUMBER {
+                assert_eq!(
+                    valid.contains(x),
+                    (min..=max).contains(&x),
+                    "{} in {}..={}",
+                    x,
+                    min,
+                    max
+                );
+            }
+        }
+        assert_eq!(GameMask::range(-5, 100), GameMask::range(1, 64));
+    }
+
+    #[test]
+    fn test_mask_overlap_matches_vec_overlap() {
+        let games = [
+            vec![32, 35, 41, 48, 50, 59],
+            vec![1, 35, 41, 49, 50, 60],
+            vec![1, 2, 3, 4, 5, 6],
+            vec![59, 50, 48, 41, 35, 32],
+        ];
+        for a in &games {
+            for b in &games {
+                let expected = a.iter().filter(|x| b.contains(x)).count();
+                let mask_a = GameMask::from_numbers(a).unwrap();
+                let mask_b = GameMask::from_numbers(b).unwrap();
+                assert_eq!(mask_a.overlap(mask_b), expected);
+                assert_eq!(mask_a.is_subset_of(mask_b), expected == a.len());
+            }
+        }
+    }
+}
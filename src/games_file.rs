@@ -0,0 +1,755 @@
+use crate::config::Config;
+use crate::custom_utils;
+use crate::game::{Game, GameStats};
+use crate::persistence;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// Name of the index column, the first field of the header row.
+pub const INDEX_COLUMN: &str = "game";
+
+/// Name of the label column, the last field of the header row of a labeled file.
+pub const LABEL_COLUMN: &str = "label";
+
+/// Field separator of the CSV files unless configured otherwise.
+pub const DEFAULT_DELIMITER: u8 = b',';
+
+/// Field separators a CSV file may be written with, told apart by `sniff_delimiter`.
+pub const DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+/// How the games are laid out in a CSV file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// A header row (`game,n1,n2,...`), then one game per row after its 1-based index.
+    Indexed,
+    /// One game per row, with no header nor index, as written by earlier versions.
+    Plain,
+    /// Like `Indexed`, with a last `label` column holding the label of each game.
+    Labeled,
+}
+
+impl Layout {
+    /// The layout chosen by `csv_header` in the configuration, with the label column when the
+    /// games are labeled.
+    pub fn configured(config: &Config) -> Self {
+        match (config.csv_header, config.labeled()) {
+            (true, true) => Layout::Labeled,
+            (true, false) => Layout::Indexed,
+            (false, _) => Layout::Plain,
+        }
+    }
+}
+
+/// A game as written in the JSON and NDJSON formats.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct GameRecord {
+    /// Position of the game in the file, starting at 1.
+    pub index: usize,
+    /// The numbers of the game.
+    pub numbers: Vec<i64>,
+    /// The combinadic number of the game, telling its position among all games in colex order.
+    pub rank: i64,
+    /// The label of the game, left out when it has none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The figures of the numbers of the game, written with `output_stats_columns`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<GameStats>,
+}
+
+impl GameRecord {
+    /// The game of the record, with its label.
+    pub fn game(&self) -> Game {
+        let game = Game::new(self.numbers.clone());
+        match &self.label {
+            Some(label) => game.with_label(label.clone()),
+            None => game,
+        }
+    }
+}
+
+/// The records of games numbered from `previous + 1`, ranked for numbers starting at
+/// `min_number`, with the figures of each game for `stats_range`, the smallest and largest
+/// numbers of the lottery, when given.
+pub fn records(
+    games: &[Game],
+    min_number: i64,
+    previous: usize,
+    stats_range: Option<(i64, i64)>,
+) -> Vec<GameRecord> {
+    games
+        .iter()
+        .enumerate()
+        .map(|(index, game)| {
+            let mut numbers = game.to_vec();
+            numbers.sort_unstable();
+            GameRecord {
+                index: previous + index + 1,
+                rank: custom_utils::game2enum_from(&numbers, min_number),
+                numbers: game.to_vec(),
+                label: game.label().map(str::to_string),
+                stats: stats_range.map(|(min, max)| game.stats(min, max)),
+            }
+        })
+        .collect()
+}
+
+/// Formats games as a JSON array of records.
+///
+/// # Arguments
+/// * `games` - The games, in order.
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1), for the ranks.
+/// * `stats_range` - The smallest and largest numbers of the lottery, to write the figures of
+///   each game (see `Game::stats`) in a `stats` field; `None` leaves them out.
+///
+/// # Returns
+/// * The content of the file.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::game::Game;
+/// use rk_lottery::games_file::to_json;
+///
+/// let json = to_json(&[Game::new(vec![1, 2, 3, 4, 5, 7])], 1, None);
+/// assert_eq!(
+///     String::from_utf8(json).unwrap(),
+///     "[{\"index\":1,\"numbers\":[1,2,3,4,5,7],\"rank\":1}]\n"
+/// );
+/// ```
+pub fn to_json(games: &[Game], min_number: i64, stats_range: Option<(i64, i64)>) -> Vec<u8> {
+    let mut json = serde_json::to_vec(&records(games, min_number, 0, stats_range))
+        .expect("records serialize to JSON");
+    json.push(b'\n');
+    json
+}
+
+/// Formats games as one JSON record per line, numbered from `previous + 1` so the lines can be
+/// appended to a file already holding `previous` games, with their figures for `stats_range`
+/// like `to_json`.
+pub fn to_ndjson(
+    games: &[Game],
+    min_number: i64,
+    previous: usize,
+    stats_range: Option<(i64, i64)>,
+) -> Vec<u8> {
+    let mut ndjson = Vec::new();
+    for record in records(games, min_number, previous, stats_range) {
+        serde_json::to_writer(&mut ndjson, &record).expect("records serialize to JSON");
+        ndjson.push(b'\n');
+    }
+    ndjson
+}
+
+/// Whether `content` holds JSON or NDJSON records rather than CSV rows.
+pub fn is_json(content: &str) -> bool {
+    matches!(content.trim_start().chars().next(), Some('[') | Some('{'))
+}
+
+/// Reads records written by `to_json` or `to_ndjson`, telling them apart by the first character.
+///
+/// # Returns
+/// * The records, in file order.
+/// * An error reporting the file as corrupted, naming the line of the first record that cannot
+///   be read for NDJSON.
+pub fn read_records(content: &str) -> Result<Vec<GameRecord>, String> {
+    if content.trim_start().starts_with('[') {
+        return serde_json::from_str(content)
+            .map_err(|error| persistence::corrupted(error).to_string());
+    }
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line, record)| {
+            serde_json::from_str(record)
+                .map_err(|error| format!("line {}: {}", line + 1, persistence::corrupted(error)))
+        })
+        .collect()
+}
+
+/// The header row of an indexed file: the index column, then `n1` to `n<numbers_per_game>`.
+pub fn header(numbers_per_game: usize) -> Vec<String> {
+    std::iter::once(INDEX_COLUMN.to_string())
+        .chain((1..=numbers_per_game).map(|n| format!("n{}", n)))
+        .collect()
+}
+
+/// Whether the header row of a CSV file names the columns of the figures of the games, after
+/// their numbers.
+pub fn has_stats_columns(content: &[u8]) -> bool {
+    let mut rows = Rows::new(content, DEFAULT_DELIMITER);
+    rows.next();
+    rows.stats_columns()
+}
+
+/// Guesses the field separator of a CSV file from its first line: the one of `DELIMITERS`
+/// appearing the most in it, `default` on a tie with it or when the line holds none of them.
+///
+/// # Arguments
+/// * `content` - The start of the file, holding at least its first line.
+/// * `default` - The field separator expected, e.g. the configured one.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::games_file::sniff_delimiter;
+///
+/// assert_eq!(sniff_delimiter(b"game;n1;n2\n1;4;9\n", b','), b';');
+/// assert_eq!(sniff_delimiter(b"7\n12\n", b';'), b';');
+/// ```
+pub fn sniff_delimiter(content: &[u8], default: u8) -> u8 {
+    let line = content.split(|&byte| byte == b'\n').next().unwrap_or(&[]);
+    let count = |delimiter: u8| line.iter().filter(|&&byte| byte == delimiter).count();
+    let (best, most) = DELIMITERS
+        .iter()
+        .map(|&delimiter| (delimiter, count(delimiter)))
+        .fold((default, count(default)), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        });
+    if most == 0 {
+        default
+    } else {
+        best
+    }
+}
+
+/// A CSV writer separating the fields with `delimiter`, quoting those holding it.
+fn writer(delimiter: u8) -> csv::Writer<Vec<u8>> {
+    csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new())
+}
+
+/// Formats games as a whole CSV file.
+///
+/// # Arguments
+/// * `games` - The games, in order.
+/// * `layout` - Whether to write the header row and the index column, and the label column.
+/// * `numbers_per_game` - The number of numbers per game, sizing the header row.
+/// * `delimiter` - The field separator, e.g. `DEFAULT_DELIMITER`.
+/// * `width` - The digits each number is zero-padded to, e.g. 2 for `05`; 0 writes the
+///   numbers as they are.
+/// * `stats_range` - The smallest and largest numbers of the lottery, to write the figures of
+///   each game (see `Game::stats`) after its numbers, in the columns of `GameStats::COLUMNS`;
+///   `None` leaves them out. Only written with a header row naming them.
+///
+/// # Returns
+/// * The content of the file.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::game::Game;
+/// use rk_lottery::games_file::{to_csv, Layout, DEFAULT_DELIMITER};
+///
+/// let games = vec![Game::new(vec![1, 2, 3, 4, 5, 16])];
+/// let csv = to_csv(&games, Layout::Indexed, 6, DEFAULT_DELIMITER, 0, None);
+/// assert_eq!(String::from_utf8(csv).unwrap(), "game,n1,n2,n3,n4,n5,n6\n1,1,2,3,4,5,16\n");
+/// let csv = String::from_utf8(to_csv(&games, Layout::Plain, 6, b';', 2, None)).unwrap();
+/// assert_eq!(csv, "01;02;03;04;05;16\n");
+/// let csv = to_csv(&games, Layout::Indexed, 6, DEFAULT_DELIMITER, 0, Some((1, 60)));
+/// assert_eq!(
+///     String::from_utf8(csv).unwrap(),
+///     "game,n1,n2,n3,n4,n5,n6,sum,odd,low,max_run,min,max\n1,1,2,3,4,5,16,31,3,6,5,1,16\n"
+/// );
+/// ```
+pub fn to_csv(
+    games: &[Game],
+    layout: Layout,
+    numbers_per_game: usize,
+    delimiter: u8,
+    width: usize,
+    stats_range: Option<(i64, i64)>,
+) -> Vec<u8> {
+    let mut wtr = writer(delimiter);
+    let mut row = header(numbers_per_game);
+    if stats_range.is_some() {
+        row.extend(GameStats::COLUMNS.map(str::to_string));
+    }
+    if layout == Layout::Labeled {
+        row.push(LABEL_COLUMN.to_string());
+    }
+    if layout != Layout::Plain {
+        wtr.write_record(row)
+            .expect("writing to memory cannot fail");
+    }
+    write_rows(&mut wtr, games, layout, 0, width, stats_range);
+    wtr.into_inner().expect("writing to memory cannot fail")
+}
+
+/// Formats games as rows to append to a file already holding `previous` games, numbering
+/// them from `previous + 1` in the indexed layout, their fields separated by `delimiter`,
+/// their numbers zero-padded to `width` digits and followed by their figures for
+/// `stats_range` like `to_csv`.
+pub fn rows_csv(
+    games: &[Game],
+    layout: Layout,
+    previous: usize,
+    delimiter: u8,
+    width: usize,
+    stats_range: Option<(i64, i64)>,
+) -> Vec<u8> {
+    let mut wtr = writer(delimiter);
+    write_rows(&mut wtr, games, layout, previous, width, stats_range);
+    wtr.into_inner().expect("writing to memory cannot fail")
+}
+
+/// Writes one row per game, numbered from `previous + 1` in the indexed layouts, an unlabeled
+/// game leaving its label field empty in the labeled one.
+fn write_rows(
+    wtr: &mut csv::Writer<Vec<u8>>,
+    games: &[Game],
+    layout: Layout,
+    previous: usize,
+    width: usize,
+    stats_range: Option<(i64, i64)>,
+) {
+    for (index, game) in games.iter().enumerate() {
+        let stats = stats_range
+            .filter(|_| layout != Layout::Plain)
+            .map(|(min, max)| game.stats(min, max).values())
+            .into_iter()
+            .flatten()
+            .map(|value| value.to_string());
+        let numbers = game
+            .iter()
+            .map(|number| format!("{:0width$}", number, width = width))
+            .chain(stats);
+        let index = std::iter::once((previous + index + 1).to_string());
+        let row: Vec<String> = match layout {
+            Layout::Indexed => index.chain(numbers).collect(),
+            Layout::Plain => numbers.collect(),
+            Layout::Labeled => index
+                .chain(numbers)
+                .chain(std::iter::once(
+                    game.label().unwrap_or_default().to_string(),
+                ))
+                .collect(),
+        };
+        wtr.write_record(&row)
+            .expect("writing to memory cannot fail");
+    }
+}
+
+/// Reads the games of a CSV file in either layout, telling them apart by the first row.
+///
+/// # Arguments
+/// * `reader` - The CSV content.
+/// * `delimiter` - The field separator expected, unless the first row tells another one (see
+///   `sniff_delimiter`).
+///
+/// # Returns
+/// * The layout of the file, `None` when it holds no row at all.
+/// * The game of each row, index column left out and labeled from the label column, or why
+///   the row cannot be read.
+pub fn read_rows<R: io::BufRead>(
+    reader: R,
+    delimiter: u8,
+) -> (Option<Layout>, Vec<Result<Game, String>>) {
+    let mut rows = Rows::new(reader, delimiter);
+    let games = rows.by_ref().collect();
+    (rows.layout(), games)
+}
+
+/// The game rows of a CSV file in either layout, read one at a time so that large files are
+/// never held in memory; see `read_rows`.
+pub struct Rows<R> {
+    records: csv::StringRecordsIntoIter<R>,
+    layout: Option<Layout>,
+    delimiter: u8,
+    /// Whether the header row names the columns of the figures of the games.
+    stats_columns: bool,
+    /// The row last read, as in the file.
+    record: csv::StringRecord,
+}
+
+impl<R: io::BufRead> Rows<R> {
+    /// Starts reading the rows of a CSV file, their field separator told from the first row
+    /// (see `sniff_delimiter`), `delimiter` when the row tells none.
+    pub fn new(mut reader: R, delimiter: u8) -> Self {
+        // A read error shows up again as the first row is read
+        let delimiter = match reader.fill_buf() {
+            Ok(start) => sniff_delimiter(start, delimiter),
+            Err(_) => delimiter,
+        };
+        let rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(delimiter)
+            .from_reader(reader);
+        Self {
+            records: rdr.into_records(),
+            layout: None,
+            delimiter,
+            stats_columns: false,
+            record: csv::StringRecord::new(),
+        }
+    }
+
+    /// The layout of the file, known once the first row is read, `None` before or when the
+    /// file holds no row at all.
+    pub fn layout(&self) -> Option<Layout> {
+        self.layout
+    }
+
+    /// The field separator the rows are read with.
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    /// Whether the header row names the columns of `GameStats::COLUMNS` after the numbers,
+    /// which are left out of the games read; known once the first row is read.
+    pub fn stats_columns(&self) -> bool {
+        self.stats_columns
+    }
+
+    /// The fields of the row last read, as in the file, index and label columns included;
+    /// none when the row cannot be read at all.
+    pub fn fields(&self) -> &csv::StringRecord {
+        &self.record
+    }
+}
+
+impl<R: io::BufRead> Iterator for Rows<R> {
+    /// The game of a row, index column left out and labeled from the label column, or why the
+    /// row cannot be read.
+    type Item = Result<Game, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let record = match self.records.next()? {
+                Ok(record) => record,
+                Err(error) => {
+                    self.layout.get_or_insert(Layout::Plain);
+                    self.record.clear();
+                    return Some(Err(error.to_string()));
+                }
+            };
+            // The first row is either the header of an indexed file or the first game
+            let (skip, labeled) = match self.layout {
+                Some(Layout::Indexed) => (1, false),
+                Some(Layout::Labeled) => (1, true),
+                Some(Layout::Plain) => (0, false),
+                None if record.get(0).map(str::trim) == Some(INDEX_COLUMN) => {
+                    let labeled = record.iter().next_back().map(str::trim) == Some(LABEL_COLUMN);
+                    let end = record.len() - usize::from(labeled);
+                    self.stats_columns = end >= GameStats::COLUMNS.len()
+                        && record
+                            .iter()
+                            .take(end)
+                            .skip(end - GameStats::COLUMNS.len())
+                            .map(str::trim)
+                            .eq(GameStats::COLUMNS);
+                    self.layout = Some(if labeled {
+                        Layout::Labeled
+                    } else {
+                        Layout::Indexed
+                    });
+                    continue;
+                }
+                None => {
+                    self.layout = Some(Layout::Plain);
+                    (0, false)
+                }
+            };
+            let fields = record.len() - usize::from(labeled && record.len() > skip);
+            let stats = if self.stats_columns {
+                GameStats::COLUMNS.len()
+            } else {
+                0
+            };
+            let numbers = record
+                .iter()
+                .take(fields.saturating_sub(stats))
+                .skip(skip)
+                .enumerate()
+                .map(|(position, item)| {
+                    item.trim().parse::<i64>().map_err(|error| {
+                        format!(
+                            "field {} ({:?}) is not a number: {}",
+                            skip + position + 1,
+                            item.trim(),
+                            error
+                        )
+                    })
+                })
+                .collect::<Result<Vec<i64>, _>>();
+            let label = record
+                .get(fields)
+                .filter(|_| labeled)
+                .map(str::trim)
+                .filter(|label| !label.is_empty());
+            let game = numbers.map(|numbers| match label {
+                Some(label) => Game::new(numbers).with_label(label),
+                None => Game::new(numbers),
+            });
+            self.record = record;
+            return Some(game);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn games() -> Vec<Game> {
+        vec![
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![7, 8, 9, 10, 11, 12]),
+        ]
+    }
+
+    #[test]
+    fn test_both_layouts_read_back() {
+        for layout in [Layout::Indexed, Layout::Plain] {
+            let csv = to_csv(&games(), layout, 6, DEFAULT_DELIMITER, 0, None);
+            let (read_layout, rows) = read_rows(csv.as_slice(), DEFAULT_DELIMITER);
+            assert_eq!(read_layout, Some(layout));
+            let rows: Vec<Vec<i64>> = rows.into_iter().map(|row| row.unwrap().into()).collect();
+            let expected: Vec<Vec<i64>> = games().iter().map(|game| game.to_vec()).collect();
+            assert_eq!(rows, expected);
+        }
+        assert_eq!(read_rows(&b""[..], DEFAULT_DELIMITER).0, None);
+    }
+
+    #[test]
+    fn test_labeled_games_keep_their_labels_and_the_others_none() {
+        let games = vec![
+            Game::new(vec![1, 2, 3, 4, 5, 6]).with_label("Maria, the aunt"),
+            Game::new(vec![7, 8, 9, 10, 11, 12]),
+        ];
+        let csv = to_csv(&games, Layout::Labeled, 6, DEFAULT_DELIMITER, 0, None);
+        assert_eq!(
+            String::from_utf8(csv.clone()).unwrap(),
+            "game,n1,n2,n3,n4,n5,n6,label\n1,1,2,3,4,5,6,\"Maria, the aunt\"\n2,7,8,9,10,11,12,\n"
+        );
+        let (layout, rows) = read_rows(csv.as_slice(), DEFAULT_DELIMITER);
+        assert_eq!(layout, Some(Layout::Labeled));
+        let read: Vec<Game> = rows.into_iter().map(Result::unwrap).collect();
+        assert_eq!(read, games);
+
+        let records = records(&games, 1, 0, None);
+        assert_eq!(records[0].label.as_deref(), Some("Maria, the aunt"));
+        assert_eq!(records[1].game(), games[1]);
+        let json = String::from_utf8(to_json(&games, 1, None)).unwrap();
+        assert_eq!(json.matches("\"label\"").count(), 1);
+    }
+
+    #[test]
+    fn test_semicolon_files_read_back_whatever_the_delimiter_expected() {
+        let csv = to_csv(&games(), Layout::Indexed, 6, b';', 0, None);
+        assert_eq!(
+            String::from_utf8(csv.clone()).unwrap(),
+            "game;n1;n2;n3;n4;n5;n6\n1;1;2;3;4;5;6\n2;7;8;9;10;11;12\n"
+        );
+        let expected: Vec<Vec<i64>> = games().iter().map(|game| game.to_vec()).collect();
+        for expected_delimiter in [b',', b';', b'\t'] {
+            let mut rows = Rows::new(csv.as_slice(), expected_delimiter);
+            let read: Vec<Vec<i64>> = rows.by_ref().map(|row| row.unwrap().into()).collect();
+            assert_eq!(read, expected);
+            assert_eq!(rows.layout(), Some(Layout::Indexed));
+            assert_eq!(rows.delimiter(), b';');
+        }
+
+        let plain = to_csv(&games(), Layout::Plain, 6, b'\t', 0, None);
+        assert!(plain.starts_with(b"1\t2\t3\t4\t5\t6\n7\t"));
+        let (layout, rows) = read_rows(plain.as_slice(), DEFAULT_DELIMITER);
+        assert_eq!(layout, Some(Layout::Plain));
+        assert_eq!(rows[1], Ok(Game::new(vec![7, 8, 9, 10, 11, 12])));
+        let mut appended = csv;
+        appended.extend(rows_csv(&games()[..1], Layout::Indexed, 2, b';', 0, None));
+        assert!(appended.ends_with(b"\n3;1;2;3;4;5;6\n"));
+    }
+
+    #[test]
+    fn test_sniffing_picks_the_most_frequent_delimiter_of_the_first_line() {
+        assert_eq!(sniff_delimiter(b"1,2,3,4,5,6\n", b';'), b',');
+        assert_eq!(sniff_delimiter(b"game|n1|n2", b','), b'|');
+        assert_eq!(sniff_delimiter(b"1\t2\t3\n4,5,6,7\n", b','), b'\t');
+        // Ties and lines without any delimiter keep the one expected
+        assert_eq!(sniff_delimiter(b"1;2,3\n", b','), b',');
+        assert_eq!(sniff_delimiter(b"1;2,3\n", b';'), b';');
+        assert_eq!(sniff_delimiter(b"", b';'), b';');
+        // A header field holding the delimiter is quoted, and reads back whole
+        let mut wtr = writer(b';');
+        wtr.write_record(["game;id", "n1"]).unwrap();
+        let content = wtr.into_inner().unwrap();
+        assert_eq!(content, b"\"game;id\";n1\n");
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(sniff_delimiter(&content, b','))
+            .from_reader(content.as_slice());
+        assert_eq!(rdr.headers().unwrap(), vec!["game;id", "n1"]);
+    }
+
+    #[test]
+    fn test_appended_rows_continue_the_index() {
+        let mut csv = to_csv(
+            &games()[..1],
+            Layout::Indexed,
+            6,
+            DEFAULT_DELIMITER,
+            0,
+            None,
+        );
+        csv.extend(rows_csv(
+            &games()[1..],
+            Layout::Indexed,
+            1,
+            DEFAULT_DELIMITER,
+            0,
+            None,
+        ));
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "game,n1,n2,n3,n4,n5,n6\n1,1,2,3,4,5,6\n2,7,8,9,10,11,12\n"
+        );
+        assert_eq!(
+            rows_csv(&games()[1..], Layout::Plain, 1, DEFAULT_DELIMITER, 0, None),
+            b"7,8,9,10,11,12\n"
+        );
+    }
+
+    #[test]
+    fn test_padded_and_unpadded_rows_read_back_alike() {
+        let padded = to_csv(&games(), Layout::Indexed, 6, DEFAULT_DELIMITER, 2, None);
+        assert_eq!(
+            String::from_utf8(padded.clone()).unwrap(),
+            "game,n1,n2,n3,n4,n5,n6\n1,01,02,03,04,05,06\n2,07,08,09,10,11,12\n"
+        );
+        let expected: Vec<Vec<i64>> = games().iter().map(|game| game.to_vec()).collect();
+        let (_, rows) = read_rows(padded.as_slice(), DEFAULT_DELIMITER);
+        let read: Vec<Vec<i64>> = rows.into_iter().map(|row| row.unwrap().into()).collect();
+        assert_eq!(read, expected);
+
+        // Rows padded or not, e.g. appended by another program, may follow one another
+        let mut mixed = to_csv(&games()[..1], Layout::Plain, 6, b';', 3, None);
+        mixed.extend(rows_csv(&games()[1..], Layout::Plain, 1, b';', 0, None));
+        mixed.extend(b"0013;4;05;16;0;017\n");
+        assert!(mixed.starts_with(b"001;002;003;004;005;006\n7;8;9;10;11;12\n"));
+        let (layout, rows) = read_rows(mixed.as_slice(), DEFAULT_DELIMITER);
+        assert_eq!(layout, Some(Layout::Plain));
+        let read: Vec<Vec<i64>> = rows.into_iter().map(|row| row.unwrap().into()).collect();
+        assert_eq!(read[..2], expected[..]);
+        assert_eq!(read[2], [13, 4, 5, 16, 0, 17]);
+    }
+
+    #[test]
+    fn test_json_and_ndjson_round_trip() {
+        let games = games();
+        let expected: Vec<(usize, Vec<i64>, i64)> = games
+            .iter()
+            .enumerate()
+            .map(|(index, game)| (index + 1, game.to_vec(), custom_utils::game2enum(game)))
+            .collect();
+        let fields = |records: Vec<GameRecord>| -> Vec<(usize, Vec<i64>, i64)> {
+            records
+                .into_iter()
+                .map(|record| (record.index, record.numbers, record.rank))
+                .collect()
+        };
+
+        let json = String::from_utf8(to_json(&games, 1, None)).unwrap();
+        assert!(is_json(&json));
+        let parsed: Vec<GameRecord> = serde_json::from_str(&json).unwrap();
+        assert_eq!(fields(parsed), expected);
+        assert_eq!(fields(read_records(&json).unwrap()), expected);
+
+        let mut ndjson = to_ndjson(&games[..1], 1, 0, None);
+        ndjson.extend(to_ndjson(&games[1..], 1, 1, None));
+        let ndjson = String::from_utf8(ndjson).unwrap();
+        assert_eq!(ndjson.lines().count(), 2);
+        for (line, expected) in ndjson.lines().zip(&expected) {
+            let record: GameRecord = serde_json::from_str(line).unwrap();
+            assert_eq!(
+                (record.index, record.numbers, record.rank),
+                expected.clone()
+            );
+        }
+        assert_eq!(fields(read_records(&ndjson).unwrap()), expected);
+        assert!(!is_json("1,2,3,4,5,6\n"));
+    }
+
+    #[test]
+    fn test_stats_columns_are_written_and_left_out_when_read() {
+        let games = vec![
+            Game::new(vec![1, 2, 3, 4, 5, 6]).with_label("Ana"),
+            Game::new(vec![60, 59, 58, 10, 31, 30]),
+        ];
+        let mut csv = to_csv(
+            &games,
+            Layout::Labeled,
+            6,
+            DEFAULT_DELIMITER,
+            0,
+            Some((1, 60)),
+        );
+        csv.extend(rows_csv(
+            &[Game::new(vec![30, 31, 32, 33, 34, 35])],
+            Layout::Labeled,
+            2,
+            DEFAULT_DELIMITER,
+            0,
+            Some((1, 60)),
+        ));
+        assert_eq!(
+            String::from_utf8(csv.clone()).unwrap(),
+            "game,n1,n2,n3,n4,n5,n6,sum,odd,low,max_run,min,max,label\n\
+             1,1,2,3,4,5,6,21,3,6,6,1,6,Ana\n\
+             2,60,59,58,10,31,30,248,2,2,3,10,60,\n\
+             3,30,31,32,33,34,35,195,3,1,6,30,35,\n"
+        );
+        assert!(has_stats_columns(&csv));
+        let (layout, rows) = read_rows(csv.as_slice(), DEFAULT_DELIMITER);
+        assert_eq!(layout, Some(Layout::Labeled));
+        let read: Vec<Game> = rows.into_iter().map(Result::unwrap).collect();
+        assert_eq!(read[..2], games);
+        assert!(!has_stats_columns(&to_csv(
+            &games,
+            Layout::Indexed,
+            6,
+            DEFAULT_DELIMITER,
+            0,
+            None
+        )));
+        // Without a header row naming them, the figures are not written
+        let plain = to_csv(&games[1..], Layout::Plain, 6, b';', 0, Some((1, 60)));
+        assert_eq!(String::from_utf8(plain).unwrap(), "60;59;58;10;31;30\n");
+
+        let json = String::from_utf8(to_json(&games[1..], 1, Some((1, 60)))).unwrap();
+        assert!(json.contains(
+            "\"stats\":{\"sum\":248,\"odd\":2,\"low\":2,\"max_run\":3,\"min\":10,\"max\":60}"
+        ));
+        let records = read_records(&json).unwrap();
+        assert_eq!(records[0].stats, Some(games[1].stats(1, 60)));
+        assert_eq!(records[0].game(), games[1]);
+        let ndjson = String::from_utf8(to_ndjson(&games, 1, 0, None)).unwrap();
+        assert!(!ndjson.contains("stats"));
+    }
+
+    #[test]
+    fn test_rank_ignores_the_order_of_the_numbers() {
+        let records = records(&[Game::new(vec![7, 5, 4, 3, 2, 1])], 1, 0, None);
+        assert_eq!(records[0].rank, 1);
+        assert_eq!(records[0].numbers, vec![7, 5, 4, 3, 2, 1]);
+        assert!(read_records("{\"index\":1}\n")
+            .unwrap_err()
+            .starts_with("line 1: file appears corrupted: "));
+        assert!(read_records("[{\"index\":1,\"numbers\":[1,2")
+            .unwrap_err()
+            .starts_with("file appears corrupted: "));
+    }
+
+    #[test]
+    fn test_unreadable_rows_are_reported() {
+        let (layout, rows) = read_rows(&b"game,n1,n2\n1,4,x\n2,5,6\n"[..], DEFAULT_DELIMITER);
+        assert_eq!(layout, Some(Layout::Indexed));
+        assert_eq!(
+            rows[0],
+            Err("field 3 (\"x\") is not a number: invalid digit found in string".to_string())
+        );
+        assert_eq!(rows[1], Ok(Game::new(vec![5, 6])));
+    }
+}
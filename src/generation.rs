@@ -0,0 +1,3961 @@
+use crate::checkpoint::Checkpoint;
+use crate::config::{self, Config, InvalidInitial, OutputOrder, RngKind, Strategy};
+use crate::constraints::{ConstraintSet, Violation};
+use crate::custom_utils::{self, GameIterator};
+use crate::data_structures::{
+    DenseNumberSet, NumberSet, RankSet, SubsetIndex, TripletIndex, TripletSet,
+};
+use crate::feasibility::{self, Feasibility};
+use crate::game::{self, Game};
+use crate::interactive::Decision;
+use crate::rng::{CounterRng, DrawCounter, FastForward, SharedRng};
+use crate::shared_set::{ConcurrentRankSet, ShardedNumberSet};
+use crate::stats::{GenerationStats, Outcome, PROGRESS_INTERVAL};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Two games of `initial_games` sharing triplets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripletConflict {
+    /// Position of the earlier game in `initial_games`, starting at 0.
+    pub earlier: usize,
+    /// Position of the later game, starting at 0.
+    pub index: usize,
+    /// The triplets the games share, in the order of the later game.
+    pub triplets: Vec<Vec<i64>>,
+}
+
+impl fmt::Display for TripletConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let triplets: Vec<String> = self
+            .triplets
+            .iter()
+            .map(|triplet| Game::new(triplet.clone()).to_string())
+            .collect();
+        write!(
+            f,
+            "games #{} and #{} share {}",
+            self.earlier + 1,
+            self.index + 1,
+            triplets.join(", ")
+        )
+    }
+}
+
+/// Number of most recent attempts over which the rejection rate is measured.
+pub const REJECTION_WINDOW: usize = 10_000;
+
+/// Rejection rate over the window above which random sampling gives way to a sequential scan.
+pub const REJECTION_THRESHOLD: f64 = 0.999;
+
+/// Number of valid candidates among which the greedy-coverage strategy picks each game.
+pub const GREEDY_SAMPLE_SIZE: usize = 1000;
+
+/// An error preventing the generation of the requested games.
+#[derive(Debug)]
+pub enum GenError {
+    /// More games were requested than can possibly avoid sharing a triplet.
+    Infeasible {
+        requested: usize,
+        bound: u64,
+        min_desired_number: i64,
+        max_number: i64,
+    },
+    /// More games were requested than fit with each number in at most `max_number_usage` games.
+    UsageInfeasible {
+        requested: usize,
+        max_number_usage: usize,
+        bound: usize,
+    },
+    /// More games were requested than can pairwise share at most `max_overlap` numbers.
+    OverlapInfeasible {
+        requested: usize,
+        max_overlap: usize,
+        bound: u64,
+    },
+    /// A game of `initial_games` breaks the constraints.
+    InvalidInitialGame {
+        /// Position of the game in `initial_games`, starting at 0.
+        index: usize,
+        violations: Vec<Violation>,
+    },
+    /// Games of `initial_games` share triplets with earlier ones, every pair of them listed in
+    /// the order of the later game.
+    RepeatedTriplets { conflicts: Vec<TripletConflict> },
+    /// A game of `initial_games` repeats an earlier one, when the triplets are not tracked.
+    DuplicateInitialGame {
+        /// Position of the game in `initial_games`, starting at 0.
+        index: usize,
+        /// Position of the earlier equal game, starting at 0.
+        earlier: usize,
+    },
+    /// A game of `initial_games` shares a triplet with a game of an earlier run, or of the
+    /// files of `avoid_triplets_from`.
+    StoredTriplet {
+        /// Position of the game in `initial_games`, starting at 0.
+        index: usize,
+        triplet: Vec<i64>,
+    },
+    /// The configuration combines options that cannot be used together.
+    Unsupported(&'static str),
+    /// The worker threads of the parallel generation could not be started.
+    ThreadPool(rayon::ThreadPoolBuildError),
+    /// Every remaining game in the range was tried without reaching the requested count.
+    Exhausted { generated: usize, requested: usize },
+    /// `max_attempts` candidates in a row were rejected before reaching the requested count.
+    OutOfAttempts {
+        generated: usize,
+        requested: usize,
+        max_attempts: u64,
+    },
+    /// The saved sets of a previous run disagree with its games.
+    Inconsistent { differences: Vec<String> },
+    /// The run was cancelled before reaching the requested count.
+    Cancelled {
+        /// The games so far, initial games included, sharing no triplet.
+        partial: Vec<Game>,
+    },
+}
+
+impl fmt::Display for GenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenError::Infeasible {
+                requested,
+                bound,
+                min_desired_number,
+                max_number,
+            } => write!(
+                f,
+                "{} games requested, but at most {} games with numbers {} to {} can avoid sharing a triplet",
+                requested, bound, min_desired_number, max_number
+            ),
+            GenError::UsageInfeasible {
+                requested,
+                max_number_usage,
+                bound,
+            } => write!(
+                f,
+                "{} games requested, but at most {} games fit with each number in at most {} games (max_number_usage)",
+                requested, bound, max_number_usage
+            ),
+            GenError::OverlapInfeasible {
+                requested,
+                max_overlap,
+                bound,
+            } => write!(
+                f,
+                "{} games requested, but at most {} games can share at most {} numbers with each other (max_overlap)",
+                requested, bound, max_overlap
+            ),
+            GenError::InvalidInitialGame { index, violations } => {
+                let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                write!(
+                    f,
+                    "Initial game #{} is invalid: {}",
+                    index + 1,
+                    violations.join("; ")
+                )
+            }
+            GenError::RepeatedTriplets { conflicts } => {
+                let conflicts: Vec<String> = conflicts.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "Initial games share triplets: {}",
+                    conflicts.join("; ")
+                )
+            }
+            GenError::DuplicateInitialGame { index, earlier } => write!(
+                f,
+                "Initial game #{} repeats initial game #{}",
+                index + 1,
+                earlier + 1
+            ),
+            GenError::StoredTriplet { index, triplet } => write!(
+                f,
+                "Initial game #{} shares triplet {} with a game of an earlier run or of avoid_triplets_from",
+                index + 1,
+                Game::new(triplet.clone())
+            ),
+            GenError::Unsupported(message) => write!(f, "{}", message),
+            GenError::ThreadPool(error) => write!(f, "Cannot start the worker threads: {}", error),
+            GenError::Exhausted {
+                generated,
+                requested,
+            } => write!(
+                f,
+                "No more valid games exist: only {} of the {} requested games could be generated",
+                generated, requested
+            ),
+            GenError::OutOfAttempts {
+                generated,
+                requested,
+                max_attempts,
+            } => write!(
+                f,
+                "{} candidates in a row were rejected (max_attempts): only {} of the {} requested games could be generated",
+                max_attempts, generated, requested
+            ),
+            GenError::Inconsistent { differences } => {
+                let shown = differences.len().min(5);
+                write!(
+                    f,
+                    "Cannot resume, the saved sets disagree with the games: {}",
+                    differences[..shown].join("; ")
+                )?;
+                if differences.len() > shown {
+                    write!(f, "; and {} more", differences.len() - shown)?;
+                }
+                Ok(())
+            }
+            GenError::Cancelled { partial } => {
+                write!(f, "Generation cancelled after {} games", partial.len())
+            }
+        }
+    }
+}
+
+impl std::error::Error for GenError {}
+
+/// Supplies candidate games, first by uniform random sampling and, once almost every
+/// candidate is being rejected, by scanning the remaining space sequentially.
+///
+/// The scan starts at a random rank (drawn from the same seeded generator) and wraps
+/// around, visiting every game in the range exactly once, so generation is guaranteed to
+/// terminate while staying reproducible for a fixed seed.
+pub struct CandidateSource {
+    /// Random generator of range-valid games.
+    rng: Box<dyn FnMut() -> Game>,
+    /// Offset added to games of the reduced space to get the real numbers.
+    offset: i64,
+    /// The smallest number of the lottery, from which the games are ranked.
+    min_number: i64,
+    /// Number of distinct numbers in the reduced space.
+    span: i64,
+    /// Number of numbers per game.
+    numbers_per_game: i64,
+    /// Outcomes (`true` = rejected) of the most recent random attempts.
+    window: VecDeque<bool>,
+    /// Number of rejections currently in `window`.
+    rejections_in_window: usize,
+    /// Size of the window of attempts considered.
+    window_size: usize,
+    /// Rejection rate that triggers the sequential scan.
+    threshold: f64,
+    /// The sequential scan, once it has started.
+    scan: Option<Box<dyn Iterator<Item = Game>>>,
+    /// Number of games drawn from `rng`, the start of the scan included.
+    draws: u64,
+    /// The count of the words `rng` drew from its generator, when it is counted.
+    counter: Option<Box<dyn DrawCounter>>,
+    /// The rank the scan started from.
+    scan_start: i64,
+    /// Number of candidates given by the scan.
+    scanned: u64,
+    /// Candidates rejected in a row after which no more are given, when limited.
+    max_attempts: Option<u64>,
+    /// Number of candidates rejected since the last one accepted.
+    rejected_in_row: u64,
+}
+
+/// How far a candidate source went, for a source of the same generator and seed to carry on
+/// from there with `CandidateSource::restore`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SourceState {
+    /// Number of games drawn from the random generator, the start of the scan included.
+    pub draws: u64,
+    /// Number of 32-bit words the games were drawn with, `None` when they are not counted.
+    #[serde(default)]
+    pub rng_draws: Option<u64>,
+    /// Outcomes (`true` = rejected) of the most recent random attempts, oldest first.
+    pub window: Vec<bool>,
+    /// The rank the sequential scan started from and the candidates it gave, `None` before
+    /// it starts.
+    pub scan: Option<(i64, u64)>,
+    /// Number of candidates rejected since the last one accepted.
+    #[serde(default)]
+    pub rejected_in_row: u64,
+}
+
+impl CandidateSource {
+    /// Creates a candidate source for games with numbers in `[min_desired_number, max_number]`.
+    ///
+    /// # Arguments
+    /// * `seed` - A `u64` seed for reproducible randomness.
+    /// * `min_desired_number` - The minimum number allowed in a game.
+    /// * `max_number` - The maximum number in a game (e.g., 60).
+    /// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+    /// * `window_size` - Number of recent attempts used to measure the rejection rate.
+    /// * `threshold` - Rejection rate above which the sequential scan starts.
+    pub fn new(
+        seed: u64,
+        min_desired_number: i64,
+        max_number: i64,
+        numbers_per_game: i64,
+        window_size: usize,
+        threshold: f64,
+    ) -> Self {
+        Self::with_rng(
+            StdRng::seed_from_u64(seed),
+            min_desired_number,
+            max_number,
+            numbers_per_game,
+            window_size,
+            threshold,
+        )
+    }
+
+    /// Creates a candidate source drawing from any random number generator instead of a
+    /// seeded `StdRng`, counting the words drawn from it; see `CandidateSource::new` for the
+    /// other arguments.
+    pub fn with_rng<R: FastForward + 'static>(
+        rng: R,
+        min_desired_number: i64,
+        max_number: i64,
+        numbers_per_game: i64,
+        window_size: usize,
+        threshold: f64,
+    ) -> Self {
+        let rng = SharedRng::new(rng);
+        Self::with_game_rng(
+            custom_utils::create_ranged_game_rng_with(
+                rng.clone(),
+                min_desired_number,
+                max_number,
+                numbers_per_game,
+            ),
+            min_desired_number,
+            max_number,
+            numbers_per_game,
+            window_size,
+            threshold,
+        )
+        .with_draw_counter(rng)
+    }
+
+    /// Creates a candidate source drawing its random candidates from `game_rng`, which must
+    /// only return games of `[min_desired_number, max_number]`, e.g. by weighted sampling; see
+    /// `CandidateSource::new` for the other arguments.
+    pub fn with_game_rng(
+        game_rng: impl FnMut() -> Game + 'static,
+        min_desired_number: i64,
+        max_number: i64,
+        numbers_per_game: i64,
+        window_size: usize,
+        threshold: f64,
+    ) -> Self {
+        let offset = min_desired_number - 1;
+        Self {
+            rng: Box::new(game_rng),
+            offset,
+            min_number: 1,
+            span: max_number - offset,
+            numbers_per_game,
+            window: VecDeque::with_capacity(window_size),
+            rejections_in_window: 0,
+            window_size,
+            threshold,
+            scan: None,
+            draws: 0,
+            counter: None,
+            scan_start: 0,
+            scanned: 0,
+            max_attempts: None,
+            rejected_in_row: 0,
+        }
+    }
+
+    /// Gives no more candidates once `max_attempts` in a row were rejected, if given, until
+    /// one is accepted or the source is reset; see `is_out_of_attempts`.
+    pub fn with_max_attempts(mut self, max_attempts: Option<u64>) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Returns whether the last `max_attempts` candidates were rejected, the source giving no
+    /// more.
+    pub fn is_out_of_attempts(&self) -> bool {
+        self.max_attempts
+            .is_some_and(|max_attempts| self.rejected_in_row >= max_attempts)
+    }
+
+    /// Starts drawing at random again, forgetting the recent outcomes and the sequential scan,
+    /// if started, e.g. once the constraints changed: candidates turned down before may be
+    /// drawn again, and the scan starts again from a new rank should almost every candidate
+    /// be rejected anew.
+    pub fn reset(&mut self) {
+        self.window.clear();
+        self.rejections_in_window = 0;
+        self.scan = None;
+        self.scanned = 0;
+        self.rejected_in_row = 0;
+    }
+
+    /// Counts the words drawn by the games of the source with `counter`, which the game
+    /// generator draws from, so that `restore` can skip them rather than draw the games again.
+    pub fn with_draw_counter(mut self, counter: impl DrawCounter + 'static) -> Self {
+        self.counter = Some(Box::new(counter));
+        self
+    }
+
+    /// Returns the number of 32-bit words the candidates were drawn with, `None` when they are
+    /// not counted.
+    pub fn rng_draws(&self) -> Option<u64> {
+        self.counter.as_ref().map(|counter| counter.draws())
+    }
+
+    /// Sets the smallest number of the lottery (1 by default), from which the candidates are
+    /// ranked; `min_desired_number` must not be below it.
+    pub fn with_min_number(mut self, min_number: i64) -> Self {
+        self.min_number = min_number;
+        self
+    }
+
+    /// Returns the smallest number of the lottery.
+    pub fn min_number(&self) -> i64 {
+        self.min_number
+    }
+
+    /// Returns the largest number of the candidates.
+    pub fn max_number(&self) -> i64 {
+        self.offset + self.span
+    }
+
+    /// Returns whether the source has switched to the sequential scan.
+    pub fn is_scanning(&self) -> bool {
+        self.scan.is_some()
+    }
+
+    /// Returns the next candidate game, or `None` once the sequential scan has visited
+    /// every game in the range or the source is out of attempts.
+    pub fn next_candidate(&mut self) -> Option<Game> {
+        if self.is_out_of_attempts() {
+            return None;
+        }
+        match self.scan.as_mut() {
+            Some(scan) => {
+                self.scanned += 1;
+                scan.next()
+            }
+            None => {
+                self.draws += 1;
+                Some((self.rng)())
+            }
+        }
+    }
+
+    /// Returns how far the source went: the games drawn, the recent outcomes recorded and the
+    /// candidates scanned.
+    pub fn state(&self) -> SourceState {
+        SourceState {
+            draws: self.draws,
+            rng_draws: self.rng_draws(),
+            window: self.window.iter().copied().collect(),
+            scan: self
+                .is_scanning()
+                .then_some((self.scan_start, self.scanned)),
+            rejected_in_row: self.rejected_in_row,
+        }
+    }
+
+    /// Carries on from `state`, as saved by a source of the same generator and seed, so the
+    /// next candidates are those it would have given: the words it drew are skipped, for
+    /// a source counting them, or else the games it drew are drawn again and dropped. Meant
+    /// for a source that has not drawn yet.
+    pub fn restore(&mut self, state: &SourceState) {
+        match (&self.counter, state.rng_draws) {
+            (Some(counter), Some(rng_draws)) => {
+                counter.fast_forward(rng_draws.saturating_sub(counter.draws()));
+                self.draws = state.draws;
+            }
+            _ => {
+                while self.draws < state.draws {
+                    self.draws += 1;
+                    (self.rng)();
+                }
+            }
+        }
+        self.window = state.window.iter().copied().collect();
+        self.rejections_in_window = self.window.iter().filter(|&&rejected| rejected).count();
+        if let Some((start, scanned)) = state.scan {
+            self.scan_from(start);
+            while self.scanned < scanned {
+                self.next_candidate();
+            }
+        }
+        self.rejected_in_row = state.rejected_in_row;
+    }
+
+    /// Records whether the last candidate was rejected, switching to the sequential scan
+    /// when the rejection rate over the window exceeds the threshold.
+    ///
+    /// # Arguments
+    /// * `rejected` - `true` if the last candidate was rejected.
+    pub fn record(&mut self, rejected: bool) {
+        self.rejected_in_row = if rejected {
+            self.rejected_in_row + 1
+        } else {
+            0
+        };
+        if self.is_scanning() {
+            return;
+        }
+        self.window.push_back(rejected);
+        if rejected {
+            self.rejections_in_window += 1;
+        }
+        if self.window.len() > self.window_size && self.window.pop_front() == Some(true) {
+            self.rejections_in_window -= 1;
+        }
+        if self.window.len() == self.window_size
+            && self.rejections_in_window as f64 > self.threshold * self.window_size as f64
+        {
+            self.start_scan();
+        }
+    }
+
+    /// Starts scanning the reduced space from a random rank, wrapping around at the end.
+    fn start_scan(&mut self) {
+        self.draws += 1;
+        let start_game: Game = (self.rng)().iter().map(|&x| x - self.offset).collect();
+        self.scan_from(custom_utils::game2enum(&start_game));
+    }
+
+    /// Starts scanning the reduced space from rank `start`, wrapping around at the end.
+    fn scan_from(&mut self, start: i64) {
+        log::info!(
+            "Rejection rate over the last {} attempts exceeded {:.1}%, switching to a sequential scan from rank {}",
+            self.window_size,
+            self.threshold * 100.0,
+            start
+        );
+
+        let mut tail = GameIterator::new(self.span, self.numbers_per_game);
+        tail.skip_to(start);
+        let head = GameIterator::new(self.span, self.numbers_per_game).take(start as usize);
+        let offset = self.offset;
+        self.scan_start = start;
+        self.scan = Some(Box::new(
+            tail.chain(head)
+                .map(move |game| game.iter().map(|&x| x + offset).collect()),
+        ));
+    }
+}
+
+/// Generates games until `games` holds `no_of_games` of them, keeping `game_set` and
+/// `triplet_set` up to date.
+///
+/// # Arguments
+/// * `games` - The games accepted so far; new games are appended to it.
+/// * `game_set` - The combinadic numbers of the accepted games.
+/// * `triplet_set` - The combinadic numbers of the triplets of the accepted games.
+/// * `constraints` - The rules every game must follow, counting the rejections.
+/// * `source` - Supplies the candidate games.
+/// * `no_of_games` - The number of games wanted.
+/// * `stats` - The counters of the run, updated with every candidate.
+///
+/// # Returns
+/// * `Ok(())` once enough games were generated, or earlier if `stats` was interrupted.
+/// * An error if every remaining game in the range was tried without success.
+pub fn generate_remaining_games<S: RankSet>(
+    games: &mut Vec<Game>,
+    game_set: &mut NumberSet,
+    triplet_set: &mut S,
+    constraints: &mut ConstraintSet,
+    source: &mut CandidateSource,
+    no_of_games: usize,
+    stats: &mut GenerationStats,
+) -> Result<(), GenError> {
+    while games.len() < no_of_games
+        && accept_next_game(
+            games,
+            game_set,
+            triplet_set,
+            constraints,
+            source,
+            no_of_games,
+            stats,
+        )?
+    {}
+    Ok(())
+}
+
+/// Draws candidates until one is valid and accepts it, appending it to `games` and counting it
+/// in the sets, the constraints and `stats`; see `generate_remaining_games` for the arguments.
+///
+/// # Returns
+/// * Whether a game was accepted, `false` meaning `stats` was interrupted first.
+/// * An error if every remaining game in the range was tried without success.
+fn accept_next_game<S: RankSet>(
+    games: &mut Vec<Game>,
+    game_set: &mut NumberSet,
+    triplet_set: &mut S,
+    constraints: &mut ConstraintSet,
+    source: &mut CandidateSource,
+    no_of_games: usize,
+    stats: &mut GenerationStats,
+) -> Result<bool, GenError> {
+    while !stats.interrupted() {
+        if check_next_candidate(
+            games,
+            game_set,
+            triplet_set,
+            constraints,
+            source,
+            no_of_games,
+            stats,
+        )? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Draws a single candidate, accepting it if valid like `accept_next_game`. Once the source
+/// gives no more, a constraint is relaxed when the constraints relax (see `relax`).
+///
+/// # Returns
+/// * Whether the candidate was accepted, `false` also when a constraint was just relaxed.
+/// * An error if every remaining game in the range was tried without success, or if the
+///   source is out of attempts, and no constraint was relaxed.
+fn check_next_candidate<S: RankSet>(
+    games: &mut Vec<Game>,
+    game_set: &mut NumberSet,
+    triplet_set: &mut S,
+    constraints: &mut ConstraintSet,
+    source: &mut CandidateSource,
+    no_of_games: usize,
+    stats: &mut GenerationStats,
+) -> Result<bool, GenError> {
+    let Some(game) = check_candidate(
+        games,
+        game_set,
+        triplet_set,
+        constraints,
+        source,
+        no_of_games,
+        stats,
+    )?
+    else {
+        return Ok(false);
+    };
+    constraints.accept(&game);
+    stats.accept(&game);
+    games.push(game);
+    Ok(true)
+}
+
+/// Draws a single candidate and checks it like `check_next_candidate`, without accepting it:
+/// a valid candidate is counted in `game_set` and `triplet_set` only.
+///
+/// # Returns
+/// * The candidate if valid, `None` if it was turned down or a constraint was just relaxed.
+/// * An error like `check_next_candidate`.
+fn check_candidate<S: RankSet>(
+    games: &[Game],
+    game_set: &mut NumberSet,
+    triplet_set: &mut S,
+    constraints: &mut ConstraintSet,
+    source: &mut CandidateSource,
+    no_of_games: usize,
+    stats: &mut GenerationStats,
+) -> Result<Option<Game>, GenError> {
+    let game = match source.next_candidate() {
+        Some(game) => game,
+        None if relax(games, game_set, constraints, source, stats) => return Ok(None),
+        None => {
+            return Err(match source.max_attempts {
+                Some(max_attempts) if source.is_out_of_attempts() => GenError::OutOfAttempts {
+                    generated: games.len(),
+                    requested: no_of_games,
+                    max_attempts,
+                },
+                _ => GenError::Exhausted {
+                    generated: games.len(),
+                    requested: no_of_games,
+                },
+            })
+        }
+    };
+    let game_no = custom_utils::game2enum_from(&game, source.min_number());
+
+    let outcome = if !game_set.add_number(game_no) {
+        Outcome::DuplicateGame
+    } else if constraints.reject(&game) {
+        Outcome::Rejected
+    } else {
+        let triplet_nos = constraints.triplet_nos(&game, source.min_number());
+        if triplet_nos.is_none_or(|triplet_nos| triplet_set.check_and_insert_all(&triplet_nos)) {
+            Outcome::Valid
+        } else {
+            Outcome::SharedTriplet
+        }
+    };
+    source.record(outcome != Outcome::Valid);
+    stats.record(outcome);
+    if outcome == Outcome::Valid {
+        return Ok(Some(game));
+    }
+    log_rejection(&game, game_no, outcome, constraints);
+    Ok(None)
+}
+
+/// Gives up on the least important constraint left once no more candidates follow them all,
+/// when the constraints relax, with a warning logged. The candidates drawn so far are
+/// forgotten but for the games accepted, those the constraint turned down being worth drawing
+/// again, and the source starts over.
+///
+/// # Returns
+/// * Whether a constraint was relaxed.
+fn relax(
+    games: &[Game],
+    game_set: &mut NumberSet,
+    constraints: &mut ConstraintSet,
+    source: &mut CandidateSource,
+    stats: &GenerationStats,
+) -> bool {
+    let Some(name) = constraints.relax(games.len(), stats.attempts) else {
+        return false;
+    };
+    log::warn!(
+        "no more games follow every constraint after {} games and {} candidates, relaxing the {} constraint",
+        games.len(),
+        stats.attempts,
+        name
+    );
+    *game_set = games
+        .iter()
+        .map(|game| custom_utils::game2enum_from(game, source.min_number()))
+        .collect();
+    source.reset();
+    true
+}
+
+/// Logs at debug level why a candidate was turned down, with its rank and, for a candidate
+/// breaking the constraints, the violations found. A candidate sharing a triplet names the
+/// game holding it when the constraints track the triplet owners.
+fn log_rejection(game: &[i64], game_no: i64, outcome: Outcome, constraints: &ConstraintSet) {
+    if !log::log_enabled!(log::Level::Debug) {
+        return;
+    }
+    let candidate = Game::new(game.to_vec());
+    if outcome == Outcome::Rejected {
+        let violations: Vec<String> = constraints
+            .violations(game)
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect();
+        log::debug!(
+            "Rejected candidate {} (rank {}): {}: {}",
+            candidate,
+            game_no,
+            outcome,
+            violations.join("; ")
+        );
+    } else if let Some((triplet, owner)) = (outcome == Outcome::SharedTriplet)
+        .then(|| constraints.triplet_owner(game))
+        .flatten()
+    {
+        log::debug!(
+            "Rejected candidate {} (rank {}): {}: game #{} holds {}",
+            candidate,
+            game_no,
+            outcome,
+            owner + 1,
+            Game::new(triplet)
+        );
+    } else {
+        log::debug!(
+            "Rejected candidate {} (rank {}): {}",
+            candidate,
+            game_no,
+            outcome
+        );
+    }
+}
+
+/// The outcome of a generation run: the games along with the sets and counters built on the way.
+pub struct Generated {
+    /// The initial games followed by the generated ones.
+    pub games: Vec<Game>,
+    /// The combinadic numbers of the games drawn.
+    pub game_set: NumberSet,
+    /// The combinadic numbers of the triplets of the games.
+    pub triplet_set: TripletSet,
+    /// The constraints the games follow, with their rejection counts.
+    pub constraints: ConstraintSet,
+    /// The counters of the run.
+    pub stats: GenerationStats,
+    /// Whether the run was interrupted before reaching `no_of_games`, `games` holding the games
+    /// found so far.
+    pub interrupted: bool,
+}
+
+/// Generates the games described by a configuration: the initial games, completed with
+/// generated ones until there are `no_of_games` of them, no two sharing a triplet.
+///
+/// # Arguments
+/// * `config` - The configuration, which size of lottery, constraints, random number
+///   generator, strategy and threads to use.
+///
+/// # Returns
+/// * The games, in order, for `no_of_games` games.
+/// * An error if the games cannot be generated.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::config::Config;
+/// use rk_lottery::generation::generate_games;
+///
+/// let config = Config::from_toml(
+///     r#"
+///     no_of_games = 5
+///     initial_games = [[1, 2, 3, 4, 5, 6]]
+///     seed = 7
+///     max_number = 60
+///     min_desired_number = 1
+///     "#,
+/// )
+/// .unwrap();
+/// let games = generate_games(&config).unwrap();
+/// assert_eq!(games.len(), 5);
+/// assert_eq!(games[0].numbers(), &[1, 2, 3, 4, 5, 6]);
+/// ```
+pub fn generate_games(config: &Config) -> Result<Vec<Game>, GenError> {
+    generate(config, GenerationStats::default()).map(|generated| generated.games)
+}
+
+/// Generates the games described by a configuration like `generate_games`, for callers running
+/// it on a worker thread: `cancel` is checked before every candidate, so another thread can
+/// stop the run, and `progress` is given the counters every `PROGRESS_INTERVAL` candidates and
+/// once the games are generated.
+///
+/// Like a `GameGenerator`, it draws on a single thread, with the random, weighted or quick-pick strategy.
+///
+/// # Arguments
+/// * `config` - The configuration of the run.
+/// * `cancel` - Set, e.g. by the thread serving a request, to stop the run.
+/// * `progress` - Called with the counters as the run progresses.
+///
+/// # Returns
+/// * The games, in order, for `no_of_games` games.
+/// * `GenError::Cancelled` with the games so far once `cancel` is set, or another error if
+///   the games cannot be generated.
+pub fn generate_games_with_cancel(
+    config: &Config,
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(&GenerationStats),
+) -> Result<Vec<Game>, GenError> {
+    let mut generator = GameGenerator::builder().config(config.clone()).build()?;
+    while generator.games.len() < generator.no_of_games {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(GenError::Cancelled {
+                partial: generator.games,
+            });
+        }
+        generator.check_next_candidate()?;
+        if generator.stats.attempts % PROGRESS_INTERVAL == 0 {
+            progress(&generator.stats);
+        }
+    }
+    let generated = generator.into_generated();
+    progress(&generated.stats);
+    Ok(generated.games)
+}
+
+/// Generates the games described by a configuration like `generate_games`, also returning
+/// the sets of game and triplet numbers and the counters of the run.
+///
+/// Prints a warning to stderr when the requested number of games is close to the
+/// theoretical bound.
+///
+/// # Arguments
+/// * `config` - The configuration of the run.
+/// * `stats` - The counters to update, reporting the progress to their observer.
+pub fn generate(config: &Config, mut stats: GenerationStats) -> Result<Generated, GenError> {
+    check_feasibility(config)?;
+    let InitialGames {
+        games,
+        game_set,
+        triplet_set,
+        constraints,
+        ..
+    } = start_from_initial_games(config, &mut stats)?;
+    complete(config, games, game_set, triplet_set, constraints, stats)
+}
+
+/// Generates the games described by a configuration like `generate`, none sharing a triplet
+/// with those of `avoided` either, e.g. the triplets of the games of earlier runs.
+///
+/// # Arguments
+/// * `config` - The configuration of the run.
+/// * `avoided` - The combinadic numbers of the triplets no game may hold.
+/// * `stats` - The counters to update, reporting the progress to their observer.
+///
+/// # Returns
+/// * The games, their triplets only in `triplet_set`.
+/// * An error if an initial game holds a triplet of `avoided`, or if the games cannot be
+///   generated.
+pub fn generate_avoiding(
+    config: &Config,
+    avoided: &TripletSet,
+    mut stats: GenerationStats,
+) -> Result<Generated, GenError> {
+    check_feasibility(config)?;
+    let InitialGames {
+        games,
+        rows,
+        game_set,
+        mut triplet_set,
+        constraints,
+    } = start_from_initial_games(config, &mut stats)?;
+    for (&index, game) in rows.iter().zip(&games) {
+        let triplet_nos = custom_utils::game2triplet_enums_from(game, config.min_number);
+        if let Some(position) = triplet_nos.iter().position(|&no| avoided.contains(no)) {
+            return Err(GenError::StoredTriplet {
+                index,
+                triplet: custom_utils::game2triplets(game)[position].clone(),
+            });
+        }
+    }
+    triplet_set.merge_from(avoided);
+    let mut generated = complete(config, games, game_set, triplet_set, constraints, stats)?;
+    let mut own = TripletSet::default();
+    for game in &generated.games {
+        own.check_and_insert_all(&custom_utils::game2triplet_enums_from(
+            game,
+            config.min_number,
+        ));
+    }
+    generated.triplet_set = own;
+    Ok(generated)
+}
+
+/// Builds a `GameGenerator`; see `GameGenerator::builder`.
+#[derive(Default)]
+pub struct GameGeneratorBuilder {
+    /// The configuration of the run, which must be given.
+    config: Option<Config>,
+    /// The seed replacing that of the configuration.
+    seed: Option<u64>,
+    /// The counters the generator updates.
+    stats: GenerationStats,
+}
+
+impl GameGeneratorBuilder {
+    /// Sets the configuration of the run: the size of the lottery, the initial games, the
+    /// constraints, the random number generator and the strategy.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Sets the seed of the standard generator, replacing `seed` of the configuration.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sets the counters the generator updates, reporting the progress to their observer and
+    /// stopping the generator once their stop flag is raised.
+    pub fn stats(mut self, stats: GenerationStats) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Checks the initial games of the configuration and makes the generator, ready to draw.
+    ///
+    /// # Returns
+    /// * The generator, yielding the initial games first.
+    /// * An error if no configuration was given, if it sets `threads` or the greedy-coverage
+    ///   strategy, which do not draw the games one at a time, if the games requested cannot
+    ///   avoid sharing a triplet or if an initial game is invalid.
+    pub fn build(self) -> Result<GameGenerator, GenError> {
+        let mut config = self.config.ok_or(GenError::Unsupported(
+            "A GameGenerator needs a configuration, give it with `config`",
+        ))?;
+        if let Some(seed) = self.seed {
+            config.seed = Some(seed);
+        }
+        if config.threads.is_some() {
+            return Err(GenError::Unsupported(
+                "A GameGenerator draws the games on a single thread, unset `threads`",
+            ));
+        }
+        if config.strategy == Strategy::GreedyCoverage {
+            return Err(GenError::Unsupported(
+                "A GameGenerator cannot pick the games by greedy coverage, use `generate`",
+            ));
+        }
+        check_feasibility(&config)?;
+        let mut stats = self.stats;
+        let InitialGames {
+            games,
+            game_set,
+            triplet_set,
+            constraints,
+            ..
+        } = start_from_initial_games(&config, &mut stats)?;
+        GameGenerator::start(&config, games, game_set, triplet_set, constraints, stats)
+    }
+}
+
+/// Generates the games of a configuration one at a time, as an iterator: the initial games
+/// first, then each game as soon as it is accepted, until there are `no_of_games` of them.
+///
+/// Stopping early leaves the games yielded so far sharing no triplet, and `into_generated`
+/// gives them along with their sets, for saving or resuming. After an error, such as every
+/// remaining game of a tiny lottery being tried, the iterator ends.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::config::Config;
+/// use rk_lottery::generation::GameGenerator;
+///
+/// let config = Config::from_toml(
+///     "no_of_games = 50\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\n",
+/// )
+/// .unwrap();
+/// let mut generator = GameGenerator::builder().config(config).seed(42).build().unwrap();
+/// let games: Vec<_> = generator.by_ref().take(3).collect::<Result<_, _>>().unwrap();
+/// assert_eq!(games.len(), 3);
+/// assert_eq!(generator.stats().games, 3);
+/// ```
+pub struct GameGenerator {
+    /// The number of games wanted.
+    no_of_games: usize,
+    /// The games accepted so far, initial games included.
+    games: Vec<Game>,
+    /// The combinadic numbers of the games drawn.
+    game_set: NumberSet,
+    /// The combinadic numbers of the triplets of the accepted games.
+    triplet_set: TripletIndex,
+    /// The rules every game must follow, counting the rejections.
+    constraints: ConstraintSet,
+    /// Supplies the candidate games.
+    source: CandidateSource,
+    /// The counters of the run.
+    stats: GenerationStats,
+    /// Number of games of `games` yielded so far.
+    yielded: usize,
+    /// Whether an error ended the generation.
+    failed: bool,
+    /// When the generator started, for the wall time of the run.
+    started: Instant,
+}
+
+impl GameGenerator {
+    /// Starts building a generator, from a configuration given with
+    /// `GameGeneratorBuilder::config`.
+    pub fn builder() -> GameGeneratorBuilder {
+        GameGeneratorBuilder::default()
+    }
+
+    /// Makes a generator carrying on from games already checked and counted in the sets and
+    /// constraints.
+    fn start(
+        config: &Config,
+        games: Vec<Game>,
+        mut game_set: NumberSet,
+        triplet_set: TripletSet,
+        constraints: ConstraintSet,
+        mut stats: GenerationStats,
+    ) -> Result<Self, GenError> {
+        let started = Instant::now();
+        let mut triplet_set = TripletIndex::new(triplet_set, config.min_number, config.max_number);
+        start_run(config, &games, &mut game_set, &mut triplet_set, &mut stats)?;
+        Ok(Self {
+            no_of_games: config.no_of_games,
+            games,
+            game_set,
+            triplet_set,
+            constraints,
+            source: configured_source(config),
+            stats,
+            yielded: 0,
+            failed: false,
+            started,
+        })
+    }
+
+    /// Returns the counters of the run so far; the rejections by constraint and the wall time
+    /// are only added by `into_generated`.
+    pub fn stats(&self) -> &GenerationStats {
+        &self.stats
+    }
+
+    /// Returns the games yielded so far.
+    pub fn games(&self) -> &[Game] {
+        &self.games[..self.yielded]
+    }
+
+    /// Returns the number of games wanted, initial games included.
+    pub fn no_of_games(&self) -> usize {
+        self.no_of_games
+    }
+
+    /// Generates the remaining games without yielding them, writing a checkpoint of the run
+    /// as often as the `[checkpoint]` table of `config` says, if it has one. A checkpoint that
+    /// cannot be written is skipped with a warning logged, the run going on.
+    fn run(&mut self, config: &Config) -> Result<(), GenError> {
+        let Some(checkpoints) = &config.checkpoint else {
+            while self.games.len() < self.no_of_games && self.accept_next_game()? {}
+            self.yielded = self.games.len();
+            return Ok(());
+        };
+        let interval = checkpoints
+            .every_minutes
+            .map(|minutes| Duration::from_secs_f64(minutes * 60.0));
+        let mut last_games = self.games.len();
+        let mut last_time = Instant::now();
+        while self.games.len() < self.no_of_games && self.accept_next_game()? {
+            let due = checkpoints
+                .every_games
+                .is_some_and(|every| self.games.len() - last_games >= every)
+                || interval.is_some_and(|interval| last_time.elapsed() >= interval);
+            // The last game ends the run, which then saves its games anyway
+            if !due || self.games.len() == self.no_of_games {
+                continue;
+            }
+            last_games = self.games.len();
+            last_time = Instant::now();
+            match self
+                .checkpoint(config)
+                .save(&checkpoints.dir, checkpoints.keep)
+            {
+                Ok(path) => log::info!(
+                    "Wrote a checkpoint of {} games to {}",
+                    self.games.len(),
+                    path.display()
+                ),
+                Err(error) => log::warn!(
+                    "{}: {}, checkpoint of {} games skipped",
+                    checkpoints.dir,
+                    error,
+                    self.games.len()
+                ),
+            }
+        }
+        self.yielded = self.games.len();
+        Ok(())
+    }
+
+    /// Where the run is: its games, sets and counters so far and how far the candidate source
+    /// went, for `resume_from_checkpoint` to carry on from.
+    pub fn checkpoint(&self, config: &Config) -> Checkpoint {
+        let mut stats = self.stats.counters();
+        for (name, count) in self.constraints.rejection_counts() {
+            *stats
+                .constraint_rejections
+                .entry(name.to_string())
+                .or_default() += count;
+        }
+        stats.elapsed += self.started.elapsed();
+        stats.rng_draws += self.source.rng_draws().unwrap_or(0);
+        Checkpoint {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            config: config.clone(),
+            games: self.games.clone(),
+            game_set: self.game_set.clone(),
+            triplet_set: self.triplet_set.to_set(),
+            stats,
+            source: self.source.state(),
+        }
+    }
+
+    /// Accepts the next game; see `accept_next_game`.
+    fn accept_next_game(&mut self) -> Result<bool, GenError> {
+        accept_next_game(
+            &mut self.games,
+            &mut self.game_set,
+            &mut self.triplet_set,
+            &mut self.constraints,
+            &mut self.source,
+            self.no_of_games,
+            &mut self.stats,
+        )
+    }
+
+    /// Checks a single candidate; see `check_next_candidate`.
+    fn check_next_candidate(&mut self) -> Result<bool, GenError> {
+        check_next_candidate(
+            &mut self.games,
+            &mut self.game_set,
+            &mut self.triplet_set,
+            &mut self.constraints,
+            &mut self.source,
+            self.no_of_games,
+            &mut self.stats,
+        )
+    }
+
+    /// Draws candidates until one is valid and lets `decide` choose what becomes of it, the
+    /// initial games counting as yielded. An accepted game is yielded at once; the triplets of
+    /// any other are taken back, and a game put back by `Decision::Reroll` may be drawn again.
+    ///
+    /// # Arguments
+    /// * `decide` - Given the candidate and the games yielded so far.
+    ///
+    /// # Returns
+    /// * The candidate and the decision, `None` once there are `no_of_games` games or if the
+    ///   stop flag was raised first.
+    /// * An error if every remaining game in the range was tried without success.
+    pub fn review_next_candidate(
+        &mut self,
+        decide: impl FnOnce(&Game, &[Game]) -> Decision,
+    ) -> Result<Option<(Game, Decision)>, GenError> {
+        self.yielded = self.games.len();
+        while self.games.len() < self.no_of_games && !self.stats.interrupted() {
+            let Some(game) = check_candidate(
+                &self.games,
+                &mut self.game_set,
+                &mut self.triplet_set,
+                &mut self.constraints,
+                &mut self.source,
+                self.no_of_games,
+                &mut self.stats,
+            )?
+            else {
+                continue;
+            };
+            let decision = decide(&game, &self.games);
+            if decision == Decision::Accept {
+                self.constraints.accept(&game);
+                self.stats.accept(&game);
+                self.games.push(game.clone());
+                self.yielded = self.games.len();
+                return Ok(Some((game, decision)));
+            }
+            let min_number = self.source.min_number();
+            for triplet_no in self
+                .constraints
+                .triplet_nos(&game, min_number)
+                .into_iter()
+                .flatten()
+            {
+                self.triplet_set.remove_number(triplet_no);
+            }
+            if decision == Decision::Reroll {
+                self.game_set
+                    .remove_number(custom_utils::game2enum_from(&game, min_number));
+            }
+            return Ok(Some((game, decision)));
+        }
+        Ok(None)
+    }
+
+    /// Ends the run: the games accepted, yielded or not, with their sets and the counters of
+    /// the run, the words drawn, the rejections by constraint and the wall time added.
+    pub fn into_generated(mut self) -> Generated {
+        self.stats.rng_draws += self.source.rng_draws().unwrap_or(0);
+        finish_run(
+            self.no_of_games,
+            self.started,
+            self.games,
+            self.game_set,
+            self.triplet_set.into_set(),
+            self.constraints,
+            self.stats,
+        )
+    }
+}
+
+impl Iterator for GameGenerator {
+    type Item = Result<Game, GenError>;
+
+    /// Yields the next initial game, or else draws candidates until one is accepted; `None`
+    /// once there are `no_of_games` games, after an error or when the stop flag is raised.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.yielded == self.games.len() {
+            if self.failed || self.games.len() >= self.no_of_games {
+                return None;
+            }
+            match self.accept_next_game() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(error) => {
+                    self.failed = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+        self.yielded += 1;
+        Some(Ok(self.games[self.yielded - 1].clone()))
+    }
+}
+
+/// The initial games a run starts from, with the sets and constraints having accepted them.
+struct InitialGames {
+    /// The initial games kept, in order.
+    games: Vec<Game>,
+    /// The position in `initial_games` of each game kept, starting at 0.
+    rows: Vec<usize>,
+    /// The combinadic numbers of the games.
+    game_set: NumberSet,
+    /// The combinadic numbers of the triplets of the games.
+    triplet_set: TripletSet,
+    constraints: ConstraintSet,
+}
+
+/// Checks the initial games of a configuration, none breaking a constraint nor sharing a
+/// triplet with an earlier one, and builds the sets and constraints a run starts from.
+///
+/// The games breaking a constraint are skipped or fixed as `on_invalid_initial` says, the
+/// games skipped counted in `stats`.
+///
+/// # Returns
+/// * The initial games kept, with their sets and constraints.
+/// * An error naming the first initial game found invalid, by its position in
+///   `initial_games`.
+fn start_from_initial_games(
+    config: &Config,
+    stats: &mut GenerationStats,
+) -> Result<InitialGames, GenError> {
+    let initial_games = config.distinct_initial_games();
+    let mut games: Vec<Game> = Vec::with_capacity(initial_games.len());
+    // The position in `initial_games` of each game kept, repeats included, to name the games
+    // in the errors as configured
+    let mut rows: Vec<usize> = Vec::with_capacity(initial_games.len());
+    let mut game_set = NumberSet::new();
+    let mut triplet_set = TripletSet::default();
+    // The initial games holding each triplet, to name every pair a repeated triplet comes from
+    let mut triplet_owners: HashMap<i64, Vec<usize>> = HashMap::new();
+    let mut conflicts: Vec<TripletConflict> = Vec::new();
+    let mut duplicate = None;
+
+    // Build the constraints every game must follow, for both initial and generated games
+    let mut constraints = ConstraintSet::from_config(config);
+
+    // initialize the game_set and triplet_set with the initial games:
+    for (index, game) in initial_games {
+        // Sorted like the games read from a configuration file, when built otherwise, e.g.
+        // the games of a file resumed
+        let game = game.sorted();
+        // check if game is valid, reporting every rule it breaks
+        let violations = initial_violations(&constraints, &game);
+        let game = if violations.is_empty() {
+            game
+        } else {
+            match handle_invalid_initial(config, &constraints, index, game, violations)? {
+                Some(fixed) => fixed,
+                None => {
+                    stats.skipped_initial += 1;
+                    continue;
+                }
+            }
+        };
+        let game = &game;
+        // Convert game to number:
+        let game_no = custom_utils::game2enum_from(game, config.min_number);
+        // Convert to triplet numbers, for the games kept apart by their triplets:
+        if let Some(triplet_nos) = constraints.triplet_nos(game, config.min_number) {
+            // Insert the triplets, each one held already naming every earlier game holding it:
+            for (position, &triplet_no) in triplet_nos.iter().enumerate() {
+                triplet_set.add_number(triplet_no);
+                let owners = triplet_owners.entry(triplet_no).or_default();
+                for &earlier in owners.iter() {
+                    let triplet = custom_utils::game2triplets(game)[position].clone();
+                    match conflicts
+                        .iter_mut()
+                        .find(|conflict| conflict.index == index && conflict.earlier == earlier)
+                    {
+                        Some(conflict) => conflict.triplets.push(triplet),
+                        None => conflicts.push(TripletConflict {
+                            earlier,
+                            index,
+                            triplets: vec![triplet],
+                        }),
+                    }
+                }
+                owners.push(index);
+            }
+        }
+        // Add game to game_set, and count its numbers as used:
+        if !game_set.add_number(game_no) && duplicate.is_none() {
+            let earlier = games
+                .iter()
+                .position(|other| custom_utils::game2enum_from(other, config.min_number) == game_no)
+                .expect("the rank of the game is that of an earlier one");
+            duplicate = Some(GenError::DuplicateInitialGame {
+                index,
+                earlier: rows[earlier],
+            });
+        }
+        constraints.accept(game);
+        games.push(game.clone());
+        rows.push(index);
+    }
+    if !conflicts.is_empty() {
+        if !config.allow_initial_conflicts {
+            return Err(GenError::RepeatedTriplets { conflicts });
+        }
+        for conflict in &conflicts {
+            log::warn!(
+                "initial {}, kept as allow_initial_conflicts is set",
+                conflict
+            );
+        }
+    }
+    // Even when they may share triplets, the initial games may not repeat one another
+    match duplicate {
+        Some(error) => Err(error),
+        None => Ok(InitialGames {
+            games,
+            rows,
+            game_set,
+            triplet_set,
+            constraints,
+        }),
+    }
+}
+
+/// The rules an initial game breaks; initial games drawn in past contests are only warned
+/// about, when the draws are loaded.
+fn initial_violations(constraints: &ConstraintSet, game: &Game) -> Vec<Violation> {
+    let mut violations = constraints.violations(game);
+    violations.retain(|violation| *violation != Violation::HistoricalDraw);
+    violations
+}
+
+/// Deals with an initial game breaking the constraints as `on_invalid_initial` says, warning
+/// about the game skipped or fixed in the log.
+///
+/// # Arguments
+/// * `config` - The configuration of the run.
+/// * `constraints` - The constraints, having accepted the initial games kept before this one.
+/// * `index` - Position of the game in the initial games, starting at 0.
+/// * `game` - The game, its numbers sorted.
+/// * `violations` - The rules the game breaks.
+///
+/// # Returns
+/// * The game fixed, or `None` to leave it out.
+/// * An error naming the rules the game breaks when `on_invalid_initial` is `"error"`.
+fn handle_invalid_initial(
+    config: &Config,
+    constraints: &ConstraintSet,
+    index: usize,
+    game: Game,
+    violations: Vec<Violation>,
+) -> Result<Option<Game>, GenError> {
+    let listed = |violations: &[Violation]| -> String {
+        let violations: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        violations.join("; ")
+    };
+    match config.on_invalid_initial {
+        InvalidInitial::Error => Err(GenError::InvalidInitialGame { index, violations }),
+        InvalidInitial::Skip => {
+            log::warn!(
+                "initial game #{} ({}) skipped as on_invalid_initial is \"skip\": {}",
+                index + 1,
+                game,
+                listed(&violations)
+            );
+            Ok(None)
+        }
+        InvalidInitial::Fix => {
+            let fixed = game.clone().deduped();
+            let left = initial_violations(constraints, &fixed);
+            if left.is_empty() {
+                log::warn!(
+                    "initial game #{} ({}) fixed to {} as on_invalid_initial is \"fix\": {}",
+                    index + 1,
+                    game,
+                    fixed,
+                    listed(&violations)
+                );
+                return Ok(Some(fixed));
+            }
+            log::warn!(
+                "initial game #{} ({}) skipped as on_invalid_initial is \"fix\" but it cannot be fixed: {}",
+                index + 1,
+                game,
+                listed(&left)
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Generates the `batches` batches of games described by a configuration, batch `i` being
+/// generated as `generate` would with the settings of `config.batch(i)`. When
+/// `share_triplet_set` is set, each batch also avoids the triplets of the batches before it.
+///
+/// # Arguments
+/// * `config` - The configuration of the run.
+/// * `stats` - Gives the counters of each batch, from its number starting at 1.
+///
+/// # Returns
+/// * The batches, in order. If one was interrupted, it is the last one returned.
+/// * An error if a batch cannot be generated.
+pub fn generate_batches(
+    config: &Config,
+    mut stats: impl FnMut(usize) -> GenerationStats,
+) -> Result<Vec<Generated>, GenError> {
+    if config.share_triplet_set {
+        check_feasibility(&Config {
+            no_of_games: config.no_of_games * config.batches,
+            ..config.clone()
+        })?;
+    }
+    let mut batches = Vec::new();
+    // The triplets of every batch so far, when shared
+    let mut shared = TripletSet::default();
+    for batch in 1..=config.batches {
+        let batch_config = config.batch(batch);
+        let generated = if config.share_triplet_set && batch > 1 {
+            let constraints = ConstraintSet::from_config(&batch_config);
+            let triplet_set = std::mem::take(&mut shared);
+            let mut generated = complete(
+                &batch_config,
+                Vec::new(),
+                NumberSet::new(),
+                triplet_set,
+                constraints,
+                stats(batch),
+            )?;
+            // Keep the triplets of this batch's games only with the batch
+            let mut own = TripletSet::default();
+            for game in &generated.games {
+                own.check_and_insert_all(&custom_utils::game2triplet_enums_from(
+                    game,
+                    config.min_number,
+                ));
+            }
+            shared = std::mem::replace(&mut generated.triplet_set, own);
+            generated
+        } else {
+            generate(&batch_config, stats(batch))?
+        };
+        if config.share_triplet_set && batch == 1 {
+            shared.merge_from(&generated.triplet_set);
+        }
+        let interrupted = generated.interrupted;
+        batches.push(generated);
+        if interrupted {
+            break;
+        }
+    }
+    Ok(batches)
+}
+
+/// Adds games to those of an existing file. The games of the file come first, followed by the
+/// initial games of the configuration that neither repeat one of them nor share a triplet with
+/// them, a warning being logged for each initial game left out; then `no_of_games`
+/// more games are generated, initial games included, none sharing a triplet with the file.
+///
+/// # Arguments
+/// * `config` - The configuration, `no_of_games` being the number of games to add.
+/// * `existing` - The games of the file, in order.
+/// * `stats` - The counters of the run.
+///
+/// # Returns
+/// * The games of the file followed by the added ones.
+/// * An error if the games cannot be generated, or if the games of the file break the
+///   constraints or share a triplet.
+pub fn append(
+    config: &Config,
+    existing: Vec<Game>,
+    stats: GenerationStats,
+) -> Result<Generated, GenError> {
+    let (initial_games, warnings) = merge_initial_games(
+        &existing,
+        &config.initial_games,
+        &ConstraintSet::from_config(config),
+        config.min_number,
+    );
+    for warning in warnings {
+        log::warn!("{}", warning);
+    }
+    let config = Config {
+        no_of_games: existing.len() + config.no_of_games,
+        initial_games: existing.into_iter().chain(initial_games).collect(),
+        ..config.clone()
+    };
+    generate(&config, stats)
+}
+
+/// Leaves out the initial games repeating a game of `existing` or, unless `constraints` let
+/// games share triplets, sharing a triplet with one.
+///
+/// # Returns
+/// * The initial games kept, in order.
+/// * The reason each left out game was, for a warning.
+fn merge_initial_games(
+    existing: &[Game],
+    initial_games: &[Game],
+    constraints: &ConstraintSet,
+    min_number: i64,
+) -> (Vec<Game>, Vec<String>) {
+    let sorted = |game: &Game| {
+        let mut numbers = game.to_vec();
+        numbers.sort_unstable();
+        numbers
+    };
+    // The row of the first existing game containing each triplet, by combinadic number
+    let mut triplet_rows = SubsetIndex::new();
+    for (index, game) in existing.iter().enumerate() {
+        let triplet_nos = constraints.triplet_nos(&sorted(game), min_number);
+        for triplet_no in triplet_nos.into_iter().flatten() {
+            triplet_rows.insert(triplet_no, index + 1);
+        }
+    }
+
+    let mut kept = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, game) in initial_games.iter().enumerate() {
+        let numbers = sorted(game);
+        if let Some(row) = existing.iter().position(|other| sorted(other) == numbers) {
+            warnings.push(format!(
+                "Initial game #{} ({}) is already game #{} of the file, left out",
+                index + 1,
+                game,
+                row + 1
+            ));
+            continue;
+        }
+        let triplet_nos = constraints.triplet_nos(&numbers, min_number);
+        let shared = triplet_nos
+            .iter()
+            .flatten()
+            .zip(custom_utils::game2triplets(&numbers))
+            .find_map(|(no, triplet)| triplet_rows.owner(*no).map(|row| (row, triplet)));
+        if let Some((row, triplet)) = shared {
+            warnings.push(format!(
+                "Initial game #{} ({}) shares triplet {} with game #{} of the file, left out",
+                index + 1,
+                game,
+                Game::new(triplet),
+                row
+            ));
+            continue;
+        }
+        kept.push(game.clone());
+    }
+    (kept, warnings)
+}
+
+/// Continues a previous run: completes its games, whose numbers are saved in `game_set` and
+/// `triplet_set`, with generated ones until there are `no_of_games` of them.
+///
+/// # Arguments
+/// * `config` - The configuration of the run.
+/// * `games` - The games of the previous run.
+/// * `game_set` - The combinadic numbers of the games, as saved by the previous run.
+/// * `triplet_set` - The combinadic numbers of their triplets, as saved by the previous run.
+/// * `stats` - The counters to update, reporting the progress to their observer.
+///
+/// # Returns
+/// * The previous games followed by the generated ones.
+/// * An error if the sets disagree with the games (see `check_consistency`), or if the games
+///   cannot be generated.
+pub fn resume(
+    config: &Config,
+    games: Vec<Game>,
+    game_set: NumberSet,
+    triplet_set: TripletSet,
+    stats: GenerationStats,
+) -> Result<Generated, GenError> {
+    check_feasibility(config)?;
+    check_consistency(&games, &game_set, &triplet_set, config.min_number)?;
+    let mut constraints = ConstraintSet::from_config(config);
+    for game in &games {
+        constraints.accept(game);
+    }
+    complete(config, games, game_set, triplet_set, constraints, stats)
+}
+
+/// Carries on a run from one of its checkpoints, as if it had never stopped: for the same
+/// seed, the games are those the run would have given, and it goes on writing checkpoints.
+///
+/// # Arguments
+/// * `config` - The configuration of the run, that of the checkpoint, with the files it names
+///   loaded.
+/// * `checkpoint` - The checkpoint, as written by the run.
+/// * `stats` - The counters to update, reporting the progress to their observer; those of the
+///   checkpoint are added to them.
+///
+/// # Returns
+/// * The games of the checkpoint followed by the generated ones.
+/// * An error if the sets of the checkpoint disagree with its games (see
+///   `check_consistency`), or if the games cannot be generated.
+pub fn resume_from_checkpoint(
+    config: &Config,
+    checkpoint: Checkpoint,
+    mut stats: GenerationStats,
+) -> Result<Generated, GenError> {
+    check_feasibility(config)?;
+    if config.unique_triplets() {
+        check_consistency(
+            &checkpoint.games,
+            &checkpoint.game_set,
+            &checkpoint.triplet_set,
+            config.min_number,
+        )?;
+    }
+    let mut constraints = ConstraintSet::from_config(config);
+    for game in &checkpoint.games {
+        constraints.accept(game);
+    }
+    // The source counts the words it skips, those of the checkpoint
+    let mut carried = checkpoint.stats;
+    carried.rng_draws = 0;
+    stats += &carried;
+    let mut generator = GameGenerator::start(
+        config,
+        checkpoint.games,
+        checkpoint.game_set,
+        checkpoint.triplet_set,
+        constraints,
+        stats,
+    )?;
+    generator.source.restore(&checkpoint.source);
+    generator.run(config)?;
+    Ok(arranged(config, generator.into_generated()))
+}
+
+/// Checks that saved sets agree with a batch of games: `game_set` holds the combinadic number
+/// of every game (along with those of the candidates rejected while drawing them), and
+/// `triplet_set` holds exactly the combinadic numbers of their triplets, none shared between
+/// games.
+///
+/// # Returns
+/// * An error listing every difference found, if any.
+pub fn check_consistency(
+    games: &[Game],
+    game_set: &NumberSet,
+    triplet_set: &TripletSet,
+    min_number: i64,
+) -> Result<(), GenError> {
+    let mut differences = Vec::new();
+    let mut expected_triplets = NumberSet::new();
+    for (index, game) in games.iter().enumerate() {
+        if game.len() != 6 {
+            differences.push(format!("game #{} has {} numbers", index + 1, game.len()));
+            continue;
+        }
+        // The games of a file may have their numbers in any order
+        let game_no = custom_utils::game2enum_from(&game.clone().sorted(), min_number);
+        if !game_set.contains(game_no) {
+            differences.push(format!(
+                "game #{} ({}) is missing from the game set",
+                index + 1,
+                game
+            ));
+        }
+        let triplet_nos = custom_utils::game2triplet_enums_from(game, min_number);
+        let triplets = custom_utils::game2triplets(game);
+        for (triplet_no, triplet) in triplet_nos.iter().zip(triplets) {
+            let triplet = Game::new(triplet);
+            if !triplet_set.contains(*triplet_no) {
+                differences.push(format!(
+                    "triplet {} of game #{} is missing from the triplet set",
+                    triplet,
+                    index + 1
+                ));
+            }
+            if !expected_triplets.add_number(*triplet_no) {
+                differences.push(format!(
+                    "triplet {} of game #{} is in an earlier game",
+                    triplet,
+                    index + 1
+                ));
+            }
+        }
+    }
+    let mut extra: Vec<i64> = triplet_set
+        .ranks()
+        .filter(|&triplet_no| !expected_triplets.contains(triplet_no))
+        .collect();
+    extra.sort_unstable();
+    if !extra.is_empty() {
+        differences.push(format!(
+            "the triplet set holds {} number(s) of no game triplet, starting with {}",
+            extra.len(),
+            extra[0]
+        ));
+    }
+
+    if differences.is_empty() {
+        Ok(())
+    } else {
+        Err(GenError::Inconsistent { differences })
+    }
+}
+
+/// What became of candidates drawn by `sample_candidates`.
+#[derive(Debug)]
+pub struct CandidateSample {
+    /// The candidates drawn.
+    pub drawn: usize,
+    /// The candidates passing the constraints.
+    pub passed: usize,
+    /// The time taken to draw, rank and check them.
+    pub elapsed: Duration,
+}
+
+impl CandidateSample {
+    /// The fraction of the candidates passing the constraints.
+    pub fn pass_rate(&self) -> f64 {
+        self.passed as f64 / self.drawn.max(1) as f64
+    }
+
+    /// The candidates checked per second.
+    pub fn attempts_per_second(&self) -> f64 {
+        self.drawn as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+/// Draws `count` candidates as a single-threaded run of `config` would, ranking them and their
+/// triplets and checking them against the constraints, but keeping none of them: how often
+/// they pass and how fast they are checked tell how long the run may take.
+///
+/// # Arguments
+/// * `config` - The configuration of the run.
+/// * `count` - The number of candidates to draw.
+pub fn sample_candidates(config: &Config, count: usize) -> CandidateSample {
+    let mut source = configured_source(config);
+    let mut constraints = ConstraintSet::from_config(config);
+    let started = Instant::now();
+    let mut passed = 0;
+    for _ in 0..count {
+        // Nothing is recorded, so the source never turns to the scan, which can run out
+        let game = source
+            .next_candidate()
+            .expect("random candidates never run out");
+        std::hint::black_box(custom_utils::game2enum_from(&game, source.min_number()));
+        if !constraints.reject(&game) {
+            passed += 1;
+            std::hint::black_box(constraints.triplet_nos(&game, source.min_number()));
+        }
+    }
+    CandidateSample {
+        drawn: count,
+        passed,
+        elapsed: started.elapsed(),
+    }
+}
+
+/// Checks that a run of `config` can start, as `generate` does before drawing any candidate:
+/// the games requested are not ruled out by the constraints, with a warning logged when they
+/// are close to the bound, and the initial games are valid.
+pub fn check_start(config: &Config) -> Result<(), GenError> {
+    check_feasibility(config)?;
+    start_from_initial_games(config, &mut GenerationStats::default()).map(|_| ())
+}
+
+/// Makes sure the requested number of games is not ruled out by the constraints, logging a
+/// warning when it is close to the bound.
+fn check_feasibility(config: &Config) -> Result<(), GenError> {
+    let numbers_per_game = config.numbers_per_game();
+    // Games sharing no triplet share at most 2 numbers, so a larger overlap bounds nothing more
+    let max_overlap = match config.max_overlap() {
+        Some(max_overlap) if !config.unique_triplets() || max_overlap < 2 => Some(max_overlap),
+        _ => (!config.unique_triplets()).then_some(numbers_per_game),
+    };
+    let bound = match max_overlap {
+        Some(max_overlap) => feasibility::overlap_upper_bound(
+            config.min_desired_number,
+            config.max_number,
+            numbers_per_game as i64,
+            max_overlap as i64,
+        ),
+        None => feasibility::triplet_upper_bound(
+            config.min_desired_number,
+            config.max_number,
+            numbers_per_game as i64,
+        ),
+    };
+    match feasibility::check_feasibility(config.no_of_games, bound) {
+        Feasibility::Feasible => {}
+        Feasibility::NearBound { bound } => log::warn!(
+            "{} games requested, close to the upper bound of {} games for numbers {} to {}; generation may be slow or fail",
+            config.no_of_games, bound, config.min_desired_number, config.max_number
+        ),
+        Feasibility::Infeasible { bound } => {
+            return Err(match max_overlap {
+                Some(max_overlap) => GenError::OverlapInfeasible {
+                    requested: config.no_of_games,
+                    max_overlap,
+                    bound,
+                },
+                None => GenError::Infeasible {
+                    requested: config.no_of_games,
+                    bound,
+                    min_desired_number: config.min_desired_number,
+                    max_number: config.max_number,
+                },
+            })
+        }
+    }
+    if let Some(max_number_usage) = config.max_number_usage {
+        let numbers = (config.max_number - config.min_desired_number + 1).max(0) as usize;
+        let bound = feasibility::usage_upper_bound(numbers, max_number_usage, numbers_per_game);
+        if config.no_of_games > bound {
+            return Err(GenError::UsageInfeasible {
+                requested: config.no_of_games,
+                max_number_usage,
+                bound,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Generates games with the configured generator, strategy and threads, after those already
+/// in `games` and in the sets, until there are `no_of_games` of them, adding the rejections by
+/// constraint and the wall time of the run to `stats` at the end.
+fn complete(
+    config: &Config,
+    mut games: Vec<Game>,
+    mut game_set: NumberSet,
+    triplet_set: TripletSet,
+    mut constraints: ConstraintSet,
+    mut stats: GenerationStats,
+) -> Result<Generated, GenError> {
+    // A single thread drawing at random accepts the games one at a time
+    if config.threads.is_none() && config.strategy != Strategy::GreedyCoverage {
+        let mut generator =
+            GameGenerator::start(config, games, game_set, triplet_set, constraints, stats)?;
+        generator.run(config)?;
+        return Ok(arranged(config, generator.into_generated()));
+    }
+    let started = Instant::now();
+    let mut triplet_set = TripletIndex::new(triplet_set, config.min_number, config.max_number);
+    start_run(config, &games, &mut game_set, &mut triplet_set, &mut stats)?;
+    match (config.threads, config.rng) {
+        (Some(threads), RngKind::Std) => generate_remaining_games_parallel(
+            &mut games,
+            &mut game_set,
+            &mut triplet_set,
+            &mut constraints,
+            config.seed.unwrap_or(config::DEFAULT_SEED),
+            config.min_number,
+            config.min_desired_number,
+            config.max_number,
+            config.numbers_per_game() as i64,
+            threads,
+            config.no_of_games,
+            &mut stats,
+        )?,
+        (Some(_), RngKind::Os | RngKind::Counter) => {
+            return Err(GenError::Unsupported(
+                "Parallel generation needs the seeded \"std\" generator",
+            ))
+        }
+        (None, _) => {
+            let mut source = configured_source(config);
+            let generated = generate_greedy_coverage(
+                &mut games,
+                &mut game_set,
+                &mut triplet_set,
+                &mut constraints,
+                &mut source,
+                config.no_of_games,
+                GREEDY_SAMPLE_SIZE,
+                &mut stats,
+            );
+            stats.rng_draws += source.rng_draws().unwrap_or(0);
+            generated?
+        }
+    }
+    Ok(arranged(
+        config,
+        finish_run(
+            config.no_of_games,
+            started,
+            games,
+            game_set,
+            triplet_set.into_set(),
+            constraints,
+            stats,
+        ),
+    ))
+}
+
+/// Puts the games of a run in the order of `output_order`, then labels those left without
+/// one, when the configuration labels its games, so that automatic labels follow the rows.
+fn arranged(config: &Config, mut generated: Generated) -> Generated {
+    order_games(&mut generated.games, config);
+    if config.labeled() {
+        game::label_games(&mut generated.games, &config.label_pool);
+    }
+    generated
+}
+
+/// Puts games in the order of `output_order`: left as found, shuffled with the seed of the
+/// run (from OS entropy for the "os" generator), or sorted by their numbers or their rank.
+///
+/// # Arguments
+/// * `games` - The games, in the order they were found.
+/// * `config` - The configuration, which order, seed and smallest number to use.
+pub fn order_games(games: &mut [Game], config: &Config) {
+    match config.output_order {
+        OutputOrder::Discovery => {}
+        OutputOrder::Shuffle => match config.effective_seed() {
+            Some(seed) => games.shuffle(&mut StdRng::seed_from_u64(seed)),
+            None => games.shuffle(&mut rand::rngs::OsRng),
+        },
+        OutputOrder::Asc => games.sort_by_cached_key(|game| game.clone().sorted()),
+        OutputOrder::Rank => {
+            games.sort_by_cached_key(|game| custom_utils::game2enum_from(game, config.min_number))
+        }
+    }
+}
+
+/// Checks the configuration can be generated from as it is, counts the games a run starts
+/// from in `stats` and sizes the sets for the games to come at once, rather than rehashing
+/// them as they grow.
+fn start_run(
+    config: &Config,
+    games: &[Game],
+    game_set: &mut NumberSet,
+    triplet_set: &mut TripletIndex,
+    stats: &mut GenerationStats,
+) -> Result<(), GenError> {
+    stats.requested = config.no_of_games;
+    stats.games = games.len();
+    for game in games {
+        stats.start_from(game);
+    }
+    let missing = config.no_of_games.saturating_sub(games.len());
+    game_set.reserve(missing);
+    if config.unique_triplets() {
+        triplet_set.reserve(20 * missing);
+    }
+    if config.threads.is_some() && config.strategy == Strategy::GreedyCoverage {
+        return Err(GenError::Unsupported(
+            "The greedy-coverage strategy runs on a single thread, unset `threads`",
+        ));
+    }
+    if config.threads.is_some() && config.strategy == Strategy::Weighted {
+        return Err(GenError::Unsupported(
+            "The weighted strategy runs on a single thread, unset `threads`",
+        ));
+    }
+    if config.weights_from_history.is_some() {
+        return Err(GenError::Unsupported(
+            "Load the weights of `weights_from_history` into `weights` before generating",
+        ));
+    }
+    if config.historical_draws_file.is_some() && config.historical_ranks.is_none() {
+        return Err(GenError::Unsupported(
+            "Load the draws of `historical_draws_file` into `historical_ranks` before generating",
+        ));
+    }
+    if config.blacklist_file.is_some() && config.blacklist_file_games.is_none() {
+        return Err(GenError::Unsupported(
+            "Load the games of `blacklist_file` into `blacklist_file_games` before generating",
+        ));
+    }
+    log::info!(
+        "Generating {} games with the {:?} strategy, {} already there",
+        config.no_of_games,
+        config.strategy,
+        games.len()
+    );
+    Ok(())
+}
+
+/// Ends a run started at `started`, adding the rejections by constraint and the wall time of
+/// the run to `stats` and reporting them.
+fn finish_run(
+    no_of_games: usize,
+    started: Instant,
+    games: Vec<Game>,
+    game_set: NumberSet,
+    triplet_set: TripletSet,
+    constraints: ConstraintSet,
+    mut stats: GenerationStats,
+) -> Generated {
+    for (name, count) in constraints.rejection_counts() {
+        *stats
+            .constraint_rejections
+            .entry(name.to_string())
+            .or_default() += count;
+    }
+    stats.elapsed += started.elapsed();
+    stats.report();
+    let interrupted = games.len() < no_of_games;
+    if interrupted {
+        log::info!("Interrupted after {} of {} games", games.len(), no_of_games);
+    }
+    Generated {
+        games,
+        game_set,
+        triplet_set,
+        constraints,
+        stats,
+        interrupted,
+    }
+}
+
+/// The candidate source of a single-threaded run: random draws from the configured generator,
+/// restricted to the desired number range, falling back to a sequential scan once almost every
+/// candidate is rejected.
+fn configured_source(config: &Config) -> CandidateSource {
+    match config.rng {
+        RngKind::Std => candidate_source(
+            StdRng::seed_from_u64(config.seed.unwrap_or(config::DEFAULT_SEED)),
+            config,
+        ),
+        RngKind::Counter => candidate_source(
+            CounterRng::seed_from_u64(config.seed.unwrap_or(config::DEFAULT_SEED)),
+            config,
+        ),
+        RngKind::Os => {
+            log::warn!("drawing games from OS entropy: the seed is ignored and this run cannot be reproduced");
+            candidate_source(rand::rngs::OsRng, config)
+        }
+    }
+}
+
+/// The candidate source of a single-threaded run drawing from `rng`: uniformly over the games
+/// of the desired number range, or by the weights of the numbers for the weighted strategy.
+fn candidate_source<R: FastForward + 'static>(rng: R, config: &Config) -> CandidateSource {
+    let numbers_per_game = config.numbers_per_game() as i64;
+    let source = if config.strategy == Strategy::Weighted {
+        let rng = SharedRng::new(rng);
+        CandidateSource::with_game_rng(
+            custom_utils::create_weighted_game_rng_with(
+                rng.clone(),
+                &config.number_weights(),
+                numbers_per_game as usize,
+            ),
+            config.min_desired_number,
+            config.max_number,
+            numbers_per_game,
+            REJECTION_WINDOW,
+            REJECTION_THRESHOLD,
+        )
+        .with_draw_counter(rng)
+    } else {
+        CandidateSource::with_rng(
+            rng,
+            config.min_desired_number,
+            config.max_number,
+            numbers_per_game,
+            REJECTION_WINDOW,
+            REJECTION_THRESHOLD,
+        )
+    };
+    source
+        .with_min_number(config.min_number)
+        .with_max_attempts(config.max_attempts)
+}
+
+/// A candidate that passed the checks, with the combinadic numbers of its triplets.
+struct CheckedCandidate {
+    game: Game,
+    /// The combinadic numbers of its triplets, `None` for a game not kept apart by them.
+    triplet_nos: Option<[i64; 20]>,
+}
+
+/// Generates games until `games` holds `no_of_games` of them, each time picking, among a pool
+/// of `sample_size` valid candidates, the one covering the most pairs and numbers not yet
+/// covered by the accepted games.
+///
+/// Since no two games may share a triplet, every valid candidate covers as many new triplets
+/// as any other, so the choice is made on pairs first and numbers second, the earliest drawn
+/// candidate winning ties. Candidates not picked stay in the pool while they remain valid,
+/// so the sequential scan of `source` still reaches every game of the range.
+///
+/// # Arguments
+/// * `games` - The games accepted so far; new games are appended to it.
+/// * `game_set` - The combinadic numbers of the games drawn so far.
+/// * `triplet_set` - The combinadic numbers of the triplets of the accepted games.
+/// * `constraints` - The rules every game must follow, counting the rejections.
+/// * `source` - Supplies the candidate games.
+/// * `no_of_games` - The number of games wanted.
+/// * `sample_size` - The number of valid candidates compared at each step.
+/// * `stats` - The counters of the run, updated with every candidate.
+///
+/// # Returns
+/// * `Ok(())` once enough games were generated, or earlier if `stats` was interrupted.
+/// * An error if every remaining game in the range was tried without success.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_greedy_coverage<S: RankSet>(
+    games: &mut Vec<Game>,
+    game_set: &mut NumberSet,
+    triplet_set: &mut S,
+    constraints: &mut ConstraintSet,
+    source: &mut CandidateSource,
+    no_of_games: usize,
+    sample_size: usize,
+    stats: &mut GenerationStats,
+) -> Result<(), GenError> {
+    let min_number = source.min_number();
+    // Pairs and numbers have small known domains, probed for every candidate of the pool
+    let span = (source.max_number() - min_number + 1).max(0);
+    let mut pair_set = DenseNumberSet::new(custom_utils::binomial(span, 2) as usize);
+    let mut number_set = DenseNumberSet::new((source.max_number() + 1).max(0) as usize);
+    for game in games.iter() {
+        cover(game, min_number, &mut pair_set, &mut number_set);
+    }
+
+    let mut pool: Vec<CheckedCandidate> = Vec::new();
+    let mut exhausted = false;
+    while games.len() < no_of_games && !stats.interrupted() {
+        // Drop the candidates made invalid by the last accepted game, then top the pool up
+        pool.retain(|candidate| {
+            !candidate
+                .triplet_nos
+                .iter()
+                .flatten()
+                .any(|&no| triplet_set.contains(no))
+        });
+        while pool.len() < sample_size && !exhausted {
+            if stats.interrupted() {
+                return Ok(());
+            }
+            let game = match source.next_candidate() {
+                Some(game) => game,
+                None => {
+                    exhausted = true;
+                    break;
+                }
+            };
+            let game_no = custom_utils::game2enum_from(&game, min_number);
+            let mut triplet_nos = None;
+            let outcome = if !game_set.add_number(game_no) {
+                Outcome::DuplicateGame
+            } else if constraints.reject(&game) {
+                Outcome::Rejected
+            } else {
+                triplet_nos = constraints.triplet_nos(&game, min_number);
+                if triplet_nos
+                    .iter()
+                    .flatten()
+                    .any(|&no| triplet_set.contains(no))
+                {
+                    Outcome::SharedTriplet
+                } else {
+                    Outcome::Valid
+                }
+            };
+            source.record(outcome != Outcome::Valid);
+            stats.record(outcome);
+            if outcome == Outcome::Valid {
+                pool.push(CheckedCandidate { game, triplet_nos });
+            } else {
+                log_rejection(&game, game_no, outcome, constraints);
+            }
+        }
+
+        let mut best: Option<(usize, (usize, usize))> = None;
+        for (index, candidate) in pool.iter().enumerate() {
+            let score = new_coverage(&candidate.game, min_number, &pair_set, &number_set);
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((index, score));
+            }
+        }
+        let Some((index, _)) = best else {
+            return Err(GenError::Exhausted {
+                generated: games.len(),
+                requested: no_of_games,
+            });
+        };
+
+        let candidate = pool.remove(index);
+        // Games accepted since the candidate was checked may have used up one of its numbers
+        if constraints.first_violation(&candidate.game).is_some() {
+            continue;
+        }
+        if let Some(triplet_nos) = candidate.triplet_nos {
+            triplet_set.check_and_insert_all(&triplet_nos);
+        }
+        cover(&candidate.game, min_number, &mut pair_set, &mut number_set);
+        constraints.accept(&candidate.game);
+        stats.accept(&candidate.game);
+        games.push(candidate.game);
+    }
+    Ok(())
+}
+
+/// Adds the pairs and numbers of a game to the covered ones.
+fn cover(
+    game: &[i64],
+    min_number: i64,
+    pair_set: &mut DenseNumberSet,
+    number_set: &mut DenseNumberSet,
+) {
+    for pair in custom_utils::game2ksubsets(game, 2) {
+        pair_set.add_number(pair_rank(&pair, min_number));
+    }
+    for &number in game {
+        number_set.add_number(number);
+    }
+}
+
+/// The rank of a pair of a game, whose numbers are distinct numbers of the lottery.
+fn pair_rank(pair: &[i64], min_number: i64) -> i64 {
+    custom_utils::subset2enum_from(pair, 2, min_number)
+        .expect("the numbers of a game are distinct numbers of the lottery")
+}
+
+/// Counts the pairs and numbers of a game not covered yet.
+fn new_coverage(
+    game: &[i64],
+    min_number: i64,
+    pair_set: &DenseNumberSet,
+    number_set: &DenseNumberSet,
+) -> (usize, usize) {
+    let new_pairs = custom_utils::game2ksubsets(game, 2)
+        .iter()
+        .filter(|pair| !pair_set.contains(pair_rank(pair, min_number)))
+        .count();
+    let new_numbers = game
+        .iter()
+        .filter(|&&number| !number_set.contains(number))
+        .count();
+    (new_pairs, new_numbers)
+}
+
+/// A candidate drawn by a worker of the parallel generation, with its combinadic numbers.
+struct DrawnCandidate {
+    game: Game,
+    game_no: i64,
+    /// The combinadic numbers of its triplets, `None` for a game not kept apart by them.
+    triplet_nos: Option<[i64; 20]>,
+    /// Whether one of its triplets was already held by an accepted game when it was drawn.
+    shared_triplet: bool,
+}
+
+/// Draws a batch of `batch_size` candidates with each generator, in parallel, ranking their
+/// triplets and noting those already held in `triplet_set` or `claimed`. `claimed` may be
+/// filled by another thread meanwhile: a triplet seen in it only ever stays held.
+fn draw_batches<R, S>(
+    rngs: &mut [R],
+    batch_size: usize,
+    min_number: i64,
+    unique_triplets: bool,
+    triplet_set: &S,
+    claimed: &ShardedNumberSet,
+) -> Vec<Vec<DrawnCandidate>>
+where
+    R: FnMut() -> Game + Send,
+    S: RankSet + Sync,
+{
+    rngs.par_iter_mut()
+        .map(|rng| {
+            (0..batch_size)
+                .map(|_| {
+                    let game = rng();
+                    let game_no = custom_utils::game2enum_from(&game, min_number);
+                    let triplet_nos = (unique_triplets && game.len() == 6)
+                        .then(|| custom_utils::game2triplet_enums_from(&game, min_number));
+                    let shared_triplet = triplet_nos
+                        .iter()
+                        .flatten()
+                        .any(|&no| triplet_set.contains(no) || claimed.contains(no));
+                    DrawnCandidate {
+                        game,
+                        game_no,
+                        triplet_nos,
+                        shared_triplet,
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Generates games like `generate_remaining_games`, with `threads` workers drawing
+/// candidates in parallel.
+///
+/// Worker `i` draws from its own generator seeded with `seed + i`. Each round, every worker
+/// draws a batch of candidates and ranks their triplets, while the batches of the round
+/// before are merged on the calling thread, worker by worker and in drawing order, checking
+/// each candidate as `generate_remaining_games` does. The triplets the merge accepts go to a
+/// `ShardedNumberSet` the workers read as they draw, so that they flag the candidates already
+/// sharing a triplet, which the merge then turns down without looking their triplets up. As
+/// the merge checks every candidate in a fixed order whatever the workers saw, the output is
+/// reproducible for a fixed seed and number of threads, and with a single thread it matches
+/// `generate_remaining_games` given a `CandidateSource` built from the same seed.
+///
+/// Once a round rejects more than `REJECTION_THRESHOLD` of the candidates of every worker
+/// combined, generation carries on sequentially with a `CandidateSource` seeded with
+/// `seed + threads`, which falls back to a scan of the remaining space.
+///
+/// # Arguments
+/// * `games` - The games accepted so far; new games are appended to it.
+/// * `game_set` - The combinadic numbers of the accepted games.
+/// * `triplet_set` - The combinadic numbers of the triplets of the accepted games.
+/// * `constraints` - The rules every game must follow, counting the rejections.
+/// * `seed` - A `u64` seed for reproducible randomness.
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+/// * `min_desired_number` - The minimum number allowed in a game.
+/// * `max_number` - The maximum number in a game (e.g., 60).
+/// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+/// * `threads` - The number of workers (and threads) drawing candidates.
+/// * `no_of_games` - The number of games wanted.
+/// * `stats` - The counters of the run, updated as the candidates are merged.
+///
+/// # Returns
+/// * `Ok(())` once enough games were generated, or after the round `stats` was interrupted in.
+/// * An error if the thread pool cannot be created or every remaining game in the range was
+///   tried without success.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_remaining_games_parallel<S: RankSet + Sync>(
+    games: &mut Vec<Game>,
+    game_set: &mut NumberSet,
+    triplet_set: &mut S,
+    constraints: &mut ConstraintSet,
+    seed: u64,
+    min_number: i64,
+    min_desired_number: i64,
+    max_number: i64,
+    numbers_per_game: i64,
+    threads: usize,
+    no_of_games: usize,
+    stats: &mut GenerationStats,
+) -> Result<(), GenError> {
+    let threads = threads.max(1);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(GenError::ThreadPool)?;
+    let mut rngs: Vec<_> = (0..threads as u64)
+        .map(|i| {
+            custom_utils::create_ranged_game_rng(
+                seed.wrapping_add(i),
+                min_desired_number,
+                max_number,
+                numbers_per_game,
+            )
+        })
+        .collect();
+    let batch_size = REJECTION_WINDOW.div_ceil(threads);
+    let unique_triplets = !constraints.allows_shared_triplets();
+    let mut pending: Vec<Vec<DrawnCandidate>> = Vec::new();
+
+    while games.len() < no_of_games && !stats.interrupted() {
+        let claimed = ShardedNumberSet::default();
+        let mut drawn = Vec::new();
+        let mut attempts = 0;
+        let mut accepted = 0;
+        pool.in_place_scope(|scope| {
+            let (rngs, shared, claimed) = (&mut rngs, &*triplet_set, &claimed);
+            let drawn = &mut drawn;
+            scope.spawn(move |_| {
+                *drawn = draw_batches(
+                    rngs,
+                    batch_size,
+                    min_number,
+                    unique_triplets,
+                    shared,
+                    claimed,
+                );
+            });
+
+            // Merge the candidates in a fixed order, so the output does not depend on scheduling
+            for candidate in pending.drain(..).flatten() {
+                if games.len() == no_of_games {
+                    break;
+                }
+                attempts += 1;
+                let outcome = if game_set.contains(candidate.game_no) {
+                    Outcome::DuplicateGame
+                } else if constraints.reject(&candidate.game) {
+                    Outcome::Rejected
+                } else if candidate.shared_triplet {
+                    Outcome::SharedTriplet
+                } else if candidate.triplet_nos.is_none_or(|triplet_nos| {
+                    !triplet_nos.iter().any(|&no| shared.contains(no))
+                        && claimed.check_and_insert_all(&triplet_nos)
+                }) {
+                    Outcome::Valid
+                } else {
+                    Outcome::SharedTriplet
+                };
+                stats.record(outcome);
+                if outcome == Outcome::Valid {
+                    game_set.add_number(candidate.game_no);
+                    constraints.accept(&candidate.game);
+                    stats.accept(&candidate.game);
+                    games.push(candidate.game);
+                    accepted += 1;
+                } else {
+                    log_rejection(&candidate.game, candidate.game_no, outcome, constraints);
+                }
+            }
+        });
+        for triplet_no in claimed.into_number_set() {
+            triplet_set.add_number(triplet_no);
+        }
+        pending = drawn;
+
+        if games.len() < no_of_games
+            && (attempts - accepted) as f64 > REJECTION_THRESHOLD * attempts as f64
+        {
+            log::info!(
+                "Rejection rate over the last {} attempts exceeded {:.1}%, continuing on a single thread",
+                attempts,
+                REJECTION_THRESHOLD * 100.0
+            );
+            let mut source = CandidateSource::new(
+                seed.wrapping_add(threads as u64),
+                min_desired_number,
+                max_number,
+                numbers_per_game,
+                REJECTION_WINDOW,
+                REJECTION_THRESHOLD,
+            )
+            .with_min_number(min_number);
+            return generate_remaining_games(
+                games,
+                game_set,
+                triplet_set,
+                constraints,
+                &mut source,
+                no_of_games,
+                stats,
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::{
+        DistinctConstraint, GameConstraint, LengthConstraint, RangeConstraint, Violation,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    /// Runs the generation for a tiny lottery, with numbers 49 to 60 only.
+    fn generate_tiny(no_of_games: usize) -> (Result<(), String>, Vec<Game>, bool) {
+        let mut games = Vec::new();
+        let mut game_set = NumberSet::new();
+        let mut triplet_set = TripletSet::default();
+        let mut source = CandidateSource::new(42, 49, 60, 6, 100, 0.95);
+        let result = generate_remaining_games(
+            &mut games,
+            &mut game_set,
+            &mut triplet_set,
+            &mut ConstraintSet::new()
+                .with(LengthConstraint {
+                    numbers_per_game: 6,
+                })
+                .with(RangeConstraint::new(49, 60))
+                .with(DistinctConstraint),
+            &mut source,
+            no_of_games,
+            &mut GenerationStats::default(),
+        )
+        .map_err(|e| e.to_string());
+        (result, games, source.is_scanning())
+    }
+
+    /// The standard constraints for games of 6 numbers between `min_desired_number` and 60.
+    fn standard_constraints(min_desired_number: i64) -> ConstraintSet {
+        ConstraintSet::new()
+            .with(LengthConstraint {
+                numbers_per_game: 6,
+            })
+            .with(RangeConstraint::new(min_desired_number, 60))
+            .with(DistinctConstraint)
+    }
+
+    /// Runs either the random or the greedy-coverage generation with a small sample.
+    fn generate_with_strategy(
+        greedy: bool,
+        seed: u64,
+        min_desired_number: i64,
+        no_of_games: usize,
+    ) -> (Result<(), String>, Vec<Game>) {
+        let mut games = Vec::new();
+        let mut game_set = NumberSet::new();
+        let mut triplet_set = TripletSet::default();
+        let mut constraints = standard_constraints(min_desired_number);
+        let mut source = CandidateSource::new(seed, min_desired_number, 60, 6, 100, 0.95);
+        let result = if greedy {
+            generate_greedy_coverage(
+                &mut games,
+                &mut game_set,
+                &mut triplet_set,
+                &mut constraints,
+                &mut source,
+                no_of_games,
+                200,
+                &mut GenerationStats::default(),
+            )
+        } else {
+            generate_remaining_games(
+                &mut games,
+                &mut game_set,
+                &mut triplet_set,
+                &mut constraints,
+                &mut source,
+                no_of_games,
+                &mut GenerationStats::default(),
+            )
+        };
+        (result.map_err(|e| e.to_string()), games)
+    }
+
+    /// Runs the parallel generation for games with numbers 31 to 60.
+    fn generate_parallel(
+        seed: u64,
+        min_desired_number: i64,
+        threads: usize,
+        no_of_games: usize,
+    ) -> (Result<(), String>, Vec<Game>, GenerationStats) {
+        let mut games = Vec::new();
+        let mut stats = GenerationStats::default();
+        let result = generate_remaining_games_parallel(
+            &mut games,
+            &mut NumberSet::new(),
+            &mut TripletSet::default(),
+            &mut ConstraintSet::new()
+                .with(LengthConstraint {
+                    numbers_per_game: 6,
+                })
+                .with(RangeConstraint::new(min_desired_number, 60))
+                .with(DistinctConstraint),
+            seed,
+            1,
+            min_desired_number,
+            60,
+            6,
+            threads,
+            no_of_games,
+            &mut stats,
+        )
+        .map_err(|e| e.to_string());
+        (result, games, stats)
+    }
+
+    /// Asserts that no two games share a triplet.
+    fn assert_triplets_unique(games: &[Game]) {
+        let mut triplet_set = TripletSet::default();
+        for game in games {
+            let triplet_nos: Vec<i64> = custom_utils::game2triplets(game)
+                .iter()
+                .map(|triplet| custom_utils::triplet2enum(triplet).unwrap())
+                .collect();
+            assert!(triplet_set.check_and_insert_all(&triplet_nos));
+        }
+    }
+
+    #[test]
+    fn test_record_switches_to_scan_on_high_rejection_rate() {
+        let mut source = CandidateSource::new(1, 31, 60, 6, 10, 0.5);
+        for _ in 0..10 {
+            source.record(false);
+        }
+        assert!(!source.is_scanning());
+        for _ in 0..5 {
+            source.record(true);
+        }
+        assert!(!source.is_scanning()); // Exactly 50%, not above the threshold
+        source.record(true);
+        assert!(source.is_scanning());
+    }
+
+    #[test]
+    fn test_scan_visits_every_game_once() {
+        let mut source = CandidateSource::new(3, 53, 60, 6, 1, 0.0);
+        source.record(true);
+        assert!(source.is_scanning());
+
+        let mut seen = NumberSet::new();
+        let mut count = 0;
+        while let Some(game) = source.next_candidate() {
+            assert!(game.iter().all(|&x| (53..=60).contains(&x)));
+            assert!(seen.add_number(custom_utils::game2enum(&game)));
+            count += 1;
+        }
+        assert_eq!(count, 28); // C(8, 6)
+    }
+
+    #[test]
+    fn test_restored_source_gives_the_candidates_it_would_have() {
+        let std_source: fn() -> CandidateSource = || CandidateSource::new(5, 41, 60, 6, 10, 0.5);
+        let counter_source: fn() -> CandidateSource =
+            || CandidateSource::with_rng(CounterRng::seed_from_u64(5), 41, 60, 6, 10, 0.5);
+        // Stopped before and after turning to the scan, the words drawn skipped or, for a
+        // state not counting them, the games drawn again
+        for rejections in [3, 12] {
+            for (new_source, counted) in [
+                (std_source, true),
+                (counter_source, true),
+                (counter_source, false),
+            ] {
+                let mut source = new_source();
+                for attempt in 0..20 {
+                    source.next_candidate();
+                    source.record(attempt < rejections);
+                }
+                let mut state = source.state();
+                assert_eq!(state.scan.is_some(), rejections > 5);
+                assert!(state.rng_draws.is_some_and(|draws| draws > 0));
+                if !counted {
+                    state.rng_draws = None;
+                }
+
+                let mut restored = new_source();
+                restored.restore(&state);
+                assert_eq!(restored.rng_draws(), source.rng_draws());
+                assert_eq!(
+                    restored.state(),
+                    SourceState {
+                        rng_draws: source.rng_draws(),
+                        ..state
+                    }
+                );
+                // The window carries on too: both turn to the scan at the same attempt
+                for _ in 0..30 {
+                    assert_eq!(restored.next_candidate(), source.next_candidate());
+                    restored.record(true);
+                    source.record(true);
+                }
+                assert!(restored.is_scanning());
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiny_lottery_terminates_with_exhaustion_error() {
+        let (result, games, scanning) = generate_tiny(50);
+        assert!(scanning);
+        let error = result.unwrap_err();
+        assert!(error.starts_with("No more valid games exist"), "{}", error);
+
+        // Whatever was found must still respect triplet uniqueness
+        assert_triplets_unique(&games);
+    }
+
+    /// Rejects games whose numbers add up to more than `max_sum`.
+    struct MaxSumConstraint {
+        max_sum: i64,
+    }
+
+    impl GameConstraint for MaxSumConstraint {
+        fn name(&self) -> &'static str {
+            "sum"
+        }
+
+        fn check(&self, game: &[i64]) -> Option<Violation> {
+            let sum: i64 = game.iter().sum();
+            (sum > self.max_sum).then_some(Violation::AboveMaximum { value: sum })
+        }
+    }
+
+    /// Rejects games of more than 3 odd numbers.
+    struct ParityConstraint;
+
+    impl GameConstraint for ParityConstraint {
+        fn name(&self) -> &'static str {
+            "parity"
+        }
+
+        fn check(&self, game: &[i64]) -> Option<Violation> {
+            let odd = game.iter().filter(|&&number| number % 2 == 1).count();
+            (odd > 3).then_some(Violation::AboveMaximum { value: odd as i64 })
+        }
+    }
+
+    #[test]
+    fn test_infeasible_constraint_is_relaxed_or_runs_out_of_attempts() {
+        // No 6 numbers of 49..=60 add up to 300 or less
+        let constraints = || {
+            standard_constraints(49)
+                .with(MaxSumConstraint { max_sum: 300 })
+                .with(ParityConstraint)
+        };
+        let mut games = Vec::new();
+        let mut constraints_relaxed =
+            constraints().relax_on_exhaustion(true, &["parity".into(), "sum".into()]);
+        let mut stats = GenerationStats::default();
+        generate_remaining_games(
+            &mut games,
+            &mut NumberSet::new(),
+            &mut TripletSet::default(),
+            &mut constraints_relaxed,
+            &mut CandidateSource::new(42, 49, 60, 6, 100, 0.95),
+            3,
+            &mut stats,
+        )
+        .unwrap();
+        assert_eq!(games.len(), 3);
+        assert_triplets_unique(&games);
+        assert!(games
+            .iter()
+            .all(|game| ParityConstraint.check(game).is_none()));
+        let relaxations = constraints_relaxed.relaxations();
+        assert_eq!(relaxations.len(), 1);
+        assert_eq!(relaxations[0].constraint, "sum");
+        assert_eq!(relaxations[0].games, 0);
+        // Relaxed once the scan of the 924 games found none
+        assert!(relaxations[0].attempts > 924);
+        // Every game of the lottery was turned down by the sum once
+        assert!(constraints_relaxed
+            .rejection_counts()
+            .contains(&("sum", 924)));
+
+        // Without relaxing, the run stops after max_attempts rejections in a row
+        let error = generate_remaining_games(
+            &mut Vec::new(),
+            &mut NumberSet::new(),
+            &mut TripletSet::default(),
+            &mut constraints(),
+            &mut CandidateSource::new(42, 49, 60, 6, 100, 0.95).with_max_attempts(Some(500)),
+            5,
+            &mut GenerationStats::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            GenError::OutOfAttempts {
+                generated: 0,
+                requested: 5,
+                max_attempts: 500
+            }
+        ));
+    }
+
+    #[test]
+    fn test_greedy_coverage_beats_random_on_small_lottery() {
+        for seed in [1, 2, 3] {
+            let (result, random) = generate_with_strategy(false, seed, 41, 12);
+            assert!(result.is_ok());
+            let (result, greedy) = generate_with_strategy(true, seed, 41, 12);
+            assert!(result.is_ok());
+            assert_eq!(greedy.len(), 12);
+            assert_triplets_unique(&greedy);
+
+            // Both cover 20 new triplets per game, but greedy spreads the pairs further
+            let triplets = crate::analysis::coverage_stats(&greedy, 3, 1, 60).covered;
+            assert_eq!(triplets, 12 * 20);
+            let greedy_pairs = crate::analysis::coverage_stats(&greedy, 2, 1, 60).covered;
+            let random_pairs = crate::analysis::coverage_stats(&random, 2, 1, 60).covered;
+            assert!(
+                greedy_pairs >= random_pairs,
+                "{} < {}",
+                greedy_pairs,
+                random_pairs
+            );
+        }
+    }
+
+    #[test]
+    fn test_greedy_coverage_is_reproducible_and_terminates() {
+        let (_, first) = generate_with_strategy(true, 9, 41, 12);
+        let (_, second) = generate_with_strategy(true, 9, 41, 12);
+        assert_eq!(first, second);
+
+        let (result, games) = generate_with_strategy(true, 42, 49, 50);
+        let error = result.unwrap_err();
+        assert!(error.starts_with("No more valid games exist"), "{}", error);
+        assert!(!games.is_empty());
+        assert_triplets_unique(&games);
+    }
+
+    #[test]
+    fn test_parallel_single_thread_matches_sequential() {
+        let mut games = Vec::new();
+        let mut source = CandidateSource::new(7, 31, 60, 6, REJECTION_WINDOW, REJECTION_THRESHOLD);
+        generate_remaining_games(
+            &mut games,
+            &mut NumberSet::new(),
+            &mut TripletSet::default(),
+            &mut ConstraintSet::new()
+                .with(LengthConstraint {
+                    numbers_per_game: 6,
+                })
+                .with(RangeConstraint::new(31, 60))
+                .with(DistinctConstraint),
+            &mut source,
+            60,
+            &mut GenerationStats::default(),
+        )
+        .unwrap();
+
+        let (result, parallel, _) = generate_parallel(7, 31, 1, 60);
+        assert!(result.is_ok());
+        assert_eq!(parallel, games);
+    }
+
+    #[test]
+    fn test_parallel_output_is_valid_and_reproducible() {
+        let (result, first, first_stats) = generate_parallel(11, 31, 4, 60);
+        assert!(result.is_ok());
+        assert_eq!(first.len(), 60);
+        assert_triplets_unique(&first);
+        assert!(first
+            .iter()
+            .all(|game| game.iter().all(|&x| (31..=60).contains(&x))));
+
+        // The candidates seen sharing a triplet while drawing depend on scheduling, not the counts
+        let (_, second, second_stats) = generate_parallel(11, 31, 4, 60);
+        assert_eq!(first, second);
+        assert_eq!(first_stats.attempts, second_stats.attempts);
+        assert_eq!(first_stats.shared_triplets, second_stats.shared_triplets);
+        assert!(first_stats.shared_triplets > 0);
+    }
+
+    #[test]
+    fn test_parallel_tiny_lottery_terminates_with_exhaustion_error() {
+        let (result, games, _) = generate_parallel(42, 49, 4, 50);
+        let error = result.unwrap_err();
+        assert!(error.starts_with("No more valid games exist"), "{}", error);
+        assert!(!games.is_empty());
+        assert_triplets_unique(&games);
+    }
+
+    #[test]
+    fn test_zero_based_lottery() {
+        let mut games = Vec::new();
+        let mut game_set = NumberSet::new();
+        let mut triplet_set = TripletSet::default();
+        let mut source = CandidateSource::new(8, 0, 24, 6, REJECTION_WINDOW, REJECTION_THRESHOLD)
+            .with_min_number(0);
+        generate_remaining_games(
+            &mut games,
+            &mut game_set,
+            &mut triplet_set,
+            &mut ConstraintSet::new()
+                .with(LengthConstraint {
+                    numbers_per_game: 6,
+                })
+                .with(RangeConstraint::new(0, 24))
+                .with(DistinctConstraint),
+            &mut source,
+            20,
+            &mut GenerationStats::default(),
+        )
+        .unwrap();
+
+        assert!(games
+            .iter()
+            .all(|game| game.iter().all(|x| (0..=24).contains(x))));
+        assert!(games.iter().any(|game| game.contains(&0)));
+        let mut zero_based_triplets = NumberSet::new();
+        for game in &games {
+            let triplet_nos = custom_utils::game2triplet_enums_from(game, 0);
+            assert!(triplet_nos.iter().all(|&no| no >= 0));
+            assert!(zero_based_triplets.check_and_insert_all(&triplet_nos));
+        }
+    }
+
+    #[test]
+    fn test_tiny_lottery_is_reproducible() {
+        let (_, first, _) = generate_tiny(50);
+        let (_, second, _) = generate_tiny(50);
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+
+        // Asking for exactly what the exhaustive run found succeeds
+        let (result, games, _) = generate_tiny(first.len());
+        assert!(result.is_ok());
+        assert_eq!(games.len(), first.len());
+    }
+
+    #[test]
+    fn test_borrowed_conversions_end_to_end() {
+        // Push many candidates through the borrowing conversion pipeline, as the
+        // generation loop does, without cloning the games.
+        let mut rng = custom_utils::create_ranged_game_rng(2024, 1, 60, 6);
+        let mut game_set = NumberSet::new();
+        let mut triplet_set = TripletSet::default();
+        let mut accepted = 0;
+        for _ in 0..10_000 {
+            let game = rng();
+            let game_no = custom_utils::game2enum(&game);
+            assert_eq!(custom_utils::enum2game(game_no), Ok(game.to_vec()));
+
+            let triplet_nos: Vec<i64> = custom_utils::game2triplets(&game)
+                .iter()
+                .map(|triplet| custom_utils::triplet2enum(triplet).unwrap())
+                .collect();
+            assert_eq!(triplet_nos.len(), 20);
+            if game_set.add_number(game_no) && triplet_set.check_and_insert_all(&triplet_nos) {
+                accepted += 1;
+            }
+        }
+        assert!(accepted > 0);
+    }
+
+    fn resume_config(no_of_games: usize) -> Config {
+        Config::from_toml(&format!(
+            "no_of_games = {}\ninitial_games = []\nseed = 3\nmax_number = 60\nmin_desired_number = 31\n",
+            no_of_games
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_stats_add_up_for_every_strategy() {
+        // The arithmetic runs make the constraints turn down some of the candidates
+        for config in [
+            resume_config(40),
+            Config {
+                strategy: Strategy::GreedyCoverage,
+                ..resume_config(20)
+            },
+            Config {
+                threads: Some(3),
+                ..resume_config(40)
+            },
+        ]
+        .map(|config| Config {
+            max_arithmetic_run: Some(3),
+            ..config
+        }) {
+            let generated = generate(&config, GenerationStats::default()).unwrap();
+            let stats = &generated.stats;
+            assert_eq!(stats.requested, config.no_of_games);
+            assert_eq!(stats.games, generated.games.len());
+            let rejected: u64 = generated
+                .constraints
+                .rejection_counts()
+                .iter()
+                .map(|(_, count)| count)
+                .sum();
+            assert_eq!(stats.rejected, rejected);
+            assert!(stats.rejected > 0);
+            assert_eq!(generated.triplet_set.len(), 20 * stats.games);
+            // Greedy candidates left in the pool are valid but not accepted
+            if config.strategy == Strategy::Random {
+                assert_eq!(stats.attempts, stats.rejections() + stats.games as u64);
+            } else {
+                assert!(stats.attempts > stats.rejections() + stats.games as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_resume_completes_a_previous_run() {
+        let previous = generate(&resume_config(10), GenerationStats::default()).unwrap();
+        let resumed = resume(
+            &resume_config(30),
+            previous.games.clone(),
+            previous.game_set,
+            previous.triplet_set,
+            GenerationStats::default(),
+        )
+        .unwrap();
+        assert_eq!(resumed.games.len(), 30);
+        assert_eq!(resumed.games[..10], previous.games[..]);
+        assert_eq!(resumed.triplet_set.len(), 30 * 20);
+        assert_triplets_unique(&resumed.games);
+    }
+
+    #[test]
+    fn test_interrupted_run_keeps_consistent_partial_progress() {
+        let config = Config {
+            min_desired_number: 1,
+            ..resume_config(1000)
+        };
+        for config in [
+            config.clone(),
+            Config {
+                strategy: Strategy::GreedyCoverage,
+                ..config.clone()
+            },
+            Config {
+                threads: Some(2),
+                ..config.clone()
+            },
+        ] {
+            // Interrupt at the first progress report, after PROGRESS_INTERVAL candidates
+            let stop = Arc::new(AtomicBool::new(false));
+            let flag = Arc::clone(&stop);
+            let stats = GenerationStats::with_observer(move |_: &GenerationStats| {
+                flag.store(true, Ordering::Relaxed)
+            })
+            .with_stop_flag(stop);
+            let generated = generate(&config, stats).unwrap();
+            assert!(generated.interrupted);
+            assert!(generated.games.len() < config.no_of_games);
+            assert_eq!(generated.stats.games, generated.games.len());
+            assert_triplets_unique(&generated.games);
+            check_consistency(
+                &generated.games,
+                &generated.game_set,
+                &generated.triplet_set,
+                config.min_number,
+            )
+            .unwrap();
+        }
+
+        // A run stopped before it starts keeps its initial games only
+        let stop = Arc::new(AtomicBool::new(true));
+        let config = Config {
+            initial_games: vec![Game::new(vec![31, 32, 33, 34, 35, 36])],
+            ..resume_config(5)
+        };
+        let generated = generate(&config, GenerationStats::default().with_stop_flag(stop)).unwrap();
+        assert!(generated.interrupted);
+        assert_eq!(generated.games, config.initial_games);
+        let resumed = resume(
+            &config,
+            generated.games,
+            generated.game_set,
+            generated.triplet_set,
+            GenerationStats::default(),
+        )
+        .unwrap();
+        assert!(!resumed.interrupted);
+        assert_eq!(resumed.games.len(), 5);
+    }
+
+    #[test]
+    fn test_stats_carry_on_across_an_interrupted_and_resumed_run() {
+        let config = resume_config(40);
+        // Interrupt once 15 games are accepted
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let mut seen = 0;
+        let stats = GenerationStats::default()
+            .with_game_hook(move |_| {
+                seen += 1;
+                if seen == 15 {
+                    flag.store(true, Ordering::Relaxed);
+                }
+            })
+            .with_stop_flag(stop);
+        let first = generate(&config, stats).unwrap();
+        assert!(first.interrupted);
+        assert_eq!(first.stats.accepted, 15);
+        // The counters as the manifest of the interrupted run saves them
+        let saved: GenerationStats =
+            serde_json::from_value(serde_json::to_value(&first.stats).unwrap()).unwrap();
+
+        let resume_with = |stats| {
+            resume(
+                &config,
+                first.games.clone(),
+                first.game_set.clone(),
+                first.triplet_set.clone(),
+                stats,
+            )
+            .unwrap()
+        };
+        let own = resume_with(GenerationStats::default()).stats;
+        let mut carried = GenerationStats::default();
+        carried += &saved;
+        let resumed = resume_with(carried);
+        assert_eq!(resumed.games.len(), 40);
+        let total = &resumed.stats;
+        assert_eq!(own.accepted, 25);
+        assert_eq!(total.accepted, 40);
+        assert_eq!(total.attempts, first.stats.attempts + own.attempts);
+        assert_eq!(total.attempts, total.rejections() + total.accepted);
+        assert_eq!(
+            total.duplicate_games,
+            first.stats.duplicate_games + own.duplicate_games
+        );
+        assert_eq!(total.rejected, first.stats.rejected + own.rejected);
+        assert_eq!(
+            total.shared_triplets,
+            first.stats.shared_triplets + own.shared_triplets
+        );
+        assert!(!total.constraint_rejections.is_empty());
+        for (name, count) in &total.constraint_rejections {
+            assert_eq!(
+                *count,
+                first.stats.constraint_rejections[name] + own.constraint_rejections[name]
+            );
+        }
+        assert!(total.elapsed >= first.stats.elapsed);
+    }
+
+    #[test]
+    fn test_batches_are_independent_unless_sharing_triplets() {
+        let config = Config {
+            batches: 3,
+            initial_games: vec![Game::new(vec![31, 32, 33, 34, 35, 36])],
+            min_desired_number: 1,
+            ..resume_config(30)
+        };
+        let batches = generate_batches(&config, |_| GenerationStats::default()).unwrap();
+        assert_eq!(batches.len(), 3);
+        for (index, generated) in batches.iter().enumerate() {
+            let alone = generate(&config.batch(index + 1), GenerationStats::default()).unwrap();
+            assert_eq!(generated.games, alone.games);
+            assert_eq!(generated.games[0], config.initial_games[0]);
+        }
+        assert_ne!(batches[0].games, batches[1].games);
+
+        let config = Config {
+            share_triplet_set: true,
+            ..config
+        };
+        let batches = generate_batches(&config, |_| GenerationStats::default()).unwrap();
+        assert_eq!(batches[0].games[0], config.initial_games[0]);
+        let all: Vec<Game> = batches
+            .iter()
+            .flat_map(|generated| generated.games.clone())
+            .collect();
+        assert_eq!(all.len(), 90);
+        assert_triplets_unique(&all);
+        for generated in &batches {
+            assert_eq!(generated.triplet_set.len(), 20 * generated.games.len());
+        }
+
+        // Each batch fits the bound, but not both of them together
+        let shared = Config {
+            batches: 2,
+            share_triplet_set: true,
+            ..resume_config(150)
+        };
+        assert!(matches!(
+            generate_batches(&shared, |_| GenerationStats::default()),
+            Err(GenError::Infeasible { requested: 300, .. })
+        ));
+    }
+
+    #[test]
+    fn test_avoided_triplets_are_left_to_their_games() {
+        let first = generate(&resume_config(20), GenerationStats::default()).unwrap();
+        let config = Config {
+            seed: Some(4),
+            ..resume_config(20)
+        };
+        let second =
+            generate_avoiding(&config, &first.triplet_set, GenerationStats::default()).unwrap();
+        assert_eq!(second.triplet_set.len(), 20 * 20);
+        let all: Vec<Game> = first.games.iter().chain(&second.games).cloned().collect();
+        assert_triplets_unique(&all);
+
+        let config = Config {
+            initial_games: vec![first.games[3].clone()],
+            ..config
+        };
+        assert!(matches!(
+            generate_avoiding(&config, &first.triplet_set, GenerationStats::default()),
+            Err(GenError::StoredTriplet { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_number_usage_cap_holds_for_every_strategy() {
+        let initial = Game::new(vec![31, 32, 33, 34, 35, 36]);
+        for config in [
+            resume_config(30),
+            Config {
+                strategy: Strategy::GreedyCoverage,
+                ..resume_config(30)
+            },
+            Config {
+                threads: Some(3),
+                ..resume_config(30)
+            },
+        ]
+        .map(|config| Config {
+            max_number_usage: Some(7),
+            initial_games: vec![initial.clone()],
+            ..config
+        }) {
+            let generated = generate(&config, GenerationStats::default()).unwrap();
+            assert_eq!(generated.games.len(), 30);
+            assert_eq!(generated.games[0], initial);
+            assert_triplets_unique(&generated.games);
+            let mut usage = std::collections::HashMap::new();
+            for number in generated.games.iter().flat_map(|game| game.to_vec()) {
+                *usage.entry(number).or_insert(0) += 1;
+            }
+            assert!(usage.values().all(|&count| count <= 7), "{:?}", usage);
+            assert_eq!(
+                generated.constraints.rejection_counts().last().unwrap().0,
+                "usage"
+            );
+        }
+    }
+
+    #[test]
+    fn test_infeasible_number_usage_fails_fast() {
+        // 30 numbers in at most 8 games each fill 40 games of 6 numbers
+        let config = Config {
+            max_number_usage: Some(8),
+            ..resume_config(41)
+        };
+        let error = generate(&config, GenerationStats::default())
+            .err()
+            .expect("the cap leaves room for 40 games only");
+        assert!(matches!(
+            error,
+            GenError::UsageInfeasible {
+                requested: 41,
+                max_number_usage: 8,
+                bound: 40
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "41 games requested, but at most 40 games fit with each number in at most 8 games (max_number_usage)"
+        );
+    }
+
+    fn weighted_config(no_of_games: usize, max_number: i64, weights: &str) -> Config {
+        Config::from_toml(&format!(
+            "no_of_games = {}\ninitial_games = []\nseed = 5\nmax_number = {}\nmin_desired_number = 1\nstrategy = \"weighted\"\n[weights]\n{}",
+            no_of_games, max_number, weights
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_weighted_strategy_favors_heavy_numbers_and_skips_zero_weights() {
+        let weights: String = (1..=10).map(|n| format!("{} = 0\n", n)).collect();
+        let config = weighted_config(40, 60, &format!("{}11 = 50\n12 = 50\n", weights));
+        let generated = generate(&config, GenerationStats::default()).unwrap();
+        assert_eq!(generated.games.len(), 40);
+        assert_triplets_unique(&generated.games);
+        assert!(generated
+            .games
+            .iter()
+            .all(|game| game.iter().all(|&n| n > 10)));
+        // Uniformly, 11 and 12 would each be in about 5 of the 40 games
+        let count = |number: i64| {
+            generated
+                .games
+                .iter()
+                .filter(|game| game.contains(&number))
+                .count()
+        };
+        assert!(
+            count(11) >= 20 && count(12) >= 20,
+            "{} {}",
+            count(11),
+            count(12)
+        );
+        assert_eq!(
+            generate(&config, GenerationStats::default()).unwrap().games,
+            generated.games
+        );
+        assert!(matches!(
+            generate(
+                &Config {
+                    threads: Some(2),
+                    ..config
+                },
+                GenerationStats::default()
+            ),
+            Err(GenError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_weighted_scan_skips_zero_weights_too() {
+        // 10 numbers of positive weight hold few triplet-disjoint games, so the scan runs
+        let config = weighted_config(10, 12, "1 = 0\n2 = 0\n");
+        let mut games = Vec::new();
+        let mut source = candidate_source(StdRng::seed_from_u64(5), &config);
+        let result = generate_remaining_games(
+            &mut games,
+            &mut NumberSet::new(),
+            &mut TripletSet::default(),
+            &mut ConstraintSet::from_config(&config),
+            &mut source,
+            config.no_of_games,
+            &mut GenerationStats::default(),
+        );
+        assert!(matches!(result, Err(GenError::Exhausted { .. })));
+        assert!(source.is_scanning());
+        assert!(!games.is_empty());
+        assert_triplets_unique(&games);
+        assert!(games.iter().all(|game| game.iter().all(|&n| n > 2)));
+    }
+
+    #[test]
+    fn test_historical_draws_are_never_generated() {
+        let config = resume_config(10);
+        let first = generate(&config, GenerationStats::default()).unwrap();
+        // Pretend the 3rd and 7th games were drawn in past contests
+        let mut ranks = NumberSet::new();
+        for game in [&first.games[2], &first.games[6]] {
+            ranks.add_number(custom_utils::game2enum_from(game, 1));
+        }
+        let config = Config {
+            historical_ranks: Some(std::sync::Arc::new(ranks)),
+            ..config
+        };
+        let second = generate(&config, GenerationStats::default()).unwrap();
+        assert_eq!(second.games[..2], first.games[..2]);
+        assert!(!second.games.contains(&first.games[2]));
+        assert!(!second.games.contains(&first.games[6]));
+        assert_eq!(
+            second.constraints.rejection_counts().last(),
+            Some(&("historical", 2))
+        );
+
+        // An initial game drawn in the past is kept, the caller warns about it
+        let config = Config {
+            initial_games: vec![first.games[2].clone()],
+            ..config
+        };
+        assert_eq!(
+            generate(&config, GenerationStats::default()).unwrap().games[0],
+            first.games[2]
+        );
+
+        let unloaded = Config {
+            historical_draws_file: Some("draws.csv".to_string()),
+            historical_ranks: None,
+            ..config
+        };
+        assert!(matches!(
+            generate(&unloaded, GenerationStats::default()),
+            Err(GenError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_append_adds_games_sharing_no_triplet() {
+        let existing = generate(&resume_config(10), GenerationStats::default()).unwrap();
+        let appended = append(
+            &resume_config(15),
+            existing.games.clone(),
+            GenerationStats::default(),
+        )
+        .unwrap();
+        assert_eq!(appended.games.len(), 25);
+        assert_eq!(appended.games[..10], existing.games[..]);
+        assert_triplets_unique(&appended.games);
+    }
+
+    #[test]
+    fn test_repeated_initial_triplet_names_the_game_holding_it() {
+        let config = Config {
+            initial_games: [
+                [31, 32, 33, 34, 35, 36],
+                [40, 41, 42, 43, 44, 45],
+                [50, 51, 52, 53, 54, 55],
+                [36, 40, 41, 43, 50, 55],
+            ]
+            .map(|numbers| Game::new(numbers.to_vec()))
+            .to_vec(),
+            ..resume_config(5)
+        };
+        let error = match generate(&config, GenerationStats::default()) {
+            Err(error) => error,
+            Ok(_) => panic!("the fourth initial game repeats a triplet of the second one"),
+        };
+        assert!(matches!(
+            &error,
+            GenError::RepeatedTriplets { conflicts } if conflicts[..] == [TripletConflict {
+                earlier: 1,
+                index: 3,
+                triplets: vec![vec![40, 41, 43]],
+            }]
+        ));
+        // In whatever order the numbers are written
+        let reversed = Config {
+            initial_games: vec![
+                Game::new(vec![31, 32, 33, 34, 35, 36]),
+                Game::new(vec![36, 35, 34, 33, 32, 31]),
+            ],
+            ..resume_config(5)
+        };
+        assert!(matches!(
+            generate(&reversed, GenerationStats::default()),
+            Err(GenError::RepeatedTriplets { conflicts })
+                if conflicts.len() == 1 && conflicts[0].triplets.len() == 20
+        ));
+
+        // Generated candidates name the accepted game holding their shared triplet
+        let mut constraints = ConstraintSet::new().track_triplet_owners(1);
+        for game in &config.initial_games[..3] {
+            constraints.accept(game);
+        }
+        assert_eq!(
+            constraints.triplet_owner(&[1, 2, 50, 52, 55, 60]),
+            Some((vec![50, 52, 55], 2))
+        );
+        assert_eq!(constraints.triplet_owner(&[1, 2, 31, 40, 50, 60]), None);
+        assert_eq!(
+            ConstraintSet::new().triplet_owner(&[31, 32, 33, 1, 2, 3]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_blacklisted_games_are_never_generated() {
+        let previous = generate(&resume_config(20), GenerationStats::default()).unwrap();
+        let blacklisted = [previous.games[3].clone(), previous.games[11].clone()];
+        let config = Config {
+            blacklisted_games: vec![blacklisted[0].clone()],
+            blacklist_file_games: Some(vec![blacklisted[1].clone()]),
+            ..resume_config(20)
+        };
+        let generated = generate(&config, GenerationStats::default()).unwrap();
+        assert_eq!(generated.games.len(), 20);
+        assert_eq!(generated.games[..3], previous.games[..3]);
+        for game in &blacklisted {
+            let rank = custom_utils::game2enum(game);
+            assert!(!generated
+                .games
+                .iter()
+                .any(|other| custom_utils::game2enum(other) == rank));
+        }
+        // Nor do their triplets take part in the run
+        assert_eq!(generated.triplet_set.len(), 20 * 20);
+        assert!(generated.stats.rejected >= 2);
+
+        // The blacklist must be loaded before generating
+        let unloaded = Config {
+            blacklist_file: Some("cursed.csv".to_string()),
+            ..resume_config(5)
+        };
+        assert!(matches!(
+            generate(&unloaded, GenerationStats::default()),
+            Err(GenError::Unsupported(_))
+        ));
+        // And an initial game cannot be blacklisted
+        let config = Config {
+            initial_games: vec![blacklisted[0].clone()],
+            ..config
+        };
+        assert!(matches!(
+            generate(&config, GenerationStats::default()),
+            Err(GenError::InvalidInitialGame { index: 0, violations })
+                if violations == [Violation::Blacklisted]
+        ));
+    }
+
+    #[test]
+    fn test_no_two_games_share_more_than_max_shared_numbers() {
+        // Quick-picks share triplets, unless kept from sharing 3 numbers
+        for (max_shared_numbers, extra, no_of_games) in [
+            (1, "min_desired_number = 31\n", 10),
+            (
+                2,
+                "min_desired_number = 1\nstrategy = \"quick-pick\"\n",
+                200,
+            ),
+        ] {
+            let config = Config::from_toml(&format!(
+                "no_of_games = {}\ninitial_games = []\nseed = 3\nmax_number = 60\nmax_shared_numbers = {}\n{}",
+                no_of_games, max_shared_numbers, extra
+            ))
+            .unwrap();
+            assert_eq!(config.max_overlap(), Some(max_shared_numbers));
+            let generated = generate(&config, GenerationStats::default()).unwrap();
+            assert_eq!(generated.games.len(), no_of_games);
+            for (i, game) in generated.games.iter().enumerate() {
+                for other in &generated.games[..i] {
+                    let shared = game.iter().filter(|x| other.contains(x)).count();
+                    assert!(shared <= max_shared_numbers, "{} and {}", game, other);
+                }
+            }
+            let histogram = crate::analysis::overlap_histogram(&generated.games);
+            assert_eq!(histogram.len(), max_shared_numbers + 1);
+            assert_eq!(
+                histogram.iter().sum::<usize>(),
+                no_of_games * (no_of_games - 1) / 2
+            );
+        }
+    }
+
+    #[test]
+    fn test_quick_pick_lets_games_share_triplets_but_not_repeat() {
+        let quick_pick = |no_of_games: usize, initial_games: &[[i64; 6]]| {
+            Config {
+            initial_games: initial_games
+                .iter()
+                .map(|numbers| Game::new(numbers.to_vec()))
+                .collect(),
+            ..Config::from_toml(&format!(
+                "no_of_games = {}\ninitial_games = []\nseed = 3\nmax_number = 60\nmin_desired_number = 53\nstrategy = \"quick-pick\"\n",
+                no_of_games
+            ))
+            .unwrap()
+        }
+        };
+
+        // The 8 numbers from 53 hold 2 games sharing no triplet, but 28 distinct games
+        let generated = generate(&quick_pick(28, &[]), GenerationStats::default()).unwrap();
+        assert_eq!(generated.games.len(), 28);
+        assert!(generated.triplet_set.is_empty());
+        let mut ranks: Vec<i64> = generated
+            .games
+            .iter()
+            .map(|game| custom_utils::game2enum(game))
+            .collect();
+        ranks.sort_unstable();
+        ranks.dedup();
+        assert_eq!(ranks.len(), 28);
+        assert!(matches!(
+            generate(&quick_pick(29, &[]), GenerationStats::default()),
+            Err(GenError::OverlapInfeasible {
+                requested: 29,
+                bound: 28,
+                ..
+            })
+        ));
+
+        // Initial games may share a triplet, but not repeat one another
+        let generated = generate(
+            &quick_pick(3, &[[53, 54, 55, 56, 57, 58], [53, 54, 55, 57, 59, 60]]),
+            GenerationStats::default(),
+        )
+        .unwrap();
+        assert_eq!(generated.games.len(), 3);
+        let error = match generate(
+            &quick_pick(3, &[[53, 54, 55, 56, 57, 58], [58, 57, 56, 55, 54, 53]]),
+            GenerationStats::default(),
+        ) {
+            Err(error) => error,
+            Ok(_) => panic!("the second initial game repeats the first one"),
+        };
+        assert!(matches!(
+            error,
+            GenError::DuplicateInitialGame {
+                index: 1,
+                earlier: 0
+            }
+        ));
+        assert_eq!(error.to_string(), "Initial game #2 repeats initial game #1");
+    }
+
+    #[test]
+    fn test_initial_games_are_named_by_their_row_once_repeats_are_left_out() {
+        let config = |initial_games: &str| {
+            Config::from_toml(&format!(
+                "no_of_games = 6\ninitial_games = [[31, 32, 33, 34, 35, 36], [36, 35, 34, 33, 32, 31], {}]\nseed = 3\nmax_number = 60\nmin_desired_number = 1\nallow_duplicate_initial = \"dedupe\"\n",
+                initial_games
+            ))
+            .unwrap()
+        };
+        let error = |result: Result<Generated, GenError>| match result {
+            Err(error) => error.to_string(),
+            Ok(_) => panic!("an initial game after the repeat is refused"),
+        };
+        assert_eq!(
+            error(generate(
+                &config("[1, 2, 3, 10, 11, 12], [1, 2, 3, 20, 21, 22]"),
+                GenerationStats::default()
+            )),
+            "Initial games share triplets: games #3 and #4 share 01-02-03"
+        );
+        assert_eq!(
+            error(generate(
+                &config("[13, 14, 15, 16, 17, 61]"),
+                GenerationStats::default()
+            )),
+            "Initial game #3 is invalid: number 61 is above the maximum playable number"
+        );
+        let avoided: TripletSet = custom_utils::game2triplet_enums_from(&[1, 2, 3, 4, 5, 6], 1)
+            .into_iter()
+            .collect();
+        assert_eq!(
+            error(generate_avoiding(
+                &config("[1, 2, 3, 10, 11, 12]"),
+                &avoided,
+                GenerationStats::default()
+            )),
+            "Initial game #3 shares triplet 01-02-03 with a game of an earlier run or of avoid_triplets_from"
+        );
+    }
+
+    #[test]
+    fn test_invalid_initial_games_are_refused_skipped_or_fixed() {
+        let config = |mode: &str| {
+            Config::from_toml(&format!(
+                "no_of_games = 6\ninitial_games = [[1, 2, 3, 4, 5, 6], [12, 7, 7, 8, 9, 10, 11], [13, 14, 15, 16, 17, 61], [20, 20, 21, 22, 23, 24]]\nseed = 3\nmax_number = 60\nmin_desired_number = 1\non_invalid_initial = \"{}\"\n",
+                mode
+            ))
+            .unwrap()
+        };
+        let error = match generate(&config("error"), GenerationStats::default()) {
+            Err(error) => error,
+            Ok(_) => panic!("the second initial game repeats a number"),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Initial game #2 is invalid: game has 7 numbers instead of 6; number 7 appears more than once"
+        );
+
+        // The games left out are made up for by generated games
+        let skipped = generate(&config("skip"), GenerationStats::default()).unwrap();
+        assert_eq!(skipped.games.len(), 6);
+        assert_eq!(skipped.games[0].numbers(), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(skipped.stats.skipped_initial, 3);
+        assert_eq!(skipped.stats.accepted, 5);
+        assert!(skipped.games[1..]
+            .iter()
+            .all(|game| game.iter().all(|&number| (1..=60).contains(&number))));
+
+        // A repeated number is dropped, but none is made up for a game left short
+        let fixed = generate(&config("fix"), GenerationStats::default()).unwrap();
+        assert_eq!(fixed.games.len(), 6);
+        assert_eq!(fixed.games[1].numbers(), [7, 8, 9, 10, 11, 12]);
+        assert_eq!(fixed.stats.skipped_initial, 2);
+        assert_eq!(fixed.stats.accepted, 4);
+        assert_eq!(fixed.stats.counters().skipped_initial, 2);
+        assert!(check_start(&config("fix")).is_ok());
+    }
+
+    #[test]
+    fn test_initial_conflicts_are_all_reported_or_allowed() {
+        let strict = Config {
+            initial_games: [
+                [31, 32, 33, 34, 35, 36],
+                [40, 41, 42, 43, 44, 45],
+                [50, 51, 52, 53, 54, 55],
+                [36, 40, 41, 43, 50, 55],
+                [31, 32, 33, 50, 51, 52],
+            ]
+            .map(|numbers| Game::new(numbers.to_vec()))
+            .to_vec(),
+            ..resume_config(10)
+        };
+        let error = match generate(&strict, GenerationStats::default()) {
+            Err(error) => error,
+            Ok(_) => panic!("the last two initial games share triplets with earlier ones"),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Initial games share triplets: games #2 and #4 share 40-41-43; games #1 and #5 share 31-32-33; games #3 and #5 share 50-51-52"
+        );
+
+        // A triplet held by three games is reported for each pair of them
+        let shared = Config {
+            initial_games: [
+                [31, 32, 33, 40, 50, 60],
+                [31, 32, 33, 41, 51, 59],
+                [40, 41, 42, 43, 44, 45],
+                [31, 32, 33, 42, 52, 58],
+            ]
+            .map(|numbers| Game::new(numbers.to_vec()))
+            .to_vec(),
+            ..resume_config(10)
+        };
+        match generate(&shared, GenerationStats::default()) {
+            Err(error) => assert_eq!(
+                error.to_string(),
+                "Initial games share triplets: games #1 and #2 share 31-32-33; games #1 and #4 share 31-32-33; games #2 and #4 share 31-32-33"
+            ),
+            Ok(_) => panic!("the first, second and fourth initial games share a triplet"),
+        }
+
+        // Kept as they are, the generated games still share no triplet with any game
+        let lenient = Config {
+            allow_initial_conflicts: true,
+            ..strict.clone()
+        };
+        let generated = generate(&lenient, GenerationStats::default()).unwrap();
+        assert_eq!(generated.games.len(), 10);
+        assert_eq!(generated.games[..5], strict.initial_games[..]);
+        let mut triplet_set = TripletSet::default();
+        for game in &generated.games[..5] {
+            for triplet_no in custom_utils::game2triplet_enums(game) {
+                triplet_set.add_number(triplet_no);
+            }
+        }
+        for game in &generated.games[5..] {
+            assert!(triplet_set.check_and_insert_all(&custom_utils::game2triplet_enums(game)));
+        }
+        assert_eq!(generated.triplet_set.len(), triplet_set.len());
+
+        // But they still may not repeat one another
+        let repeated = Config {
+            initial_games: vec![
+                Game::new(vec![31, 32, 33, 34, 35, 36]),
+                Game::new(vec![36, 35, 34, 33, 32, 31]),
+            ],
+            ..lenient
+        };
+        assert!(matches!(
+            generate(&repeated, GenerationStats::default()),
+            Err(GenError::DuplicateInitialGame {
+                index: 1,
+                earlier: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_deduplicated_initial_games_are_started_from_once() {
+        let repeated = Config {
+            initial_games: vec![
+                Game::new(vec![31, 32, 33, 34, 35, 36]),
+                Game::new(vec![40, 41, 42, 43, 44, 45]),
+                Game::new(vec![36, 35, 34, 33, 32, 31]),
+            ],
+            allow_duplicate_initial: config::DuplicateInitial::Dedupe,
+            ..resume_config(6)
+        };
+        let generated = generate(&repeated, GenerationStats::default()).unwrap();
+        assert_eq!(generated.games.len(), 6);
+        assert_eq!(generated.games[..2], repeated.initial_games[..2]);
+        assert_triplets_unique(&generated.games);
+    }
+
+    #[test]
+    fn test_merge_leaves_out_initial_games_clashing_with_the_file() {
+        let existing = [
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![7, 8, 9, 10, 11, 12]),
+        ];
+        let initial_games = [
+            Game::new(vec![6, 5, 4, 3, 2, 1]),
+            Game::new(vec![7, 8, 9, 20, 21, 22]),
+            Game::new(vec![30, 31, 32, 33, 34, 35]),
+        ];
+        let (kept, warnings) =
+            merge_initial_games(&existing, &initial_games, &ConstraintSet::new(), 1);
+        assert_eq!(kept, initial_games[2..]);
+        assert_eq!(
+            warnings,
+            [
+                "Initial game #1 (06-05-04-03-02-01) is already game #1 of the file, left out",
+                "Initial game #2 (07-08-09-20-21-22) shares triplet 07-08-09 with game #2 of the file, left out",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resume_refuses_inconsistent_sets() {
+        let previous = generate(&resume_config(10), GenerationStats::default()).unwrap();
+
+        // The games file lost its last game, but the sets still hold it
+        let result = resume(
+            &resume_config(30),
+            previous.games[..9].to_vec(),
+            previous.game_set,
+            previous.triplet_set,
+            GenerationStats::default(),
+        );
+        match result {
+            Err(GenError::Inconsistent { differences }) => {
+                assert_eq!(differences.len(), 1, "{:?}", differences);
+                assert!(differences[0]
+                    .starts_with("the triplet set holds 20 number(s) of no game triplet"));
+            }
+            other => panic!("unexpected result: {:?}", other.map(|g| g.games.len())),
+        }
+
+        // A game missing from the sets is named
+        let error = check_consistency(
+            &previous.games[..1],
+            &NumberSet::new(),
+            &TripletSet::default(),
+            1,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with(&format!("Cannot resume, the saved sets disagree with the games: game #1 ({}) is missing from the game set", previous.games[0])));
+        assert!(error.to_string().ends_with("; and 16 more"));
+    }
+
+    #[test]
+    fn test_run_carries_on_from_a_checkpoint_as_if_never_stopped() {
+        let mut std_games = Vec::new();
+        for (strategy, rng) in [
+            (Strategy::Random, RngKind::Std),
+            (Strategy::QuickPick, RngKind::Std),
+            (Strategy::Random, RngKind::Counter),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let uninterrupted = generate(
+                &Config {
+                    strategy,
+                    rng,
+                    ..resume_config(40)
+                },
+                GenerationStats::default(),
+            )
+            .unwrap();
+            assert!(uninterrupted.stats.rng_draws > 0);
+            // The counter generator draws other games than the standard one of the same seed
+            if rng == RngKind::Counter {
+                assert_ne!(uninterrupted.games, std_games);
+            } else if strategy == Strategy::Random {
+                std_games = uninterrupted.games.clone();
+            }
+            let config = Config {
+                strategy,
+                rng,
+                checkpoint: Some(config::CheckpointConfig {
+                    dir: dir.path().to_string_lossy().into_owned(),
+                    every_games: Some(10),
+                    keep: 5,
+                    ..config::CheckpointConfig::default()
+                }),
+                ..resume_config(40)
+            };
+            let checkpointed = generate(&config, GenerationStats::default()).unwrap();
+            assert_eq!(checkpointed.games, uninterrupted.games);
+
+            // None is written for the last game, which ends the run
+            let (checkpoint, _) =
+                Checkpoint::load(dir.path().join("checkpoint_000000010.json")).unwrap();
+            assert!(!dir.path().join("checkpoint_000000040.json").exists());
+            assert_eq!(checkpoint.games, uninterrupted.games[..10]);
+            let restarted =
+                resume_from_checkpoint(&config, checkpoint, GenerationStats::default()).unwrap();
+            assert_eq!(restarted.games, uninterrupted.games);
+            assert_eq!(restarted.stats.attempts, uninterrupted.stats.attempts);
+            assert_eq!(restarted.stats.accepted, uninterrupted.stats.accepted);
+            assert_eq!(restarted.stats.rng_draws, uninterrupted.stats.rng_draws);
+            assert_eq!(
+                restarted.stats.constraint_rejections,
+                uninterrupted.stats.constraint_rejections
+            );
+            assert_eq!(restarted.triplet_set, uninterrupted.triplet_set);
+        }
+    }
+
+    #[test]
+    fn test_output_orders_permute_the_games_found() {
+        let found = Config {
+            initial_games: vec![Game::new(vec![55, 56, 57, 58, 59, 60])],
+            label_pool: vec!["Maria".to_string()],
+            ..resume_config(20)
+        };
+        let ordered = |output_order, seed| {
+            generate(
+                &Config {
+                    output_order,
+                    seed: Some(seed),
+                    ..found.clone()
+                },
+                GenerationStats::default(),
+            )
+            .unwrap()
+            .games
+        };
+        let discovery = ordered(OutputOrder::Discovery, 3);
+        assert_eq!(discovery[0].numbers(), &[55, 56, 57, 58, 59, 60]);
+        let unlabeled = |games: &[Game]| {
+            let mut numbers: Vec<Vec<i64>> =
+                games.iter().map(|game| game.numbers().to_vec()).collect();
+            numbers.sort_unstable();
+            numbers
+        };
+
+        // Each order holds every game found, once
+        let shuffled = ordered(OutputOrder::Shuffle, 3);
+        let ascending = ordered(OutputOrder::Asc, 3);
+        let by_rank = ordered(OutputOrder::Rank, 3);
+        for games in [&shuffled, &ascending, &by_rank] {
+            assert_eq!(unlabeled(games), unlabeled(&discovery));
+        }
+        assert_ne!(shuffled, discovery);
+        assert_eq!(ordered(OutputOrder::Shuffle, 3), shuffled);
+        assert!(ascending.windows(2).all(|pair| pair[0] < pair[1]));
+        let ranks: Vec<i64> = by_rank
+            .iter()
+            .map(|game| custom_utils::game2enum_from(game, found.min_number))
+            .collect();
+        assert!(ranks.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_ne!(ascending, by_rank);
+
+        // The labels are given in the order written
+        assert_eq!(shuffled[0].label(), Some("Maria"));
+        assert_eq!(shuffled[1].label(), Some("auto-2"));
+    }
+}
@@ -0,0 +1,481 @@
+use crate::custom_utils;
+use crate::data_structures::NumberSet;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Number of numbers drawn in each result.
+pub const NUMBERS_PER_DRAW: usize = 6;
+
+/// A calendar day, as written in the files of past draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// Number of days of `month` in `year`, 0 for a month out of 1 to 12.
+    fn days_in_month(year: u16, month: u8) -> u8 {
+        match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if year.is_multiple_of(4)
+                && (!year.is_multiple_of(100) || year.is_multiple_of(400)) =>
+            {
+                29
+            }
+            2 => 28,
+            _ => 0,
+        }
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    /// Parses a date in the Brazilian format (`dd/mm/yyyy`) or in ISO 8601 (`yyyy-mm-dd`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("'{}' is not a valid date", s);
+        let fields: Vec<&str> = if s.contains('/') {
+            s.split('/').rev().collect()
+        } else {
+            s.split('-').collect()
+        };
+        let [year, month, day] = fields[..] else {
+            return Err(invalid());
+        };
+        let year: u16 = year.parse().map_err(|_| invalid())?;
+        let month: u8 = month.parse().map_err(|_| invalid())?;
+        let day: u8 = day.parse().map_err(|_| invalid())?;
+        if year < 1000 || day == 0 || day > Date::days_in_month(year, month) {
+            return Err(invalid());
+        }
+        Ok(Date { year, month, day })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}/{:02}/{:04}", self.day, self.month, self.year)
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A past result of the lottery.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Draw {
+    /// The number of the contest, when the file has them.
+    pub contest: Option<u32>,
+    pub date: Date,
+    /// The numbers drawn, sorted.
+    pub numbers: Vec<i64>,
+}
+
+/// Where the fields of a draw are in the rows of a file.
+struct Columns {
+    contest: Option<usize>,
+    date: usize,
+    numbers: Vec<usize>,
+}
+
+impl Columns {
+    /// The columns named by a header row: `Concurso` (or `contest`), the first column whose
+    /// name holds `data` (or `date`), and the `Bola` (or `Dezena`) columns, falling back to the
+    /// six columns after the date.
+    fn from_header(header: &csv::StringRecord) -> Result<Self, String> {
+        let names: Vec<String> = header
+            .iter()
+            .map(|name| name.trim().to_lowercase())
+            .collect();
+        let date = names
+            .iter()
+            .position(|name| name.contains("data") || name.contains("date"))
+            .ok_or_else(|| "row 1: the header has no date column".to_string())?;
+        let mut numbers: Vec<usize> = (0..names.len())
+            .filter(|&column| {
+                names[column].starts_with("bola") || names[column].starts_with("dezena")
+            })
+            .collect();
+        if numbers.len() != NUMBERS_PER_DRAW {
+            numbers = (date + 1..=date + NUMBERS_PER_DRAW).collect();
+        }
+        Ok(Columns {
+            contest: names
+                .iter()
+                .position(|name| name == "concurso" || name == "contest"),
+            date,
+            numbers,
+        })
+    }
+
+    /// The columns of a file without header, found from its first draw: the first field
+    /// holding a date, the six fields after it, and the contest just before it, if any.
+    fn from_first_draw(record: &csv::StringRecord, date: usize) -> Self {
+        let contest = date
+            .checked_sub(1)
+            .filter(|&column| record[column].trim().parse::<u32>().is_ok());
+        Columns {
+            contest,
+            date,
+            numbers: (date + 1..=date + NUMBERS_PER_DRAW).collect(),
+        }
+    }
+
+    /// Reads the draw of a row, checking its numbers are distinct and in
+    /// `min_number..=max_number`.
+    fn draw(
+        &self,
+        record: &csv::StringRecord,
+        min_number: i64,
+        max_number: i64,
+    ) -> Result<Draw, String> {
+        let field = |column: usize| {
+            record
+                .get(column)
+                .map(str::trim)
+                .ok_or_else(|| format!("the row has only {} fields", record.len()))
+        };
+        let date: Date = field(self.date)?.parse()?;
+        let contest = match self.contest {
+            Some(column) => {
+                let value = field(column)?;
+                let contest = value
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a contest number", value))?;
+                Some(contest)
+            }
+            None => None,
+        };
+        let mut numbers = Vec::with_capacity(NUMBERS_PER_DRAW);
+        for &column in &self.numbers {
+            let value = field(column)?;
+            let number: i64 = value
+                .parse()
+                .map_err(|_| format!("'{}' is not a number", value))?;
+            if !(min_number..=max_number).contains(&number) {
+                return Err(format!(
+                    "number {} is outside {} to {}",
+                    number, min_number, max_number
+                ));
+            }
+            if numbers.contains(&number) {
+                return Err(format!("number {} is drawn twice", number));
+            }
+            numbers.push(number);
+        }
+        numbers.sort_unstable();
+        Ok(Draw {
+            contest,
+            date,
+            numbers,
+        })
+    }
+}
+
+/// Reads a CSV file of past draws, one draw per row: a date and six numbers, optionally after
+/// a contest number and followed by other fields.
+///
+/// Fields may be separated by commas or semicolons. Files with a header row, like the export
+/// of the Caixa results (`Concurso;Data do Sorteio;Bola1;...;Bola6;...`), are read by column
+/// name; otherwise the numbers are the six fields after the first date. Dates are read in the
+/// Brazilian format (`dd/mm/yyyy`) or as `yyyy-mm-dd`.
+///
+/// # Arguments
+/// * `content` - The content of the file.
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+/// * `max_number` - The largest number of the lottery (e.g., 60).
+///
+/// # Returns
+/// * The draws, sorted by date then contest, and a warning for each row left out, naming the
+///   row (starting at 1).
+/// * An error if the header row names no date column.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::history::read_draws;
+///
+/// let (draws, warnings) = read_draws("18/03/1996;09;39;37;49;43;41\n", 1, 60).unwrap();
+/// assert_eq!(draws[0].date.to_string(), "18/03/1996");
+/// assert_eq!(draws[0].numbers, vec![9, 37, 39, 41, 43, 49]);
+/// assert!(warnings.is_empty());
+/// ```
+pub fn read_draws(
+    content: &str,
+    min_number: i64,
+    max_number: i64,
+) -> Result<(Vec<Draw>, Vec<String>), String> {
+    let content = content.trim_start_matches('\u{feff}');
+    let first_line = content.lines().next().unwrap_or("");
+    let delimiter = if first_line.matches(';').count() > first_line.matches(',').count() {
+        b';'
+    } else {
+        b','
+    };
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(content.as_bytes());
+
+    let mut columns = None;
+    let mut draws = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, record) in rdr.records().enumerate() {
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                warnings.push(format!("row {}: {}", index + 1, error));
+                continue;
+            }
+        };
+        let row = record
+            .position()
+            .map_or(index + 1, |position| position.line() as usize);
+        // The first row is either a header or the first draw
+        let columns = match columns {
+            Some(ref columns) => columns,
+            None => {
+                let date = record
+                    .iter()
+                    .position(|field| field.parse::<Date>().is_ok());
+                let Some(date) = date else {
+                    columns = Some(Columns::from_header(&record)?);
+                    continue;
+                };
+                columns.insert(Columns::from_first_draw(&record, date))
+            }
+        };
+        match columns.draw(&record, min_number, max_number) {
+            Ok(draw) => draws.push(draw),
+            Err(error) => warnings.push(format!("row {}: {}", row, error)),
+        }
+    }
+    draws.sort_by_key(|draw| (draw.date, draw.contest));
+    Ok((draws, warnings))
+}
+
+/// How often and how recently a number was drawn.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct NumberFrequency {
+    pub number: i64,
+    /// Number of draws the number is in.
+    pub draws: usize,
+    /// Share of the draws the number is in, from 0 to 1.
+    pub frequency: f64,
+    /// The date of the last draw the number is in, `None` if it was never drawn.
+    pub last_drawn: Option<Date>,
+    /// Number of draws since the number was last drawn: 0 if it is in the latest draw, all of
+    /// them if it was never drawn.
+    pub drought: usize,
+}
+
+/// The frequencies of the numbers of a lottery over its past draws.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FrequencyReport {
+    /// Number of draws read.
+    pub draws: usize,
+    /// The dates of the first and last draws, `None` without draws.
+    pub first_date: Option<Date>,
+    pub last_date: Option<Date>,
+    /// Every number of the lottery, most drawn first, then by number.
+    pub numbers: Vec<NumberFrequency>,
+}
+
+impl FrequencyReport {
+    /// The numbers drawn the longest time ago, with the length of their drought.
+    pub fn longest_drought(&self) -> (Vec<i64>, usize) {
+        let longest = self
+            .numbers
+            .iter()
+            .map(|number| number.drought)
+            .max()
+            .unwrap_or(0);
+        let mut numbers: Vec<i64> = self
+            .numbers
+            .iter()
+            .filter(|number| number.drought == longest)
+            .map(|number| number.number)
+            .collect();
+        numbers.sort_unstable();
+        (numbers, longest)
+    }
+}
+
+/// Counts how often and how recently each number of `min_number..=max_number` was drawn.
+///
+/// # Arguments
+/// * `draws` - The past draws, sorted by date as `read_draws` returns them.
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+/// * `max_number` - The largest number of the lottery (e.g., 60).
+///
+/// # Returns
+/// * The frequency of each number, most drawn first.
+pub fn frequencies(draws: &[Draw], min_number: i64, max_number: i64) -> FrequencyReport {
+    let mut numbers: Vec<NumberFrequency> = (min_number..=max_number)
+        .map(|number| {
+            let drawn: Vec<usize> = draws
+                .iter()
+                .enumerate()
+                .filter(|(_, draw)| draw.numbers.contains(&number))
+                .map(|(index, _)| index)
+                .collect();
+            let last = drawn.last().copied();
+            NumberFrequency {
+                number,
+                draws: drawn.len(),
+                frequency: if draws.is_empty() {
+                    0.0
+                } else {
+                    drawn.len() as f64 / draws.len() as f64
+                },
+                last_drawn: last.map(|index| draws[index].date),
+                drought: last.map_or(draws.len(), |index| draws.len() - 1 - index),
+            }
+        })
+        .collect();
+    numbers.sort_by(|a, b| b.draws.cmp(&a.draws).then(a.number.cmp(&b.number)));
+    FrequencyReport {
+        draws: draws.len(),
+        first_date: draws.first().map(|draw| draw.date),
+        last_date: draws.last().map(|draw| draw.date),
+        numbers,
+    }
+}
+
+/// The combinadic numbers of the games drawn, for numbers starting at `min_number`.
+pub fn ranks(draws: &[Draw], min_number: i64) -> NumberSet {
+    draws
+        .iter()
+        .map(|draw| custom_utils::game2enum_from(&draw.numbers, min_number))
+        .collect()
+}
+
+/// The first draw of the numbers of `game`, in any order, if they were ever drawn.
+pub fn find_draw<'a>(draws: &'a [Draw], game: &[i64]) -> Option<&'a Draw> {
+    let mut numbers = game.to_vec();
+    numbers.sort_unstable();
+    draws.iter().find(|draw| draw.numbers == numbers)
+}
+
+/// Weights for the weighted strategy favoring the numbers drawn most often: 1 plus the number
+/// of draws each number of `min_number..=max_number` is in, so that no number is left out.
+pub fn weights(draws: &[Draw], min_number: i64, max_number: i64) -> Vec<(i64, f64)> {
+    (min_number..=max_number)
+        .map(|number| {
+            let drawn = draws
+                .iter()
+                .filter(|draw| draw.numbers.contains(&number))
+                .count();
+            (number, 1.0 + drawn as f64)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../tests/fixtures/draws.csv");
+
+    #[test]
+    fn test_dates_in_both_formats() {
+        let date: Date = "09/03/2024".parse().unwrap();
+        assert_eq!(
+            date,
+            Date {
+                year: 2024,
+                month: 3,
+                day: 9
+            }
+        );
+        assert_eq!("2024-03-09".parse::<Date>().unwrap(), date);
+        assert_eq!(date.to_string(), "09/03/2024");
+        assert!("29/02/2024".parse::<Date>().is_ok());
+        for invalid in [
+            "29/02/2023",
+            "31/04/1996",
+            "00/01/2000",
+            "1/13/2000",
+            "2000",
+            "x",
+        ] {
+            assert!(invalid.parse::<Date>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_caixa_export_is_read_skipping_malformed_rows() {
+        let (draws, warnings) = read_draws(FIXTURE, 1, 60).unwrap();
+        let contests: Vec<Option<u32>> = draws.iter().map(|draw| draw.contest).collect();
+        assert_eq!(
+            contests,
+            [Some(1), Some(2), Some(3), Some(4), Some(5), Some(10)]
+        );
+        assert_eq!(draws[0].numbers, vec![4, 5, 30, 33, 41, 52]);
+        assert_eq!(draws[5].date.to_string(), "06/05/1996");
+        assert_eq!(
+            warnings,
+            [
+                "row 7: '31/04/1996' is not a valid date",
+                "row 8: number 61 is outside 1 to 60",
+                "row 9: number 5 is drawn twice",
+                "row 10: 'x' is not a number",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plain_rows_are_read_and_sorted_by_date() {
+        let content = "2024-03-09,1,2,3,4,5,6\n\n10/02/2024,7,8,9,10,11,12,extra\n12,13\n";
+        let (draws, warnings) = read_draws(content, 1, 60).unwrap();
+        assert_eq!(draws.len(), 2);
+        assert_eq!(draws[0].numbers, vec![7, 8, 9, 10, 11, 12]);
+        assert_eq!(draws[0].contest, None);
+        assert_eq!(warnings, ["row 4: '12' is not a valid date"]);
+        assert!(read_draws("a;b;c\n", 1, 60).is_err());
+    }
+
+    #[test]
+    fn test_frequencies_and_droughts() {
+        let (draws, _) = read_draws(FIXTURE, 1, 60).unwrap();
+        let report = frequencies(&draws, 1, 60);
+        assert_eq!(report.draws, 6);
+        assert_eq!(report.numbers.len(), 60);
+        assert_eq!(report.first_date.unwrap().to_string(), "11/03/1996");
+        // 5 is in four draws, the latest among them
+        let hottest = &report.numbers[0];
+        assert_eq!((hottest.number, hottest.draws, hottest.drought), (5, 4, 0));
+        assert!((hottest.frequency - 4.0 / 6.0).abs() < 1e-12);
+        let number = |n: i64| report.numbers.iter().find(|f| f.number == n).unwrap();
+        assert_eq!(number(9).drought, 4);
+        assert_eq!(number(9).last_drawn.unwrap().to_string(), "18/03/1996");
+        assert_eq!(number(2).last_drawn, None);
+        assert_eq!(number(2).drought, 6);
+        let (numbers, drought) = report.longest_drought();
+        assert_eq!(drought, 6);
+        assert!(numbers.starts_with(&[2, 3, 7]));
+
+        let ranks = ranks(&draws, 1);
+        // Contest 5 repeats the numbers of contest 4
+        assert_eq!(ranks.len(), 5);
+        assert!(ranks.contains(custom_utils::game2enum(&[4, 5, 30, 33, 41, 52])));
+        assert_eq!(
+            find_draw(&draws, &[41, 5, 4, 52, 30, 33]).map(|draw| draw.contest),
+            Some(Some(1))
+        );
+        assert_eq!(find_draw(&draws, &[1, 2, 3, 4, 5, 6]), None);
+
+        let weights = weights(&draws, 1, 60);
+        assert_eq!(weights.len(), 60);
+        assert_eq!(weights[4], (5, 5.0));
+        assert_eq!(weights[1], (2, 1.0));
+    }
+}
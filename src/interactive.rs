@@ -0,0 +1,343 @@
+use crate::config::Config;
+use crate::custom_utils;
+use crate::game::{Game, GameStats};
+use crate::generation::{GameGenerator, GenError, Generated};
+use crate::stats::GenerationStats;
+use console::{Key, Term};
+use std::fmt;
+use std::io::{self, IsTerminal};
+
+/// File the decisions of an interactive session are written to by `--interactive`.
+pub const SESSION_LOG_FILE: &str = "session_log.csv";
+
+/// What becomes of a candidate reviewed in an interactive session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// The game is played, as a game accepted by a run.
+    Accept,
+    /// The game is turned down for good, never offered again.
+    Reject,
+    /// The game is put back and another drawn in its place; it may be offered again later.
+    Reroll,
+    /// The session ends with the games accepted so far, the candidate turned down.
+    Quit,
+}
+
+impl Decision {
+    /// The decision of a key: `a` accepts, `r` rejects, `n` draws a new game and `q` quits,
+    /// in either case.
+    pub fn from_key(key: char) -> Option<Self> {
+        match key.to_ascii_lowercase() {
+            'a' => Some(Decision::Accept),
+            'r' => Some(Decision::Reject),
+            'n' => Some(Decision::Reroll),
+            'q' => Some(Decision::Quit),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Decision::Accept => "accept",
+            Decision::Reject => "reject",
+            Decision::Reroll => "reroll",
+            Decision::Quit => "quit",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A valid candidate as shown for review: the game with its figures and how close it comes to
+/// the games accepted before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidateReview {
+    /// The candidate.
+    pub game: Game,
+    /// The row the game would take if accepted, starting at 1, initial games included.
+    pub row: usize,
+    /// The number of games wanted, initial games included.
+    pub requested: usize,
+    /// The figures of the game.
+    pub stats: GameStats,
+    /// The most numbers the game shares with an accepted game, with the row of the first such
+    /// game; `None` before any game is accepted.
+    pub overlap: Option<(usize, usize)>,
+}
+
+impl CandidateReview {
+    /// Describes `game`, drawn after the games of `accepted`, for games of `min_number` to
+    /// `max_number`.
+    pub fn new(
+        game: &Game,
+        accepted: &[Game],
+        requested: usize,
+        min_number: i64,
+        max_number: i64,
+    ) -> Self {
+        let mut overlap: Option<(usize, usize)> = None;
+        for (row, other) in accepted.iter().enumerate() {
+            let shared = custom_utils::game_overlap(game, other);
+            if overlap.is_none_or(|(most, _)| shared > most) {
+                overlap = Some((shared, row + 1));
+            }
+        }
+        Self {
+            game: game.clone(),
+            row: accepted.len() + 1,
+            requested,
+            stats: game.stats(min_number, max_number),
+            overlap,
+        }
+    }
+}
+
+/// The candidate on a line, its figures on the next, e.g.
+/// `Game 4 of 10: 05-12-33-41-50-58` then
+/// `sum 199, 3 odd, 2 low, longest run 1, shares 2 number(s) with game #3`.
+impl fmt::Display for CandidateReview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Game {} of {}: {}", self.row, self.requested, self.game)?;
+        write!(
+            f,
+            "sum {}, {} odd, {} low, longest run {}, ",
+            self.stats.sum, self.stats.odd, self.stats.low, self.stats.max_run
+        )?;
+        match self.overlap {
+            Some((0, _)) => write!(f, "shares no number with the games accepted"),
+            Some((shared, row)) => write!(f, "shares {} number(s) with game #{}", shared, row),
+            None => write!(f, "the first game"),
+        }
+    }
+}
+
+/// A decision of an interactive session, as recorded in the session log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionEntry {
+    /// The candidate reviewed.
+    pub game: Game,
+    pub decision: Decision,
+    /// Number of games accepted after the decision, initial games included.
+    pub games: usize,
+    /// Number of candidates drawn by then, those turned down by the rules included.
+    pub attempts: u64,
+}
+
+/// Writes the decisions of a session as CSV, one row per candidate reviewed, in order:
+/// `step,game,decision,games,attempts`, the step starting at 1.
+pub fn session_log_csv(entries: &[SessionEntry]) -> Vec<u8> {
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    wtr.write_record(["step", "game", "decision", "games", "attempts"])
+        .expect("writing to memory cannot fail");
+    for (step, entry) in entries.iter().enumerate() {
+        wtr.write_record([
+            (step + 1).to_string(),
+            entry.game.to_string(),
+            entry.decision.to_string(),
+            entry.games.to_string(),
+            entry.attempts.to_string(),
+        ])
+        .expect("writing to memory cannot fail");
+    }
+    wtr.into_inner().expect("writing to memory cannot fail")
+}
+
+/// Generates the games of a configuration one candidate at a time, each valid candidate
+/// reviewed by `decide` before it is accepted, until there are `no_of_games` games, the
+/// session is quit or the stop flag of `stats` is raised.
+///
+/// # Arguments
+/// * `config` - The configuration of the run, drawing its games one at a time.
+/// * `stats` - The counters to update.
+/// * `session` - The decisions taken, appended to as they are, for the session log.
+/// * `decide` - Chooses what becomes of each candidate, e.g. from the keys typed by
+///   `terminal_decider` or from a script.
+///
+/// # Returns
+/// * The games accepted, initial games included, `interrupted` if there are fewer than
+///   `no_of_games` of them.
+/// * An error if the configuration does not draw its games one at a time, an initial game is
+///   invalid or every remaining game in the range was tried without success.
+pub fn review(
+    config: &Config,
+    stats: GenerationStats,
+    session: &mut Vec<SessionEntry>,
+    mut decide: impl FnMut(&CandidateReview) -> Decision,
+) -> Result<Generated, GenError> {
+    let mut generator = GameGenerator::builder()
+        .config(config.clone())
+        .stats(stats)
+        .build()?;
+    let requested = generator.no_of_games();
+    while let Some((game, decision)) = generator.review_next_candidate(|game, accepted| {
+        decide(&CandidateReview::new(
+            game,
+            accepted,
+            requested,
+            config.min_number,
+            config.max_number,
+        ))
+    })? {
+        session.push(SessionEntry {
+            game,
+            decision,
+            games: generator.games().len(),
+            attempts: generator.stats().attempts,
+        });
+        if decision == Decision::Quit {
+            break;
+        }
+    }
+    Ok(generator.into_generated())
+}
+
+/// Reads the decisions from the keys typed in the terminal, showing each candidate on
+/// stdout; keys of no decision are ignored, Escape and Ctrl-C quit the session, and so does a
+/// key that cannot be read, with a warning logged.
+///
+/// # Returns
+/// * The decider, for `review`.
+/// * An error if stdin or stdout is not a terminal, e.g. when the output is piped.
+pub fn terminal_decider() -> io::Result<impl FnMut(&CandidateReview) -> Decision> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(io::Error::other(
+            "--interactive needs a terminal to read the keys from; run without it to generate the games in batch mode",
+        ));
+    }
+    let term = Term::stdout();
+    Ok(move |review: &CandidateReview| {
+        println!("\n{}", review);
+        println!("[a]ccept  [r]eject  [n]ew draw  [q]uit");
+        loop {
+            match term.read_key() {
+                Ok(Key::Char(key)) => {
+                    if let Some(decision) = Decision::from_key(key) {
+                        return decision;
+                    }
+                }
+                Ok(Key::Escape | Key::CtrlC) => return Decision::Quit,
+                Ok(_) => {}
+                Err(error) => {
+                    log::warn!("cannot read the key typed ({}), quitting", error);
+                    return Decision::Quit;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(no_of_games: usize) -> Config {
+        Config::from_toml(&format!(
+            "no_of_games = {}\ninitial_games = [[1, 2, 3, 4, 5, 6]]\nseed = 3\nmax_number = 60\nmin_desired_number = 1\n",
+            no_of_games
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_scripted_decisions_pick_the_games() {
+        let mut script = vec![
+            Decision::Reject,
+            Decision::Accept,
+            Decision::Reroll,
+            Decision::Reject,
+            Decision::Accept,
+        ]
+        .into_iter();
+        let mut reviews = Vec::new();
+        let mut session = Vec::new();
+        let generated = review(
+            &config(3),
+            GenerationStats::default(),
+            &mut session,
+            |review| {
+                reviews.push(review.clone());
+                script.next().unwrap()
+            },
+        )
+        .unwrap();
+
+        assert!(!generated.interrupted);
+        assert_eq!(
+            session
+                .iter()
+                .map(|entry| (entry.decision, entry.games))
+                .collect::<Vec<_>>(),
+            [
+                (Decision::Reject, 1),
+                (Decision::Accept, 2),
+                (Decision::Reroll, 2),
+                (Decision::Reject, 2),
+                (Decision::Accept, 3)
+            ]
+        );
+        assert_eq!(
+            generated.games[1..],
+            [session[1].game.clone(), session[4].game.clone()]
+        );
+        assert_eq!(reviews[0].row, 2);
+        assert_eq!(reviews[0].requested, 3);
+        assert_eq!(reviews[2].row, 3);
+        assert!(reviews[2].overlap.is_some_and(|(shared, _)| shared < 3));
+        // The triplets of the games turned down stay free, a rejected game is never drawn again
+        assert_eq!(generated.triplet_set.len(), 3 * 20);
+        let rank = |entry: &SessionEntry| custom_utils::game2enum_from(&entry.game, 1);
+        assert!(generated.game_set.contains(rank(&session[0])));
+        assert!(!generated.game_set.contains(rank(&session[2])));
+        assert_eq!(generated.stats.accepted, 2);
+
+        let log = String::from_utf8(session_log_csv(&session)).unwrap();
+        let rows: Vec<&str> = log.lines().collect();
+        assert_eq!(rows[0], "step,game,decision,games,attempts");
+        assert!(rows[3].starts_with(&format!("3,{},reroll,2,", session[2].game)));
+        assert_eq!(rows.len(), 6);
+    }
+
+    #[test]
+    fn test_quitting_keeps_the_games_accepted() {
+        let mut session = Vec::new();
+        let generated = review(
+            &config(5),
+            GenerationStats::default(),
+            &mut session,
+            |review| {
+                if review.row < 3 {
+                    Decision::Accept
+                } else {
+                    Decision::Quit
+                }
+            },
+        )
+        .unwrap();
+        assert!(generated.interrupted);
+        assert_eq!(generated.games.len(), 2);
+        assert_eq!(session.last().unwrap().decision, Decision::Quit);
+        assert_eq!(generated.triplet_set.len(), 2 * 20);
+    }
+
+    #[test]
+    fn test_review_shows_the_figures_and_the_closest_game() {
+        let accepted = [
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![10, 20, 30, 40, 50, 60]),
+        ];
+        let review = CandidateReview::new(
+            &Game::new(vec![5, 12, 30, 41, 50, 58]),
+            &accepted,
+            10,
+            1,
+            60,
+        );
+        assert_eq!(
+            review.to_string(),
+            "Game 3 of 10: 05-12-30-41-50-58\nsum 196, 2 odd, 3 low, longest run 1, shares 2 number(s) with game #2"
+        );
+        assert_eq!(Decision::from_key('A'), Some(Decision::Accept));
+        assert_eq!(Decision::from_key('x'), None);
+    }
+}
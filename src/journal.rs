@@ -0,0 +1,360 @@
+use crate::custom_utils;
+use crate::data_structures::{NumberSet, SetFileError, SetFormat, SetHeader, TripletSet};
+use crate::game::Game;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// File the games of a run are journaled to as they are accepted, when `journal` is set.
+pub const JOURNAL_FILE: &str = "run_journal.bin";
+
+/// Bytes a journal starts with.
+pub const JOURNAL_MAGIC: &[u8; 4] = b"RKJL";
+
+/// Version of the journal format, written after `JOURNAL_MAGIC`.
+pub const JOURNAL_VERSION: u8 = 1;
+
+/// Accepted games between two flushes of the journal to disk.
+pub const SYNC_INTERVAL: usize = 100;
+
+/// Size of a record: the rank of a game and the ranks of its 20 triplets, as little-endian
+/// 64-bit integers, followed by a 32-bit checksum of those 168 bytes.
+const RECORD_LEN: usize = 21 * 8 + 4;
+
+/// An append-only log of the games accepted by a run, from which the sets of games and
+/// triplets can be rebuilt if the run dies before saving them (see `Replay`).
+///
+/// Each game is written as one fixed-size record with a checksum, so a record cut short by a
+/// crash is told apart from the complete ones and dropped on replay. The records are handed
+/// to the operating system as they are appended, and flushed to disk every `sync_interval`
+/// games.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::journal::{Journal, Replay};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("run_journal.bin");
+/// let mut journal = Journal::create(&path, 100).unwrap();
+/// journal.append_game(&[1, 2, 3, 4, 5, 6], 1).unwrap();
+/// drop(journal);
+/// let replay = Replay::from_file(&path).unwrap();
+/// assert_eq!(replay.game_nos, [0]);
+/// assert_eq!(replay.triplet_set.len(), 20);
+/// ```
+pub struct Journal {
+    file: File,
+    path: PathBuf,
+    /// Games flushed to disk at once.
+    sync_interval: usize,
+    /// Games appended since the last flush to disk.
+    unsynced: usize,
+}
+
+impl Journal {
+    /// Creates an empty journal, replacing the file at `path` if there is one.
+    ///
+    /// # Arguments
+    /// * `path` - The file of the journal.
+    /// * `sync_interval` - The number of games between two flushes to disk, 1 at least.
+    ///
+    /// # Returns
+    /// * The journal, its header written to disk.
+    /// * An error if the file could not be written.
+    pub fn create(path: impl AsRef<Path>, sync_interval: usize) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::create(&path)?;
+        file.write_all(JOURNAL_MAGIC)?;
+        file.write_all(&[JOURNAL_VERSION])?;
+        file.sync_all()?;
+        Ok(Self {
+            file,
+            path,
+            sync_interval: sync_interval.max(1),
+            unsynced: 0,
+        })
+    }
+
+    /// Appends the record of an accepted game.
+    ///
+    /// # Arguments
+    /// * `game_no` - The combinadic number of the game.
+    /// * `triplet_nos` - The combinadic numbers of its triplets.
+    ///
+    /// # Returns
+    /// * `Ok(())` once the record is written, and flushed to disk if `sync_interval` games
+    ///   have been appended since the last flush.
+    /// * An error if the file could not be written.
+    pub fn append(&mut self, game_no: i64, triplet_nos: &[i64; 20]) -> io::Result<()> {
+        let mut record = Vec::with_capacity(RECORD_LEN);
+        for rank in std::iter::once(&game_no).chain(triplet_nos) {
+            record.extend_from_slice(&rank.to_le_bytes());
+        }
+        let checksum = fnv1a(&record);
+        record.extend_from_slice(&checksum.to_le_bytes());
+        self.file.write_all(&record)?;
+        self.unsynced += 1;
+        if self.unsynced >= self.sync_interval {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Appends the record of an accepted game, from its numbers in ascending order.
+    ///
+    /// # Arguments
+    /// * `game` - The numbers of the game.
+    /// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+    pub fn append_game(&mut self, game: &[i64], min_number: i64) -> io::Result<()> {
+        self.append(
+            custom_utils::game2enum_from(game, min_number),
+            &custom_utils::game2triplet_enums_from(game, min_number),
+        )
+    }
+
+    /// Flushes the records appended so far to disk.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()?;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Replaces the journal by snapshots of the sets, once the run has completed: the sets
+    /// are saved like `NumberSet::save_with_header` does, then the journal is removed.
+    ///
+    /// # Arguments
+    /// * `game_set` - The set of game numbers of the run.
+    /// * `triplet_set` - The set of triplet numbers of the run.
+    /// * `n` - The number of numbers of the lottery, recorded in the headers of the sets.
+    /// * `games_path` - The file the game set is saved to.
+    /// * `triplets_path` - The file the triplet set is saved to.
+    /// * `format` - The format of the snapshots, by file extension when `None`.
+    ///
+    /// # Returns
+    /// * `Ok(())` once the snapshots are written and the journal removed.
+    /// * An error if a file could not be written or removed, the journal being kept then.
+    pub fn compact(
+        mut self,
+        game_set: &NumberSet,
+        triplet_set: &TripletSet,
+        n: i64,
+        games_path: &str,
+        triplets_path: &str,
+        format: Option<SetFormat>,
+    ) -> io::Result<()> {
+        self.sync()?;
+        game_set.save_with_header(games_path, format, &SetHeader::games(n))?;
+        triplet_set.save_with_header(triplets_path, format, &SetHeader::triplets(n))?;
+        fs::remove_file(&self.path)
+    }
+}
+
+/// The games and sets rebuilt from a journal left by a run that did not complete.
+#[derive(Debug, Default)]
+pub struct Replay {
+    /// The combinadic numbers of the games, in the order they were accepted.
+    pub game_nos: Vec<i64>,
+    /// The combinadic numbers of the games.
+    pub game_set: NumberSet,
+    /// The combinadic numbers of the triplets of the games.
+    pub triplet_set: TripletSet,
+    /// Number of bytes after the last complete record, dropped from the replay.
+    pub dropped_bytes: usize,
+}
+
+impl Replay {
+    /// Replays the journal saved in a file; see `from_bytes`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SetFileError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Replays a journal, stopping at the last complete record: a record cut short, or whose
+    /// checksum does not match, ends the replay along with the bytes after it.
+    ///
+    /// # Returns
+    /// * The games of the complete records and their sets.
+    /// * An error if the bytes are not a journal, or one of a later version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SetFileError> {
+        let Some(rest) = bytes.strip_prefix(JOURNAL_MAGIC) else {
+            return Err(SetFileError::WrongFormat("file is not a journal of games"));
+        };
+        let Some((&version, records)) = rest.split_first() else {
+            return Err(SetFileError::Corrupted(
+                "the journal ends in its header".to_string(),
+            ));
+        };
+        if version != JOURNAL_VERSION {
+            return Err(SetFileError::UnsupportedVersion(version));
+        }
+
+        let mut replay = Self::default();
+        let mut replayed = 0;
+        for record in records.chunks(RECORD_LEN) {
+            let Some((ranks, checksum)) = record.split_last_chunk::<4>() else {
+                break;
+            };
+            if record.len() != RECORD_LEN || fnv1a(ranks) != u32::from_le_bytes(*checksum) {
+                break;
+            }
+            let mut ranks = ranks
+                .chunks_exact(8)
+                .map(|bytes| i64::from_le_bytes(bytes.try_into().expect("chunks of 8 bytes")));
+            let game_no = ranks.next().expect("a record holds 21 ranks");
+            replay.game_nos.push(game_no);
+            replay.game_set.add_number(game_no);
+            for triplet_no in ranks {
+                if u32::try_from(triplet_no).is_err() {
+                    return Err(SetFileError::Corrupted(format!(
+                        "triplet rank {} of game rank {} is not that of a supported lottery",
+                        triplet_no, game_no
+                    )));
+                }
+                replay.triplet_set.add_number(triplet_no);
+            }
+            replayed += RECORD_LEN;
+        }
+        replay.dropped_bytes = records.len() - replayed;
+        Ok(replay)
+    }
+
+    /// The games of the journal, in the order they were accepted, numbers in ascending order.
+    ///
+    /// # Arguments
+    /// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+    /// * `max_number` - The largest number of the lottery (e.g., 60).
+    ///
+    /// # Returns
+    /// * The games.
+    /// * An error if a rank is not that of a game of the lottery.
+    pub fn games(&self, min_number: i64, max_number: i64) -> Result<Vec<Game>, SetFileError> {
+        self.game_nos
+            .iter()
+            .map(|&game_no| {
+                custom_utils::enum2subset_from(game_no, max_number - min_number + 1, 6, min_number)
+                    .map(Game::new)
+                    .map_err(|error| SetFileError::Corrupted(error.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// The 32-bit FNV-1a hash of `bytes`, the checksum of a record.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A journal of the games given, as written to disk.
+    fn journal_of(games: &[[i64; 6]]) -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        let mut journal = Journal::create(&path, 2).unwrap();
+        for game in games {
+            journal.append_game(game, 1).unwrap();
+        }
+        drop(journal);
+        fs::read(&path).unwrap()
+    }
+
+    const GAMES: [[i64; 6]; 3] = [
+        [1, 2, 3, 4, 5, 6],
+        [1, 7, 8, 9, 10, 11],
+        [20, 30, 40, 50, 55, 60],
+    ];
+
+    #[test]
+    fn test_replay_rebuilds_the_games_and_sets() {
+        let bytes = journal_of(&GAMES);
+        assert_eq!(bytes.len(), 5 + 3 * RECORD_LEN);
+        let replay = Replay::from_bytes(&bytes).unwrap();
+        assert_eq!(replay.dropped_bytes, 0);
+        assert_eq!(
+            replay.games(1, 60).unwrap(),
+            GAMES.map(|game| Game::new(game.to_vec()))
+        );
+        assert_eq!(replay.game_set.len(), 3);
+        assert_eq!(replay.triplet_set.len(), 60);
+        assert!(replay
+            .triplet_set
+            .contains(custom_utils::triplet2enum(&[40, 50, 60]).unwrap()));
+        assert!(Replay::from_bytes(&journal_of(&[]))
+            .unwrap()
+            .game_nos
+            .is_empty());
+    }
+
+    #[test]
+    fn test_replay_stops_at_the_last_complete_record() {
+        let bytes = journal_of(&GAMES);
+        // Cut in the middle of the third record, then right after the second one
+        for (end, dropped) in [(5 + 2 * RECORD_LEN + 50, 50), (5 + 2 * RECORD_LEN, 0)] {
+            let replay = Replay::from_bytes(&bytes[..end]).unwrap();
+            assert_eq!(replay.game_nos.len(), 2);
+            assert_eq!(replay.triplet_set.len(), 40);
+            assert_eq!(replay.dropped_bytes, dropped);
+        }
+        // A record whose bytes were not all written ends the replay, the records after it too
+        let mut garbled = bytes.clone();
+        garbled[5 + RECORD_LEN + 3] ^= 0xff;
+        let replay = Replay::from_bytes(&garbled).unwrap();
+        assert_eq!(replay.game_nos.len(), 1);
+        assert_eq!(replay.dropped_bytes, 2 * RECORD_LEN);
+    }
+
+    #[test]
+    fn test_other_files_are_not_replayed() {
+        assert!(matches!(
+            Replay::from_bytes(b"[1,2,3]"),
+            Err(SetFileError::WrongFormat(_))
+        ));
+        assert!(matches!(
+            Replay::from_bytes(b"RKJL"),
+            Err(SetFileError::Corrupted(_))
+        ));
+        assert!(matches!(
+            Replay::from_bytes(b"RKJL\x09"),
+            Err(SetFileError::UnsupportedVersion(9))
+        ));
+        // A rank beyond the games of the lottery
+        let replay = Replay::from_bytes(&journal_of(&GAMES)).unwrap();
+        assert!(replay.games(1, 50).is_err());
+    }
+
+    #[test]
+    fn test_compact_saves_snapshots_and_removes_the_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        let mut journal = Journal::create(&path, 1).unwrap();
+        for game in &GAMES {
+            journal.append_game(game, 1).unwrap();
+        }
+        let replay = Replay::from_file(&path).unwrap();
+        let games_path = dir.path().join("games.csv");
+        let triplets_path = dir.path().join("triplet_set.log");
+        let (games_path, triplets_path) = (
+            games_path.to_str().unwrap(),
+            triplets_path.to_str().unwrap(),
+        );
+        journal
+            .compact(
+                &replay.game_set,
+                &replay.triplet_set,
+                60,
+                games_path,
+                triplets_path,
+                None,
+            )
+            .unwrap();
+        assert!(!path.exists());
+        assert_eq!(
+            NumberSet::load_with_header(games_path).unwrap(),
+            (replay.game_set.clone(), Some(SetHeader::games(60)))
+        );
+        assert_eq!(NumberSet::load(triplets_path).unwrap(), replay.triplet_set);
+    }
+}
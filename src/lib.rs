@@ -0,0 +1,63 @@
+//! Generation of lottery games in which no two games share a triplet of numbers.
+//!
+//! Games and their triplets are identified by their combinadic numbers, so uniqueness is
+//! checked with plain sets of integers. The `generation` module drives a whole run from a
+//! [`config::Config`]; the other modules expose the building blocks it relies on.
+//!
+//! # Examples
+//! ```
+//! use rk_lottery::config::Config;
+//! use rk_lottery::custom_utils;
+//! use rk_lottery::data_structures::NumberSet;
+//!
+//! let config = Config::from_toml(
+//!     r#"
+//!     no_of_games = 10
+//!     initial_games = []
+//!     seed = 1
+//!     max_number = 60
+//!     min_desired_number = 1
+//!     "#,
+//! )
+//! .unwrap();
+//! let games = rk_lottery::generation::generate_games(&config).unwrap();
+//!
+//! let mut triplet_set = NumberSet::new();
+//! for game in &games {
+//!     assert!(triplet_set.check_and_insert_all(&custom_utils::game2triplet_enums(game)));
+//! }
+//! ```
+
+pub mod analysis;
+pub mod caixa;
+pub mod checkpoint;
+pub mod config;
+pub mod constraints;
+pub mod custom_utils;
+pub mod data_structures;
+pub mod diff;
+pub mod error;
+pub mod feasibility;
+pub mod game;
+pub mod games_file;
+pub mod generation;
+pub mod history;
+pub mod interactive;
+pub mod journal;
+pub mod manifest;
+pub mod optimize;
+pub mod persistence;
+pub mod repair;
+pub mod report;
+pub mod rng;
+pub mod scoring;
+pub mod selftest;
+pub mod shared_set;
+pub mod stats;
+#[cfg(feature = "sqlite")]
+pub mod storage;
+pub mod ticket_table;
+pub mod validation;
+pub mod wheel;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
@@ -1,81 +1,3016 @@
-mod config;
-mod custom_utils;
-mod data_structures;
-
-use config::Config;
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = Config::from_file("config.toml")?;
-    let mut wtr = csv::Writer::from_path("optimized_games.csv")?;
-    let mut games = config.initial_games.clone();
-    let mut game_set = data_structures::NumberSet::new();
-    let mut triplet_set = data_structures::NumberSet::new();
-
-    // Instantiate the invalidate_game closure
-    let invalidate_game =
-        custom_utils::create_invalidate_game(config.min_desired_number, config.max_number);
-
-    // initialize the game_set and triplet_set with the initial games:
-    for game in games.clone() {
-        // check if game is valid:
-        if invalidate_game(game.as_ref()) {
-            // Game is not valid, inform the game:
-            panic!("Invalid given at input found! Game: {:?}", game);
-        }
-        // Convert game to number:
-        let game_no = custom_utils::game2enum(game.clone());
-        // Convert to triplets:
-        let triplets = custom_utils::game2triplets(game.clone());
-        // Convert triplets to numbers:
-        let triplet_nos = triplets
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressStyle};
+use rand::SeedableRng;
+#[cfg(feature = "net")]
+use rk_lottery::caixa::FetchError;
+use rk_lottery::config::{self, Config, Lottery, OutputFormat, OutputOrder, Overrides, Strategy};
+use rk_lottery::data_structures::{
+    NumberSet, Rank, SetFileError, SetHeader, SetKind, SubsetIndex, TripletSet,
+};
+use rk_lottery::error::{self, Error, ExitSummary};
+use rk_lottery::games_file::{self, Layout};
+use rk_lottery::manifest::{self, RunManifest};
+use rk_lottery::stats::GenerationStats;
+#[cfg(feature = "sqlite")]
+use rk_lottery::storage::{Storage, StorageError};
+use rk_lottery::{
+    analysis, checkpoint, constraints, custom_utils, diff, feasibility, game, generation, history,
+    interactive, journal, optimize, persistence, repair, report, rng, scoring, selftest,
+    ticket_table, validation, wheel,
+};
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Generates lottery games in which no two games share a triplet of numbers.
+///
+/// Settings are read from the configuration file; the flags below replace the values of the
+/// file, which in turn replace the built-in defaults. Without a mode flag, the games are
+/// generated (or the wheel of the `[wheel]` section, when present) and written to the output file.
+#[derive(Parser)]
+#[command(group(ArgGroup::new("mode").multiple(false)))]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Configuration file to read the settings from
+    #[arg(long, value_name = "PATH", default_value = "config.toml")]
+    config: String,
+    /// Number of games to generate, replacing `no_of_games` of the configuration file
+    #[arg(long, value_name = "N")]
+    games: Option<usize>,
+    /// Random seed, replacing `seed` of the configuration file
+    #[arg(long, value_name = "S")]
+    seed: Option<u64>,
+    /// Maximum playable number, replacing `max_number` of the configuration file
+    #[arg(long, value_name = "N")]
+    max_number: Option<i64>,
+    /// Minimum number allowed in a game, replacing `min_desired_number` of the configuration file
+    #[arg(long, value_name = "N")]
+    min_desired: Option<i64>,
+    /// File the games are written to, replacing `output` of the configuration file
+    /// (optimized_games.csv by default)
+    #[arg(long, value_name = "PATH")]
+    output: Option<String>,
+    /// Format of the output file, replacing `output_format` of the configuration file (csv by
+    /// default)
+    #[arg(long, value_name = "FORMAT", value_enum)]
+    format: Option<OutputFormat>,
+    /// Write the games in an order shuffled with the seed of the run, instead of the order
+    /// they were found in (same as `output_order = "shuffle"` in the configuration file)
+    #[arg(long, conflicts_with = "sort_output")]
+    shuffle_output: bool,
+    /// Write the games sorted by their numbers (asc) or by their combinadic rank (rank),
+    /// replacing `output_order` of the configuration file
+    #[arg(long, value_name = "ORDER", value_enum)]
+    sort_output: Option<OutputOrder>,
+    /// How the games are chosen among the valid candidates, replacing `strategy` of the
+    /// configuration file (random by default)
+    #[arg(long, value_name = "STRATEGY", value_enum)]
+    strategy: Option<Strategy>,
+    /// Continue the previous run from the output file, games.csv and triplet_set.log,
+    /// appending the missing games (same as `resume = true` in the configuration file)
+    #[arg(long)]
+    resume: bool,
+    /// Add --games (`no_of_games`) games to those already in the output file, sharing no
+    /// triplet with them (same as `append = true` in the configuration file)
+    #[arg(long)]
+    append: bool,
+    /// Generate N independent batches of games, each with its own seed and output file,
+    /// replacing `batches` of the configuration file
+    #[arg(long, value_name = "N")]
+    batches: Option<usize>,
+    /// Carry on the run of a checkpoint, with its settings, as if it had never stopped: PATH is
+    /// a checkpoint file, or a directory of them whose latest is used
+    #[arg(
+        long,
+        value_name = "PATH",
+        group = "mode",
+        conflicts_with_all = [
+            "config", "games", "seed", "max_number", "min_desired", "output", "format",
+            "shuffle_output", "sort_output", "strategy", "resume", "append", "batches",
+        ]
+    )]
+    from_checkpoint: Option<String>,
+    /// Do not show the progress bar while generating
+    #[arg(long)]
+    quiet: bool,
+    /// Print the summary of the generated games as JSON on stdout; see --exit-summary for how
+    /// the run ended
+    #[arg(long)]
+    json_summary: bool,
+    /// Print how the run ended, succeeded or not, as a JSON object on the last line of stderr,
+    /// or write it to FILE (given as --exit-summary=FILE); see --json-summary for the figures of
+    /// the games generated
+    #[arg(long, value_name = "FILE", num_args = 0..=1, require_equals = true)]
+    exit_summary: Option<Option<String>>,
+    /// Print a histogram of the games playing each number once generated, flagging the numbers
+    /// none plays, and write the counts to number_frequencies.csv
+    #[arg(long, conflicts_with = "json_summary")]
+    histogram: bool,
+    /// Review the candidates one at a time in the terminal, accepting, rejecting or redrawing
+    /// each with a key, and write the decisions to session_log.csv
+    #[arg(
+        long,
+        conflicts_with_all = ["mode", "json_summary", "resume", "append", "batches"]
+    )]
+    interactive: bool,
+    /// Check the configuration and estimate whether the games can be generated and how long
+    /// it would take instead, writing nothing
+    #[arg(long, group = "mode")]
+    dry_run: bool,
+    /// Check a game against the constraints instead, e.g. 03-11-22-34-45-58
+    #[arg(long, value_name = "GAME", group = "mode")]
+    check: Option<String>,
+    /// Print the games of a file (the output file by default) as a table of tickets instead
+    #[arg(long, value_name = "FILE", num_args = 0..=1, group = "mode")]
+    print: Option<Option<String>>,
+    /// Improve the games of a CSV file by simulated annealing instead
+    #[arg(long, value_name = "CSV", group = "mode")]
+    optimize: Option<String>,
+    /// Verify the combinadic conversions instead, for the K-subsets of 1..=N (20 and 6 by default)
+    #[arg(long, num_args = 0..=2, value_names = ["N", "K"], group = "mode")]
+    selftest: Option<Vec<String>>,
+    /// Test the random number generator for uniformity instead, drawing GAMES games (100000 by
+    /// default) at the SIGNIFICANCE level (0.01 by default)
+    #[arg(long, num_args = 0..=2, value_names = ["GAMES", "SIGNIFICANCE"], group = "mode")]
+    analyze_rng: Option<Vec<String>>,
+    /// Print the report of --analyze-rng as JSON
+    #[arg(long, requires = "analyze_rng")]
+    json: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check a CSV file of games (one game per row, like the output file) against the
+    /// constraints, reporting invalid rows and rows sharing a triplet
+    Validate {
+        /// The CSV file to check
+        file: String,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Tell why a run would turn a game down after the games of a file: every constraint it
+    /// breaks, the game it repeats and the games holding its triplets
+    Explain {
+        /// The game, e.g. 1,2,3,4,5,6
+        #[arg(long, value_name = "GAME")]
+        game: String,
+        /// The games accepted before it (the initial games of the configuration by default)
+        #[arg(long, value_name = "CSV")]
+        against: Option<String>,
+    },
+    /// Clean up a CSV file of games: drop rows repeating an earlier game, move rows that are
+    /// not playable games to a rejects file and sort the numbers of each game
+    Repair {
+        /// The CSV file to repair
+        file: String,
+        /// File the repaired games are written to (FILE with `_repaired` before its extension
+        /// by default)
+        #[arg(long, value_name = "PATH")]
+        output: Option<String>,
+        /// File the rejected rows are written to (FILE with `_rejects` before its extension by
+        /// default)
+        #[arg(long, value_name = "PATH")]
+        rejects: Option<String>,
+    },
+    /// Report the pairs of games, one from each file, sharing SUBSET_SIZE numbers or more, e.g.
+    /// before merging two pools of tickets
+    Diff {
+        /// The first games file
+        a: String,
+        /// The second games file
+        b: String,
+        /// How many numbers two games must share to be reported
+        #[arg(long, value_name = "SUBSET_SIZE", default_value_t = 3)]
+        subset_size: usize,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Analyze how the games of a file spread the numbers
+    Analyze {
+        #[command(subcommand)]
+        analysis: Analysis,
+    },
+    /// Score the tickets of a games file against the results of a contest
+    #[command(group(ArgGroup::new("result").required(true)))]
+    Check {
+        /// The numbers drawn, e.g. 4,18,23,39,51,60, given twice for the two results of the
+        /// Dupla Sena
+        #[arg(long, value_name = "NUMBERS", group = "result")]
+        draw: Vec<String>,
+        /// Download the latest result of the lottery from the Caixa API instead (needs the
+        /// `net` feature)
+        #[arg(long, group = "result")]
+        fetch_latest: bool,
+        /// Download the result of contest N from the Caixa API instead (needs the `net`
+        /// feature)
+        #[arg(long, value_name = "N", group = "result")]
+        contest: Option<u32>,
+        /// The games file (the output file of the configuration by default)
+        #[arg(long, value_name = "CSV")]
+        games: Option<String>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Estimate how the tickets of a games file do over many random draws: the best match of
+    /// each draw, the prize tickets per draw and, with a `[prizes]` table, the winnings
+    Simulate {
+        /// The games file (the output file of the configuration by default)
+        #[arg(long, value_name = "CSV")]
+        games: Option<String>,
+        /// Number of results to draw, from the seed of the configuration
+        #[arg(long, value_name = "M", default_value_t = 100_000)]
+        draws: u64,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Report how often and how recently each number was drawn in a CSV file of past results
+    /// (a date and six numbers per row, like the export of the Caixa results), or how often the
+    /// games of a ticket database play each number
+    Stats {
+        /// The file of past draws, or `sqlite:path` for the games of a ticket database
+        #[arg(long, value_name = "CSV")]
+        history: String,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Report on the runs archived in a directory: the games of the output file of every
+    /// manifest found, the triplets they cover together, the numbers none of them plays and the
+    /// games of different runs sharing triplets
+    Report {
+        /// The directory searched for manifests (files named `*manifest*.json`), its
+        /// subdirectories included
+        #[arg(long, value_name = "DIR")]
+        dir: String,
+        /// File the report is also written to, as JSON
+        #[arg(long, value_name = "PATH")]
+        output: Option<String>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Convert between combinadic ranks and games, printing one result per line
+    #[command(group(ArgGroup::new("direction").required(true)))]
+    Convert {
+        /// Turn comma-separated ranks into games, or the ranks read from stdin (one per line)
+        /// when no value is given
+        #[arg(long, value_name = "RANKS", num_args = 0..=1, group = "direction")]
+        to_game: Option<Option<String>>,
+        /// Turn a game into its rank, or the games read from stdin (one per line) when no
+        /// value is given
+        #[arg(long, value_name = "GAME", num_args = 0..=1, group = "direction")]
+        to_rank: Option<Option<String>>,
+        /// Convert triplets, like those of triplet_set.log, instead of games (same as --k 3)
+        #[arg(long, conflicts_with = "k")]
+        triplet: bool,
+        /// How many numbers the lottery has (max_number - min_number + 1 of the configuration
+        /// by default)
+        #[arg(long, value_name = "N")]
+        n: Option<i64>,
+        /// How many numbers are in a game (6 by default)
+        #[arg(long, value_name = "K")]
+        k: Option<i64>,
+    },
+    /// Write the ranks of a saved set, like games.csv or triplet_set.log, as the sorted games
+    /// or triplets they stand for to a CSV file, reporting the ranks that stand for none
+    ExportSet {
+        /// The file of the set
+        file: String,
+        /// What the ranks stand for: game or triplet (as the header of the file says by
+        /// default, or game for a file saved without one)
+        #[arg(long, value_name = "KIND")]
+        kind: Option<SetKind>,
+        /// File the games or triplets are written to (FILE with `_export.csv` in place of its
+        /// extension by default)
+        #[arg(long, value_name = "PATH")]
+        output: Option<String>,
+    },
+    /// Check that the output file of a run matches its manifest, generating the games again
+    /// from the recorded settings when the run can be reproduced
+    VerifyManifest {
+        /// The manifest written by the run
+        #[arg(default_value = manifest::MANIFEST_FILE)]
+        manifest: String,
+    },
+    /// Print the completion script of SHELL, e.g. for bash:
+    /// `rk_lottery completions bash > /etc/bash_completion.d/rk_lottery`
+    Completions {
+        /// The shell to complete in
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page of the program, or write the pages of the program and of each
+    /// subcommand to a directory
+    Man {
+        /// Directory the pages are written to, one `.1` file per page
+        #[arg(long, value_name = "DIR")]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Analysis {
+    /// Count how many games contain each pair of numbers, as a CSV matrix with a header row
+    /// and 0 on the diagonal
+    Pairs {
+        /// The CSV file of games
+        file: String,
+        /// File the matrix is written to (stdout by default, unless --top is given)
+        #[arg(long, value_name = "PATH")]
+        output: Option<String>,
+        /// Print the N pairs in the most games instead (10 by default)
+        #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "10")]
+        top: Option<usize>,
+    },
+}
+
+impl Cli {
+    /// The settings given on the command line, to merge into the configuration file.
+    fn overrides(&self) -> Overrides {
+        Overrides {
+            no_of_games: self.games,
+            seed: self.seed,
+            max_number: self.max_number,
+            min_desired_number: self.min_desired,
+            output: self.output.clone(),
+            resume: self.resume,
+            append: self.append,
+            output_format: self.format,
+            batches: self.batches,
+            strategy: self.strategy,
+            output_order: if self.shuffle_output {
+                Some(OutputOrder::Shuffle)
+            } else {
+                self.sort_output
+            },
+        }
+    }
+}
+
+/// Logs to stderr as `RUST_LOG` says or, without it, only the warnings of the library, printed
+/// like those of the program.
+fn init_logging() {
+    if std::env::var_os("RUST_LOG").is_some() {
+        env_logger::init();
+        return;
+    }
+    env_logger::Builder::new()
+        .filter_level(log::LevelFilter::Warn)
+        .format(|buf, record| match record.level() {
+            log::Level::Error => writeln!(buf, "Error: {}", record.args()),
+            _ => writeln!(buf, "Warning: {}", record.args()),
+        })
+        .init();
+}
+
+fn main() -> ExitCode {
+    init_logging();
+    let cli = Cli::parse();
+    let target = cli.exit_summary.clone();
+    let mut summary = ExitSummary::default();
+    let result = run(cli, &mut summary);
+    report_exit(result, summary, target)
+}
+
+/// Reports how the run ended, the one place the error ending a run is printed: the error to
+/// stderr, but for the changes of a tool, told on stdout already, then, with `--exit-summary`,
+/// the summary of the run as JSON to stderr or to the file given.
+///
+/// # Returns
+/// * The exit code of the error, 0 on success, or 5 if the summary of a successful run
+///   cannot be written.
+fn report_exit(
+    result: Result<(), Error>,
+    mut summary: ExitSummary,
+    target: Option<Option<String>>,
+) -> ExitCode {
+    if let Err(error) = &result {
+        if !matches!(error, Error::Changed(_)) {
+            eprintln!("Error: {}", error);
+        }
+    }
+    summary.finish(&result);
+    let Some(target) = target else {
+        return ExitCode::from(summary.exit_code);
+    };
+    let json = serde_json::to_string(&summary).expect("the summary serializes to JSON");
+    match target {
+        None => eprintln!("{}", json),
+        Some(path) => {
+            if let Err(error) =
+                persistence::write_atomically(&path, format!("{}\n", json).as_bytes())
+            {
+                let error = Error::io(&path, error);
+                eprintln!("Error: {}", error);
+                if result.is_ok() {
+                    return ExitCode::from(error.exit_code());
+                }
+            }
+        }
+    }
+    ExitCode::from(summary.exit_code)
+}
+
+/// Prints the man page of the program, or writes it and those of the subcommands to `output`.
+fn write_man_pages(output: Option<&str>) -> Result<(), Error> {
+    let command = Cli::command();
+    match output {
+        Some(output) => {
+            std::fs::create_dir_all(output).map_err(|error| Error::io(output, error))?;
+            clap_mangen::generate_to(command, output).map_err(|error| Error::io(output, error))
+        }
+        None => clap_mangen::Man::new(command)
+            .render(&mut std::io::stdout())
+            .map_err(|error| Error::io("stdout", error)),
+    }
+}
+
+/// Runs the mode selected by the command line arguments.
+fn run(cli: Cli, summary: &mut ExitSummary) -> Result<(), Error> {
+    // The manifest holds its own settings, the configuration file is not needed
+    match &cli.command {
+        Some(Command::VerifyManifest { manifest }) => return run_verify_manifest(manifest),
+        // Generated from the definition of the command line alone
+        Some(Command::Completions { shell }) => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(*shell, &mut command, name, &mut std::io::stdout());
+            return Ok(());
+        }
+        Some(Command::Man { output }) => return write_man_pages(output.as_deref()),
+        _ => {}
+    }
+    // A checkpoint holds the settings of its run, the configuration file is not needed
+    let checkpoint = match &cli.from_checkpoint {
+        Some(path) => Some(load_checkpoint(path)?),
+        None => None,
+    };
+    let config = match &checkpoint {
+        Some(checkpoint) => checkpoint.config.clone(),
+        None => Config::from_file(&cli.config)
+            .and_then(|config| config.merge(&cli.overrides()).map_err(Into::into))
+            .map_err(|error| Error::Config(format!("{}: {}", cli.config, error)))?,
+    };
+    summary.seed = config.effective_seed();
+
+    match &cli.command {
+        Some(Command::Validate { file, json }) => {
+            let config = load_blacklist(load_historical_draws(config.clone())?)?;
+            return run_validate(file, *json, &config);
+        }
+        Some(Command::Explain { game, against }) => {
+            let config = load_blacklist(load_historical_draws(config)?)?;
+            return run_explain(game, against.as_deref(), &config);
+        }
+        Some(Command::Diff {
+            a,
+            b,
+            subset_size,
+            json,
+        }) => return run_diff(a, b, *subset_size, *json, &config),
+        Some(Command::Repair {
+            file,
+            output,
+            rejects,
+        }) => {
+            let output = output
+                .clone()
+                .unwrap_or_else(|| config::suffixed_path(file, "repaired"));
+            let rejects = rejects
+                .clone()
+                .unwrap_or_else(|| config::suffixed_path(file, "rejects"));
+            return run_repair(file, &output, &rejects, &config);
+        }
+        Some(Command::Analyze {
+            analysis: Analysis::Pairs { file, output, top },
+        }) => return run_analyze_pairs(file, output.as_deref(), *top, &config),
+        Some(Command::Check {
+            draw,
+            fetch_latest: _,
+            contest,
+            games,
+            json,
+        }) => {
+            // Without --draw, the results are fetched: the latest ones unless --contest is given
+            let draws = if draw.is_empty() {
+                let fetched = fetch_results(config.lottery, *contest)?;
+                eprintln!(
+                    "Fetched contest {} of {}",
+                    fetched[0].contest.expect("results have a contest"),
+                    fetched[0].date
+                );
+                fetched
+                    .into_iter()
+                    .map(|draw| game::Game::new(draw.numbers).to_string())
+                    .collect()
+            } else {
+                draw.clone()
+            };
+            return run_check_draw(
+                &draws,
+                games.as_deref().unwrap_or(&config.output),
+                *json,
+                &config,
+            );
+        }
+        Some(Command::Simulate { games, draws, json }) => {
+            return run_simulate(
+                games.as_deref().unwrap_or(&config.output),
+                *draws,
+                *json,
+                &config,
+            )
+        }
+        Some(Command::Stats { history, json }) => return run_stats(history, *json, &config),
+        Some(Command::Report { dir, output, json }) => {
+            return run_report(dir, output.as_deref(), *json, &config)
+        }
+        Some(Command::Convert {
+            to_game,
+            to_rank,
+            triplet,
+            n,
+            k,
+        }) => {
+            let n = n.unwrap_or(config.max_number - config.min_number + 1);
+            let k = if *triplet { 3 } else { k.unwrap_or(6) };
+            return match (to_game, to_rank) {
+                (Some(ranks), _) => run_convert(ranks.as_deref(), true, n, k, &config),
+                (None, Some(games)) => run_convert(games.as_deref(), false, n, k, &config),
+                (None, None) => unreachable!("clap requires a direction"),
+            };
+        }
+        Some(Command::ExportSet { file, kind, output }) => {
+            let output = output.clone().unwrap_or_else(|| {
+                let path = std::path::Path::new(file);
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                path.with_file_name(format!("{}_export.csv", stem))
+                    .to_string_lossy()
+                    .into_owned()
+            });
+            return run_export_set(file, *kind, &output, &config);
+        }
+        Some(Command::VerifyManifest { .. })
+        | Some(Command::Completions { .. })
+        | Some(Command::Man { .. })
+        | None => {}
+    }
+    if let Some(game) = &cli.check {
+        return check_game(
+            game,
+            &load_blacklist(load_historical_draws(config.clone())?)?,
+        );
+    }
+    if let Some(path) = &cli.print {
+        let path = path.as_deref().unwrap_or(&config.output);
+        let games = read_games(path, &config)?;
+        print!(
+            "{}",
+            ticket_table::render_table(
+                &games,
+                config.max_number,
+                config.tickets_per_block,
+                config.stats_range(),
+            )
+        );
+        return Ok(());
+    }
+    if let Some(path) = &cli.optimize {
+        return run_optimize(path, &config);
+    }
+    if let Some(values) = &cli.selftest {
+        let parse = |value: &String| {
+            value
+                .parse()
+                .map_err(|_| Error::Usage(format!("'{}' is not a valid selftest size", value)))
+        };
+        return match values.as_slice() {
+            [] => run_selftest(20, 6),
+            [n, k] => run_selftest(parse(n)?, parse(k)?),
+            _ => Err(Error::Usage(
+                "Usage: rk_lottery --selftest [<N> <K>]".to_string(),
+            )),
+        };
+    }
+    if let Some(values) = &cli.analyze_rng {
+        return run_analyze_rng(values, cli.json, &config);
+    }
+
+    if cli.dry_run {
+        return run_dry_run(&load_blacklist(load_historical_draws(
+            load_history_weights(config)?,
+        )?)?);
+    }
+    if let Some(wheel_config) = &config.wheel {
+        if cli.interactive {
+            return Err(Error::Usage(
+                "--interactive cannot review the games of a wheel, which are picked together"
+                    .to_string(),
+            ));
+        }
+        return run_wheel(wheel_config, &config);
+    }
+
+    let config = load_blacklist(load_historical_draws(load_history_weights(config)?)?)?;
+    let decide = match cli.interactive {
+        true => Some(start_interactive(&config)?),
+        false => None,
+    };
+    let mut session = Vec::new();
+    let stop = interrupt_on_ctrl_c();
+    if config.batches > 1 {
+        return run_batches(&config, &cli, &stop, summary);
+    }
+    // A run that died before saving its games left them in its journal
+    let replay = if config.resume && std::path::Path::new(journal::JOURNAL_FILE).exists() {
+        Some(replay_journal(&config)?)
+    } else {
+        if config.journal && std::path::Path::new(journal::JOURNAL_FILE).exists() {
+            eprintln!(
+                "Warning: {} holds the games of a run that did not finish, replaced by this run (pass --resume to continue that run instead)",
+                journal::JOURNAL_FILE
+            );
+        }
+        None
+    };
+    let journal = if config.journal {
+        let created = journal::Journal::create(journal::JOURNAL_FILE, journal::SYNC_INTERVAL)
+            .map_err(|error| Error::io(journal::JOURNAL_FILE, error))?;
+        Some(Rc::new(RefCell::new(Some(created))))
+    } else {
+        None
+    };
+    let bar = progress_bar(cli.quiet || cli.interactive, config.no_of_games);
+    let mut stats = progress_stats(&bar, String::new(), &stop);
+    if let Some(journal) = &journal {
+        stats = stats.with_game_hook(journal_hook(Rc::clone(journal), config.min_number));
+    }
+    #[cfg(feature = "sqlite")]
+    let storage = match config.sqlite_path() {
+        Some(path) => Some(RunStorage::open(path, &config)?),
+        None => None,
+    };
+    #[cfg(feature = "sqlite")]
+    if let Some(storage) = &storage {
+        stats = stats.with_game_hook(storage.hook(config.min_number, Arc::clone(&stop)));
+    }
+
+    // When resuming, start from the games and sets saved by the previous run
+    // and keep the layout of the output file
+    let mut layout = Layout::configured(&config);
+    // Whether the output file misses games the run starts from, and is written again
+    let mut rewrite = false;
+    // The games of the checkpoint carried on from, written again with the others
+    let restarted = checkpoint.as_ref().map(|checkpoint| checkpoint.games.len());
+    let (generated, previous) = if let Some(checkpoint) = checkpoint {
+        (
+            generation::resume_from_checkpoint(&config, checkpoint, stats),
+            0,
+        )
+    } else if let Some((games, game_set, triplet_set)) = replay {
+        rewrite = true;
+        let previous = games.len();
+        let generated = generation::resume(&config, games, game_set, triplet_set, stats);
+        (generated, previous)
+    } else if config.resume {
+        let (file_layout, games) = read_games_file(&config.output, &config)?;
+        layout = file_layout.unwrap_or(layout);
+        // The sets may have been saved in any format; the game set only speeds up the run
+        // by remembering the rejected candidates, so it can be rebuilt from the games
+        let n = config.max_number - config.min_number + 1;
+        let mut game_set = load_saved_set("games.csv", SetHeader::games(n), true)?;
+        if game_set.is_empty() && !games.is_empty() {
+            eprintln!(
+                "Warning: games.csv not found, rebuilding it from the games of {}",
+                config.output
+            );
+            game_set = games
+                .iter()
+                .map(|game| custom_utils::game2enum_from(&game.clone().sorted(), config.min_number))
+                .collect();
+        }
+        let triplet_set = load_saved_set("triplet_set.log", SetHeader::triplets(n), false)?;
+        stats += &resumed_stats(&config);
+        let previous = games.len();
+        let generated = generation::resume(&config, games, game_set, triplet_set, stats);
+        (generated, previous)
+    } else if config.append {
+        // A missing output file is a run with nothing to append to
+        let (file_layout, games) = match std::fs::metadata(&config.output) {
+            Ok(_) => read_games_file(&config.output, &config)?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (None, Vec::new()),
+            Err(error) => return Err(Error::io(&config.output, error)),
+        };
+        layout = file_layout.unwrap_or(layout);
+        let previous = games.len();
+        bar.set_length((previous + config.no_of_games) as u64);
+        (generation::append(&config, games, stats), previous)
+    } else if let Some(decide) = decide {
+        (interactive::review(&config, stats, &mut session, decide), 0)
+    } else {
+        let avoided = load_avoided_triplets(&config)?;
+        #[cfg(feature = "sqlite")]
+        let avoided = match &storage {
+            Some(storage) => {
+                let mut avoided = avoided.unwrap_or_default();
+                avoided.merge_from(&storage.avoided);
+                Some(avoided)
+            }
+            None => avoided,
+        };
+        let generated = match &avoided {
+            Some(avoided) => generation::generate_avoiding(&config, avoided, stats),
+            None => generation::generate(&config, stats),
+        };
+        (generated, 0)
+    };
+    bar.finish_and_clear();
+    // The decisions are kept even when the session ends in an error
+    if cli.interactive {
+        persistence::write_atomically(
+            interactive::SESSION_LOG_FILE,
+            &interactive::session_log_csv(&session),
+        )
+        .map_err(|error| Error::io(interactive::SESSION_LOG_FILE, error))?;
+        log::info!(
+            "Wrote the {} decisions of the session to {}",
+            session.len(),
+            interactive::SESSION_LOG_FILE
+        );
+    }
+    let generated = match generated {
+        Ok(generated) => generated,
+        Err(error) => {
+            // No game is saved, so there is nothing to resume either
+            if journal.is_some() {
+                let _ = std::fs::remove_file(journal::JOURNAL_FILE);
+            }
+            return Err(error.into());
+        }
+    };
+    #[cfg(feature = "sqlite")]
+    if let Some(failure) = storage.and_then(|storage| storage.failure.take()) {
+        return Err(failure);
+    }
+
+    if generated.interrupted {
+        eprintln!(
+            "Interrupted, saving the {} games found so far",
+            generated.games.len()
+        );
+    }
+    // A JSON array cannot be extended in place, so it is written again
+    if previous > 0 && !rewrite && config.output_format != OutputFormat::Json {
+        if config.output_format == OutputFormat::Csv
+            && config.labeled()
+            && layout != Layout::Labeled
+        {
+            eprintln!(
+                "Warning: {} has no label column, the labels of the new games are left out",
+                config.output
+            );
+        }
+        append_games(
+            &config.output,
+            &generated.games[previous..],
+            &config,
+            layout,
+            previous,
+        )?;
+    } else {
+        write_games(
+            &config.output,
+            &generated.games,
+            Some(&generated.stats),
+            &config,
+            layout,
+        )?;
+    }
+    match journal.and_then(|journal| journal.borrow_mut().take()) {
+        // The sets replace the journal once saved
+        Some(journal) => {
+            journal
+                .compact(
+                    &generated.game_set,
+                    &generated.triplet_set,
+                    config.max_number - config.min_number + 1,
+                    "games.csv",
+                    "triplet_set.log",
+                    config.set_format,
+                )
+                .map_err(|error| Error::io(journal::JOURNAL_FILE, error))?;
+            log::info!(
+                "Saved the sets of games and triplets to games.csv and triplet_set.log, removed {}",
+                journal::JOURNAL_FILE
+            );
+        }
+        None => save_sets(&generated, "games.csv", "triplet_set.log", &config)?,
+    }
+
+    // Record how the output file was made, to tell it apart and to make it again
+    let output =
+        persistence::read_file(&config.output).map_err(|error| Error::io(&config.output, error))?;
+    let mut manifest = RunManifest::new(&config, &generated, &output);
+    manifest.interactive = cli.interactive;
+    manifest
+        .save_to_file(manifest::MANIFEST_FILE)
+        .map_err(|error| Error::io(manifest::MANIFEST_FILE, error))?;
+    log::info!(
+        "Wrote the manifest of the run to {}",
+        manifest::MANIFEST_FILE
+    );
+    let frequencies = if cli.histogram {
+        Some(write_histogram(
+            &generated.games,
+            analysis::FREQUENCIES_FILE,
+            &config,
+        )?)
+    } else {
+        None
+    };
+    let mut outputs = vec![
+        config.output.clone(),
+        "games.csv".to_string(),
+        "triplet_set.log".to_string(),
+        manifest::MANIFEST_FILE.to_string(),
+    ];
+    if cli.histogram {
+        outputs.push(analysis::FREQUENCIES_FILE.to_string());
+    }
+    if cli.interactive {
+        outputs.push(interactive::SESSION_LOG_FILE.to_string());
+    }
+    summary.add_run(&generated, outputs);
+
+    // Printed once every file is written, for none to be lost when stdout is closed early
+    to_stdout(|out| {
+        if cli.json_summary {
+            print_json_summary(out, &generated, &config)?;
+        } else {
+            if let Some(restarted) = restarted {
+                writeln!(
+                    out,
+                    "Carried on from a checkpoint of {} games, generated {} more",
+                    restarted,
+                    generated.games.len() - restarted
+                )?;
+            } else if config.resume {
+                writeln!(
+                    out,
+                    "Resumed from {} games, generated {} more",
+                    previous,
+                    generated.games.len() - previous
+                )?;
+            } else if config.append {
+                writeln!(
+                    out,
+                    "Appended {} games to the {} of {}",
+                    generated.games.len() - previous,
+                    previous,
+                    config.output
+                )?;
+            }
+            print_summary(out, &generated, &config)?;
+        }
+        if let Some(table) = &frequencies {
+            print_histogram(out, table, analysis::FREQUENCIES_FILE, &config)?;
+        }
+        Ok(())
+    })?;
+
+    if generated.interrupted {
+        return Err(Error::Interrupted {
+            games: generated.games.len(),
+            requested: generated.stats.requested,
+            batch: None,
+        });
+    }
+    Ok(())
+}
+
+/// Generates the batches of a run of several, writing each to its own output file along with
+/// its sets and its manifest, all named by `config::batch_path`.
+fn run_batches(
+    config: &Config,
+    cli: &Cli,
+    stop: &Arc<AtomicBool>,
+    summary: &mut ExitSummary,
+) -> Result<(), Error> {
+    let bar = progress_bar(cli.quiet, config.no_of_games);
+    let batches = generation::generate_batches(config, |batch| {
+        bar.set_position(0);
+        progress_stats(
+            &bar,
+            format!("batch {} of {}, ", batch, config.batches),
+            stop,
+        )
+    });
+    bar.finish_and_clear();
+    let batches = batches?;
+
+    let mut frequencies = Vec::new();
+    for (index, generated) in batches.iter().enumerate() {
+        let batch = index + 1;
+        let batch_config = config.batch(batch);
+        write_games(
+            &batch_config.output,
+            &generated.games,
+            Some(&generated.stats),
+            &batch_config,
+            Layout::configured(&batch_config),
+        )?;
+        save_sets(
+            generated,
+            &config::batch_path("games.csv", batch),
+            &config::batch_path("triplet_set.log", batch),
+            config,
+        )?;
+        let output = persistence::read_file(&batch_config.output)
+            .map_err(|error| Error::io(&batch_config.output, error))?;
+        let mut manifest = RunManifest::new(&batch_config, generated, &output);
+        manifest.batch = Some(batch);
+        let path = config::batch_path(manifest::MANIFEST_FILE, batch);
+        manifest
+            .save_to_file(&path)
+            .map_err(|error| Error::io(&path, error))?;
+        let mut outputs = vec![
+            batch_config.output.clone(),
+            config::batch_path("games.csv", batch),
+            config::batch_path("triplet_set.log", batch),
+            path,
+        ];
+        if cli.histogram {
+            let path = config::batch_path(analysis::FREQUENCIES_FILE, batch);
+            frequencies.push(write_histogram(&generated.games, &path, &batch_config)?);
+            outputs.push(path);
+        }
+        summary.add_run(generated, outputs);
+    }
+
+    // Printed once every file is written, for none to be lost when stdout is closed early
+    to_stdout(|out| {
+        let mut summaries = Vec::new();
+        for (index, generated) in batches.iter().enumerate() {
+            let batch = index + 1;
+            let batch_config = config.batch(batch);
+            if cli.json_summary {
+                let mut summary = json_summary(generated, &batch_config);
+                summary["batch"] = batch.into();
+                summary["seed"] = batch_config.effective_seed().into();
+                summary["output"] = batch_config.output.clone().into();
+                summaries.push(summary);
+            } else {
+                let seed = match batch_config.effective_seed() {
+                    Some(seed) => format!("seed {}", seed),
+                    None => "OS entropy".to_string(),
+                };
+                writeln!(
+                    out,
+                    "Batch {} of {} ({}), written to {}",
+                    batch, config.batches, seed, batch_config.output
+                )?;
+                print_summary(out, generated, &batch_config)?;
+            }
+            if let Some(table) = frequencies.get(index) {
+                let path = config::batch_path(analysis::FREQUENCIES_FILE, batch);
+                print_histogram(out, table, &path, &batch_config)?;
+            }
+        }
+        if cli.json_summary {
+            serde_json::to_writer_pretty(&mut *out, &summaries)?;
+            writeln!(out)?;
+        } else if batches.len() > 1 {
+            let mut total = GenerationStats::default();
+            for generated in &batches {
+                total += &generated.stats;
+            }
+            writeln!(out, "All {} batches: {}", batches.len(), total)?;
+        }
+        Ok(())
+    })?;
+
+    match batches.last() {
+        Some(last) if last.interrupted => Err(Error::Interrupted {
+            games: last.games.len(),
+            requested: last.stats.requested,
+            batch: Some(batches.len()),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Writes to `path` the counts of the games playing each number of the lottery.
+fn write_histogram(
+    games: &[game::Game],
+    path: &str,
+    config: &Config,
+) -> Result<analysis::FrequencyTable, Error> {
+    let table = analysis::frequency_table(games, config.min_number, config.max_number);
+    persistence::write_atomically(path, &table.to_csv()).map_err(|error| Error::io(path, error))?;
+    Ok(table)
+}
+
+/// Prints the histogram written to `path` by `write_histogram`, then the numbers no game plays.
+fn print_histogram(
+    out: &mut impl Write,
+    table: &analysis::FrequencyTable,
+    path: &str,
+    config: &Config,
+) -> io::Result<()> {
+    write!(out, "{}", table)?;
+    let never_played = table.never_played();
+    if !never_played.is_empty() {
+        let width = config.max_number.to_string().len();
+        let numbers: Vec<String> = never_played
             .iter()
-            .map(|triplet| custom_utils::triplet2enum(triplet.clone()))
-            .collect::<Vec<_>>();
-        // Try to insert triplets into triplet_set:
-        if !triplet_set.check_and_insert_all(triplet_nos) {
-            // If insertion fails, then a repeated triplet was found, should not happen here! Inform the triplets: and game:
-            panic!(
-                "Repeated triplet found! This should not happen! Game: {:?}, Triplets: {:?}",
-                game, triplets
+            .map(|number| format!("{:0width$}", number, width = width))
+            .collect();
+        writeln!(
+            out,
+            "{} number(s) in no game: {}",
+            never_played.len(),
+            numbers.join(", ")
+        )?;
+    }
+    writeln!(out, "Wrote the frequencies of the numbers to {}", path)
+}
+
+/// Prints what `print` writes to stdout. Stdout closed early, as by `| head`, ends the printing
+/// without an error.
+fn to_stdout(print: impl FnOnce(&mut io::StdoutLock) -> io::Result<()>) -> Result<(), Error> {
+    match print(&mut io::stdout().lock()) {
+        Err(error) if error.kind() != io::ErrorKind::BrokenPipe => Err(Error::io("stdout", error)),
+        _ => Ok(()),
+    }
+}
+
+/// A progress bar of `len` games, hidden when `quiet`.
+fn progress_bar(quiet: bool, len: usize) -> ProgressBar {
+    let bar = if quiet {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(len as u64)
+    };
+    bar.set_style(
+        ProgressStyle::with_template("{bar:40} {pos}/{len} games, {msg}")
+            .expect("the progress bar template is valid"),
+    );
+    bar
+}
+
+/// Counters showing the games found and the candidates tried on `bar`, after `label`, and
+/// stopping the run once `stop` is set.
+fn progress_stats(bar: &ProgressBar, label: String, stop: &Arc<AtomicBool>) -> GenerationStats {
+    let bar = bar.clone();
+    GenerationStats::with_observer(move |stats: &GenerationStats| {
+        bar.set_position(stats.games as u64);
+        bar.set_message(format!(
+            "{}{} candidates, rejected: {} by constraints, {} duplicate games, {} sharing a triplet",
+            label, stats.attempts, stats.rejected, stats.duplicate_games, stats.shared_triplets
+        ));
+    })
+    .with_stop_flag(Arc::clone(stop))
+}
+
+/// The counters of the run that wrote the output file, saved in its manifest, for the run
+/// resuming it to carry them on. Without a manifest, or with one describing another file, the
+/// counters start from zero, a warning being printed in the latter case.
+fn resumed_stats(config: &Config) -> GenerationStats {
+    let path = manifest::MANIFEST_FILE;
+    let manifest = match RunManifest::load_from_file(path) {
+        Ok(manifest) => manifest,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return GenerationStats::default()
+        }
+        Err(error) => {
+            eprintln!(
+                "Warning: {}: {}, counting the candidates of this run only",
+                path, error
             );
+            return GenerationStats::default();
         }
-        // Add game to game_set:
-        game_set.add_number(game_no);
+    };
+    let digest = persistence::read_file(&config.output).map(|output| manifest::sha256_hex(&output));
+    if manifest.config.output != config.output || digest.ok() != Some(manifest.sha256) {
+        eprintln!(
+            "Warning: {} was not written along with {}, counting the candidates of this run only",
+            path, config.output
+        );
+        return GenerationStats::default();
     }
+    manifest.stats
+}
 
-    // Create random number generator for combinadics
-    let mut rng =
-        custom_utils::create_combinadic_rng(config.seed.unwrap_or(12345), config.max_number, 6);
+/// Loads the checkpoint at `path`, a file or a directory of them, checking the settings of its
+/// run.
+fn load_checkpoint(path: &str) -> Result<checkpoint::Checkpoint, Error> {
+    let (checkpoint, file) =
+        checkpoint::Checkpoint::load(path).map_err(|error| Error::io(path, error))?;
+    let file = file.to_string_lossy();
+    checkpoint
+        .config
+        .validate()
+        .map_err(|error| Error::Config(format!("{}: {}", file, error)))?;
+    if checkpoint.version != env!("CARGO_PKG_VERSION") {
+        eprintln!(
+            "Warning: {} was written by version {}, the games may differ from those of an uninterrupted run",
+            file, checkpoint.version
+        );
+    }
+    log::info!(
+        "Carrying on from the checkpoint of {} games of {}",
+        checkpoint.games.len(),
+        file
+    );
+    Ok(checkpoint)
+}
 
-    while games.len() < config.no_of_games {
-        let game_no = rng();
-        let game = custom_utils::enum2game(game_no);
+/// Reads the games and sets of a run that did not finish from its journal, warning about the
+/// bytes of a record cut short.
+fn replay_journal(config: &Config) -> Result<(Vec<game::Game>, NumberSet, TripletSet), Error> {
+    let path = journal::JOURNAL_FILE;
+    let replay = journal::Replay::from_file(path).map_err(|error| Error::io(path, error))?;
+    if replay.dropped_bytes > 0 {
+        eprintln!(
+            "Warning: {}: dropped the last {} bytes, a record cut short",
+            path, replay.dropped_bytes
+        );
+    }
+    let games = replay
+        .games(config.min_number, config.max_number)
+        .map_err(|error| Error::io(path, error))?;
+    eprintln!(
+        "Warning: resuming from the {} games journaled in {} by a run that did not finish",
+        games.len(),
+        path
+    );
+    Ok((games, replay.game_set, replay.triplet_set))
+}
 
-        if !game_set.add_number(game_no) || invalidate_game(&game) {
-            continue;
+/// Appends each game passed to the journal, in ascending order. If the journal cannot be
+/// written, a warning is printed and the games are no longer journaled.
+fn journal_hook(
+    journal: Rc<RefCell<Option<journal::Journal>>>,
+    min_number: i64,
+) -> impl FnMut(&[i64]) {
+    move |game| {
+        let mut journal = journal.borrow_mut();
+        let mut numbers = game.to_vec();
+        numbers.sort_unstable();
+        let appended = match journal.as_mut() {
+            Some(writer) => writer.append_game(&numbers, min_number),
+            None => return,
+        };
+        if let Err(error) = appended {
+            eprintln!(
+                "Warning: {}: {}, no longer journaling the games",
+                journal::JOURNAL_FILE,
+                error
+            );
+            *journal = None;
         }
+    }
+}
+
+/// The ticket database of `storage` during a run: the games of the run are stored as they are
+/// accepted, none sharing a triplet with the games of the runs stored before.
+#[cfg(feature = "sqlite")]
+struct RunStorage {
+    path: String,
+    storage: RefCell<Storage>,
+    run_id: i64,
+    /// The triplets of the games of the runs stored before.
+    avoided: TripletSet,
+    /// Why the games stopped being stored, if they did.
+    failure: RefCell<Option<Error>>,
+}
+
+#[cfg(feature = "sqlite")]
+impl RunStorage {
+    /// Opens the database at `path` and records the run in it.
+    fn open(path: &str, config: &Config) -> Result<Rc<Self>, Error> {
+        let mut storage = Storage::open(path).map_err(|error| storage_error(path, error))?;
+        let avoided = storage
+            .triplets()
+            .map_err(|error| storage_error(path, error))?;
+        let run_id = storage
+            .begin_run(
+                config.effective_seed(),
+                config.min_number,
+                config.max_number,
+            )
+            .map_err(|error| storage_error(path, error))?;
+        log::info!(
+            "Storing the games as run {} of {}, sharing no triplet with the {} stored",
+            run_id,
+            path,
+            avoided.len()
+        );
+        Ok(Rc::new(Self {
+            path: path.to_string(),
+            storage: RefCell::new(storage),
+            run_id,
+            avoided,
+            failure: RefCell::new(None),
+        }))
+    }
 
-        let triplets = custom_utils::game2triplets(game.clone());
-        let triplet_nos = triplets
+    /// Stores each game passed, in order. Once one cannot be stored, e.g. as another run
+    /// stored a game sharing one of its triplets meanwhile, the failure is kept and `stop` is
+    /// set to end the run.
+    fn hook(self: &Rc<Self>, min_number: i64, stop: Arc<AtomicBool>) -> impl FnMut(&[i64]) {
+        let this = Rc::clone(self);
+        let mut index = 0;
+        move |game| {
+            if this.failure.borrow().is_some() {
+                return;
+            }
+            index += 1;
+            let stored =
+                this.storage
+                    .borrow_mut()
+                    .insert_game(this.run_id, index, game, min_number);
+            let failure = match stored {
+                Ok(true) => return,
+                Ok(false) => Error::Validation(format!(
+                    "{}: game #{} ({}) shares a triplet with a game stored meanwhile, the {} games before it are stored as run {}",
+                    this.path,
+                    index,
+                    game::Game::new(game.to_vec()),
+                    index - 1,
+                    this.run_id
+                )),
+                Err(error) => storage_error(&this.path, error),
+            };
+            *this.failure.borrow_mut() = Some(failure);
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Wraps an error met while reading or writing the ticket database at `path`.
+#[cfg(feature = "sqlite")]
+fn storage_error(path: &str, error: StorageError) -> Error {
+    match error {
+        StorageError::Sqlite(error) => Error::io(path, std::io::Error::other(error)),
+        StorageError::OtherLottery { .. } => Error::Config(format!("{}: {}", path, error)),
+        StorageError::WrongSize { .. } => Error::Validation(format!("{}: {}", path, error)),
+    }
+}
+
+/// Reads the games of every run stored in the ticket database at `path`.
+#[cfg(feature = "sqlite")]
+fn read_stored_games(path: &str) -> Result<Vec<game::Game>, Error> {
+    Storage::open(path)
+        .and_then(|storage| storage.games())
+        .map_err(|error| storage_error(path, error))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn read_stored_games(path: &str) -> Result<Vec<game::Game>, Error> {
+    Err(sqlite_unsupported(path))
+}
+
+/// The error of a binary built without the `sqlite` feature, given the ticket database at
+/// `path`.
+#[cfg(not(feature = "sqlite"))]
+fn sqlite_unsupported(path: &str) -> Error {
+    Error::Config(format!(
+        "{}{}: reading a ticket database is not supported by this binary, rebuild it with `cargo build --features sqlite`",
+        config::SQLITE_PREFIX,
+        path
+    ))
+}
+
+/// Loads a set saved by a previous run to resume it, refusing one holding other ranks than
+/// `expected` and warning about one saved without a header by an older version.
+///
+/// # Arguments
+/// * `path` - The file of the set.
+/// * `expected` - What the ranks of the set should stand for.
+/// * `missing_ok` - Whether a missing file gives an empty set rather than an error.
+fn load_saved_set<T: Rank>(
+    path: &str,
+    expected: SetHeader,
+    missing_ok: bool,
+) -> Result<NumberSet<T>, Error> {
+    match NumberSet::load_checked(path, &expected) {
+        Ok((set, legacy)) => {
+            if legacy {
+                eprintln!(
+                    "Warning: {} was saved without a header (version 1), assuming it holds {} ranks of {} numbers out of {}",
+                    path, expected.kind, expected.k, expected.n
+                );
+            }
+            Ok(set)
+        }
+        Err(SetFileError::NotFound) if missing_ok => Ok(NumberSet::default()),
+        Err(
+            error @ (SetFileError::KindMismatch { .. } | SetFileError::ParameterMismatch { .. }),
+        ) => Err(Error::Validation(format!(
+            "{}: {}, cannot resume from it",
+            path, error
+        ))),
+        Err(error) => Err(Error::io(path, error)),
+    }
+}
+
+/// Saves the sets of game and triplet numbers of a run.
+fn save_sets(
+    generated: &generation::Generated,
+    games_path: &str,
+    triplets_path: &str,
+    config: &Config,
+) -> Result<(), Error> {
+    let n = config.max_number - config.min_number + 1;
+    let header = SetHeader {
+        k: config.numbers_per_game() as i64,
+        ..SetHeader::games(n)
+    };
+    generated
+        .game_set
+        .save_with_header(games_path, config.set_format, &header)
+        .map_err(|error| Error::io(games_path, error))?;
+    generated
+        .triplet_set
+        .save_with_header(triplets_path, config.set_format, &SetHeader::triplets(n))
+        .map_err(|error| Error::io(triplets_path, error))?;
+    log::info!(
+        "Saved the sets of games and triplets to {} and {}",
+        games_path,
+        triplets_path
+    );
+    Ok(())
+}
+
+/// Checks a run of `--interactive` starts from its initial games alone and draws its games one
+/// at a time, then makes the decider reading the keys typed.
+///
+/// # Returns
+/// * The decider, for `interactive::review`.
+/// * A usage error if the run carries on from other games or avoids triplets, does not draw
+///   its games one at a time, or if stdin or stdout is not a terminal.
+fn start_interactive(
+    config: &Config,
+) -> Result<impl FnMut(&interactive::CandidateReview) -> interactive::Decision, Error> {
+    if config.resume || config.append || config.journal || config.batches > 1 {
+        return Err(Error::Usage(
+            "--interactive reviews a single batch from its initial games, unset resume, append, journal and batches".to_string(),
+        ));
+    }
+    if !config.avoid_triplets_from.is_empty() || config.storage.is_some() {
+        return Err(Error::Usage(
+            "--interactive cannot avoid the triplets of other games, unset avoid_triplets_from and storage".to_string(),
+        ));
+    }
+    if config.threads.is_some() || config.strategy == Strategy::GreedyCoverage {
+        return Err(Error::Usage(
+            "--interactive draws the games one at a time, unset threads and the greedy-coverage strategy".to_string(),
+        ));
+    }
+    interactive::terminal_decider().map_err(|error| Error::Usage(error.to_string()))
+}
+
+/// Installs a Ctrl-C handler setting the returned flag, which stops the generation so the
+/// games found so far can be saved. A second Ctrl-C quits at once.
+fn interrupt_on_ctrl_c() -> Arc<AtomicBool> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&stop);
+    let installed = ctrlc::set_handler(move || {
+        if flag.swap(true, Ordering::Relaxed) {
+            std::process::exit(i32::from(error::INTERRUPTED_EXIT_CODE));
+        }
+        eprintln!("Stopping, press Ctrl-C again to quit without saving");
+    });
+    if let Err(error) = installed {
+        log::warn!(
+            "Cannot handle Ctrl-C, interrupting will lose the run: {}",
+            error
+        );
+    }
+    stop
+}
+
+/// Replaces `weights_from_history` by the `[weights]` it gives, 1 plus the draws of each number,
+/// so that the manifest records the weights the games were drawn with.
+fn load_history_weights(mut config: Config) -> Result<Config, Error> {
+    let Some(path) = config.weights_from_history.take() else {
+        return Ok(config);
+    };
+    let content = std::fs::read_to_string(&path).map_err(|error| Error::io(&path, error))?;
+    let (draws, warnings) = history::read_draws(&content, config.min_number, config.max_number)
+        .map_err(|error| Error::Config(format!("{}: {}", path, error)))?;
+    for warning in &warnings {
+        eprintln!("Warning: {}: {}", path, warning);
+    }
+    if draws.is_empty() {
+        return Err(Error::Config(format!("{}: no draw could be read", path)));
+    }
+    let weights = history::weights(&draws, config.min_desired_number, config.max_number);
+    config.weights = Some(
+        weights
+            .into_iter()
+            .map(|(number, weight)| (number.to_string(), weight))
+            .collect(),
+    );
+    log::info!("Weighted the numbers by {} draws of {}", draws.len(), path);
+    Ok(config)
+}
+
+/// Reads the draws of `historical_draws_file` into `historical_ranks`, so that no game drawn
+/// in a past contest is generated, warning about the initial games that were.
+fn load_historical_draws(mut config: Config) -> Result<Config, Error> {
+    let Some(path) = &config.historical_draws_file else {
+        return Ok(config);
+    };
+    let content = std::fs::read_to_string(path).map_err(|error| Error::io(path, error))?;
+    let (draws, warnings) = history::read_draws(&content, config.min_number, config.max_number)
+        .map_err(|error| Error::Config(format!("{}: {}", path, error)))?;
+    for warning in &warnings {
+        eprintln!("Warning: {}: {}", path, warning);
+    }
+    for (index, game) in config.initial_games.iter().enumerate() {
+        if let Some(draw) = history::find_draw(&draws, game) {
+            eprintln!(
+                "Warning: initial game #{} ({}) was drawn on {}",
+                index + 1,
+                game,
+                draw.date
+            );
+        }
+    }
+    log::info!("Leaving out the {} games drawn in {}", draws.len(), path);
+    config.historical_ranks = Some(Arc::new(history::ranks(&draws, config.min_number)));
+    Ok(config)
+}
+
+/// Reads the games of the files of `avoid_triplets_from`, whose triplets the games generated
+/// must not hold, warning about the games of these files sharing a triplet with each other.
+///
+/// # Returns
+/// * The combinadic numbers of the triplets of the games of the files, `None` without files.
+/// * An error if a file cannot be read, or holds something else than games of the lottery.
+fn load_avoided_triplets(config: &Config) -> Result<Option<TripletSet>, Error> {
+    if config.avoid_triplets_from.is_empty() {
+        return Ok(None);
+    }
+    let mut avoided = TripletSet::default();
+    // The file and row of the game holding each triplet, to name it in the warnings
+    let mut owners = SubsetIndex::new();
+    let mut holders: Vec<(&str, usize)> = Vec::new();
+    for path in &config.avoid_triplets_from {
+        for (row, game) in read_games(path, config)?.iter().enumerate() {
+            if !config.is_lottery_game(game) {
+                return Err(Error::Validation(format!(
+                    "{}: game #{} ({}) is not a game of {} distinct numbers from {} to {}",
+                    path,
+                    row + 1,
+                    game,
+                    config.numbers_per_game(),
+                    config.min_number,
+                    config.max_number
+                )));
+            }
+            let triplet_nos = custom_utils::game2triplet_enums_from(game, config.min_number);
+            let owner = holders.len();
+            holders.push((path, row));
+            // Each game is warned about once, naming the first triplet it shares
+            let mut shared = None;
+            for (position, &triplet_no) in triplet_nos.iter().enumerate() {
+                avoided.add_number(triplet_no);
+                if let Some(earlier) = owners.insert(triplet_no, owner) {
+                    shared = shared.or(Some((position, earlier)));
+                }
+            }
+            if let Some((position, earlier)) = shared {
+                let (earlier_path, earlier_row) = holders[earlier];
+                eprintln!(
+                    "Warning: {}: game #{} ({}) shares triplet {} with game #{} of {}",
+                    path,
+                    row + 1,
+                    game,
+                    game::Game::new(custom_utils::game2triplets(game)[position].clone()),
+                    earlier_row + 1,
+                    earlier_path
+                );
+            }
+        }
+    }
+    eprintln!(
+        "Avoiding the {} triplets of the {} games of {}",
+        avoided.len(),
+        holders.len(),
+        config.avoid_triplets_from.join(", ")
+    );
+    Ok(Some(avoided))
+}
+
+/// Reads the games of `blacklist_file` into `blacklist_file_games`, so that they are never
+/// generated, like `blacklisted_games`.
+fn load_blacklist(mut config: Config) -> Result<Config, Error> {
+    let Some(path) = &config.blacklist_file else {
+        return Ok(config);
+    };
+    let games = read_games(path, &config)?;
+    log::info!("Leaving out the {} games of {}", games.len(), path);
+    config.blacklist_file_games = Some(games.into_iter().map(game::Game::sorted).collect());
+    config
+        .validate()
+        .map_err(|error| Error::Config(format!("{}: {}", path, error)))?;
+    Ok(config)
+}
+
+/// Checks the output file of a run against the SHA-256 digest of its manifest, then
+/// generates the games again from the settings of the manifest and checks they match too.
+fn run_verify_manifest(path: &str) -> Result<(), Error> {
+    let manifest = RunManifest::load_from_file(path).map_err(|error| Error::io(path, error))?;
+    let config = &manifest.config;
+    config
+        .validate()
+        .map_err(|error| Error::Config(format!("{}: {}", path, error)))?;
+
+    let output =
+        persistence::read_file(&config.output).map_err(|error| Error::io(&config.output, error))?;
+    let digest = manifest::sha256_hex(&output);
+    if digest != manifest.sha256 {
+        return Err(Error::Check(format!(
+            "{} does not match {}: its SHA-256 is {}, the manifest records {}",
+            config.output, path, digest, manifest.sha256
+        )));
+    }
+    println!("{} matches the SHA-256 recorded in {}", config.output, path);
+
+    if let Some(reason) = manifest.irreproducible_reason() {
+        println!("The games cannot be generated again: {}", reason);
+        return Ok(());
+    }
+    let config = &load_blacklist(load_historical_draws(config.clone())?)?;
+    let generated = match load_avoided_triplets(config)? {
+        Some(avoided) => {
+            generation::generate_avoiding(config, &avoided, GenerationStats::default())?
+        }
+        None => generation::generate(config, GenerationStats::default())?,
+    };
+    let digest = manifest::sha256_hex(&format_games(
+        &generated.games,
+        Some(&generated.stats),
+        config,
+        Layout::configured(config),
+    )?);
+    if digest != manifest.sha256 {
+        return Err(Error::Check(format!(
+            "Generating the games again from {} gives a different file: its SHA-256 is {} (manifest written by version {}, this is version {})",
+            path,
+            digest,
+            manifest.version,
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+    println!(
+        "Generating the games again with seed {} gives the same file",
+        manifest.seed.unwrap_or(config::DEFAULT_SEED)
+    );
+    Ok(())
+}
+
+/// Prints the number of games generated, their coverage and the candidates turned down.
+fn print_summary(
+    out: &mut impl Write,
+    generated: &generation::Generated,
+    config: &Config,
+) -> io::Result<()> {
+    let games = &generated.games;
+    writeln!(out, "Generated {} games", games.len())?;
+    if generated.stats.skipped_initial > 0 {
+        writeln!(
+            out,
+            "Skipped {} invalid initial game(s), generating others in their place",
+            generated.stats.skipped_initial
+        )?;
+    }
+    if matches!(
+        config.strategy,
+        config::Strategy::Random | config::Strategy::QuickPick
+    ) {
+        writeln!(
+            out,
+            "{}",
+            analysis::coverage_stats(games, 3, config.min_number, config.max_number)
+        )?;
+    } else {
+        // Compare with what as many purely random tickets would be expected to cover
+        for subset_size in [3, 2] {
+            let stats =
+                analysis::coverage_stats(games, subset_size, config.min_number, config.max_number);
+            let expected = analysis::expected_random_coverage(
+                games.len(),
+                subset_size,
+                config.numbers_per_game() as i64,
+                config.min_desired_number,
+                config.max_number,
+            );
+            writeln!(
+                out,
+                "{} (random tickets: about {:.0}, {:.2}%)",
+                stats,
+                expected,
+                100.0 * expected / stats.total as f64
+            )?;
+        }
+    }
+    let rejections: Vec<String> = generated
+        .constraints
+        .rejection_counts()
+        .iter()
+        .map(|(name, count)| format!("{}: {}", name, count))
+        .collect();
+    writeln!(out, "Rejections by constraint: {}", rejections.join(", "))?;
+    if let Some(max_number_usage) = config.max_number_usage {
+        let histogram =
+            analysis::usage_histogram(games, config.min_desired_number, config.max_number);
+        let usage: Vec<String> = histogram
+            .iter()
+            .enumerate()
+            .filter(|&(_, &numbers)| numbers > 0)
+            .map(|(games, numbers)| format!("{} in {} game(s)", numbers, games))
+            .collect();
+        writeln!(
+            out,
+            "Number usage, at most {} games per number: {}",
+            max_number_usage,
+            usage.join(", ")
+        )?;
+    }
+    if let Some(max_overlap) = config.max_overlap() {
+        let overlaps: Vec<String> = analysis::overlap_histogram(games)
             .iter()
-            .map(|triplet| custom_utils::triplet2enum(triplet.clone()))
-            .collect::<Vec<_>>();
+            .enumerate()
+            .filter(|&(_, &pairs)| pairs > 0)
+            .map(|(shared, pairs)| format!("{} sharing {}", pairs, shared))
+            .collect();
+        writeln!(
+            out,
+            "Pairs of games, at most {} numbers shared: {}",
+            max_overlap,
+            overlaps.join(", ")
+        )?;
+    }
+    writeln!(
+        out,
+        "Sets: games {}, triplets {}",
+        generated.game_set.stats(),
+        generated.triplet_set.stats()
+    )?;
+    writeln!(out, "{}", generated.stats)
+}
+
+/// Prints the summary of `print_summary` as a JSON object.
+fn print_json_summary(
+    out: &mut impl Write,
+    generated: &generation::Generated,
+    config: &Config,
+) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, &json_summary(generated, config))?;
+    writeln!(out)
+}
+
+/// The summary of `print_summary`, as a JSON object.
+fn json_summary(generated: &generation::Generated, config: &Config) -> serde_json::Value {
+    let rejections: std::collections::BTreeMap<&str, u64> = generated
+        .constraints
+        .rejection_counts()
+        .into_iter()
+        .collect();
+    let mut summary = serde_json::json!({
+        "games": generated.games.len(),
+        "stats": generated.stats,
+        "rejections_by_constraint": rejections,
+        "triplets": analysis::coverage_stats(&generated.games, 3, config.min_number, config.max_number),
+        "pairs": analysis::coverage_stats(&generated.games, 2, config.min_number, config.max_number),
+        "sets": {
+            "games": generated.game_set.stats(),
+            "triplets": generated.triplet_set.stats(),
+        },
+    });
+    if config.max_number_usage.is_some() {
+        summary["number_usage"] = serde_json::json!(analysis::usage_histogram(
+            &generated.games,
+            config.min_desired_number,
+            config.max_number
+        ));
+    }
+    if config.max_overlap().is_some() {
+        summary["overlaps"] = serde_json::json!(analysis::overlap_histogram(&generated.games));
+    }
+    summary
+}
+
+/// Checks a game given as a string against the configured constraints.
+fn check_game(input: &str, config: &Config) -> Result<(), Error> {
+    let game: game::Game = input
+        .parse()
+        .map_err(|error| Error::Validation(format!("{}: {}", input, error)))?;
+    let violations =
+        constraints::validate_game(&game, &constraints::ConstraintSet::from_config(config));
+    if violations.is_empty() {
+        println!("{} is a valid game", game);
+        return Ok(());
+    }
+
+    println!("{} is not a valid game:", game);
+    for violation in &violations {
+        println!("  - {}", violation);
+    }
+    Err(Error::Validation(format!(
+        "Game {} breaks {} rule(s)",
+        game,
+        violations.len()
+    )))
+}
+
+/// Runs a game given as a string through the checks of a run having accepted the games of
+/// `against` (the initial games without it), printing every reason it would be turned down.
+fn run_explain(input: &str, against: Option<&str>, config: &Config) -> Result<(), Error> {
+    let game: game::Game = input
+        .parse()
+        .map_err(|error| Error::Validation(format!("{}: {}", input, error)))?;
+    // The row of each game, from 1: in the file, or in `initial_games` for the initial
+    // games, the repeats left out by `allow_duplicate_initial` counted
+    let (games, rows): (Vec<game::Game>, Vec<usize>) = match against {
+        Some(path) => {
+            let games = read_games(path, config)?;
+            let rows = (1..=games.len()).collect();
+            (games, rows)
+        }
+        None => config
+            .distinct_initial_games()
+            .into_iter()
+            .map(|(index, game)| (game, index + 1))
+            .unzip(),
+    };
+    let explanation = validation::explain(
+        &game,
+        &games,
+        constraints::ConstraintSet::from_config(config),
+        config.min_number,
+    );
+    if explanation.accepted() {
+        println!("{} would be accepted", game);
+        return Ok(());
+    }
+
+    println!("{} would be rejected:", game);
+    for violation in &explanation.violations {
+        println!("  - {}", violation);
+    }
+    if let Some(row) = explanation.duplicate_of {
+        println!("  - it is game #{} already", rows[row - 1]);
+    }
+    // The triplets held by each game, the games in order of their first triplet
+    let mut holders: Vec<(usize, Vec<String>)> = Vec::new();
+    for (triplet, row) in &explanation.shared_triplets {
+        let triplet = game::Game::new(triplet.clone()).to_string();
+        match holders.iter_mut().find(|(holder, _)| holder == row) {
+            Some((_, triplets)) => triplets.push(triplet),
+            None => holders.push((*row, vec![triplet])),
+        }
+    }
+    for (row, triplets) in &holders {
+        println!("  - game #{} holds {}", rows[row - 1], triplets.join(", "));
+    }
+    let reasons = explanation.violations.len()
+        + usize::from(explanation.duplicate_of.is_some())
+        + holders.len();
+    Err(Error::Validation(format!(
+        "Game {} would be rejected for {} reason(s)",
+        game, reasons
+    )))
+}
+
+/// Checks the games of a CSV file against the configured constraints and triplet uniqueness,
+/// printing every problem found.
+fn run_validate(path: &str, json: bool, config: &Config) -> Result<(), Error> {
+    let constraints = constraints::ConstraintSet::from_config(config);
+    let report = if path.starts_with(config::SQLITE_PREFIX) {
+        // The games of every run are checked together, as a file holding them all
+        let games = read_games(path, config)?;
+        let csv = games_file::to_csv(
+            &games,
+            Layout::Indexed,
+            config.numbers_per_game(),
+            games_file::DEFAULT_DELIMITER,
+            0,
+            None,
+        );
+        validation::validate_csv(
+            &csv[..],
+            &constraints,
+            config.min_number,
+            config.max_overlap(),
+            games_file::DEFAULT_DELIMITER,
+        )
+    } else {
+        let file = persistence::open(path).map_err(|error| Error::io(path, error))?;
+        validation::validate_csv(
+            file,
+            &constraints,
+            config.min_number,
+            config.max_overlap(),
+            config.csv_delimiter_byte(),
+        )
+    };
+
+    if json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|error| Error::Check(error.to_string()))?;
+        println!("{}", json);
+    } else {
+        for issue in &report.issues {
+            println!("{}", issue);
+        }
+    }
+    if !report.passed() {
+        return Err(Error::Validation(format!(
+            "{}: {} problem(s) found in {} rows",
+            path,
+            report.issues.len(),
+            report.rows
+        )));
+    }
+    if !json {
+        println!("{}: all {} games are valid", path, report.rows);
+    }
+    Ok(())
+}
+
+/// Prints the pairs of games of two files sharing `subset_size` numbers or more.
+fn run_diff(
+    a: &str,
+    b: &str,
+    subset_size: usize,
+    json: bool,
+    config: &Config,
+) -> Result<(), Error> {
+    if !(1..=6).contains(&subset_size) {
+        return Err(Error::Usage(format!(
+            "--subset-size must be between 1 and 6, not {}",
+            subset_size
+        )));
+    }
+    let games_a = read_games(a, config)?;
+    let games_b = read_games(b, config)?;
+    let collisions = diff::diff_games(&games_a, &games_b, subset_size, config.min_number);
+
+    if json {
+        let report = serde_json::json!({
+            "a": a,
+            "b": b,
+            "subset_size": subset_size,
+            "games_a": games_a.len(),
+            "games_b": games_b.len(),
+            "collisions": collisions,
+        });
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|error| Error::Check(error.to_string()))?;
+        println!("{}", json);
+        return Ok(());
+    }
+    if !collisions.is_empty() {
+        println!("Rows of {} and {}:", a, b);
+    }
+    for collision in &collisions {
+        println!("  {}", collision);
+    }
+    println!(
+        "{} pair(s) of games share {} or more numbers between {} ({} games) and {} ({} games)",
+        collisions.len(),
+        subset_size,
+        a,
+        games_a.len(),
+        b,
+        games_b.len()
+    );
+    Ok(())
+}
+
+/// Counts the games of a CSV file containing each pair of numbers, reading one row at a time,
+/// then writes the matrix to `output` (stdout when missing and no summary is asked for) and
+/// prints the `top` pairs in the most games.
+fn run_analyze_pairs(
+    path: &str,
+    output: Option<&str>,
+    top: Option<usize>,
+    config: &Config,
+) -> Result<(), Error> {
+    let file = persistence::open(path).map_err(|error| Error::io(path, error))?;
+    let mut matrix = analysis::PairMatrix::new(config.min_number, config.max_number);
+    for (row, game) in games_file::Rows::new(file, config.csv_delimiter_byte()).enumerate() {
+        game.and_then(|game| matrix.add(&game))
+            .map_err(|error| Error::Validation(format!("{}: row {}: {}", path, row + 1, error)))?;
+    }
+
+    let csv = matrix.to_csv();
+    match output {
+        Some(output) => {
+            persistence::write_atomically(output, &csv)
+                .map_err(|error| Error::io(output, error))?;
+            println!(
+                "Wrote the pair matrix of the {} games of {} to {}",
+                matrix.games, path, output
+            );
+        }
+        None if top.is_none() => {
+            print!("{}", String::from_utf8_lossy(&csv));
+        }
+        None => {}
+    }
+    if let Some(top) = top {
+        let pairs = matrix.most_repeated(top);
+        println!(
+            "Most repeated pairs in the {} games of {}:",
+            matrix.games, path
+        );
+        for (a, b, count) in &pairs {
+            println!("  {:02}-{:02}: {} game(s)", a, b, count);
+        }
+        if pairs.is_empty() {
+            println!("  none");
+        }
+    }
+    Ok(())
+}
+
+/// Repairs a CSV file of games, writing the games kept to `output` and the rejected rows to
+/// `rejects`. Ends with the changed status, exiting with 1, when anything had to be changed.
+fn run_repair(path: &str, output: &str, rejects: &str, config: &Config) -> Result<(), Error> {
+    let file = persistence::open(path).map_err(|error| Error::io(path, error))?;
+    let repair = repair::repair_csv(
+        file,
+        config.numbers_per_game(),
+        config.min_number,
+        config.max_number,
+        config.csv_delimiter_byte(),
+    );
+    if !repair.changed() {
+        println!("{}: nothing to repair in {} rows", path, repair.rows);
+        return Ok(());
+    }
+
+    let count =
+        |matches: fn(&repair::Fix) -> bool| repair.fixes.iter().filter(|fix| matches(fix)).count();
+    let duplicates = count(|fix| matches!(fix, repair::Fix::Duplicate { .. }));
+    let rejected = count(|fix| matches!(fix, repair::Fix::Rejected { .. }));
+    let sorted = count(|fix| matches!(fix, repair::Fix::Sorted { .. }));
+    for fix in &repair.fixes {
+        if !matches!(fix, repair::Fix::Sorted { .. }) {
+            println!("{}", fix);
+        }
+    }
+
+    let layout = repair.layout.unwrap_or(Layout::configured(config));
+    let csv = games_file::to_csv(
+        &repair.games,
+        layout,
+        config.numbers_per_game(),
+        repair.delimiter,
+        config.number_width(),
+        repair
+            .stats_columns
+            .then_some((config.min_number, config.max_number)),
+    );
+    persistence::write_atomically(output, &csv).map_err(|error| Error::io(output, error))?;
+    println!("Wrote the {} games kept to {}", repair.games.len(), output);
+    if rejected > 0 {
+        persistence::write_atomically(rejects, &repair.rejects_csv())
+            .map_err(|error| Error::io(rejects, error))?;
+        println!("Wrote the {} rejected rows to {}", rejected, rejects);
+    }
+    let changes = format!(
+        "{}: {} of {} rows changed: {} duplicate(s) dropped, {} rejected, {} sorted",
+        path,
+        duplicates + rejected + sorted,
+        repair.rows,
+        duplicates,
+        rejected,
+        sorted
+    );
+    println!("{}", changes);
+    Err(Error::Changed(changes))
+}
+
+/// Downloads the results of `contest` of `lottery`, or the latest ones for `None`, from the
+/// Caixa API.
+#[cfg(feature = "net")]
+fn fetch_results(lottery: Lottery, contest: Option<u32>) -> Result<Vec<history::Draw>, Error> {
+    let hint = "type the numbers drawn with --draw instead, e.g. --draw 4,18,23,39,51,60";
+    rk_lottery::caixa::fetch_results(lottery, contest).map_err(|error| match error {
+        FetchError::Network { url, message } => Error::io(
+            &url,
+            std::io::Error::other(format!("cannot fetch the result: {}; {}", message, hint)),
+        ),
+        FetchError::Invalid { .. } => Error::Validation(format!("{}; {}", error, hint)),
+    })
+}
+
+#[cfg(not(feature = "net"))]
+fn fetch_results(_lottery: Lottery, _contest: Option<u32>) -> Result<Vec<history::Draw>, Error> {
+    Err(Error::Config(
+        "fetching the result is not supported by this binary, rebuild it with `cargo build --features net` or type the numbers drawn with --draw".to_string(),
+    ))
+}
+
+/// Scores the tickets of a games file against the results of a contest, one per `--draw` (two
+/// for the Dupla Sena), printing a table of the matches in each result and the number of
+/// tickets hitting each prize tier.
+fn run_check_draw(draws: &[String], path: &str, json: bool, config: &Config) -> Result<(), Error> {
+    let results = config.lottery.results_per_draw();
+    if draws.len() != results {
+        return Err(Error::Usage(format!(
+            "The {} draws {} result(s) per contest, give --draw {} time(s), not {}",
+            config.lottery,
+            results,
+            results,
+            draws.len()
+        )));
+    }
+    let rules = constraints::ConstraintSet::new()
+        .with(constraints::LengthConstraint {
+            numbers_per_game: config.lottery.numbers_per_game(),
+        })
+        .with(constraints::RangeConstraint::new(
+            config.min_number,
+            config.max_number,
+        ))
+        .with(constraints::DistinctConstraint);
+    let draws = draws
+        .iter()
+        .map(|draw| {
+            let parsed: game::Game = draw
+                .parse()
+                .map_err(|error| Error::Validation(format!("Invalid draw {}: {}", draw, error)))?;
+            let violations = rules.violations(&parsed);
+            if !violations.is_empty() {
+                let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                return Err(Error::Validation(format!(
+                    "Invalid draw {}: {}",
+                    parsed,
+                    violations.join("; ")
+                )));
+            }
+            Ok(parsed)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let report = scoring::score_games(&read_games(path, config)?, &draws, config.lottery);
+    if json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|error| Error::Check(error.to_string()))?;
+        println!("{}", json);
+        return Ok(());
+    }
+
+    // A single result has one column of matches, two have one each and the best of them
+    let mut columns: Vec<String> = (1..=results).map(|draw| format!("Draw {}", draw)).collect();
+    match results {
+        1 => {
+            println!("Draw: {}", report.draws[0]);
+            columns = vec!["Matches".to_string()];
+        }
+        _ => {
+            for (index, draw) in report.draws.iter().enumerate() {
+                println!("Draw {}: {}", index + 1, draw);
+            }
+            columns.push("Best".to_string());
+        }
+    }
+    // The label column only shows when some ticket is labeled
+    let labeled = report.tickets.iter().any(|ticket| ticket.label.is_some());
+    // Two digits and a dash per number, 17 characters for a game of 6
+    let width = 3 * config.numbers_per_game() - 1;
+    let mut header = format!("{:>5}  {:<width$}", "Row", "Game");
+    for column in &columns {
+        header += &format!("  {:>7}", column);
+    }
+    if labeled {
+        header += "  Label";
+    }
+    println!("{}", header);
+    for ticket in &report.tickets {
+        let mut line = format!("{:>5}  {:<width$}", ticket.row, ticket.game.to_string());
+        for matches in &ticket.matches {
+            line += &format!("  {:>7}", matches);
+        }
+        if results > 1 {
+            line += &format!("  {:>7}", ticket.best);
+        }
+        if let Some(label) = &ticket.label {
+            line += &format!("  {}", label);
+        }
+        println!("{}", line);
+    }
+    for &(matches, name) in scoring::prize_tiers(config.lottery) {
+        let hits: Vec<String> = match results {
+            1 => vec![format!("{} ticket(s)", report.prizes[0][name])],
+            _ => report
+                .prizes
+                .iter()
+                .enumerate()
+                .map(|(index, prizes)| format!("{} ticket(s) in draw {}", prizes[name], index + 1))
+                .collect(),
+        };
+        println!("{} ({} matches): {}", name, matches, hits.join(", "));
+    }
+    for ticket in &report.tickets {
+        let owner = match &ticket.label {
+            Some(label) => label.clone(),
+            None => format!("Ticket #{}", ticket.row),
+        };
+        for (index, &matches) in ticket.matches.iter().enumerate() {
+            if let Some(prize) = scoring::prize_tier(config.lottery, matches) {
+                match results {
+                    1 => println!("{} hit the {}!", owner, prize),
+                    _ => println!("{} hit the {} of draw {}!", owner, prize, index + 1),
+                }
+            }
+        }
+    }
+    Ok(())
+}
 
-        if !triplet_set.check_and_insert_all(triplet_nos) {
+/// Scores the tickets of a games file against `draws` random results, printing the
+/// distribution of the best match per draw and the prize tickets expected per draw.
+fn run_simulate(path: &str, draws: u64, json: bool, config: &Config) -> Result<(), Error> {
+    if draws == 0 {
+        return Err(Error::Usage("--draws must be at least 1".to_string()));
+    }
+    let games = read_games(path, config)?;
+    let seed = config.effective_seed();
+    let simulation = match seed {
+        Some(seed) => scoring::simulate(
+            &games,
+            draws,
+            config.lottery.results_per_draw(),
+            config.lottery.numbers_per_game(),
+            &mut rand::rngs::StdRng::seed_from_u64(seed),
+            config.min_number,
+            config.max_number,
+        ),
+        None => scoring::simulate(
+            &games,
+            draws,
+            config.lottery.results_per_draw(),
+            config.lottery.numbers_per_game(),
+            &mut rand::rngs::OsRng,
+            config.min_number,
+            config.max_number,
+        ),
+    }
+    .map_err(|error| Error::Validation(format!("{}: {}", path, error)))?;
+    let winnings = config
+        .prizes
+        .as_ref()
+        .map(|prizes| simulation.expected_winnings(prizes));
+
+    if json {
+        let expected: serde_json::Map<String, serde_json::Value> =
+            scoring::prize_tiers(config.lottery)
+                .iter()
+                .map(|&(matches, name)| {
+                    (name.to_string(), simulation.expected_hits(matches).into())
+                })
+                .collect();
+        let report = serde_json::json!({
+            "games": path,
+            "lottery": config.lottery,
+            "seed": seed,
+            "simulation": simulation,
+            "expected_hits_per_draw": expected,
+            "expected_winnings_per_draw": winnings,
+        });
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|error| Error::Check(error.to_string()))?;
+        println!("{}", json);
+        return Ok(());
+    }
+
+    match config.lottery.results_per_draw() {
+        1 => println!(
+            "{} tickets of {} against {} random draws",
+            simulation.tickets, path, simulation.draws
+        ),
+        results => println!(
+            "{} tickets of {} against {} random {} draws of {} results",
+            simulation.tickets, path, simulation.draws, config.lottery, results
+        ),
+    }
+    println!("Best match  Draws");
+    for (matches, count) in simulation.best_matches.iter().enumerate() {
+        println!(
+            "{:>10}  {} ({:.4}%)",
+            matches,
+            count,
+            100.0 * *count as f64 / simulation.draws as f64
+        );
+    }
+    for &(matches, name) in scoring::prize_tiers(config.lottery) {
+        println!(
+            "{} ({} matches): {:.6} ticket(s) per draw",
+            name,
+            matches,
+            simulation.expected_hits(matches)
+        );
+    }
+    if let Some(winnings) = winnings {
+        println!(
+            "Expected winnings per draw, ticket price taken off: {:.2}",
+            winnings
+        );
+    }
+    Ok(())
+}
+
+/// Prints the frequency, last appearance and current drought of each number over a file of
+/// past draws, most drawn first, warning about the rows that cannot be read.
+fn run_stats(path: &str, json: bool, config: &Config) -> Result<(), Error> {
+    if let Some(path) = path.strip_prefix(config::SQLITE_PREFIX) {
+        return run_stored_stats(path, json, config);
+    }
+    let content = std::fs::read_to_string(path).map_err(|error| Error::io(path, error))?;
+    let (draws, warnings) = history::read_draws(&content, config.min_number, config.max_number)
+        .map_err(|error| Error::Validation(format!("{}: {}", path, error)))?;
+    for warning in &warnings {
+        eprintln!("Warning: {}: {}", path, warning);
+    }
+    if draws.is_empty() {
+        return Err(Error::Validation(format!(
+            "{}: no draw could be read",
+            path
+        )));
+    }
+    let report = history::frequencies(&draws, config.min_number, config.max_number);
+
+    if json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|error| Error::Check(error.to_string()))?;
+        println!("{}", json);
+        return Ok(());
+    }
+
+    println!(
+        "{} draws from {} to {}",
+        report.draws,
+        report.first_date.expect("there are draws"),
+        report.last_date.expect("there are draws")
+    );
+    println!(
+        "{:>6}  {:>5}  {:>9}  {:<10}  Drought",
+        "Number", "Draws", "Frequency", "Last drawn"
+    );
+    for number in &report.numbers {
+        let last_drawn = number
+            .last_drawn
+            .map_or("never".to_string(), |date| date.to_string());
+        println!(
+            "{:>6}  {:>5}  {:>8.2}%  {:<10}  {}",
+            number.number,
+            number.draws,
+            100.0 * number.frequency,
+            last_drawn,
+            number.drought
+        );
+    }
+    let (numbers, drought) = report.longest_drought();
+    let numbers: Vec<String> = numbers.iter().map(|number| number.to_string()).collect();
+    println!(
+        "Longest current drought: {} draw(s), for {}",
+        drought,
+        numbers.join(", ")
+    );
+    Ok(())
+}
+
+/// The manifests of a directory and of its subdirectories, files whose name holds `manifest`
+/// and ends with `.json`, sorted by path.
+fn find_manifests(dir: &std::path::Path) -> Result<Vec<std::path::PathBuf>, Error> {
+    let path = dir.to_string_lossy();
+    let mut manifests = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(|error| Error::io(&path, error))? {
+        let path = entry.map_err(|error| Error::io(&path, error))?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_dir() {
+            manifests.extend(find_manifests(&path)?);
+        } else if name.contains("manifest") && name.ends_with(".json") {
+            manifests.push(path);
+        }
+    }
+    manifests.sort();
+    Ok(manifests)
+}
+
+/// Reads the run of a manifest of an archive, its output file being looked for next to it.
+///
+/// # Returns
+/// * The run and the games of its output file.
+/// * Why the entry is left out of the report: the manifest or its output file cannot be
+///   read, its output file is that of an entry read before, or its games are of another
+///   lottery than that of the configuration.
+fn read_archived_run(
+    path: &std::path::Path,
+    seen: &mut Vec<std::path::PathBuf>,
+    config: &Config,
+) -> Result<(report::ArchivedRun, Vec<game::Game>), String> {
+    let manifest = RunManifest::load_from_file(&path.to_string_lossy())
+        .map_err(|error| format!("cannot read the manifest: {}", error))?;
+    let run_config = &manifest.config;
+    if (run_config.min_number, run_config.max_number) != (config.min_number, config.max_number) {
+        return Err(format!(
+            "its games are of the numbers from {} to {}, not from {} to {} as configured",
+            run_config.min_number, run_config.max_number, config.min_number, config.max_number
+        ));
+    }
+    let output = path
+        .parent()
+        .unwrap_or(std::path::Path::new(""))
+        .join(&run_config.output);
+    let output_name = output.to_string_lossy().into_owned();
+    let content = persistence::read_file(&output_name)
+        .map_err(|error| Error::io(&output_name, error).to_string())?;
+    let canonical = output.canonicalize().unwrap_or_else(|_| output.clone());
+    if seen.contains(&canonical) {
+        return Err(format!(
+            "{} was read for another manifest already",
+            output_name
+        ));
+    }
+    let games = read_games(&output_name, run_config).map_err(|error| error.to_string())?;
+    seen.push(canonical);
+    let run = report::ArchivedRun {
+        manifest: path.to_string_lossy().into_owned(),
+        output: output_name,
+        seed: manifest.seed,
+        timestamp: manifest.timestamp,
+        games: games.len(),
+        matches_manifest: manifest::sha256_hex(&content) == manifest.sha256,
+    };
+    Ok((run, games))
+}
+
+/// Prints the report of the runs archived in `dir`, or writes it to `output` as JSON, warning
+/// about the entries that cannot be read, which are left out.
+fn run_report(dir: &str, output: Option<&str>, json: bool, config: &Config) -> Result<(), Error> {
+    let mut report = report::ArchiveReport::new(config.min_number, config.max_number);
+    let mut seen = Vec::new();
+    for path in find_manifests(std::path::Path::new(dir))? {
+        match read_archived_run(&path, &mut seen, config) {
+            Ok((run, games)) => {
+                if !run.matches_manifest {
+                    eprintln!(
+                        "Warning: {} was changed since {} was written, its games are reported as they are now",
+                        run.output, run.manifest
+                    );
+                }
+                report.add(run, &games);
+            }
+            Err(reason) => {
+                eprintln!("Warning: {}: {}, skipped", path.display(), reason);
+                report.skip(path.to_string_lossy(), reason);
+            }
+        }
+    }
+    if report.runs.is_empty() {
+        return Err(Error::Validation(format!(
+            "{}: no run could be read, {} manifest(s) skipped",
+            dir,
+            report.skipped.len()
+        )));
+    }
+
+    let serialize =
+        || serde_json::to_string_pretty(&report).map_err(|error| Error::Check(error.to_string()));
+    if let Some(output) = output {
+        persistence::write_atomically(output, serialize()?.as_bytes())
+            .map_err(|error| Error::io(output, error))?;
+        eprintln!("Wrote the report to {}", output);
+    }
+    if json {
+        println!("{}", serialize()?);
+        return Ok(());
+    }
+
+    println!(
+        "{} games in {} run(s) of {}, {} manifest(s) skipped",
+        report.games,
+        report.runs.len(),
+        dir,
+        report.skipped.len()
+    );
+    for run in &report.runs {
+        let seed = match run.seed {
+            Some(seed) => format!("seed {}", seed),
+            None => "OS entropy".to_string(),
+        };
+        println!(
+            "  {}: {} games of {} ({})",
+            run.manifest, run.games, run.output, seed
+        );
+    }
+    println!("{}", report.coverage);
+    let never_played: Vec<String> = report
+        .never_played
+        .iter()
+        .map(|number| number.to_string())
+        .collect();
+    println!(
+        "Numbers never played: {}",
+        if never_played.is_empty() {
+            "none".to_string()
+        } else {
+            never_played.join(", ")
+        }
+    );
+    println!(
+        "{} pair(s) of games of different runs share triplets",
+        report.collisions.len()
+    );
+    for collision in &report.collisions {
+        println!("  {}", collision);
+    }
+    Ok(())
+}
+
+/// Prints the runs of a ticket database and how many of their games play each number, most
+/// played first.
+#[cfg(feature = "sqlite")]
+fn run_stored_stats(path: &str, json: bool, config: &Config) -> Result<(), Error> {
+    let report = Storage::open(path)
+        .and_then(|storage| storage.report(config.min_number, config.max_number))
+        .map_err(|error| storage_error(path, error))?;
+
+    if json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|error| Error::Check(error.to_string()))?;
+        println!("{}", json);
+        return Ok(());
+    }
+
+    println!(
+        "{} games stored by {} run(s) in {}",
+        report.games,
+        report.runs.len(),
+        path
+    );
+    for run in &report.runs {
+        let seed = match run.seed {
+            Some(seed) => format!("seed {}", seed),
+            None => "OS entropy".to_string(),
+        };
+        println!("Run {} ({}): {} games", run.run_id, seed, run.games);
+    }
+    println!("{:>6}  {:>5}  {:>9}", "Number", "Games", "Frequency");
+    for usage in &report.numbers {
+        let frequency = usage.games as f64 / report.games.max(1) as f64;
+        println!(
+            "{:>6}  {:>5}  {:>8.2}%",
+            usage.number,
+            usage.games,
+            100.0 * frequency
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn run_stored_stats(path: &str, _json: bool, _config: &Config) -> Result<(), Error> {
+    Err(sqlite_unsupported(path))
+}
+
+/// Writes the ranks of a saved set as the games or triplets they stand for, of the lottery of
+/// the header of the set or of the configuration for a set saved without one. Fails with a
+/// validation error when some ranks stand for none, having written the others.
+fn run_export_set(
+    path: &str,
+    kind: Option<SetKind>,
+    output: &str,
+    config: &Config,
+) -> Result<(), Error> {
+    let (set, header): (NumberSet, _) =
+        NumberSet::load_with_header(path).map_err(|error| Error::io(path, error))?;
+    let n = config.max_number - config.min_number + 1;
+    let kind = kind
+        .or(header.map(|header| header.kind))
+        .unwrap_or(SetKind::Game);
+    let configured = match kind {
+        SetKind::Game => SetHeader {
+            k: config.numbers_per_game() as i64,
+            ..SetHeader::games(n)
+        },
+        SetKind::Triplet => SetHeader::triplets(n),
+    };
+    let header = match header {
+        Some(header) => {
+            match configured.check(&header) {
+                Err(error @ SetFileError::KindMismatch { .. }) => {
+                    return Err(Error::Validation(format!("{}: {}", path, error)))
+                }
+                Err(error) => eprintln!("Warning: {}: {}, exporting them as such", path, error),
+                Ok(()) => {}
+            }
+            header
+        }
+        None => configured,
+    };
+
+    let out_of_range = set
+        .export_games_from(header.n, header.k, config.min_number, output)
+        .map_err(|error| Error::io(output, error))?;
+    for error in &out_of_range {
+        eprintln!("Warning: {}: {}", path, error);
+    }
+    println!(
+        "Wrote the {} {}s of {} to {}",
+        set.len() - out_of_range.len(),
+        header.kind,
+        path,
+        output
+    );
+    if out_of_range.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Validation(format!(
+            "{}: {} of {} ranks stand for no {} of {} numbers out of {}",
+            path,
+            out_of_range.len(),
+            set.len(),
+            header.kind,
+            header.k,
+            header.n
+        )))
+    }
+}
+
+/// Converts ranks to games (`to_game`) or games to ranks, printing one result per line.
+///
+/// `values` holds comma-separated ranks, or a single game, and when missing the inputs are read
+/// from stdin, one per line. Games are `k`-subsets of the `n` numbers starting at `min_number`.
+fn run_convert(
+    values: Option<&str>,
+    to_game: bool,
+    n: i64,
+    k: i64,
+    config: &Config,
+) -> Result<(), Error> {
+    let inputs: Vec<String> = match values {
+        Some(values) if to_game => values.split(',').map(|rank| rank.to_string()).collect(),
+        Some(game) => vec![game.to_string()],
+        None => std::io::stdin()
+            .lock()
+            .lines()
+            .collect::<Result<_, _>>()
+            .map_err(|error| Error::io("stdin", error))?,
+    };
+
+    let max_number = config.min_number + n - 1;
+    let rules = constraints::ConstraintSet::new()
+        .with(constraints::LengthConstraint {
+            numbers_per_game: k as usize,
+        })
+        .with(constraints::RangeConstraint::new(
+            config.min_number,
+            max_number,
+        ))
+        .with(constraints::DistinctConstraint);
+    for input in inputs.iter().map(|input| input.trim()) {
+        if input.is_empty() {
             continue;
         }
+        if to_game {
+            let rank: i64 = input
+                .parse()
+                .map_err(|_| Error::Validation(format!("'{}' is not a valid rank", input)))?;
+            let subset = custom_utils::enum2subset_from(rank, n, k, config.min_number)
+                .map_err(|error| Error::Validation(error.to_string()))?;
+            println!("{}", game::Game::new(subset));
+        } else {
+            let game: game::Game = input
+                .parse()
+                .map_err(|error| Error::Validation(format!("{}: {}", input, error)))?;
+            let violations = rules.violations(&game);
+            if !violations.is_empty() {
+                let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                return Err(Error::Validation(format!(
+                    "{} is not a {}-subset of {}..={}: {}",
+                    input,
+                    k,
+                    config.min_number,
+                    max_number,
+                    violations.join("; ")
+                )));
+            }
+            let mut numbers = game.to_vec();
+            numbers.sort_unstable();
+            let rank = custom_utils::subset2enum_from(&numbers, k as usize, config.min_number)
+                .map_err(|error| Error::Validation(error.to_string()))?;
+            println!("{}", rank);
+        }
+    }
+    Ok(())
+}
 
-        games.push(game);
+/// Generates an abbreviated wheel instead of random games, verifies its guarantee and
+/// writes it to the output file.
+fn run_wheel(wheel_config: &config::WheelConfig, config: &Config) -> Result<(), Error> {
+    if wheel_config
+        .pool
+        .iter()
+        .any(|&x| x < config.min_desired_number || x > config.max_number)
+    {
+        return Err(Error::Config(format!(
+            "Wheel pool {:?} contains numbers outside {}..={}",
+            wheel_config.pool, config.min_desired_number, config.max_number
+        )));
     }
 
-    for row in games {
-        let string_row: Vec<String> = row.iter().map(|item| item.to_string()).collect();
-        wtr.write_record(&string_row)?;
+    let mut games = wheel::generate_wheel(&wheel_config.pool, wheel_config.guarantee, 6)
+        .map_err(|error| Error::Config(error.to_string()))?;
+    if config.labeled() {
+        game::label_games(&mut games, &config.label_pool);
+    }
+    let missing = wheel::verify_wheel(&games, &wheel_config.pool, wheel_config.guarantee);
+    if !missing.is_empty() {
+        return Err(Error::Check(format!(
+            "Wheel guarantee does not hold for subsets {:?}",
+            missing
+        )));
     }
 
-    game_set.save_to_file("games.csv")?;
-    triplet_set.save_to_file("triplet_set.log")?;
-    wtr.flush()?;
+    println!(
+        "Generated a {}-if-{} wheel of {} numbers with {} games",
+        wheel_config.guarantee,
+        wheel_config.guarantee,
+        wheel_config.pool.len(),
+        games.len()
+    );
+    write_games(
+        &config.output,
+        &games,
+        None,
+        config,
+        Layout::configured(config),
+    )
+}
+
+/// Reports whether the games of the configuration can be generated and roughly how long it
+/// would take, without generating nor writing anything: the games of the desired numbers, the
+/// upper bound on the games sharing no triplet, and the attempts expected per game as the
+/// triplets of the accepted games fill up, timed by checking a sample of candidates.
+fn run_dry_run(config: &Config) -> Result<(), Error> {
+    // Candidates drawn to measure how often they pass the constraints and how fast
+    const SAMPLE: usize = 100_000;
+
+    if config.wheel.is_some() {
+        return Err(Error::Usage(
+            "--dry-run estimates runs drawing their games, not wheels".to_string(),
+        ));
+    }
+    let (min, max) = (config.min_desired_number, config.max_number);
+    let numbers_per_game = config.numbers_per_game();
+    let k = numbers_per_game as i64;
+    println!("Dry run: no game is generated and no file is written");
+    println!(
+        "Numbers {} to {}: {} games of {} numbers",
+        min,
+        max,
+        feasibility::valid_games(min, max, k),
+        numbers_per_game
+    );
+    let mut bound = feasibility::valid_games(min, max, k);
+    if config.unique_triplets() {
+        bound = feasibility::triplet_upper_bound(min, max, k);
+        println!("Upper bound: {} games sharing no triplet", bound);
+    }
+    if let Some(max_overlap) = config.max_overlap() {
+        let by_overlap = feasibility::overlap_upper_bound(min, max, k, max_overlap as i64);
+        println!(
+            "Upper bound: {} games sharing at most {} numbers",
+            by_overlap, max_overlap
+        );
+        bound = bound.min(by_overlap);
+    }
+    if let Some(max_number_usage) = config.max_number_usage {
+        let numbers = (max - min + 1).max(0) as usize;
+        let by_usage =
+            feasibility::usage_upper_bound(numbers, max_number_usage, numbers_per_game) as u64;
+        println!(
+            "Upper bound: {} games with each number in at most {}",
+            by_usage, max_number_usage
+        );
+        bound = bound.min(by_usage);
+    }
+    println!(
+        "Requested: {} games, {:.0}% of the bound",
+        config.no_of_games,
+        100.0 * config.no_of_games as f64 / bound.max(1) as f64
+    );
+    generation::check_start(config)?;
+
+    let sample = generation::sample_candidates(config, SAMPLE);
+    println!(
+        "Constraints: {:.1}% of {} candidates pass them",
+        100.0 * sample.pass_rate(),
+        sample.drawn
+    );
+    if !config.unique_triplets() {
+        println!(
+            "Expected attempts: only estimated for games of 6 numbers, kept apart by their triplets"
+        );
+        return Ok(());
+    }
+    let start = config.distinct_initial_games().len();
+    let attempts =
+        feasibility::expected_attempts(start, config.no_of_games, min, max, k, sample.pass_rate());
+    if attempts.is_empty() {
+        return Ok(());
+    }
+    println!("Expected attempts per accepted game:");
+    let last = attempts.len() - 1;
+    let mut milestones: Vec<usize> = [0, last / 4, last / 2, 3 * last / 4, last].to_vec();
+    milestones.dedup();
+    for index in milestones {
+        println!("  game {:>6}: {:>14.1}", start + index + 1, attempts[index]);
+    }
+    let total: f64 = attempts.iter().sum();
+    if total.is_finite() {
+        println!(
+            "Expected attempts: {:.0} in total, {} on one thread at {:.0} attempts/s",
+            total,
+            format_seconds(total / sample.attempts_per_second()),
+            sample.attempts_per_second()
+        );
+    } else {
+        println!("Expected attempts: unbounded, some games cannot be expected to be found");
+    }
+    Ok(())
+}
+
+/// Formats a rough duration in seconds with the unit best suited to its size.
+fn format_seconds(seconds: f64) -> String {
+    if seconds < 1.0 {
+        "under a second".to_string()
+    } else if seconds < 120.0 {
+        format!("about {:.0} s", seconds)
+    } else if seconds < 7200.0 {
+        format!("about {:.0} min", seconds / 60.0)
+    } else if seconds < 172_800.0 {
+        format!("about {:.1} h", seconds / 3600.0)
+    } else {
+        format!("about {:.0} days", seconds / 86_400.0)
+    }
+}
+
+/// Improves the games of a CSV file by simulated annealing, writing them to the output file.
+fn run_optimize(path: &str, config: &Config) -> Result<(), Error> {
+    if !config.unique_triplets() {
+        return Err(Error::Usage(format!(
+            "--optimize keeps the games from sharing a triplet, and needs games of 6 numbers, not {}",
+            config.numbers_per_game()
+        )));
+    }
+    let mut games = read_games(path, config)?;
+    let before = optimize::batch_metrics(&games, config.min_number, config.max_number);
+    let accepted = optimize::anneal(
+        &mut games,
+        &constraints::ConstraintSet::from_config(config),
+        &config.optimize.clone().unwrap_or_default(),
+        config.seed.unwrap_or(config::DEFAULT_SEED),
+        config.min_number,
+        config.min_desired_number,
+        config.max_number,
+    )
+    .map_err(|error| Error::Validation(format!("{}: {}", path, error)))?;
+    let after = optimize::batch_metrics(&games, config.min_number, config.max_number);
+
+    println!("Accepted {} moves", accepted);
+    println!("Before: {}", before);
+    println!("After:  {}", after);
+
+    write_games(
+        &config.output,
+        &games,
+        None,
+        config,
+        Layout::configured(config),
+    )?;
+    Ok(())
+}
+
+/// Verifies the combinadic conversions exhaustively for the `k`-subsets of `1..=n` and their
+/// triplets, then on a random sample of the full 60/6 space.
+fn run_selftest(n: i64, k: i64) -> Result<(), Error> {
+    let mut progress = |done: u64, total: u64| println!("  {} / {}", done, total);
+    let failed = |mismatch: selftest::Mismatch| Error::Check(mismatch.to_string());
+
+    println!("Checking every {}-subset of 1..={}", k, n);
+    let checked = selftest::verify_exhaustive(n, k, &mut progress).map_err(failed)?;
+    println!("Checking every triplet of 1..={}", n);
+    let checked = checked + selftest::verify_exhaustive(n, 3, &mut progress).map_err(failed)?;
+    println!("Checking a random sample of the games of 1..=60");
+    let checked =
+        checked + selftest::verify_sample(60, 6, 100_000, 12345, &mut progress).map_err(failed)?;
+
+    println!("All {} ranks convert back and forth correctly", checked);
+    Ok(())
+}
+
+/// Tests the configured random number generator for uniformity over the playable numbers.
+///
+/// `values` holds the optional number of games to draw (100000 by default) and significance
+/// level (0.01 by default), and `json` selects machine-readable output.
+fn run_analyze_rng(values: &[String], json: bool, config: &Config) -> Result<(), Error> {
+    let usage = || {
+        Error::Usage(
+            "Usage: rk_lottery --analyze-rng [<GAMES> [<SIGNIFICANCE>]] [--json]".to_string(),
+        )
+    };
+    let draws = match values.first() {
+        Some(value) => value.parse().map_err(|_| usage())?,
+        None => 100_000,
+    };
+    let significance = match values.get(1) {
+        Some(value) => value.parse().map_err(|_| usage())?,
+        None => 0.01,
+    };
+
+    let report = match config.rng {
+        config::RngKind::Std => analysis::analyze_rng(
+            rand::rngs::StdRng::seed_from_u64(config.seed.unwrap_or(config::DEFAULT_SEED)),
+            draws,
+            config.min_number,
+            config.max_number,
+            config.numbers_per_game() as i64,
+            significance,
+        ),
+        config::RngKind::Counter => analysis::analyze_rng(
+            rng::CounterRng::seed_from_u64(config.seed.unwrap_or(config::DEFAULT_SEED)),
+            draws,
+            config.min_number,
+            config.max_number,
+            config.numbers_per_game() as i64,
+            significance,
+        ),
+        config::RngKind::Os => analysis::analyze_rng(
+            rand::rngs::OsRng,
+            draws,
+            config.min_number,
+            config.max_number,
+            config.numbers_per_game() as i64,
+            significance,
+        ),
+    };
+
+    if json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|error| Error::Check(error.to_string()))?;
+        println!("{}", json);
+    } else {
+        for (number, frequency) in &report.frequencies {
+            println!("{:>3}: {}", number, frequency);
+        }
+        println!("Expected frequency: {:.1}", report.expected_frequency);
+        println!("{}", report);
+    }
+    if !report.passed {
+        return Err(Error::Check(
+            "The random number generator failed the uniformity check".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Reads games written by `write_games`, in any format and layout, or the games of every run
+/// of a ticket database given as `sqlite:path`.
+fn read_games(path: &str, config: &Config) -> Result<Vec<game::Game>, Error> {
+    read_games_file(path, config).map(|(_, games)| games)
+}
+
+/// Reads games written by `write_games`, along with the CSV layout of the file (`None` if
+/// empty, in JSON or in a ticket database). CSV fields are separated by `csv_delimiter`, unless
+/// the first row tells another separator.
+fn read_games_file(
+    path: &str,
+    config: &Config,
+) -> Result<(Option<Layout>, Vec<game::Game>), Error> {
+    if let Some(path) = path.strip_prefix(config::SQLITE_PREFIX) {
+        return read_stored_games(path).map(|games| (None, games));
+    }
+    let content = persistence::read_to_string(path).map_err(|error| Error::io(path, error))?;
+    if games_file::is_json(&content) {
+        let records = games_file::read_records(&content)
+            .map_err(|error| Error::Validation(format!("{}: {}", path, error)))?;
+        let games = records.into_iter().map(|record| record.game()).collect();
+        return Ok((None, games));
+    }
+    let (layout, rows) = games_file::read_rows(content.as_bytes(), config.csv_delimiter_byte());
+    let games = rows
+        .into_iter()
+        .enumerate()
+        .map(|(row, game)| {
+            game.map_err(|error| Error::Validation(format!("{}: row {}: {}", path, row + 1, error)))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((layout, games))
+}
+
+/// Appends games to a CSV or NDJSON file written by `write_games` and holding `previous` games,
+/// CSV rows keeping the field separator of the file.
+fn append_games(
+    path: &str,
+    games: &[game::Game],
+    config: &Config,
+    layout: Layout,
+    previous: usize,
+) -> Result<(), Error> {
+    // Rewrite the whole file rather than appending, so an interrupted run leaves it intact
+    let mut content = persistence::read_file(path).map_err(|error| Error::io(path, error))?;
+    let rows = match config.output_format {
+        OutputFormat::Ndjson => {
+            games_file::to_ndjson(games, config.min_number, previous, config.stats_range())
+        }
+        _ => {
+            let delimiter = games_file::sniff_delimiter(&content, config.csv_delimiter_byte());
+            // The figures of the games follow the header row of the file
+            let stats_range = games_file::has_stats_columns(&content)
+                .then_some((config.min_number, config.max_number));
+            games_file::rows_csv(
+                games,
+                layout,
+                previous,
+                delimiter,
+                config.number_width(),
+                stats_range,
+            )
+        }
+    };
+    content.extend(rows);
+    persistence::write_atomically(path, &content).map_err(|error| Error::io(path, error))?;
+    log::info!("Appended {} games to {}", games.len(), path);
+    Ok(())
+}
+
+/// Formats games in the output format of the configuration, in `layout` for CSV, along with
+/// the counters of the run that generated them for XLSX.
+fn format_games(
+    games: &[game::Game],
+    stats: Option<&GenerationStats>,
+    config: &Config,
+    layout: Layout,
+) -> Result<Vec<u8>, Error> {
+    Ok(match config.output_format {
+        OutputFormat::Csv => games_file::to_csv(
+            games,
+            layout,
+            config.numbers_per_game(),
+            config.csv_delimiter_byte(),
+            config.number_width(),
+            config.stats_range(),
+        ),
+        OutputFormat::Json => games_file::to_json(games, config.min_number, config.stats_range()),
+        OutputFormat::Ndjson => {
+            games_file::to_ndjson(games, config.min_number, 0, config.stats_range())
+        }
+        #[cfg(feature = "xlsx")]
+        OutputFormat::Xlsx => rk_lottery::xlsx::to_xlsx(games, config.numbers_per_game(), stats)
+            .map_err(|error| Error::io(&config.output, std::io::Error::other(error.to_string())))?,
+        #[cfg(not(feature = "xlsx"))]
+        OutputFormat::Xlsx => {
+            let _ = stats;
+            return Err(Error::Config(
+                "output_format xlsx is not supported by this binary, rebuild it with `cargo build --features xlsx`"
+                    .to_string(),
+            ));
+        }
+    })
+}
 
+/// Writes games to a file in the output format of the configuration, in `layout` for CSV.
+///
+/// The games are written in the order given, which for a run is the order they were accepted
+/// in: the initial games first, then the generated ones.
+fn write_games(
+    path: &str,
+    games: &[game::Game],
+    stats: Option<&GenerationStats>,
+    config: &Config,
+    layout: Layout,
+) -> Result<(), Error> {
+    persistence::write_atomically(path, &format_games(games, stats, config, layout)?)
+        .map_err(|error| Error::io(path, error))?;
+    log::info!("Wrote {} games to {}", games.len(), path);
     Ok(())
 }
@@ -0,0 +1,249 @@
+use crate::config::{Config, RngKind};
+use crate::constraints::Relaxation;
+use crate::generation::Generated;
+use crate::persistence;
+use crate::stats::GenerationStats;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File the manifest of a run is written to.
+pub const MANIFEST_FILE: &str = "run_manifest.json";
+
+/// The candidates a run turned down, by reason.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Rejections {
+    /// Number of candidates drawn before.
+    pub duplicate_games: u64,
+    /// Number of candidates sharing a triplet with an accepted game.
+    pub shared_triplets: u64,
+    /// Number of candidates rejected by each constraint, by constraint name.
+    pub constraints: BTreeMap<String, u64>,
+}
+
+/// What is needed to tell how a games file was made and to make it again.
+#[derive(Serialize, Deserialize)]
+pub struct RunManifest {
+    /// Version of the program that made the run.
+    pub version: String,
+    /// When the run ended, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The seed the games were drawn with, `None` when drawn from OS entropy.
+    pub seed: Option<u64>,
+    /// The settings of the run, command line overrides included, with the seed made explicit.
+    pub config: Config,
+    /// Number of candidates checked, by the run and those it resumed.
+    pub attempts: u64,
+    /// The candidates turned down, by the run and those it resumed.
+    pub rejections: Rejections,
+    /// The counters of the run, carried on by a run resuming it; zero for a manifest written
+    /// by an older version.
+    #[serde(default)]
+    pub stats: GenerationStats,
+    /// SHA-256 digest of the output file, in lowercase hexadecimal.
+    pub sha256: String,
+    /// Whether the run was interrupted, the output file holding fewer than `no_of_games` games.
+    #[serde(default)]
+    pub interrupted: bool,
+    /// The batch the output file holds, starting at 1, for a run of several batches.
+    #[serde(default)]
+    pub batch: Option<usize>,
+    /// Whether the games were kept from sharing a triplet; the triplet set of a run whose
+    /// games were not, e.g. drawn by the quick-pick strategy, is written empty.
+    #[serde(default = "default_unique_triplets")]
+    pub unique_triplets: bool,
+    /// The constraints relaxed by `relax_on_exhaustion`, in the order they were.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relaxed: Vec<Relaxation>,
+    /// Whether the games were picked by hand with `--interactive`, as told by the session log.
+    #[serde(default)]
+    pub interactive: bool,
+}
+
+/// Manifests written before games could share triplets are of games that did not.
+fn default_unique_triplets() -> bool {
+    true
+}
+
+impl RunManifest {
+    /// Describes a finished run.
+    ///
+    /// # Arguments
+    /// * `config` - The effective configuration of the run.
+    /// * `generated` - The outcome of the run.
+    /// * `output` - The content of the output file written.
+    pub fn new(config: &Config, generated: &Generated, output: &[u8]) -> Self {
+        let seed = config.effective_seed();
+        let unique_triplets = config.unique_triplets();
+        let mut config = config.clone();
+        if seed.is_some() {
+            config.seed = seed;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            timestamp,
+            seed,
+            config,
+            attempts: generated.stats.attempts,
+            rejections: Rejections {
+                duplicate_games: generated.stats.duplicate_games,
+                shared_triplets: generated.stats.shared_triplets,
+                constraints: generated.stats.constraint_rejections.clone(),
+            },
+            stats: generated.stats.counters(),
+            sha256: sha256_hex(output),
+            interrupted: generated.interrupted,
+            batch: None,
+            unique_triplets,
+            relaxed: generated.constraints.relaxations().to_vec(),
+            interactive: false,
+        }
+    }
+
+    /// Why generating again from the manifest would not give the same games, if it would not.
+    pub fn irreproducible_reason(&self) -> Option<&'static str> {
+        if self.config.rng == RngKind::Os {
+            Some("the games were drawn from OS entropy")
+        } else if self.config.resume {
+            Some("the run resumed a previous one")
+        } else if self.config.append {
+            Some("the run added games to an existing file")
+        } else if self.interactive {
+            Some("the games were picked in an interactive session")
+        } else if self.interrupted {
+            Some("the run was interrupted")
+        } else if self.config.share_triplet_set && self.batch.is_some_and(|batch| batch > 1) {
+            Some("the batch avoided the triplets of the batches before it")
+        } else if self.config.storage.is_some() {
+            Some("the games avoided the triplets of the runs stored before")
+        } else {
+            None
+        }
+    }
+
+    /// Saves the manifest to a file in JSON format.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file where the manifest should be saved.
+    ///
+    /// # Returns
+    /// * `Ok(())` on success.
+    /// * An error if the file could not be written.
+    pub fn save_to_file(&self, filename: &str) -> std::io::Result<()> {
+        let serialized = serde_json::to_string_pretty(self)?;
+        persistence::write_atomically(filename, serialized.as_bytes())
+    }
+
+    /// Loads a manifest saved by `save_to_file`.
+    ///
+    /// # Arguments
+    /// * `filename` - The path to the file to load from.
+    ///
+    /// # Returns
+    /// * The manifest, whose configuration is not validated yet.
+    /// * An error if the file could not be read or parsed.
+    pub fn load_from_file(filename: &str) -> std::io::Result<Self> {
+        let content = fs::read_to_string(filename)?;
+        serde_json::from_str(&content).map_err(persistence::corrupted)
+    }
+}
+
+/// The SHA-256 digest of `bytes`, in lowercase hexadecimal.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::generate;
+    use crate::stats::GenerationStats;
+
+    fn config(extra: &str) -> Config {
+        Config::from_toml(&format!(
+            "no_of_games = 5\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\n{}",
+            extra
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_manifest_records_the_run_and_round_trips() {
+        let config = config("");
+        let generated = generate(&config, GenerationStats::default()).unwrap();
+        let manifest = RunManifest::new(&config, &generated, b"1,2,3,4,5,6\n");
+        assert_eq!(manifest.seed, Some(crate::config::DEFAULT_SEED));
+        assert_eq!(manifest.config.seed, manifest.seed);
+        assert_eq!(manifest.attempts, generated.stats.attempts);
+        assert_eq!(manifest.rejections.constraints.len(), 3);
+        assert_eq!(manifest.irreproducible_reason(), None);
+        assert!(!manifest.interrupted);
+        assert!(manifest.unique_triplets);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(MANIFEST_FILE);
+        let path = path.to_str().unwrap();
+        manifest.save_to_file(path).unwrap();
+        let loaded = RunManifest::load_from_file(path).unwrap();
+        assert_eq!(loaded.config.no_of_games, 5);
+        assert_eq!(loaded.seed, manifest.seed);
+        assert_eq!(loaded.rejections, manifest.rejections);
+        assert_eq!(loaded.stats.attempts, generated.stats.attempts);
+        assert_eq!(loaded.stats.accepted, 5);
+        let elapsed = loaded.stats.elapsed.as_secs_f64() - generated.stats.elapsed.as_secs_f64();
+        assert!(elapsed.abs() < 1e-6);
+        assert_eq!(loaded.sha256, sha256_hex(b"1,2,3,4,5,6\n"));
+        assert!(loaded.config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_quick_pick_runs_note_their_triplets_are_not_tracked() {
+        let config = config("strategy = \"quick-pick\"");
+        let generated = generate(&config, GenerationStats::default()).unwrap();
+        assert!(generated.triplet_set.is_empty());
+        let manifest = RunManifest::new(&config, &generated, b"");
+        assert!(!manifest.unique_triplets);
+        assert_eq!(manifest.irreproducible_reason(), None);
+
+        // Manifests written before hold games kept from sharing a triplet
+        let mut json = serde_json::to_value(&manifest).unwrap();
+        json.as_object_mut().unwrap().remove("unique_triplets");
+        let loaded: RunManifest = serde_json::from_value(json).unwrap();
+        assert!(loaded.unique_triplets);
+    }
+
+    #[test]
+    fn test_entropy_and_resumed_runs_are_not_reproducible() {
+        let generated = generate(&config(""), GenerationStats::default()).unwrap();
+        let manifest = RunManifest::new(&config("rng = \"os\""), &generated, b"");
+        assert_eq!(manifest.seed, None);
+        assert!(manifest.irreproducible_reason().is_some());
+        let manifest = RunManifest::new(&config("resume = true"), &generated, b"");
+        assert!(manifest.irreproducible_reason().is_some());
+        let manifest = RunManifest::new(&config("append = true"), &generated, b"");
+        assert!(manifest.irreproducible_reason().is_some());
+        let stored = Config {
+            storage: Some("sqlite:tickets.db".to_string()),
+            ..config("")
+        };
+        let manifest = RunManifest::new(&stored, &generated, b"");
+        assert!(manifest.irreproducible_reason().is_some());
+        let mut manifest = RunManifest::new(&config(""), &generated, b"");
+        manifest.interactive = true;
+        assert!(manifest.irreproducible_reason().is_some());
+    }
+}
@@ -0,0 +1,332 @@
+use crate::analysis::{self, CoverageStats};
+use crate::config::OptimizeConfig;
+use crate::constraints::ConstraintSet;
+use crate::custom_utils;
+use crate::data_structures::{NumberSet, TripletSet};
+use crate::game::Game;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+
+/// Quality measures of a batch of games.
+#[derive(Debug, PartialEq)]
+pub struct BatchMetrics {
+    /// The largest number of numbers shared by two games.
+    pub max_overlap: usize,
+    /// Number of times a pair of numbers appears in more than one game, counted per pair of games.
+    pub shared_pairs: u64,
+    /// Coverage of the pairs of numbers.
+    pub pairs: CoverageStats,
+    /// Coverage of the triplets of numbers.
+    pub triplets: CoverageStats,
+}
+
+impl fmt::Display for BatchMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "max overlap {}, {} shared pairs, {}, {}",
+            self.max_overlap, self.shared_pairs, self.pairs, self.triplets
+        )
+    }
+}
+
+/// Measures a batch of games.
+///
+/// # Arguments
+/// * `games` - The games to measure.
+/// * `min_number` - The smallest playable number (e.g., 0 or 1).
+/// * `max_number` - The maximum playable number.
+pub fn batch_metrics(games: &[Game], min_number: i64, max_number: i64) -> BatchMetrics {
+    let mut max_overlap = 0;
+    let mut shared_pairs = 0;
+    for (i, a) in games.iter().enumerate() {
+        for b in &games[i + 1..] {
+            let overlap = custom_utils::game_overlap(a, b);
+            max_overlap = max_overlap.max(overlap);
+            shared_pairs += pairs_in(overlap);
+        }
+    }
+    BatchMetrics {
+        max_overlap,
+        shared_pairs,
+        pairs: analysis::coverage_stats(games, 2, min_number, max_number),
+        triplets: analysis::coverage_stats(games, 3, min_number, max_number),
+    }
+}
+
+/// Number of pairs among `overlap` numbers.
+fn pairs_in(overlap: usize) -> u64 {
+    (overlap * overlap.saturating_sub(1) / 2) as u64
+}
+
+/// The combinadic numbers of the triplets of a game.
+fn triplet_nos(game: &[i64], min_number: i64) -> [i64; 20] {
+    custom_utils::game2triplet_enums_from(game, min_number)
+}
+
+/// Improves a batch of games by simulated annealing, reducing the pairs shared between games.
+///
+/// Since no two games may share a triplet, two games overlap by at most 2 numbers and every
+/// game covers 20 triplets of its own: the room left for improvement is in the pairs, so the
+/// energy minimized is `shared_pairs` of `BatchMetrics`. Each iteration either replaces a
+/// number of a game by another one, or swaps a number between two games. A move that breaks a
+/// constraint, repeats a game or repeats a triplet is never accepted; otherwise it is accepted
+/// if it does not raise the energy, or else with probability exp(-increase / temperature). The
+/// temperature decreases geometrically from `initial_temperature` to `final_temperature`.
+///
+/// # Arguments
+/// * `games` - The games to improve, modified in place, their numbers sorted.
+/// * `constraints` - The rules every game must follow.
+/// * `schedule` - The number of iterations and the temperature schedule.
+/// * `seed` - A `u64` seed for reproducible randomness.
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+/// * `min_desired_number` - The minimum number allowed in a game.
+/// * `max_number` - The maximum number in a game (e.g., 60).
+///
+/// # Returns
+/// * The number of accepted moves.
+/// * An error if the games given are not valid to begin with.
+pub fn anneal(
+    games: &mut [Game],
+    constraints: &ConstraintSet,
+    schedule: &OptimizeConfig,
+    seed: u64,
+    min_number: i64,
+    min_desired_number: i64,
+    max_number: i64,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    for game in games.iter_mut() {
+        *game = game.clone().sorted();
+    }
+    let mut game_set = NumberSet::new();
+    let mut triplet_set = TripletSet::default();
+    for (index, game) in games.iter().enumerate() {
+        if let Some(violation) = constraints.first_violation(game) {
+            return Err(format!("Game #{} is invalid: {}", index + 1, violation).into());
+        }
+        if !game_set.add_number(custom_utils::game2enum_from(game, min_number))
+            || !triplet_set.check_and_insert_all(&triplet_nos(game, min_number))
+        {
+            return Err(
+                format!("Game #{} shares a triplet with a previous game", index + 1).into(),
+            );
+        }
+    }
+
+    if games.is_empty() {
+        return Ok(0);
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut accepted = 0;
+    for iteration in 0..schedule.iterations {
+        let progress = iteration as f64 / schedule.iterations.max(1) as f64;
+        let temperature = schedule.initial_temperature
+            * (schedule.final_temperature / schedule.initial_temperature).powf(progress);
+
+        // Propose new contents for one or two games
+        let i = rng.gen_range(0..games.len());
+        let mut changes = vec![(i, games[i].to_vec())];
+        if games.len() > 1 && rng.gen_bool(0.5) {
+            let j = (i + rng.gen_range(1..games.len())) % games.len();
+            let x = games[i][rng.gen_range(0..games[i].len())];
+            let y = games[j][rng.gen_range(0..games[j].len())];
+            if games[j].contains(&x) || games[i].contains(&y) {
+                continue;
+            }
+            let mut other = games[j].to_vec();
+            changes[0]
+                .1
+                .iter_mut()
+                .filter(|n| **n == x)
+                .for_each(|n| *n = y);
+            other.iter_mut().filter(|n| **n == y).for_each(|n| *n = x);
+            changes.push((j, other));
+        } else {
+            let position = rng.gen_range(0..games[i].len());
+            let number = rng.gen_range(min_desired_number..=max_number);
+            if games[i].contains(&number) {
+                continue;
+            }
+            changes[0].1[position] = number;
+        }
+        for (_, numbers) in changes.iter_mut() {
+            numbers.sort_unstable();
+        }
+        if changes
+            .iter()
+            .any(|(_, numbers)| constraints.first_violation(numbers).is_some())
+        {
+            continue;
+        }
+
+        // Energy change, against the games left untouched and between the changed games
+        let changed: Vec<usize> = changes.iter().map(|&(index, _)| index).collect();
+        let mut delta = 0i64;
+        for (index, numbers) in &changes {
+            for (other, game) in games.iter().enumerate() {
+                if !changed.contains(&other) {
+                    delta += pairs_in(custom_utils::game_overlap(numbers, game)) as i64
+                        - pairs_in(custom_utils::game_overlap(&games[*index], game)) as i64;
+                }
+            }
+        }
+        if let [(a, new_a), (b, new_b)] = changes.as_slice() {
+            delta += pairs_in(custom_utils::game_overlap(new_a, new_b)) as i64
+                - pairs_in(custom_utils::game_overlap(&games[*a], &games[*b])) as i64;
+        }
+        if delta > 0 && rng.gen::<f64>() >= (-(delta as f64) / temperature).exp() {
+            continue;
+        }
+
+        // Swap the sets over to the new games, restoring them if uniqueness would be broken
+        for &index in &changed {
+            game_set.remove_number(custom_utils::game2enum_from(&games[index], min_number));
+            for no in triplet_nos(&games[index], min_number) {
+                triplet_set.remove_number(no);
+            }
+        }
+        let mut inserted = Vec::new();
+        let mut unique = true;
+        for (_, numbers) in &changes {
+            let game_no = custom_utils::game2enum_from(numbers, min_number);
+            let nos = triplet_nos(numbers, min_number);
+            if game_set.contains(game_no) || !triplet_set.check_and_insert_all(&nos) {
+                unique = false;
+                break;
+            }
+            game_set.add_number(game_no);
+            inserted.push((game_no, nos));
+        }
+        if !unique {
+            for (game_no, nos) in inserted {
+                game_set.remove_number(game_no);
+                for no in nos {
+                    triplet_set.remove_number(no);
+                }
+            }
+            for &index in &changed {
+                game_set.add_number(custom_utils::game2enum_from(&games[index], min_number));
+                triplet_set.check_and_insert_all(&triplet_nos(&games[index], min_number));
+            }
+            continue;
+        }
+
+        for (index, numbers) in changes {
+            games[index] = Game::new(numbers);
+        }
+        accepted += 1;
+    }
+    Ok(accepted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::{DistinctConstraint, LengthConstraint, RangeConstraint};
+    use crate::generation::{self, CandidateSource};
+
+    /// The standard constraints for games of 6 numbers between 41 and 60.
+    fn constraints() -> ConstraintSet {
+        ConstraintSet::new()
+            .with(LengthConstraint {
+                numbers_per_game: 6,
+            })
+            .with(RangeConstraint::new(41, 60))
+            .with(DistinctConstraint)
+    }
+
+    /// A random batch of valid games with numbers 41 to 60.
+    fn random_games(no_of_games: usize) -> Vec<Game> {
+        let mut games = Vec::new();
+        generation::generate_remaining_games(
+            &mut games,
+            &mut NumberSet::new(),
+            &mut TripletSet::default(),
+            &mut constraints(),
+            &mut CandidateSource::new(3, 41, 60, 6, 100, 0.95),
+            no_of_games,
+            &mut crate::stats::GenerationStats::default(),
+        )
+        .unwrap();
+        games
+    }
+
+    fn schedule(iterations: u64) -> OptimizeConfig {
+        OptimizeConfig {
+            iterations,
+            ..OptimizeConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_batch_metrics() {
+        let games = vec![
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![1, 2, 7, 8, 9, 10]),
+            Game::new(vec![1, 11, 12, 13, 14, 15]),
+        ];
+        let metrics = batch_metrics(&games, 1, 15);
+        assert_eq!(metrics.max_overlap, 2);
+        assert_eq!(metrics.shared_pairs, 1); // Only 1-2 appears twice
+        assert_eq!(metrics.pairs.covered, 44); // 3 * 15 - 1
+        assert_eq!(metrics.triplets.covered, 60);
+    }
+
+    #[test]
+    fn test_anneal_reduces_shared_pairs_and_keeps_games_valid() {
+        let mut games = random_games(15);
+        let before = batch_metrics(&games, 1, 60);
+        let accepted = anneal(&mut games, &constraints(), &schedule(20_000), 1, 1, 41, 60).unwrap();
+        let after = batch_metrics(&games, 1, 60);
+
+        assert!(accepted > 0);
+        assert!(
+            after.shared_pairs < before.shared_pairs,
+            "{} / {}",
+            after,
+            before
+        );
+        assert_eq!(after.triplets.covered, 15 * 20);
+        assert_eq!(games.len(), 15);
+
+        // Every game is still valid, sorted, and no triplet is repeated
+        let mut triplet_set = TripletSet::default();
+        for game in &games {
+            assert!(constraints().first_violation(game).is_none());
+            assert!(game.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(triplet_set.check_and_insert_all(&triplet_nos(game, 1)));
+        }
+    }
+
+    #[test]
+    fn test_anneal_is_reproducible() {
+        let mut first = random_games(10);
+        let mut second = first.clone();
+        anneal(&mut first, &constraints(), &schedule(2_000), 5, 1, 41, 60).unwrap();
+        anneal(&mut second, &constraints(), &schedule(2_000), 5, 1, 41, 60).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_anneal_rejects_invalid_batches() {
+        let mut shared = vec![
+            Game::new(vec![41, 42, 43, 44, 45, 46]),
+            Game::new(vec![41, 42, 43, 54, 55, 56]),
+        ];
+        assert!(anneal(&mut shared, &constraints(), &schedule(10), 1, 1, 41, 60).is_err());
+
+        let mut out_of_range = vec![Game::new(vec![1, 42, 43, 44, 45, 46])];
+        assert!(anneal(
+            &mut out_of_range,
+            &constraints(),
+            &schedule(10),
+            1,
+            1,
+            41,
+            60
+        )
+        .is_err());
+    }
+}
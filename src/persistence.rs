@@ -0,0 +1,203 @@
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// First bytes of a gzip stream, by which `read_file` recognizes a compressed file.
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether the files written to `path` are compressed, that is whether it ends with `.gz`.
+pub fn is_gzip_path(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+}
+
+/// Replaces the content of a file without ever leaving it partly written: the content goes to
+/// a temporary file in the same directory, flushed to disk, then renamed over `path`. If
+/// anything fails on the way, `path` keeps its previous content (or stays missing). A `path`
+/// ending with `.gz` gets the content compressed with gzip.
+///
+/// # Arguments
+/// * `path` - The file to write.
+/// * `contents` - Its new content.
+///
+/// # Returns
+/// * `Ok(())` once the new content is in place.
+/// * An error if the temporary file could not be written or renamed.
+pub fn write_atomically(path: impl AsRef<Path>, contents: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    if is_gzip_path(path) {
+        replace_with(path, |file| {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            encoder.write_all(contents)?;
+            encoder.finish().map(drop)
+        })
+    } else {
+        replace_with(path, |file| file.write_all(contents))
+    }
+}
+
+/// Reads a whole file, decompressing it when it starts with the `GZIP_MAGIC` bytes, whatever
+/// its name, so that a compressed file still opens once renamed.
+///
+/// # Arguments
+/// * `path` - The file to read.
+///
+/// # Returns
+/// * The content of the file, decompressed if need be.
+/// * An error if the file cannot be read, or of kind `InvalidData` if it is not a valid gzip
+///   stream although it starts like one.
+pub fn read_file(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    decompress(fs::read(path)?)
+}
+
+/// Reads a whole file like `read_file`, as text.
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    String::from_utf8(read_file(path)?).map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("file is not valid UTF-8: {}", error),
+        )
+    })
+}
+
+/// Opens a file for reading as it goes, decompressing it when it starts with the `GZIP_MAGIC`
+/// bytes like `read_file` does.
+///
+/// # Arguments
+/// * `path` - The file to read.
+///
+/// # Returns
+/// * A buffered reader of the content of the file, decompressed if need be.
+/// * An error if the file cannot be opened.
+pub fn open(path: impl AsRef<Path>) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Decompresses `contents` if it is a gzip stream, or gives it back unchanged otherwise.
+pub fn decompress(contents: Vec<u8>) -> io::Result<Vec<u8>> {
+    if !contents.starts_with(&GZIP_MAGIC) {
+        return Ok(contents);
+    }
+    let mut decompressed = Vec::new();
+    MultiGzDecoder::new(contents.as_slice())
+        .read_to_end(&mut decompressed)
+        .map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("file is not a valid gzip stream: {}", error),
+            )
+        })?;
+    Ok(decompressed)
+}
+
+/// Replaces the content of `path` with what `write` puts in a temporary file, as described in
+/// `write_atomically`.
+fn replace_with(path: &Path, write: impl FnOnce(&mut File) -> io::Result<()>) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    // The temporary file is removed when dropped, if it was not renamed
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    write(tmp.as_file_mut())?;
+    tmp.as_file().sync_all()?;
+    tmp.persist(path).map_err(|error| error.error)?;
+    Ok(())
+}
+
+/// Describes a JSON file that cannot be parsed, usually because a write was interrupted and
+/// the file ends early.
+pub fn corrupted(error: serde_json::Error) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("file appears corrupted: {}", error),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Writes half of `contents` then fails, as a crash or a full disk would.
+    fn interrupted(file: &mut File, contents: &[u8]) -> io::Result<()> {
+        file.write_all(&contents[..contents.len() / 2])?;
+        Err(io::Error::other("interrupted"))
+    }
+
+    #[test]
+    fn test_interrupted_write_keeps_the_old_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("games.csv");
+        let old = b"[1,2,3,4,5,6,7,8,9,10]";
+        let new = b"[11,12,13,14,15,16,17,18,19,20]";
+
+        // Writing in place leaves a truncated file behind
+        fs::write(&path, old).unwrap();
+        let mut file = File::create(&path).unwrap();
+        assert!(interrupted(&mut file, new).is_err());
+        assert_eq!(fs::read(&path).unwrap(), &new[..new.len() / 2]);
+
+        // Replacing leaves either the old or the new complete file, and no temporary file
+        fs::write(&path, old).unwrap();
+        assert!(replace_with(&path, |file| interrupted(file, new)).is_err());
+        assert_eq!(fs::read(&path).unwrap(), old);
+        write_atomically(&path, new).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), new);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_gz_files_are_compressed_and_read_back_whatever_their_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("games.csv");
+        let compressed = dir.path().join("games.csv.GZ");
+        let contents: Vec<u8> = (0..2_000)
+            .flat_map(|row| format!("{}\n", row).into_bytes())
+            .collect();
+
+        write_atomically(&plain, &contents).unwrap();
+        write_atomically(&compressed, &contents).unwrap();
+        assert_eq!(fs::read(&plain).unwrap(), contents);
+        assert!(fs::read(&compressed).unwrap().starts_with(&GZIP_MAGIC));
+        assert_eq!(read_file(&plain).unwrap(), contents);
+        assert_eq!(read_file(&compressed).unwrap(), contents);
+
+        let renamed = dir.path().join("games.csv");
+        fs::rename(&compressed, &renamed).unwrap();
+        assert_eq!(read_to_string(&renamed).unwrap().lines().count(), 2_000);
+        assert_eq!(open(&renamed).unwrap().lines().count(), 2_000);
+    }
+
+    #[test]
+    fn test_corrupted_gzip_stream_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("triplet_set.log.gz");
+        write_atomically(&path, &[b'7'; 1_000]).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.truncate(bytes.len() / 2);
+        fs::write(&path, &bytes).unwrap();
+        let error = read_file(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error
+            .to_string()
+            .starts_with("file is not a valid gzip stream: "));
+    }
+
+    #[test]
+    fn test_truncated_json_is_reported_as_corrupted() {
+        let error = serde_json::from_str::<Vec<i64>>("[1,2,3").unwrap_err();
+        assert!(corrupted(error)
+            .to_string()
+            .starts_with("file appears corrupted: "));
+    }
+}
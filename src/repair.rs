@@ -0,0 +1,265 @@
+use crate::constraints::{ConstraintSet, DistinctConstraint, LengthConstraint, RangeConstraint};
+use crate::game::Game;
+use crate::games_file::{self, Layout};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+/// A change made to a file of games, with the rows involved (starting at 1).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fix {
+    /// A row repeating the game of an earlier row, numbers in any order, dropped.
+    Duplicate { row: usize, first_row: usize },
+    /// A row that cannot be read or is not a playable game, moved to the rejects file.
+    Rejected {
+        row: usize,
+        /// The row as in the file, its fields joined by the delimiter of the file.
+        record: String,
+        reason: String,
+    },
+    /// A row whose numbers were put in increasing order.
+    Sorted { row: usize },
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fix::Duplicate { row, first_row } => {
+                write!(f, "row {} repeats row {}, dropped", row, first_row)
+            }
+            Fix::Rejected { row, reason, .. } => write!(f, "row {} rejected: {}", row, reason),
+            Fix::Sorted { row } => write!(f, "row {} sorted", row),
+        }
+    }
+}
+
+/// The outcome of repairing a file of games.
+#[derive(Debug)]
+pub struct Repair {
+    /// The layout of the file, `None` when it holds no row at all.
+    pub layout: Option<Layout>,
+    /// The field separator of the file, for the repaired file and the rejects to keep.
+    pub delimiter: u8,
+    /// Whether the header row names the columns of the figures of the games, for the repaired
+    /// file to write them again.
+    pub stats_columns: bool,
+    /// Number of rows read.
+    pub rows: usize,
+    /// The games kept, in file order, numbers sorted.
+    pub games: Vec<Game>,
+    /// The changes made, in row order.
+    pub fixes: Vec<Fix>,
+}
+
+impl Repair {
+    /// Whether the repaired games differ from the file.
+    pub fn changed(&self) -> bool {
+        !self.fixes.is_empty()
+    }
+
+    /// The rejected rows as a CSV file: a `row,reason,numbers` header, then each row with the
+    /// row as in the file, quoted, in its `numbers` field.
+    pub fn rejects_csv(&self) -> Vec<u8> {
+        let mut wtr = csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(Vec::new());
+        wtr.write_record(["row", "reason", "numbers"])
+            .expect("writing to memory cannot fail");
+        for fix in &self.fixes {
+            if let Fix::Rejected {
+                row,
+                record,
+                reason,
+            } = fix
+            {
+                wtr.write_record([&row.to_string(), reason, record])
+                    .expect("writing to memory cannot fail");
+            }
+        }
+        wtr.into_inner().expect("writing to memory cannot fail")
+    }
+}
+
+/// Cleans up a CSV file of games in either layout: rows that cannot be read or are not
+/// playable games (wrong number of numbers, numbers out of `min_number..=max_number` or
+/// appearing twice) are rejected, rows repeating an earlier game are dropped, and the
+/// numbers of the remaining games are sorted.
+///
+/// Only the structure of the games is checked: games breaking the other constraints, or
+/// sharing a triplet, are real tickets and are kept, `validate` reports them.
+///
+/// # Arguments
+/// * `reader` - The CSV content.
+/// * `numbers_per_game` - The number of numbers of a game (e.g., 6).
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+/// * `max_number` - The largest number of the lottery (e.g., 60).
+/// * `delimiter` - The field separator expected, unless the first row tells another one.
+///
+/// # Returns
+/// * The games kept and the changes made.
+pub fn repair_csv<R: io::BufRead>(
+    reader: R,
+    numbers_per_game: usize,
+    min_number: i64,
+    max_number: i64,
+    delimiter: u8,
+) -> Repair {
+    let constraints = ConstraintSet::new()
+        .with(LengthConstraint { numbers_per_game })
+        .with(RangeConstraint::new(min_number, max_number))
+        .with(DistinctConstraint);
+    let mut rows = games_file::Rows::new(reader, delimiter);
+    let mut games = Vec::new();
+    let mut fixes = Vec::new();
+    // The row of each game kept, by its sorted numbers
+    let mut first_rows: HashMap<Vec<i64>, usize> = HashMap::new();
+    let mut row = 0;
+    while let Some(game) = rows.next() {
+        row += 1;
+        // The row as in the file, for the rejects file to keep what is to be fixed
+        let record = || {
+            let fields: Vec<&str> = rows.fields().iter().collect();
+            fields.join(&char::from(rows.delimiter()).to_string())
+        };
+        let game = match game {
+            Ok(game) => game,
+            Err(reason) => {
+                fixes.push(Fix::Rejected {
+                    row,
+                    record: record(),
+                    reason,
+                });
+                continue;
+            }
+        };
+
+        let violations = constraints.violations(&game);
+        if !violations.is_empty() {
+            let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+            fixes.push(Fix::Rejected {
+                row,
+                record: record(),
+                reason: violations.join("; "),
+            });
+            continue;
+        }
+
+        let sorted = game.clone().sorted();
+        if let Some(&first_row) = first_rows.get(sorted.numbers()) {
+            fixes.push(Fix::Duplicate { row, first_row });
+            continue;
+        }
+        if sorted != game {
+            fixes.push(Fix::Sorted { row });
+        }
+        first_rows.insert(sorted.numbers().to_vec(), row);
+        games.push(sorted);
+    }
+    Repair {
+        layout: rows.layout(),
+        delimiter: rows.delimiter(),
+        stats_columns: rows.stats_columns(),
+        rows: row,
+        games,
+        fixes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repair(content: &str) -> Repair {
+        repair_csv(content.as_bytes(), 6, 1, 60, games_file::DEFAULT_DELIMITER)
+    }
+
+    #[test]
+    fn test_clean_file_is_unchanged() {
+        let repair = repair("game,n1,n2,n3,n4,n5,n6\n1,1,2,3,4,5,6\n2,1,2,3,7,8,9\n");
+        assert_eq!(repair.layout, Some(Layout::Indexed));
+        assert_eq!(repair.rows, 2);
+        assert_eq!(repair.games.len(), 2);
+        assert!(!repair.changed(), "{:?}", repair.fixes);
+    }
+
+    #[test]
+    fn test_duplicates_are_dropped_in_any_order() {
+        let repair = repair("1,2,3,4,5,6\n7,8,9,10,11,12\n1,2,3,4,5,6\n12,11,10,9,8,7\n");
+        assert_eq!(repair.games.len(), 2);
+        assert_eq!(
+            repair.fixes,
+            [
+                Fix::Duplicate {
+                    row: 3,
+                    first_row: 1
+                },
+                Fix::Duplicate {
+                    row: 4,
+                    first_row: 2
+                },
+            ]
+        );
+        assert_eq!(repair.fixes[0].to_string(), "row 3 repeats row 1, dropped");
+    }
+
+    #[test]
+    fn test_each_structural_defect_is_rejected() {
+        let repair = repair("1,2,3,4,5\n1,2,3,4,5,61\n1,2,3,4,5,5\n0,2,3,4,5,6\n1,2,x,4,5,6\n");
+        assert!(repair.games.is_empty());
+        let reasons: Vec<String> = repair.fixes.iter().map(|fix| fix.to_string()).collect();
+        assert_eq!(
+            reasons[..4],
+            [
+                "row 1 rejected: game has 5 numbers instead of 6",
+                "row 2 rejected: number 61 is above the maximum playable number",
+                "row 3 rejected: number 5 appears more than once",
+                "row 4 rejected: number 0 is below the minimum desired number",
+            ]
+        );
+        assert_eq!(
+            String::from_utf8(repair.rejects_csv())
+                .unwrap()
+                .lines()
+                .collect::<Vec<_>>()[..3],
+            [
+                "row,reason,numbers",
+                "1,game has 5 numbers instead of 6,\"1,2,3,4,5\"",
+                "2,number 61 is above the maximum playable number,\"1,2,3,4,5,61\"",
+            ]
+        );
+        assert_eq!(
+            reasons[4],
+            "row 5 rejected: field 3 (\"x\") is not a number: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn test_rejected_rows_are_kept_as_in_the_file() {
+        let repair = repair("game,n1,n2,n3,n4,n5,n6\n1,1,2,3,4,5,6\n2,1, 2,x,4,5,6\n3,1,2,3\n");
+        let rejects = String::from_utf8(repair.rejects_csv()).unwrap();
+        let mut rdr = csv::Reader::from_reader(rejects.as_bytes());
+        let records: Vec<Vec<String>> = rdr
+            .records()
+            .map(|record| record.unwrap().iter().map(String::from).collect())
+            .collect();
+        assert_eq!(
+            records,
+            [
+                [
+                    "2",
+                    "field 4 (\"x\") is not a number: invalid digit found in string",
+                    "2,1, 2,x,4,5,6"
+                ],
+                ["3", "game has 3 numbers instead of 6", "3,1,2,3"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_numbers_are_sorted() {
+        let repair = repair("6,5,4,3,2,1\n7,8,9,10,11,12\n");
+        assert_eq!(repair.games[0].numbers(), &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(repair.fixes, [Fix::Sorted { row: 1 }]);
+        assert!(repair.changed());
+    }
+}
@@ -0,0 +1,233 @@
+use crate::analysis::CoverageStats;
+use crate::custom_utils;
+use crate::game::Game;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// A run of an archive, as told by its manifest and its output file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArchivedRun {
+    /// The manifest of the run.
+    pub manifest: String,
+    /// The output file of the run, found from the directory of its manifest.
+    pub output: String,
+    /// The seed the games were drawn with, `None` when drawn from OS entropy.
+    pub seed: Option<u64>,
+    /// When the run ended, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Number of games of the output file.
+    pub games: usize,
+    /// Whether the output file still has the SHA-256 digest recorded in the manifest.
+    pub matches_manifest: bool,
+}
+
+/// An entry of an archive left out of the report, with the reason.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkippedEntry {
+    /// The manifest of the entry.
+    pub manifest: String,
+    pub reason: String,
+}
+
+/// Two games of different runs sharing triplets, the games of a run being kept from sharing
+/// one with each other only, with their rows (starting at 1).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunCollision {
+    /// The manifest of the earlier run.
+    pub manifest_a: String,
+    pub row_a: usize,
+    /// The manifest of the later run.
+    pub manifest_b: String,
+    pub row_b: usize,
+    /// The triplets the two games share, sorted.
+    pub triplets: Vec<Vec<i64>>,
+}
+
+impl fmt::Display for RunCollision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let triplets: Vec<String> = self
+            .triplets
+            .iter()
+            .map(|triplet| Game::new(triplet.clone()).to_string())
+            .collect();
+        write!(
+            f,
+            "{} game #{} and {} game #{} share {}",
+            self.manifest_a,
+            self.row_a,
+            self.manifest_b,
+            self.row_b,
+            triplets.join(", ")
+        )
+    }
+}
+
+/// The games of the runs of an archive taken together: how many there are, the triplets they
+/// cover, the numbers none plays and the triplets shared by games of different runs.
+#[derive(Debug, Serialize)]
+pub struct ArchiveReport {
+    /// The runs added, in order.
+    pub runs: Vec<ArchivedRun>,
+    /// The entries left out, in order.
+    pub skipped: Vec<SkippedEntry>,
+    /// Number of games of every run.
+    pub games: usize,
+    /// The distinct triplets of the games of every run.
+    pub coverage: CoverageStats,
+    /// The numbers of the lottery no game plays, in increasing order.
+    pub never_played: Vec<i64>,
+    /// The pairs of games of different runs sharing triplets, by run then row of the later
+    /// game.
+    pub collisions: Vec<RunCollision>,
+    #[serde(skip)]
+    min_number: i64,
+    /// Games playing each number of the lottery.
+    #[serde(skip)]
+    plays: Vec<usize>,
+    /// The run (its index in `runs`) and row of the first game holding each triplet.
+    #[serde(skip)]
+    owners: HashMap<i64, (usize, usize)>,
+}
+
+impl ArchiveReport {
+    /// Creates an empty report for the numbers of `min_number..=max_number`.
+    pub fn new(min_number: i64, max_number: i64) -> Self {
+        let span = (max_number - min_number + 1).max(0);
+        Self {
+            runs: Vec::new(),
+            skipped: Vec::new(),
+            games: 0,
+            coverage: CoverageStats {
+                subset_size: 3,
+                covered: 0,
+                total: custom_utils::binomial(span, 3) as u64,
+            },
+            never_played: (min_number..=max_number).collect(),
+            collisions: Vec::new(),
+            min_number,
+            plays: vec![0; span as usize],
+            owners: HashMap::new(),
+        }
+    }
+
+    /// Adds the games of a run, reporting those sharing triplets with games of the runs added
+    /// before it. Numbers outside the lottery are left out of the counts.
+    ///
+    /// # Arguments
+    /// * `run` - The run, whose `games` is set to the number of `games`.
+    /// * `games` - The games of its output file, in order.
+    pub fn add(&mut self, mut run: ArchivedRun, games: &[Game]) {
+        let index = self.runs.len();
+        for (row, game) in games.iter().enumerate() {
+            for &number in game.iter() {
+                if let Some(plays) = usize::try_from(number - self.min_number)
+                    .ok()
+                    .and_then(|offset| self.plays.get_mut(offset))
+                {
+                    *plays += 1;
+                }
+            }
+            let mut numbers = game.to_vec();
+            numbers.sort_unstable();
+            // The triplets of this game held by earlier runs, by run and row of their game
+            let mut shared: BTreeMap<(usize, usize), Vec<Vec<i64>>> = BTreeMap::new();
+            custom_utils::for_each_ksubset(&numbers, 3, |triplet| {
+                let Ok(rank) = custom_utils::subset2enum_from(triplet, 3, self.min_number) else {
+                    return;
+                };
+                if rank >= self.coverage.total as i64 {
+                    return;
+                }
+                let owner = *self.owners.entry(rank).or_insert((index, row));
+                if owner.0 != index {
+                    shared.entry(owner).or_default().push(triplet.to_vec());
+                }
+            });
+            for ((earlier, earlier_row), triplets) in shared {
+                self.collisions.push(RunCollision {
+                    manifest_a: self.runs[earlier].manifest.clone(),
+                    row_a: earlier_row + 1,
+                    manifest_b: run.manifest.clone(),
+                    row_b: row + 1,
+                    triplets,
+                });
+            }
+        }
+        run.games = games.len();
+        self.games += games.len();
+        self.coverage.covered = self.owners.len() as u64;
+        self.never_played = (self.min_number..)
+            .zip(&self.plays)
+            .filter(|(_, &plays)| plays == 0)
+            .map(|(number, _)| number)
+            .collect();
+        self.runs.push(run);
+    }
+
+    /// Leaves an entry out of the report, telling why.
+    pub fn skip(&mut self, manifest: impl Into<String>, reason: impl Into<String>) {
+        self.skipped.push(SkippedEntry {
+            manifest: manifest.into(),
+            reason: reason.into(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(manifest: &str) -> ArchivedRun {
+        ArchivedRun {
+            manifest: manifest.to_string(),
+            output: "optimized_games.csv".to_string(),
+            seed: Some(1),
+            timestamp: 0,
+            games: 0,
+            matches_manifest: true,
+        }
+    }
+
+    #[test]
+    fn test_runs_add_up_and_collide_across_runs_only() {
+        let mut report = ArchiveReport::new(1, 10);
+        assert_eq!(report.never_played, (1..=10).collect::<Vec<_>>());
+        // The games of a run of quick-picks may share triplets with each other
+        report.add(
+            run("january.json"),
+            &[
+                Game::new(vec![1, 2, 3, 4, 5, 6]),
+                Game::new(vec![1, 2, 3, 7, 8, 9]),
+            ],
+        );
+        assert!(report.collisions.is_empty());
+        assert_eq!(report.coverage.covered, 39);
+        report.add(
+            run("february.json"),
+            &[
+                Game::new(vec![9, 8, 7, 6, 5, 4]),
+                Game::new(vec![1, 4, 7, 8, 9, 10]),
+            ],
+        );
+        report.skip("broken.json", "cannot read the manifest");
+
+        assert_eq!(report.games, 4);
+        assert_eq!(report.runs[1].games, 2);
+        assert_eq!(report.never_played, Vec::<i64>::new());
+        assert_eq!(report.coverage.total, 120);
+        assert_eq!(
+            report
+                .collisions
+                .iter()
+                .map(|collision| collision.to_string())
+                .collect::<Vec<_>>(),
+            [
+                "january.json game #1 and february.json game #1 share 04-05-06",
+                "january.json game #2 and february.json game #1 share 07-08-09",
+                "january.json game #2 and february.json game #2 share 01-07-08, 01-07-09, 01-08-09, 07-08-09",
+            ]
+        );
+        assert_eq!(report.skipped[0].manifest, "broken.json");
+    }
+}
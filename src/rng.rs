@@ -0,0 +1,313 @@
+use rand::rngs::{OsRng, StdRng};
+use rand::{Error, RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Increment of the counter of `CounterRng` between two words, the golden ratio as a 64-bit
+/// fraction, as in SplitMix64.
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// A random number generator that can skip the next 32-bit words it would give.
+///
+/// The default redraws and drops them, which any generator can do; a counter-based generator
+/// jumps directly.
+pub trait FastForward: RngCore {
+    /// Skips the next `words` 32-bit words, as if they had been drawn one at a time with
+    /// `next_u32` and dropped.
+    fn fast_forward(&mut self, words: u64) {
+        for _ in 0..words {
+            self.next_u32();
+        }
+    }
+}
+
+/// The standard generator draws its words from blocks, `next_u64` taking two of them and
+/// `fill_bytes` one per 4 bytes, so redrawing single words gets to the same state.
+impl FastForward for StdRng {}
+
+/// Skipping entropy only costs time; the words drawn next are unpredictable anyway.
+impl FastForward for OsRng {}
+
+/// A counter-based generator: word `i` is the SplitMix64 mix of the seed and `i`, so that
+/// skipping words is a matter of adding to the counter.
+///
+/// Its words are not those of the standard generator, so the games drawn for a seed are not
+/// either; it is picked with `rng = "counter"`.
+///
+/// # Examples
+/// ```
+/// use rand::{RngCore, SeedableRng};
+/// use rk_lottery::rng::{CounterRng, FastForward};
+///
+/// let mut drawn = CounterRng::seed_from_u64(7);
+/// let mut skipped = drawn.clone();
+/// for _ in 0..1_000_000 {
+///     drawn.next_u32();
+/// }
+/// skipped.fast_forward(1_000_000);
+/// assert_eq!(skipped.next_u64(), drawn.next_u64());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CounterRng {
+    /// The seed, mixed once so that close seeds give unrelated words.
+    key: u64,
+    /// Index of the next word.
+    counter: u64,
+}
+
+impl CounterRng {
+    /// The SplitMix64 finalizer, mixing the bits of `z`.
+    fn mix(mut z: u64) -> u64 {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl SeedableRng for CounterRng {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self {
+            key: Self::mix(u64::from_le_bytes(seed)),
+            counter: 0,
+        }
+    }
+
+    fn seed_from_u64(seed: u64) -> Self {
+        Self::from_seed(seed.to_le_bytes())
+    }
+}
+
+impl RngCore for CounterRng {
+    fn next_u32(&mut self) -> u32 {
+        let word = Self::mix(
+            self.key
+                .wrapping_add(self.counter.wrapping_add(1).wrapping_mul(GOLDEN_GAMMA)),
+        );
+        self.counter = self.counter.wrapping_add(1);
+        (word >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = u64::from(self.next_u32());
+        (u64::from(self.next_u32()) << 32) | low
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let word = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&word[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl FastForward for CounterRng {
+    fn fast_forward(&mut self, words: u64) {
+        self.counter = self.counter.wrapping_add(words);
+    }
+}
+
+/// Wraps a random number generator, counting the 32-bit words it gives: one for `next_u32`,
+/// two for `next_u64` and one per 4 bytes, rounded up, for `fill_bytes`. The words themselves
+/// are those of the generator, unchanged.
+///
+/// A generator of the same seed fast-forwarded by the count carries on where this one is.
+///
+/// # Examples
+/// ```
+/// use rand::rngs::StdRng;
+/// use rand::{Rng, SeedableRng};
+/// use rk_lottery::rng::CountingRng;
+///
+/// let mut rng = CountingRng::new(StdRng::seed_from_u64(1));
+/// let _: f64 = rng.gen();
+/// let _: u32 = rng.gen();
+/// assert_eq!(rng.draws(), 3);
+///
+/// let mut replay = CountingRng::new(StdRng::seed_from_u64(1));
+/// replay.fast_forward(rng.draws());
+/// assert_eq!(replay.gen::<u64>(), rng.gen::<u64>());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CountingRng<R> {
+    rng: R,
+    /// Number of words given so far, those skipped included.
+    draws: u64,
+}
+
+impl<R: RngCore> CountingRng<R> {
+    /// Counts the words of `rng` from now on.
+    pub fn new(rng: R) -> Self {
+        Self { rng, draws: 0 }
+    }
+
+    /// Returns the number of 32-bit words given so far, those skipped included.
+    pub fn draws(&self) -> u64 {
+        self.draws
+    }
+
+    /// Skips the next `words` words, counting them; see `FastForward`.
+    pub fn fast_forward(&mut self, words: u64)
+    where
+        R: FastForward,
+    {
+        self.rng.fast_forward(words);
+        self.draws += words;
+    }
+}
+
+impl<R: RngCore> RngCore for CountingRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        self.draws += 1;
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.draws += 2;
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.draws += dest.len().div_ceil(4) as u64;
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.draws += dest.len().div_ceil(4) as u64;
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// A `CountingRng` shared by handles, e.g. one moved into the closure drawing the games and
+/// one kept to read the count and to skip ahead, through `DrawCounter`.
+pub struct SharedRng<R>(Rc<RefCell<CountingRng<R>>>);
+
+impl<R: RngCore> SharedRng<R> {
+    /// Shares `rng`, counting its words from now on.
+    pub fn new(rng: R) -> Self {
+        Self(Rc::new(RefCell::new(CountingRng::new(rng))))
+    }
+}
+
+impl<R> Clone for SharedRng<R> {
+    fn clone(&self) -> Self {
+        Self(Rc::clone(&self.0))
+    }
+}
+
+impl<R: RngCore> RngCore for SharedRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        self.0.borrow_mut().next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.borrow_mut().next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.borrow_mut().fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.0.borrow_mut().try_fill_bytes(dest)
+    }
+}
+
+/// The count of a generator drawn from elsewhere, whatever its type.
+pub trait DrawCounter {
+    /// Returns the number of 32-bit words given so far, those skipped included.
+    fn draws(&self) -> u64;
+
+    /// Skips the next `words` words, counting them.
+    fn fast_forward(&self, words: u64);
+}
+
+impl<R: FastForward> DrawCounter for SharedRng<R> {
+    fn draws(&self) -> u64 {
+        self.0.borrow().draws()
+    }
+
+    fn fast_forward(&self, words: u64) {
+        self.0.borrow_mut().fast_forward(words)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    /// Draws from `rng` as the game generators do: words, pairs of words and bytes.
+    fn draw_mixed(rng: &mut impl RngCore, rounds: usize) -> Vec<u64> {
+        (0..rounds)
+            .flat_map(|round| {
+                let mut bytes = [0; 8];
+                rng.fill_bytes(&mut bytes[..round % 9]);
+                [
+                    u64::from(rng.next_u32()),
+                    rng.next_u64(),
+                    rng.gen_range(0..50_063_860),
+                    u64::from_le_bytes(bytes),
+                ]
+            })
+            .collect()
+    }
+
+    /// Checks that fast-forwarding a fresh generator by the words drawn from another of the
+    /// same seed gives the values it gives next.
+    fn assert_fast_forward_skips_the_draws<R: FastForward + Clone>(rng: R) {
+        for rounds in [0, 1, 5, 37, 300] {
+            let mut drawn = CountingRng::new(rng.clone());
+            draw_mixed(&mut drawn, rounds);
+            let mut skipped = CountingRng::new(rng.clone());
+            skipped.fast_forward(drawn.draws());
+            assert_eq!(skipped.draws(), drawn.draws());
+            assert_eq!(draw_mixed(&mut skipped, 20), draw_mixed(&mut drawn, 20));
+        }
+    }
+
+    #[test]
+    fn test_fast_forward_equals_drawing_and_dropping() {
+        assert_fast_forward_skips_the_draws(StdRng::seed_from_u64(2925));
+        assert_fast_forward_skips_the_draws(CounterRng::seed_from_u64(2925));
+
+        // Counting leaves the values of the generator as they are
+        let mut counted = CountingRng::new(StdRng::seed_from_u64(5));
+        assert_eq!(
+            draw_mixed(&mut counted, 10),
+            draw_mixed(&mut StdRng::seed_from_u64(5), 10)
+        );
+
+        // The handles of a shared generator draw from it and count together
+        let shared = SharedRng::new(CounterRng::seed_from_u64(5));
+        let mut handle = shared.clone();
+        handle.next_u64();
+        assert_eq!(DrawCounter::draws(&shared), 2);
+        DrawCounter::fast_forward(&shared, 3);
+        let mut expected = CounterRng::seed_from_u64(5);
+        expected.fast_forward(5);
+        assert_eq!(handle.next_u32(), expected.next_u32());
+    }
+
+    #[test]
+    fn test_counter_words_are_spread_and_differ_by_seed() {
+        let mut rng = CounterRng::seed_from_u64(1);
+        let mut counts = [0; 10];
+        for _ in 0..100_000 {
+            counts[rng.gen_range(0..10)] += 1;
+        }
+        assert!(
+            counts.iter().all(|&count| (9_500..10_500).contains(&count)),
+            "{:?}",
+            counts
+        );
+        let mut other = CounterRng::seed_from_u64(2);
+        assert_ne!(CounterRng::seed_from_u64(1).next_u64(), other.next_u64());
+    }
+}
@@ -0,0 +1,430 @@
+use crate::config::{Lottery, PrizesConfig};
+use crate::custom_utils;
+use crate::game::{Game, GameMask};
+use rand::Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The prize tiers of the Mega-Sena, by number of matches.
+pub const PRIZE_TIERS: [(usize, &str); 3] = [(4, "quadra"), (5, "quina"), (6, "sena")];
+
+/// The prize tiers of the Dupla Sena, which pays the terno too, by number of matches.
+pub const DUPLA_SENA_PRIZE_TIERS: [(usize, &str); 4] =
+    [(3, "terno"), (4, "quadra"), (5, "quina"), (6, "sena")];
+
+/// The prize tiers of the Lotofácil, named by their matches like the results of Caixa.
+pub const LOTOFACIL_PRIZE_TIERS: [(usize, &str); 5] = [
+    (11, "11 acertos"),
+    (12, "12 acertos"),
+    (13, "13 acertos"),
+    (14, "14 acertos"),
+    (15, "15 acertos"),
+];
+
+/// The prize tiers of `lottery`, by number of matches.
+pub fn prize_tiers(lottery: Lottery) -> &'static [(usize, &'static str)] {
+    match lottery {
+        Lottery::MegaSena => &PRIZE_TIERS,
+        Lottery::DuplaSena => &DUPLA_SENA_PRIZE_TIERS,
+        Lottery::Lotofacil => &LOTOFACIL_PRIZE_TIERS,
+    }
+}
+
+/// The name of the prize tier of `lottery` paying a ticket matching `matches` numbers, if any.
+pub fn prize_tier(lottery: Lottery, matches: usize) -> Option<&'static str> {
+    prize_tiers(lottery)
+        .iter()
+        .find(|&&(tier, _)| tier == matches)
+        .map(|&(_, name)| name)
+}
+
+/// How one ticket did against the results of a contest.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TicketScore {
+    /// Row of the ticket in the games file, starting at 1.
+    pub row: usize,
+    /// The numbers of the ticket, its label left to `label`.
+    pub game: Game,
+    /// The label of the ticket, e.g. the name of its owner, left out when it has none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Number of drawn numbers on the ticket, in each result of the contest.
+    pub matches: Vec<usize>,
+    /// The largest of `matches`.
+    pub best: usize,
+}
+
+/// How a batch of tickets did against the results of a contest.
+#[derive(Debug, Serialize)]
+pub struct DrawReport {
+    /// The results of the contest, in the order drawn.
+    pub draws: Vec<Game>,
+    /// The score of every ticket, in file order.
+    pub tickets: Vec<TicketScore>,
+    /// Number of tickets hitting each prize tier in each result, by tier name.
+    pub prizes: Vec<BTreeMap<&'static str, usize>>,
+}
+
+impl DrawReport {
+    /// The largest number of matches of a ticket over the results, 0 without tickets.
+    pub fn best(&self) -> usize {
+        self.tickets
+            .iter()
+            .map(|ticket| ticket.best)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Scores tickets against the results of a contest, every ticket playing in each of them.
+///
+/// # Arguments
+/// * `games` - The tickets played.
+/// * `draws` - The numbers drawn in each result, one for the Mega-Sena and two for the Dupla
+///   Sena.
+/// * `lottery` - The lottery drawn, telling the prize tiers.
+///
+/// # Returns
+/// * The matches of every ticket in each result and the number of tickets hitting each prize
+///   tier in each result.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::config::Lottery;
+/// use rk_lottery::game::Game;
+/// use rk_lottery::scoring::score_games;
+///
+/// let games = vec![Game::new(vec![4, 18, 23, 39, 50, 59])];
+/// let draw = Game::new(vec![4, 18, 23, 39, 51, 60]);
+/// let report = score_games(&games, &[draw], Lottery::MegaSena);
+/// assert_eq!(report.tickets[0].matches, [4]);
+/// assert_eq!(report.prizes[0]["quadra"], 1);
+/// ```
+pub fn score_games(games: &[Game], draws: &[Game], lottery: Lottery) -> DrawReport {
+    let tickets: Vec<TicketScore> = games
+        .iter()
+        .enumerate()
+        .map(|(index, game)| {
+            let matches: Vec<usize> = draws
+                .iter()
+                .map(|draw| custom_utils::game_overlap(game, draw))
+                .collect();
+            TicketScore {
+                row: index + 1,
+                game: Game::new(game.to_vec()),
+                label: game.label().map(str::to_string),
+                best: matches.iter().copied().max().unwrap_or(0),
+                matches,
+            }
+        })
+        .collect();
+    let prizes = (0..draws.len())
+        .map(|draw| {
+            prize_tiers(lottery)
+                .iter()
+                .map(|&(matches, name)| {
+                    let hits = tickets
+                        .iter()
+                        .filter(|ticket| ticket.matches[draw] == matches)
+                        .count();
+                    (name, hits)
+                })
+                .collect()
+        })
+        .collect();
+    DrawReport {
+        draws: draws.to_vec(),
+        tickets,
+        prizes,
+    }
+}
+
+/// How a batch of tickets did against many random draws.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Simulation {
+    /// Number of draws simulated.
+    pub draws: u64,
+    /// Number of tickets played in each draw.
+    pub tickets: usize,
+    /// Number of draws whose best ticket matched 0, 1, ... numbers, up to the numbers of a
+    /// result, in any of their results.
+    pub best_matches: Vec<u64>,
+    /// Number of tickets matching 0, 1, ... numbers over all the draws, for each result of a
+    /// draw.
+    pub hits: Vec<Vec<u64>>,
+}
+
+impl Simulation {
+    /// The average number of tickets matching `matches` numbers in a draw, over its results.
+    pub fn expected_hits(&self, matches: usize) -> f64 {
+        let hits: u64 = self.hits.iter().map(|hits| hits[matches]).sum();
+        hits as f64 / self.draws as f64
+    }
+
+    /// The average winnings of a draw, the price of the tickets taken off.
+    pub fn expected_winnings(&self, prizes: &PrizesConfig) -> f64 {
+        let won: f64 = (0..self.best_matches.len())
+            .map(|matches| self.expected_hits(matches) * prizes.prize(matches))
+            .sum();
+        won - prizes.ticket_price * self.tickets as f64
+    }
+}
+
+/// Scores tickets against `draws` random draws of `results_per_draw` results each, every
+/// result drawn uniformly among the subsets of `numbers_per_draw` numbers of
+/// `min_number..=max_number`, on bitmasks so that millions of draws stay fast.
+///
+/// # Arguments
+/// * `games` - The tickets played.
+/// * `draws` - The number of draws, at least 1.
+/// * `results_per_draw` - The results of each draw, every ticket playing in all of them: 1
+///   for the Mega-Sena, 2 for the Dupla Sena.
+/// * `numbers_per_draw` - The numbers of a result: 6, or 15 for the Lotofácil.
+/// * `rng` - The random number generator to draw from, seeded for reproducible runs.
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+/// * `max_number` - The largest number of the lottery (e.g., 60).
+///
+/// # Returns
+/// * The distribution of the best match per draw and the matches of the tickets.
+/// * An error if the lottery has more than `GameMask::MAX_NUMBER` numbers or a ticket has
+///   numbers outside of it.
+pub fn simulate<R: Rng>(
+    games: &[Game],
+    draws: u64,
+    results_per_draw: usize,
+    numbers_per_draw: usize,
+    rng: &mut R,
+    min_number: i64,
+    max_number: i64,
+) -> Result<Simulation, String> {
+    // Masks hold numbers from 1, so the numbers are shifted by the start of the lottery
+    let span = max_number - min_number + 1;
+    if !(numbers_per_draw as i64..=GameMask::MAX_NUMBER).contains(&span) {
+        return Err(format!(
+            "Only lotteries of {} to {} numbers can be simulated, not {}",
+            numbers_per_draw,
+            GameMask::MAX_NUMBER,
+            span
+        ));
+    }
+    let shift =
+        |numbers: &[i64]| -> Vec<i64> { numbers.iter().map(|&x| x - min_number + 1).collect() };
+    let tickets = games
+        .iter()
+        .enumerate()
+        .map(|(index, game)| {
+            let in_range = game.iter().all(|x| (min_number..=max_number).contains(x));
+            GameMask::from_numbers(&shift(game))
+                .filter(|mask| in_range && mask.len() == game.len())
+                .ok_or_else(|| {
+                    format!(
+                        "Ticket #{} ({}) has numbers outside {} to {} or repeated",
+                        index + 1,
+                        game,
+                        min_number,
+                        max_number
+                    )
+                })
+        })
+        .collect::<Result<Vec<GameMask>, String>>()?;
+
+    let mut simulation = Simulation {
+        draws,
+        tickets: tickets.len(),
+        best_matches: vec![0; numbers_per_draw + 1],
+        hits: vec![vec![0; numbers_per_draw + 1]; results_per_draw],
+    };
+    let mut numbers = vec![0; numbers_per_draw];
+    for _ in 0..draws {
+        let mut best = 0;
+        for hits in simulation.hits.iter_mut() {
+            // Draw distinct numbers, each result being as likely as any other
+            let mut drawn = 0;
+            while drawn < numbers.len() {
+                let number = rng.gen_range(1..=span);
+                if !numbers[..drawn].contains(&number) {
+                    numbers[drawn] = number;
+                    drawn += 1;
+                }
+            }
+            let draw = GameMask::from_numbers(&numbers).expect("drawn numbers fit in a mask");
+
+            for ticket in &tickets {
+                let matches = ticket.overlap(draw).min(numbers_per_draw);
+                hits[matches] += 1;
+                best = best.max(matches);
+            }
+        }
+        simulation.best_matches[best] += 1;
+    }
+    Ok(simulation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Tickets matching 0 to 6 numbers of the draw 4-18-23-39-51-60, in that order.
+    fn tickets() -> Vec<Game> {
+        vec![
+            Game::new(vec![1, 2, 3, 5, 6, 7]),
+            Game::new(vec![4, 2, 3, 5, 6, 7]),
+            Game::new(vec![4, 18, 3, 5, 6, 7]),
+            Game::new(vec![4, 18, 23, 5, 6, 7]),
+            Game::new(vec![4, 18, 23, 39, 6, 7]),
+            Game::new(vec![4, 18, 23, 39, 51, 7]),
+            Game::new(vec![60, 51, 39, 23, 18, 4]),
+        ]
+    }
+
+    #[test]
+    fn test_score_games_counts_matches_and_prizes() {
+        let draw = Game::new(vec![4, 18, 23, 39, 51, 60]);
+        let report = score_games(&tickets(), &[draw], Lottery::MegaSena);
+        let matches: Vec<usize> = report.tickets.iter().map(|ticket| ticket.best).collect();
+        assert_eq!(matches, vec![0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(report.tickets[4].matches, [4]);
+        assert_eq!(report.tickets[6].row, 7);
+        assert_eq!(report.best(), 6);
+        assert_eq!(
+            report.prizes,
+            [BTreeMap::from([("quadra", 1), ("quina", 1), ("sena", 1)])]
+        );
+        assert_eq!(prize_tier(Lottery::MegaSena, 4), Some("quadra"));
+        assert_eq!(prize_tier(Lottery::MegaSena, 3), None);
+    }
+
+    #[test]
+    fn test_score_games_without_prizes() {
+        let draw = Game::new(vec![4, 18, 23, 39, 51, 60]);
+        let report = score_games(&tickets()[..4], &[draw], Lottery::MegaSena);
+        assert_eq!(report.best(), 3);
+        assert!(report.prizes[0].values().all(|&hits| hits == 0));
+    }
+
+    #[test]
+    fn test_dupla_sena_tickets_play_in_both_results() {
+        // The first two tickets only hit in the second result
+        let draws = [
+            Game::new(vec![4, 18, 23, 39, 51, 60]),
+            Game::new(vec![1, 2, 3, 5, 6, 8]),
+        ];
+        let report = score_games(&tickets(), &draws, Lottery::DuplaSena);
+        assert_eq!(report.tickets[0].matches, [0, 5]);
+        assert_eq!(report.tickets[0].best, 5);
+        assert_eq!(report.tickets[1].matches, [1, 4]);
+        assert_eq!(report.tickets[3].matches, [3, 2]);
+        assert_eq!(report.tickets[6].matches, [6, 0]);
+        assert_eq!(
+            report.prizes,
+            [
+                BTreeMap::from([("terno", 1), ("quadra", 1), ("quina", 1), ("sena", 1)]),
+                BTreeMap::from([("terno", 1), ("quadra", 1), ("quina", 1), ("sena", 0)]),
+            ]
+        );
+        assert_eq!(prize_tier(Lottery::DuplaSena, 3), Some("terno"));
+    }
+
+    #[test]
+    fn test_lotofacil_tickets_of_15_numbers_pay_from_11_matches() {
+        let draw: Game = (1..=15).collect();
+        let tickets: Vec<Game> = [10, 11, 14, 15]
+            .iter()
+            .map(|&hits| (1..=hits).chain(16..31 - hits).collect())
+            .collect();
+        let report = score_games(&tickets, &[draw], Lottery::Lotofacil);
+        let matches: Vec<usize> = report.tickets.iter().map(|ticket| ticket.best).collect();
+        assert_eq!(matches, [10, 11, 14, 15]);
+        assert_eq!(
+            report.prizes,
+            [BTreeMap::from([
+                ("11 acertos", 1),
+                ("12 acertos", 0),
+                ("13 acertos", 0),
+                ("14 acertos", 1),
+                ("15 acertos", 1),
+            ])]
+        );
+        assert_eq!(prize_tier(Lottery::Lotofacil, 10), None);
+
+        // Two subsets of 15 of 25 numbers share at least 5 of them
+        let mut rng = StdRng::seed_from_u64(3);
+        let simulation = simulate(&tickets, 2000, 1, 15, &mut rng, 1, 25).unwrap();
+        assert_eq!(simulation.best_matches.len(), 16);
+        assert_eq!(simulation.best_matches.iter().sum::<u64>(), 2000);
+        assert!(simulation.hits[0][..5].iter().all(|&hits| hits == 0));
+        let prizes = PrizesConfig {
+            acertos_15: 1000.0,
+            ..PrizesConfig::default()
+        };
+        assert_eq!(
+            simulation.expected_winnings(&prizes),
+            1000.0 * simulation.expected_hits(15)
+        );
+    }
+
+    #[test]
+    fn test_simulation_of_every_game_of_a_tiny_lottery() {
+        // The 7 games of numbers 1 to 7: one matches any draw fully, the others by 5
+        let games: Vec<Game> = (1..=7)
+            .map(|left_out| (1..=7).filter(|&x| x != left_out).collect())
+            .collect();
+        let mut rng = StdRng::seed_from_u64(1);
+        let simulation = simulate(&games, 1000, 1, 6, &mut rng, 1, 7).unwrap();
+        assert_eq!(simulation.best_matches, [0, 0, 0, 0, 0, 0, 1000]);
+        assert_eq!(simulation.hits, [[0, 0, 0, 0, 0, 6000, 1000]]);
+        assert_eq!(simulation.expected_hits(5), 6.0);
+        // Both results of a double draw pay
+        let double = simulate(&games, 1000, 2, 6, &mut rng, 1, 7).unwrap();
+        assert_eq!(double.hits, [[0, 0, 0, 0, 0, 6000, 1000]; 2]);
+        assert_eq!(double.expected_hits(5), 12.0);
+
+        let prizes = PrizesConfig {
+            quina: 10.0,
+            sena: 100.0,
+            ticket_price: 2.0,
+            ..PrizesConfig::default()
+        };
+        assert_eq!(simulation.expected_winnings(&prizes), 60.0 + 100.0 - 14.0);
+    }
+
+    #[test]
+    fn test_seeded_simulation_counts() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let simulation = simulate(&tickets(), 10_000, 1, 6, &mut rng, 1, 60).unwrap();
+        assert_eq!(simulation.best_matches.iter().sum::<u64>(), 10_000);
+        assert_eq!(simulation.hits[0].iter().sum::<u64>(), 70_000);
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(
+            simulate(&tickets(), 10_000, 1, 6, &mut rng, 1, 60).unwrap(),
+            simulation
+        );
+        assert_eq!(simulation.best_matches, [2422, 4939, 2289, 333, 17, 0, 0]);
+        assert_eq!(simulation.hits, [[36042, 26639, 6638, 661, 20, 0, 0]]);
+
+        // The best ticket of a double draw is the best over both results
+        let mut rng = StdRng::seed_from_u64(7);
+        let double = simulate(&tickets(), 10_000, 2, 6, &mut rng, 1, 60).unwrap();
+        assert_eq!(double.hits.len(), 2);
+        assert!(double
+            .hits
+            .iter()
+            .all(|hits| hits.iter().sum::<u64>() == 70_000));
+        assert!(double.best_matches[0] < simulation.best_matches[0]);
+    }
+
+    #[test]
+    fn test_simulation_rejects_tickets_outside_the_lottery() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let error = simulate(&tickets(), 10, 1, 6, &mut rng, 1, 50).unwrap_err();
+        assert_eq!(
+            error,
+            "Ticket #6 (04-18-23-39-51-07) has numbers outside 1 to 50 or repeated"
+        );
+        assert!(simulate(&tickets(), 10, 1, 6, &mut rng, 1, 100).is_err());
+        // A zero-based lottery of 64 numbers fits
+        assert!(simulate(&tickets(), 10, 1, 6, &mut rng, 0, 63).is_ok());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::custom_utils;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+
+/// A rank whose conversion to a subset and back does not give the rank again.
+#[derive(Debug, PartialEq)]
+pub struct Mismatch {
+    /// The maximum number of the space checked.
+    pub n: i64,
+    /// The number of numbers per subset of the space checked.
+    pub k: i64,
+    /// The offending rank.
+    pub rank: i64,
+    /// The subset the rank converted to.
+    pub subset: Vec<i64>,
+    /// The rank the subset converted back to, `None` if it could not be ranked.
+    pub roundtrip: Option<i64>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rank {} of the {}-subsets of 1..={} converts to {:?}, which ",
+            self.rank, self.k, self.n, self.subset
+        )?;
+        match self.roundtrip {
+            Some(roundtrip) => write!(f, "converts back to rank {}", roundtrip),
+            None => write!(f, "cannot be ranked"),
+        }
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+/// Checks that a rank converts to a valid subset (ascending, within `1..=n`) that converts
+/// back to the same rank.
+fn check_rank(rank: i64, n: i64, k: i64) -> Result<Vec<i64>, Mismatch> {
+    // An in-range rank reported out of range shows up as an empty subset
+    let subset = custom_utils::enum2subset(rank, n, k).unwrap_or_default();
+    let roundtrip = custom_utils::subset2enum(&subset, k as usize).ok();
+    let valid = subset.len() == k as usize
+        && subset.windows(2).all(|pair| pair[0] < pair[1])
+        && subset.iter().all(|&x| (1..=n).contains(&x));
+    if !valid || roundtrip != Some(rank) {
+        return Err(Mismatch {
+            n,
+            k,
+            rank,
+            subset,
+            roundtrip,
+        });
+    }
+    Ok(subset)
+}
+
+/// Checks that the triplet ranks of a game, computed directly or triplet by triplet, agree
+/// and convert back to the triplets.
+fn check_triplets(game: &[i64], n: i64) -> Result<(), Mismatch> {
+    let triplets = custom_utils::game2triplets(game);
+    for (triplet, rank) in triplets.iter().zip(custom_utils::game2triplet_enums(game)) {
+        let roundtrip = custom_utils::triplet2enum(triplet).ok();
+        if roundtrip != Some(rank) || check_rank(rank, n, 3)? != *triplet {
+            return Err(Mismatch {
+                n,
+                k: 3,
+                rank,
+                subset: triplet.clone(),
+                roundtrip,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Verifies every rank of the `k`-subsets of `1..=n`, and the triplets of every subset when
+/// `k` is 6.
+///
+/// # Arguments
+/// * `n` - The maximum number (e.g., 20).
+/// * `k` - The number of numbers per subset (e.g., 6).
+/// * `progress` - Called with the number of ranks checked so far and the total, about every 10%.
+///
+/// # Returns
+/// * The number of ranks checked.
+/// * The first mismatch found.
+pub fn verify_exhaustive(
+    n: i64,
+    k: i64,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<u64, Mismatch> {
+    let total = custom_utils::binomial(n, k) as u64;
+    let step = (total / 10).max(1);
+    for rank in 0..total {
+        let subset = check_rank(rank as i64, n, k)?;
+        if k == 6 {
+            check_triplets(&subset, n)?;
+        }
+        if (rank + 1) % step == 0 {
+            progress(rank + 1, total);
+        }
+    }
+    Ok(total)
+}
+
+/// Verifies `samples` random ranks of the `k`-subsets of `1..=n`, and their triplets when `k`
+/// is 6.
+///
+/// # Arguments
+/// * `n` - The maximum number (e.g., 60).
+/// * `k` - The number of numbers per subset (e.g., 6).
+/// * `samples` - The number of ranks to check.
+/// * `seed` - A `u64` seed for reproducible randomness.
+/// * `progress` - Called with the number of ranks checked so far and the total, about every 10%.
+///
+/// # Returns
+/// * The number of ranks checked.
+/// * The first mismatch found.
+pub fn verify_sample(
+    n: i64,
+    k: i64,
+    samples: u64,
+    seed: u64,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<u64, Mismatch> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let total = custom_utils::binomial(n, k);
+    let step = (samples / 10).max(1);
+    for done in 1..=samples {
+        let subset = check_rank(rng.gen_range(0..total), n, k)?;
+        if k == 6 {
+            check_triplets(&subset, n)?;
+        }
+        if done % step == 0 {
+            progress(done, samples);
+        }
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_exhaustive_small_spaces() {
+        let mut reports = Vec::new();
+        assert_eq!(
+            verify_exhaustive(12, 6, &mut |done, total| reports.push((done, total))),
+            Ok(924)
+        );
+        assert_eq!(reports.len(), 10);
+        assert_eq!(reports.last(), Some(&(920, 924)));
+
+        assert_eq!(verify_exhaustive(20, 3, &mut |_, _| {}), Ok(1140));
+        assert_eq!(verify_exhaustive(6, 6, &mut |_, _| {}), Ok(1));
+    }
+
+    #[test]
+    fn test_verify_sample_full_space() {
+        assert_eq!(verify_sample(60, 6, 2_000, 1, &mut |_, _| {}), Ok(2_000));
+    }
+
+    #[test]
+    fn test_mismatch_reports_the_rank() {
+        let mismatch = Mismatch {
+            n: 60,
+            k: 6,
+            rank: 42,
+            subset: vec![1, 2, 3, 4, 5, 48],
+            roundtrip: Some(41),
+        };
+        assert_eq!(
+            mismatch.to_string(),
+            "rank 42 of the 6-subsets of 1..=60 converts to [1, 2, 3, 4, 5, 48], which converts back to rank 41"
+        );
+        let mismatch = Mismatch {
+            subset: vec![],
+            roundtrip: None,
+            ..mismatch
+        };
+        assert_eq!(
+            mismatch.to_string(),
+            "rank 42 of the 6-subsets of 1..=60 converts to [], which cannot be ranked"
+        );
+    }
+}
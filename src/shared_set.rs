@@ -0,0 +1,289 @@
+use crate::data_structures::NumberSet;
+use std::collections::HashSet;
+use std::sync::{Mutex, MutexGuard};
+
+/// Number of shards of a `ShardedNumberSet` made with `Default`.
+pub const DEFAULT_SHARDS: usize = 16;
+
+/// A set of ranks several threads can update at once, through a shared reference.
+///
+/// `check_and_insert_all` is atomic: of two threads inserting overlapping ranks at the same
+/// time, only one succeeds, so no triplet ever ends up claimed by two games.
+pub trait ConcurrentRankSet: Sync {
+    /// Adds a number to the set.
+    ///
+    /// # Returns
+    /// * `true` if the number was not present in the set and was added.
+    /// * `false` if the number was already present in the set.
+    fn add_number(&self, number: i64) -> bool;
+
+    /// Checks if a number is in the set.
+    fn contains(&self, number: i64) -> bool;
+
+    /// Returns the number of elements in the set.
+    fn len(&self) -> usize;
+
+    /// Checks if the set is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts all the numbers at once, only if none is in the set yet nor repeated in
+    /// `numbers`, no other thread seeing part of them inserted.
+    ///
+    /// # Returns
+    /// * `true` if all the numbers were inserted.
+    /// * `false` if any number was already present, the set being left unchanged.
+    fn check_and_insert_all(&self, numbers: &[i64]) -> bool;
+}
+
+/// Locks a set; a thread panicking while holding it leaves it whole, as no operation panics
+/// halfway through, so a poisoned lock is taken over.
+fn lock(set: &Mutex<HashSet<i64>>) -> MutexGuard<'_, HashSet<i64>> {
+    set.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Inserts `numbers` in the sets locked for them, `set_of` giving the position in `sets` of the
+/// set of each number, all or nothing as `ConcurrentRankSet::check_and_insert_all` does.
+fn insert_all(
+    sets: &mut [MutexGuard<'_, HashSet<i64>>],
+    numbers: &[i64],
+    set_of: impl Fn(i64) -> usize,
+) -> bool {
+    if numbers
+        .iter()
+        .any(|&number| sets[set_of(number)].contains(&number))
+    {
+        return false;
+    }
+    for (position, &number) in numbers.iter().enumerate() {
+        if !sets[set_of(number)].insert(number) {
+            // Repeated in `numbers`: the numbers before it were all inserted by this call
+            for &inserted in &numbers[..position] {
+                sets[set_of(inserted)].remove(&inserted);
+            }
+            return false;
+        }
+    }
+    true
+}
+
+/// A `NumberSet` behind a single mutex, every operation locking the whole set.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::shared_set::{ConcurrentRankSet, SharedNumberSet};
+///
+/// let set = SharedNumberSet::new();
+/// std::thread::scope(|scope| {
+///     scope.spawn(|| set.check_and_insert_all(&[1, 2, 3]));
+///     scope.spawn(|| set.check_and_insert_all(&[4, 5, 6]));
+/// });
+/// assert_eq!(set.len(), 6);
+/// ```
+#[derive(Debug, Default)]
+pub struct SharedNumberSet {
+    numbers: Mutex<HashSet<i64>>,
+}
+
+impl SharedNumberSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The numbers of the set, once no other thread uses it.
+    pub fn into_number_set(self) -> NumberSet {
+        self.numbers
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .into_iter()
+            .collect()
+    }
+}
+
+impl From<NumberSet> for SharedNumberSet {
+    fn from(set: NumberSet) -> Self {
+        Self {
+            numbers: Mutex::new(set.into_iter().collect()),
+        }
+    }
+}
+
+impl ConcurrentRankSet for SharedNumberSet {
+    fn add_number(&self, number: i64) -> bool {
+        lock(&self.numbers).insert(number)
+    }
+
+    fn contains(&self, number: i64) -> bool {
+        lock(&self.numbers).contains(&number)
+    }
+
+    fn len(&self) -> usize {
+        lock(&self.numbers).len()
+    }
+
+    fn check_and_insert_all(&self, numbers: &[i64]) -> bool {
+        insert_all(&mut [lock(&self.numbers)], numbers, |_| 0)
+    }
+}
+
+/// A set of ranks split into shards by rank modulo the number of shards, each behind its own
+/// mutex, so threads inserting ranks of different shards do not wait for each other.
+///
+/// `check_and_insert_all` locks the shards of the ranks given, always in increasing shard
+/// order so that two threads never wait on each other's shards, and inserts while holding all
+/// of them. `len` adds up the shards one after the other, so it is exact only when no other
+/// thread is inserting.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::shared_set::{ConcurrentRankSet, ShardedNumberSet};
+///
+/// let set = ShardedNumberSet::new(4);
+/// assert!(set.check_and_insert_all(&[1, 5, 9]));
+/// assert!(!set.check_and_insert_all(&[2, 9]));
+/// assert!(!set.contains(2));
+/// assert_eq!(set.into_number_set().len(), 3);
+/// ```
+#[derive(Debug)]
+pub struct ShardedNumberSet {
+    shards: Vec<Mutex<HashSet<i64>>>,
+}
+
+impl ShardedNumberSet {
+    /// Creates an empty set of `shards` shards, 1 at least.
+    pub fn new(shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1)).map(|_| Mutex::default()).collect(),
+        }
+    }
+
+    /// The shard holding `number`.
+    fn shard(&self, number: i64) -> usize {
+        number.rem_euclid(self.shards.len() as i64) as usize
+    }
+
+    /// The numbers of the set, once no other thread uses it.
+    pub fn into_number_set(self) -> NumberSet {
+        self.shards
+            .into_iter()
+            .flat_map(|shard| {
+                shard
+                    .into_inner()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+            })
+            .collect()
+    }
+}
+
+impl Default for ShardedNumberSet {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHARDS)
+    }
+}
+
+impl ConcurrentRankSet for ShardedNumberSet {
+    fn add_number(&self, number: i64) -> bool {
+        lock(&self.shards[self.shard(number)]).insert(number)
+    }
+
+    fn contains(&self, number: i64) -> bool {
+        lock(&self.shards[self.shard(number)]).contains(&number)
+    }
+
+    fn len(&self) -> usize {
+        self.shards.iter().map(|shard| lock(shard).len()).sum()
+    }
+
+    fn check_and_insert_all(&self, numbers: &[i64]) -> bool {
+        let mut shards: Vec<usize> = numbers.iter().map(|&number| self.shard(number)).collect();
+        shards.sort_unstable();
+        shards.dedup();
+        // Locked in increasing shard order, which every thread follows
+        let mut guards: Vec<_> = shards
+            .iter()
+            .map(|&shard| lock(&self.shards[shard]))
+            .collect();
+        insert_all(&mut guards, numbers, |number| {
+            shards
+                .binary_search(&self.shard(number))
+                .expect("the shard of every number is locked")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::custom_utils;
+    use rand::rngs::StdRng;
+    use rand::seq::index;
+    use rand::SeedableRng;
+
+    fn check_api(set: impl ConcurrentRankSet) {
+        assert!(set.is_empty());
+        assert!(set.add_number(7));
+        assert!(!set.add_number(7));
+        assert!(set.check_and_insert_all(&[1, 2, 3, -4]));
+        assert!(!set.check_and_insert_all(&[10, 11, 3]));
+        assert!(!set.check_and_insert_all(&[20, 21, 20]));
+        assert!(!set.contains(10) && !set.contains(20) && !set.contains(21));
+        assert!(set.contains(-4));
+        assert_eq!(set.len(), 5);
+    }
+
+    #[test]
+    fn test_sets_behave_like_a_number_set() {
+        check_api(SharedNumberSet::new());
+        check_api(ShardedNumberSet::new(3));
+        check_api(ShardedNumberSet::new(1));
+        let set = SharedNumberSet::from([1, 2].into_iter().collect::<NumberSet>());
+        assert!(!set.check_and_insert_all(&[2]));
+        assert_eq!(set.into_number_set(), [1, 2].into_iter().collect());
+    }
+
+    /// Has 8 threads draw games of a 30-number lottery and claim their triplets, then checks
+    /// no triplet was claimed by two games and the set holds exactly the triplets claimed.
+    fn hammer(set: impl ConcurrentRankSet) {
+        let claimed: Vec<Vec<i64>> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..8)
+                .map(|worker| {
+                    let set = &set;
+                    scope.spawn(move || {
+                        let mut rng = StdRng::seed_from_u64(worker);
+                        let mut claimed = Vec::new();
+                        for _ in 0..2_000 {
+                            let mut game: Vec<i64> = index::sample(&mut rng, 30, 6)
+                                .into_iter()
+                                .map(|number| number as i64 + 1)
+                                .collect();
+                            game.sort_unstable();
+                            let triplet_nos = custom_utils::game2triplet_enums_from(&game, 1);
+                            if set.check_and_insert_all(&triplet_nos) {
+                                claimed.extend(triplet_nos);
+                            }
+                        }
+                        claimed
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        let claimed: Vec<i64> = claimed.into_iter().flatten().collect();
+        let distinct: HashSet<i64> = claimed.iter().copied().collect();
+        assert_eq!(distinct.len(), claimed.len(), "a triplet was claimed twice");
+        assert_eq!(set.len(), claimed.len());
+        assert!(claimed.len() > 20);
+    }
+
+    #[test]
+    fn test_concurrent_claims_never_share_a_triplet() {
+        hammer(SharedNumberSet::new());
+        hammer(ShardedNumberSet::default());
+        hammer(ShardedNumberSet::new(3));
+    }
+}
@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::AddAssign;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Number of candidates between two progress reports.
+pub const PROGRESS_INTERVAL: u64 = 1000;
+
+/// Number of accepted games between two milestones logged at info level.
+pub const MILESTONE_INTERVAL: usize = 1000;
+
+/// A function called with the counters as a run progresses.
+pub type Observer = Box<dyn FnMut(&GenerationStats)>;
+
+/// A function called with each game of a run, e.g. to journal it.
+pub type GameHook = Box<dyn FnMut(&[i64])>;
+
+/// What the checks found about a candidate game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    /// The candidate passed every check.
+    Valid,
+    /// The candidate was drawn before.
+    DuplicateGame,
+    /// The candidate breaks a constraint.
+    Rejected,
+    /// The candidate shares a triplet with an accepted game.
+    SharedTriplet,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Valid => write!(f, "valid"),
+            Outcome::DuplicateGame => write!(f, "drawn before"),
+            Outcome::Rejected => write!(f, "breaks a constraint"),
+            Outcome::SharedTriplet => write!(f, "shares a triplet with an accepted game"),
+        }
+    }
+}
+
+/// Counters of a generation run, reported to an optional observer as they change.
+///
+/// The counters are saved in the manifest of the run, so that a run resuming it carries them
+/// on (see `AddAssign`) rather than starting from zero.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationStats {
+    /// Number of games wanted.
+    pub requested: usize,
+    /// Number of games so far, initial games included.
+    pub games: usize,
+    /// Number of candidates checked.
+    pub attempts: u64,
+    /// Number of candidates accepted, the games the run starts from left out.
+    pub accepted: u64,
+    /// Number of initial games left out for breaking the constraints, with
+    /// `on_invalid_initial`.
+    pub skipped_initial: usize,
+    /// Number of candidates drawn before.
+    pub duplicate_games: u64,
+    /// Number of candidates breaking a constraint.
+    pub rejected: u64,
+    /// Number of candidates sharing a triplet with an accepted game.
+    pub shared_triplets: u64,
+    /// Number of 32-bit words the candidates were drawn with from the random generator, by a
+    /// single-threaded run, once the run ends: skipping as many words from the seed carries on
+    /// where the run stopped.
+    pub rng_draws: u64,
+    /// Number of candidates rejected by each constraint, by constraint name, once the run ends.
+    pub constraint_rejections: BTreeMap<String, u64>,
+    /// Wall time spent generating, once the run ends, saved in seconds.
+    #[serde(with = "seconds", rename = "elapsed_secs")]
+    pub elapsed: Duration,
+    /// Called every `PROGRESS_INTERVAL` candidates and at the end of the run.
+    #[serde(skip)]
+    observer: Option<Observer>,
+    /// Called with each game of the batch, those it starts from included.
+    #[serde(skip)]
+    on_game: Option<GameHook>,
+    /// Set from another thread, e.g. on Ctrl-C, to stop the run early.
+    #[serde(skip)]
+    stop: Option<Arc<AtomicBool>>,
+}
+
+impl GenerationStats {
+    /// Creates counters reporting to `observer` as the run progresses.
+    pub fn with_observer(observer: impl FnMut(&GenerationStats) + 'static) -> Self {
+        Self {
+            observer: Some(Box::new(observer)),
+            ..Self::default()
+        }
+    }
+
+    /// Passes each game of the batch to `hook`: the games the run starts from (initial games,
+    /// or those of the file resumed or appended to) then every game accepted, in order. A hook
+    /// given before gets each game first.
+    pub fn with_game_hook(mut self, mut hook: impl FnMut(&[i64]) + 'static) -> Self {
+        self.on_game = Some(match self.on_game.take() {
+            Some(mut before) => Box::new(move |game: &[i64]| {
+                before(game);
+                hook(game);
+            }),
+            None => Box::new(hook),
+        });
+        self
+    }
+
+    /// Stops the run, keeping the games found so far, once `stop` is set.
+    pub fn with_stop_flag(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Whether the run was asked to stop early.
+    pub fn interrupted(&self) -> bool {
+        self.stop
+            .as_ref()
+            .is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
+    /// Counts one candidate.
+    pub fn record(&mut self, outcome: Outcome) {
+        self.record_many(outcome, 1);
+    }
+
+    /// Counts `count` candidates with the same outcome, as found by a parallel worker.
+    pub fn record_many(&mut self, outcome: Outcome, count: u64) {
+        let before = self.attempts;
+        self.attempts += count;
+        match outcome {
+            Outcome::Valid => {}
+            Outcome::DuplicateGame => self.duplicate_games += count,
+            Outcome::Rejected => self.rejected += count,
+            Outcome::SharedTriplet => self.shared_triplets += count,
+        }
+        if self.attempts / PROGRESS_INTERVAL != before / PROGRESS_INTERVAL {
+            self.report();
+        }
+    }
+
+    /// Passes a game the batch starts from to the game hook, if any, without counting it.
+    pub fn start_from(&mut self, game: &[i64]) {
+        if let Some(hook) = &mut self.on_game {
+            hook(game);
+        }
+    }
+
+    /// Counts a game added to the batch, passing it to the game hook and logging a milestone
+    /// every `MILESTONE_INTERVAL` games.
+    pub fn accept(&mut self, game: &[i64]) {
+        self.start_from(game);
+        self.games += 1;
+        self.accepted += 1;
+        if self.games.is_multiple_of(MILESTONE_INTERVAL) {
+            log::info!(
+                "{} of {} games accepted after {} candidates",
+                self.games,
+                self.requested,
+                self.attempts
+            );
+        }
+    }
+
+    /// Total number of candidates turned down.
+    pub fn rejections(&self) -> u64 {
+        self.duplicate_games + self.rejected + self.shared_triplets
+    }
+
+    /// Passes the counters to the observer, if any.
+    pub fn report(&mut self) {
+        if let Some(mut observer) = self.observer.take() {
+            observer(self);
+            self.observer = Some(observer);
+        }
+    }
+
+    /// The counters alone, without the observer, the game hook and the stop flag.
+    pub fn counters(&self) -> GenerationStats {
+        let mut counters = GenerationStats {
+            requested: self.requested,
+            games: self.games,
+            skipped_initial: self.skipped_initial,
+            ..GenerationStats::default()
+        };
+        counters += self;
+        counters
+    }
+}
+
+/// Adds the work counted by other counters, e.g. those of the run resumed or of another batch:
+/// the candidates and their outcomes, the words drawn, the rejections by constraint and the
+/// wall time. The games wanted, found and skipped stay those of `self`, and nothing is reported
+/// to the observer.
+impl AddAssign<&GenerationStats> for GenerationStats {
+    fn add_assign(&mut self, other: &GenerationStats) {
+        self.attempts += other.attempts;
+        self.accepted += other.accepted;
+        self.duplicate_games += other.duplicate_games;
+        self.rejected += other.rejected;
+        self.shared_triplets += other.shared_triplets;
+        self.rng_draws += other.rng_draws;
+        for (name, count) in &other.constraint_rejections {
+            *self.constraint_rejections.entry(name.clone()).or_default() += count;
+        }
+        self.elapsed += other.elapsed;
+    }
+}
+
+/// Saves a duration as a number of seconds.
+mod seconds {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Duration::try_from_secs_f64(f64::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for GenerationStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} candidates tried, {} rejected: {} by constraints, {} duplicate games, {} sharing a triplet",
+            self.attempts,
+            self.rejections(),
+            self.rejected,
+            self.duplicate_games,
+            self.shared_triplets
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_record_counts_each_outcome() {
+        let mut stats = GenerationStats::default();
+        stats.record(Outcome::Valid);
+        stats.accept(&[1, 2, 3, 4, 5, 6]);
+        stats.record(Outcome::DuplicateGame);
+        stats.record_many(Outcome::Rejected, 3);
+        stats.record_many(Outcome::SharedTriplet, 2);
+
+        assert_eq!(stats.attempts, 7);
+        assert_eq!(stats.games, 1);
+        assert_eq!(stats.duplicate_games, 1);
+        assert_eq!(stats.rejected, 3);
+        assert_eq!(stats.shared_triplets, 2);
+        assert_eq!(stats.rejections(), 6);
+        assert_eq!(
+            stats.to_string(),
+            "7 candidates tried, 6 rejected: 3 by constraints, 1 duplicate games, 2 sharing a triplet"
+        );
+    }
+
+    #[test]
+    fn test_game_hook_sees_every_game() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let games = Rc::clone(&seen);
+        let counted = Rc::new(RefCell::new(0));
+        let count = Rc::clone(&counted);
+        let mut stats = GenerationStats::default()
+            .with_game_hook(move |game| games.borrow_mut().push(game.to_vec()))
+            .with_game_hook(move |_| *count.borrow_mut() += 1);
+        stats.start_from(&[1, 2, 3, 4, 5, 6]);
+        stats.accept(&[7, 8, 9, 10, 11, 12]);
+        assert_eq!(stats.games, 1);
+        assert_eq!(
+            *seen.borrow(),
+            vec![vec![1, 2, 3, 4, 5, 6], vec![7, 8, 9, 10, 11, 12]]
+        );
+        assert_eq!(*counted.borrow(), 2);
+    }
+
+    #[test]
+    fn test_observer_is_called_every_interval() {
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let seen = Rc::clone(&reports);
+        let mut stats =
+            GenerationStats::with_observer(move |stats| seen.borrow_mut().push(stats.attempts));
+
+        for _ in 0..2 * PROGRESS_INTERVAL + 1 {
+            stats.record(Outcome::Rejected);
+        }
+        stats.record_many(Outcome::Valid, PROGRESS_INTERVAL);
+        stats.report();
+        assert_eq!(
+            *reports.borrow(),
+            vec![
+                PROGRESS_INTERVAL,
+                2 * PROGRESS_INTERVAL,
+                3 * PROGRESS_INTERVAL + 1,
+                3 * PROGRESS_INTERVAL + 1
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stats_serialize_without_the_observer() {
+        let mut stats = GenerationStats::with_observer(|_| {});
+        stats.requested = 2;
+        stats.record(Outcome::SharedTriplet);
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["requested"], 2);
+        assert_eq!(json["shared_triplets"], 1);
+        assert!(json.get("observer").is_none());
+    }
+
+    #[test]
+    fn test_counters_add_up_and_round_trip() {
+        let mut first = GenerationStats::default();
+        first.record_many(Outcome::Rejected, 4);
+        first.record(Outcome::Valid);
+        first.accept(&[1, 2, 3, 4, 5, 6]);
+        first.constraint_rejections.insert("sum".to_string(), 4);
+        first.elapsed = Duration::from_millis(1500);
+        let json = serde_json::to_string(&first).unwrap();
+        assert!(json.contains("\"elapsed_secs\":1.5"));
+        let saved: GenerationStats = serde_json::from_str(&json).unwrap();
+
+        let mut second = GenerationStats::with_observer(|_| {});
+        second.requested = 3;
+        second.games = 1;
+        second.record(Outcome::SharedTriplet);
+        second.record(Outcome::Valid);
+        second.accept(&[7, 8, 9, 10, 11, 12]);
+        second.constraint_rejections.insert("odd".to_string(), 0);
+        second.elapsed = Duration::from_millis(250);
+        second += &saved;
+
+        assert_eq!((second.attempts, second.accepted), (7, 2));
+        assert_eq!((second.requested, second.games), (3, 2));
+        assert_eq!((second.rejected, second.shared_triplets), (4, 1));
+        assert_eq!(
+            second.constraint_rejections,
+            BTreeMap::from([("odd".to_string(), 0), ("sum".to_string(), 4)])
+        );
+        assert_eq!(second.elapsed, Duration::from_millis(1750));
+        let counters = second.counters();
+        assert_eq!((counters.attempts, counters.games), (7, 2));
+        assert!(counters.observer.is_none());
+    }
+}
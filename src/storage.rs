@@ -0,0 +1,416 @@
+use crate::custom_utils;
+use crate::data_structures::TripletSet;
+use crate::game::Game;
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+/// Tables of a ticket database, created when missing. Every triplet rank is the primary key of
+/// its row of `triplets`, so the database itself turns down a game sharing a triplet with a
+/// game of any run stored.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        run_id INTEGER PRIMARY KEY,
+        seed TEXT,
+        min_number INTEGER NOT NULL,
+        max_number INTEGER NOT NULL,
+        version TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS games (
+        run_id INTEGER NOT NULL REFERENCES runs (run_id),
+        game INTEGER NOT NULL,
+        n1 INTEGER NOT NULL,
+        n2 INTEGER NOT NULL,
+        n3 INTEGER NOT NULL,
+        n4 INTEGER NOT NULL,
+        n5 INTEGER NOT NULL,
+        n6 INTEGER NOT NULL,
+        rank INTEGER NOT NULL,
+        PRIMARY KEY (run_id, game)
+    );
+    CREATE TABLE IF NOT EXISTS triplets (
+        rank INTEGER PRIMARY KEY,
+        run_id INTEGER NOT NULL,
+        game INTEGER NOT NULL,
+        FOREIGN KEY (run_id, game) REFERENCES games (run_id, game)
+    );
+";
+
+/// An error reading or writing a ticket database.
+#[derive(Debug)]
+pub enum StorageError {
+    /// The database could not be opened, read or written.
+    Sqlite(rusqlite::Error),
+    /// The runs stored are of another lottery, whose ranks do not compare with this one's.
+    OtherLottery {
+        min_number: i64,
+        max_number: i64,
+        stored_min_number: i64,
+        stored_max_number: i64,
+    },
+    /// A game to store does not have 6 numbers.
+    WrongSize { game: Vec<i64> },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Sqlite(error) => write!(f, "{}", error),
+            StorageError::OtherLottery {
+                min_number,
+                max_number,
+                stored_min_number,
+                stored_max_number,
+            } => write!(
+                f,
+                "the runs stored are of numbers {} to {}, not {} to {}",
+                stored_min_number, stored_max_number, min_number, max_number
+            ),
+            StorageError::WrongSize { game } => write!(
+                f,
+                "cannot store {}: it has {} numbers, not 6",
+                Game::new(game.clone()),
+                game.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Sqlite(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(error: rusqlite::Error) -> Self {
+        StorageError::Sqlite(error)
+    }
+}
+
+/// A run stored in a ticket database, with the number of its games.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StoredRun {
+    pub run_id: i64,
+    /// The seed the games were drawn with, `None` for OS entropy.
+    pub seed: Option<u64>,
+    pub games: usize,
+}
+
+/// How many stored games play a number.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NumberUsage {
+    pub number: i64,
+    pub games: usize,
+}
+
+/// The runs of a ticket database and how often their games play each number.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StorageReport {
+    pub runs: Vec<StoredRun>,
+    /// Number of games stored, over every run.
+    pub games: usize,
+    /// Every number of the lottery, most played first, then by number.
+    pub numbers: Vec<NumberUsage>,
+}
+
+/// A SQLite database keeping the games of every run, along with their triplets.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::storage::Storage;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let mut storage = Storage::open(dir.path().join("tickets.db")).unwrap();
+/// let run = storage.begin_run(Some(7), 1, 60).unwrap();
+/// assert!(storage.insert_game(run, 1, &[1, 2, 3, 4, 5, 6], 1).unwrap());
+/// // Shares the triplet 1-2-3
+/// assert!(!storage.insert_game(run, 2, &[1, 2, 3, 7, 8, 9], 1).unwrap());
+/// assert_eq!(storage.games().unwrap().len(), 1);
+/// ```
+pub struct Storage {
+    connection: Connection,
+}
+
+impl Storage {
+    /// Opens the database at `path`, creating it and its tables when missing.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let connection = Connection::open(path)?;
+        // One transaction per game is then fast enough, and still survives a crash
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Records a new run, of the same lottery as the runs stored.
+    ///
+    /// # Arguments
+    /// * `seed` - The seed the games are drawn with, `None` for OS entropy.
+    /// * `min_number` - The smallest number of the lottery (e.g., 0 or 1), which the ranks
+    ///   stored count from.
+    /// * `max_number` - The largest number of the lottery.
+    ///
+    /// # Returns
+    /// * The identifier of the run, to store its games under.
+    /// * An error if the runs stored are of another lottery.
+    pub fn begin_run(
+        &mut self,
+        seed: Option<u64>,
+        min_number: i64,
+        max_number: i64,
+    ) -> Result<i64, StorageError> {
+        let stored: Option<(i64, i64)> = self
+            .connection
+            .query_row(
+                "SELECT min_number, max_number FROM runs ORDER BY run_id LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        if let Some((stored_min_number, stored_max_number)) = stored {
+            if (stored_min_number, stored_max_number) != (min_number, max_number) {
+                return Err(StorageError::OtherLottery {
+                    min_number,
+                    max_number,
+                    stored_min_number,
+                    stored_max_number,
+                });
+            }
+        }
+        self.connection.execute(
+            "INSERT INTO runs (seed, min_number, max_number, version) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                seed.map(|seed| seed.to_string()),
+                min_number,
+                max_number,
+                env!("CARGO_PKG_VERSION")
+            ],
+        )?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /// Stores a game of a run along with its triplets, in one transaction.
+    ///
+    /// # Arguments
+    /// * `run_id` - The run, as returned by `begin_run`.
+    /// * `index` - The position of the game in the run, starting at 1.
+    /// * `game` - The 6 numbers of the game, in the order they are played.
+    /// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+    ///
+    /// # Returns
+    /// * `true` once the game is stored.
+    /// * `false` if it shares a triplet with a stored game, or is already stored at `index`,
+    ///   the database being left unchanged.
+    pub fn insert_game(
+        &mut self,
+        run_id: i64,
+        index: usize,
+        game: &[i64],
+        min_number: i64,
+    ) -> Result<bool, StorageError> {
+        if game.len() != 6 {
+            return Err(StorageError::WrongSize {
+                game: game.to_vec(),
+            });
+        }
+        let mut sorted = game.to_vec();
+        sorted.sort_unstable();
+        let transaction = self.connection.transaction()?;
+        let inserted = transaction.execute(
+            "INSERT INTO games (run_id, game, n1, n2, n3, n4, n5, n6, rank)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                run_id,
+                index as i64,
+                game[0],
+                game[1],
+                game[2],
+                game[3],
+                game[4],
+                game[5],
+                custom_utils::game2enum_from(&sorted, min_number)
+            ],
+        );
+        if is_constraint_violation(&inserted) {
+            return Ok(false);
+        }
+        inserted?;
+        {
+            let mut insert_triplet = transaction
+                .prepare_cached("INSERT INTO triplets (rank, run_id, game) VALUES (?1, ?2, ?3)")?;
+            for triplet_no in custom_utils::game2triplet_enums_from(&sorted, min_number) {
+                let inserted = insert_triplet.execute(params![triplet_no, run_id, index as i64]);
+                // Dropping the transaction rolls the game back
+                if is_constraint_violation(&inserted) {
+                    return Ok(false);
+                }
+                inserted?;
+            }
+        }
+        transaction.commit()?;
+        Ok(true)
+    }
+
+    /// The combinadic numbers of the triplets of every game stored.
+    pub fn triplets(&self) -> Result<TripletSet, StorageError> {
+        let mut statement = self.connection.prepare("SELECT rank FROM triplets")?;
+        let mut triplet_set = TripletSet::default();
+        for rank in statement.query_map([], |row| row.get::<_, i64>(0))? {
+            triplet_set.add_number(rank?);
+        }
+        Ok(triplet_set)
+    }
+
+    /// The games stored, run after run, each run's in order.
+    pub fn games(&self) -> Result<Vec<Game>, StorageError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT n1, n2, n3, n4, n5, n6 FROM games ORDER BY run_id, game")?;
+        let games = statement.query_map([], |row| {
+            (0..6)
+                .map(|column| row.get(column))
+                .collect::<Result<Vec<i64>, _>>()
+                .map(Game::new)
+        })?;
+        Ok(games.collect::<Result<_, _>>()?)
+    }
+
+    /// The runs stored, in order, with the number of their games.
+    pub fn runs(&self) -> Result<Vec<StoredRun>, StorageError> {
+        let mut statement = self.connection.prepare(
+            "SELECT runs.run_id, runs.seed, COUNT(games.game) FROM runs
+             LEFT JOIN games ON games.run_id = runs.run_id
+             GROUP BY runs.run_id ORDER BY runs.run_id",
+        )?;
+        let runs = statement.query_map([], |row| {
+            let seed: Option<String> = row.get(1)?;
+            Ok(StoredRun {
+                run_id: row.get(0)?,
+                seed: seed.and_then(|seed| seed.parse().ok()),
+                games: row.get::<_, i64>(2)? as usize,
+            })
+        })?;
+        Ok(runs.collect::<Result<_, _>>()?)
+    }
+
+    /// The runs stored, and how many of their games play each number of
+    /// `min_number..=max_number`.
+    pub fn report(&self, min_number: i64, max_number: i64) -> Result<StorageReport, StorageError> {
+        let games = self.games()?;
+        let mut numbers: Vec<NumberUsage> = (min_number..=max_number)
+            .map(|number| NumberUsage {
+                number,
+                games: games.iter().filter(|game| game.contains(&number)).count(),
+            })
+            .collect();
+        numbers.sort_by_key(|usage| (std::cmp::Reverse(usage.games), usage.number));
+        Ok(StorageReport {
+            runs: self.runs()?,
+            games: games.len(),
+            numbers,
+        })
+    }
+}
+
+/// Whether a statement was turned down by a constraint of the tables, a repeated triplet rank
+/// or a game stored twice.
+fn is_constraint_violation<T>(result: &rusqlite::Result<T>) -> bool {
+    matches!(
+        result,
+        Err(rusqlite::Error::SqliteFailure(error, _)) if error.code == ErrorCode::ConstraintViolation
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::generation;
+    use crate::stats::GenerationStats;
+    use std::collections::HashSet;
+
+    fn config(seed: u64) -> Config {
+        Config::from_toml(&format!(
+            "no_of_games = 25\ninitial_games = []\nseed = {}\nmax_number = 60\nmin_desired_number = 31\n",
+            seed
+        ))
+        .unwrap()
+    }
+
+    /// Generates the games of a run avoiding the triplets stored, and stores them.
+    fn store_run(storage: &mut Storage, config: &Config) -> Vec<Game> {
+        let avoided = storage.triplets().unwrap();
+        let generated =
+            generation::generate_avoiding(config, &avoided, GenerationStats::default()).unwrap();
+        let run_id = storage
+            .begin_run(config.seed, config.min_number, config.max_number)
+            .unwrap();
+        for (index, game) in generated.games.iter().enumerate() {
+            assert!(storage
+                .insert_game(run_id, index + 1, game, config.min_number)
+                .unwrap());
+        }
+        generated.games
+    }
+
+    #[test]
+    fn test_two_runs_share_no_triplet_through_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tickets.db");
+        let mut storage = Storage::open(&path).unwrap();
+        let first = store_run(&mut storage, &config(1));
+        drop(storage);
+        // The second run starts from the database alone
+        let mut storage = Storage::open(&path).unwrap();
+        let second = store_run(&mut storage, &config(2));
+
+        let stored = storage.games().unwrap();
+        assert_eq!(stored, [first.clone(), second].concat());
+        let triplets: HashSet<i64> = stored
+            .iter()
+            .flat_map(|game| custom_utils::game2triplet_enums(game))
+            .collect();
+        assert_eq!(triplets.len(), 20 * stored.len());
+        assert_eq!(storage.triplets().unwrap().len(), triplets.len());
+        assert_eq!(
+            storage.runs().unwrap(),
+            [
+                StoredRun {
+                    run_id: 1,
+                    seed: Some(1),
+                    games: 25
+                },
+                StoredRun {
+                    run_id: 2,
+                    seed: Some(2),
+                    games: 25
+                }
+            ]
+        );
+
+        // The constraint turns down a game of the first run played again, leaving no trace
+        let run_id = storage.begin_run(None, 1, 60).unwrap();
+        assert!(!storage.insert_game(run_id, 1, &first[0], 1).unwrap());
+        assert_eq!(storage.games().unwrap().len(), 50);
+        assert_eq!(storage.triplets().unwrap().len(), 20 * 50);
+        let report = storage.report(1, 60).unwrap();
+        assert_eq!(report.games, 50);
+        assert_eq!(report.numbers.len(), 60);
+        let played: usize = report.numbers.iter().map(|usage| usage.games).sum();
+        assert_eq!(played, 6 * 50);
+        assert_eq!(report.numbers.last().unwrap().games, 0);
+        assert!(matches!(
+            storage.begin_run(None, 0, 60),
+            Err(StorageError::OtherLottery { .. })
+        ));
+        assert!(matches!(
+            storage.insert_game(run_id, 1, &[1, 2, 3], 1),
+            Err(StorageError::WrongSize { .. })
+        ));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::game::{Game, GameStats};
+
+/// Renders games as a table to read tickets off or print: one ticket per row after its
+/// 1-based number, numbers sorted and zero-padded to the width of `max_number`, and a blank
+/// line after every `tickets_per_block` tickets.
+///
+/// # Arguments
+/// * `games` - The games, in order.
+/// * `max_number` - The largest number of the lottery, sizing the number columns.
+/// * `tickets_per_block` - The number of tickets between two blank lines (0 for a single block).
+/// * `stats_range` - The smallest and largest numbers of the lottery, to add the figures of
+///   each ticket (see `Game::stats`) in the columns of `GameStats::COLUMNS`; `None` leaves
+///   them out.
+///
+/// # Returns
+/// * The table, each line ending with a newline.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::game::Game;
+/// use rk_lottery::ticket_table::render_table;
+///
+/// let games = [Game::new(vec![58, 3, 45, 11, 34, 22])];
+/// let table = render_table(&games, 60, 5, None);
+/// assert_eq!(table, "#  Numbers\n1  03 11 22 34 45 58\n");
+/// let table = render_table(&games, 60, 5, Some((1, 60)));
+/// assert_eq!(
+///     table,
+///     "#  Numbers            sum  odd  low  max_run  min  max\n\
+///      1  03 11 22 34 45 58  173    3    3        1    3   58\n"
+/// );
+/// ```
+pub fn render_table(
+    games: &[Game],
+    max_number: i64,
+    tickets_per_block: usize,
+    stats_range: Option<(i64, i64)>,
+) -> String {
+    let index_width = games.len().to_string().len();
+    let number_width = max_number.to_string().len();
+    let rows: Vec<(String, Vec<String>)> = games
+        .iter()
+        .map(|game| {
+            let mut numbers = game.to_vec();
+            numbers.sort_unstable();
+            let numbers: Vec<String> = numbers
+                .iter()
+                .map(|number| format!("{:0width$}", number, width = number_width))
+                .collect();
+            let stats = stats_range
+                .map(|(min, max)| game.stats(min, max).values())
+                .into_iter()
+                .flatten()
+                .map(|value| value.to_string())
+                .collect();
+            (numbers.join(" "), stats)
+        })
+        .collect();
+    // The figures are aligned on the widest tickets and values; without them no line is padded
+    let numbers_width = match stats_range {
+        Some(_) => rows
+            .iter()
+            .map(|(numbers, _)| numbers.len())
+            .fold("Numbers".len(), usize::max),
+        None => 0,
+    };
+    let stats_widths: Vec<usize> = match stats_range {
+        Some(_) => GameStats::COLUMNS
+            .iter()
+            .enumerate()
+            .map(|(column, name)| {
+                rows.iter()
+                    .map(|(_, stats)| stats[column].len())
+                    .fold(name.len(), usize::max)
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    let line = |index: &str, numbers: &str, stats: &[&str]| {
+        let mut line = format!(
+            "{:>index_width$}  {:numbers_width$}",
+            index,
+            numbers,
+            index_width = index_width,
+            numbers_width = numbers_width
+        );
+        for (value, width) in stats.iter().zip(&stats_widths) {
+            line.push_str(&format!("  {:>width$}", value, width = width));
+        }
+        line.push('\n');
+        line
+    };
+    let mut table = line("#", "Numbers", &GameStats::COLUMNS[..stats_widths.len()]);
+    for (index, (numbers, stats)) in rows.iter().enumerate() {
+        if tickets_per_block > 0 && index > 0 && index % tickets_per_block == 0 {
+            table.push('\n');
+        }
+        let stats: Vec<&str> = stats.iter().map(String::as_str).collect();
+        table.push_str(&line(&(index + 1).to_string(), numbers, &stats));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn games() -> Vec<Game> {
+        vec![
+            Game::new(vec![58, 3, 45, 11, 34, 22]),
+            Game::new(vec![1, 2, 7, 9, 30, 60]),
+            Game::new(vec![5, 15, 25, 35, 45, 55]),
+            Game::new(vec![60, 50, 40, 30, 20, 10]),
+            Game::new(vec![4, 8, 12, 16, 23, 42]),
+        ]
+    }
+
+    #[test]
+    fn test_table_in_blocks() {
+        assert_eq!(
+            render_table(&games(), 60, 2, None),
+            "\
+#  Numbers
+1  03 11 22 34 45 58
+2  01 02 07 09 30 60
+
+3  05 15 25 35 45 55
+4  10 20 30 40 50 60
+
+5  04 08 12 16 23 42
+"
+        );
+    }
+
+    #[test]
+    fn test_column_widths_follow_the_largest_numbers() {
+        let mut games = games();
+        games.extend(games.clone());
+        games.extend(games.clone());
+        assert_eq!(
+            render_table(&games[..11], 100, 0, None)
+                .lines()
+                .skip(9)
+                .collect::<Vec<_>>(),
+            [
+                " 9  010 020 030 040 050 060",
+                "10  004 008 012 016 023 042",
+                "11  003 011 022 034 045 058"
+            ]
+        );
+        assert_eq!(render_table(&[], 60, 5, None), "#  Numbers\n");
+    }
+
+    #[test]
+    fn test_stats_columns_follow_the_numbers() {
+        assert_eq!(
+            render_table(&games()[1..4], 60, 2, Some((1, 60))),
+            "\
+#  Numbers            sum  odd  low  max_run  min  max
+1  01 02 07 09 30 60  109    3    5        2    1   60
+2  05 15 25 35 45 55  180    6    3        1    5   55
+
+3  10 20 30 40 50 60  210    0    3        1   10   60
+"
+        );
+    }
+}
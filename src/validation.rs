@@ -0,0 +1,488 @@
+use crate::constraints::{ConstraintSet, GameConstraint, MaxOverlapConstraint, Violation};
+use crate::custom_utils;
+use crate::data_structures::SubsetIndex;
+use crate::game::Game;
+use crate::games_file;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+/// A problem found in a file of games, with the rows involved (starting at 1).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Issue {
+    /// A row cannot be read as a game.
+    Malformed { row: usize, message: String },
+    /// A game breaks the constraints.
+    Invalid { row: usize, violations: Vec<String> },
+    /// A game shares a triplet with an earlier one.
+    SharedTriplet {
+        first_row: usize,
+        row: usize,
+        triplet: Vec<i64>,
+    },
+    /// A game shares more than `max_overlap` numbers with an earlier one.
+    Overlap {
+        first_row: usize,
+        row: usize,
+        shared: usize,
+        max_overlap: usize,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Malformed { row, message } => write!(f, "row {} is malformed: {}", row, message),
+            Issue::Invalid { row, violations } => {
+                write!(f, "row {} is invalid: {}", row, violations.join("; "))
+            }
+            Issue::SharedTriplet {
+                first_row,
+                row,
+                triplet,
+            } => write!(
+                f,
+                "rows {} and {} share triplet {}",
+                first_row,
+                row,
+                Game::new(triplet.clone())
+            ),
+            Issue::Overlap {
+                first_row,
+                row,
+                shared,
+                max_overlap,
+            } => write!(
+                f,
+                "rows {} and {} share {} numbers, at most {} allowed",
+                first_row, row, shared, max_overlap
+            ),
+        }
+    }
+}
+
+/// The outcome of checking a file of games.
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    /// Number of rows read.
+    pub rows: usize,
+    /// The problems found, in row order.
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    /// Whether the file is free of problems.
+    pub fn passed(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Checks the games of a CSV file (one game per row, as written by the generator, with or
+/// without the header row and index column) against the constraints, that no two games of 6
+/// numbers share a triplet, and that no two games share more than `max_overlap` numbers.
+///
+/// Rows that cannot be read are reported and skipped, so a single run reports every problem.
+/// Triplets and overlaps are only compared between games that follow the constraints. Rows
+/// are numbered from the first game, the header row left out.
+///
+/// # Arguments
+/// * `reader` - The CSV content.
+/// * `constraints` - The rules every game must follow.
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+/// * `max_overlap` - The most numbers two games may share, if limited.
+/// * `delimiter` - The field separator expected, unless the first row tells another one.
+///
+/// # Returns
+/// * The report of the problems found.
+pub fn validate_csv<R: io::BufRead>(
+    reader: R,
+    constraints: &ConstraintSet,
+    min_number: i64,
+    max_overlap: Option<usize>,
+    delimiter: u8,
+) -> ValidationReport {
+    let (_, games) = games_file::read_rows(reader, delimiter);
+    // The rows of the games containing each triplet, by combinadic number
+    let mut triplet_rows: HashMap<i64, Vec<usize>> = HashMap::new();
+    // The games compared for their overlap, with the row of each
+    let mut overlap =
+        max_overlap.map(|max_overlap| MaxOverlapConstraint::new(max_overlap, min_number));
+    let mut overlap_rows = Vec::new();
+    let rows = games.len();
+    let mut issues = Vec::new();
+    for (index, game) in games.into_iter().enumerate() {
+        let row = index + 1;
+        let game = match game {
+            Ok(game) => game,
+            Err(message) => {
+                issues.push(Issue::Malformed { row, message });
+                continue;
+            }
+        };
+
+        let violations = constraints.violations(&game);
+        if !violations.is_empty() {
+            issues.push(Issue::Invalid {
+                row,
+                violations: violations.iter().map(|v| v.to_string()).collect(),
+            });
+            continue;
+        }
+
+        if let Some(overlap) = &mut overlap {
+            for violation in overlap.check_all(&game) {
+                if let Violation::Overlap {
+                    game: index,
+                    shared,
+                    max_overlap,
+                } = violation
+                {
+                    issues.push(Issue::Overlap {
+                        first_row: overlap_rows[index - 1],
+                        row,
+                        shared,
+                        max_overlap,
+                    });
+                }
+            }
+            overlap.accept(&game);
+            overlap_rows.push(row);
+        }
+
+        // Report each earlier game sharing a triplet once, with the first triplet shared
+        let game = game.sorted();
+        let Some(triplet_nos) = constraints.triplet_nos(&game, min_number) else {
+            continue;
+        };
+        let mut reported = Vec::new();
+        for (triplet_no, triplet) in triplet_nos.iter().zip(custom_utils::game2triplets(&game)) {
+            let owners = triplet_rows.entry(*triplet_no).or_default();
+            for &first_row in owners.iter() {
+                if !reported.contains(&first_row) {
+                    reported.push(first_row);
+                    issues.push(Issue::SharedTriplet {
+                        first_row,
+                        row,
+                        triplet: triplet.clone(),
+                    });
+                }
+            }
+            owners.push(row);
+        }
+    }
+    ValidationReport { rows, issues }
+}
+
+/// Why a candidate game would or would not be accepted after a list of games.
+#[derive(Debug, PartialEq)]
+pub struct Explanation {
+    /// The rules of the constraints the candidate breaks, constraint by constraint.
+    pub violations: Vec<Violation>,
+    /// The row (starting at 1) of the game equal to the candidate, if any.
+    pub duplicate_of: Option<usize>,
+    /// Each triplet of the candidate held by a game, with the row of the first game holding
+    /// it, in the order of the triplets of the sorted candidate; left out for a duplicate.
+    pub shared_triplets: Vec<(Vec<i64>, usize)>,
+}
+
+impl Explanation {
+    /// Whether a run would accept the candidate.
+    pub fn accepted(&self) -> bool {
+        self.violations.is_empty() && self.duplicate_of.is_none() && self.shared_triplets.is_empty()
+    }
+}
+
+/// Runs a candidate game through the checks of a run having accepted `games`: the
+/// constraints, which see the games accepted first, then the sets of games and triplets,
+/// finding the games the candidate repeats or shares triplets with.
+///
+/// Unlike a run, which stops at the first check failed, every check is made. The triplets are
+/// only compared for a candidate of 6 distinct numbers of the lottery, the others having none
+/// to rank.
+///
+/// # Arguments
+/// * `candidate` - The numbers of the candidate, in any order.
+/// * `games` - The games accepted before the candidate, in order.
+/// * `constraints` - The rules every game must follow.
+/// * `min_number` - The smallest number of the lottery (e.g., 0 or 1).
+///
+/// # Returns
+/// * Every reason the candidate would be turned down, none if it would be accepted.
+pub fn explain(
+    candidate: &[i64],
+    games: &[Game],
+    mut constraints: ConstraintSet,
+    min_number: i64,
+) -> Explanation {
+    for game in games {
+        constraints.accept(game);
+    }
+    let violations = constraints.violations(candidate);
+
+    let mut sorted = candidate.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    let mut explanation = Explanation {
+        violations,
+        duplicate_of: None,
+        shared_triplets: Vec::new(),
+    };
+    if sorted.len() != 6 || candidate.len() != 6 || sorted[0] < min_number {
+        return explanation;
+    }
+    let game_no = custom_utils::game2enum_from(&sorted, min_number);
+    explanation.duplicate_of = games
+        .iter()
+        .position(|game| {
+            custom_utils::game2enum_from(&game.clone().sorted(), min_number) == game_no
+        })
+        .map(|index| index + 1);
+    if explanation.duplicate_of.is_some() {
+        return explanation;
+    }
+
+    // The row of the first game holding each triplet, by combinadic number
+    let mut triplet_rows = SubsetIndex::new();
+    for (index, game) in games.iter().enumerate().filter(|(_, game)| game.len() == 6) {
+        for triplet_no in custom_utils::game2triplet_enums_from(game, min_number) {
+            triplet_rows.insert(triplet_no, index + 1);
+        }
+    }
+    explanation.shared_triplets = custom_utils::game2triplet_enums_from(&sorted, min_number)
+        .iter()
+        .zip(custom_utils::game2triplets(&sorted))
+        .filter_map(|(triplet_no, triplet)| {
+            triplet_rows.owner(*triplet_no).map(|row| (triplet, row))
+        })
+        .collect();
+    explanation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::{
+        ArithmeticProgressionConstraint, DistinctConstraint, LengthConstraint, RangeConstraint,
+    };
+
+    fn validate(content: &str) -> ValidationReport {
+        let constraints = ConstraintSet::new()
+            .with(LengthConstraint {
+                numbers_per_game: 6,
+            })
+            .with(RangeConstraint::new(1, 60))
+            .with(DistinctConstraint);
+        validate_csv(
+            content.as_bytes(),
+            &constraints,
+            1,
+            None,
+            games_file::DEFAULT_DELIMITER,
+        )
+    }
+
+    #[test]
+    fn test_clean_file_passes() {
+        let report = validate("1,2,3,4,5,6\n1,2,7,8,9,10\n11,12,13,14,15,16\n");
+        assert_eq!(report.rows, 3);
+        assert!(report.passed(), "{:?}", report.issues);
+    }
+
+    #[test]
+    fn test_shared_triplet_reports_both_rows() {
+        let report = validate("1,2,3,4,5,6\n20,21,22,23,24,25\n22,30,20,40,24,50\n");
+        assert_eq!(
+            report.issues,
+            vec![Issue::SharedTriplet {
+                first_row: 2,
+                row: 3,
+                triplet: vec![20, 22, 24],
+            }]
+        );
+        assert_eq!(
+            report.issues[0].to_string(),
+            "rows 2 and 3 share triplet 20-22-24"
+        );
+    }
+
+    #[test]
+    fn test_games_of_15_numbers_are_compared_by_their_overlap() {
+        let constraints = ConstraintSet::new()
+            .with(LengthConstraint {
+                numbers_per_game: 15,
+            })
+            .with(RangeConstraint::new(1, 25));
+        let rows: Vec<Vec<i64>> = vec![
+            (1..=15).collect(),
+            (11..=25).collect(),
+            (1..=10).chain(16..=20).collect(),
+            (1..=11).chain(21..=24).collect(),
+        ];
+        let content: String = rows
+            .iter()
+            .map(|row| {
+                let numbers: Vec<String> = row.iter().map(|x| x.to_string()).collect();
+                numbers.join(",") + "\n"
+            })
+            .collect();
+        let report = validate_csv(
+            content.as_bytes(),
+            &constraints,
+            1,
+            Some(10),
+            games_file::DEFAULT_DELIMITER,
+        );
+        // Sharing triplets, which every two games of 15 of 25 numbers do, is no issue
+        assert_eq!(
+            report.issues,
+            vec![Issue::Overlap {
+                first_row: 1,
+                row: 4,
+                shared: 11,
+                max_overlap: 10,
+            }]
+        );
+        assert_eq!(
+            report.issues[0].to_string(),
+            "rows 1 and 4 share 11 numbers, at most 10 allowed"
+        );
+    }
+
+    #[test]
+    fn test_each_earlier_row_sharing_a_triplet_is_reported_once() {
+        let report = validate(
+            "1,2,3,4,5,6\n20,21,22,23,24,25\n1,2,3,4,21,22\n40,41,42,43,44,45\n1,2,21,22,23,44\n",
+        );
+        let rows: Vec<(usize, usize)> = report
+            .issues
+            .iter()
+            .map(|issue| match issue {
+                Issue::SharedTriplet { first_row, row, .. } => (*first_row, *row),
+                _ => panic!("unexpected issue {}", issue),
+            })
+            .collect();
+        // Row 5 shares 01-02-21 with row 3 and 21-22-23 with row 2, kept as their first row
+        assert_eq!(rows, [(1, 3), (3, 5), (2, 5)]);
+
+        // A triplet held by three rows is reported for each pair of them
+        let report = validate("1,2,3,10,20,30\n1,2,3,11,21,31\n1,2,3,12,22,32\n");
+        let rows: Vec<(usize, usize)> = report
+            .issues
+            .iter()
+            .map(|issue| match issue {
+                Issue::SharedTriplet { first_row, row, .. } => (*first_row, *row),
+                _ => panic!("unexpected issue {}", issue),
+            })
+            .collect();
+        assert_eq!(rows, [(1, 2), (1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn test_malformed_and_invalid_rows_are_reported_and_skipped() {
+        let report = validate("1,2,3,4,5,6\n1,2,x,8,9,10\n1,2,3,4,5\n1,2,3,7,8,9\n");
+        assert_eq!(report.rows, 4);
+        assert_eq!(report.issues.len(), 3);
+        assert!(matches!(report.issues[0], Issue::Malformed { row: 2, .. }));
+        assert!(matches!(report.issues[1], Issue::Invalid { row: 3, .. }));
+        assert!(matches!(
+            report.issues[2],
+            Issue::SharedTriplet {
+                first_row: 1,
+                row: 4,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_indexed_file_is_read_without_its_header() {
+        let report = validate("game,n1,n2,n3,n4,n5,n6\n1,1,2,3,4,5,6\n2,1,2,3,7,8,9\n");
+        assert_eq!(report.rows, 2);
+        assert_eq!(
+            report.issues,
+            vec![Issue::SharedTriplet {
+                first_row: 1,
+                row: 2,
+                triplet: vec![1, 2, 3],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_explain_reports_every_reason_to_turn_a_candidate_down() {
+        let constraints = || {
+            ConstraintSet::new()
+                .with(LengthConstraint {
+                    numbers_per_game: 6,
+                })
+                .with(RangeConstraint::new(10, 60))
+                .with(DistinctConstraint)
+                .with(ArithmeticProgressionConstraint { max_run: 2 })
+        };
+        let games = [
+            Game::new(vec![10, 20, 30, 40, 50, 60]),
+            Game::new(vec![11, 12, 13, 24, 35, 46]),
+        ];
+
+        // Below the range and on a progression, and sharing a triplet with the second game
+        let explanation = explain(&[12, 1, 3, 5, 13, 11], &games, constraints(), 1);
+        assert!(!explanation.accepted());
+        assert_eq!(
+            explanation.violations,
+            vec![
+                Violation::BelowMinimum { value: 1 },
+                Violation::BelowMinimum { value: 3 },
+                Violation::BelowMinimum { value: 5 },
+                Violation::ArithmeticProgression {
+                    start: 1,
+                    difference: 2,
+                    length: 3,
+                },
+            ]
+        );
+        assert_eq!(explanation.duplicate_of, None);
+        assert_eq!(explanation.shared_triplets, vec![(vec![11, 12, 13], 2)]);
+
+        let explanation = explain(&[10, 20, 41, 50, 57, 60], &games, constraints(), 1);
+        assert!(explanation.violations.is_empty());
+        let rows: Vec<usize> = explanation
+            .shared_triplets
+            .iter()
+            .map(|(_, row)| *row)
+            .collect();
+        assert_eq!(rows, vec![1; 4]);
+
+        // A game of the list is a duplicate, triplets aside
+        let explanation = explain(&[60, 50, 40, 30, 20, 10], &games, constraints(), 1);
+        assert_eq!(explanation.duplicate_of, Some(1));
+        assert!(explanation.shared_triplets.is_empty());
+
+        // Too short to rank, only the constraints tell
+        let explanation = explain(&[11, 12, 24], &games, constraints(), 1);
+        assert_eq!(
+            explanation.violations,
+            vec![Violation::WrongLength {
+                expected: 6,
+                actual: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn test_explain_accepts_a_candidate_passing_every_check() {
+        let games = [Game::new(vec![1, 2, 3, 4, 5, 6])];
+        let explanation = explain(&[1, 2, 7, 8, 9, 20], &games, ConstraintSet::new(), 1);
+        assert!(explanation.accepted(), "{:?}", explanation);
+    }
+
+    #[test]
+    fn test_report_serializes_to_json() {
+        let report = validate("1,2,3,4,5,6\n1,2,3,7,8,9\n");
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["rows"], 2);
+        assert_eq!(json["issues"][0]["kind"], "shared-triplet");
+        assert_eq!(json["issues"][0]["triplet"], serde_json::json!([1, 2, 3]));
+    }
+}
@@ -0,0 +1,233 @@
+use crate::custom_utils;
+use crate::data_structures::{DenseNumberSet, RankSet};
+use crate::game::Game;
+
+/// The most distinct numbers a wheel pool may have. Every game of the pool is a candidate,
+/// scanned again for each game picked: 30 numbers already make 593,775 candidates and take
+/// seconds, while 45 would make over 8 million.
+pub const MAX_POOL_SIZE: usize = 30;
+
+/// Generates an abbreviated wheel: a set of games drawn from `pool` such that any
+/// `guarantee` numbers of the pool appear together in at least one game.
+///
+/// Games are chosen greedily, each time picking the game that covers the most
+/// `guarantee`-subsets not yet covered, so the result is small but not necessarily minimal.
+///
+/// # Arguments
+/// * `pool` - The favorite numbers to wheel.
+/// * `guarantee` - The number of drawn pool numbers guaranteed to appear in a single game.
+/// * `numbers_per_game` - The number of numbers per game (e.g., 6).
+///
+/// # Returns
+/// * The games of the wheel, each in ascending order.
+/// * An error if the pool or the guarantee are not usable, e.g. for a pool of more than
+///   `MAX_POOL_SIZE` numbers.
+///
+/// # Examples
+/// ```
+/// use rk_lottery::wheel::{generate_wheel, verify_wheel};
+///
+/// let pool = [1, 5, 9, 14, 23, 31, 42, 57];
+/// let games = generate_wheel(&pool, 3, 6).unwrap();
+/// assert!(verify_wheel(&games, &pool, 3).is_empty());
+/// ```
+pub fn generate_wheel(
+    pool: &[i64],
+    guarantee: usize,
+    numbers_per_game: usize,
+) -> Result<Vec<Game>, Box<dyn std::error::Error>> {
+    let mut pool = pool.to_vec();
+    pool.sort_unstable();
+    pool.dedup();
+    if pool.len() < numbers_per_game {
+        return Err(format!(
+            "The wheel pool needs at least {} distinct numbers, got {}",
+            numbers_per_game,
+            pool.len()
+        )
+        .into());
+    }
+    if pool.len() > MAX_POOL_SIZE {
+        return Err(format!(
+            "The wheel pool can have at most {} distinct numbers, got {}",
+            MAX_POOL_SIZE,
+            pool.len()
+        )
+        .into());
+    }
+    if guarantee == 0 || guarantee > numbers_per_game {
+        return Err(format!(
+            "The wheel guarantee must be between 1 and {}, got {}",
+            numbers_per_game, guarantee
+        )
+        .into());
+    }
+
+    // Subsets are ranked from the smallest pool number, which also covers lotteries from 0
+    let min_number = pool[0];
+    let candidates = custom_utils::game2ksubsets(&pool, numbers_per_game);
+    let candidate_subsets: Vec<Vec<i64>> = candidates
+        .iter()
+        .map(|game| {
+            custom_utils::game2ksubsets(game, guarantee)
+                .iter()
+                .map(|subset| {
+                    custom_utils::subset2enum_from(subset, guarantee, min_number)
+                        .expect("the pool numbers are distinct and from its smallest up")
+                })
+                .collect()
+        })
+        .collect();
+    let mut remaining = custom_utils::binomial(pool.len() as i64, guarantee as i64);
+    // The bits grow up to the largest subset rank covered
+    let mut covered = DenseNumberSet::default();
+    let mut games = Vec::new();
+
+    while remaining > 0 {
+        // Pick the candidate covering the most uncovered subsets, the first one on ties
+        let (best, _) = candidate_subsets
+            .iter()
+            .enumerate()
+            .map(|(i, subsets)| {
+                let uncovered = subsets.iter().filter(|&&s| !covered.contains(s)).count();
+                (i, uncovered)
+            })
+            .fold(
+                (0, 0),
+                |best, current| {
+                    if current.1 > best.1 {
+                        current
+                    } else {
+                        best
+                    }
+                },
+            );
+
+        for &subset_no in &candidate_subsets[best] {
+            if covered.add_number(subset_no) {
+                remaining -= 1;
+            }
+        }
+        games.push(Game::new(candidates[best].clone()));
+    }
+
+    Ok(games)
+}
+
+/// Checks that a set of games is a wheel of `pool` for the given guarantee.
+///
+/// # Arguments
+/// * `games` - The games of the wheel.
+/// * `pool` - The wheeled numbers.
+/// * `guarantee` - The number of drawn pool numbers that must appear in a single game.
+///
+/// # Returns
+/// * The `guarantee`-subsets of the pool not contained in any game; empty if the guarantee holds.
+pub fn verify_wheel(games: &[Game], pool: &[i64], guarantee: usize) -> Vec<Vec<i64>> {
+    // Subsets are ranked from the smallest number seen, so that no two share a rank
+    let min_number = games
+        .iter()
+        .flat_map(|game| game.iter())
+        .chain(pool)
+        .copied()
+        .min()
+        .unwrap_or(1);
+    // A subset repeating a number covers none of the pool
+    let mut covered = DenseNumberSet::default();
+    for game in games {
+        let mut numbers = game.to_vec();
+        numbers.sort_unstable();
+        custom_utils::for_each_ksubset(&numbers, guarantee, |subset| {
+            if let Ok(rank) = custom_utils::subset2enum_from(subset, guarantee, min_number) {
+                covered.add_number(rank);
+            }
+        });
+    }
+
+    let mut pool = pool.to_vec();
+    pool.sort_unstable();
+    pool.dedup();
+    custom_utils::game2ksubsets(&pool, guarantee)
+        .into_iter()
+        .filter(|subset| {
+            let rank = custom_utils::subset2enum_from(subset, guarantee, min_number)
+                .expect("the pool numbers are distinct and from the smallest number up");
+            !covered.contains(rank)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wheel_9_numbers_3_if_3() {
+        let pool = vec![5, 12, 23, 31, 38, 44, 50, 57, 60];
+        let games = generate_wheel(&pool, 3, 6).unwrap();
+
+        assert!(verify_wheel(&games, &pool, 3).is_empty());
+        // Far fewer games than the C(9, 6) = 84 of a full wheel
+        assert!(games.len() <= 12, "Wheel has {} games", games.len());
+        for game in &games {
+            assert_eq!(game.len(), 6);
+            assert!(game.iter().all(|number| pool.contains(number)));
+        }
+    }
+
+    #[test]
+    fn test_wheel_12_numbers_4_if_4() {
+        let pool: Vec<i64> = (31..=42).collect();
+        let games = generate_wheel(&pool, 4, 6).unwrap();
+        assert!(verify_wheel(&games, &pool, 4).is_empty());
+    }
+
+    #[test]
+    fn test_wheel_with_zero_in_pool() {
+        let pool: Vec<i64> = (0..=8).collect();
+        let games = generate_wheel(&pool, 3, 6).unwrap();
+        assert!(verify_wheel(&games, &pool, 3).is_empty());
+        assert!(games.iter().any(|game| game.contains(&0)));
+
+        // Without the games containing 0, all C(8, 2) triplets with 0 are reported missing
+        let without_zero: Vec<Game> = games
+            .into_iter()
+            .filter(|game| !game.contains(&0))
+            .collect();
+        let missing = verify_wheel(&without_zero, &pool, 3);
+        assert_eq!(
+            missing.iter().filter(|subset| subset.contains(&0)).count(),
+            28
+        );
+    }
+
+    #[test]
+    fn test_wheel_of_exactly_one_game() {
+        let pool = vec![60, 1, 2, 3, 4, 5];
+        assert_eq!(
+            generate_wheel(&pool, 6, 6).unwrap(),
+            vec![Game::new(vec![1, 2, 3, 4, 5, 60])]
+        );
+    }
+
+    #[test]
+    fn test_verify_wheel_reports_missing_subsets() {
+        let pool = vec![1, 2, 3, 4, 5, 6, 7];
+        let missing = verify_wheel(&[Game::new(vec![1, 2, 3, 4, 5, 6])], &pool, 2);
+        assert_eq!(missing.len(), 6); // Every pair containing 7
+        assert!(missing.iter().all(|pair| pair.contains(&7)));
+    }
+
+    #[test]
+    fn test_wheel_rejects_bad_parameters() {
+        assert!(generate_wheel(&[1, 2, 3, 4, 5], 3, 6).is_err());
+        assert!(generate_wheel(&[1, 2, 3, 4, 5, 5], 3, 6).is_err());
+        assert!(generate_wheel(&[1, 2, 3, 4, 5, 6, 7], 0, 6).is_err());
+        assert!(generate_wheel(&[1, 2, 3, 4, 5, 6, 7], 7, 6).is_err());
+        let pool: Vec<i64> = (1..=MAX_POOL_SIZE as i64 + 1).collect();
+        assert_eq!(
+            generate_wheel(&pool, 3, 6).unwrap_err().to_string(),
+            "The wheel pool can have at most 30 distinct numbers, got 31"
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::game::Game;
+use crate::games_file;
+use crate::stats::GenerationStats;
+use rust_xlsxwriter::{DocProperties, ExcelDateTime, Format, Workbook, XlsxError};
+
+/// Name of the sheet holding the games.
+pub const GAMES_SHEET: &str = "Games";
+
+/// Name of the sheet holding the summary of the run.
+pub const SUMMARY_SHEET: &str = "Summary";
+
+/// Writes games as an Excel workbook: a sheet of games, one per row after its 1-based index and
+/// followed by its sum and number of odd numbers, and a sheet summing up the run.
+///
+/// The workbook is dated 2000-01-01 rather than when written, and the summary leaves the
+/// elapsed time out, so the same games and counters always give the same file.
+///
+/// # Arguments
+/// * `games` - The games, in order.
+/// * `numbers_per_game` - The number of numbers per game, sizing the header row.
+/// * `stats` - The counters of the run that generated the games, `None` for games made
+///   otherwise (e.g., a wheel), only counted then.
+///
+/// # Returns
+/// * The content of the file.
+pub fn to_xlsx(
+    games: &[Game],
+    numbers_per_game: usize,
+    stats: Option<&GenerationStats>,
+) -> Result<Vec<u8>, XlsxError> {
+    let mut workbook = Workbook::new();
+    let created = ExcelDateTime::from_ymd(2000, 1, 1)?;
+    workbook.set_properties(&DocProperties::new().set_creation_datetime(&created));
+    let bold = Format::new().set_bold();
+
+    let sheet = workbook.add_worksheet().set_name(GAMES_SHEET)?;
+    let mut header = games_file::header(numbers_per_game);
+    header.extend(["sum".to_string(), "odd".to_string()]);
+    // The label column only shows when some game is labeled
+    let labeled = games.iter().any(|game| game.label().is_some());
+    if labeled {
+        header.push(games_file::LABEL_COLUMN.to_string());
+    }
+    sheet.write_row_with_format(0, 0, header, &bold)?;
+    sheet.set_freeze_panes(1, 0)?;
+    for (index, game) in games.iter().enumerate() {
+        let row = index as u32 + 1;
+        sheet.write_number(row, 0, row as f64)?;
+        sheet.write_row(row, 1, game.iter().map(|&number| number as f64))?;
+        let sum: i64 = game.iter().sum();
+        let odd = game.iter().filter(|&&number| number % 2 != 0).count();
+        let column = game.len() as u16 + 1;
+        sheet.write_number(row, column, sum as f64)?;
+        sheet.write_number(row, column + 1, odd as f64)?;
+        if let Some(label) = game.label() {
+            sheet.write_string(row, column + 2, label)?;
+        }
+    }
+
+    let mut summary = vec![("games", games.len() as f64)];
+    if let Some(stats) = stats {
+        summary.extend([
+            ("requested", stats.requested as f64),
+            ("attempts", stats.attempts as f64),
+            ("accepted", stats.accepted as f64),
+            ("duplicate games", stats.duplicate_games as f64),
+            ("rejected by constraints", stats.rejected as f64),
+            ("shared triplets", stats.shared_triplets as f64),
+        ]);
+    }
+    let sheet = workbook.add_worksheet().set_name(SUMMARY_SHEET)?;
+    sheet.write_row_with_format(0, 0, ["statistic", "value"], &bold)?;
+    let constraints = stats
+        .into_iter()
+        .flat_map(|stats| &stats.constraint_rejections);
+    let rows = summary
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .chain(constraints.map(|(name, &count)| (format!("rejected by {}", name), count as f64)));
+    for (index, (name, value)) in rows.enumerate() {
+        let row = index as u32 + 1;
+        sheet.write_string(row, 0, name)?;
+        sheet.write_number(row, 1, value)?;
+    }
+    sheet.autofit();
+
+    workbook.save_to_buffer()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calamine::{Data, DataType, Reader, Xlsx};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_games_and_summary_read_back() {
+        let games = [
+            Game::new(vec![1, 2, 3, 4, 5, 6]),
+            Game::new(vec![7, 9, 11, 20, 40, 60]),
+        ];
+        let mut stats = GenerationStats::default();
+        stats.requested = 2;
+        stats.attempts = 5;
+        stats.accepted = 2;
+        stats.shared_triplets = 3;
+        stats.constraint_rejections.insert("range".to_string(), 4);
+        let content = to_xlsx(&games, 6, Some(&stats)).unwrap();
+        assert_eq!(content, to_xlsx(&games, 6, Some(&stats)).unwrap());
+
+        let mut workbook: Xlsx<_> = calamine::open_workbook_from_rs(Cursor::new(content)).unwrap();
+        assert_eq!(workbook.sheet_names(), [GAMES_SHEET, SUMMARY_SHEET]);
+        let sheet = workbook.worksheet_range(GAMES_SHEET).unwrap();
+        assert_eq!(sheet.get_size(), (3, 9));
+        assert_eq!(sheet.get_value((0, 0)), Some(&Data::String("game".into())));
+        assert_eq!(sheet.get_value((0, 8)), Some(&Data::String("odd".into())));
+        let row: Vec<f64> = sheet
+            .rows()
+            .nth(2)
+            .unwrap()
+            .iter()
+            .map(|cell| cell.as_f64().unwrap())
+            .collect();
+        assert_eq!(row, [2.0, 7.0, 9.0, 11.0, 20.0, 40.0, 60.0, 147.0, 3.0]);
+
+        let summary = workbook.worksheet_range(SUMMARY_SHEET).unwrap();
+        let rows: Vec<(String, f64)> = summary
+            .rows()
+            .skip(1)
+            .map(|row| (row[0].to_string(), row[1].as_f64().unwrap()))
+            .collect();
+        assert_eq!(rows[0], ("games".to_string(), 2.0));
+        assert_eq!(rows[2], ("attempts".to_string(), 5.0));
+        assert_eq!(
+            rows.last().unwrap(),
+            &("rejected by range".to_string(), 4.0)
+        );
+    }
+
+    #[test]
+    fn test_games_made_otherwise_are_only_counted() {
+        let content = to_xlsx(&[Game::new(vec![1, 2, 3, 4, 5, 6])], 6, None).unwrap();
+        let mut workbook: Xlsx<_> = calamine::open_workbook_from_rs(Cursor::new(content)).unwrap();
+        let summary = workbook.worksheet_range(SUMMARY_SHEET).unwrap();
+        assert_eq!(summary.get_size(), (2, 2));
+    }
+}
@@ -0,0 +1,2789 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+const CONFIG: &str =
+    "no_of_games = 3\ninitial_games = []\nseed = 1\nmax_number = 60\nmin_desired_number = 1\n";
+
+/// Reads an output file with its header row, checking the header and the index column, and
+/// returns the numbers of each game.
+fn read_output(path: &std::path::Path) -> Vec<Vec<i64>> {
+    let mut rdr = csv::Reader::from_path(path).unwrap();
+    assert_eq!(
+        rdr.headers().unwrap(),
+        vec!["game", "n1", "n2", "n3", "n4", "n5", "n6"]
+    );
+    rdr.records()
+        .enumerate()
+        .map(|(index, record)| {
+            let record = record.unwrap();
+            assert_eq!(record[0], (index + 1).to_string());
+            record.iter().skip(1).map(|x| x.parse().unwrap()).collect()
+        })
+        .collect()
+}
+
+/// Runs the program with the given arguments in a directory holding `settings.toml`,
+/// returning the rows written to `path`.
+fn run(args: &[&str], path: &str) -> Vec<Vec<i64>> {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("settings.toml"), CONFIG).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .args(["--config", "settings.toml"])
+        .args(args)
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    read_output(&dir.path().join(path))
+}
+
+#[test]
+fn test_flags_override_the_configuration_file() {
+    assert_eq!(run(&[], "optimized_games.csv").len(), 3);
+
+    let games = run(
+        &[
+            "--games",
+            "5",
+            "--max-number",
+            "30",
+            "--min-desired",
+            "10",
+            "--output",
+            "tickets.csv",
+        ],
+        "tickets.csv",
+    );
+    assert_eq!(games.len(), 5);
+    assert!(games.iter().flatten().all(|&x| (10..=30).contains(&x)));
+}
+
+#[test]
+fn test_seed_flag_changes_the_games() {
+    let first = run(&["--seed", "1"], "optimized_games.csv");
+    assert_eq!(run(&[], "optimized_games.csv"), first);
+    assert_ne!(run(&["--seed", "2"], "optimized_games.csv"), first);
+}
+
+#[test]
+fn test_same_seed_gives_byte_identical_files() {
+    for config in [CONFIG.to_string(), format!("{}threads = 2\n", CONFIG)] {
+        let files = || {
+            let dir = tempfile::tempdir().unwrap();
+            fs::write(dir.path().join("config.toml"), &config).unwrap();
+            let status = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+                .args(["--quiet", "--games", "40"])
+                .current_dir(dir.path())
+                .status()
+                .unwrap();
+            assert!(status.success());
+            ["optimized_games.csv", "games.csv", "triplet_set.log"]
+                .map(|file| fs::read(dir.path().join(file)).unwrap())
+        };
+        assert_eq!(files(), files());
+    }
+}
+
+#[test]
+fn test_help_documents_every_flag() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .arg("--help")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let help = String::from_utf8_lossy(&output.stdout);
+    for flag in [
+        "--config",
+        "--games",
+        "--seed",
+        "--max-number",
+        "--min-desired",
+        "--output",
+        "--quiet",
+        "--json-summary",
+    ] {
+        assert!(help.contains(flag), "{} is not documented", flag);
+    }
+    assert!(help.contains("replace the values of the file"));
+}
+
+#[test]
+fn test_completions_and_man_pages_cover_the_subcommands() {
+    let output = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?} failed", args);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    for shell in ["bash", "zsh", "fish", "powershell"] {
+        let script = output(&["completions", shell]);
+        for name in ["validate", "check", "verify-manifest", "strategy"] {
+            assert!(script.contains(name), "{} completion lacks {}", shell, name);
+        }
+        // PowerShell completes the flags, not their values
+        if shell != "powershell" {
+            assert!(script.contains("greedy-coverage") && script.contains("ndjson"));
+        }
+    }
+    assert!(output(&["man"]).contains("verify\\-manifest"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let pages = dir.path().join("man");
+    output(&["man", "--output", pages.to_str().unwrap()]);
+    for page in [
+        "rk_lottery.1",
+        "rk_lottery-validate.1",
+        "rk_lottery-analyze-pairs.1",
+    ] {
+        assert!(pages.join(page).exists(), "{} was not written", page);
+    }
+}
+
+#[test]
+fn test_json_summary_reports_the_attempt_statistics() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("settings.toml"), CONFIG).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .args(["--config", "settings.toml", "--quiet", "--json-summary"])
+        .env_remove("RUST_LOG")
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let stats = &summary["stats"];
+    assert_eq!(stats["requested"], 3);
+    assert_eq!(stats["games"], 3);
+    let attempts = stats["attempts"].as_u64().unwrap();
+    // Every candidate drawn for the first time is remembered, and the sets were sized upfront
+    let sets = &summary["sets"];
+    assert_eq!(
+        sets["games"]["len"].as_u64().unwrap(),
+        attempts - stats["duplicate_games"].as_u64().unwrap()
+    );
+    assert_eq!(sets["triplets"]["len"], 60);
+    assert!(sets["triplets"]["capacity"].as_u64().unwrap() >= 60);
+    assert!(sets["triplets"]["bytes"].as_u64().unwrap() > 60 * 9);
+    let turned_down = ["duplicate_games", "rejected", "shared_triplets"]
+        .iter()
+        .map(|key| stats[*key].as_u64().unwrap())
+        .sum::<u64>();
+    assert_eq!(attempts, 3 + turned_down);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_debug_log_gives_the_reason_of_every_rejection() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("settings.toml"),
+        format!(
+            "{}max_arithmetic_run = 2\n",
+            CONFIG.replace("no_of_games = 3", "no_of_games = 30")
+        ),
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .args(["--config", "settings.toml", "--quiet", "--json-summary"])
+        .env("RUST_LOG", "rk_lottery=debug")
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let stats = &summary["stats"];
+    let log = String::from_utf8_lossy(&output.stderr);
+    let count = |reason: &str| {
+        log.lines()
+            .filter(|line| line.contains("Rejected candidate") && line.contains(reason))
+            .count() as u64
+    };
+    assert_eq!(count("drawn before"), stats["duplicate_games"]);
+    assert_eq!(count("breaks a constraint: "), stats["rejected"]);
+    assert_eq!(count("shares a triplet"), stats["shared_triplets"]);
+    assert!(stats["rejected"].as_u64().unwrap() > 0);
+    assert!(log.contains("Wrote 30 games to optimized_games.csv"));
+}
+
+#[test]
+fn test_debug_log_names_the_game_holding_a_shared_triplet() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("settings.toml"),
+        format!(
+            "{}track_triplet_owners = true\n",
+            CONFIG.replace("no_of_games = 3", "no_of_games = 30")
+        ),
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .args(["--config", "settings.toml", "--quiet"])
+        .env("RUST_LOG", "rk_lottery=debug")
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let games = fs::read_to_string(dir.path().join("optimized_games.csv")).unwrap();
+    let games: Vec<&str> = games.lines().skip(1).collect();
+    let log = String::from_utf8_lossy(&output.stderr);
+    let shared: Vec<&str> = log
+        .lines()
+        .filter(|line| line.contains("shares a triplet"))
+        .collect();
+    assert!(!shared.is_empty());
+    for line in shared {
+        // e.g. "...: shares a triplet with an accepted game: game #7 holds 05-23-41"
+        let (_, owner) = line.split_once(": game #").expect(line);
+        let (owner, triplet) = owner.split_once(" holds ").unwrap();
+        let row = games[owner.parse::<usize>().unwrap() - 1];
+        assert!(
+            triplet.split('-').all(|number| row
+                .split(',')
+                .skip(1)
+                .any(|n| n.parse() == number.parse::<i64>())),
+            "{} is not in game {}",
+            triplet,
+            row
+        );
+    }
+}
+
+#[test]
+fn test_headerless_output_for_compatibility() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        format!("{}csv_header = false\n", CONFIG),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    assert_eq!(run(&[]), Some(0));
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_path(dir.path().join("optimized_games.csv"))
+        .unwrap();
+    let games: Vec<csv::StringRecord> = rdr.records().map(Result::unwrap).collect();
+    assert_eq!(games.len(), 3);
+    assert!(games.iter().all(|game| game.len() == 6));
+
+    // Resuming keeps the layout of the file
+    assert_eq!(run(&["--games", "5", "--resume"]), Some(0));
+    let content = fs::read_to_string(dir.path().join("optimized_games.csv")).unwrap();
+    assert_eq!(content.lines().count(), 5);
+    assert!(content.lines().all(|line| line.split(',').count() == 6));
+}
+
+#[test]
+fn test_validate_and_check_read_the_indexed_output() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    };
+    assert_eq!(run(&[]).0, Some(0));
+    let (code, stdout) = run(&["validate", "optimized_games.csv"]);
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("all 3 games are valid"));
+
+    let first = &read_output(&dir.path().join("optimized_games.csv"))[0];
+    let draw: Vec<String> = first.iter().map(|x| x.to_string()).collect();
+    let (code, stdout) = run(&["check", "--draw", &draw.join(","), "--json"]);
+    assert_eq!(code, Some(0));
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["tickets"].as_array().unwrap().len(), 3);
+    assert_eq!(report["tickets"][0]["matches"][0], 6);
+}
+
+#[test]
+fn test_semicolon_output_is_validated_and_resumed() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        format!("{}csv_delimiter = \";\"\n", CONFIG),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    let output_file = dir.path().join("optimized_games.csv");
+    let rows = || -> Vec<Vec<i64>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(b';')
+            .from_path(&output_file)
+            .unwrap();
+        assert_eq!(
+            rdr.headers().unwrap(),
+            vec!["game", "n1", "n2", "n3", "n4", "n5", "n6"]
+        );
+        rdr.records()
+            .map(|record| record.unwrap().iter().map(|x| x.parse().unwrap()).collect())
+            .collect()
+    };
+
+    assert!(run(&["--games", "5"]).status.success());
+    let content = fs::read_to_string(&output_file).unwrap();
+    assert!(content.starts_with("game;n1;n2;n3;n4;n5;n6\n1;"));
+    assert_eq!(rows().len(), 5);
+    let output = run(&["validate", "optimized_games.csv"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("all 5 games are valid"));
+
+    // Appended rows keep the delimiter of the file, whatever is configured now
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    assert!(run(&["--games", "8", "--resume"]).status.success());
+    let games = rows();
+    assert_eq!(games.len(), 8);
+    assert!(games
+        .iter()
+        .enumerate()
+        .all(|(index, game)| game[0] == index as i64 + 1));
+    // Comma files of unknown origin are told apart from the first row
+    fs::write(
+        dir.path().join("config.toml"),
+        format!("{}csv_delimiter = \"\\t\"\n", CONFIG),
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("tickets.csv"),
+        "1,2,3,4,5,6\n7,8,9,10,11,12\n",
+    )
+    .unwrap();
+    let output = run(&["validate", "tickets.csv"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("all 2 games are valid"));
+}
+
+/// Checks JSON records against the games they describe: 1-based indices and combinadic ranks.
+fn assert_records(records: &[serde_json::Value]) {
+    for (index, record) in records.iter().enumerate() {
+        assert_eq!(record["index"], index + 1);
+        let mut numbers: Vec<i64> = serde_json::from_value(record["numbers"].clone()).unwrap();
+        assert_eq!(numbers.len(), 6);
+        numbers.sort_unstable();
+        assert_eq!(
+            record["rank"],
+            rk_lottery::custom_utils::game2enum(&numbers)
+        );
+    }
+}
+
+#[test]
+fn test_json_and_ndjson_formats() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+
+    assert_eq!(
+        run(&["--format", "json", "--output", "games.json"]),
+        Some(0)
+    );
+    let records: Vec<serde_json::Value> =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("games.json")).unwrap()).unwrap();
+    assert_eq!(records.len(), 3);
+    assert_records(&records);
+
+    // The same games as the CSV output
+    assert_eq!(run(&[]), Some(0));
+    let csv_games = read_output(&dir.path().join("optimized_games.csv"));
+    let json_games: Vec<Vec<i64>> = records
+        .iter()
+        .map(|record| serde_json::from_value(record["numbers"].clone()).unwrap())
+        .collect();
+    assert_eq!(json_games, csv_games);
+
+    // NDJSON lines are appended when resuming, the JSON array is written again
+    let read_ndjson = || -> Vec<serde_json::Value> {
+        fs::read_to_string(dir.path().join("games.ndjson"))
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    };
+    assert_eq!(
+        run(&["--format", "ndjson", "--output", "games.ndjson"]),
+        Some(0)
+    );
+    assert_eq!(read_ndjson().len(), 3);
+    assert_eq!(
+        run(&[
+            "--format",
+            "ndjson",
+            "--output",
+            "games.ndjson",
+            "--games",
+            "5",
+            "--resume"
+        ]),
+        Some(0)
+    );
+    let records = read_ndjson();
+    assert_eq!(records.len(), 5);
+    assert_records(&records);
+
+    assert_eq!(run(&["--format", "xml"]), Some(2));
+}
+
+#[test]
+fn test_xlsx_format_needs_the_xlsx_feature() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--format", "xlsx", "--output", "games.xlsx"]);
+    if cfg!(feature = "xlsx") {
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(fs::metadata(dir.path().join("games.xlsx")).unwrap().len() > 0);
+        // The workbook does not depend on when it was written
+        assert_eq!(run(&["verify-manifest"]).status.code(), Some(0));
+        let output = run(&["--format", "xlsx", "--output", "games.xlsx", "--resume"]);
+        assert_eq!(output.status.code(), Some(2));
+    } else {
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--features xlsx"));
+        assert!(!dir.path().join("games.xlsx").exists());
+    }
+}
+
+#[test]
+fn test_runs_stored_in_a_database_share_no_triplet() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        format!("{}storage = \"sqlite:tickets.db\"\n", CONFIG),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&[]);
+    if !cfg!(feature = "sqlite") {
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--features sqlite"));
+        fs::write(dir.path().join("plain.toml"), CONFIG).unwrap();
+        let output = run(&["--config", "plain.toml", "validate", "sqlite:tickets.db"]);
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--features sqlite"));
+        return;
+    }
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // The same seed again, whose games would all share the triplets of the first run
+    let output = run(&["--games", "20"]);
+    assert!(output.status.success());
+    let second = read_output(&dir.path().join("optimized_games.csv"));
+    assert_eq!(second.len(), 20);
+
+    let output = run(&["validate", "sqlite:tickets.db"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("all 23 games are valid"));
+    let output = run(&["stats", "--history", "sqlite:tickets.db", "--json"]);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["games"], 23);
+    assert_eq!(report["runs"][1]["games"], 20);
+    let output = run(&[
+        "check",
+        "--draw",
+        "4,18,23,39,51,60",
+        "--games",
+        "sqlite:tickets.db",
+    ]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_print_renders_the_output_file_as_a_table() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("settings.toml"),
+        format!("{}tickets_per_block = 2\n", CONFIG),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(["--config", "settings.toml"])
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    assert!(run(&["--format", "ndjson"]).status.success());
+    let output = run(&["--print"]);
+    assert!(output.status.success());
+    let table = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], "#  Numbers");
+    assert_eq!(lines[3], "");
+    for line in [lines[1], lines[2], lines[4]] {
+        let numbers: Vec<&str> = line[3..].split(' ').collect();
+        assert_eq!(numbers.len(), 6);
+        assert!(numbers.iter().all(|number| number.len() == 2));
+        assert!(numbers.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
+
+/// Runs `validate` on a file holding `games`, returning the exit code and the standard output.
+fn validate(games: &str, json: bool) -> (Option<i32>, String) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    fs::write(dir.path().join("tickets.csv"), games).unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rk_lottery"));
+    command
+        .args(["validate", "tickets.csv"])
+        .current_dir(dir.path());
+    if json {
+        command.arg("--json");
+    }
+    let output = command.output().unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
+
+#[test]
+fn test_validate_reports_shared_triplets_with_their_rows() {
+    let (code, stdout) = validate("1,2,3,4,5,6\n7,8,9,10,11,12\n", false);
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("all 2 games are valid"));
+
+    let (code, stdout) = validate("1,2,3,4,5,6\n7,8,9,10,11,12\n1,7,8,9,20,30\n", false);
+    assert_eq!(code, Some(3));
+    assert_eq!(stdout, "rows 2 and 3 share triplet 07-08-09\n");
+}
+
+#[test]
+fn test_validate_json_output() {
+    let (code, stdout) = validate("1,2,3,4,5,6\n1,2,three,4,5,6\n", true);
+    assert_eq!(code, Some(3));
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["rows"], 2);
+    assert_eq!(report["issues"][0]["kind"], "malformed");
+    assert_eq!(report["issues"][0]["row"], 2);
+}
+
+/// Runs `check` against the draw 4-18-23-39-51-60 with `args`, on a file holding tickets
+/// matching 0 to 6 numbers, returning the exit code and the standard output.
+fn check_draw(args: &[&str]) -> (Option<i32>, String) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("settings.toml"), CONFIG).unwrap();
+    fs::write(
+        dir.path().join("optimized_games.csv"),
+        "1,2,3,5,6,7\n4,2,3,5,6,7\n4,18,3,5,6,7\n4,18,23,5,6,7\n4,18,23,39,6,7\n4,18,23,39,51,7\n4,18,23,39,51,60\n",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .args(["--config", "settings.toml", "check"])
+        .args(args)
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
+
+#[test]
+fn test_check_scores_tickets_against_the_draw() {
+    let (code, stdout) = check_draw(&["--draw", "4,18,23,39,51,60"]);
+    assert_eq!(code, Some(0));
+    let matches: Vec<&str> = stdout
+        .lines()
+        .skip(2)
+        .take(7)
+        .map(|line| line.split_whitespace().last().unwrap())
+        .collect();
+    assert_eq!(matches, ["0", "1", "2", "3", "4", "5", "6"]);
+    assert!(stdout.contains("quadra (4 matches): 1 ticket(s)"));
+    assert!(stdout.contains("sena (6 matches): 1 ticket(s)"));
+
+    let (code, stdout) = check_draw(&["--draw", "4,18,23,39,51,60", "--json"]);
+    assert_eq!(code, Some(0));
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["tickets"][5]["matches"][0], 5);
+    assert_eq!(report["prizes"][0]["quina"], 1);
+}
+
+#[test]
+fn test_dupla_sena_tickets_are_scored_against_both_results() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 1\ninitial_games = []\nmax_number = 50\nmin_desired_number = 1\n\
+         lottery = \"dupla-sena\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("optimized_games.csv"),
+        "1,2,3,5,6,7\n4,18,23,39,9,10\n",
+    )
+    .unwrap();
+    let check = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .arg("check")
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    };
+    let draws = ["--draw", "4,18,23,39,47,50", "--draw", "1,2,3,5,6,8"];
+
+    // The first ticket only hits in the second result
+    let (code, stdout) = check(&draws);
+    assert_eq!(code, Some(0), "{}", stdout);
+    assert!(stdout.contains("Draw 2: 01-02-03-05-06-08\n"));
+    let matches: Vec<Vec<&str>> = stdout
+        .lines()
+        .skip(3)
+        .take(2)
+        .map(|line| line.split_whitespace().skip(2).collect())
+        .collect();
+    assert_eq!(matches, [["0", "5", "5"], ["4", "0", "4"]]);
+    assert!(stdout.contains("quina (5 matches): 0 ticket(s) in draw 1, 1 ticket(s) in draw 2\n"));
+    assert!(stdout.contains("Ticket #1 hit the quina of draw 2!"));
+    assert!(stdout.contains("Ticket #2 hit the quadra of draw 1!"));
+
+    let mut args = draws.to_vec();
+    args.push("--json");
+    let (code, stdout) = check(&args);
+    assert_eq!(code, Some(0));
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["tickets"][0]["matches"], serde_json::json!([0, 5]));
+    assert_eq!(report["tickets"][0]["best"], 5);
+    assert_eq!(report["prizes"][1]["quina"], 1);
+    assert_eq!(report["prizes"][0]["terno"], 0);
+
+    // Both results are needed
+    assert_eq!(check(&draws[..2]).0, Some(2));
+}
+
+#[test]
+fn test_lotofacil_games_of_15_numbers_are_generated_validated_and_checked() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 5\ninitial_games = []\nseed = 1\nmax_number = 25\nmin_desired_number = 1\n\
+         lottery = \"lotofacil\"\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    };
+    assert_eq!(run(&[]).0, Some(0));
+
+    let mut rdr = csv::Reader::from_path(dir.path().join("optimized_games.csv")).unwrap();
+    let header: Vec<String> = std::iter::once("game".to_string())
+        .chain((1..=15).map(|n| format!("n{}", n)))
+        .collect();
+    assert_eq!(rdr.headers().unwrap(), header);
+    let games: Vec<Vec<i64>> = rdr
+        .records()
+        .map(|record| {
+            record
+                .unwrap()
+                .iter()
+                .skip(1)
+                .map(|x| x.parse().unwrap())
+                .collect()
+        })
+        .collect();
+    assert_eq!(games.len(), 5);
+    for (index, game) in games.iter().enumerate() {
+        assert!(game.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", game);
+        assert!(game.iter().all(|number| (1..=25).contains(number)));
+        // No two games share the 11 numbers of the lowest prize
+        for other in &games[..index] {
+            assert!(game.iter().filter(|x| other.contains(x)).count() <= 10);
+        }
+    }
+
+    let (code, stdout) = run(&["validate", "optimized_games.csv"]);
+    assert_eq!(code, Some(0), "{}", stdout);
+    assert!(stdout.contains("all 5 games are valid"));
+
+    let draw: Vec<String> = games[0].iter().map(|x| x.to_string()).collect();
+    let (code, stdout) = run(&["check", "--draw", &draw.join(",")]);
+    assert_eq!(code, Some(0), "{}", stdout);
+    assert!(stdout.contains("15 acertos (15 matches): 1 ticket(s)\n"));
+    assert!(stdout.contains("11 acertos (11 matches): 0 ticket(s)\n"));
+    // A draw of the Lotofácil has 15 numbers
+    assert_eq!(run(&["check", "--draw", "1,2,3,4,5,6"]).0, Some(3));
+}
+
+#[test]
+fn test_quick_picks_share_triplets_but_never_repeat() {
+    let dir = tempfile::tempdir().unwrap();
+    // The 10 numbers from 51 hold 6 games sharing no triplet
+    let config = "no_of_games = 20\ninitial_games = []\nseed = 1\nmax_number = 60\nmin_desired_number = 51\n";
+    fs::write(
+        dir.path().join("config.toml"),
+        format!("{}strategy = \"quick-pick\"\n", config),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    };
+    assert_eq!(run(&[]).0, Some(0));
+    let games = fs::read_to_string(dir.path().join("optimized_games.csv")).unwrap();
+    let mut rows: Vec<&str> = games
+        .lines()
+        .skip(1)
+        .map(|row| row.split_once(',').unwrap().1)
+        .collect();
+    assert_eq!(rows.len(), 20);
+    rows.sort_unstable();
+    rows.dedup();
+    assert_eq!(rows.len(), 20);
+    assert!(fs::read_to_string(dir.path().join("triplet_set.log"))
+        .unwrap()
+        .contains("\"data\":[]"));
+    let manifest = fs::read_to_string(dir.path().join("run_manifest.json")).unwrap();
+    assert!(
+        manifest.contains("\"unique_triplets\": false"),
+        "{}",
+        manifest
+    );
+
+    let (code, stdout) = run(&["validate", "optimized_games.csv"]);
+    assert_eq!(code, Some(0), "{}", stdout);
+    // The games do share triplets, which the other strategies turn down
+    fs::write(dir.path().join("config.toml"), config).unwrap();
+    let (code, stdout) = run(&["validate", "optimized_games.csv"]);
+    assert_eq!(code, Some(3), "{}", stdout);
+    assert!(stdout.contains(" share triplet "), "{}", stdout);
+}
+
+#[test]
+fn test_labels_go_from_the_initial_games_to_the_output_and_the_check() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 4\nseed = 1\nmax_number = 60\nmin_desired_number = 1\n\
+         label_pool = [\"Maria\", \"Ana\"]\n\
+         initial_games = [{ numbers = [4, 18, 23, 39, 6, 7], label = \"Maria\" }, [1, 2, 3, 5, 8, 9]]\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    // The plain initial game takes the first free name of the pool, the games generated
+    // after it the auto labels
+    run(&[]);
+    let mut rdr = csv::Reader::from_path(dir.path().join("optimized_games.csv")).unwrap();
+    assert_eq!(
+        rdr.headers().unwrap(),
+        vec!["game", "n1", "n2", "n3", "n4", "n5", "n6", "label"]
+    );
+    let labels: Vec<String> = rdr
+        .records()
+        .map(|record| record.unwrap()[7].to_string())
+        .collect();
+    assert_eq!(labels, ["Maria", "Ana", "auto-3", "auto-4"]);
+
+    let stdout = run(&["check", "--draw", "4,18,23,39,51,60"]);
+    assert!(stdout.contains("Maria hit the quadra!"), "{}", stdout);
+    let stdout = run(&["check", "--draw", "4,18,23,39,51,60", "--json"]);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["tickets"][0]["label"], "Maria");
+    assert_eq!(report["tickets"][0]["game"][0], 4);
+
+    run(&["--format", "json", "--output", "games.json"]);
+    let records: Vec<serde_json::Value> =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("games.json")).unwrap()).unwrap();
+    assert_eq!(records[1]["label"], "Ana");
+    assert_records(&records);
+}
+
+#[test]
+fn test_check_rejects_invalid_draws() {
+    assert_eq!(check_draw(&["--draw", "4,18,23,39,51"]).0, Some(3));
+    assert_eq!(check_draw(&["--draw", "4,4,23,39,51,60"]).0, Some(3));
+    assert_eq!(check_draw(&["--draw", "4,18,23,39,51,61"]).0, Some(3));
+    // A missing games file is a file error
+    assert_eq!(
+        check_draw(&["--draw", "4,18,23,39,51,60", "--games", "missing.csv"]).0,
+        Some(5)
+    );
+    // The result comes from one source only
+    assert_eq!(check_draw(&[]).0, Some(2));
+    assert_eq!(
+        check_draw(&["--draw", "4,18,23,39,51,60", "--contest", "2925"]).0,
+        Some(2)
+    );
+    if !cfg!(feature = "net") {
+        assert_eq!(check_draw(&["--fetch-latest"]).0, Some(2));
+        assert_eq!(check_draw(&["--contest", "2925"]).0, Some(2));
+    }
+}
+
+/// Runs `convert` with `args`, writing `stdin` to its standard input, returning the exit code
+/// and the standard output.
+fn convert(args: &[&str], stdin: &str) -> (Option<i32>, String) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .arg("convert")
+        .args(args)
+        .current_dir(dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
+
+#[test]
+fn test_convert_ranks_piped_through_stdin() {
+    let (code, stdout) = convert(&["--to-game"], "0\n1\n50063859\n");
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        stdout,
+        "01-02-03-04-05-06\n01-02-03-04-05-07\n55-56-57-58-59-60\n"
+    );
+
+    let (code, stdout) = convert(&["--to-game", "--triplet"], "0\n34219\n");
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "01-02-03\n58-59-60\n");
+
+    let (code, stdout) = convert(&["--to-rank"], "01-02-03-04-05-06\n55-56-57-58-59-60\n");
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "0\n50063859\n");
+}
+
+#[test]
+fn test_convert_values_and_explicit_sizes() {
+    let (code, stdout) = convert(&["--to-game", "3,4", "--n", "5", "--k", "2"], "");
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "01-04\n02-04\n");
+
+    let (code, stdout) = convert(&["--to-rank", "5,12,33,41,50,59"], "");
+    assert_eq!(code, Some(0));
+    let rank = stdout.trim().to_string();
+    assert_eq!(convert(&["--to-game", &rank], "").1, "05-12-33-41-50-59\n");
+}
+
+#[test]
+fn test_convert_rejects_out_of_range_inputs() {
+    assert_eq!(convert(&["--to-game", "50063860"], "").0, Some(3));
+    assert_eq!(convert(&["--to-game=-1"], "").0, Some(3));
+    assert_eq!(
+        convert(&["--to-rank", "1,2,61", "--triplet"], "").0,
+        Some(3)
+    );
+    assert_eq!(convert(&["--to-rank", "1,2,3"], "").0, Some(3));
+}
+
+#[test]
+fn test_resume_appends_the_missing_games() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    let rows = || read_output(&dir.path().join("optimized_games.csv"));
+
+    assert_eq!(run(&["--games", "10"]), Some(0));
+    let first = rows();
+    assert_eq!(run(&["--games", "25", "--resume"]), Some(0));
+    let games = rows();
+    assert_eq!(games.len(), 25);
+    assert_eq!(games[..10], first[..]);
+
+    // No triplet appears twice across the combined output
+    let mut triplets = std::collections::HashSet::new();
+    for game in &games {
+        for i in 0..6 {
+            for j in i + 1..6 {
+                for k in j + 1..6 {
+                    assert!(triplets.insert((game[i], game[j], game[k])));
+                }
+            }
+        }
+    }
+
+    // Dropping a game from the output leaves the saved sets disagreeing with it
+    let content = fs::read_to_string(dir.path().join("optimized_games.csv")).unwrap();
+    let truncated: Vec<&str> = content.lines().take(25).collect();
+    fs::write(
+        dir.path().join("optimized_games.csv"),
+        truncated.join("\n") + "\n",
+    )
+    .unwrap();
+    assert_eq!(run(&["--games", "30", "--resume"]), Some(3));
+    assert_eq!(rows().len(), 24);
+}
+
+#[test]
+fn test_resume_carries_on_the_counters_of_the_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    let stats = || {
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.path().join("run_manifest.json")).unwrap())
+                .unwrap();
+        manifest["stats"].clone()
+    };
+    let count = |stats: &serde_json::Value, key: &str| stats[key].as_u64().unwrap();
+
+    assert!(run(&["--games", "10"]).status.success());
+    let first = stats();
+    assert_eq!(count(&first, "accepted"), 10);
+    let output = run(&["--games", "25", "--resume"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).is_empty());
+    let total = stats();
+    assert_eq!(count(&total, "accepted"), 25);
+    assert!(count(&total, "attempts") > count(&first, "attempts"));
+    assert_eq!(
+        count(&total, "attempts"),
+        count(&total, "accepted")
+            + count(&total, "duplicate_games")
+            + count(&total, "rejected")
+            + count(&total, "shared_triplets")
+    );
+    assert!(total["elapsed_secs"].as_f64().unwrap() >= first["elapsed_secs"].as_f64().unwrap());
+
+    // A manifest of another file says nothing of the games resumed
+    let path = dir.path().join("run_manifest.json");
+    let mut manifest: serde_json::Value =
+        serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    manifest["sha256"] = "0".repeat(64).into();
+    fs::write(&path, manifest.to_string()).unwrap();
+    let output = run(&["--games", "30", "--resume"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "Warning: run_manifest.json was not written along with optimized_games.csv, counting the candidates of this run only"
+    ));
+    assert_eq!(count(&stats(), "accepted"), 5);
+}
+
+#[test]
+fn test_resume_reads_sets_saved_in_any_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let run = |settings: &str, args: &[&str]| {
+        fs::write(
+            dir.path().join("config.toml"),
+            CONFIG.to_string() + settings,
+        )
+        .unwrap();
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+
+    assert_eq!(
+        run("set_format = \"binary\"\n", &["--games", "10"]),
+        Some(0)
+    );
+    let triplets = fs::read(dir.path().join("triplet_set.log")).unwrap();
+    assert!(triplets.starts_with(b"RKNS\x02\x01"));
+    // The format is told by the file, whatever the configuration says now
+    assert_eq!(run("", &["--games", "20", "--resume"]), Some(0));
+    assert!(fs::read_to_string(dir.path().join("triplet_set.log"))
+        .unwrap()
+        .starts_with("{\"version\":2,\"kind\":\"triplet\",\"n\":60,\"k\":3,\"data\":["));
+    assert_eq!(
+        read_output(&dir.path().join("optimized_games.csv")).len(),
+        20
+    );
+
+    // games.csv is a real CSV file now, but the JSON ones of older versions still resume
+    let header = "# rk_lottery set version=2 kind=game n=60 k=6\nrank\n";
+    let games = fs::read_to_string(dir.path().join("games.csv")).unwrap();
+    assert!(games.starts_with(header));
+    let ranks: Vec<&str> = games.lines().skip(2).collect();
+    fs::write(
+        dir.path().join("games.csv"),
+        format!("[{}]", ranks.join(",")),
+    )
+    .unwrap();
+    assert_eq!(run("", &["--games", "25", "--resume"]), Some(0));
+    assert!(fs::read_to_string(dir.path().join("games.csv"))
+        .unwrap()
+        .starts_with(header));
+}
+
+#[test]
+fn test_resume_refuses_sets_saved_for_other_games() {
+    let dir = tempfile::tempdir().unwrap();
+    let run = |settings: &str, args: &[&str]| {
+        fs::write(
+            dir.path().join("config.toml"),
+            CONFIG.to_string() + settings,
+        )
+        .unwrap();
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    assert_eq!(run("", &["--games", "10"]).status.code(), Some(0));
+
+    // The same ranks are other games in a lottery of 50 numbers
+    let output = run("", &["--games", "15", "--resume", "--max-number", "50"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "games.csv: file holds the ranks of 6 numbers out of 60, not 6 out of 50 as configured, cannot resume from it"
+    ));
+
+    // The games of the triplet set
+    let triplets = fs::read(dir.path().join("triplet_set.log")).unwrap();
+    fs::copy(
+        dir.path().join("games.csv"),
+        dir.path().join("triplet_set.log"),
+    )
+    .unwrap();
+    let output = run("", &["--games", "15", "--resume"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("triplet_set.log: file holds game ranks, triplet ranks were expected"));
+
+    // A set written by a later version is not guessed at
+    fs::write(
+        dir.path().join("triplet_set.log"),
+        String::from_utf8(triplets.clone())
+            .unwrap()
+            .replace("\"version\":2", "\"version\":3"),
+    )
+    .unwrap();
+    let output = run("", &["--games", "15", "--resume"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("triplet_set.log: file of version 3, which this program cannot read"));
+
+    // A bare array of an older version is taken as is, with a warning
+    let data = String::from_utf8(triplets).unwrap();
+    let data = &data[data.find('[').unwrap()..data.len() - 1];
+    fs::write(dir.path().join("triplet_set.log"), data).unwrap();
+    let output = run("", &["--games", "15", "--resume"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "Warning: triplet_set.log was saved without a header (version 1), assuming it holds triplet ranks of 3 numbers out of 60"
+    ));
+}
+
+#[test]
+fn test_gz_output_is_compressed_and_resumed_from() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(["--output", "tickets.csv.gz"])
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    let rows = || {
+        let compressed = fs::read(dir.path().join("tickets.csv.gz")).unwrap();
+        assert!(compressed.starts_with(&[0x1f, 0x8b]));
+        let mut content = String::new();
+        flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut content)
+            .unwrap();
+        content.lines().count()
+    };
+    assert_eq!(run(&["--games", "10"]).status.code(), Some(0));
+    assert_eq!(rows(), 11);
+
+    // An archived set compressed under its own name still opens
+    let triplets = fs::read(dir.path().join("triplet_set.log")).unwrap();
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(&triplets).unwrap();
+    fs::write(
+        dir.path().join("triplet_set.log"),
+        encoder.finish().unwrap(),
+    )
+    .unwrap();
+    let output = run(&["--games", "15", "--resume"]);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(rows(), 16);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .args(["validate", "tickets.csv.gz"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+
+    // A damaged stream is reported rather than read as an empty or short set
+    fs::write(dir.path().join("triplet_set.log"), [0x1f, 0x8b, 8, 0, 1, 2]).unwrap();
+    let output = run(&["--games", "20", "--resume"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("triplet_set.log: file is not a valid gzip stream"));
+}
+
+#[test]
+fn test_export_set_writes_games_that_rank_back_to_the_set() {
+    use rk_lottery::custom_utils::subset2enum;
+    use rk_lottery::data_structures::NumberSet;
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    let reranked = |path: &str, k: usize| -> NumberSet {
+        let mut rdr = csv::Reader::from_path(dir.path().join(path)).unwrap();
+        rdr.records()
+            .map(|record| {
+                let numbers: Vec<i64> = record
+                    .unwrap()
+                    .iter()
+                    .skip(1)
+                    .map(|number| number.parse().unwrap())
+                    .collect();
+                assert_eq!(numbers.len(), k);
+                subset2enum(&numbers, k).unwrap()
+            })
+            .collect()
+    };
+    let set = |path: &str| NumberSet::load(dir.path().join(path).to_str().unwrap()).unwrap();
+    assert_eq!(run(&["--games", "10"]).status.code(), Some(0));
+
+    let output = run(&["export-set", "games.csv"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Wrote the 10 games of games.csv to games_export.csv"));
+    let exported = fs::read_to_string(dir.path().join("games_export.csv")).unwrap();
+    assert!(exported.starts_with("game,n1,n2,n3,n4,n5,n6\n1,"));
+    assert_eq!(reranked("games_export.csv", 6), set("games.csv"));
+
+    // The header of the set tells its kind
+    let output = run(&["export-set", "triplet_set.log", "--output", "triplets.csv"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(reranked("triplets.csv", 3), set("triplet_set.log"));
+    let output = run(&["export-set", "triplet_set.log", "--kind", "game"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("triplet_set.log: file holds triplet ranks, game ranks were expected"));
+
+    // The ranks of a set saved without a header for a larger lottery are reported by rank
+    let ranks: Vec<i64> = set("games.csv").into_iter().collect();
+    fs::write(
+        dir.path().join("legacy.log"),
+        serde_json::to_string(&ranks).unwrap(),
+    )
+    .unwrap();
+    let output = run(&["--max-number", "40", "export-set", "legacy.log"]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let too_large = rk_lottery::custom_utils::binomial(40, 6);
+    let reported = ranks.iter().filter(|&&rank| rank >= too_large).count();
+    assert!(reported > 0);
+    assert_eq!(
+        stderr.matches("Warning: legacy.log: rank ").count(),
+        reported
+    );
+    assert!(stderr.contains(&format!(
+        "legacy.log: {} of 10 ranks stand for no game of 6 numbers out of 40",
+        reported
+    )));
+    assert_eq!(
+        reranked("legacy_export.csv", 6).len() + reported,
+        ranks.len()
+    );
+}
+
+#[test]
+fn test_resume_rebuilds_a_missing_game_set_only() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    assert_eq!(run(&["--games", "10"]).status.code(), Some(0));
+    fs::remove_file(dir.path().join("games.csv")).unwrap();
+    let output = run(&["--games", "15", "--resume"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "Warning: games.csv not found, rebuilding it from the games of optimized_games.csv"
+    ));
+    assert_eq!(
+        read_output(&dir.path().join("optimized_games.csv")).len(),
+        15
+    );
+
+    fs::remove_file(dir.path().join("triplet_set.log")).unwrap();
+    let output = run(&["--games", "20", "--resume"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("triplet_set.log: file not found"));
+    fs::write(dir.path().join("triplet_set.log"), "[1,2").unwrap();
+    let output = run(&["--games", "20", "--resume"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("triplet_set.log: file appears corrupted: invalid JSON at line 1 column 4"));
+}
+
+#[test]
+fn test_resume_replays_the_journal_of_a_run_that_died() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        format!("{}journal = true\n", CONFIG),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    let journal_path = dir.path().join(rk_lottery::journal::JOURNAL_FILE);
+
+    // A run that completes replaces its journal by the saved sets
+    assert_eq!(run(&["--games", "10"]).status.code(), Some(0));
+    assert!(!journal_path.exists());
+    let games = read_output(&dir.path().join("optimized_games.csv"));
+
+    // A run dying while writing its seventh game leaves its journal and no output file
+    let mut journal = rk_lottery::journal::Journal::create(&journal_path, 1).unwrap();
+    for game in &games[..7] {
+        journal.append_game(game, 1).unwrap();
+    }
+    drop(journal);
+    let bytes = fs::read(&journal_path).unwrap();
+    fs::write(&journal_path, &bytes[..bytes.len() - 100]).unwrap();
+    fs::remove_file(dir.path().join("optimized_games.csv")).unwrap();
+    fs::remove_file(dir.path().join("games.csv")).unwrap();
+
+    let output = run(&["--games", "12", "--resume"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("run_journal.bin: dropped the last 72 bytes, a record cut short"));
+    assert!(stderr.contains(
+        "Warning: resuming from the 6 games journaled in run_journal.bin by a run that did not finish"
+    ));
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("Resumed from 6 games, generated 6 more")
+    );
+    let resumed = read_output(&dir.path().join("optimized_games.csv"));
+    assert_eq!(resumed.len(), 12);
+    assert_eq!(resumed[..6], games[..6]);
+    assert!(!journal_path.exists());
+    assert!(dir.path().join("games.csv").exists());
+}
+
+#[test]
+fn test_append_adds_games_sharing_no_triplet_with_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    let rows = || read_output(&dir.path().join("optimized_games.csv"));
+
+    // Appending to a missing file starts it
+    assert_eq!(run(&["--games", "10", "--append"]).status.code(), Some(0));
+    let first = rows();
+    assert_eq!(first.len(), 10);
+
+    // An initial game already in the file is left out with a warning
+    let numbers: Vec<String> = first[0].iter().map(|x| x.to_string()).collect();
+    fs::write(
+        dir.path().join("config.toml"),
+        CONFIG.replace(
+            "initial_games = []",
+            &format!("initial_games = [[{}]]", numbers.join(", ")),
+        ),
+    )
+    .unwrap();
+    let output = run(&["--games", "15", "--append"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning: Initial game #1 ("), "{}", stderr);
+    assert!(stderr.contains(") is already game #1 of the file, left out"));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Appended 15 games to the 10 of optimized_games.csv"));
+    let games = rows();
+    assert_eq!(games.len(), 25);
+    assert_eq!(games[..10], first[..]);
+
+    // No triplet appears twice across the combined output
+    let mut triplets = std::collections::HashSet::new();
+    for game in &games {
+        let mut game = game.clone();
+        game.sort_unstable();
+        for i in 0..6 {
+            for j in i + 1..6 {
+                for k in j + 1..6 {
+                    assert!(triplets.insert((game[i], game[j], game[k])));
+                }
+            }
+        }
+    }
+    let output = run(&["validate", "optimized_games.csv"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    // Resuming and appending at once is refused
+    assert_eq!(run(&["--append", "--resume"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_batches_write_one_output_per_batch() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        format!("{}share_triplet_set = true\n", CONFIG),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&[
+        "--quiet",
+        "--batches",
+        "3",
+        "--games",
+        "20",
+        "--json-summary",
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summaries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let summaries = summaries.as_array().unwrap();
+    assert_eq!(summaries.len(), 3);
+    for (index, summary) in summaries.iter().enumerate() {
+        assert_eq!(summary["batch"], index + 1);
+        assert_eq!(summary["seed"], 1 + index);
+        assert_eq!(
+            summary["output"],
+            format!("optimized_games_{}.csv", index + 1)
+        );
+        assert_eq!(summary["games"], 20);
+    }
+    assert!(!dir.path().join("optimized_games.csv").exists());
+
+    // No triplet appears in two batches
+    let mut triplets = std::collections::HashSet::new();
+    for batch in 1..=3 {
+        let games = read_output(&dir.path().join(format!("optimized_games_{}.csv", batch)));
+        assert_eq!(games.len(), 20);
+        for mut game in games {
+            game.sort_unstable();
+            for i in 0..6 {
+                for j in i + 1..6 {
+                    for k in j + 1..6 {
+                        assert!(triplets.insert((game[i], game[j], game[k])));
+                    }
+                }
+            }
+        }
+        assert!(dir.path().join(format!("games_{}.csv", batch)).exists());
+        assert!(dir
+            .path()
+            .join(format!("triplet_set_{}.log", batch))
+            .exists());
+    }
+
+    // The first batch stands alone, the later ones depend on those before them
+    let output = run(&["verify-manifest", "run_manifest_1.json"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("gives the same file"));
+    let output = run(&["verify-manifest", "run_manifest_2.json"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("cannot be generated again: the batch avoided the triplets"));
+
+    // Without sharing, a batch is the run of its seed alone
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    assert!(run(&["--quiet", "--batches", "2"]).status.success());
+    let second = read_output(&dir.path().join("optimized_games_2.csv"));
+    assert!(run(&["--quiet", "--seed", "2"]).status.success());
+    assert_eq!(read_output(&dir.path().join("optimized_games.csv")), second);
+}
+
+#[test]
+fn test_repair_cleans_up_a_ticket_file() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    fs::write(
+        dir.path().join("tickets.csv"),
+        "6,5,4,3,2,1\n7,8,9,10,11,12\n1,2,3,4,5,6\n1,2,3,4,5\n13,14,15,16,17,77\n20,20,21,22,23,24\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["repair", "tickets.csv"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("row 3 repeats row 1, dropped"));
+    assert!(stdout.contains("row 4 rejected: game has 5 numbers instead of 6"));
+    assert!(stdout.contains("row 5 rejected: number 77 is above the maximum playable number"));
+    assert!(stdout.contains("row 6 rejected: number 20 appears more than once"));
+    assert!(stdout.ends_with(
+        "tickets.csv: 5 of 6 rows changed: 1 duplicate(s) dropped, 3 rejected, 1 sorted\n"
+    ));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("tickets_repaired.csv")).unwrap(),
+        "1,2,3,4,5,6\n7,8,9,10,11,12\n"
+    );
+    let rejects = fs::read_to_string(dir.path().join("tickets_rejects.csv")).unwrap();
+    assert_eq!(rejects.lines().count(), 4);
+    assert!(rejects
+        .starts_with("row,reason,numbers\n4,game has 5 numbers instead of 6,\"1,2,3,4,5\"\n"));
+
+    // The repaired file needs no more repair
+    let output = run(&["repair", "tickets_repaired.csv", "--output", "unused.csv"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("tickets_repaired.csv: nothing to repair in 2 rows"));
+    assert!(!dir.path().join("unused.csv").exists());
+}
+
+#[test]
+fn test_diff_reports_games_colliding_across_files() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    fs::write(
+        dir.path().join("a.csv"),
+        "game,n1,n2,n3,n4,n5,n6\n1,1,2,3,4,5,6\n2,7,8,9,10,11,12\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("b.csv"),
+        "20,21,22,23,24,25\n12,11,10,30,31,32\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("c.csv"), "1,2,7,8,40,41\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let stdout = run(&["diff", "a.csv", "b.csv"]);
+    assert!(
+        stdout.contains("  rows 2 and 2 share 10-11-12: 07-08-09-10-11-12 and 10-11-12-30-31-32\n")
+    );
+    assert!(stdout.ends_with(
+        "1 pair(s) of games share 3 or more numbers between a.csv (2 games) and b.csv (2 games)\n"
+    ));
+
+    let report: serde_json::Value =
+        serde_json::from_str(&run(&["diff", "a.csv", "b.csv", "--json"])).unwrap();
+    assert_eq!(report["subset_size"], 3);
+    assert_eq!(report["collisions"][0]["row_a"], 2);
+    assert_eq!(report["collisions"][0]["row_b"], 2);
+    assert_eq!(
+        report["collisions"][0]["shared"],
+        serde_json::json!([10, 11, 12])
+    );
+
+    // Pairs are shared with both games of a, but no triplet
+    assert!(run(&["diff", "a.csv", "c.csv"]).starts_with("0 pair(s)"));
+    let report: serde_json::Value = serde_json::from_str(&run(&[
+        "diff",
+        "a.csv",
+        "c.csv",
+        "--subset-size",
+        "2",
+        "--json",
+    ]))
+    .unwrap();
+    assert_eq!(report["collisions"].as_array().unwrap().len(), 2);
+}
+
+/// Runs `simulate` with `args` in a directory holding `settings.toml` and the tickets of
+/// `check_draw` in the output file, returning the exit code and the standard output.
+fn simulate(settings: &str, args: &[&str]) -> (Option<i32>, String) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("settings.toml"), settings).unwrap();
+    fs::write(
+        dir.path().join("optimized_games.csv"),
+        "1,2,3,5,6,7\n4,2,3,5,6,7\n4,18,3,5,6,7\n4,18,23,5,6,7\n4,18,23,39,6,7\n4,18,23,39,51,7\n4,18,23,39,51,60\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("all.csv"),
+        "2,3,4,5,6,7\n1,3,4,5,6,7\n1,2,4,5,6,7\n1,2,3,5,6,7\n1,2,3,4,6,7\n1,2,3,4,5,7\n1,2,3,4,5,6\n",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .args(["--config", "settings.toml", "simulate"])
+        .args(args)
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
+
+#[test]
+fn test_simulate_reports_the_matches_of_random_draws() {
+    // Every game of a 7-number lottery is played, so each draw hits one sena and six quinas
+    let tiny =
+        "no_of_games = 1\ninitial_games = []\nseed = 1\nmax_number = 7\nmin_desired_number = 1\n\
+                [prizes]\nquina = 10.0\nsena = 100.0\nticket_price = 2.0\n";
+    let (code, stdout) = simulate(tiny, &["--games", "all.csv", "--draws", "1000"]);
+    assert_eq!(code, Some(0), "{}", stdout);
+    assert!(stdout.starts_with("7 tickets of all.csv against 1000 random draws\n"));
+    assert!(stdout.contains("         6  1000 (100.0000%)\n"));
+    assert!(stdout.contains("quina (5 matches): 6.000000 ticket(s) per draw\n"));
+    assert!(stdout.contains("Expected winnings per draw, ticket price taken off: 146.00\n"));
+    // Both results of a Dupla Sena draw pay, the terno too
+    let dupla = tiny.replace("[prizes]", "lottery = \"dupla-sena\"\n[prizes]");
+    let (code, stdout) = simulate(&dupla, &["--games", "all.csv", "--draws", "1000"]);
+    assert_eq!(code, Some(0), "{}", stdout);
+    assert!(stdout
+        .starts_with("7 tickets of all.csv against 1000 random Dupla Sena draws of 2 results\n"));
+    assert!(stdout.contains("terno (3 matches): 0.000000 ticket(s) per draw\n"));
+    assert!(stdout.contains("quina (5 matches): 12.000000 ticket(s) per draw\n"));
+    assert!(stdout.contains("Expected winnings per draw, ticket price taken off: 306.00\n"));
+
+    let (code, stdout) = simulate(CONFIG, &["--draws", "5000", "--json"]);
+    assert_eq!(code, Some(0));
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["seed"], 1);
+    assert_eq!(report["simulation"]["tickets"], 7);
+    let best: Vec<u64> =
+        serde_json::from_value(report["simulation"]["best_matches"].clone()).unwrap();
+    assert_eq!(best, [1262, 2472, 1103, 154, 9, 0, 0]);
+    assert_eq!(report["expected_hits_per_draw"]["quadra"], 0.002);
+    assert!(report["expected_winnings_per_draw"].is_null());
+    // The same seed draws the same results
+    assert_eq!(simulate(CONFIG, &["--draws", "5000", "--json"]).1, stdout);
+
+    assert_eq!(simulate(CONFIG, &["--draws", "0"]).0, Some(2));
+    assert_eq!(simulate(tiny, &["--draws", "10"]).0, Some(3));
+}
+
+#[test]
+fn test_stats_reports_number_frequencies_of_past_draws() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    fs::copy(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/draws.csv"),
+        dir.path().join("draws.csv"),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["stats", "--history", "draws.csv"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "6 draws from 11/03/1996 to 06/05/1996");
+    assert_eq!(lines[2], "     5      4     66.67%  06/05/1996  0");
+    assert!(lines[62].starts_with("Longest current drought: 6 draw(s), for 2, 3, 7, "));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning: draws.csv: row 8: number 61 is outside 1 to 60\n"));
+    assert_eq!(stderr.lines().count(), 4);
+
+    let output = run(&["stats", "--history", "draws.csv", "--json"]);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["draws"], 6);
+    assert_eq!(report["numbers"][0]["number"], 5);
+    assert_eq!(report["numbers"][0]["last_drawn"], "06/05/1996");
+
+    fs::write(dir.path().join("empty.csv"), "Concurso;Data do Sorteio\n").unwrap();
+    assert_eq!(
+        run(&["stats", "--history", "empty.csv"]).status.code(),
+        Some(3)
+    );
+    assert_eq!(
+        run(&["stats", "--history", "missing.csv"]).status.code(),
+        Some(5)
+    );
+}
+
+#[test]
+fn test_weights_from_history_are_recorded_in_the_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        format!(
+            "{}strategy = \"weighted\"\nweights_from_history = \"draws.csv\"\n",
+            CONFIG
+        ),
+    )
+    .unwrap();
+    fs::copy(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/draws.csv"),
+        dir.path().join("draws.csv"),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--quiet", "--games", "10"]);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Warning: draws.csv: row 7: "));
+    assert_eq!(
+        read_output(&dir.path().join("optimized_games.csv")).len(),
+        10
+    );
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("run_manifest.json")).unwrap())
+            .unwrap();
+    assert_eq!(manifest["config"]["weights"]["5"], 5.0);
+    assert_eq!(manifest["config"]["weights"]["2"], 1.0);
+    assert!(manifest["config"]["weights_from_history"].is_null());
+
+    // The manifest holds the weights, the history file is not needed any more
+    fs::remove_file(dir.path().join("draws.csv")).unwrap();
+    assert_eq!(run(&["verify-manifest"]).status.code(), Some(0));
+    assert_eq!(run(&["--quiet"]).status.code(), Some(5));
+}
+
+#[test]
+fn test_analyze_pairs_writes_the_co_occurrence_matrix() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    fs::write(
+        dir.path().join("games.csv"),
+        "game,n1,n2,n3\n1,1,2,3\n2,3,2,4\n3,2,3,5\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("bad.csv"), "1,2,3\n1,2,61\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(["--max-number", "5"])
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    };
+    let matrix =
+        "number,1,2,3,4,5\n1,0,1,1,0,0\n2,1,0,3,1,1\n3,1,3,0,1,1\n4,0,1,1,0,0\n5,0,1,1,0,0\n";
+
+    assert_eq!(
+        run(&["analyze", "pairs", "games.csv"]),
+        (Some(0), matrix.to_string())
+    );
+
+    let (code, stdout) = run(&[
+        "analyze",
+        "pairs",
+        "games.csv",
+        "--output",
+        "pairs.csv",
+        "--top",
+        "2",
+    ]);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("pairs.csv")).unwrap(),
+        matrix
+    );
+    assert_eq!(
+        stdout,
+        "Wrote the pair matrix of the 3 games of games.csv to pairs.csv\n\
+         Most repeated pairs in the 3 games of games.csv:\n  02-03: 3 game(s)\n  01-02: 1 game(s)\n"
+    );
+    assert_eq!(
+        run(&["analyze", "pairs", "games.csv", "--top"])
+            .1
+            .lines()
+            .count(),
+        8
+    );
+
+    let (code, _) = run(&["analyze", "pairs", "bad.csv"]);
+    assert_eq!(code, Some(3));
+}
+
+#[test]
+fn test_historical_draws_are_left_out_with_a_warning_for_initial_games() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        CONFIG.replace(
+            "initial_games = []",
+            "initial_games = [[41, 5, 4, 52, 30, 33]]\nhistorical_draws_file = \"draws.csv\"",
+        ),
+    )
+    .unwrap();
+    fs::copy(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/draws.csv"),
+        dir.path().join("draws.csv"),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--quiet"]);
+    assert_eq!(output.status.code(), Some(0));
+    // The numbers of the initial game are sorted when read, and written so
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Warning: initial game #1 (04-05-30-33-41-52) was drawn on 11/03/1996\n"));
+    assert_eq!(
+        read_output(&dir.path().join("optimized_games.csv"))[0],
+        [4, 5, 30, 33, 41, 52]
+    );
+    assert_eq!(run(&["verify-manifest"]).status.code(), Some(0));
+
+    let output = run(&["--check", "01-05-06-27-42-59"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("the game was drawn in a past contest")
+    );
+}
+
+#[test]
+fn test_number_usage_cap_is_reported_and_checked_up_front() {
+    let dir = tempfile::tempdir().unwrap();
+    let settings = |no_of_games: usize| {
+        CONFIG
+            .replace("no_of_games = 3", &format!("no_of_games = {}", no_of_games))
+            .replace("min_desired_number = 1", "min_desired_number = 31")
+            + "max_number_usage = 7\n"
+    };
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(["--config", "settings.toml", "--quiet"])
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    fs::write(dir.path().join("settings.toml"), settings(30)).unwrap();
+    let output = run(&["--json-summary"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let histogram: Vec<u64> = summary["number_usage"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|numbers| numbers.as_u64().unwrap())
+        .collect();
+    assert!(histogram.len() <= 8, "{:?}", histogram);
+    assert_eq!(histogram.iter().sum::<u64>(), 30);
+    let games = read_output(&dir.path().join("optimized_games.csv"));
+    for number in 31..=60 {
+        let usage = games.iter().filter(|game| game.contains(&number)).count();
+        assert!(usage <= 7 && histogram[usage] > 0);
+    }
+
+    let output = run(&[]);
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Number usage, at most 7 games per number: "));
+
+    // 30 numbers in at most 7 games each fill 35 games only
+    fs::write(dir.path().join("settings.toml"), settings(36)).unwrap();
+    let output = run(&[]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("36 games requested, but at most 35 games fit"));
+}
+
+#[test]
+fn test_triplets_of_the_avoided_files_are_never_generated() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        CONFIG
+            .replace("no_of_games = 3", "no_of_games = 30")
+            .replace("min_desired_number = 1", "min_desired_number = 31")
+            + "avoid_triplets_from = [\"last_year.csv\", \"brothers_pool.csv\"]\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("last_year.csv"),
+        "31,32,33,34,35,36\n37,38,39,40,41,42\n43,44,45,46,47,48\n",
+    )
+    .unwrap();
+    // The brother holds a triplet of last year's second game
+    fs::write(
+        dir.path().join("brothers_pool.csv"),
+        "49,50,51,52,53,54\n38,40,42,55,56,57\n",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .arg("--quiet")
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(stderr.contains(
+        "Warning: brothers_pool.csv: game #2 (38-40-42-55-56-57) shares triplet 38-40-42 with game #2 of last_year.csv\n"
+    ));
+    assert!(stderr
+        .contains("Avoiding the 99 triplets of the 5 games of last_year.csv, brothers_pool.csv\n"));
+
+    let avoided: Vec<Vec<i64>> = [
+        [31, 32, 33, 34, 35, 36],
+        [37, 38, 39, 40, 41, 42],
+        [43, 44, 45, 46, 47, 48],
+        [49, 50, 51, 52, 53, 54],
+        [38, 40, 42, 55, 56, 57],
+    ]
+    .iter()
+    .flat_map(|game| rk_lottery::custom_utils::game2ksubsets(game, 3))
+    .collect();
+    let games = read_output(&dir.path().join("optimized_games.csv"));
+    assert_eq!(games.len(), 30);
+    for game in &games {
+        for triplet in rk_lottery::custom_utils::game2ksubsets(game, 3) {
+            assert!(
+                !avoided.contains(&triplet),
+                "{:?} holds {:?}",
+                game,
+                triplet
+            );
+        }
+    }
+
+    // None of the games of the files is copied to the output
+    assert!(!games.contains(&vec![31, 32, 33, 34, 35, 36]));
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .arg("verify-manifest")
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_blacklisted_games_are_left_out_and_flagged() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        CONFIG.to_string()
+            + "blacklisted_games = [[31, 32, 33, 34, 35, 36]]\nblacklist_file = \"cursed.csv\"\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("cursed.csv"), "4,8,15,16,23,61\n").unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    // The games of the file must be games of the lottery too
+    let output = run(&["--quiet"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("cursed.csv: blacklisted game #2 (04-08-15-16-23-61) is not a game of 6 distinct numbers from 1 to 60"));
+    fs::write(dir.path().join("cursed.csv"), "4,8,15,16,23,42\n").unwrap();
+    let output = run(&["--quiet"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(
+        !read_output(&dir.path().join("optimized_games.csv")).contains(&vec![4, 8, 15, 16, 23, 42])
+    );
+    assert_eq!(run(&["verify-manifest"]).status.code(), Some(0));
+
+    fs::write(
+        dir.path().join("tickets.csv"),
+        "1,2,3,4,5,6\n42,23,16,15,8,4\n",
+    )
+    .unwrap();
+    let output = run(&["validate", "tickets.csv"]);
+    assert_eq!(output.status.code(), Some(3));
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(report.contains("the game is blacklisted"), "{}", report);
+    assert_eq!(report.matches("blacklisted").count(), 1, "{}", report);
+    let output = run(&["explain", "--game", "4,8,15,16,23,42"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("the game is blacklisted"));
+}
+
+#[test]
+fn test_max_shared_numbers_holds_for_every_pair_of_games() {
+    let dir = tempfile::tempdir().unwrap();
+    let settings = |no_of_games: usize| {
+        CONFIG
+            .replace("no_of_games = 3", &format!("no_of_games = {}", no_of_games))
+            .replace("min_desired_number = 1", "min_desired_number = 31")
+            + "max_shared_numbers = 1\n"
+    };
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(["--config", "settings.toml", "--quiet"])
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    fs::write(dir.path().join("settings.toml"), settings(10)).unwrap();
+    let output = run(&["--json-summary"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let histogram: Vec<u64> = summary["overlaps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|pairs| pairs.as_u64().unwrap())
+        .collect();
+    assert!(histogram.len() <= 2, "{:?}", histogram);
+    assert_eq!(histogram.iter().sum::<u64>(), 45);
+    let games = read_output(&dir.path().join("optimized_games.csv"));
+    for (index, game) in games.iter().enumerate() {
+        for other in &games[..index] {
+            assert!(game.iter().filter(|x| other.contains(x)).count() <= 1);
+        }
+    }
+    let output = run(&[]);
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Pairs of games, at most 1 numbers shared: "));
+
+    // Each of the 435 pairs of the 30 numbers is in one game at most, 15 per game
+    fs::write(dir.path().join("settings.toml"), settings(30)).unwrap();
+    let output = run(&[]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("30 games requested, but at most 29 games can share at most 1 numbers"));
+}
+
+#[test]
+fn test_dry_run_estimates_without_writing_anything() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("settings.toml"), CONFIG).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(["--config", "settings.toml", "--dry-run"])
+            .args(args)
+            .env_remove("RUST_LOG")
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--games", "100", "--min-desired", "31"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(report.contains("Numbers 31 to 60: 593775 games of 6 numbers"));
+    assert!(report.contains("Upper bound: 203 games sharing no triplet"));
+    assert!(report.contains("Requested: 100 games, 49% of the bound"));
+    assert!(report.contains("Constraints: 100.0% of 100000 candidates pass them"));
+    assert!(report.contains("  game      1:            1.0"));
+    assert!(report.contains("  game    100: "));
+    assert!(report.contains("Expected attempts: "));
+    assert!(output.stderr.is_empty());
+
+    // Past half of the bound, the run is warned about
+    let output = run(&["--games", "110", "--min-desired", "31"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Warning: 110 games requested, close to the upper bound of 203 games for numbers 31 to 60; generation may be slow or fail\n"
+    );
+
+    // Ruled out by the bound, as the run would be
+    let output = run(&["--games", "500", "--min-desired", "35"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Upper bound: 130 games"));
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    // The games requested are compared with the tightest bound, here that of the usage cap
+    fs::write(
+        dir.path().join("settings.toml"),
+        CONFIG.to_string() + "max_number_usage = 3\n",
+    )
+    .unwrap();
+    let output = run(&["--games", "14", "--min-desired", "31"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(report.contains("Upper bound: 15 games with each number in at most 3"));
+    assert!(report.contains("Requested: 14 games, 93% of the bound"));
+}
+
+#[test]
+fn test_explain_gives_every_reason_a_game_is_turned_down() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("settings.toml"),
+        CONFIG.replace("min_desired_number = 1", "min_desired_number = 3")
+            + "max_arithmetic_run = 4\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("tickets.csv"),
+        "1,2,3,4,5,6\n10,20,30,40,50,60\n",
+    )
+    .unwrap();
+    let explain = |game: &str| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(["--config", "settings.toml", "explain", "--game", game])
+            .args(["--against", "tickets.csv"])
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = explain("01-02-03-04-05-20");
+    assert_eq!(output.status.code(), Some(3));
+    let report = String::from_utf8_lossy(&output.stdout);
+    let reasons: Vec<&str> = report.lines().skip(1).collect();
+    assert_eq!(
+        reasons,
+        [
+            "  - number 1 is below the minimum desired number",
+            "  - number 2 is below the minimum desired number",
+            "  - 5 numbers form an arithmetic progression starting at 1 with difference 1",
+            "  - game #1 holds 01-02-03, 01-02-04, 01-02-05, 01-03-04, 01-03-05, 01-04-05, 02-03-04, 02-03-05, 02-04-05, 03-04-05",
+        ]
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("rejected for 4 reason(s)"));
+
+    let output = explain("10,20,30,40,50,60");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("it is game #2 already"));
+
+    let output = explain("7 13 22 35 41 58");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "07-13-22-35-41-58 would be accepted\n"
+    );
+}
+
+#[test]
+fn test_output_can_be_shuffled_or_sorted_and_is_recorded_in_the_manifest() {
+    let mut found = run(&["--games", "30"], "optimized_games.csv");
+    let shuffled = run(
+        &["--games", "30", "--shuffle-output"],
+        "optimized_games.csv",
+    );
+    assert_ne!(shuffled, found);
+    assert_eq!(
+        run(
+            &["--games", "30", "--shuffle-output"],
+            "optimized_games.csv"
+        ),
+        shuffled
+    );
+    let ascending = run(
+        &["--games", "30", "--sort-output", "asc"],
+        "optimized_games.csv",
+    );
+    let by_rank = run(
+        &["--games", "30", "--sort-output", "rank"],
+        "optimized_games.csv",
+    );
+    found.sort_unstable();
+    for games in [&shuffled, &ascending, &by_rank] {
+        let mut games = games.clone();
+        games.sort_unstable();
+        assert_eq!(games, found);
+    }
+    assert_eq!(ascending, found);
+    assert_ne!(by_rank, ascending);
+
+    // The manifest records the order, and the shuffled games are generated again
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let command = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    assert!(command(&["--quiet", "--shuffle-output"]).status.success());
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&fs::read(dir.path().join("run_manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["config"]["output_order"], "shuffle");
+    let output = command(&["verify-manifest"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let output = command(&["--shuffle-output", "--sort-output", "rank"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = command(&["--sort-output", "shuffle"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = command(&["--shuffle-output", "--append"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("output_order cannot be combined"));
+}
+
+#[test]
+fn test_report_sums_up_the_archived_runs_and_skips_broken_entries() {
+    let archives = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/archives");
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let report = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .arg("report")
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = report(&["--dir", archives, "--output", "report.json"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(stderr.contains("broken/run_manifest.json: cannot read the manifest: "));
+    assert!(stdout.starts_with(&format!(
+        "30 games in 2 run(s) of {}, 1 manifest(s) skipped\n",
+        archives
+    )));
+    assert!(stdout.contains("596 / 34220 triplets covered (1.74%)\n"));
+    assert!(stdout.contains("Numbers never played: 25, 43, 50\n"));
+    assert!(stdout.contains("4 pair(s) of games of different runs share triplets\n"));
+    assert!(stdout.contains("2025-03/run_manifest.json game #10 and "));
+    assert!(stdout.contains("2025-09/run_manifest.json game #1 share 11-30-32\n"));
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&fs::read(dir.path().join("report.json")).unwrap()).unwrap();
+    assert_eq!(json["games"], 30);
+    assert_eq!(json["runs"].as_array().unwrap().len(), 2);
+    assert_eq!(json["runs"][1]["seed"], 2);
+    assert_eq!(json["runs"][1]["matches_manifest"], true);
+    assert_eq!(json["coverage"]["covered"], 596);
+    assert_eq!(json["never_played"], serde_json::json!([25, 43, 50]));
+    assert_eq!(json["collisions"].as_array().unwrap().len(), 4);
+    assert!(json["skipped"][0]["manifest"]
+        .as_str()
+        .unwrap()
+        .ends_with("broken/run_manifest.json"));
+
+    // A missing output file, a copied manifest and a changed output are reported per entry
+    let copy = dir.path().join("archives");
+    for run in ["2025-03", "2025-09"] {
+        fs::create_dir_all(copy.join(run)).unwrap();
+        for file in ["run_manifest.json", "optimized_games.csv"] {
+            fs::copy(
+                format!("{}/{}/{}", archives, run, file),
+                copy.join(run).join(file),
+            )
+            .unwrap();
+        }
+    }
+    fs::copy(
+        copy.join("2025-03/run_manifest.json"),
+        copy.join("2025-03/run_manifest_copy.json"),
+    )
+    .unwrap();
+    let mut changed = fs::OpenOptions::new()
+        .append(true)
+        .open(copy.join("2025-03/optimized_games.csv"))
+        .unwrap();
+    changed.write_all(b"16,1,2,3,4,5,6\n").unwrap();
+    fs::remove_file(copy.join("2025-09/optimized_games.csv")).unwrap();
+    let output = report(&["--dir", "archives"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(stderr.contains("Warning: archives/2025-03/optimized_games.csv was changed since archives/2025-03/run_manifest.json was written"));
+    assert!(stderr.contains("Warning: archives/2025-03/run_manifest_copy.json: archives/2025-03/optimized_games.csv was read for another manifest already, skipped\n"));
+    assert!(stderr.contains("Warning: archives/2025-09/run_manifest.json: "));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .starts_with("16 games in 1 run(s) of archives, 2 manifest(s) skipped\n"));
+
+    std::fs::remove_dir_all(copy.join("2025-03")).unwrap();
+    let output = report(&["--dir", "archives"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).lines().last(),
+        Some("Error: archives: no run could be read, 1 manifest(s) skipped")
+    );
+}
+
+#[test]
+fn test_zero_padded_output_is_validated_checked_and_resumed() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        format!("{}zero_pad = true\n", CONFIG),
+    )
+    .unwrap();
+    let command = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .arg("--quiet")
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    let padded = |content: &str| {
+        content
+            .lines()
+            .skip(1)
+            .all(|row| row.split(',').skip(1).all(|field| field.len() == 2))
+    };
+    assert!(command(&[]).status.success());
+    let path = dir.path().join("optimized_games.csv");
+    assert!(padded(&fs::read_to_string(&path).unwrap()));
+    let games = read_output(&path);
+    assert_eq!(games.len(), 3);
+    assert!(games.iter().flatten().any(|&number| number < 10));
+
+    let output = command(&["--resume", "--games", "6"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(padded(&fs::read_to_string(&path).unwrap()));
+    let resumed = read_output(&path);
+    assert_eq!(resumed.len(), 6);
+    assert_eq!(resumed[..3], games[..]);
+
+    let output = command(&["validate", "optimized_games.csv"]);
+    assert!(output.status.success());
+
+    // An unpadded copy of a padded row, written by hand, reads as the same game
+    let (row, game) = resumed
+        .iter()
+        .enumerate()
+        .find(|(_, game)| game[0] < 10)
+        .unwrap();
+    let unpadded: Vec<String> = game.iter().map(|number| number.to_string()).collect();
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    writeln!(file, "7,{}", unpadded.join(",")).unwrap();
+    assert_eq!(read_output(&path)[6], *game);
+    let output = command(&["validate", "optimized_games.csv"]);
+    assert_eq!(output.status.code(), Some(3));
+    let triplet: Vec<String> = game[..3]
+        .iter()
+        .map(|number| format!("{:02}", number))
+        .collect();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!(
+            "rows {} and 7 share triplet {}\n",
+            row + 1,
+            triplet.join("-")
+        )
+    );
+    let draw: Vec<String> = games[0].iter().map(|number| number.to_string()).collect();
+    let output = command(&["check", "--draw", &draw.join(",")]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("6 matches"));
+}
+
+#[test]
+fn test_histogram_counts_the_numbers_of_the_games() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 2\ninitial_games = [[1, 2, 3, 4, 5, 6], [1, 2, 7, 8, 9, 10]]\nmax_number = 60\nmin_desired_number = 1\n",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .args(["--quiet", "--histogram"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    let first = lines
+        .iter()
+        .position(|line| line.starts_with("01 "))
+        .unwrap();
+    assert_eq!(
+        lines[first..first + 11],
+        [
+            "01  2  ##",
+            "02  2  ##",
+            "03  1  #",
+            "04  1  #",
+            "05  1  #",
+            "06  1  #",
+            "07  1  #",
+            "08  1  #",
+            "09  1  #",
+            "10  1  #",
+            "11  0  (never played)"
+        ]
+    );
+    assert_eq!(lines[first + 59], "60  0  (never played)");
+    assert!(lines[first + 60].starts_with("50 number(s) in no game: 11, 12, "));
+    assert!(lines[first + 60].ends_with(", 59, 60"));
+    let csv = fs::read_to_string(dir.path().join("number_frequencies.csv")).unwrap();
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows.len(), 61);
+    assert_eq!(rows[..4], ["number,count", "1,2", "2,2", "3,1"]);
+    assert_eq!(rows[60], "60,0");
+}
+
+#[test]
+fn test_closed_stdout_still_writes_every_file() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .args(["--quiet", "--histogram"])
+        .current_dir(dir.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // As `| head -0` would, nothing printed is read
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for file in [
+        "optimized_games.csv",
+        "games.csv",
+        "triplet_set.log",
+        "run_manifest.json",
+        "number_frequencies.csv",
+    ] {
+        assert!(dir.path().join(file).exists(), "{} is missing", file);
+    }
+}
+
+#[test]
+fn test_stats_columns_follow_the_numbers_and_are_read_past() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        format!("{}output_stats_columns = true\n", CONFIG),
+    )
+    .unwrap();
+    let command = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .arg("--quiet")
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    assert!(command(&[]).status.success());
+    let output = command(&["--resume", "--games", "5"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let mut rdr = csv::Reader::from_path(dir.path().join("optimized_games.csv")).unwrap();
+    assert_eq!(
+        rdr.headers().unwrap(),
+        vec![
+            "game", "n1", "n2", "n3", "n4", "n5", "n6", "sum", "odd", "low", "max_run", "min",
+            "max"
+        ]
+    );
+    let rows: Vec<Vec<i64>> = rdr
+        .records()
+        .map(|record| {
+            record
+                .unwrap()
+                .iter()
+                .map(|field| field.parse().unwrap())
+                .collect()
+        })
+        .collect();
+    assert_eq!(rows.len(), 5);
+    for row in &rows {
+        let numbers = &row[1..7];
+        assert_eq!(row[7], numbers.iter().sum::<i64>());
+        assert_eq!(
+            row[8],
+            numbers.iter().filter(|&&number| number % 2 == 1).count() as i64
+        );
+        assert_eq!(
+            row[9],
+            numbers.iter().filter(|&&number| number <= 30).count() as i64
+        );
+        assert_eq!(row[11], *numbers.iter().min().unwrap());
+        assert_eq!(row[12], *numbers.iter().max().unwrap());
+    }
+
+    // The figures are no numbers of the games
+    let output = command(&["validate", "optimized_games.csv"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let output = command(&["--print"]);
+    assert!(output.status.success());
+    let table = String::from_utf8_lossy(&output.stdout);
+    assert!(table
+        .lines()
+        .next()
+        .unwrap()
+        .ends_with("sum  odd  low  max_run  min  max"));
+
+    fs::write(
+        dir.path().join("config.toml"),
+        format!(
+            "{}output_stats_columns = true\ncsv_header = false\n",
+            CONFIG
+        ),
+    )
+    .unwrap();
+    assert_eq!(command(&[]).status.code(), Some(2));
+}
+
+#[test]
+fn test_run_carried_on_from_a_checkpoint_writes_the_same_games() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        CONFIG.replace("no_of_games = 3", "no_of_games = 40")
+            + "max_arithmetic_run = 3\n[checkpoint]\ndir = \"progress\"\nevery_games = 10\nkeep = 2\n",
+    )
+    .unwrap();
+    let command = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+            .arg("--quiet")
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+    };
+    assert!(command(&[]).status.success());
+    let mut checkpoints: Vec<String> = fs::read_dir(dir.path().join("progress"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    checkpoints.sort();
+    assert_eq!(
+        checkpoints,
+        ["checkpoint_000000020.json", "checkpoint_000000030.json"]
+    );
+    let uninterrupted = fs::read(dir.path().join("optimized_games.csv")).unwrap();
+    let manifest = |dir: &std::path::Path| {
+        let mut manifest: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.join("run_manifest.json")).unwrap()).unwrap();
+        manifest["timestamp"].take();
+        manifest["stats"]["elapsed_secs"].take();
+        manifest
+    };
+    let uninterrupted_manifest = manifest(dir.path());
+    assert!(
+        uninterrupted_manifest["stats"]["rng_draws"]
+            .as_u64()
+            .unwrap()
+            > 0
+    );
+
+    // The checkpoint holds the settings, the configuration file is not needed
+    for file in [
+        "config.toml",
+        "optimized_games.csv",
+        "games.csv",
+        "triplet_set.log",
+        "run_manifest.json",
+    ] {
+        fs::remove_file(dir.path().join(file)).unwrap();
+    }
+    let output = command(&["--from-checkpoint", "progress"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .starts_with("Carried on from a checkpoint of 30 games, generated 10 more\n"));
+    assert_eq!(
+        fs::read(dir.path().join("optimized_games.csv")).unwrap(),
+        uninterrupted
+    );
+    assert_eq!(manifest(dir.path()), uninterrupted_manifest);
+    assert!(command(&["verify-manifest"]).status.success());
+
+    let output = command(&["--from-checkpoint", "progress", "--games", "50"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = command(&["--from-checkpoint", "missing"]);
+    assert_eq!(output.status.code(), Some(5));
+}
+
+#[test]
+fn test_relax_on_exhaustion_gives_up_on_the_least_important_constraint() {
+    let dir = tempfile::tempdir().unwrap();
+    // The only game of 55 to 60 is a progression of 6 numbers, which the usage allows
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 1\ninitial_games = []\nseed = 1\nmax_number = 60\nmin_desired_number = 55\nmax_arithmetic_run = 3\nmax_number_usage = 2\nrelax_on_exhaustion = true\nconstraint_priority = [\"usage\", \"arithmetic-progression\"]\n",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning: no more games follow every constraint after 0 games and "));
+    assert!(stderr.contains("relaxing the arithmetic-progression constraint"));
+    let games = fs::read_to_string(dir.path().join("optimized_games.csv")).unwrap();
+    assert_eq!(games.lines().nth(1), Some("1,55,56,57,58,59,60"));
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("run_manifest.json")).unwrap())
+            .unwrap();
+    let relaxed = manifest["relaxed"].as_array().unwrap();
+    assert_eq!(relaxed.len(), 1);
+    assert_eq!(relaxed[0]["constraint"], "arithmetic-progression");
+    assert_eq!(relaxed[0]["games"], 0);
+}
+
+#[test]
+fn test_interactive_needs_a_terminal() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .arg("--interactive")
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("batch mode"));
+    assert!(!dir.path().join("optimized_games.csv").exists());
+
+    // A run carrying on from other games is refused before the terminal is looked for
+    fs::write(
+        dir.path().join("config.toml"),
+        format!("{}append = true\n", CONFIG),
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .arg("--interactive")
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unset resume, append"));
+}
+
+#[test]
+fn test_skipped_initial_games_are_warned_about() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 3\ninitial_games = [[1, 2, 3, 4, 5, 6], [1, 2, 3, 4, 5, 77]]\nseed = 1\nmax_number = 60\nmin_desired_number = 1\non_invalid_initial = \"skip\"\n",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .arg("--quiet")
+        .env_remove("RUST_LOG")
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with(
+        "Warning: initial game #2 (01-02-03-04-05-77) skipped as on_invalid_initial is \"skip\": "
+    ));
+    assert_eq!(
+        read_output(&dir.path().join("optimized_games.csv")).len(),
+        3
+    );
+}
+
+#[test]
+fn test_allowed_initial_conflicts_are_warned_about() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 3\ninitial_games = [[1, 2, 3, 4, 5, 6], [1, 2, 3, 7, 8, 9]]\nseed = 1\nmax_number = 60\nmin_desired_number = 1\nallow_initial_conflicts = true\n",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .arg("--quiet")
+        .env_remove("RUST_LOG")
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Warning: initial games #1 and #2 share 01-02-03, kept as allow_initial_conflicts is set\n"
+    );
+}
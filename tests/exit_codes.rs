@@ -0,0 +1,238 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Runs the program in `dir` with the given arguments.
+fn run_in(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("the program runs")
+}
+
+/// Runs the program in a fresh directory holding `config` as config.toml.
+fn run_with_config(config: &str) -> Output {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), config).unwrap();
+    run_in(dir.path(), &[])
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// The JSON object `--exit-summary` printed on the last line of stderr.
+fn summary(output: &Output) -> serde_json::Value {
+    let stderr = stderr(output);
+    serde_json::from_str(stderr.lines().last().expect("a summary line")).unwrap()
+}
+
+#[test]
+fn test_successful_run_exits_with_0() {
+    let output = run_with_config(
+        "no_of_games = 3\ninitial_games = []\nseed = 1\nmax_number = 60\nmin_desired_number = 1\n",
+    );
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[test]
+fn test_config_errors_exit_with_2() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = run_in(dir.path(), &[]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("Error: config.toml: "));
+
+    let output = run_with_config("no_of_games = \"many\"\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output).lines().count(), 1);
+}
+
+#[test]
+fn test_invalid_initial_game_exits_with_3() {
+    let output = run_with_config(
+        "no_of_games = 3\ninitial_games = [[1, 2, 3, 4, 5]]\nmax_number = 60\nmin_desired_number = 1\n",
+    );
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).starts_with("Error: Initial game #1 is invalid"));
+}
+
+#[test]
+fn test_skipped_initial_game_is_counted_in_the_summary_and_the_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 3\ninitial_games = [[1, 2, 3, 4, 5], [7, 8, 9, 10, 11, 12]]\nseed = 1\nmax_number = 60\nmin_desired_number = 1\non_invalid_initial = \"skip\"\n",
+    )
+    .unwrap();
+    let output = run_in(dir.path(), &["--exit-summary"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stderr(&output).starts_with(
+        "Warning: initial game #1 (01-02-03-04-05) skipped as on_invalid_initial is \"skip\": game has 5 numbers instead of 6\n"
+    ));
+    let summary = summary(&output);
+    assert_eq!(summary["games"], 3);
+    assert_eq!(summary["skipped_initial"], 1);
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&fs::read(dir.path().join("run_manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["stats"]["skipped_initial"], 1);
+}
+
+#[test]
+fn test_repeated_triplet_in_initial_games_exits_with_3() {
+    let output = run_with_config(
+        "no_of_games = 4\ninitial_games = [[1, 5, 9, 20, 30, 40], [2, 6, 11, 21, 31, 41], [1, 9, 20, 33, 44, 55], [2, 6, 21, 30, 45, 59]]\nmax_number = 60\nmin_desired_number = 1\n",
+    );
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        stderr(&output),
+        "Error: Initial games share triplets: games #1 and #3 share 01-09-20; games #2 and #4 share 02-06-21\n"
+    );
+}
+
+#[test]
+fn test_repeated_initial_game_is_a_config_error() {
+    let config = "no_of_games = 4\ninitial_games = [[1, 5, 9, 20, 30, 40], [40, 30, 20, 9, 5, 1]]\nmax_number = 60\nmin_desired_number = 1\n";
+    let output = run_with_config(config);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with(
+        "Error: config.toml: initial games repeat earlier ones: #2 repeats #1 (01-05-09-20-30-40); remove the repeats"
+    ));
+
+    let deduped = format!("{}allow_duplicate_initial = \"dedupe\"\n", config);
+    assert_eq!(run_with_config(&deduped).status.code(), Some(0));
+}
+
+#[test]
+fn test_infeasible_request_exits_with_4() {
+    let output = run_with_config(
+        "no_of_games = 100\ninitial_games = []\nmax_number = 60\nmin_desired_number = 41\n",
+    );
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(stderr(&output).lines().count(), 1);
+}
+
+#[test]
+fn test_running_out_of_attempts_exits_with_4() {
+    // The only game of 55 to 60 is a progression of 6 numbers
+    let output = run_with_config(
+        "no_of_games = 1\ninitial_games = []\nseed = 1\nmax_number = 60\nmin_desired_number = 55\nmax_arithmetic_run = 3\nmax_attempts = 50\n",
+    );
+    assert_eq!(output.status.code(), Some(4));
+    assert!(stderr(&output).contains("Error: 50 candidates in a row were rejected (max_attempts)"));
+}
+
+#[test]
+fn test_unwritable_output_exits_with_5() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 3\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\n",
+    )
+    .unwrap();
+    // A directory in place of the output file cannot be written to
+    fs::create_dir(dir.path().join("optimized_games.csv")).unwrap();
+    let output = run_in(dir.path(), &[]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(stderr(&output).starts_with("Error: optimized_games.csv: "));
+}
+
+#[test]
+fn test_resuming_from_a_truncated_set_file_exits_with_5() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 3\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\n",
+    )
+    .unwrap();
+    assert_eq!(run_in(dir.path(), &[]).status.code(), Some(0));
+    // As left by a crash in the middle of writing it
+    let path = dir.path().join("triplet_set.log");
+    let content = fs::read_to_string(&path).unwrap();
+    fs::write(&path, &content[..content.len() / 2]).unwrap();
+
+    let output = run_in(dir.path(), &["--games", "5", "--resume"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(
+        stderr(&output).starts_with("Error: triplet_set.log: file appears corrupted: "),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn test_exit_summary_tells_how_the_run_ended() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 3\ninitial_games = []\nseed = 1\nmax_number = 60\nmin_desired_number = 1\n",
+    )
+    .unwrap();
+    let output = run_in(dir.path(), &["--quiet", "--exit-summary"]);
+    assert_eq!(output.status.code(), Some(0));
+    let json = summary(&output);
+    assert_eq!(json["status"], "ok");
+    assert_eq!(json["exit_code"], 0);
+    assert_eq!(json["games"], 3);
+    assert_eq!(json["attempts"], 3);
+    assert_eq!(json["seed"], 1);
+    assert_eq!(json["rejections"]["constraints"]["range"], 0);
+    assert_eq!(json["outputs"][0], "optimized_games.csv");
+    assert!(json["error"].is_null());
+
+    // Along with --json-summary, which prints the figures of the games on stdout
+    let output = run_in(dir.path(), &["--quiet", "--json-summary", "--exit-summary"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    let games: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(games["games"], 3);
+    assert_eq!(summary(&output)["status"], "ok");
+
+    // A validation failure, written to the file given
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 3\ninitial_games = [[1, 2, 3, 4, 5]]\nmax_number = 60\nmin_desired_number = 1\n",
+    )
+    .unwrap();
+    let output = run_in(dir.path(), &["--exit-summary=summary.json"]);
+    assert_eq!(output.status.code(), Some(3));
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("summary.json")).unwrap())
+            .unwrap();
+    assert_eq!(json["status"], "error");
+    assert_eq!(json["exit_code"], 3);
+    assert!(json["games"].is_null());
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Initial game #1 is invalid"));
+
+    // Exhaustion, telling the games found before
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 10\ninitial_games = []\nseed = 1\nmax_number = 60\nmin_desired_number = 49\n",
+    )
+    .unwrap();
+    let output = run_in(dir.path(), &["--quiet", "--exit-summary"]);
+    assert_eq!(output.status.code(), Some(4));
+    let json = summary(&output);
+    assert_eq!(json["exit_code"], 4);
+    assert_eq!(json["error"]["kind"], "generation");
+    assert!(json["error"]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("No more valid games exist"));
+    assert!(json["games"].as_u64().is_some_and(|games| games < 10));
+    assert_eq!(json["outputs"], serde_json::json!([]));
+}
+
+#[test]
+fn test_usage_errors_exit_with_2() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("config.toml"),
+        "no_of_games = 3\ninitial_games = []\nmax_number = 60\nmin_desired_number = 1\n",
+    )
+    .unwrap();
+    let output = run_in(dir.path(), &["--unknown"]);
+    assert_eq!(output.status.code(), Some(2));
+}
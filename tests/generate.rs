@@ -0,0 +1,208 @@
+use rk_lottery::config::Config;
+use rk_lottery::constraints::ConstraintSet;
+use rk_lottery::custom_utils;
+use rk_lottery::data_structures::TripletSet;
+use rk_lottery::generation::{self, GameGenerator, GenError};
+use rk_lottery::stats::GenerationStats;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+fn config(no_of_games: usize, min_desired_number: i64) -> Config {
+    Config::from_toml(&format!(
+        r#"
+        no_of_games = {}
+        initial_games = [[41, 43, 47, 50, 55, 59]]
+        seed = 11
+        max_number = 60
+        min_desired_number = {}
+        max_arithmetic_run = 4
+        "#,
+        no_of_games, min_desired_number
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_generate_games_from_config() {
+    let config = config(25, 31);
+    let games = generation::generate_games(&config).unwrap();
+    assert_eq!(games.len(), 25);
+    assert_eq!(games[0], config.initial_games[0]);
+
+    let mut constraints = ConstraintSet::from_config(&config);
+    let mut triplet_set = TripletSet::default();
+    for game in &games {
+        assert!(!constraints.reject(game), "{} is invalid", game);
+        assert!(triplet_set.check_and_insert_all(&custom_utils::game2triplet_enums(game)));
+    }
+
+    // The same configuration gives the same games
+    assert_eq!(generation::generate_games(&config).unwrap(), games);
+}
+
+#[test]
+fn test_generator_yields_the_games_one_at_a_time() {
+    let config = config(25, 31);
+    let mut generator = GameGenerator::builder()
+        .config(config.clone())
+        .build()
+        .unwrap();
+    assert_eq!(generator.next().unwrap().unwrap(), config.initial_games[0]);
+    let first: Vec<_> = generator.by_ref().take(9).map(Result::unwrap).collect();
+    assert_eq!(generator.stats().games, 10);
+    assert_eq!(generator.games().len(), 10);
+    let attempts = generator.stats().attempts;
+    let rest: Vec<_> = generator.by_ref().map(Result::unwrap).collect();
+    assert_eq!(rest.len(), 15);
+    assert!(generator.stats().attempts > attempts);
+    assert!(generator.next().is_none());
+
+    // Taken one at a time or all at once, the games are the same
+    let games: Vec<_> = config
+        .initial_games
+        .iter()
+        .chain(&first)
+        .chain(&rest)
+        .cloned()
+        .collect();
+    assert_eq!(games, generation::generate_games(&config).unwrap());
+
+    // The seed of the builder replaces that of the configuration
+    let reseeded = |seed| {
+        GameGenerator::builder()
+            .config(config.clone())
+            .seed(seed)
+            .build()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(reseeded(11), games);
+    assert_ne!(reseeded(12), games);
+}
+
+#[test]
+fn test_generator_stops_on_exhaustion_and_when_asked() {
+    // Games of numbers 49 to 60 run out of fresh triplets before the bound of 11
+    let tiny = Config {
+        max_arithmetic_run: None,
+        initial_games: Vec::new(),
+        ..config(11, 49)
+    };
+    let mut generator = GameGenerator::builder().config(tiny).build().unwrap();
+    let mut games = Vec::new();
+    let error = loop {
+        match generator.next() {
+            Some(Ok(game)) => games.push(game),
+            Some(Err(error)) => break error,
+            None => panic!("the generator ended without an error"),
+        }
+    };
+    assert!(
+        matches!(error, GenError::Exhausted { generated, requested: 11 } if generated == games.len()),
+        "{}",
+        error
+    );
+    assert!(generator.next().is_none());
+    let mut triplet_set = TripletSet::default();
+    for game in &games {
+        assert!(triplet_set.check_and_insert_all(&custom_utils::game2triplet_enums(game)));
+    }
+
+    // Raising the stop flag ends the iteration, the games so far kept
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut generator = GameGenerator::builder()
+        .config(config(25, 31))
+        .stats(GenerationStats::default().with_stop_flag(Arc::clone(&stop)))
+        .build()
+        .unwrap();
+    assert_eq!(generator.by_ref().take(5).count(), 5);
+    stop.store(true, Ordering::Relaxed);
+    assert!(generator.next().is_none());
+    let generated = generator.into_generated();
+    assert!(generated.interrupted);
+    assert_eq!(generated.games.len(), 5);
+    assert_eq!(generated.triplet_set.len(), 100);
+}
+
+#[test]
+fn test_generation_cancelled_from_another_thread_keeps_valid_games() {
+    let wide = config(300, 1);
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (reached, progress_made) = std::sync::mpsc::channel();
+    let canceller = {
+        let cancel = Arc::clone(&cancel);
+        std::thread::spawn(move || {
+            progress_made.recv().unwrap();
+            cancel.store(true, Ordering::Relaxed);
+        })
+    };
+    // Hold the run at its first progress report until the other thread cancels it
+    let mut reports = 0;
+    let result = generation::generate_games_with_cancel(&wide, &cancel, |stats| {
+        reports += 1;
+        if reports == 1 {
+            assert_eq!(stats.attempts, 1000);
+            reached.send(()).unwrap();
+            while !cancel.load(Ordering::Relaxed) {
+                std::thread::yield_now();
+            }
+        }
+    });
+    canceller.join().unwrap();
+    assert_eq!(reports, 1);
+
+    let partial = match result {
+        Err(GenError::Cancelled { partial }) => partial,
+        other => panic!("unexpected result: {:?}", other.map(|games| games.len())),
+    };
+    assert!(partial.len() > 1 && partial.len() < 300);
+    assert_eq!(partial[0], wide.initial_games[0]);
+    let mut constraints = ConstraintSet::from_config(&wide);
+    let mut triplet_set = TripletSet::default();
+    for game in &partial {
+        assert!(!constraints.reject(game), "{} is invalid", game);
+        assert!(triplet_set.check_and_insert_all(&custom_utils::game2triplet_enums(game)));
+    }
+    // The games so far are those of an uncancelled run
+    let games = generation::generate_games(&wide).unwrap();
+    assert_eq!(partial, games[..partial.len()]);
+
+    // Left alone, the run reports its progress and ends with every game
+    let mut last = 0;
+    let games =
+        generation::generate_games_with_cancel(&config(25, 31), &AtomicBool::new(false), |stats| {
+            last = stats.games
+        })
+        .unwrap();
+    assert_eq!((games.len(), last), (25, 25));
+}
+
+#[test]
+fn test_generator_needs_a_configuration_drawn_one_game_at_a_time() {
+    assert!(matches!(
+        GameGenerator::builder().build(),
+        Err(GenError::Unsupported(_))
+    ));
+    let threaded = Config {
+        threads: Some(2),
+        ..config(5, 31)
+    };
+    assert!(matches!(
+        GameGenerator::builder().config(threaded).build(),
+        Err(GenError::Unsupported(_))
+    ));
+    assert!(matches!(
+        GameGenerator::builder().config(config(100, 41)).build(),
+        Err(GenError::Infeasible { bound: 57, .. })
+    ));
+}
+
+#[test]
+fn test_generate_games_reports_infeasible_requests() {
+    // C(20, 3) / C(6, 3) = 57 games at most for numbers 41 to 60
+    match generation::generate_games(&config(100, 41)) {
+        Err(GenError::Infeasible { bound, .. }) => assert_eq!(bound, 57),
+        other => panic!("unexpected result: {:?}", other.map(|games| games.len())),
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+const CONFIG: &str =
+    "no_of_games = 6\ninitial_games = []\nseed = 4\nmax_number = 60\nmin_desired_number = 1\n";
+
+/// Runs the program in `dir` with the given arguments.
+fn run_in(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rk_lottery"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("the program runs")
+}
+
+/// Generates games in a fresh directory, with `args` on the command line.
+fn generate(args: &[&str]) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("config.toml"), CONFIG).unwrap();
+    let output = run_in(dir.path(), &[&["--quiet"], args].concat());
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    dir
+}
+
+#[test]
+fn test_manifest_records_the_effective_settings() {
+    let dir = generate(&["--games", "8", "--seed", "9"]);
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dir.path().join("run_manifest.json")).unwrap())
+            .unwrap();
+    assert_eq!(manifest["seed"], 9);
+    assert_eq!(manifest["config"]["no_of_games"], 8);
+    assert_eq!(manifest["config"]["seed"], 9);
+    assert_eq!(manifest["version"], env!("CARGO_PKG_VERSION"));
+    assert!(manifest["timestamp"].as_u64().unwrap() > 0);
+    assert!(manifest["attempts"].as_u64().unwrap() >= 8);
+    assert_eq!(manifest["rejections"]["constraints"]["range"], 0);
+    assert_eq!(manifest["sha256"].as_str().unwrap().len(), 64);
+}
+
+#[test]
+fn test_verify_manifest_accepts_the_untouched_output() {
+    let dir = generate(&[]);
+    // The manifest is enough, the configuration file may have changed since
+    fs::remove_file(dir.path().join("config.toml")).unwrap();
+    let output = run_in(dir.path(), &["verify-manifest"]);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("optimized_games.csv matches the SHA-256"));
+    assert!(stdout.contains("gives the same file"));
+}
+
+#[test]
+fn test_verify_manifest_fails_on_a_tampered_output() {
+    let dir = generate(&["--output", "tickets.csv"]);
+    let path = dir.path().join("tickets.csv");
+    let tampered = fs::read_to_string(&path).unwrap().replacen(',', ";", 1);
+    fs::write(&path, tampered).unwrap();
+
+    let output = run_in(dir.path(), &["verify-manifest", "run_manifest.json"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .starts_with("Error: tickets.csv does not match run_manifest.json"));
+}
+
+#[test]
+fn test_verify_manifest_fails_when_the_games_differ() {
+    let dir = generate(&[]);
+    // A consistent manifest and output, but with settings that give other games
+    let path = dir.path().join("run_manifest.json");
+    let manifest = fs::read_to_string(&path)
+        .unwrap()
+        .replace("\"seed\": 4", "\"seed\": 5");
+    fs::write(&path, manifest).unwrap();
+
+    let output = run_in(dir.path(), &["verify-manifest"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .starts_with("Error: Generating the games again from run_manifest.json"));
+}
@@ -0,0 +1,202 @@
+//! Properties of the combinadic conversions, the rank sets and the constraints, checked on
+//! random inputs. The seed is fixed so every run tries the same cases; a failing case is
+//! saved in `properties.proptest-regressions`, next to this file, and tried first by the runs
+//! after it.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::subsequence;
+use proptest::test_runner::{FileFailurePersistence, RngSeed};
+use rk_lottery::config::Config;
+use rk_lottery::constraints::ConstraintSet;
+use rk_lottery::custom_utils;
+use rk_lottery::data_structures::{DenseNumberSet, NumberSet, RankSet, TripletSet};
+
+/// Largest rank of the sets checked by `check_and_insert_all_is_all_or_nothing`.
+const RANKS: i64 = 200;
+
+fn config() -> ProptestConfig {
+    ProptestConfig {
+        cases: 512,
+        rng_seed: RngSeed::Fixed(2925),
+        failure_persistence: Some(Box::new(FileFailurePersistence::SourceParallel(
+            "proptest-regressions",
+        ))),
+        ..ProptestConfig::default()
+    }
+}
+
+/// A subset of `k` numbers of a lottery of `n` numbers starting at `min_number`, in random
+/// order, as `(n, k, min_number, subset)`.
+fn subset(max_k: i64) -> impl Strategy<Value = (i64, usize, i64, Vec<i64>)> {
+    (1..=60i64, 0..=1i64).prop_flat_map(move |(n, min_number)| {
+        (0..=n.min(max_k) as usize).prop_flat_map(move |k| {
+            let numbers: Vec<i64> = (min_number..min_number + n).collect();
+            subsequence(numbers, k)
+                .prop_shuffle()
+                .prop_map(move |subset| (n, k, min_number, subset))
+        })
+    })
+}
+
+/// A game of 6 distinct numbers of `min_number..min_number + 60`, in random order, with its
+/// `min_number`.
+fn game() -> impl Strategy<Value = (i64, Vec<i64>)> {
+    (0..=1i64).prop_flat_map(|min_number| {
+        subsequence((min_number..min_number + 60).collect::<Vec<_>>(), 6)
+            .prop_shuffle()
+            .prop_map(move |game| (min_number, game))
+    })
+}
+
+/// The length of the longest arithmetic progression among the distinct numbers of a game.
+fn longest_progression(game: &[i64]) -> usize {
+    let mut numbers = game.to_vec();
+    numbers.sort_unstable();
+    numbers.dedup();
+    let mut longest = numbers.len().min(1);
+    for (i, &start) in numbers.iter().enumerate() {
+        for &second in &numbers[i + 1..] {
+            let length = (0..)
+                .map(|m| start + m * (second - start))
+                .take_while(|term| numbers.contains(term))
+                .count();
+            longest = longest.max(length);
+        }
+    }
+    longest
+}
+
+/// Checks `check_and_insert_all` on a set holding `existing`, membership being compared over
+/// `0..RANKS` before and after.
+fn assert_all_or_nothing(
+    mut set: impl RankSet,
+    existing: &[i64],
+    numbers: &[i64],
+) -> Result<(), TestCaseError> {
+    for &number in existing {
+        set.add_number(number);
+    }
+    let before: Vec<bool> = (0..RANKS).map(|rank| set.contains(rank)).collect();
+    let mut distinct = numbers.to_vec();
+    distinct.sort_unstable();
+    distinct.dedup();
+    let insertable =
+        distinct.len() == numbers.len() && numbers.iter().all(|&n| !before[n as usize]);
+
+    prop_assert_eq!(set.check_and_insert_all(numbers), insertable);
+    for rank in 0..RANKS {
+        let expected = before[rank as usize] || (insertable && numbers.contains(&rank));
+        prop_assert_eq!(set.contains(rank), expected, "rank {}", rank);
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(config())]
+
+    #[test]
+    fn ranks_unrank_to_the_sorted_subset((n, k, min_number, subset) in subset(8)) {
+        let rank = custom_utils::subset2enum_from(&subset, k, min_number).unwrap();
+        prop_assert!((0..custom_utils::binomial(n, k as i64)).contains(&rank));
+        let mut sorted = subset.clone();
+        sorted.sort_unstable();
+        prop_assert_eq!(
+            custom_utils::enum2subset_from(rank, n, k as i64, min_number).unwrap(),
+            sorted.clone()
+        );
+        // The order of the numbers does not change the rank, which the unchecked ranking gives
+        // for the sorted numbers
+        prop_assert_eq!(custom_utils::subset2enum_from(&sorted, k, min_number).unwrap(), rank);
+        prop_assert_eq!(custom_utils::game2enum_from(&sorted, min_number), rank);
+    }
+
+    #[test]
+    fn triplet_ranks_match_the_triplets_in_any_order((min_number, game) in game()) {
+        let triplet_nos = custom_utils::game2triplet_enums_from(&game, min_number);
+        let triplets = custom_utils::game2triplets(&game);
+        for (triplet_no, triplet) in triplet_nos.iter().zip(&triplets) {
+            prop_assert_eq!(
+                custom_utils::subset2enum_from(triplet, 3, min_number).unwrap(),
+                *triplet_no
+            );
+        }
+        let mut sorted = game.clone();
+        sorted.sort_unstable();
+        let mut expected = custom_utils::game2triplet_enums_from(&sorted, min_number);
+        let mut found = triplet_nos;
+        expected.sort_unstable();
+        found.sort_unstable();
+        prop_assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn ksubsets_count_the_binomial((_, _, _, game) in subset(10), k in 0..=12usize) {
+        let subsets = custom_utils::game2ksubsets(&game, k);
+        prop_assert_eq!(
+            subsets.len() as i64,
+            custom_utils::binomial(game.len() as i64, k as i64)
+        );
+        for subset in &subsets {
+            prop_assert_eq!(subset.len(), k);
+            // Each subset keeps the numbers in the order of the game
+            let positions: Vec<usize> = subset
+                .iter()
+                .map(|number| game.iter().position(|x| x == number).unwrap())
+                .collect();
+            prop_assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+        let mut distinct = subsets.clone();
+        distinct.sort();
+        distinct.dedup();
+        prop_assert_eq!(distinct.len(), subsets.len());
+    }
+
+    #[test]
+    fn check_and_insert_all_is_all_or_nothing(
+        existing in vec(0..RANKS, 0..50),
+        numbers in vec(0..RANKS, 0..25),
+    ) {
+        assert_all_or_nothing(NumberSet::new(), &existing, &numbers)?;
+        assert_all_or_nothing(TripletSet::default(), &existing, &numbers)?;
+        assert_all_or_nothing(DenseNumberSet::new(RANKS as usize), &existing, &numbers)?;
+    }
+
+    #[test]
+    fn constraints_accept_only_games_following_their_rules(
+        min_desired_number in 1..=30i64,
+        max_number in 40..=60i64,
+        max_arithmetic_run in proptest::option::of(3..=5usize),
+        games in vec(vec(0..=65i64, 4..=8), 1..20),
+    ) {
+        let config = Config::from_toml(&format!(
+            "no_of_games = 1\ninitial_games = []\nmax_number = {}\nmin_desired_number = {}\n{}",
+            max_number,
+            min_desired_number,
+            max_arithmetic_run
+                .map(|run| format!("max_arithmetic_run = {}\n", run))
+                .unwrap_or_default()
+        ))
+        .unwrap();
+        let mut constraints = ConstraintSet::from_config(&config);
+        for game in &games {
+            let rejected = constraints.reject(game);
+            prop_assert_eq!(constraints.first_violation(game).is_some(), rejected);
+            prop_assert_eq!(!constraints.violations(game).is_empty(), rejected);
+            if rejected {
+                continue;
+            }
+            let mut distinct = game.clone();
+            distinct.sort_unstable();
+            distinct.dedup();
+            prop_assert_eq!(game.len(), 6);
+            prop_assert_eq!(distinct.len(), 6, "{:?} repeats a number", game);
+            prop_assert!(game
+                .iter()
+                .all(|number| (min_desired_number..=max_number).contains(number)));
+            if let Some(max_run) = max_arithmetic_run {
+                prop_assert!(longest_progression(game) <= max_run, "{:?}", game);
+            }
+        }
+    }
+}